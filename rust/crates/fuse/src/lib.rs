@@ -1,10 +1,15 @@
-use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::hash::{Hash as StdHash, Hasher};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use futures::executor::block_on;
-use hashtree_core::{Cid, HashTree, HashTreeConfig, HashTreeError, LinkType, Store};
+use hashtree_core::{
+    merge_trees, Cid, ConflictResolver, DirEntry as TreeDirEntry, Hash, HashTree, HashTreeConfig,
+    HashTreeError, Link, LinkType, MergeConflict, MergeResult, MergeStrategy, PosixMeta, Store,
+};
 use thiserror::Error;
 
 pub const ROOT_INODE: u64 = 1;
@@ -25,6 +30,8 @@ pub enum FsError {
     NotEmpty,
     #[error("invalid entry name")]
     InvalidName,
+    #[error("not a symbolic link")]
+    NotSymlink,
     #[error("tree error: {0}")]
     Tree(String),
     #[error("publish error: {0}")]
@@ -41,6 +48,7 @@ impl From<HashTreeError> for FsError {
 pub enum EntryKind {
     File,
     Directory,
+    Symlink,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -48,6 +56,10 @@ pub struct EntryAttr {
     pub inode: u64,
     pub size: u64,
     pub kind: EntryKind,
+    /// Permission bits recorded in the tree, if any
+    pub mode: Option<u32>,
+    /// Modification time (seconds since the Unix epoch) recorded in the tree, if any
+    pub mtime: Option<i64>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -59,6 +71,14 @@ pub struct DirEntry {
 
 pub trait RootPublisher: Send + Sync {
     fn publish(&self, cid: &Cid) -> Result<(), FsError>;
+
+    /// Latest root published for the tree by any writer, if known
+    ///
+    /// Checked before each publish: a root the mount hasn't seen yet is
+    /// merged into the mount first. The default never reports one.
+    fn latest(&self) -> Result<Option<Cid>, FsError> {
+        Ok(None)
+    }
 }
 
 #[derive(Debug, Clone, Eq)]
@@ -84,6 +104,115 @@ struct ResolvedEntry {
     cid: Cid,
     link_type: LinkType,
     size: u64,
+    meta: Option<HashMap<String, serde_json::Value>>,
+}
+
+impl ResolvedEntry {
+    fn posix(&self) -> PosixMeta {
+        PosixMeta::from_meta(self.meta.as_ref())
+    }
+}
+
+/// Unflushed writes to an open file
+///
+/// Content is tracked in pages that follow the leaf chunks of the last
+/// flushed version, whatever chunking produced them (fixed or content-defined),
+/// and continue in `chunk_size` pages past its end. Pages that were never
+/// written keep pointing at the base chunks, so a flush only stores what
+/// changed.
+struct DirtyFile {
+    /// Leaf chunks of the last flushed version with their byte offsets
+    base: Vec<(u64, Link)>,
+    /// Size of the last flushed version, where the base pages end
+    base_end: u64,
+    /// Bytes of the base version still visible (shrinks on truncate)
+    base_len: u64,
+    pages: BTreeMap<u64, Page>,
+    size: u64,
+}
+
+enum Page {
+    /// Buffered bytes from the page start, zero-filled up to the page extent
+    Data(Vec<u8>),
+    /// A completely written page already stored as a chunk
+    Stored(Link),
+}
+
+impl DirtyFile {
+    fn new(chunks: Vec<Link>) -> Self {
+        let mut base = Vec::with_capacity(chunks.len());
+        let mut offset = 0;
+        for link in chunks.into_iter().filter(|link| link.size > 0) {
+            let size = link.size;
+            base.push((offset, link));
+            offset += size;
+        }
+        Self {
+            base,
+            base_end: offset,
+            base_len: offset,
+            pages: BTreeMap::new(),
+            size: offset,
+        }
+    }
+
+    /// Index of the page containing byte `offset`
+    fn page_index(&self, offset: u64, page_size: u64) -> u64 {
+        if offset < self.base_end {
+            self.base
+                .partition_point(|(start, link)| start + link.size <= offset) as u64
+        } else {
+            self.base.len() as u64 + (offset - self.base_end) / page_size
+        }
+    }
+
+    /// Byte range `[start, end)` covered by page `index`
+    fn page_range(&self, index: u64, page_size: u64) -> (u64, u64) {
+        match self.base.get(index as usize) {
+            Some((start, link)) => (*start, start + link.size),
+            None => {
+                let start = self.base_end + (index - self.base.len() as u64) * page_size;
+                (start, start + page_size)
+            }
+        }
+    }
+
+    /// Number of pages starting below `size`
+    fn page_count(&self, size: u64, page_size: u64) -> u64 {
+        if size == 0 {
+            0
+        } else {
+            self.page_index(size - 1, page_size) + 1
+        }
+    }
+
+    /// Base chunk that exactly covers `[start, start + len)`, if still visible
+    fn base_chunk(&self, start: u64, len: u64) -> Option<&Link> {
+        if start + len > self.base_len {
+            return None;
+        }
+        let index = self
+            .base
+            .binary_search_by_key(&start, |(offset, _)| *offset)
+            .ok()?;
+        let link = &self.base[index].1;
+        if link.size == len {
+            Some(link)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Default)]
+struct PublishState {
+    pending: bool,
+    last_publish: Option<Instant>,
+    /// Last root handed to the publisher (the mount root until then),
+    /// used as the base when merging a concurrent update
+    published: Option<Cid>,
+    /// Roots published from this mount or already merged into it
+    seen: HashSet<Hash>,
 }
 
 pub struct HashtreeFuse<S: Store> {
@@ -94,6 +223,10 @@ pub struct HashtreeFuse<S: Store> {
     parents: RwLock<HashMap<u64, u64>>,
     next_inode: AtomicU64,
     publisher: Option<Arc<dyn RootPublisher>>,
+    publish_debounce: Option<Duration>,
+    publish_state: Mutex<PublishState>,
+    conflict_resolver: Arc<dyn ConflictResolver>,
+    dirty: Mutex<HashMap<u64, DirtyFile>>,
     modify_lock: Mutex<()>,
 }
 
@@ -125,16 +258,40 @@ impl<S: Store> HashtreeFuse<S> {
 
         Ok(Self {
             tree,
+            publish_state: Mutex::new(PublishState {
+                published: Some(root.clone()),
+                seen: HashSet::from([root.hash]),
+                ..PublishState::default()
+            }),
             root: RwLock::new(root),
             paths: RwLock::new(paths),
             children: RwLock::new(HashMap::new()),
             parents: RwLock::new(parents),
             next_inode: AtomicU64::new(ROOT_INODE + 1),
             publisher,
+            publish_debounce: None,
+            conflict_resolver: Arc::new(MergeStrategy::KeepBoth),
+            dirty: Mutex::new(HashMap::new()),
             modify_lock: Mutex::new(()),
         })
     }
 
+    /// Publish at most once per `interval`
+    ///
+    /// Root changes inside the interval are published by a later flush or by
+    /// `sync()`.
+    pub fn with_publish_debounce(mut self, interval: Duration) -> Self {
+        self.publish_debounce = Some(interval);
+        self
+    }
+
+    /// How conflicts are settled when a publish merges in a root published
+    /// elsewhere (by default both versions are kept)
+    pub fn with_conflict_resolver(mut self, resolver: Arc<dyn ConflictResolver>) -> Self {
+        self.conflict_resolver = resolver;
+        self
+    }
+
     pub fn current_root(&self) -> Cid {
         self.root.read().unwrap().clone()
     }
@@ -170,6 +327,8 @@ impl<S: Store> HashtreeFuse<S> {
                 inode,
                 size: 0,
                 kind: EntryKind::Directory,
+                mode: None,
+                mtime: None,
             });
         }
 
@@ -179,6 +338,11 @@ impl<S: Store> HashtreeFuse<S> {
     }
 
     pub fn read_file(&self, inode: u64, offset: u64, size: u32) -> Result<Vec<u8>, FsError> {
+        if let Some(file) = self.dirty.lock().unwrap().get(&inode) {
+            let end = offset.saturating_add(size as u64).min(file.size);
+            return self.read_dirty_range(file, offset, end);
+        }
+
         let path = self.path_for_inode(inode)?;
         let entry = self.resolve_entry(&path)?;
         if entry.link_type == LinkType::Dir {
@@ -217,6 +381,13 @@ impl<S: Store> HashtreeFuse<S> {
         Ok(data)
     }
 
+    /// Target of a symlink entry
+    pub fn read_link(&self, inode: u64) -> Result<String, FsError> {
+        let path = self.path_for_inode(inode)?;
+        let entry = self.resolve_entry(&path)?;
+        entry.posix().symlink.ok_or(FsError::NotSymlink)
+    }
+
     pub fn read_dir(&self, inode: u64) -> Result<Vec<DirEntry>, FsError> {
         let path = self.path_for_inode(inode)?;
         let dir_cid = self.resolve_dir_cid(&path)?;
@@ -226,10 +397,11 @@ impl<S: Store> HashtreeFuse<S> {
 
         for entry in entries {
             let child_inode = self.get_or_create_child_inode(inode, &entry.name)?;
+            let kind = Self::kind_from_entry(entry.link_type, &entry.posix());
             out.push(DirEntry {
                 inode: child_inode,
                 name: entry.name,
-                kind: Self::kind_from_link(entry.link_type),
+                kind,
             });
         }
 
@@ -266,6 +438,8 @@ impl<S: Store> HashtreeFuse<S> {
             inode,
             size,
             kind: EntryKind::File,
+            mode: None,
+            mtime: None,
         })
     }
 
@@ -298,25 +472,103 @@ impl<S: Store> HashtreeFuse<S> {
             inode,
             size: 0,
             kind: EntryKind::Directory,
+            mode: None,
+            mtime: None,
         })
     }
 
+    /// Buffer a write; the tree is updated when the file is flushed
     pub fn write_file(&self, inode: u64, offset: u64, data: &[u8]) -> Result<u32, FsError> {
         let _guard = self.modify_lock.lock().unwrap();
-        let path = self.path_for_inode(inode)?;
-        let existing = self.read_file_full(&path)?;
-        let new_data = Self::apply_write(existing, offset, data);
-        self.update_file_at_path(&path, new_data)?;
+        let mut dirty = self.dirty.lock().unwrap();
+        let file = self.dirty_file(&mut dirty, inode)?;
+        self.buffer_write(file, offset, data)?;
         Ok(data.len() as u32)
     }
 
+    /// Buffer a size change; the tree is updated when the file is flushed
     pub fn truncate_file(&self, inode: u64, size: u64) -> Result<(), FsError> {
         let _guard = self.modify_lock.lock().unwrap();
-        let path = self.path_for_inode(inode)?;
-        let existing = self.read_file_full(&path)?;
-        let new_data = Self::apply_truncate(existing, size);
-        self.update_file_at_path(&path, new_data)?;
-        Ok(())
+        let mut dirty = self.dirty.lock().unwrap();
+        let file = self.dirty_file(&mut dirty, inode)?;
+        self.buffer_truncate(file, size)
+    }
+
+    /// Write buffered changes of a file into the tree and publish the new root
+    pub fn flush(&self, inode: u64) -> Result<(), FsError> {
+        let _guard = self.modify_lock.lock().unwrap();
+        self.flush_locked(inode)?;
+        self.publish_root(false)
+    }
+
+    /// Like `flush`, but publishes immediately regardless of debounce
+    pub fn fsync(&self, inode: u64) -> Result<(), FsError> {
+        let _guard = self.modify_lock.lock().unwrap();
+        self.flush_locked(inode)?;
+        self.publish_root(true)
+    }
+
+    /// Flush all buffered files and publish any unpublished root
+    pub fn sync(&self) -> Result<(), FsError> {
+        let _guard = self.modify_lock.lock().unwrap();
+        let inodes: Vec<u64> = self.dirty.lock().unwrap().keys().copied().collect();
+        for inode in inodes {
+            self.flush_locked(inode)?;
+        }
+        self.publish_root(true)
+    }
+
+    /// Merge a root published elsewhere (e.g. another device) into the mount
+    ///
+    /// Buffered writes are flushed first, then the current root is merged
+    /// with `theirs` using the last published root as the base. Conflicts
+    /// are settled by `resolver` and returned; the merged root is published
+    /// right away.
+    pub fn merge_root(
+        &self,
+        theirs: &Cid,
+        resolver: &dyn ConflictResolver,
+    ) -> Result<Vec<MergeConflict>, FsError> {
+        let _guard = self.modify_lock.lock().unwrap();
+        let inodes: Vec<u64> = self.dirty.lock().unwrap().keys().copied().collect();
+        for inode in inodes {
+            self.flush_locked(inode)?;
+        }
+
+        let before = self.current_root();
+        let base = self.publish_state.lock().unwrap().published.clone();
+        let result = self.merge_into_root(base.as_ref(), theirs, resolver)?;
+        {
+            let mut state = self.publish_state.lock().unwrap();
+            state.seen.insert(theirs.hash);
+            if result.root != before {
+                state.pending = true;
+            }
+        }
+        self.publish_root(true)?;
+        Ok(result.conflicts)
+    }
+
+    /// Merge `theirs` into the current root against `base`, without publishing
+    fn merge_into_root(
+        &self,
+        base: Option<&Cid>,
+        theirs: &Cid,
+        resolver: &dyn ConflictResolver,
+    ) -> Result<MergeResult, FsError> {
+        // A root that isn't available would list as empty, i.e. as deleting everything
+        if block_on(self.tree.get_directory_node(theirs))?.is_none() {
+            return Err(FsError::InvalidRoot);
+        }
+        let result = block_on(merge_trees(
+            &self.tree,
+            base,
+            &self.current_root(),
+            theirs,
+            resolver,
+        ))?;
+        *self.root.write().unwrap() = result.root.clone();
+        Ok(result)
     }
 
     pub fn unlink(&self, parent: u64, name: &str) -> Result<(), FsError> {
@@ -338,6 +590,7 @@ impl<S: Store> HashtreeFuse<S> {
         ))?;
 
         self.apply_root_update(new_root)?;
+        self.discard_dirty(parent, name);
         self.remove_paths_prefix(&child_path);
         self.children.write().unwrap().remove(&ChildKey {
             parent,
@@ -422,6 +675,20 @@ impl<S: Store> HashtreeFuse<S> {
         let mut new_path = new_parent_path.clone();
         new_path.push(new_name.to_string());
 
+        // Buffered writes follow the inode; those of a replaced file are dropped
+        let replaced = self
+            .children
+            .read()
+            .unwrap()
+            .get(&ChildKey {
+                parent: new_parent,
+                name: new_name.to_string(),
+            })
+            .copied();
+        if let Some(replaced) = replaced.filter(|replaced| *replaced != inode) {
+            self.dirty.lock().unwrap().remove(&replaced);
+        }
+
         self.children.write().unwrap().remove(&ChildKey {
             parent,
             name: name.to_string(),
@@ -462,6 +729,7 @@ impl<S: Store> HashtreeFuse<S> {
                 cid: self.current_root(),
                 link_type: LinkType::Dir,
                 size: 0,
+                meta: None,
             });
         }
 
@@ -480,6 +748,7 @@ impl<S: Store> HashtreeFuse<S> {
             },
             link_type: entry.link_type,
             size: entry.size,
+            meta: entry.meta,
         })
     }
 
@@ -505,14 +774,24 @@ impl<S: Store> HashtreeFuse<S> {
         inode: u64,
         entry: ResolvedEntry,
     ) -> Result<EntryAttr, FsError> {
-        let kind = Self::kind_from_link(entry.link_type);
+        let posix = entry.posix();
+        let kind = Self::kind_from_entry(entry.link_type, &posix);
+        let dirty_size = self.dirty.lock().unwrap().get(&inode).map(|file| file.size);
         let size = if kind == EntryKind::Directory {
             0
+        } else if let Some(size) = dirty_size {
+            size
         } else {
             self.entry_size(&entry)?
         };
 
-        Ok(EntryAttr { inode, size, kind })
+        Ok(EntryAttr {
+            inode,
+            size,
+            kind,
+            mode: posix.mode,
+            mtime: posix.mtime,
+        })
     }
 
     fn entry_size(&self, entry: &ResolvedEntry) -> Result<u64, FsError> {
@@ -527,40 +806,331 @@ impl<S: Store> HashtreeFuse<S> {
         Ok(data.len() as u64)
     }
 
-    fn read_file_full(&self, path: &[String]) -> Result<Vec<u8>, FsError> {
-        let entry = self.resolve_entry(path)?;
-        if entry.link_type == LinkType::Dir {
-            return Err(FsError::IsDir);
+    fn dirty_file<'a>(
+        &self,
+        dirty: &'a mut HashMap<u64, DirtyFile>,
+        inode: u64,
+    ) -> Result<&'a mut DirtyFile, FsError> {
+        match dirty.entry(inode) {
+            Entry::Occupied(file) => Ok(file.into_mut()),
+            Entry::Vacant(slot) => {
+                let path = self.path_for_inode(inode)?;
+                let entry = self.resolve_entry(&path)?;
+                if entry.link_type == LinkType::Dir {
+                    return Err(FsError::IsDir);
+                }
+                let chunks = block_on(self.tree.file_chunks(&entry.cid))?;
+                Ok(slot.insert(DirtyFile::new(chunks)))
+            }
         }
-        let data = block_on(self.tree.get(&entry.cid, None))?.ok_or(FsError::NotFound)?;
+    }
+
+    fn discard_dirty(&self, parent: u64, name: &str) {
+        let inode = self
+            .children
+            .read()
+            .unwrap()
+            .get(&ChildKey {
+                parent,
+                name: name.to_string(),
+            })
+            .copied();
+        if let Some(inode) = inode {
+            self.dirty.lock().unwrap().remove(&inode);
+        }
+    }
+
+    fn page_size(&self) -> u64 {
+        self.tree.chunk_size() as u64
+    }
+
+    /// Number of bytes of page `index` that lie within the file
+    fn page_extent(&self, file: &DirtyFile, index: u64) -> u64 {
+        let (start, end) = file.page_range(index, self.page_size());
+        file.size.min(end).saturating_sub(start)
+    }
+
+    fn read_chunk(&self, link: &Link) -> Result<Vec<u8>, FsError> {
+        let cid = Cid {
+            hash: link.hash,
+            key: link.key,
+        };
+        block_on(self.tree.get(&cid, None))?.ok_or(FsError::NotFound)
+    }
+
+    /// Read `[start, end)` of the base version, zero-filled past `base_len`
+    fn read_base(&self, file: &DirtyFile, start: u64, end: u64) -> Result<Vec<u8>, FsError> {
+        let mut out = Vec::with_capacity((end - start) as usize);
+        let visible_end = end.min(file.base_len);
+        for (offset, link) in &file.base {
+            if *offset >= visible_end {
+                break;
+            }
+            let chunk_end = offset + link.size;
+            if chunk_end <= start {
+                continue;
+            }
+            let data = self.read_chunk(link)?;
+            let from = start.saturating_sub(*offset) as usize;
+            let to = (visible_end.min(chunk_end) - offset) as usize;
+            out.extend_from_slice(&data[from..to]);
+        }
+        out.resize((end - start) as usize, 0);
+        Ok(out)
+    }
+
+    /// Full current content of page `index`
+    fn page_bytes(&self, file: &DirtyFile, index: u64) -> Result<Vec<u8>, FsError> {
+        let extent = self.page_extent(file, index);
+        let mut data = match file.pages.get(&index) {
+            Some(Page::Data(data)) => data.clone(),
+            Some(Page::Stored(link)) => self.read_chunk(link)?,
+            None => {
+                let (start, _) = file.page_range(index, self.page_size());
+                return self.read_base(file, start, start + extent);
+            }
+        };
+        data.resize(extent as usize, 0);
         Ok(data)
     }
 
-    fn update_file_at_path(&self, path: &[String], data: Vec<u8>) -> Result<(), FsError> {
-        let (parent_path, name) = path.split_at(path.len() - 1);
-        let (cid, size) = block_on(self.tree.put(&data))?;
-        let link_type = self.link_type_for_size(size);
+    fn read_dirty_range(&self, file: &DirtyFile, start: u64, end: u64) -> Result<Vec<u8>, FsError> {
+        if start >= end {
+            return Ok(vec![]);
+        }
+        let page_size = self.page_size();
+        let mut out = Vec::with_capacity((end - start) as usize);
+        for index in file.page_index(start, page_size)..=file.page_index(end - 1, page_size) {
+            let (page_start, _) = file.page_range(index, page_size);
+            let data = self.page_bytes(file, index)?;
+            let from = start.saturating_sub(page_start) as usize;
+            let to = (end - page_start).min(data.len() as u64) as usize;
+            out.extend_from_slice(&data[from..to]);
+        }
+        Ok(out)
+    }
 
-        let new_root = block_on(self.tree.set_entry(
+    /// Buffered bytes of page `index`, loading them if needed
+    fn load_page<'a>(
+        &self,
+        file: &'a mut DirtyFile,
+        index: u64,
+    ) -> Result<&'a mut Vec<u8>, FsError> {
+        let loaded = match file.pages.get(&index) {
+            Some(Page::Data(_)) => None,
+            Some(Page::Stored(link)) => Some(self.read_chunk(link)?),
+            None => {
+                let (start, _) = file.page_range(index, self.page_size());
+                let end = start + self.page_extent(file, index);
+                Some(self.read_base(file, start, end)?)
+            }
+        };
+        if let Some(data) = loaded {
+            file.pages.insert(index, Page::Data(data));
+        }
+        match file.pages.get_mut(&index) {
+            Some(Page::Data(data)) => Ok(data),
+            _ => unreachable!("page was just loaded"),
+        }
+    }
+
+    fn buffer_write(&self, file: &mut DirtyFile, offset: u64, data: &[u8]) -> Result<(), FsError> {
+        let page_size = self.page_size();
+        let end = offset + data.len() as u64;
+        file.size = file.size.max(end);
+
+        let mut pos = offset;
+        while pos < end {
+            let index = file.page_index(pos, page_size);
+            let (page_start, page_end) = file.page_range(index, page_size);
+            let page_len = (page_end - page_start) as usize;
+            let in_page = (pos - page_start) as usize;
+            let len = (end - pos).min((page_len - in_page) as u64) as usize;
+            let src = &data[(pos - offset) as usize..][..len];
+
+            let page = self.load_page(file, index)?;
+            if page.len() < in_page + len {
+                page.resize(in_page + len, 0);
+            }
+            page[in_page..in_page + len].copy_from_slice(src);
+
+            // A write that completes a page (e.g. a sequential copy) stores it
+            // right away instead of holding the whole file in memory
+            if in_page + len == page_len && page.len() == page_len {
+                let link = block_on(self.tree.put_chunk(page))?;
+                file.pages.insert(index, Page::Stored(link));
+            }
+            pos += len as u64;
+        }
+        Ok(())
+    }
+
+    fn buffer_truncate(&self, file: &mut DirtyFile, size: u64) -> Result<(), FsError> {
+        let page_size = self.page_size();
+        if size < file.size {
+            file.base_len = file.base_len.min(size);
+            let page_count = file.page_count(size, page_size);
+            file.pages.split_off(&page_count);
+
+            // Cut the last remaining page at the new size
+            let index = page_count.saturating_sub(1);
+            let (page_start, page_end) = file.page_range(index, page_size);
+            if size > page_start && size < page_end {
+                let tail = (size - page_start) as usize;
+                let data = match file.pages.get(&index) {
+                    Some(Page::Data(data)) => Some(data[..tail.min(data.len())].to_vec()),
+                    Some(Page::Stored(link)) => {
+                        let mut data = self.read_chunk(link)?;
+                        data.truncate(tail);
+                        Some(data)
+                    }
+                    None => None,
+                };
+                if let Some(data) = data {
+                    file.pages.insert(index, Page::Data(data));
+                }
+            }
+        }
+        file.size = size;
+        Ok(())
+    }
+
+    /// Store the file's pages as chunks, reusing unchanged base chunks
+    ///
+    /// Base pages can be smaller than `chunk_size`, so the link type follows
+    /// the number of chunks rather than the file size.
+    fn store_dirty_file(&self, file: &DirtyFile) -> Result<(Cid, u64, LinkType), FsError> {
+        let page_size = self.page_size();
+        let page_count = file.page_count(file.size, page_size);
+        let mut chunks = Vec::with_capacity(page_count as usize);
+
+        for index in 0..page_count {
+            let (start, _) = file.page_range(index, page_size);
+            let extent = self.page_extent(file, index);
+            let link = match file.pages.get(&index) {
+                Some(Page::Stored(link)) => link.clone(),
+                Some(Page::Data(data)) if data.len() as u64 == extent => {
+                    block_on(self.tree.put_chunk(data))?
+                }
+                Some(Page::Data(_)) => {
+                    let data = self.page_bytes(file, index)?;
+                    block_on(self.tree.put_chunk(&data))?
+                }
+                None => match file.base_chunk(start, extent) {
+                    Some(link) => link.clone(),
+                    None => {
+                        let data = self.read_base(file, start, start + extent)?;
+                        block_on(self.tree.put_chunk(&data))?
+                    }
+                },
+            };
+            chunks.push(link);
+        }
+
+        let link_type = if chunks.len() > 1 {
+            LinkType::File
+        } else {
+            LinkType::Blob
+        };
+        let (cid, size) = block_on(self.tree.put_file_from_chunks(chunks))?;
+        Ok((cid, size, link_type))
+    }
+
+    /// Move a file's buffered changes into the tree without publishing
+    fn flush_locked(&self, inode: u64) -> Result<(), FsError> {
+        let mut dirty = self.dirty.lock().unwrap();
+        let file = match dirty.get(&inode) {
+            Some(file) => file,
+            None => return Ok(()),
+        };
+
+        let path = self.path_for_inode(inode)?;
+        let (parent_path, name) = path.split_at(path.len() - 1);
+        let (cid, size, link_type) = self.store_dirty_file(file)?;
+
+        // Keep existing metadata (mode etc.) and stamp the new mtime
+        let mut meta = self
+            .resolve_entry(&path)
+            .ok()
+            .and_then(|entry| entry.meta)
+            .unwrap_or_default();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        let mut posix = PosixMeta::from_meta(Some(&meta)).with_mtime(now);
+        posix.symlink = None;
+        posix.apply_to(&mut meta);
+
+        let entry = TreeDirEntry::from_cid(name[0].as_str(), &cid)
+            .with_size(size)
+            .with_link_type(link_type)
+            .with_meta(meta);
+        let new_root = block_on(self.tree.set_dir_entry(
             &self.current_root(),
             &self.path_refs(parent_path),
-            name[0].as_str(),
-            &cid,
-            size,
-            link_type,
+            entry,
         ))?;
 
-        self.apply_root_update(new_root)
+        dirty.remove(&inode);
+        self.set_root(new_root);
+        Ok(())
     }
 
-    fn apply_root_update(&self, new_root: Cid) -> Result<(), FsError> {
+    fn set_root(&self, new_root: Cid) {
+        let changed = {
+            let mut root = self.root.write().unwrap();
+            let changed = *root != new_root;
+            *root = new_root;
+            changed
+        };
+        if changed {
+            self.publish_state.lock().unwrap().pending = true;
+        }
+    }
+
+    /// Publish the current root if it changed, honoring debounce unless forced
+    ///
+    /// A root published elsewhere since the last publish is merged in first.
+    fn publish_root(&self, force: bool) -> Result<(), FsError> {
+        let mut state = self.publish_state.lock().unwrap();
+        if !state.pending {
+            return Ok(());
+        }
+        if !force {
+            if let (Some(interval), Some(last)) = (self.publish_debounce, state.last_publish) {
+                if last.elapsed() < interval {
+                    return Ok(());
+                }
+            }
+        }
+
+        if let Some(publisher) = &self.publisher {
+            if let Some(theirs) = publisher.latest()? {
+                if !state.seen.contains(&theirs.hash) {
+                    let base = state.published.clone();
+                    self.merge_into_root(base.as_ref(), &theirs, &*self.conflict_resolver)?;
+                    state.seen.insert(theirs.hash);
+                }
+            }
+        }
+
+        let root = self.current_root();
         if let Some(publisher) = &self.publisher {
-            publisher.publish(&new_root)?;
+            publisher.publish(&root)?;
         }
-        *self.root.write().unwrap() = new_root;
+        state.pending = false;
+        state.seen.insert(root.hash);
+        state.published = Some(root);
+        state.last_publish = Some(Instant::now());
         Ok(())
     }
 
+    fn apply_root_update(&self, new_root: Cid) -> Result<(), FsError> {
+        self.set_root(new_root);
+        self.publish_root(false)
+    }
+
     fn link_type_for_size(&self, size: u64) -> LinkType {
         if size as usize > self.tree.chunk_size() {
             LinkType::File
@@ -688,6 +1258,14 @@ impl<S: Store> HashtreeFuse<S> {
             .unwrap_or(ROOT_INODE)
     }
 
+    fn kind_from_entry(link_type: LinkType, posix: &PosixMeta) -> EntryKind {
+        if posix.is_symlink() {
+            EntryKind::Symlink
+        } else {
+            Self::kind_from_link(link_type)
+        }
+    }
+
     fn kind_from_link(link_type: LinkType) -> EntryKind {
         match link_type {
             LinkType::Dir => EntryKind::Directory,
@@ -695,28 +1273,6 @@ impl<S: Store> HashtreeFuse<S> {
         }
     }
 
-    fn apply_write(mut existing: Vec<u8>, offset: u64, data: &[u8]) -> Vec<u8> {
-        let offset_usize = offset as usize;
-        if existing.len() < offset_usize {
-            existing.resize(offset_usize, 0);
-        }
-        if existing.len() < offset_usize + data.len() {
-            existing.resize(offset_usize + data.len(), 0);
-        }
-        existing[offset_usize..offset_usize + data.len()].copy_from_slice(data);
-        existing
-    }
-
-    fn apply_truncate(mut existing: Vec<u8>, size: u64) -> Vec<u8> {
-        let size = size as usize;
-        if existing.len() > size {
-            existing.truncate(size);
-        } else if existing.len() < size {
-            existing.resize(size, 0);
-        }
-        existing
-    }
-
     fn path_refs<'a>(&self, path: &'a [String]) -> Vec<&'a str> {
         path.iter().map(|p| p.as_str()).collect()
     }
@@ -745,7 +1301,7 @@ mod fuse_impl {
     impl FsError {
         fn errno(&self) -> i32 {
             match self {
-                FsError::InvalidRoot | FsError::InvalidName => libc::EINVAL,
+                FsError::InvalidRoot | FsError::InvalidName | FsError::NotSymlink => libc::EINVAL,
                 FsError::NotFound => libc::ENOENT,
                 FsError::NotDir => libc::ENOTDIR,
                 FsError::IsDir => libc::EISDIR,
@@ -766,9 +1322,19 @@ mod fuse_impl {
         }
 
         fn file_attr(&self, attr: &EntryAttr) -> FileAttr {
-            let (kind, perm, nlink) = match attr.kind {
+            let (kind, default_perm, nlink) = match attr.kind {
                 EntryKind::Directory => (FileType::Directory, 0o755, 2),
                 EntryKind::File => (FileType::RegularFile, 0o644, 1),
+                EntryKind::Symlink => (FileType::Symlink, 0o777, 1),
+            };
+            let perm = attr
+                .mode
+                .map_or(default_perm, |mode| (mode & 0o7777) as u16);
+            let mtime = match attr.mtime {
+                Some(secs) if secs >= 0 => {
+                    SystemTime::UNIX_EPOCH + Duration::from_secs(secs as u64)
+                }
+                _ => SystemTime::UNIX_EPOCH,
             };
             let uid = unsafe { libc::geteuid() };
             let gid = unsafe { libc::getegid() };
@@ -778,9 +1344,9 @@ mod fuse_impl {
                 ino: attr.inode,
                 size: attr.size,
                 blocks,
-                atime: SystemTime::UNIX_EPOCH,
-                mtime: SystemTime::UNIX_EPOCH,
-                ctime: SystemTime::UNIX_EPOCH,
+                atime: mtime,
+                mtime,
+                ctime: mtime,
                 crtime: SystemTime::UNIX_EPOCH,
                 kind,
                 perm,
@@ -797,6 +1363,7 @@ mod fuse_impl {
             match kind {
                 EntryKind::Directory => FileType::Directory,
                 EntryKind::File => FileType::RegularFile,
+                EntryKind::Symlink => FileType::Symlink,
             }
         }
     }
@@ -824,6 +1391,13 @@ mod fuse_impl {
             }
         }
 
+        fn readlink(&mut self, _req: &Request<'_>, ino: u64, reply: ReplyData) {
+            match self.read_link(ino) {
+                Ok(target) => reply.data(target.as_bytes()),
+                Err(err) => reply.error(err.errno()),
+            }
+        }
+
         fn open(&mut self, _req: &Request<'_>, _ino: u64, _flags: i32, reply: fuser::ReplyOpen) {
             reply.opened(0, 0);
         }
@@ -865,6 +1439,54 @@ mod fuse_impl {
             }
         }
 
+        fn flush(
+            &mut self,
+            _req: &Request<'_>,
+            ino: u64,
+            _fh: u64,
+            _lock_owner: u64,
+            reply: ReplyEmpty,
+        ) {
+            match HashtreeFuse::flush(self, ino) {
+                Ok(()) => reply.ok(),
+                Err(err) => reply.error(err.errno()),
+            }
+        }
+
+        fn release(
+            &mut self,
+            _req: &Request<'_>,
+            ino: u64,
+            _fh: u64,
+            _flags: i32,
+            _lock_owner: Option<u64>,
+            _flush: bool,
+            reply: ReplyEmpty,
+        ) {
+            match HashtreeFuse::flush(self, ino) {
+                Ok(()) => reply.ok(),
+                Err(err) => reply.error(err.errno()),
+            }
+        }
+
+        fn fsync(
+            &mut self,
+            _req: &Request<'_>,
+            ino: u64,
+            _fh: u64,
+            _datasync: bool,
+            reply: ReplyEmpty,
+        ) {
+            match HashtreeFuse::fsync(self, ino) {
+                Ok(()) => reply.ok(),
+                Err(err) => reply.error(err.errno()),
+            }
+        }
+
+        fn destroy(&mut self) {
+            let _ = self.sync();
+        }
+
         fn create(
             &mut self,
             _req: &Request<'_>,
@@ -1056,21 +1678,29 @@ mod fuse_impl {
 mod tests {
     use super::*;
     use hashtree_core::store::MemoryStore;
+    use hashtree_core::{CdcParams, ChunkingMode, MergeStrategy};
 
     struct RecordingPublisher {
         updates: Mutex<Vec<Cid>>,
+        /// Root reported as published elsewhere
+        latest: Mutex<Option<Cid>>,
     }
 
     impl RecordingPublisher {
         fn new() -> Self {
             Self {
                 updates: Mutex::new(Vec::new()),
+                latest: Mutex::new(None),
             }
         }
 
         fn updates(&self) -> Vec<Cid> {
             self.updates.lock().unwrap().clone()
         }
+
+        fn set_latest(&self, cid: &Cid) {
+            *self.latest.lock().unwrap() = Some(cid.clone());
+        }
     }
 
     impl RootPublisher for RecordingPublisher {
@@ -1078,6 +1708,10 @@ mod tests {
             self.updates.lock().unwrap().push(cid.clone());
             Ok(())
         }
+
+        fn latest(&self) -> Result<Option<Cid>, FsError> {
+            Ok(self.latest.lock().unwrap().clone())
+        }
     }
 
     async fn empty_root(store: Arc<MemoryStore>) -> Cid {
@@ -1099,6 +1733,145 @@ mod tests {
         assert_eq!(read, b"hello");
     }
 
+    #[tokio::test]
+    async fn test_posix_metadata_and_symlinks() {
+        let store = Arc::new(MemoryStore::new());
+        let tree = HashTree::new(HashTreeConfig::new(store.clone()));
+        let (file_cid, file_size) = tree.put(b"#!/bin/sh\n").await.unwrap();
+        let (link_cid, link_size) = tree.put(b"run.sh").await.unwrap();
+        let root = tree
+            .put_directory(vec![
+                TreeDirEntry::from_cid("run.sh", &file_cid)
+                    .with_size(file_size)
+                    .with_posix(&PosixMeta::default().with_mode(0o755).with_mtime(1_000)),
+                TreeDirEntry::from_cid("run", &link_cid)
+                    .with_size(link_size)
+                    .with_posix(&PosixMeta::default().with_symlink("run.sh")),
+            ])
+            .await
+            .unwrap();
+        let fs = HashtreeFuse::new(store, root).unwrap();
+
+        let file = fs.lookup_child(ROOT_INODE, "run.sh").unwrap();
+        assert_eq!(file.kind, EntryKind::File);
+        assert_eq!(file.mode, Some(0o755));
+        assert_eq!(file.mtime, Some(1_000));
+        assert!(matches!(fs.read_link(file.inode), Err(FsError::NotSymlink)));
+
+        let link = fs.lookup_child(ROOT_INODE, "run").unwrap();
+        assert_eq!(link.kind, EntryKind::Symlink);
+        assert_eq!(fs.read_link(link.inode).unwrap(), "run.sh");
+        let kinds: Vec<_> = fs
+            .read_dir(ROOT_INODE)
+            .unwrap()
+            .into_iter()
+            .map(|e| (e.name, e.kind))
+            .collect();
+        assert!(kinds.contains(&("run".to_string(), EntryKind::Symlink)));
+
+        // Writing keeps the mode and moves the mtime forward
+        fs.write_file(file.inode, 0, b"#!/bin/bash").unwrap();
+        fs.flush(file.inode).unwrap();
+        let file = fs.get_attr(file.inode).unwrap();
+        assert_eq!(file.mode, Some(0o755));
+        assert!(file.mtime.unwrap() > 1_000);
+    }
+
+    #[tokio::test]
+    async fn test_merge_concurrent_root() {
+        let store = Arc::new(MemoryStore::new());
+        let root = empty_root(store.clone()).await;
+        let publisher = Arc::new(RecordingPublisher::new());
+        let fs =
+            HashtreeFuse::new_with_publisher(store.clone(), root, Some(publisher.clone())).unwrap();
+
+        let local = fs.create_file(ROOT_INODE, "local.txt").unwrap();
+        fs.write_file(local.inode, 0, b"v1").unwrap();
+        fs.flush(local.inode).unwrap();
+        let published = fs.current_root();
+
+        // Another device picks up the published root and adds remote.txt
+        let tree = HashTree::new(HashTreeConfig::new(store));
+        let (cid, size) = tree.put(b"remote").await.unwrap();
+        let theirs = tree
+            .set_entry(&published, &[], "remote.txt", &cid, size, LinkType::Blob)
+            .await
+            .unwrap();
+
+        // Meanwhile local.txt changes here (still buffered)
+        fs.write_file(local.inode, 0, b"v2").unwrap();
+
+        let conflicts = fs.merge_root(&theirs, &MergeStrategy::Theirs).unwrap();
+        assert!(conflicts.is_empty());
+        let names: Vec<String> = fs
+            .read_dir(ROOT_INODE)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(names, vec!["local.txt", "remote.txt"]);
+        assert_eq!(fs.read_file(local.inode, 0, 16).unwrap(), b"v2");
+        assert_eq!(publisher.updates().last(), Some(&fs.current_root()));
+
+        // Both sides now edit local.txt: resolved with the strategy and reported
+        let merged = fs.current_root();
+        let (cid, size) = tree.put(b"theirs").await.unwrap();
+        let theirs = tree
+            .set_entry(&merged, &[], "local.txt", &cid, size, LinkType::Blob)
+            .await
+            .unwrap();
+        fs.write_file(local.inode, 0, b"v3").unwrap();
+
+        let conflicts = fs.merge_root(&theirs, &MergeStrategy::Theirs).unwrap();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].path, "local.txt");
+        assert_eq!(fs.read_file(local.inode, 0, 16).unwrap(), b"theirs");
+    }
+
+    #[tokio::test]
+    async fn test_publish_merges_root_published_elsewhere() {
+        let store = Arc::new(MemoryStore::new());
+        let root = empty_root(store.clone()).await;
+        let publisher = Arc::new(RecordingPublisher::new());
+        let fs =
+            HashtreeFuse::new_with_publisher(store.clone(), root, Some(publisher.clone())).unwrap();
+
+        let local = fs.create_file(ROOT_INODE, "local.txt").unwrap();
+        fs.write_file(local.inode, 0, b"v1").unwrap();
+        fs.flush(local.inode).unwrap();
+        let published = fs.current_root();
+
+        // Another device publishes a root adding remote.txt
+        let tree = HashTree::new(HashTreeConfig::new(store));
+        let (cid, size) = tree.put(b"remote").await.unwrap();
+        let theirs = tree
+            .set_entry(&published, &[], "remote.txt", &cid, size, LinkType::Blob)
+            .await
+            .unwrap();
+        publisher.set_latest(&theirs);
+
+        // The next local publish carries both changes
+        fs.write_file(local.inode, 0, b"v2").unwrap();
+        fs.flush(local.inode).unwrap();
+        let names = |fs: &HashtreeFuse<MemoryStore>| -> Vec<String> {
+            fs.read_dir(ROOT_INODE)
+                .unwrap()
+                .into_iter()
+                .map(|e| e.name)
+                .collect()
+        };
+        assert_eq!(names(&fs), vec!["local.txt", "remote.txt"]);
+        assert_eq!(fs.read_file(local.inode, 0, 16).unwrap(), b"v2");
+        assert_eq!(publisher.updates().last(), Some(&fs.current_root()));
+
+        // Roots already merged or published here (e.g. a lagging relay) are not merged again
+        publisher.set_latest(&published);
+        let extra = fs.create_file(ROOT_INODE, "extra.txt").unwrap();
+        fs.flush(extra.inode).unwrap();
+        assert_eq!(names(&fs), vec!["extra.txt", "local.txt", "remote.txt"]);
+        assert_eq!(fs.read_file(local.inode, 0, 16).unwrap(), b"v2");
+    }
+
     #[tokio::test]
     async fn test_mkdir_and_rename() {
         let store = Arc::new(MemoryStore::new());
@@ -1139,9 +1912,168 @@ mod tests {
 
         let file = fs.create_file(ROOT_INODE, "note.txt").unwrap();
         fs.write_file(file.inode, 0, b"note").unwrap();
+        fs.flush(file.inode).unwrap();
 
         let updates = publisher.updates();
         assert!(!updates.is_empty());
         assert_eq!(updates.last().unwrap(), &fs.current_root());
     }
+
+    #[tokio::test]
+    async fn test_writes_published_on_flush() {
+        let store = Arc::new(MemoryStore::new());
+        let root = empty_root(store.clone()).await;
+        let publisher = Arc::new(RecordingPublisher::new());
+        let fs = HashtreeFuse::new_with_publisher(store, root, Some(publisher.clone())).unwrap();
+
+        let file = fs.create_file(ROOT_INODE, "log.txt").unwrap();
+        let published = publisher.updates().len();
+        let root_before = fs.current_root();
+
+        for i in 0..10u64 {
+            fs.write_file(file.inode, i * 4, b"line").unwrap();
+        }
+        assert_eq!(publisher.updates().len(), published);
+        assert_eq!(fs.current_root(), root_before);
+        assert_eq!(fs.get_attr(file.inode).unwrap().size, 40);
+        assert_eq!(fs.read_file(file.inode, 36, 10).unwrap(), b"line");
+
+        fs.flush(file.inode).unwrap();
+        assert_eq!(publisher.updates().len(), published + 1);
+        assert_eq!(publisher.updates().last().unwrap(), &fs.current_root());
+        assert_eq!(fs.read_file(file.inode, 0, 8).unwrap(), b"lineline");
+
+        // Nothing left to flush
+        fs.flush(file.inode).unwrap();
+        assert_eq!(publisher.updates().len(), published + 1);
+    }
+
+    #[tokio::test]
+    async fn test_flush_reuses_unchanged_chunks() {
+        let store = Arc::new(MemoryStore::new());
+        let root = empty_root(store.clone()).await;
+        let fs = HashtreeFuse::new(store.clone(), root).unwrap();
+        let chunk_size = fs.tree.chunk_size();
+
+        let data: Vec<u8> = (0..chunk_size * 2 + 1000)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        let file = fs.create_file(ROOT_INODE, "big.bin").unwrap();
+        for (i, part) in data.chunks(64 * 1024).enumerate() {
+            fs.write_file(file.inode, (i * 64 * 1024) as u64, part)
+                .unwrap();
+        }
+        fs.flush(file.inode).unwrap();
+
+        let tree = HashTree::new(HashTreeConfig::new(store));
+        let path = vec!["big.bin".to_string()];
+        let before = fs.resolve_entry(&path).unwrap().cid;
+        assert_eq!(tree.put(&data).await.unwrap().0, before);
+
+        fs.write_file(file.inode, chunk_size as u64 + 10, b"patch")
+            .unwrap();
+        fs.flush(file.inode).unwrap();
+
+        let after = fs.resolve_entry(&path).unwrap().cid;
+        let old_chunks = tree.file_chunks(&before).await.unwrap();
+        let new_chunks = tree.file_chunks(&after).await.unwrap();
+        assert_eq!(new_chunks.len(), 3);
+        assert_eq!(new_chunks[0].hash, old_chunks[0].hash);
+        assert_ne!(new_chunks[1].hash, old_chunks[1].hash);
+        assert_eq!(new_chunks[2].hash, old_chunks[2].hash);
+
+        let mut expected = data;
+        expected[chunk_size + 10..chunk_size + 15].copy_from_slice(b"patch");
+        assert_eq!(tree.get(&after, None).await.unwrap().unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn test_overwrite_content_defined_file() {
+        let store = Arc::new(MemoryStore::new());
+        let root = empty_root(store.clone()).await;
+
+        // Written elsewhere with content-defined chunks of varying size
+        let cdc = HashTree::new(HashTreeConfig::new(store.clone()).with_chunking(
+            ChunkingMode::ContentDefined(CdcParams::new(512, 2048, 8192)),
+        ));
+        let mut seed = 7u64;
+        let data: Vec<u8> = (0..64 * 1024)
+            .map(|_| {
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+                (seed >> 56) as u8
+            })
+            .collect();
+        let (cid, size) = cdc.put(&data).await.unwrap();
+        let old_chunks = cdc.file_chunks(&cid).await.unwrap();
+        assert!(old_chunks.len() > 3);
+        assert!(old_chunks.windows(2).any(|w| w[0].size != w[1].size));
+        let root = cdc
+            .set_entry(&root, &[], "cdc.bin", &cid, size, LinkType::File)
+            .await
+            .unwrap();
+
+        let fs = HashtreeFuse::new(store, root).unwrap();
+        let file = fs.lookup_child(ROOT_INODE, "cdc.bin").unwrap();
+        let offset = old_chunks[0].size + old_chunks[1].size + 5;
+        fs.write_file(file.inode, offset, b"patch").unwrap();
+
+        let mut expected = data;
+        expected[offset as usize..offset as usize + 5].copy_from_slice(b"patch");
+        assert_eq!(
+            fs.read_file(file.inode, offset - 10, 20).unwrap(),
+            &expected[offset as usize - 10..offset as usize + 10]
+        );
+
+        fs.flush(file.inode).unwrap();
+        let after = fs.resolve_entry(&["cdc.bin".to_string()]).unwrap();
+        assert_eq!(after.link_type, LinkType::File);
+        assert_eq!(cdc.get(&after.cid, None).await.unwrap().unwrap(), expected);
+
+        // Only the chunk holding the patch is rewritten
+        let new_chunks = cdc.file_chunks(&after.cid).await.unwrap();
+        assert_eq!(new_chunks.len(), old_chunks.len());
+        for (i, (new, old)) in new_chunks.iter().zip(&old_chunks).enumerate() {
+            assert_eq!(new.size, old.size);
+            assert_eq!(new.hash == old.hash, i != 2, "chunk {i}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_truncate_then_extend_zero_fills() {
+        let store = Arc::new(MemoryStore::new());
+        let root = empty_root(store.clone()).await;
+        let fs = HashtreeFuse::new(store, root).unwrap();
+
+        let file = fs.create_file(ROOT_INODE, "file.bin").unwrap();
+        fs.write_file(file.inode, 0, b"abcdef").unwrap();
+        fs.flush(file.inode).unwrap();
+
+        fs.truncate_file(file.inode, 2).unwrap();
+        fs.write_file(file.inode, 4, b"x").unwrap();
+        assert_eq!(fs.read_file(file.inode, 0, 10).unwrap(), b"ab\0\0x");
+
+        fs.flush(file.inode).unwrap();
+        assert_eq!(fs.read_file(file.inode, 0, 10).unwrap(), b"ab\0\0x");
+    }
+
+    #[tokio::test]
+    async fn test_publish_debounce() {
+        let store = Arc::new(MemoryStore::new());
+        let root = empty_root(store.clone()).await;
+        let publisher = Arc::new(RecordingPublisher::new());
+        let fs = HashtreeFuse::new_with_publisher(store, root, Some(publisher.clone()))
+            .unwrap()
+            .with_publish_debounce(Duration::from_secs(3600));
+
+        let file = fs.create_file(ROOT_INODE, "a.txt").unwrap();
+        assert_eq!(publisher.updates().len(), 1);
+
+        fs.write_file(file.inode, 0, b"a").unwrap();
+        fs.flush(file.inode).unwrap();
+        assert_eq!(publisher.updates().len(), 1);
+
+        fs.sync().unwrap();
+        assert_eq!(publisher.updates().len(), 2);
+        assert_eq!(publisher.updates().last().unwrap(), &fs.current_root());
+    }
 }
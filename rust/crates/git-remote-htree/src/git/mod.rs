@@ -4,6 +4,7 @@
 
 pub mod error;
 pub mod object;
+pub mod pack;
 pub mod refs;
 pub mod storage;
 
@@ -71,6 +71,10 @@ impl ObjectId {
         hex::encode(self.0)
     }
 
+    pub fn as_bytes(&self) -> &[u8; 20] {
+        &self.0
+    }
+
     /// Compute object ID from raw object data (type + content)
     pub fn hash_object(obj_type: ObjectType, content: &[u8]) -> Self {
         let header = format!("{} {}\0", obj_type.as_str(), content.len());
@@ -0,0 +1,667 @@
+//! Git packfile (v2) and pack index (v2) encoding
+//!
+//! Used by the pack object layout: each push writes one self-contained pack
+//! with its `.idx` under `.git/objects/pack/`, so git can read fetched packs
+//! directly. Similar objects of the same type are stored as `OFS_DELTA`
+//! entries against an earlier object in the pack.
+
+use flate2::bufread::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::{Compression, Crc};
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+use super::object::{GitObject, ObjectId, ObjectType};
+use super::{Error, Result};
+
+const PACK_SIGNATURE: &[u8; 4] = b"PACK";
+const IDX_SIGNATURE: &[u8; 4] = b"\xfftOc";
+const VERSION: u32 = 2;
+
+const OBJ_COMMIT: u8 = 1;
+const OBJ_TREE: u8 = 2;
+const OBJ_BLOB: u8 = 3;
+const OBJ_TAG: u8 = 4;
+const OBJ_OFS_DELTA: u8 = 6;
+const OBJ_REF_DELTA: u8 = 7;
+
+/// Number of earlier objects of the same type tried as delta bases
+const DELTA_WINDOW: usize = 10;
+/// Longest delta chain allowed
+const MAX_DELTA_DEPTH: usize = 50;
+/// Objects larger than this are stored without deltas
+const MAX_DELTA_SOURCE: usize = 16 * 1024 * 1024;
+/// Block size used to find matches between base and target
+const DELTA_BLOCK: usize = 16;
+/// Largest copy a single delta instruction may encode in pack v2
+const MAX_COPY: usize = 0x10000;
+
+/// An encoded pack with its index
+pub struct Pack {
+    /// Contents of `pack-<checksum>.pack`
+    pub data: Vec<u8>,
+    /// Contents of `pack-<checksum>.idx`
+    pub index: Vec<u8>,
+    /// SHA-1 trailer of the pack, which also names it
+    pub checksum: [u8; 20],
+}
+
+impl Pack {
+    /// File name stem, `pack-<checksum>`
+    pub fn name(&self) -> String {
+        format!("pack-{}", hex::encode(self.checksum))
+    }
+}
+
+fn type_code(obj_type: ObjectType) -> u8 {
+    match obj_type {
+        ObjectType::Commit => OBJ_COMMIT,
+        ObjectType::Tree => OBJ_TREE,
+        ObjectType::Blob => OBJ_BLOB,
+        ObjectType::Tag => OBJ_TAG,
+    }
+}
+
+fn object_type(code: u8) -> Result<ObjectType> {
+    match code {
+        OBJ_COMMIT => Ok(ObjectType::Commit),
+        OBJ_TREE => Ok(ObjectType::Tree),
+        OBJ_BLOB => Ok(ObjectType::Blob),
+        OBJ_TAG => Ok(ObjectType::Tag),
+        other => Err(Error::InvalidObjectType(format!("pack type {}", other))),
+    }
+}
+
+fn invalid(msg: &str) -> Error {
+    Error::InvalidObjectFormat(format!("pack: {}", msg))
+}
+
+fn compress(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+/// Inflate a zlib stream at the start of `data`, returning (bytes, consumed)
+fn decompress(data: &[u8], expected_len: usize) -> Result<(Vec<u8>, usize)> {
+    let mut decoder = ZlibDecoder::new(data);
+    let mut out = Vec::with_capacity(expected_len);
+    decoder.read_to_end(&mut out)?;
+    if out.len() != expected_len {
+        return Err(invalid("inflated size mismatch"));
+    }
+    Ok((out, decoder.total_in() as usize))
+}
+
+/// Object header: type and size as a little-endian base-128 varint
+fn encode_entry_header(code: u8, size: usize, out: &mut Vec<u8>) {
+    let mut size = size;
+    let mut byte = (code << 4) | (size & 0x0f) as u8;
+    size >>= 4;
+    while size > 0 {
+        out.push(byte | 0x80);
+        byte = (size & 0x7f) as u8;
+        size >>= 7;
+    }
+    out.push(byte);
+}
+
+/// Base offset of an OFS_DELTA, relative to the start of the delta entry
+fn encode_ofs(mut ofs: u64, out: &mut Vec<u8>) {
+    let mut buf = vec![(ofs & 0x7f) as u8];
+    ofs >>= 7;
+    while ofs > 0 {
+        ofs -= 1;
+        buf.push(0x80 | (ofs & 0x7f) as u8);
+        ofs >>= 7;
+    }
+    buf.reverse();
+    out.extend_from_slice(&buf);
+}
+
+fn encode_size(mut size: usize, out: &mut Vec<u8>) {
+    while size >= 0x80 {
+        out.push((size & 0x7f) as u8 | 0x80);
+        size >>= 7;
+    }
+    out.push(size as u8);
+}
+
+fn read_size(data: &[u8], pos: &mut usize) -> Result<usize> {
+    let mut size = 0usize;
+    let mut shift = 0;
+    loop {
+        let byte = *data
+            .get(*pos)
+            .ok_or_else(|| invalid("truncated delta size"))?;
+        *pos += 1;
+        size |= ((byte & 0x7f) as usize) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            return Ok(size);
+        }
+        if shift > 56 {
+            return Err(invalid("delta size overflow"));
+        }
+    }
+}
+
+fn push_copy(offset: usize, len: usize, out: &mut Vec<u8>) {
+    let mut op = 0x80u8;
+    let mut args = Vec::with_capacity(7);
+    for i in 0..4 {
+        let byte = (offset >> (8 * i)) as u8;
+        if byte != 0 {
+            op |= 1 << i;
+            args.push(byte);
+        }
+    }
+    // A size of 0x10000 is encoded as zero
+    let size = if len == MAX_COPY { 0 } else { len };
+    for i in 0..3 {
+        let byte = (size >> (8 * i)) as u8;
+        if byte != 0 {
+            op |= 0x10 << i;
+            args.push(byte);
+        }
+    }
+    out.push(op);
+    out.extend_from_slice(&args);
+}
+
+fn flush_insert(pending: &mut Vec<u8>, out: &mut Vec<u8>) {
+    for chunk in pending.chunks(0x7f) {
+        out.push(chunk.len() as u8);
+        out.extend_from_slice(chunk);
+    }
+    pending.clear();
+}
+
+/// Encode `target` as copy/insert instructions against `base`
+///
+/// Returns None if the delta would not be smaller than `limit`.
+pub fn create_delta(base: &[u8], target: &[u8], limit: usize) -> Option<Vec<u8>> {
+    let mut blocks: HashMap<&[u8], Vec<usize>> = HashMap::new();
+    for offset in (0..base.len().saturating_sub(DELTA_BLOCK - 1)).step_by(DELTA_BLOCK) {
+        let candidates = blocks
+            .entry(&base[offset..offset + DELTA_BLOCK])
+            .or_default();
+        if candidates.len() < 8 {
+            candidates.push(offset);
+        }
+    }
+
+    let mut out = Vec::new();
+    encode_size(base.len(), &mut out);
+    encode_size(target.len(), &mut out);
+
+    let mut pending = Vec::new();
+    let mut pos = 0;
+    while pos < target.len() {
+        let mut best = (0usize, 0usize);
+        if pos + DELTA_BLOCK <= target.len() {
+            if let Some(candidates) = blocks.get(&target[pos..pos + DELTA_BLOCK]) {
+                for &start in candidates {
+                    let len = base[start..]
+                        .iter()
+                        .zip(&target[pos..])
+                        .take_while(|(a, b)| a == b)
+                        .count();
+                    if len > best.1 {
+                        best = (start, len);
+                    }
+                }
+            }
+        }
+
+        if best.1 >= DELTA_BLOCK {
+            flush_insert(&mut pending, &mut out);
+            let (mut start, mut len) = best;
+            pos += len;
+            while len > 0 {
+                let piece = len.min(MAX_COPY);
+                push_copy(start, piece, &mut out);
+                start += piece;
+                len -= piece;
+            }
+        } else {
+            pending.push(target[pos]);
+            pos += 1;
+        }
+
+        if out.len() + pending.len() >= limit {
+            return None;
+        }
+    }
+    flush_insert(&mut pending, &mut out);
+
+    if out.len() < limit {
+        Some(out)
+    } else {
+        None
+    }
+}
+
+/// Apply a delta produced by git (or `create_delta`) to `base`
+pub fn apply_delta(base: &[u8], delta: &[u8]) -> Result<Vec<u8>> {
+    let mut pos = 0;
+    let base_len = read_size(delta, &mut pos)?;
+    if base_len != base.len() {
+        return Err(invalid("delta base size mismatch"));
+    }
+    let result_len = read_size(delta, &mut pos)?;
+    let mut out = Vec::with_capacity(result_len);
+
+    while pos < delta.len() {
+        let op = delta[pos];
+        pos += 1;
+        if op & 0x80 != 0 {
+            let mut offset = 0usize;
+            let mut size = 0usize;
+            for i in 0..4 {
+                if op & (1 << i) != 0 {
+                    let byte = *delta.get(pos).ok_or_else(|| invalid("truncated copy"))?;
+                    offset |= (byte as usize) << (8 * i);
+                    pos += 1;
+                }
+            }
+            for i in 0..3 {
+                if op & (0x10 << i) != 0 {
+                    let byte = *delta.get(pos).ok_or_else(|| invalid("truncated copy"))?;
+                    size |= (byte as usize) << (8 * i);
+                    pos += 1;
+                }
+            }
+            if size == 0 {
+                size = MAX_COPY;
+            }
+            let end = offset
+                .checked_add(size)
+                .filter(|end| *end <= base.len())
+                .ok_or_else(|| invalid("copy out of range"))?;
+            out.extend_from_slice(&base[offset..end]);
+        } else if op != 0 {
+            let end = pos + op as usize;
+            let data = delta
+                .get(pos..end)
+                .ok_or_else(|| invalid("truncated insert"))?;
+            out.extend_from_slice(data);
+            pos = end;
+        } else {
+            return Err(invalid("reserved delta opcode"));
+        }
+    }
+
+    if out.len() != result_len {
+        return Err(invalid("delta result size mismatch"));
+    }
+    Ok(out)
+}
+
+/// Encode objects into a pack and its index
+///
+/// Objects are ordered by type and decreasing size so that each one can be
+/// delta-compressed against recently written objects of the same type. The
+/// output only depends on the set of objects, so identical pushes produce
+/// identical packs.
+pub fn build_pack(objects: &[GitObject]) -> Result<Pack> {
+    let mut order: Vec<(ObjectId, &GitObject)> = objects.iter().map(|o| (o.id(), o)).collect();
+    order.sort_by(|(a_id, a), (b_id, b)| {
+        type_code(a.obj_type)
+            .cmp(&type_code(b.obj_type))
+            .then(b.content.len().cmp(&a.content.len()))
+            .then(a_id.to_hex().cmp(&b_id.to_hex()))
+    });
+    order.dedup_by(|a, b| a.0 == b.0);
+
+    let mut data = Vec::new();
+    data.extend_from_slice(PACK_SIGNATURE);
+    data.extend_from_slice(&VERSION.to_be_bytes());
+    data.extend_from_slice(&(order.len() as u32).to_be_bytes());
+
+    // (oid, offset, crc32) for the index
+    let mut entries: Vec<(ObjectId, u64, u32)> = Vec::with_capacity(order.len());
+    // Recent objects per type: (index into order, entry offset, delta depth)
+    let mut window: HashMap<u8, Vec<(usize, u64, usize)>> = HashMap::new();
+
+    for (i, (oid, obj)) in order.iter().enumerate() {
+        let code = type_code(obj.obj_type);
+        let offset = data.len() as u64;
+        let recent = window.entry(code).or_default();
+
+        let mut best: Option<(u64, usize, Vec<u8>)> = None;
+        if obj.content.len() <= MAX_DELTA_SOURCE {
+            for &(base_index, base_offset, depth) in recent.iter().rev() {
+                if depth >= MAX_DELTA_DEPTH {
+                    continue;
+                }
+                let limit = best
+                    .as_ref()
+                    .map(|(_, _, delta)| delta.len())
+                    .unwrap_or(obj.content.len() / 2);
+                let base = &order[base_index].1.content;
+                if let Some(delta) = create_delta(base, &obj.content, limit) {
+                    best = Some((base_offset, depth + 1, delta));
+                }
+            }
+        }
+
+        let mut entry = Vec::new();
+        let depth = match best {
+            Some((base_offset, depth, delta)) => {
+                encode_entry_header(OBJ_OFS_DELTA, delta.len(), &mut entry);
+                encode_ofs(offset - base_offset, &mut entry);
+                entry.extend_from_slice(&compress(&delta)?);
+                depth
+            }
+            None => {
+                encode_entry_header(code, obj.content.len(), &mut entry);
+                entry.extend_from_slice(&compress(&obj.content)?);
+                0
+            }
+        };
+
+        let mut crc = Crc::new();
+        crc.update(&entry);
+        entries.push((*oid, offset, crc.sum()));
+        data.extend_from_slice(&entry);
+
+        if obj.content.len() <= MAX_DELTA_SOURCE {
+            recent.push((i, offset, depth));
+            if recent.len() > DELTA_WINDOW {
+                recent.remove(0);
+            }
+        }
+    }
+
+    let checksum: [u8; 20] = Sha1::digest(&data).into();
+    data.extend_from_slice(&checksum);
+
+    let index = build_index(&mut entries, &checksum);
+    Ok(Pack {
+        data,
+        index,
+        checksum,
+    })
+}
+
+fn build_index(entries: &mut [(ObjectId, u64, u32)], pack_checksum: &[u8; 20]) -> Vec<u8> {
+    entries.sort_by(|a, b| a.0.as_bytes().cmp(b.0.as_bytes()));
+
+    let mut index = Vec::new();
+    index.extend_from_slice(IDX_SIGNATURE);
+    index.extend_from_slice(&VERSION.to_be_bytes());
+
+    let mut fanout = [0u32; 256];
+    for (oid, _, _) in entries.iter() {
+        fanout[oid.as_bytes()[0] as usize] += 1;
+    }
+    let mut total = 0u32;
+    for count in fanout.iter_mut() {
+        total += *count;
+        *count = total;
+    }
+    for count in fanout {
+        index.extend_from_slice(&count.to_be_bytes());
+    }
+
+    for (oid, _, _) in entries.iter() {
+        index.extend_from_slice(oid.as_bytes());
+    }
+    for (_, _, crc) in entries.iter() {
+        index.extend_from_slice(&crc.to_be_bytes());
+    }
+
+    let mut large_offsets = Vec::new();
+    for (_, offset, _) in entries.iter() {
+        if *offset < 0x8000_0000 {
+            index.extend_from_slice(&(*offset as u32).to_be_bytes());
+        } else {
+            let slot = (large_offsets.len() / 8) as u32;
+            index.extend_from_slice(&(0x8000_0000 | slot).to_be_bytes());
+            large_offsets.extend_from_slice(&offset.to_be_bytes());
+        }
+    }
+    index.extend_from_slice(&large_offsets);
+
+    index.extend_from_slice(pack_checksum);
+    let idx_checksum: [u8; 20] = Sha1::digest(&index).into();
+    index.extend_from_slice(&idx_checksum);
+    index
+}
+
+/// Check the SHA-1 trailer of a pack and return it
+pub fn verify_pack(data: &[u8]) -> Result<[u8; 20]> {
+    if data.len() < 32 || &data[..4] != PACK_SIGNATURE {
+        return Err(invalid("bad signature"));
+    }
+    let (body, trailer) = data.split_at(data.len() - 20);
+    let checksum: [u8; 20] = Sha1::digest(body).into();
+    if checksum[..] != trailer[..] {
+        return Err(invalid("checksum mismatch"));
+    }
+    Ok(checksum)
+}
+
+/// Object IDs listed in a v2 pack index
+pub fn read_index_oids(index: &[u8]) -> Result<Vec<ObjectId>> {
+    if index.len() < 8 + 256 * 4 || &index[..4] != IDX_SIGNATURE {
+        return Err(invalid("bad index signature"));
+    }
+    if index[4..8] != VERSION.to_be_bytes() {
+        return Err(invalid("unsupported index version"));
+    }
+
+    let fanout_end = 8 + 256 * 4;
+    let count = u32::from_be_bytes([
+        index[fanout_end - 4],
+        index[fanout_end - 3],
+        index[fanout_end - 2],
+        index[fanout_end - 1],
+    ]) as usize;
+    let oids_end = fanout_end + count * 20;
+    let oids = index
+        .get(fanout_end..oids_end)
+        .ok_or_else(|| invalid("truncated index"))?;
+
+    oids.chunks(20)
+        .map(|bytes| ObjectId::from_bytes(bytes).ok_or_else(|| invalid("bad object id")))
+        .collect()
+}
+
+/// Decode every object in a pack, resolving deltas within the pack
+pub fn read_pack(data: &[u8]) -> Result<Vec<GitObject>> {
+    verify_pack(data)?;
+    let count = u32::from_be_bytes([data[8], data[9], data[10], data[11]]) as usize;
+    let body_end = data.len() - 20;
+
+    let mut objects: Vec<GitObject> = Vec::with_capacity(count);
+    let mut by_offset: HashMap<u64, usize> = HashMap::new();
+    let mut by_id: HashMap<ObjectId, usize> = HashMap::new();
+    let mut pos = 12;
+
+    for _ in 0..count {
+        let entry_offset = pos as u64;
+        let mut byte = *data.get(pos).ok_or_else(|| invalid("truncated entry"))?;
+        pos += 1;
+        let code = (byte >> 4) & 0x07;
+        let mut size = (byte & 0x0f) as usize;
+        let mut shift = 4;
+        while byte & 0x80 != 0 {
+            byte = *data.get(pos).ok_or_else(|| invalid("truncated entry"))?;
+            pos += 1;
+            size |= ((byte & 0x7f) as usize) << shift;
+            shift += 7;
+        }
+
+        let base = match code {
+            OBJ_OFS_DELTA => {
+                let mut byte = *data.get(pos).ok_or_else(|| invalid("truncated offset"))?;
+                pos += 1;
+                let mut ofs = (byte & 0x7f) as u64;
+                while byte & 0x80 != 0 {
+                    byte = *data.get(pos).ok_or_else(|| invalid("truncated offset"))?;
+                    pos += 1;
+                    ofs = ((ofs + 1) << 7) | (byte & 0x7f) as u64;
+                }
+                let base_offset = entry_offset
+                    .checked_sub(ofs)
+                    .ok_or_else(|| invalid("delta base before pack start"))?;
+                Some(
+                    *by_offset
+                        .get(&base_offset)
+                        .ok_or_else(|| invalid("missing delta base"))?,
+                )
+            }
+            OBJ_REF_DELTA => {
+                let oid = data
+                    .get(pos..pos + 20)
+                    .and_then(ObjectId::from_bytes)
+                    .ok_or_else(|| invalid("truncated base id"))?;
+                pos += 20;
+                Some(
+                    *by_id
+                        .get(&oid)
+                        .ok_or_else(|| Error::ObjectNotFound(oid.to_hex()))?,
+                )
+            }
+            _ => None,
+        };
+
+        let (inflated, consumed) = decompress(&data[pos..body_end], size)?;
+        pos += consumed;
+
+        let object = match base {
+            Some(base_index) => {
+                let base = &objects[base_index];
+                GitObject::new(base.obj_type, apply_delta(&base.content, &inflated)?)
+            }
+            None => GitObject::new(object_type(code)?, inflated),
+        };
+
+        by_offset.insert(entry_offset, objects.len());
+        by_id.insert(object.id(), objects.len());
+        objects.push(object);
+    }
+
+    Ok(objects)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    fn sample_objects() -> Vec<GitObject> {
+        let base: Vec<u8> = (0..20_000u32)
+            .flat_map(|i| format!("line {}\n", i).into_bytes())
+            .collect();
+        let mut edited = base.clone();
+        edited.splice(5000..5000, b"inserted text\n".iter().copied());
+        let mut appended = edited.clone();
+        appended.extend_from_slice(b"tail\n");
+
+        vec![
+            GitObject::new(ObjectType::Blob, base),
+            GitObject::new(ObjectType::Blob, edited),
+            GitObject::new(ObjectType::Blob, appended),
+            GitObject::new(ObjectType::Blob, b"small".to_vec()),
+            GitObject::new(ObjectType::Commit, b"tree 0000\n\nmessage\n".to_vec()),
+        ]
+    }
+
+    #[test]
+    fn test_delta_roundtrip() {
+        let base = b"the quick brown fox jumps over the lazy dog, again and again".repeat(20);
+        let mut target = base.clone();
+        target.splice(100..110, b"CHANGED".iter().copied());
+        target.extend_from_slice(b"more");
+
+        let delta = create_delta(&base, &target, target.len()).unwrap();
+        assert!(delta.len() < target.len() / 4);
+        assert_eq!(apply_delta(&base, &delta).unwrap(), target);
+    }
+
+    #[test]
+    fn test_delta_rejected_when_too_large() {
+        assert!(create_delta(b"aaaaaaaaaaaaaaaaaaaa", b"completely different", 10).is_none());
+    }
+
+    #[test]
+    fn test_pack_roundtrip_uses_deltas() {
+        let objects = sample_objects();
+        let pack = build_pack(&objects).unwrap();
+        let raw_size: usize = objects.iter().map(|o| o.content.len()).sum();
+        assert!(pack.data.len() < raw_size / 4);
+        assert_eq!(verify_pack(&pack.data).unwrap(), pack.checksum);
+
+        let mut read: Vec<String> = read_pack(&pack.data)
+            .unwrap()
+            .iter()
+            .map(|o| o.id().to_hex())
+            .collect();
+        let mut expected: Vec<String> = objects.iter().map(|o| o.id().to_hex()).collect();
+        read.sort();
+        expected.sort();
+        assert_eq!(read, expected);
+
+        let mut indexed: Vec<String> = read_index_oids(&pack.index)
+            .unwrap()
+            .iter()
+            .map(|oid| oid.to_hex())
+            .collect();
+        indexed.sort();
+        assert_eq!(indexed, expected);
+    }
+
+    #[test]
+    fn test_pack_is_deterministic() {
+        let objects = sample_objects();
+        let mut reversed = objects.clone();
+        reversed.reverse();
+        assert_eq!(
+            build_pack(&objects).unwrap().checksum,
+            build_pack(&reversed).unwrap().checksum
+        );
+    }
+
+    #[test]
+    fn test_git_reads_pack() {
+        let git_dir = tempfile::TempDir::new().unwrap();
+        let init = Command::new("git")
+            .args(["init", "--bare", "-q"])
+            .arg(git_dir.path())
+            .status();
+        if !matches!(init, Ok(status) if status.success()) {
+            eprintln!("git not available, skipping");
+            return;
+        }
+
+        let objects = sample_objects();
+        let pack = build_pack(&objects).unwrap();
+        let pack_dir = git_dir.path().join("objects/pack");
+        std::fs::write(pack_dir.join(format!("{}.pack", pack.name())), &pack.data).unwrap();
+        std::fs::write(pack_dir.join(format!("{}.idx", pack.name())), &pack.index).unwrap();
+
+        let verify = Command::new("git")
+            .arg("--git-dir")
+            .arg(git_dir.path())
+            .arg("verify-pack")
+            .arg(pack_dir.join(format!("{}.idx", pack.name())))
+            .status()
+            .unwrap();
+        assert!(verify.success());
+
+        for obj in &objects {
+            let output = Command::new("git")
+                .arg("--git-dir")
+                .arg(git_dir.path())
+                .args(["cat-file", obj.obj_type.as_str(), &obj.id().to_hex()])
+                .output()
+                .unwrap();
+            assert!(output.status.success());
+            assert_eq!(output.stdout, obj.content);
+        }
+    }
+}
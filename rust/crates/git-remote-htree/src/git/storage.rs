@@ -6,13 +6,19 @@
 //!       HEAD -> "ref: refs/heads/main"
 //!       refs/heads/main -> <commit-sha1>
 //!       objects/XX/YYYY... -> zlib-compressed loose object (standard git layout)
+//!       objects/pack/pack-<sha>.{pack,idx} -> packfiles (pack layout)
+//!
+//! With the pack layout each build reuses the packs already in the tree and
+//! writes only the objects missing from them into one new pack, so unchanged
+//! packs keep their hashes across pushes. Loose objects are not kept: the
+//! first packed build moves them into its pack.
 //!
 //! The root hash (SHA-256) is the content-addressed identifier for the entire repo state.
 
 use flate2::read::ZlibDecoder;
 use flate2::write::ZlibEncoder;
 use flate2::Compression;
-use hashtree_config::{Config, StorageBackend};
+use hashtree_config::{Config, GitObjectLayout, StorageBackend};
 use hashtree_core::store::{Store, StoreError};
 use hashtree_core::types::Hash;
 use hashtree_core::{Cid, DirEntry, HashTree, HashTreeConfig, LinkType};
@@ -20,7 +26,7 @@ use hashtree_fs::FsBlobStore;
 #[cfg(feature = "lmdb")]
 use hashtree_lmdb::LmdbBlobStore;
 use sha1::{Digest, Sha1};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::{Read, Write};
 use std::path::Path;
 use std::sync::Arc;
@@ -28,6 +34,7 @@ use tokio::runtime::{Handle, Runtime};
 use tracing::{debug, info, warn};
 
 use super::object::{parse_tree, GitObject, ObjectId, ObjectType};
+use super::pack::{build_pack, read_index_oids};
 use super::refs::{validate_ref_name, Ref};
 use super::{Error, Result};
 
@@ -126,6 +133,66 @@ impl Store for LocalStore {
             LocalStore::Lmdb(store) => store.delete(hash).await,
         }
     }
+
+    async fn put_many(
+        &self,
+        items: Vec<(Hash, Vec<u8>)>,
+    ) -> std::result::Result<Vec<bool>, StoreError> {
+        match self {
+            LocalStore::Fs(store) => store.put_many(items).await,
+            #[cfg(feature = "lmdb")]
+            LocalStore::Lmdb(store) => store.put_many(items).await,
+        }
+    }
+
+    async fn get_many(
+        &self,
+        hashes: &[Hash],
+    ) -> std::result::Result<Vec<Option<Vec<u8>>>, StoreError> {
+        match self {
+            LocalStore::Fs(store) => store.get_many(hashes).await,
+            #[cfg(feature = "lmdb")]
+            LocalStore::Lmdb(store) => store.get_many(hashes).await,
+        }
+    }
+
+    async fn has_many(&self, hashes: &[Hash]) -> std::result::Result<Vec<bool>, StoreError> {
+        match self {
+            LocalStore::Fs(store) => store.has_many(hashes).await,
+            #[cfg(feature = "lmdb")]
+            LocalStore::Lmdb(store) => store.has_many(hashes).await,
+        }
+    }
+
+    async fn list(&self) -> std::result::Result<Vec<Hash>, StoreError> {
+        LocalStore::list(self)
+    }
+}
+
+/// A packfile stored in the tree as `objects/pack/<name>.pack` + `.idx`
+#[derive(Debug, Clone)]
+pub struct PackRef {
+    /// File name stem, `pack-<sha>`
+    pub name: String,
+    pub pack: Cid,
+    pub pack_size: u64,
+    pub index: Cid,
+    pub index_size: u64,
+}
+
+/// A pack reused from the remote tree, with the objects it contains
+#[derive(Clone)]
+struct StoredPack {
+    file: PackRef,
+    oids: HashSet<String>,
+}
+
+/// Decompress a zlib loose object into a GitObject
+fn decode_loose_object(compressed: &[u8]) -> Result<GitObject> {
+    let mut decoder = ZlibDecoder::new(compressed);
+    let mut data = Vec::new();
+    decoder.read_to_end(&mut data)?;
+    GitObject::from_loose_format(&data)
 }
 
 /// Git storage backed by HashTree with configurable persistence
@@ -138,6 +205,10 @@ pub struct GitStorage {
     refs: std::sync::RwLock<HashMap<String, String>>,
     /// Cached root CID (hash + encryption key)
     root_cid: std::sync::RwLock<Option<Cid>>,
+    /// Layout of .git/objects in built trees
+    layout: GitObjectLayout,
+    /// Packs carried over from the remote tree (pack layout)
+    packs: std::sync::RwLock<Vec<StoredPack>>,
 }
 
 impl GitStorage {
@@ -168,9 +239,17 @@ impl GitStorage {
             objects: std::sync::RwLock::new(HashMap::new()),
             refs: std::sync::RwLock::new(HashMap::new()),
             root_cid: std::sync::RwLock::new(None),
+            layout: GitObjectLayout::default(),
+            packs: std::sync::RwLock::new(Vec::new()),
         })
     }
 
+    /// Set the layout used for .git/objects when building the tree
+    pub fn with_layout(mut self, layout: GitObjectLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
     /// Write an object, returning its ID
     fn write_object(&self, obj: &GitObject) -> Result<ObjectId> {
         let oid = obj.id();
@@ -308,6 +387,31 @@ impl GitStorage {
         Ok(())
     }
 
+    /// Import a packfile already stored in the remote tree
+    /// Its objects are not repacked; the pack is linked as-is on the next build
+    pub fn import_pack(&self, pack: PackRef, index_data: &[u8]) -> Result<()> {
+        let oids = read_index_oids(index_data)?
+            .iter()
+            .map(|oid| oid.to_hex())
+            .collect();
+
+        let mut packs = self
+            .packs
+            .write()
+            .map_err(|e| Error::StorageError(format!("lock: {}", e)))?;
+        if packs.iter().any(|p| p.file.name == pack.name) {
+            return Ok(());
+        }
+        packs.push(StoredPack { file: pack, oids });
+
+        // Invalidate cached root
+        if let Ok(mut root) = self.root_cid.write() {
+            *root = None;
+        }
+
+        Ok(())
+    }
+
     /// Import a ref directly (used when loading existing refs from remote)
     pub fn import_ref(&self, name: &str, value: &str) -> Result<()> {
         let mut refs = self
@@ -344,6 +448,16 @@ impl GitStorage {
         Ok(objects.len())
     }
 
+    /// Packs that the next build links under objects/pack
+    #[cfg(test)]
+    pub fn pack_refs(&self) -> Result<Vec<PackRef>> {
+        let packs = self
+            .packs
+            .read()
+            .map_err(|e| Error::StorageError(format!("lock: {}", e)))?;
+        Ok(packs.iter().map(|p| p.file.clone()).collect())
+    }
+
     /// Get the cached root CID (returns None if tree hasn't been built)
     #[allow(dead_code)]
     pub fn get_root_cid(&self) -> Result<Option<Cid>> {
@@ -474,7 +588,10 @@ impl GitStorage {
 
         let root_cid = self.runtime.block_on(async {
             // Build objects directory
-            let objects_cid = self.build_objects_dir(&objects).await?;
+            let objects_cid = match self.layout {
+                GitObjectLayout::Loose => self.build_objects_dir(&objects).await?,
+                GitObjectLayout::Pack => self.build_packed_objects_dir(&objects).await?,
+            };
 
             // Build refs directory
             let refs_cid = self.build_refs_dir(&refs).await?;
@@ -633,7 +750,17 @@ impl GitStorage {
 
     /// Build the objects directory using HashTree
     async fn build_objects_dir(&self, objects: &HashMap<String, Vec<u8>>) -> Result<Cid> {
-        if objects.is_empty() {
+        let packs = self
+            .packs
+            .read()
+            .map_err(|e| Error::StorageError(format!("lock: {}", e)))?
+            .clone();
+        let packed: HashSet<&str> = packs
+            .iter()
+            .flat_map(|p| p.oids.iter().map(String::as_str))
+            .collect();
+
+        if objects.is_empty() && packs.is_empty() {
             // Return empty directory Cid
             let empty_cid = self
                 .tree
@@ -647,6 +774,10 @@ impl GitStorage {
         // Git expects objects/XX/YYYYYY... where XX is first 2 hex chars
         let mut buckets: HashMap<String, Vec<(String, Vec<u8>)>> = HashMap::new();
         for (oid, data) in objects {
+            // Objects already in a pack from the remote stay there
+            if packed.contains(oid.as_str()) {
+                continue;
+            }
             let prefix = &oid[..2];
             let suffix = &oid[2..];
             buckets
@@ -679,6 +810,11 @@ impl GitStorage {
             top_entries.push(DirEntry::from_cid(prefix, &sub_cid).with_link_type(LinkType::Dir));
         }
 
+        if !packs.is_empty() {
+            let pack_cid = self.build_pack_dir(&packs).await?;
+            top_entries.push(DirEntry::from_cid("pack", &pack_cid).with_link_type(LinkType::Dir));
+        }
+
         // Sort for deterministic ordering
         top_entries.sort_by(|a, b| a.name.cmp(&b.name));
 
@@ -697,6 +833,108 @@ impl GitStorage {
         Ok(cid)
     }
 
+    /// Build the objects directory as packfiles
+    /// Imported packs are linked unchanged; objects missing from them, including
+    /// loose objects imported from the remote, go into a new pack
+    async fn build_packed_objects_dir(&self, objects: &HashMap<String, Vec<u8>>) -> Result<Cid> {
+        let mut packs = self
+            .packs
+            .read()
+            .map_err(|e| Error::StorageError(format!("lock: {}", e)))?
+            .clone();
+
+        let mut new_oids = HashSet::new();
+        let mut new_objects = Vec::new();
+        {
+            let packed: HashSet<&str> = packs
+                .iter()
+                .flat_map(|p| p.oids.iter().map(String::as_str))
+                .collect();
+            for (oid, compressed) in objects {
+                if !packed.contains(oid.as_str()) {
+                    new_objects.push(decode_loose_object(compressed)?);
+                    new_oids.insert(oid.clone());
+                }
+            }
+        }
+
+        if !new_objects.is_empty() {
+            let pack = build_pack(&new_objects)?;
+            let name = pack.name();
+            let (pack_cid, pack_size) = self
+                .tree
+                .put(&pack.data)
+                .await
+                .map_err(|e| Error::StorageError(format!("put {}.pack: {}", name, e)))?;
+            let (index_cid, index_size) = self
+                .tree
+                .put(&pack.index)
+                .await
+                .map_err(|e| Error::StorageError(format!("put {}.idx: {}", name, e)))?;
+            info!(
+                "Wrote {} with {} objects ({} bytes)",
+                name,
+                new_objects.len(),
+                pack.data.len()
+            );
+
+            // Later builds in this session reuse the pack like an imported one
+            let stored = StoredPack {
+                file: PackRef {
+                    name,
+                    pack: pack_cid,
+                    pack_size,
+                    index: index_cid,
+                    index_size,
+                },
+                oids: new_oids,
+            };
+            self.packs
+                .write()
+                .map_err(|e| Error::StorageError(format!("lock: {}", e)))?
+                .push(stored.clone());
+            packs.push(stored);
+        }
+
+        let pack_cid = self.build_pack_dir(&packs).await?;
+        let cid = self
+            .tree
+            .put_directory(vec![
+                DirEntry::from_cid("pack", &pack_cid).with_link_type(LinkType::Dir)
+            ])
+            .await
+            .map_err(|e| Error::StorageError(format!("put objects dir: {}", e)))?;
+
+        debug!(
+            "Built packed objects dir with {} packs: {}",
+            packs.len(),
+            hex::encode(cid.hash)
+        );
+        Ok(cid)
+    }
+
+    /// Build objects/pack with a .pack and .idx entry per pack
+    async fn build_pack_dir(&self, packs: &[StoredPack]) -> Result<Cid> {
+        let mut entries = Vec::new();
+        for stored in packs {
+            let file = &stored.file;
+            entries.push(
+                DirEntry::from_cid(format!("{}.pack", file.name), &file.pack)
+                    .with_size(file.pack_size),
+            );
+            entries.push(
+                DirEntry::from_cid(format!("{}.idx", file.name), &file.index)
+                    .with_size(file.index_size),
+            );
+        }
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+        self.tree
+            .put_directory(entries)
+            .await
+            .map_err(|e| Error::StorageError(format!("put objects/pack: {}", e)))
+    }
+
     /// Build the refs directory using HashTree
     async fn build_refs_dir(&self, refs: &HashMap<String, String>) -> Result<Cid> {
         // Group refs by category (heads, tags, etc.)
@@ -977,9 +1215,14 @@ impl GitStorage {
             .root_cid
             .write()
             .map_err(|e| Error::StorageError(format!("lock: {}", e)))?;
+        let mut packs = self
+            .packs
+            .write()
+            .map_err(|e| Error::StorageError(format!("lock: {}", e)))?;
 
         objects.clear();
         refs.clear();
+        packs.clear();
         *root = None;
         Ok(())
     }
@@ -996,6 +1239,78 @@ mod tests {
         (storage, temp_dir)
     }
 
+    /// Write a single-file commit and point refs/heads/main at it
+    fn write_commit(storage: &GitStorage, file: &str, content: &[u8]) -> ObjectId {
+        use crate::git::object::{serialize_tree, TreeEntry};
+
+        let blob = storage.write_raw_object(ObjectType::Blob, content).unwrap();
+        let tree = serialize_tree(&[TreeEntry::new(0o100644, file.to_string(), blob)]);
+        let tree = storage.write_raw_object(ObjectType::Tree, &tree).unwrap();
+        let commit = format!(
+            "tree {}\nauthor A <a@example.com> 0 +0000\ncommitter A <a@example.com> 0 +0000\n\n{}\n",
+            tree, file
+        );
+        let commit = storage
+            .write_raw_object(ObjectType::Commit, commit.as_bytes())
+            .unwrap();
+        storage
+            .write_ref("refs/heads/main", &Ref::Direct(commit))
+            .unwrap();
+        commit
+    }
+
+    fn pack_dir_names(storage: &GitStorage, root: &Cid) -> Vec<String> {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let tree = storage.hashtree();
+            let dir = tree
+                .resolve_path(root, ".git/objects/pack")
+                .await
+                .unwrap()
+                .unwrap();
+            tree.list_directory(&dir)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|e| e.name)
+                .collect()
+        })
+    }
+
+    #[test]
+    fn test_pack_layout_writes_incremental_packs() {
+        let temp_dir = TempDir::new().unwrap();
+        let first = GitStorage::open(temp_dir.path())
+            .unwrap()
+            .with_layout(GitObjectLayout::Pack);
+        write_commit(&first, "a.txt", b"first version\n");
+        let root = first.build_tree().unwrap();
+
+        let names = pack_dir_names(&first, &root);
+        assert_eq!(names.len(), 2);
+        assert!(names.iter().all(|n| n.starts_with("pack-")));
+        let existing = first.pack_refs().unwrap().remove(0);
+
+        // Next push: the existing pack is imported, only new objects are packed
+        let index_data = Runtime::new()
+            .unwrap()
+            .block_on(first.hashtree().get(&existing.index, None))
+            .unwrap()
+            .unwrap();
+        let second = GitStorage::open(temp_dir.path())
+            .unwrap()
+            .with_layout(GitObjectLayout::Pack);
+        second.import_pack(existing.clone(), &index_data).unwrap();
+        write_commit(&second, "a.txt", b"first version\n");
+        write_commit(&second, "b.txt", b"second file\n");
+        let root = second.build_tree().unwrap();
+
+        let packs = second.pack_refs().unwrap();
+        assert_eq!(packs.len(), 2);
+        assert_eq!(packs[0].pack, existing.pack);
+        assert_eq!(pack_dir_names(&second, &root).len(), 4);
+    }
+
     #[test]
     fn test_import_ref() {
         let (storage, _temp) = create_test_storage();
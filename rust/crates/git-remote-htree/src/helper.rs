@@ -5,7 +5,7 @@
 
 use crate::git::object::ObjectType;
 use crate::git::refs::Ref;
-use crate::git::storage::GitStorage;
+use crate::git::storage::{GitStorage, PackRef};
 use anyhow::{bail, Context, Result};
 use std::collections::{HashMap, HashSet};
 use std::io::Write;
@@ -21,19 +21,23 @@ use crate::nostr_client::{BlossomResult, NostrClient, PullRequestStateFilter, Re
 use hashtree_config::Config;
 
 // CachedStore: local store first, then Blossom fallback
+// Both sides are hash-checked; a corrupt local blob is dropped and refetched
 mod cached_store {
     use hashtree_blossom::BlossomStore;
-    use hashtree_core::{Hash, Store, StoreError};
+    use hashtree_core::{Hash, Store, StoreError, VerifyingStore};
     use std::sync::Arc;
 
     pub struct CachedStore {
-        local: Arc<dyn Store + Send + Sync>,
-        blossom: BlossomStore,
+        local: VerifyingStore<dyn Store + Send + Sync>,
+        blossom: VerifyingStore<BlossomStore>,
     }
 
     impl CachedStore {
         pub fn new(local: Arc<dyn Store + Send + Sync>, blossom: BlossomStore) -> Self {
-            Self { local, blossom }
+            Self {
+                local: VerifyingStore::new(local).with_delete_corrupted(true),
+                blossom: VerifyingStore::new(Arc::new(blossom)).with_delete_corrupted(true),
+            }
         }
     }
 
@@ -71,9 +75,68 @@ mod cached_store {
             // Delete from local only (don't delete from remote)
             self.local.delete(hash).await
         }
+
+        async fn put_many(&self, items: Vec<(Hash, Vec<u8>)>) -> Result<Vec<bool>, StoreError> {
+            self.local.put_many(items).await
+        }
+
+        async fn get_many(&self, hashes: &[Hash]) -> Result<Vec<Option<Vec<u8>>>, StoreError> {
+            // Try local first, in one batch
+            let mut results = match self.local.get_many(hashes).await {
+                Ok(results) => results,
+                Err(_) => vec![None; hashes.len()],
+            };
+
+            // Fetch the misses from Blossom and cache them locally
+            let misses: Vec<usize> = (0..hashes.len())
+                .filter(|&i| results[i].is_none())
+                .collect();
+            if misses.is_empty() {
+                return Ok(results);
+            }
+            let missing: Vec<Hash> = misses.iter().map(|&i| hashes[i]).collect();
+            let fetched = self.blossom.get_many(&missing).await?;
+
+            let mut cache = Vec::new();
+            for (i, data) in misses.into_iter().zip(fetched) {
+                if let Some(data) = data {
+                    cache.push((hashes[i], data.clone()));
+                    results[i] = Some(data);
+                }
+            }
+            let _ = self.local.put_many(cache).await;
+            Ok(results)
+        }
+
+        async fn has_many(&self, hashes: &[Hash]) -> Result<Vec<bool>, StoreError> {
+            let mut results = self.local.has_many(hashes).await?;
+
+            let misses: Vec<usize> = (0..hashes.len()).filter(|&i| !results[i]).collect();
+            if misses.is_empty() {
+                return Ok(results);
+            }
+            let missing: Vec<Hash> = misses.iter().map(|&i| hashes[i]).collect();
+            let found = self.blossom.has_many(&missing).await?;
+            for (i, found) in misses.into_iter().zip(found) {
+                results[i] = found;
+            }
+            Ok(results)
+        }
     }
 }
 
+/// Local git directory, honoring GIT_DIR
+fn git_dir() -> PathBuf {
+    PathBuf::from(std::env::var("GIT_DIR").unwrap_or_else(|_| ".git".to_string()))
+}
+
+/// Pack and index file links found for one pack name
+#[derive(Default)]
+struct PackFiles {
+    pack: Option<(hashtree_core::Cid, u64)>,
+    index: Option<(hashtree_core::Cid, u64)>,
+}
+
 /// Get the shared hashtree data directory
 fn get_hashtree_data_dir() -> PathBuf {
     hashtree_config::get_data_dir()
@@ -139,6 +202,22 @@ struct FetchSpec {
     name: String,
 }
 
+/// A packfile found in the remote tree's .git/objects/pack/
+struct RemotePack {
+    file: PackRef,
+    index_data: Vec<u8>,
+    /// Only downloaded when the local repo doesn't have the pack yet
+    pack_data: Option<Vec<u8>>,
+}
+
+/// Git objects loaded from a remote tree
+#[derive(Default)]
+struct RemoteObjects {
+    /// Loose objects as (oid, zlib-compressed loose object)
+    loose: Vec<(String, Vec<u8>)>,
+    packs: Vec<RemotePack>,
+}
+
 #[derive(Debug, PartialEq, Eq)]
 enum AncestorCheck {
     /// Remote tip is an ancestor of local tip: fast-forward allowed.
@@ -161,7 +240,7 @@ impl RemoteHelper {
         // Use shared hashtree storage at ~/.hashtree/data
         let data_dir = get_hashtree_data_dir();
         debug!(?data_dir, "RemoteHelper::new");
-        let storage = GitStorage::open(&data_dir)?;
+        let storage = GitStorage::open(&data_dir)?.with_layout(config.git.object_layout);
         let nostr = NostrClient::new(pubkey, signing_key, url_secret, is_private, &config)?;
 
         if is_private {
@@ -349,8 +428,21 @@ impl RemoteHelper {
 
         if let Some(ref root) = root_hash {
             // Fetch all git objects from the hashtree structure
-            let objects = self.fetch_all_git_objects(root)?;
-            info!("Loaded {} git objects from hashtree", objects.len());
+            let RemoteObjects {
+                loose: objects,
+                packs,
+            } = self.fetch_all_git_objects(root, true)?;
+            info!(
+                "Loaded {} loose git objects and {} packs from hashtree",
+                objects.len(),
+                packs.len()
+            );
+
+            for pack in &packs {
+                if let Some(ref data) = pack.pack_data {
+                    self.write_git_pack(&pack.file.name, data, &pack.index_data)?;
+                }
+            }
 
             // Batch check which objects git already has
             let existing =
@@ -391,7 +483,14 @@ impl RemoteHelper {
     }
 
     /// Fetch all git objects from hashtree's .git/objects/ directory
-    fn fetch_all_git_objects(&self, root_hash: &str) -> Result<Vec<(String, Vec<u8>)>> {
+    ///
+    /// Pack indexes are always downloaded; pack data only with `with_pack_data`
+    /// and when the local repo doesn't already have the pack.
+    fn fetch_all_git_objects(
+        &self,
+        root_hash: &str,
+        with_pack_data: bool,
+    ) -> Result<RemoteObjects> {
         // NostrClient now handles unmasking for link-visible repos (url_secret)
         // The cached key is already the real CHK key
         let encryption_key = self
@@ -412,7 +511,11 @@ impl RemoteHelper {
             .build()
             .context("Failed to create tokio runtime")?;
 
-        rt.block_on(self.fetch_git_objects_async(root_hash, encryption_key.as_ref()))
+        rt.block_on(self.fetch_git_objects_async(
+            root_hash,
+            encryption_key.as_ref(),
+            with_pack_data,
+        ))
     }
 
     /// Async implementation of git object fetching using HashTree helpers
@@ -420,7 +523,8 @@ impl RemoteHelper {
         &self,
         root_hash: &str,
         encryption_key: Option<&[u8; 32]>,
-    ) -> Result<Vec<(String, Vec<u8>)>> {
+        with_pack_data: bool,
+    ) -> Result<RemoteObjects> {
         use hashtree_blossom::BlossomStore;
         use hashtree_core::{Cid, HashTree, HashTreeConfig};
 
@@ -466,11 +570,11 @@ impl RemoteHelper {
             Ok(Some(cid)) => cid,
             Ok(None) => {
                 warn!("No .git/objects directory found");
-                return Ok(objects);
+                return Ok(RemoteObjects::default());
             }
             Err(e) => {
                 warn!("Failed to resolve .git/objects: {}", e);
-                return Ok(objects);
+                return Ok(RemoteObjects::default());
             }
         };
 
@@ -516,7 +620,7 @@ impl RemoteHelper {
                 let _ = progress_task.await;
                 eprintln!("\r  Loading objects tree... failed: {}", e);
                 warn!("Failed to walk objects directory: {}", e);
-                return Ok(objects);
+                return Ok(RemoteObjects::default());
             }
         };
         done.store(true, Ordering::Relaxed);
@@ -534,6 +638,7 @@ impl RemoteHelper {
 
         // Extract git objects from walk entries (files with 40 char hex names like "ab/cdef..." -> "abcdef...")
         let mut fetch_tasks: Vec<(String, Cid)> = Vec::new();
+        let mut pack_files: HashMap<String, PackFiles> = HashMap::new();
         for entry in walk_entries {
             // Skip directories
             if entry.link_type == LinkType::Dir {
@@ -542,7 +647,24 @@ impl RemoteHelper {
 
             // Parse path like "ab/cdef1234..." into oid "abcdef1234..."
             let parts: Vec<&str> = entry.path.split('/').collect();
-            if parts.len() == 2 && parts[0].len() == 2 && parts[1].len() == 38 {
+            if parts.len() == 2 && parts[0] == "pack" {
+                // Packed layout: pack/pack-<sha>.pack and pack/pack-<sha>.idx.
+                // Chunks of a large file share its path; the file node comes first.
+                let file = (
+                    Cid {
+                        hash: entry.hash,
+                        key: entry.key,
+                    },
+                    entry.size,
+                );
+                if let Some(name) = parts[1].strip_suffix(".pack") {
+                    let files = pack_files.entry(name.to_string()).or_default();
+                    files.pack.get_or_insert(file);
+                } else if let Some(name) = parts[1].strip_suffix(".idx") {
+                    let files = pack_files.entry(name.to_string()).or_default();
+                    files.index.get_or_insert(file);
+                }
+            } else if parts.len() == 2 && parts[0].len() == 2 && parts[1].len() == 38 {
                 if hex::decode(parts[0]).is_ok() && hex::decode(parts[1]).is_ok() {
                     let oid = format!("{}{}", parts[0], parts[1]);
                     let obj_cid = Cid {
@@ -674,7 +796,69 @@ impl RemoteHelper {
         }
 
         info!("Fetched {} git objects from hashtree", objects.len());
-        Ok(objects)
+
+        let packs = self
+            .fetch_git_packs(&tree, pack_files, with_pack_data)
+            .await?;
+
+        Ok(RemoteObjects {
+            loose: objects,
+            packs,
+        })
+    }
+
+    /// Download pack indexes, and pack data for packs missing locally
+    async fn fetch_git_packs<S: hashtree_core::Store>(
+        &self,
+        tree: &hashtree_core::HashTree<S>,
+        pack_files: HashMap<String, PackFiles>,
+        with_pack_data: bool,
+    ) -> Result<Vec<RemotePack>> {
+        let local_pack_dir = git_dir().join("objects").join("pack");
+        let mut packs = Vec::new();
+
+        for (name, files) in pack_files {
+            let (Some((pack_cid, pack_size)), Some((index_cid, index_size))) =
+                (files.pack, files.index)
+            else {
+                warn!("Skipping incomplete pack {} in remote tree", name);
+                continue;
+            };
+
+            let index_data = tree
+                .get(&index_cid, None)
+                .await
+                .with_context(|| format!("Failed to fetch {}.idx", name))?
+                .with_context(|| format!("Pack index {}.idx not found", name))?;
+
+            let have_locally = local_pack_dir.join(format!("{}.idx", name)).exists();
+            let pack_data = if with_pack_data && !have_locally {
+                eprintln!("  Loading {} ({} bytes)...", name, pack_size);
+                let data = tree
+                    .get(&pack_cid, None)
+                    .await
+                    .with_context(|| format!("Failed to fetch {}.pack", name))?
+                    .with_context(|| format!("Pack {}.pack not found", name))?;
+                Some(data)
+            } else {
+                None
+            };
+
+            packs.push(RemotePack {
+                file: PackRef {
+                    name,
+                    pack: pack_cid,
+                    pack_size,
+                    index: index_cid,
+                    index_size,
+                },
+                index_data,
+                pack_data,
+            });
+        }
+
+        info!("Fetched {} git packs from hashtree", packs.len());
+        Ok(packs)
     }
 
     /// Batch check which objects git already has (returns set of existing oids)
@@ -726,11 +910,8 @@ impl RemoteHelper {
             bail!("Invalid object id: {}", oid);
         }
 
-        let git_dir = std::env::var("GIT_DIR").unwrap_or_else(|_| ".git".to_string());
         let (dir_name, file_name) = oid.split_at(2);
-        let obj_dir = std::path::Path::new(&git_dir)
-            .join("objects")
-            .join(dir_name);
+        let obj_dir = git_dir().join("objects").join(dir_name);
         std::fs::create_dir_all(&obj_dir).context("Failed to create object directory")?;
 
         let obj_path = obj_dir.join(file_name);
@@ -743,6 +924,29 @@ impl RemoteHelper {
         Ok(())
     }
 
+    /// Write a packfile and its index into the local git object store.
+    /// The index is written last so git never sees a pack without its data.
+    fn write_git_pack(&self, name: &str, pack_data: &[u8], index_data: &[u8]) -> Result<()> {
+        let checksum = crate::git::pack::verify_pack(pack_data)?;
+        if name != format!("pack-{}", hex::encode(checksum)) {
+            bail!("Pack {} does not match its checksum", name);
+        }
+
+        let pack_dir = git_dir().join("objects").join("pack");
+        std::fs::create_dir_all(&pack_dir).context("Failed to create pack directory")?;
+
+        let index_path = pack_dir.join(format!("{}.idx", name));
+        if index_path.exists() {
+            return Ok(());
+        }
+
+        std::fs::write(pack_dir.join(format!("{}.pack", name)), pack_data)
+            .context("Failed to write git pack")?;
+        std::fs::write(&index_path, index_data).context("Failed to write git pack index")?;
+        debug!("Wrote git pack {}", name);
+        Ok(())
+    }
+
     /// Queue a push operation
     fn queue_push(&mut self, arg: &str) -> Result<()> {
         // Format: [+]<src>:<dst>
@@ -940,14 +1144,23 @@ impl RemoteHelper {
 
         // Fetch all git objects from remote hashtree
         if let Some(root) = root_hash {
-            let objects = self.fetch_all_git_objects(&root)?;
-            self.detail(&format!("  Importing {} existing objects", objects.len()));
-
-            for (oid, content) in objects {
+            let objects = self.fetch_all_git_objects(&root, false)?;
+            self.detail(&format!(
+                "  Importing {} existing objects and {} packs",
+                objects.loose.len(),
+                objects.packs.len()
+            ));
+
+            for (oid, content) in objects.loose {
                 // Content from hashtree is already the compressed loose object
                 // (that's what we store in build_objects_dir)
                 self.storage.import_compressed_object(&oid, content)?;
             }
+
+            // Packs are re-linked by hash, so only their indexes are needed
+            for pack in objects.packs {
+                self.storage.import_pack(pack.file, &pack.index_data)?;
+            }
         }
 
         self.detail("  Remote state loaded");
@@ -36,9 +36,13 @@
 
 use anyhow::{Context, Result};
 use hashtree_blossom::BlossomClient;
-use hashtree_core::{decode_tree_node, decrypt_chk, LinkType};
+use hashtree_core::{decode_tree_node, decrypt_chk, Cid, LinkType};
+use hashtree_resolver::composite::{CacheEntry, CompositeResolver, ResolvePolicy, RootCache};
+use hashtree_resolver::file::{FileResolverConfig, FileRootResolver};
+use hashtree_resolver::http::{HttpResolverConfig, HttpRootResolver};
 use nostr_sdk::prelude::*;
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
 use tracing::{debug, info, warn};
 
@@ -322,7 +326,7 @@ fn generate_and_save_key(petname: &str) -> Result<StoredKey> {
     })
 }
 
-use hashtree_config::Config;
+use hashtree_config::{Config, ResolverConfig};
 
 fn pick_latest_event<'a, I>(events: I) -> Option<&'a Event>
 where
@@ -334,6 +338,25 @@ where
         .max_by_key(|event| (event.created_at, event.id))
 }
 
+/// Composite of the configured root maps and the root cache, without Nostr
+fn fallback_resolver(config: &ResolverConfig) -> Result<CompositeResolver> {
+    let policy: ResolvePolicy = config.policy.parse()?;
+    let timeout = Duration::from_millis(config.timeout_ms);
+    let mut resolver = CompositeResolver::new(policy).with_timeout(timeout);
+    if let Some(path) = &config.file {
+        let file = FileRootResolver::new(FileResolverConfig::new(path));
+        resolver = resolver.with_backend("file", Arc::new(file));
+    }
+    if let Some(url) = &config.http {
+        let http = HttpRootResolver::new(HttpResolverConfig::new(url).with_timeout(timeout))?;
+        resolver = resolver.with_backend("http", Arc::new(http));
+    }
+    if config.cache {
+        resolver = resolver.with_cache(hashtree_config::get_root_cache_path());
+    }
+    Ok(resolver)
+}
+
 fn latest_trusted_pr_status_kinds(
     pr_events: &[Event],
     status_events: &[Event],
@@ -607,8 +630,11 @@ impl NostrClient {
                 last_log = std::time::Instant::now();
             }
             if start.elapsed() > connect_timeout {
-                debug!("Timeout waiting for relay connections - treating as empty repo");
                 let _ = client.disconnect().await;
+                if let Some(cached) = self.fetch_refs_from_fallback(repo_name).await? {
+                    return Ok(cached);
+                }
+                debug!("Timeout waiting for relay connections - treating as empty repo");
                 return Ok((HashMap::new(), None, None));
             }
             tokio::time::sleep(Duration::from_millis(50)).await;
@@ -631,6 +657,7 @@ impl NostrClient {
         debug!("Querying relays for repo {} events", repo_name);
 
         // Query with timeout - treat timeout as "no events found" for new repos
+        let mut query_failed = false;
         let events = match tokio::time::timeout(
             query_timeout,
             client.get_events_of(vec![filter], EventSource::relays(None)),
@@ -640,10 +667,12 @@ impl NostrClient {
             Ok(Ok(events)) => events,
             Ok(Err(e)) => {
                 warn!("Failed to fetch events: {}", e);
+                query_failed = true;
                 vec![]
             }
             Err(_) => {
                 debug!("Relay query timed out - treating as empty repo");
+                query_failed = true;
                 vec![]
             }
         };
@@ -662,6 +691,11 @@ impl NostrClient {
         }));
 
         let Some(event) = event else {
+            if query_failed {
+                if let Some(cached) = self.fetch_refs_from_fallback(repo_name).await? {
+                    return Ok(cached);
+                }
+            }
             let npub = PublicKey::from_hex(&self.pubkey)
                 .map(|pk| {
                     pk.to_bech32()
@@ -809,9 +843,61 @@ impl NostrClient {
         let refs = self
             .fetch_refs_from_hashtree(&root_hash, unmasked_key.as_ref())
             .await?;
+
+        // Remember the root so fetches keep working while relays are down
+        let cache_key = self
+            .resolver_key(repo_name)
+            .filter(|_| Config::load_or_default().resolver.cache);
+        if let (Some(key), Ok(mut cid)) = (cache_key, Cid::parse(&root_hash)) {
+            cid.key = unmasked_key;
+            let entry = CacheEntry::new(&cid, Some(event.created_at.as_u64()), "nostr");
+            let cache = RootCache::open(hashtree_config::get_root_cache_path());
+            if let Err(e) = cache.put(&key, entry) {
+                debug!("Failed to update root cache: {}", e);
+            }
+        }
         Ok((refs, Some(root_hash), unmasked_key))
     }
 
+    /// Key of this repo in root maps and the root cache ("npub.../repo")
+    fn resolver_key(&self, repo_name: &str) -> Option<String> {
+        let npub = PublicKey::from_hex(&self.pubkey).ok()?.to_bech32().ok()?;
+        Some(format!("{}/{}", npub, repo_name))
+    }
+
+    /// Refs at the root the `[resolver]` root maps give for this repo, or the
+    /// last root seen, used when relays are unreachable
+    async fn fetch_refs_from_fallback(
+        &self,
+        repo_name: &str,
+    ) -> Result<Option<(HashMap<String, String>, Option<String>, Option<[u8; 32]>)>> {
+        let Some(key) = self.resolver_key(repo_name) else {
+            return Ok(None);
+        };
+        let resolver = fallback_resolver(&Config::load_or_default().resolver)?;
+        let resolution = match resolver.resolve_detailed(&key).await {
+            Ok(Some(resolution)) => resolution,
+            Ok(None) => return Ok(None),
+            Err(e) => {
+                debug!("Fallback resolvers have no root for {}: {}", key, e);
+                return Ok(None);
+            }
+        };
+
+        let cid = resolution.cid;
+        let root_hash = hex::encode(cid.hash);
+        info!(
+            "Relays unreachable, using root {} for {} from {}",
+            &root_hash[..12],
+            repo_name,
+            resolution.source
+        );
+        let refs = self
+            .fetch_refs_from_hashtree(&root_hash, cid.key.as_ref())
+            .await?;
+        Ok(Some((refs, Some(root_hash), cid.key)))
+    }
+
     /// Decrypt data if encryption key is provided, then decode as tree node
     fn decrypt_and_decode(
         &self,
@@ -1677,6 +1763,37 @@ mod tests {
         assert_eq!(refs.get("refs/heads/main"), Some(&"abc123".to_string()));
     }
 
+    #[tokio::test]
+    async fn test_fallback_resolver_reads_root_map() {
+        use hashtree_resolver::composite::ResolutionSource;
+        use hashtree_resolver::RootResolver;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("roots.json");
+        let cid = Cid {
+            hash: [7; 32],
+            key: None,
+        };
+        FileRootResolver::new(FileResolverConfig::new(&path))
+            .publish("npub1x/repo", &cid)
+            .await
+            .unwrap();
+
+        let config = ResolverConfig {
+            file: Some(path.to_string_lossy().into_owned()),
+            cache: false,
+            ..ResolverConfig::default()
+        };
+        let resolution = fallback_resolver(&config)
+            .unwrap()
+            .resolve_detailed("npub1x/repo")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(resolution.cid, cid);
+        assert_eq!(resolution.source, ResolutionSource::Backend("file".into()));
+    }
+
     #[test]
     fn test_pick_latest_event_prefers_newer_timestamp() {
         let keys = Keys::generate();
@@ -513,7 +513,9 @@ pub fn compute_sha256(data: &[u8]) -> String {
 mod store_impl {
     use super::*;
     use async_trait::async_trait;
-    use hashtree_core::{to_hex, Hash, Store, StoreError};
+    use futures::stream::{self, StreamExt};
+    use hashtree_core::{to_hex, Hash, Store, StoreError, BATCH_CONCURRENCY};
+    use std::collections::hash_map::Entry;
     use std::collections::HashMap;
     use std::sync::RwLock;
 
@@ -603,6 +605,65 @@ mod store_impl {
             let mut cache = self.cache.write().unwrap();
             Ok(cache.remove(&key).is_some())
         }
+
+        async fn put_many(&self, items: Vec<(Hash, Vec<u8>)>) -> Result<Vec<bool>, StoreError> {
+            let mut cache = self.cache.write().unwrap();
+            Ok(items
+                .into_iter()
+                .map(|(hash, data)| match cache.entry(to_hex(&hash)) {
+                    Entry::Occupied(_) => false,
+                    Entry::Vacant(entry) => {
+                        entry.insert(data);
+                        true
+                    }
+                })
+                .collect())
+        }
+
+        async fn get_many(&self, hashes: &[Hash]) -> Result<Vec<Option<Vec<u8>>>, StoreError> {
+            let keys: Vec<String> = hashes.iter().map(to_hex).collect();
+            let mut results: Vec<Option<Vec<u8>>> = {
+                let cache = self.cache.read().unwrap();
+                keys.iter().map(|key| cache.get(key).cloned()).collect()
+            };
+
+            // Download cache misses concurrently
+            let misses: Vec<usize> = (0..keys.len()).filter(|&i| results[i].is_none()).collect();
+            let fetched: Vec<(usize, Vec<u8>)> = stream::iter(misses)
+                .map(|i| {
+                    let key = &keys[i];
+                    async move { self.client.try_download(key).await.map(|data| (i, data)) }
+                })
+                .buffer_unordered(BATCH_CONCURRENCY)
+                .filter_map(|found| async move { found })
+                .collect()
+                .await;
+
+            let mut cache = self.cache.write().unwrap();
+            for (i, data) in fetched {
+                cache.insert(keys[i].clone(), data.clone());
+                results[i] = Some(data);
+            }
+            Ok(results)
+        }
+
+        async fn has_many(&self, hashes: &[Hash]) -> Result<Vec<bool>, StoreError> {
+            let keys: Vec<String> = hashes.iter().map(to_hex).collect();
+            let cached: Vec<bool> = {
+                let cache = self.cache.read().unwrap();
+                keys.iter().map(|key| cache.contains_key(key)).collect()
+            };
+
+            // Check cache misses against the servers concurrently
+            Ok(stream::iter(0..keys.len())
+                .map(|i| {
+                    let (key, cached) = (&keys[i], cached[i]);
+                    async move { cached || self.client.exists(key).await }
+                })
+                .buffered(BATCH_CONCURRENCY)
+                .collect()
+                .await)
+        }
     }
 }
 
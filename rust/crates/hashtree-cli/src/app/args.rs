@@ -1,5 +1,6 @@
 use clap::{Parser, Subcommand, ValueEnum};
 use git_remote_htree::nostr_client::PullRequestStateFilter;
+use hashtree_core::ArchiveFormat;
 use std::path::PathBuf;
 
 #[derive(Parser)]
@@ -87,6 +88,9 @@ pub(crate) enum Commands {
         /// Don't push to file servers (local only)
         #[arg(long)]
         local: bool,
+        /// Add the contents of a tar (.tar, .tar.gz, .tar.zst) or zip archive as a directory
+        #[arg(long)]
+        from_archive: bool,
     },
 
     /// Get/download content by CID
@@ -96,6 +100,42 @@ pub(crate) enum Commands {
         /// Output path (default: current dir, uses CID as filename)
         #[arg(short, long)]
         output: Option<PathBuf>,
+        /// Download a directory as a single archive (use `-o -` for stdout)
+        #[arg(long, value_enum)]
+        archive: Option<ArchiveKind>,
+    },
+
+    /// Export a complete tree as a single portable bundle file
+    Export {
+        /// CID to export
+        cid: String,
+        /// Bundle path (`-` for stdout)
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Append an index for random access to blocks
+        #[arg(long)]
+        index: bool,
+    },
+
+    /// Import a bundle, verifying every block, and pin its roots
+    Import {
+        /// Bundle path (`-` for stdin)
+        path: PathBuf,
+    },
+
+    /// Show the recorded root history of a tree (newest first)
+    Log {
+        /// Tree key (npub.../treename)
+        tree: String,
+    },
+
+    /// Download an earlier version of a tree
+    Checkout {
+        /// Tree version: npub.../treename@<n|timestamp> (0 = newest)
+        target: String,
+        /// Output path (default: the tree name)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
     },
 
     /// Output file content to stdout (like cat)
@@ -119,6 +159,17 @@ pub(crate) enum Commands {
         cid: String,
     },
 
+    /// Show paths added, removed, modified or renamed between two trees
+    Diff {
+        /// Old tree CID
+        old: String,
+        /// New tree CID
+        new: String,
+        /// Print changes as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
     /// Get information about a CID
     Info {
         /// CID to inspect
@@ -142,8 +193,12 @@ pub(crate) enum Commands {
         pid_file: Option<PathBuf>,
     },
 
-    /// Run garbage collection
-    Gc,
+    /// Run garbage collection (deletes blobs unreachable from pins, indexed trees and cached roots)
+    Gc {
+        /// Report what would be deleted without deleting anything
+        #[arg(long)]
+        dry_run: bool,
+    },
 
     /// Show or set your nostr identity
     User {
@@ -153,13 +208,20 @@ pub(crate) enum Commands {
 
     /// Publish a hash to Nostr under a ref name
     Publish {
-        /// The ref name to publish under (e.g., "mydata" -> npub.../mydata)
+        /// The ref name to publish under (e.g., "mydata" -> npub.../mydata),
+        /// or "npub.../name" to publish as a delegated writer
         ref_name: String,
-        /// The hash to publish (hex encoded)
-        hash: String,
+        /// The hash to publish (hex encoded); omit to only manage writers
+        hash: Option<String>,
         /// Optional decryption key (hex encoded, for encrypted content)
         #[arg(long)]
         key: Option<String>,
+        /// Authorize an npub to publish roots for this tree (repeatable)
+        #[arg(long = "add-writer", value_name = "NPUB")]
+        add_writers: Vec<String>,
+        /// Revoke a writer's access to this tree (repeatable)
+        #[arg(long = "remove-writer", value_name = "NPUB")]
+        remove_writers: Vec<String>,
     },
 
     /// Follow a user (adds to your contact list)
@@ -229,11 +291,13 @@ pub(crate) enum Commands {
         command: StorageCommands,
     },
 
-    /// Show connected P2P peers
+    /// Show connected P2P peers, or manage the peer ban list
     Peer {
         /// Daemon address (default: 127.0.0.1:8080)
         #[arg(long, default_value = "127.0.0.1:8080")]
         addr: String,
+        #[command(subcommand)]
+        command: Option<PeerCommands>,
     },
 
     /// Pull request management
@@ -243,6 +307,27 @@ pub(crate) enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+pub(crate) enum PeerCommands {
+    /// Refuse connections from a peer and never request data from it
+    Ban {
+        /// Peer pubkey (npub or hex), or host:port of a WebSocket peer
+        peer: String,
+    },
+    /// Remove a peer's ban or allow entry
+    Unban {
+        /// Peer pubkey (npub or hex), or host:port of a WebSocket peer
+        peer: String,
+    },
+    /// Always use a peer, even if it was auto-excluded for corrupt data
+    Allow {
+        /// Peer pubkey (npub or hex), or host:port of a WebSocket peer
+        peer: String,
+    },
+    /// Show banned, allowed and auto-excluded peers
+    List,
+}
+
 #[derive(Subcommand)]
 pub(crate) enum PrCommands {
     /// Create a pull request
@@ -296,6 +381,21 @@ impl PrListState {
     }
 }
 
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub(crate) enum ArchiveKind {
+    Tar,
+    Zip,
+}
+
+impl ArchiveKind {
+    pub(crate) fn to_format(self) -> ArchiveFormat {
+        match self {
+            Self::Tar => ArchiveFormat::Tar,
+            Self::Zip => ArchiveFormat::Zip,
+        }
+    }
+}
+
 #[derive(Subcommand)]
 pub(crate) enum StorageCommands {
     /// Show storage usage statistics by priority tier
@@ -313,6 +413,13 @@ pub(crate) enum StorageCommands {
         #[arg(long)]
         r2: bool,
     },
+    /// List unfinished tree fetches
+    Jobs,
+    /// Cancel an unfinished tree fetch
+    Cancel {
+        /// Job ID (as shown by `htree storage jobs`)
+        id: String,
+    },
 }
 
 #[derive(Subcommand)]
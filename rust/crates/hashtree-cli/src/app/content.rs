@@ -106,3 +106,46 @@ pub(crate) async fn add_directory<S: hashtree_core::store::Store>(
         .cloned()
         .ok_or_else(|| anyhow::anyhow!("No root directory"))
 }
+
+/// Recursively write a stored directory tree to `dir`, restoring symlinks and metadata.
+pub(crate) async fn download_dir(
+    store: &std::sync::Arc<hashtree_cli::HashtreeStore>,
+    hash: &[u8; 32],
+    dir: &std::path::Path,
+) -> Result<()> {
+    #[cfg(unix)]
+    use anyhow::Context;
+
+    // Get listing
+    let listing = store.get_directory_listing(hash)?;
+    if let Some(listing) = listing {
+        for entry in listing.entries {
+            let entry_path = dir.join(&entry.name);
+            let entry_hash = hashtree_core::from_hex(&entry.cid)
+                .map_err(|e| anyhow::anyhow!("Invalid CID: {}", e))?;
+            #[cfg(unix)]
+            if let Some(target) = &entry.posix.symlink {
+                if entry_path.symlink_metadata().is_ok() {
+                    std::fs::remove_file(&entry_path)?;
+                }
+                std::os::unix::fs::symlink(target, &entry_path)?;
+                println!("  {} -> {}", target, entry_path.display());
+                continue;
+            }
+            if entry.is_directory {
+                std::fs::create_dir_all(&entry_path)?;
+                Box::pin(download_dir(store, &entry_hash, &entry_path)).await?;
+            } else {
+                store.write_file(&entry_hash, &entry_path)?;
+                println!("  {} -> {}", entry.cid, entry_path.display());
+            }
+            // Directories are restored after their contents
+            #[cfg(unix)]
+            entry
+                .posix
+                .restore(&entry_path)
+                .with_context(|| format!("Failed to set metadata on {}", entry_path.display()))?;
+        }
+    }
+    Ok(())
+}
@@ -64,9 +64,42 @@ pub(crate) fn format_daemon_status(status: &serde_json::Value, include_header: b
         }
     }
 
+    if let Some(jobs) = status.get("fetch_jobs").and_then(|j| j.as_array()) {
+        if !jobs.is_empty() {
+            lines.push(String::new());
+            lines.extend(format_fetch_jobs(jobs));
+        }
+    }
+
     lines.join("\n")
 }
 
+/// Format `FetchJob::status_json` entries as a "Fetch jobs:" section
+pub(crate) fn format_fetch_jobs(jobs: &[serde_json::Value]) -> Vec<String> {
+    let mut lines = vec![format!("Fetch jobs ({}):", jobs.len())];
+    for job in jobs {
+        let id = job["id"].as_str().unwrap_or("?");
+        let root = job["root"].as_str().unwrap_or("");
+        let done = job["bytes_done"].as_u64().unwrap_or(0);
+        let progress = match job["total_bytes"].as_u64() {
+            Some(total) => format!("{} / {}", format_bytes(done), format_bytes(total)),
+            None => format_bytes(done),
+        };
+        let mut line = format!(
+            "  {} {}... {} - {} pending",
+            id,
+            &root[..12.min(root.len())],
+            progress,
+            job["pending"].as_u64().unwrap_or(0)
+        );
+        if let Some(eta) = job["eta_secs"].as_u64() {
+            line.push_str(&format!(" - ETA {}s", eta));
+        }
+        lines.push(line);
+    }
+    lines
+}
+
 fn default_daemon_log_file() -> PathBuf {
     hashtree_cli::config::get_hashtree_dir()
         .join("logs")
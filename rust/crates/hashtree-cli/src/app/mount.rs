@@ -150,6 +150,21 @@ impl RootPublisher for NostrRootPublisher {
 
         Ok(())
     }
+
+    fn latest(&self) -> Result<Option<hashtree_core::Cid>, FuseFsError> {
+        let resolver = &self.resolver;
+        let key = self.key.as_str();
+        let link_key = self.link_key;
+
+        self.handle
+            .block_on(async move {
+                match link_key {
+                    Some(link_key) => resolver.resolve_shared(key, &link_key).await,
+                    None => resolver.resolve(key).await,
+                }
+            })
+            .map_err(|e| FuseFsError::Publish(e.to_string()))
+    }
 }
 
 pub(crate) async fn mount_fuse(
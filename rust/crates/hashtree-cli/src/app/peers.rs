@@ -1,6 +1,7 @@
 use anyhow::Result;
 use hashtree_cli::Config;
 
+use super::args::PeerCommands;
 use super::util::format_bytes;
 
 /// List connected peers with optional profile resolution.
@@ -179,3 +180,90 @@ pub(crate) async fn fetch_profile_name(relays: &[String], pubkey_hex: &str) -> O
         .filter(|s| !s.is_empty())
         .map(|s| s.to_string())
 }
+
+/// Parse a peer given as npub or hex pubkey into hex. WebSocket peers are
+/// given as the "host:port" (or `ws://` URL) they are keyed by.
+#[cfg(feature = "p2p")]
+pub(crate) fn parse_peer_pubkey(peer: &str) -> Result<String> {
+    use anyhow::Context;
+    use hashtree_cli::config::parse_npub;
+    use hashtree_cli::webrtc::{peer_pubkey, ws_peer_key};
+
+    if peer.starts_with("npub1") {
+        return Ok(hex::encode(parse_npub(peer)?));
+    }
+    let key = ws_peer_key(peer).unwrap_or_else(|| peer.to_string());
+    if key.contains(':') && peer_pubkey(&key) == key {
+        return Ok(key);
+    }
+    let pk = nostr::PublicKey::from_hex(peer)
+        .context("Expected an npub, hex pubkey or WebSocket peer host:port")?;
+    Ok(pk.to_hex())
+}
+
+/// Edit or show the peer ban/allow list read by the daemon.
+#[cfg(feature = "p2p")]
+pub(crate) fn manage_peer_access(command: PeerCommands) -> Result<()> {
+    use anyhow::Context;
+    use hashtree_cli::config::{get_peer_access_path, get_peer_reputation_path};
+    use hashtree_cli::webrtc::{
+        load_state_file, save_state_file, AccessList, PeerAccess, ReputationMap, AUTO_BAN_CORRUPT,
+    };
+
+    let access_path = get_peer_access_path();
+    let mut list: AccessList = load_state_file(&access_path)
+        .with_context(|| format!("Failed to read {}", access_path.display()))?;
+
+    let (pubkey, access) = match command {
+        PeerCommands::Ban { peer } => (parse_peer_pubkey(&peer)?, Some(PeerAccess::Banned)),
+        PeerCommands::Allow { peer } => (parse_peer_pubkey(&peer)?, Some(PeerAccess::Allowed)),
+        PeerCommands::Unban { peer } => (parse_peer_pubkey(&peer)?, None),
+        PeerCommands::List => {
+            let reputation: ReputationMap = load_state_file(&get_peer_reputation_path())?;
+            let excluded: Vec<_> = reputation
+                .iter()
+                .filter(|(pubkey, rep)| {
+                    rep.corrupt >= AUTO_BAN_CORRUPT && !list.contains_key(pubkey.as_str())
+                })
+                .collect();
+
+            if list.is_empty() && excluded.is_empty() {
+                println!("No banned or allowed peers");
+                return Ok(());
+            }
+            for (pubkey, access) in &list {
+                println!("{:<8} {}", access.to_string(), pubkey);
+            }
+            for (pubkey, rep) in excluded {
+                println!(
+                    "{:<8} {} ({} corrupt responses)",
+                    "excluded", pubkey, rep.corrupt
+                );
+            }
+            return Ok(());
+        }
+    };
+
+    let previous = match access {
+        Some(access) => list.insert(pubkey.clone(), access),
+        None => list.remove(&pubkey),
+    };
+    save_state_file(&access_path, &list)
+        .with_context(|| format!("Failed to write {}", access_path.display()))?;
+    match (access, previous) {
+        (Some(PeerAccess::Banned), _) => println!("Banned {}", pubkey),
+        (Some(PeerAccess::Allowed), _) => println!("Allowed {}", pubkey),
+        (None, Some(previous)) => println!("{} is no longer {}", pubkey, previous),
+        (None, None) => {
+            println!("{} was neither banned nor allowed", pubkey);
+            return Ok(());
+        }
+    }
+    println!("A running daemon picks this up within 30 seconds");
+    Ok(())
+}
+
+#[cfg(not(feature = "p2p"))]
+pub(crate) fn manage_peer_access(_command: PeerCommands) -> Result<()> {
+    anyhow::bail!("htree was built without P2P support (enable the `p2p` feature)")
+}
@@ -1,5 +1,6 @@
 use anyhow::{Context, Result};
-use hashtree_cli::{NostrKeys, NostrResolverConfig, NostrRootResolver, RootResolver};
+use hashtree_cli::{Config, NostrKeys, NostrResolverConfig, NostrRootResolver, RootResolver};
+use std::sync::Arc;
 
 /// Resolved CID with optional path.
 pub(crate) struct ResolvedCid {
@@ -17,6 +18,7 @@ pub(crate) struct ResolveOptions {
 
 /// Resolve a CID input which can be:
 /// - An nhash (bech32-encoded hash with optional key)
+/// - A key from the configured file/HTTP root maps (e.g., "local/site")
 /// - An npub/repo path (e.g., "npub1.../myrepo")
 /// - An htree:// URL (e.g., "htree://npub1.../myrepo")
 /// Returns the resolved Cid (raw bytes) and optional path within the tree.
@@ -66,54 +68,96 @@ pub(crate) async fn resolve_cid_input_with_opts(
         });
     }
 
-    // Check if it looks like an npub path (npub1.../name or npub1.../name/path)
-    if input.starts_with("npub1") && input.contains('/') {
-        let parts: Vec<&str> = input.splitn(3, '/').collect();
-        if parts.len() >= 2 {
-            let npub = parts[0];
-            let repo = parts[1];
-            let subpath = if parts.len() > 2 {
-                Some(parts[2].to_string())
-            } else {
-                None
-            };
-
-            // Resolve via nostr
-            let key = format!("{}/{}", npub, repo);
-            eprintln!("Resolving {}...", key);
-
-            let mut config = NostrResolverConfig::default();
-            if let Some(relays) = &opts.relays {
-                config.relays = relays.clone();
-            }
-            if opts.private {
-                config.secret_key = opts.secret_key.clone();
-            }
+    // Named trees: configured root maps, plus Nostr for npub keys
+    if let Some(resolved) = resolve_named(input, opts).await? {
+        return Ok(resolved);
+    }
+
+    anyhow::bail!(
+        "Invalid format. Use nhash1..., <hash>, <hash:key>, npub1.../name, or a root map key"
+    )
+}
+
+/// Resolve "<namespace>/<name>[/path]" through the configured resolver chain
+///
+/// npub namespaces add Nostr behind the file/HTTP root maps. Answers are
+/// combined per the `[resolver]` policy and cached for offline use.
+async fn resolve_named(input: &str, opts: &ResolveOptions) -> Result<Option<ResolvedCid>> {
+    let mut parts = input.splitn(3, '/');
+    let (Some(namespace), Some(name)) = (parts.next(), parts.next()) else {
+        return Ok(None);
+    };
+    if name.is_empty() {
+        return Ok(None);
+    }
+    let key = format!("{}/{}", namespace, name);
+    let subpath = parts.next().map(|p| p.to_string());
+
+    let is_npub = namespace.starts_with("npub1");
+    let nostr: Option<Arc<dyn RootResolver>> = if is_npub {
+        let mut config = NostrResolverConfig::default();
+        if let Some(relays) = &opts.relays {
+            config.relays = relays.clone();
+        }
+        if opts.private {
+            config.secret_key = opts.secret_key.clone();
+        }
+        let resolver = NostrRootResolver::new(config)
+            .await
+            .context("Failed to create nostr resolver")?;
+        Some(Arc::new(resolver))
+    } else {
+        None
+    };
 
-            let resolver = NostrRootResolver::new(config)
-                .await
-                .context("Failed to create nostr resolver")?;
-
-            let resolved = if let Some(link_key) = opts.link_key {
-                resolver.resolve_shared(&key, &link_key).await
-            } else {
-                resolver.resolve(&key).await
-            };
-
-            match resolved {
-                Ok(Some(cid)) => {
-                    eprintln!("Resolved to: {}", hashtree_core::to_hex(&cid.hash));
-                    return Ok(ResolvedCid { cid, path: subpath });
-                }
-                Ok(None) => {
-                    anyhow::bail!("No content found for {}", key);
-                }
-                Err(e) => {
-                    anyhow::bail!("Failed to resolve {}: {}", key, e);
-                }
+    let config = Config::load().unwrap_or_default();
+    let resolver = config.resolver.composite(nostr)?;
+    if resolver.backend_names().is_empty() {
+        return Ok(None);
+    }
+    if is_npub {
+        eprintln!("Resolving {}...", key);
+    }
+
+    let resolved = match opts.link_key {
+        Some(link_key) => resolver
+            .resolve_shared(&key, &link_key)
+            .await
+            .map(|cid| cid.map(|cid| (cid, None))),
+        None => resolver
+            .resolve_detailed(&key)
+            .await
+            .map(|r| r.map(|r| (r.cid, Some(r.source)))),
+    };
+    let _ = resolver.stop().await;
+
+    match resolved {
+        Ok(Some((cid, source))) => {
+            let hash = hashtree_core::to_hex(&cid.hash);
+            match source {
+                Some(source) => eprintln!("Resolved {} to: {} (via {})", key, hash, source),
+                None => eprintln!("Resolved {} to: {}", key, hash),
             }
+            Ok(Some(ResolvedCid { cid, path: subpath }))
+        }
+        Ok(None) if is_npub => anyhow::bail!("No content found for {}", key),
+        Err(e) if is_npub => anyhow::bail!("Failed to resolve {}: {}", key, e),
+        Ok(None) => Ok(None),
+        Err(e) => {
+            eprintln!("Root map lookup for {} failed: {}", key, e);
+            Ok(None)
         }
     }
+}
 
-    anyhow::bail!("Invalid format. Use nhash1..., <hash>, <hash:key>, or npub1.../name")
+/// Split an "npub.../treename" key into (pubkey hex, tree name)
+pub(crate) fn parse_tree_key(key: &str) -> Result<(String, String)> {
+    let (npub, tree_name) = key
+        .split_once('/')
+        .filter(|(_, name)| !name.is_empty())
+        .ok_or_else(|| anyhow::anyhow!("Expected npub.../treename, got {}", key))?;
+    Ok((
+        hex::encode(hashtree_cli::config::parse_npub(npub)?),
+        tree_name.to_string(),
+    ))
 }
@@ -4,8 +4,8 @@ use hashtree_cli::config::{
     ensure_auth_cookie, ensure_keys, ensure_keys_string, parse_npub, pubkey_bytes,
 };
 use hashtree_cli::{
-    BackgroundSync, Config, HashtreeServer, HashtreeStore, NostrKeys, NostrResolverConfig,
-    NostrRootResolver, NostrToBech32, RootResolver,
+    import_archive_file, BackgroundSync, Config, HashtreeServer, HashtreeStore, NostrKeys,
+    NostrResolverConfig, NostrRootResolver, NostrToBech32, RootResolver,
 };
 #[cfg(feature = "p2p")]
 use hashtree_cli::{PeerPool, WebRTCConfig, WebRTCManager};
@@ -16,13 +16,13 @@ use std::time::Duration;
 
 use super::args::{Cli, Commands, PrCommands, SocialGraphCommands, StorageCommands};
 use super::blossom::{background_blossom_push, push_to_blossom};
-use super::content::add_directory;
-use super::daemonize::{format_daemon_status, spawn_daemon, stop_daemon};
+use super::content::{add_directory, download_dir};
+use super::daemonize::{format_daemon_status, format_fetch_jobs, spawn_daemon, stop_daemon};
 use super::lists::{follow_user, list_following, list_muted, mute_user, update_profile};
 #[cfg(feature = "fuse")]
 use super::mount::mount_fuse;
-use super::peers::{fetch_profile_name, list_peers};
-use super::resolve::resolve_cid_input;
+use super::peers::{fetch_profile_name, list_peers, manage_peer_access};
+use super::resolve::{parse_tree_key, resolve_cid_input};
 use super::socialgraph::{run_socialgraph_filter, run_socialgraph_snapshot};
 use super::util::chrono_humanize_timestamp;
 
@@ -204,9 +204,22 @@ pub(crate) async fn run() -> Result<()> {
                 };
 
                 // Start WebRTC signaling manager if enabled
-                let (webrtc_handle, webrtc_state) = if config.server.enable_webrtc {
+                let (webrtc_handle, webrtc_state) = if config.server.enable_webrtc
+                    || !config.server.ws_peers.is_empty()
+                    || config.server.lan_discovery
+                {
                     let webrtc_config = WebRTCConfig {
-                        relays: config.nostr.relays.clone(),
+                        // WebSocket and LAN peers alone don't need Nostr signaling
+                        relays: if config.server.enable_webrtc {
+                            config.nostr.relays.clone()
+                        } else {
+                            Vec::new()
+                        },
+                        peer_reputation_path: Some(hashtree_cli::config::get_peer_reputation_path()),
+                        peer_access_path: Some(hashtree_cli::config::get_peer_access_path()),
+                        bandwidth: config.bandwidth.limits(),
+                        ws_peers: config.server.ws_peers.clone(),
+                        lan_discovery: config.server.lan_discovery(&addr),
                         ..Default::default()
                     };
 
@@ -388,6 +401,14 @@ pub(crate) async fn run() -> Result<()> {
             if config.server.enable_webrtc {
                 println!("WebRTC: enabled (P2P connections)");
             }
+            #[cfg(feature = "p2p")]
+            if config.server.lan_discovery {
+                println!("LAN discovery: enabled");
+            }
+            #[cfg(feature = "p2p")]
+            if !config.server.ws_peers.is_empty() {
+                println!("WebSocket peers: {}", config.server.ws_peers.join(", "));
+            }
             println!(
                 "Social graph: enabled (crawl_depth={}, max_write_distance={})",
                 config.nostr.crawl_depth, config.nostr.max_write_distance
@@ -473,6 +494,7 @@ pub(crate) async fn run() -> Result<()> {
             no_ignore,
             publish,
             local,
+            from_archive,
         } => {
             let is_dir = path.is_dir();
 
@@ -492,7 +514,13 @@ pub(crate) async fn run() -> Result<()> {
                 };
                 let tree = HashTree::new(config);
 
-                if is_dir {
+                if from_archive {
+                    let cid = import_archive_file(&tree, &path).await?;
+                    println!("hash: {}", to_hex(&cid.hash));
+                    if let Some(key) = cid.key {
+                        println!("key:  {}", to_hex(&key));
+                    }
+                } else if is_dir {
                     // For directories, use the recursive helper
                     let cid = add_directory(&tree, &path, !no_ignore).await?;
                     println!("hash: {}", to_hex(&cid.hash));
@@ -522,7 +550,11 @@ pub(crate) async fn run() -> Result<()> {
 
                 // Store and capture hash/key for potential publishing
                 let (hash_hex, key_hex): (String, Option<String>) = if public {
-                    let hash_hex = if is_dir {
+                    let hash_hex = if from_archive {
+                        store
+                            .import_archive(&path)
+                            .context("Failed to import archive")?
+                    } else if is_dir {
                         store
                             .upload_dir_with_options(&path, !no_ignore)
                             .context("Failed to add directory")?
@@ -541,7 +573,11 @@ pub(crate) async fn run() -> Result<()> {
                     println!("  hash:  {}", hash_hex);
                     (hash_hex, None)
                 } else {
-                    let cid_str = if is_dir {
+                    let cid_str = if from_archive {
+                        store
+                            .import_archive_encrypted(&path)
+                            .context("Failed to import archive")?
+                    } else if is_dir {
                         store
                             .upload_dir_encrypted_with_options(&path, !no_ignore)
                             .context("Failed to add directory")?
@@ -679,9 +715,12 @@ pub(crate) async fn run() -> Result<()> {
         Commands::Get {
             cid: cid_input,
             output,
+            archive,
         } => {
+            use futures::StreamExt;
             use hashtree_cli::{FetchConfig, Fetcher};
-            use hashtree_core::{from_hex, to_hex};
+            use hashtree_core::{archive_stream, to_hex, HashTree, HashTreeConfig};
+            use std::io::Write;
 
             // Resolve to Cid (raw bytes, no hex conversion needed for nhash)
             let resolved = resolve_cid_input(&cid_input).await?;
@@ -697,8 +736,43 @@ pub(crate) async fn run() -> Result<()> {
             // Check if it's a directory
             let listing = store.get_directory_listing(&cid.hash)?;
 
-            // Handle path: nhash/path/to/file.ext
-            if let Some(ref path) = resolved.path {
+            if let Some(kind) = archive {
+                // Stream the directory (or a subdirectory) into one tar/zip file
+                let format = kind.to_format();
+                let (dir, name) = match resolved.path.as_deref() {
+                    Some(path) if listing.is_some() => {
+                        let entry = store.resolve_path(&cid, path)?.ok_or_else(|| {
+                            anyhow::anyhow!("Path not found in directory: {}", path)
+                        })?;
+                        fetcher.fetch_tree(&store, None, &entry.hash).await?;
+                        let path = path.trim_end_matches('/');
+                        let name = path.rsplit('/').next().unwrap_or(path).to_string();
+                        (entry, name)
+                    }
+                    _ => (cid.clone(), hash_hex.clone()),
+                };
+                let out_path = output
+                    .unwrap_or_else(|| PathBuf::from(format!("{}.{}", name, format.extension())));
+                let mut out: Box<dyn Write> = if out_path.as_os_str() == "-" {
+                    Box::new(std::io::stdout().lock())
+                } else {
+                    Box::new(std::io::BufWriter::new(
+                        std::fs::File::create(&out_path)
+                            .with_context(|| format!("Failed to create {}", out_path.display()))?,
+                    ))
+                };
+
+                let tree = HashTree::new(HashTreeConfig::new(store.store_arc()).public());
+                let mut chunks = archive_stream(&tree, dir.clone(), format);
+                while let Some(chunk) = chunks.next().await {
+                    out.write_all(&chunk?)?;
+                }
+                out.flush()?;
+                if out_path.as_os_str() != "-" {
+                    println!("{} -> {}", to_hex(&dir.hash), out_path.display());
+                }
+            } else if let Some(ref path) = resolved.path {
+                // Handle path: nhash/path/to/file.ext
                 if listing.is_some() {
                     // nhash points to directory - resolve path within it
                     let resolved_cid = store
@@ -727,30 +801,6 @@ pub(crate) async fn run() -> Result<()> {
                 let out_dir = output.unwrap_or_else(|| PathBuf::from(&hash_hex));
                 std::fs::create_dir_all(&out_dir)?;
 
-                async fn download_dir(
-                    store: &Arc<HashtreeStore>,
-                    hash: &[u8; 32],
-                    dir: &std::path::Path,
-                ) -> Result<()> {
-                    // Get listing
-                    let listing = store.get_directory_listing(hash)?;
-                    if let Some(listing) = listing {
-                        for entry in listing.entries {
-                            let entry_path = dir.join(&entry.name);
-                            let entry_hash = from_hex(&entry.cid)
-                                .map_err(|e| anyhow::anyhow!("Invalid CID: {}", e))?;
-                            if entry.is_directory {
-                                std::fs::create_dir_all(&entry_path)?;
-                                Box::pin(download_dir(store, &entry_hash, &entry_path)).await?;
-                            } else {
-                                store.write_file(&entry_hash, &entry_path)?;
-                                println!("  {} -> {}", entry.cid, entry_path.display());
-                            }
-                        }
-                    }
-                    Ok(())
-                }
-
                 println!("Downloading directory to {}", out_dir.display());
                 download_dir(&store, &cid.hash, &out_dir).await?;
                 println!("Done.");
@@ -761,6 +811,144 @@ pub(crate) async fn run() -> Result<()> {
                 println!("{} -> {}", hash_hex, out_path.display());
             }
         }
+        Commands::Export {
+            cid: cid_input,
+            output,
+            index,
+        } => {
+            use hashtree_cli::{FetchConfig, Fetcher};
+            use std::io::Write;
+
+            let resolved = resolve_cid_input(&cid_input).await?;
+            let store = Arc::new(HashtreeStore::new(&data_dir)?);
+            let fetcher = Fetcher::new(FetchConfig::default());
+            fetcher.fetch_tree(&store, None, &resolved.cid.hash).await?;
+
+            let cid = match resolved.path.as_deref() {
+                Some(path) => {
+                    let entry = store
+                        .resolve_path(&resolved.cid, path)?
+                        .ok_or_else(|| anyhow::anyhow!("Path not found in directory: {}", path))?;
+                    fetcher.fetch_tree(&store, None, &entry.hash).await?;
+                    entry
+                }
+                None => resolved.cid,
+            };
+
+            let to_stdout = output.as_os_str() == "-";
+            let out: Box<dyn Write> = if to_stdout {
+                Box::new(std::io::stdout().lock())
+            } else {
+                Box::new(std::io::BufWriter::new(
+                    std::fs::File::create(&output)
+                        .with_context(|| format!("Failed to create {}", output.display()))?,
+                ))
+            };
+            let stats = store.export_bundle(&cid, out, index)?;
+            if !to_stdout {
+                println!(
+                    "Exported {} blocks ({} bytes) -> {}",
+                    stats.blocks,
+                    stats.bytes,
+                    output.display()
+                );
+            }
+        }
+        Commands::Import { path } => {
+            use hashtree_core::{nhash_encode_full, to_hex, NHashData};
+            use std::io::Read;
+
+            let input: Box<dyn Read> = if path.as_os_str() == "-" {
+                Box::new(std::io::stdin().lock())
+            } else {
+                Box::new(std::io::BufReader::new(
+                    std::fs::File::open(&path)
+                        .with_context(|| format!("Failed to open {}", path.display()))?,
+                ))
+            };
+
+            let store = HashtreeStore::new(&data_dir)?;
+            let imported = store.import_bundle(input)?;
+            println!(
+                "Imported {} blocks ({} bytes)",
+                imported.stats.blocks, imported.stats.bytes
+            );
+            for root in &imported.roots {
+                let nhash = nhash_encode_full(&NHashData {
+                    hash: root.hash,
+                    decrypt_key: root.key,
+                })
+                .unwrap_or_else(|_| to_hex(&root.hash));
+                println!("  {}", nhash);
+            }
+        }
+        Commands::Log { tree } => {
+            let (pubkey_hex, tree_name) = parse_tree_key(&tree)?;
+            let store = HashtreeStore::new(&data_dir)?;
+            let history = store.root_history(&pubkey_hex, &tree_name)?;
+            if history.is_empty() {
+                println!("No history recorded for {}", tree);
+                return Ok(());
+            }
+            for (n, version) in history.iter().enumerate() {
+                println!(
+                    "{:>4}  {}  {} ({})",
+                    n,
+                    version.hash,
+                    version.created_at,
+                    chrono_humanize_timestamp(version.created_at)
+                );
+                if let Some(prev) = &version.prev {
+                    let next_hash = history.get(n + 1).map(|v| v.hash.as_str());
+                    if next_hash != Some(prev.as_str()) {
+                        println!("        prev: {}", prev);
+                    }
+                }
+            }
+        }
+        Commands::Checkout { target, output } => {
+            use hashtree_cli::{select_root_version, FetchConfig, Fetcher};
+            use hashtree_core::{from_hex, key_from_hex, Cid};
+
+            let (tree, selector) = target.rsplit_once('@').unwrap_or((target.as_str(), "0"));
+            let (pubkey_hex, tree_name) = parse_tree_key(tree)?;
+            let store = Arc::new(HashtreeStore::new(&data_dir)?);
+            let history = store.root_history(&pubkey_hex, &tree_name)?;
+            let version = select_root_version(&history, selector)?.ok_or_else(|| {
+                anyhow::anyhow!(
+                    "No version {} in the recorded history of {}",
+                    selector,
+                    tree
+                )
+            })?;
+
+            let cid = Cid {
+                hash: from_hex(&version.hash).context("Invalid hash in root history")?,
+                key: version
+                    .key
+                    .as_deref()
+                    .map(key_from_hex)
+                    .transpose()
+                    .map_err(|e| anyhow::anyhow!("Invalid key in root history: {}", e))?,
+            };
+            let fetcher = Fetcher::new(FetchConfig::default());
+            fetcher.fetch_tree(&store, None, &cid.hash).await?;
+
+            let out_path = output.unwrap_or_else(|| PathBuf::from(&tree_name));
+            println!(
+                "Checking out {} ({}) -> {}",
+                version.hash,
+                chrono_humanize_timestamp(version.created_at),
+                out_path.display()
+            );
+            if store.get_directory_listing(&cid.hash)?.is_some() {
+                std::fs::create_dir_all(&out_path)?;
+                download_dir(&store, &cid.hash, &out_path).await?;
+            } else {
+                store.write_file_by_cid(&cid, &out_path)?;
+            }
+            println!("Done.");
+        }
         Commands::Cat { cid: cid_input } => {
             use hashtree_cli::{FetchConfig, Fetcher};
             use hashtree_core::to_hex;
@@ -801,7 +989,7 @@ pub(crate) async fn run() -> Result<()> {
             // Resolve npub/repo or htree:// URLs to CID
             let resolved = resolve_cid_input(&cid_input).await?;
             let store = HashtreeStore::new(&data_dir)?;
-            store.pin(&resolved.cid.hash)?;
+            store.pin_cid(&resolved.cid)?;
             let nhash =
                 nhash_encode(&resolved.cid.hash).unwrap_or_else(|_| to_hex(&resolved.cid.hash));
             println!("Pinned: {}", nhash);
@@ -817,6 +1005,60 @@ pub(crate) async fn run() -> Result<()> {
                 nhash_encode(&resolved.cid.hash).unwrap_or_else(|_| to_hex(&resolved.cid.hash));
             println!("Unpinned: {}", nhash);
         }
+        Commands::Diff { old, new, json } => {
+            use hashtree_cli::{FetchConfig, Fetcher};
+            use hashtree_core::PathChange;
+
+            let store = Arc::new(HashtreeStore::new(&data_dir)?);
+            let fetcher = Fetcher::new(FetchConfig::default());
+
+            let mut roots = Vec::with_capacity(2);
+            for input in [&old, &new] {
+                let resolved = resolve_cid_input(input).await?;
+                fetcher.fetch_tree(&store, None, &resolved.cid.hash).await?;
+                let cid = match resolved.path {
+                    Some(ref path) => store
+                        .resolve_path(&resolved.cid, path)?
+                        .ok_or_else(|| anyhow::anyhow!("Path not found in tree: {}", path))?,
+                    None => resolved.cid,
+                };
+                roots.push(cid);
+            }
+
+            let changes = store.diff_paths(&roots[0], &roots[1])?;
+            if json {
+                let entries: Vec<serde_json::Value> = changes
+                    .iter()
+                    .map(|change| match change {
+                        PathChange::Added(path) => {
+                            serde_json::json!({ "type": "added", "path": path })
+                        }
+                        PathChange::Removed(path) => {
+                            serde_json::json!({ "type": "removed", "path": path })
+                        }
+                        PathChange::Modified(path, old_cid, new_cid) => serde_json::json!({
+                            "type": "modified",
+                            "path": path,
+                            "old": old_cid.to_string(),
+                            "new": new_cid.to_string(),
+                        }),
+                        PathChange::Renamed(from, to) => {
+                            serde_json::json!({ "type": "renamed", "from": from, "to": to })
+                        }
+                    })
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&entries)?);
+            } else {
+                for change in &changes {
+                    match change {
+                        PathChange::Added(path) => println!("A  {}", path),
+                        PathChange::Removed(path) => println!("D  {}", path),
+                        PathChange::Modified(path, _, _) => println!("M  {}", path),
+                        PathChange::Renamed(from, to) => println!("R  {} -> {}", from, to),
+                    }
+                }
+            }
+        }
         Commands::Info { cid: cid_input } => {
             use hashtree_core::{nhash_encode, to_hex};
 
@@ -907,19 +1149,41 @@ pub(crate) async fn run() -> Result<()> {
                 Err(_) => {
                     eprintln!("Daemon not running at {}", addr);
                     eprintln!("Start with: htree start");
+
+                    // Interrupted fetches are still on disk; re-running them resumes
+                    let store = HashtreeStore::new(&data_dir)?;
+                    let jobs: Vec<serde_json::Value> = store
+                        .list_fetch_jobs()?
+                        .iter()
+                        .map(|job| job.status_json())
+                        .collect();
+                    if !jobs.is_empty() {
+                        println!("{}", format_fetch_jobs(&jobs).join("\n"));
+                    }
                 }
             }
         }
         Commands::Stop { pid_file } => {
             stop_daemon(pid_file.as_ref())?;
         }
-        Commands::Gc => {
+        Commands::Gc { dry_run } => {
             let store = HashtreeStore::new(&data_dir)?;
-            println!("Running garbage collection...");
-            let gc_stats = store.gc()?;
-            println!("Deleted {} DAGs", gc_stats.deleted_dags);
+            if dry_run {
+                println!("Running garbage collection (dry run)...");
+            } else {
+                println!("Running garbage collection...");
+            }
+            let gc_stats = store.gc_with_options(dry_run)?;
+            println!(
+                "Walked {} roots, {} reachable DAGs kept",
+                gc_stats.roots, gc_stats.reachable_dags
+            );
+            let verb = if dry_run { "Would delete" } else { "Deleted" };
+            println!("{} {} DAGs", verb, gc_stats.deleted_dags);
+            let verb = if dry_run { "Would free" } else { "Freed" };
             println!(
-                "Freed {} bytes ({:.2} KB)",
+                "{} {} bytes ({:.2} KB)",
+                verb,
                 gc_stats.freed_bytes,
                 gc_stats.freed_bytes as f64 / 1024.0
             );
@@ -983,8 +1247,15 @@ pub(crate) async fn run() -> Result<()> {
             ref_name,
             hash,
             key,
+            add_writers,
+            remove_writers,
         } => {
-            use hashtree_core::{from_hex, key_from_hex, Cid};
+            use hashtree_cli::RootVersion;
+            use hashtree_core::{from_hex, key_from_hex, to_hex, Cid};
+
+            if hash.is_none() && add_writers.is_empty() && remove_writers.is_empty() {
+                anyhow::bail!("Nothing to publish: pass a hash or --add-writer/--remove-writer");
+            }
 
             // Load config for relay list
             let config = Config::load()?;
@@ -1001,19 +1272,6 @@ pub(crate) async fn run() -> Result<()> {
                 println!("Identity: {} (new)", npub);
             }
 
-            // Parse hash and optional key
-            let hash_bytes = from_hex(&hash).context("Invalid hash (expected hex)")?;
-            let key_bytes = key
-                .as_ref()
-                .map(|k| key_from_hex(k))
-                .transpose()
-                .map_err(|e| anyhow::anyhow!("Invalid key: {}", e))?;
-
-            let cid = Cid {
-                hash: hash_bytes,
-                key: key_bytes,
-            };
-
             // Create resolver config with secret key for publishing
             let resolver_config = NostrResolverConfig {
                 relays: config.nostr.relays.clone(),
@@ -1026,22 +1284,110 @@ pub(crate) async fn run() -> Result<()> {
                 .await
                 .context("Failed to create Nostr resolver")?;
 
-            // Build Nostr key: "npub.../ref_name"
-            let nostr_key = format!("{}/{}", npub, ref_name);
+            // Build Nostr key: "npub.../ref_name", unless publishing to
+            // another owner's tree as a delegated writer
+            let nostr_key = if ref_name.starts_with("npub1") && ref_name.contains('/') {
+                ref_name.clone()
+            } else {
+                format!("{}/{}", npub, ref_name)
+            };
 
-            // Publish
-            match resolver.publish(&nostr_key, &cid).await {
-                Ok(_) => {
-                    println!("Published: {}", nostr_key);
-                    println!("  hash: {}", hash);
-                    if let Some(k) = key {
-                        println!("  key:  {}", k);
-                    }
+            if !add_writers.is_empty() || !remove_writers.is_empty() {
+                let removed = remove_writers
+                    .iter()
+                    .map(|w| parse_npub(w))
+                    .collect::<Result<Vec<_>>>()?;
+                let mut writers = resolver
+                    .writers(&nostr_key)
+                    .await
+                    .context("Failed to fetch writer list")?;
+                for writer in &add_writers {
+                    parse_npub(writer)?;
+                    writers.push(writer.clone());
                 }
-                Err(e) => {
-                    eprintln!("Publish failed: {}", e);
+                let mut seen = HashSet::new();
+                writers.retain(|w| {
+                    parse_npub(w).is_ok_and(|pk| !removed.contains(&pk) && seen.insert(pk))
+                });
+
+                if let Err(e) = resolver.set_writers(&nostr_key, &writers).await {
+                    eprintln!("Updating writers failed: {}", e);
                     std::process::exit(1);
                 }
+                println!("Writers for {}:", nostr_key);
+                if writers.is_empty() {
+                    println!("  (owner only)");
+                }
+                for writer in &writers {
+                    println!("  {}", writer);
+                }
+            }
+
+            if let Some(hash) = hash {
+                // Parse hash and optional key
+                let hash_bytes = from_hex(&hash).context("Invalid hash (expected hex)")?;
+                let key_bytes = key
+                    .as_ref()
+                    .map(|k| key_from_hex(k))
+                    .transpose()
+                    .map_err(|e| anyhow::anyhow!("Invalid key: {}", e))?;
+
+                let cid = Cid {
+                    hash: hash_bytes,
+                    key: key_bytes,
+                };
+
+                // Local root map first, so offline setups still get the update
+                if let Some(root_map) = config.resolver.file_resolver() {
+                    root_map
+                        .publish(&nostr_key, &cid)
+                        .await
+                        .context("Failed to update root map")?;
+                    println!("Root map: {} -> {}", nostr_key, root_map.path().display());
+                }
+
+                // Link the new root to the one it replaces
+                let prev = match resolver.resolve(&nostr_key).await {
+                    Ok(Some(current)) => Some(current.hash),
+                    _ => None,
+                };
+
+                // Publish
+                match resolver
+                    .publish_with_prev(&nostr_key, &cid, prev.as_ref())
+                    .await
+                {
+                    Ok(_) => {
+                        println!("Published: {}", nostr_key);
+                        println!("  hash: {}", hash);
+                        if let Some(k) = &key {
+                            println!("  key:  {}", k);
+                        }
+                        if let Some(prev) = prev.filter(|prev| *prev != cid.hash) {
+                            println!("  prev: {}", to_hex(&prev));
+                        }
+
+                        let (pubkey_hex, tree_name) = parse_tree_key(&nostr_key)?;
+                        let store = HashtreeStore::new(&data_dir)?;
+                        store.record_root_version(
+                            &pubkey_hex,
+                            &tree_name,
+                            RootVersion {
+                                hash: hash.clone(),
+                                key: key.clone(),
+                                created_at: std::time::SystemTime::now()
+                                    .duration_since(std::time::UNIX_EPOCH)
+                                    .unwrap_or_default()
+                                    .as_secs(),
+                                prev: prev.map(|prev| to_hex(&prev)),
+                            },
+                        )?;
+                    }
+                    Err(e) => {
+                        eprintln!("Publish failed: {}", e);
+                        std::process::exit(1);
+                    }
+                }
             }
 
             // Clean up
@@ -1201,6 +1547,25 @@ pub(crate) async fn run() -> Result<()> {
                         }
                     }
                 }
+                StorageCommands::Jobs => {
+                    let jobs: Vec<serde_json::Value> = store
+                        .list_fetch_jobs()?
+                        .iter()
+                        .map(|job| job.status_json())
+                        .collect();
+                    if jobs.is_empty() {
+                        println!("No unfinished fetches");
+                    } else {
+                        println!("{}", format_fetch_jobs(&jobs).join("\n"));
+                    }
+                }
+                StorageCommands::Cancel { id } => {
+                    if store.delete_fetch_job(&id)? {
+                        println!("Cancelled fetch {}", id);
+                    } else {
+                        anyhow::bail!("No fetch job with ID {}", id);
+                    }
+                }
                 StorageCommands::Evict => {
                     println!("Running eviction...");
                     let freed = store.evict_if_needed()?;
@@ -1273,9 +1638,10 @@ pub(crate) async fn run() -> Result<()> {
                 }
             }
         }
-        Commands::Peer { addr } => {
-            list_peers(&addr).await?;
-        }
+        Commands::Peer { addr, command } => match command {
+            None => list_peers(&addr).await?,
+            Some(command) => manage_peer_access(command)?,
+        },
         Commands::Pr { command } => match command {
             PrCommands::Create {
                 repo,
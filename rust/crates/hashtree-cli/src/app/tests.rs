@@ -1,9 +1,11 @@
-use super::daemonize::{build_daemon_args, parse_pid, read_pid_file, write_pid_file};
+use super::daemonize::{
+    build_daemon_args, format_daemon_status, parse_pid, read_pid_file, write_pid_file,
+};
 use super::lists::{
     build_mute_list_event, load_mute_entries, update_hex_list_file,
     update_mute_list_file_with_status, MuteEntry, MuteUpdate,
 };
-use super::resolve::resolve_cid_input;
+use super::resolve::{parse_tree_key, resolve_cid_input};
 use nostr::Kind;
 use std::path::PathBuf;
 
@@ -41,6 +43,27 @@ fn test_build_daemon_args_minimal() {
     assert_eq!(args, vec!["--addr", "0.0.0.0:8080"]);
 }
 
+#[test]
+fn test_format_daemon_status_lists_fetch_jobs() {
+    let status = serde_json::json!({
+        "status": "running",
+        "fetch_jobs": [{
+            "id": "0123456789abcdef",
+            "root": "0123456789abcdef0123",
+            "bytes_done": 2048,
+            "total_bytes": 4096,
+            "pending": 7,
+            "eta_secs": 12,
+        }],
+    });
+    let text = format_daemon_status(&status, false);
+
+    assert!(text.contains("Fetch jobs (1):"));
+    assert!(
+        text.contains("  0123456789abcdef 0123456789ab... 2.00 KB / 4.00 KB - 7 pending - ETA 12s")
+    );
+}
+
 #[test]
 fn test_parse_pid() {
     assert_eq!(parse_pid("123\n").unwrap(), 123);
@@ -230,3 +253,40 @@ async fn test_resolve_hex_cid_without_key() {
     assert_eq!(resolved.cid.hash, hash);
     assert!(resolved.cid.key.is_none());
 }
+
+#[test]
+fn test_parse_tree_key() {
+    let keys = nostr::Keys::generate();
+    let npub = nostr::ToBech32::to_bech32(&keys.public_key()).unwrap();
+
+    let (pubkey_hex, tree_name) = parse_tree_key(&format!("{}/docs", npub)).unwrap();
+    assert_eq!(pubkey_hex, keys.public_key().to_hex());
+    assert_eq!(tree_name, "docs");
+
+    assert!(parse_tree_key(&npub).is_err());
+    assert!(parse_tree_key(&format!("{}/", npub)).is_err());
+    assert!(parse_tree_key("notanpub/docs").is_err());
+}
+
+#[cfg(feature = "p2p")]
+#[test]
+fn test_parse_peer_pubkey_accepts_npub_hex_and_address() {
+    use super::peers::parse_peer_pubkey;
+
+    let keys = nostr::Keys::generate();
+    let hex = keys.public_key().to_hex();
+    let npub = nostr::ToBech32::to_bech32(&keys.public_key()).unwrap();
+
+    assert_eq!(parse_peer_pubkey(&npub).unwrap(), hex);
+    assert_eq!(parse_peer_pubkey(&hex).unwrap(), hex);
+    assert!(parse_peer_pubkey("npub1nope").is_err());
+    assert!(parse_peer_pubkey("abcd").is_err());
+
+    // WebSocket peers are keyed by host:port
+    assert_eq!(parse_peer_pubkey("10.0.0.2:8080").unwrap(), "10.0.0.2:8080");
+    assert_eq!(
+        parse_peer_pubkey("ws://10.0.0.2:8080/ws").unwrap(),
+        "10.0.0.2:8080"
+    );
+    assert!(parse_peer_pubkey("abcd:notaport").is_err());
+}
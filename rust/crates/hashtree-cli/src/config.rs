@@ -1,8 +1,14 @@
 use anyhow::{Context, Result};
+use hashtree_resolver::composite::{CompositeResolver, ResolvePolicy};
+use hashtree_resolver::file::{FileResolverConfig, FileRootResolver};
+use hashtree_resolver::http::{HttpResolverConfig, HttpRootResolver};
+use hashtree_resolver::RootResolver;
 use nostr::nips::nip19::{FromBech32, ToBech32};
 use nostr::{Keys, SecretKey};
 use serde::{Deserialize, Serialize};
 use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -16,6 +22,10 @@ pub struct Config {
     pub blossom: BlossomConfig,
     #[serde(default)]
     pub sync: SyncConfig,
+    #[serde(default)]
+    pub resolver: ResolverConfig,
+    #[serde(default)]
+    pub bandwidth: BandwidthConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -37,6 +47,14 @@ pub struct ServerConfig {
     /// Allow public access to social graph snapshot endpoint (default: false)
     #[serde(default = "default_socialgraph_snapshot_public")]
     pub socialgraph_snapshot_public: bool,
+    /// Other daemons to connect to directly over their `/ws` endpoint
+    /// (e.g. "ws://10.0.0.2:8080/ws"), without WebRTC
+    #[serde(default)]
+    pub ws_peers: Vec<String>,
+    /// Announce this daemon on the local network over UDP multicast and
+    /// connect to other daemons found there
+    #[serde(default)]
+    pub lan_discovery: bool,
 }
 
 fn default_public_writes() -> bool {
@@ -165,6 +183,155 @@ pub struct SyncConfig {
     pub blossom_timeout_ms: u64,
 }
 
+/// Root maps consulted alongside Nostr when resolving named trees
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolverConfig {
+    /// Local JSON or TOML file of key -> CID (`htree publish` also writes here)
+    #[serde(default)]
+    pub file: Option<String>,
+    /// URL of a JSON document of key -> CID
+    #[serde(default)]
+    pub http: Option<String>,
+    /// How often root maps are re-read for subscriptions, in milliseconds
+    #[serde(default = "default_resolver_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+    /// How answers from several backends are combined:
+    /// "first-wins", "newest-wins" or "quorum:<n>"
+    #[serde(default = "default_resolver_policy")]
+    pub policy: String,
+    /// Per-backend lookup timeout, in milliseconds
+    #[serde(default = "default_resolver_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Remember last-known roots on disk and serve them when backends are down
+    #[serde(default = "default_resolver_cache")]
+    pub cache: bool,
+}
+
+impl ResolverConfig {
+    /// File resolver for the configured root map, if any
+    pub fn file_resolver(&self) -> Option<FileRootResolver> {
+        let path = self.file.as_ref()?;
+        Some(FileRootResolver::new(
+            FileResolverConfig::new(path)
+                .with_poll_interval(Duration::from_millis(self.poll_interval_ms)),
+        ))
+    }
+
+    /// Configured file/HTTP resolvers, in lookup order
+    pub fn resolvers(&self) -> Result<Vec<Box<dyn RootResolver>>> {
+        let mut resolvers: Vec<Box<dyn RootResolver>> = Vec::new();
+        if let Some(file) = self.file_resolver() {
+            resolvers.push(Box::new(file));
+        }
+        if let Some(url) = &self.http {
+            let http = HttpRootResolver::new(
+                HttpResolverConfig::new(url)
+                    .with_poll_interval(Duration::from_millis(self.poll_interval_ms)),
+            )
+            .context("Failed to create HTTP resolver")?;
+            resolvers.push(Box::new(http));
+        }
+        Ok(resolvers)
+    }
+
+    /// Composite of the root maps plus an optional Nostr backend, in that order
+    pub fn composite(&self, nostr: Option<Arc<dyn RootResolver>>) -> Result<CompositeResolver> {
+        let policy: ResolvePolicy = self.policy.parse()?;
+        let mut composite =
+            CompositeResolver::new(policy).with_timeout(Duration::from_millis(self.timeout_ms));
+        if let Some(file) = self.file_resolver() {
+            composite = composite.with_backend("file", Arc::new(file));
+        }
+        if let Some(url) = &self.http {
+            let http = HttpRootResolver::new(
+                HttpResolverConfig::new(url)
+                    .with_poll_interval(Duration::from_millis(self.poll_interval_ms))
+                    .with_timeout(Duration::from_millis(self.timeout_ms)),
+            )
+            .context("Failed to create HTTP resolver")?;
+            composite = composite.with_backend("http", Arc::new(http));
+        }
+        if let Some(nostr) = nostr {
+            composite = composite.with_backend("nostr", nostr);
+        }
+        if self.cache {
+            composite = composite.with_cache(get_root_cache_path());
+        }
+        Ok(composite)
+    }
+}
+
+/// Limits on data served to P2P peers, in KiB/s (0 = unlimited)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BandwidthConfig {
+    /// Upload limit across all peers
+    #[serde(default)]
+    pub upload_kbps: u64,
+    /// Upload limit for each peer
+    #[serde(default)]
+    pub peer_upload_kbps: u64,
+    /// Limit on data fetched from other peers to answer forwarded requests
+    #[serde(default)]
+    pub forward_kbps: u64,
+    /// Forwarding limit for each requesting peer
+    #[serde(default)]
+    pub peer_forward_kbps: u64,
+    /// Only serve peers that download far more than they upload from
+    /// bandwidth other peers leave unused
+    #[serde(default)]
+    pub reciprocity: bool,
+}
+
+impl ServerConfig {
+    /// LAN discovery settings announcing the `/ws` port of `bind_address`,
+    /// if enabled
+    #[cfg(feature = "p2p")]
+    pub fn lan_discovery(&self, bind_address: &str) -> Option<hashtree_webrtc::LanDiscoveryConfig> {
+        if !self.lan_discovery {
+            return None;
+        }
+        let config = hashtree_webrtc::LanDiscoveryConfig::default();
+        // An ephemeral port isn't known yet; peers then only learn our id
+        Some(
+            match bind_address.rsplit(':').next().and_then(|p| p.parse().ok()) {
+                Some(port) if port != 0 => config.with_endpoint_port(port),
+                _ => config,
+            },
+        )
+    }
+}
+
+impl BandwidthConfig {
+    /// Limits in bytes/sec for the WebRTC layer
+    #[cfg(feature = "p2p")]
+    pub fn limits(&self) -> hashtree_webrtc::BandwidthConfig {
+        hashtree_webrtc::BandwidthConfig {
+            upload_bps: self.upload_kbps * 1024,
+            peer_upload_bps: self.peer_upload_kbps * 1024,
+            forward_bps: self.forward_kbps * 1024,
+            peer_forward_bps: self.peer_forward_kbps * 1024,
+            reciprocity: self.reciprocity,
+            ..Default::default()
+        }
+    }
+}
+
+fn default_resolver_poll_interval_ms() -> u64 {
+    1000
+}
+
+fn default_resolver_policy() -> String {
+    "first-wins".to_string()
+}
+
+fn default_resolver_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_resolver_cache() -> bool {
+    true
+}
+
 fn default_sync_enabled() -> bool {
     true
 }
@@ -250,6 +417,8 @@ impl Default for ServerConfig {
             enable_webrtc: default_enable_webrtc(),
             public_writes: default_public_writes(),
             socialgraph_snapshot_public: default_socialgraph_snapshot_public(),
+            ws_peers: Vec::new(),
+            lan_discovery: false,
         }
     }
 }
@@ -302,6 +471,19 @@ impl Default for SyncConfig {
     }
 }
 
+impl Default for ResolverConfig {
+    fn default() -> Self {
+        Self {
+            file: None,
+            http: None,
+            poll_interval_ms: default_resolver_poll_interval_ms(),
+            policy: default_resolver_policy(),
+            timeout_ms: default_resolver_timeout_ms(),
+            cache: default_resolver_cache(),
+        }
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -310,6 +492,8 @@ impl Default for Config {
             nostr: NostrConfig::default(),
             blossom: BlossomConfig::default(),
             sync: SyncConfig::default(),
+            resolver: ResolverConfig::default(),
+            bandwidth: BandwidthConfig::default(),
         }
     }
 }
@@ -346,7 +530,10 @@ impl Config {
 }
 
 // Re-export path functions from hashtree_config
-pub use hashtree_config::{get_auth_cookie_path, get_config_path, get_hashtree_dir, get_keys_path};
+pub use hashtree_config::{
+    get_auth_cookie_path, get_config_path, get_hashtree_dir, get_keys_path, get_peer_access_path,
+    get_peer_reputation_path, get_root_cache_path,
+};
 
 /// Generate and save auth cookie if it doesn't exist
 pub fn ensure_auth_cookie() -> Result<(String, String)> {
@@ -565,6 +752,107 @@ max_write_distance = 5
         assert_eq!(config.nostr.spambox_max_size_gb, 1);
     }
 
+    #[test]
+    fn test_resolver_config_deserialize() {
+        let config: Config = toml::from_str("").unwrap();
+        assert!(config.resolver.file.is_none());
+        assert!(config.resolver.resolvers().unwrap().is_empty());
+
+        let toml_str = r#"
+[resolver]
+file = "/etc/hashtree/roots.toml"
+http = "https://example.com/roots.json"
+"#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert_eq!(
+            config.resolver.file.as_deref(),
+            Some("/etc/hashtree/roots.toml")
+        );
+        assert_eq!(config.resolver.poll_interval_ms, 1000);
+        assert_eq!(config.resolver.resolvers().unwrap().len(), 2);
+        assert_eq!(config.resolver.policy, "first-wins");
+        assert!(config.resolver.cache);
+
+        let config: Config = toml::from_str(
+            r#"
+[resolver]
+file = "/etc/hashtree/roots.toml"
+policy = "quorum:2"
+cache = false
+"#,
+        )
+        .unwrap();
+        let composite = config.resolver.composite(None).unwrap();
+        assert_eq!(composite.policy(), ResolvePolicy::Quorum(2));
+        assert_eq!(composite.backend_names(), vec!["file"]);
+
+        let mut bad = config.resolver.clone();
+        bad.policy = "fastest".into();
+        assert!(bad.composite(None).is_err());
+    }
+
+    #[test]
+    fn test_server_lan_discovery_config() {
+        let config: Config = toml::from_str("").unwrap();
+        assert!(!config.server.lan_discovery);
+        assert!(config.server.ws_peers.is_empty());
+
+        let config: Config = toml::from_str(
+            r#"
+[server]
+lan_discovery = true
+ws_peers = ["ws://10.0.0.2:8080/ws"]
+"#,
+        )
+        .unwrap();
+        assert!(config.server.lan_discovery);
+        assert_eq!(config.server.ws_peers, vec!["ws://10.0.0.2:8080/ws"]);
+
+        #[cfg(feature = "p2p")]
+        {
+            let lan = config.server.lan_discovery("0.0.0.0:8080").unwrap();
+            assert_eq!(lan.endpoint_port, Some(8080));
+            let lan = config.server.lan_discovery("127.0.0.1:0").unwrap();
+            assert_eq!(lan.endpoint_port, None);
+            assert!(Config::default()
+                .server
+                .lan_discovery("0.0.0.0:8080")
+                .is_none());
+        }
+    }
+
+    #[test]
+    fn test_bandwidth_config_deserialize() {
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config.bandwidth.upload_kbps, 0);
+        assert!(!config.bandwidth.reciprocity);
+
+        let config: Config = toml::from_str(
+            r#"
+[bandwidth]
+upload_kbps = 512
+peer_upload_kbps = 64
+forward_kbps = 128
+reciprocity = true
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.bandwidth.upload_kbps, 512);
+        assert_eq!(config.bandwidth.peer_upload_kbps, 64);
+        assert_eq!(config.bandwidth.forward_kbps, 128);
+        assert_eq!(config.bandwidth.peer_forward_kbps, 0);
+        assert!(config.bandwidth.reciprocity);
+
+        #[cfg(feature = "p2p")]
+        {
+            let limits = config.bandwidth.limits();
+            assert_eq!(limits.upload_bps, 512 * 1024);
+            assert_eq!(limits.peer_upload_bps, 64 * 1024);
+            assert_eq!(limits.forward_bps, 128 * 1024);
+            assert_eq!(limits.peer_forward_bps, 0);
+        }
+    }
+
     #[test]
     fn test_auth_cookie_generation() -> Result<()> {
         let temp_dir = TempDir::new()?;
@@ -9,7 +9,9 @@ use nostr::nips::nip19::ToBech32;
 use tokio::net::TcpListener;
 use tower_http::cors::CorsLayer;
 
-use crate::config::{ensure_keys, parse_npub, pubkey_bytes, Config};
+use crate::config::{
+    ensure_keys, get_peer_access_path, get_peer_reputation_path, parse_npub, pubkey_bytes, Config,
+};
 use crate::nostr_relay::{NostrRelay, NostrRelayConfig};
 use crate::server::{AppState, HashtreeServer};
 use crate::socialgraph;
@@ -143,9 +145,22 @@ pub async fn start_embedded(opts: EmbeddedDaemonOptions) -> Result<EmbeddedDaemo
 
     #[cfg(feature = "p2p")]
     let webrtc_state: Option<Arc<WebRTCState>> = {
-        let (webrtc_state, webrtc_handle) = if config.server.enable_webrtc {
+        let (webrtc_state, webrtc_handle) = if config.server.enable_webrtc
+            || !config.server.ws_peers.is_empty()
+            || config.server.lan_discovery
+        {
             let webrtc_config = WebRTCConfig {
-                relays: config.nostr.relays.clone(),
+                // WebSocket and LAN peers alone don't need Nostr signaling
+                relays: if config.server.enable_webrtc {
+                    config.nostr.relays.clone()
+                } else {
+                    Vec::new()
+                },
+                peer_reputation_path: Some(get_peer_reputation_path()),
+                peer_access_path: Some(get_peer_access_path()),
+                bandwidth: config.bandwidth.limits(),
+                ws_peers: config.server.ws_peers.clone(),
+                lan_discovery: config.server.lan_discovery(&opts.bind_address),
                 ..Default::default()
             };
 
@@ -8,17 +8,20 @@
 use anyhow::Result;
 use hashtree_blossom::BlossomClient;
 use hashtree_config::detect_local_daemon_url;
-use hashtree_core::{decode_tree_node, to_hex};
+use hashtree_core::{decode_tree_node, from_hex, to_hex, LinkType};
 use nostr::Keys;
 use std::collections::VecDeque;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tracing::debug;
 
 use crate::config::Config as CliConfig;
-use crate::storage::HashtreeStore;
+use crate::storage::{FetchJob, HashtreeStore};
 use crate::webrtc::WebRTCState;
 
+/// How often `fetch_tree_parallel` saves its frontier
+pub const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5);
+
 /// Configuration for remote fetching
 #[derive(Clone)]
 pub struct FetchConfig {
@@ -63,6 +66,12 @@ impl Fetcher {
         Self { config, blossom }
     }
 
+    /// Create a fetcher around an already configured BlossomClient
+    pub fn with_blossom(config: FetchConfig, blossom: BlossomClient) -> Self {
+        let blossom = blossom.with_timeout(config.blossom_timeout);
+        Self { config, blossom }
+    }
+
     /// Get the underlying BlossomClient
     pub fn blossom(&self) -> &BlossomClient {
         &self.blossom
@@ -146,7 +155,12 @@ impl Fetcher {
 
     /// Fetch an entire tree with parallel downloads
     /// Uses work-stealing: always keeps `concurrency` requests in flight
-    /// Returns (chunks_fetched, bytes_fetched)
+    ///
+    /// Progress is checkpointed as a `FetchJob` every `CHECKPOINT_INTERVAL`;
+    /// calling this again for the same root resumes from the saved frontier.
+    /// Deleting the job (`HashtreeStore::delete_fetch_job`) cancels the fetch
+    /// at its next checkpoint.
+    /// Returns (chunks_fetched, bytes_fetched) for this run
     pub async fn fetch_tree_parallel(
         &self,
         store: &HashtreeStore,
@@ -155,33 +169,69 @@ impl Fetcher {
         concurrency: usize,
     ) -> Result<(usize, u64)> {
         use futures::stream::{FuturesUnordered, StreamExt};
-        use std::collections::HashSet;
-        use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+        use std::collections::{HashMap, HashSet};
 
-        // Check if we already have the root
-        if store.blob_exists(root_hash)? {
-            return Ok((0, 0));
-        }
+        let mut job = match store.get_fetch_job(&FetchJob::id_for(root_hash))? {
+            Some(job) if job.root == to_hex(root_hash) => {
+                debug!("Resuming fetch {} ({} pending)", job.id, job.frontier.len());
+                job
+            }
+            _ => {
+                // Without an unfinished job, a local root means a complete tree
+                if store.blob_exists(root_hash)? {
+                    return Ok((0, 0));
+                }
+                let job = FetchJob::new(root_hash);
+                store.put_fetch_job(&job)?;
+                job
+            }
+        };
 
-        let chunks_fetched = Arc::new(AtomicUsize::new(0));
-        let bytes_fetched = Arc::new(AtomicU64::new(0));
+        let mut chunks_fetched = 0usize;
+        let mut bytes_fetched = 0u64;
 
         // Track what we've queued to avoid duplicates
         let mut queued: HashSet<[u8; 32]> = HashSet::new();
-        let mut pending: VecDeque<[u8; 32]> = VecDeque::new();
-
-        // Seed with root
-        pending.push_back(*root_hash);
-        queued.insert(*root_hash);
+        // (hash, known leaf blob)
+        let mut pending: VecDeque<([u8; 32], bool)> = VecDeque::new();
+        // Requests in flight, kept for checkpoints
+        let mut in_flight: HashMap<[u8; 32], bool> = HashMap::new();
+        // Downloads that failed, left in the job for a later retry
+        let mut failed: Vec<([u8; 32], bool)> = Vec::new();
+
+        // Seed with the saved frontier
+        for (hash_hex, leaf) in &job.frontier {
+            if let Ok(hash) = from_hex(hash_hex) {
+                if queued.insert(hash) {
+                    pending.push_back((hash, *leaf));
+                }
+            }
+        }
 
         let mut active = FuturesUnordered::new();
+        let mut last_checkpoint = Instant::now();
 
         loop {
             // Fill up to concurrency limit from pending queue
-            while active.len() < concurrency {
-                if let Some(hash) = pending.pop_front() {
-                    // Skip if we already have it locally
-                    if store.blob_exists(&hash).unwrap_or(false) {
+            while active.len() < concurrency && !pending.is_empty() {
+                // Check the next batch against local storage in one call
+                let take = pending.len().min(concurrency - active.len());
+                let batch: Vec<([u8; 32], bool)> = pending.drain(..take).collect();
+                let hashes: Vec<[u8; 32]> = batch.iter().map(|(hash, _)| *hash).collect();
+                let present = store
+                    .blobs_exist(&hashes)
+                    .unwrap_or_else(|_| vec![false; batch.len()]);
+
+                for ((hash, leaf), present) in batch.into_iter().zip(present) {
+                    if present {
+                        // Local leaves are complete, but a local tree node may
+                        // have been fetched after the last checkpoint - expand it
+                        if !leaf {
+                            if let Some(data) = store.get_blob(&hash)? {
+                                let found = queue_children(&data, &mut pending, &mut queued);
+                                add_total(&mut job, data.len() as u64 + found);
+                            }
+                        }
                         continue;
                     }
 
@@ -204,9 +254,8 @@ impl Fetcher {
                         let data = blossom.download(&hash_hex).await;
                         (hash, data)
                     };
+                    in_flight.insert(hash, leaf);
                     active.push(fut);
-                } else {
-                    break;
                 }
             }
 
@@ -217,35 +266,52 @@ impl Fetcher {
 
             // Wait for any download to complete
             if let Some((hash, result)) = active.next().await {
+                let leaf = in_flight.remove(&hash).unwrap_or(false);
                 match result {
                     Ok(data) => {
                         // Store it
                         store.put_blob(&data)?;
-                        chunks_fetched.fetch_add(1, Ordering::Relaxed);
-                        bytes_fetched.fetch_add(data.len() as u64, Ordering::Relaxed);
-
-                        // Parse as tree node and queue children
-                        if let Ok(node) = decode_tree_node(&data) {
-                            for link in node.links {
-                                if !queued.contains(&link.hash) {
-                                    queued.insert(link.hash);
-                                    pending.push_back(link.hash);
-                                }
-                            }
+                        chunks_fetched += 1;
+                        bytes_fetched += data.len() as u64;
+                        job.chunks_done += 1;
+                        job.bytes_done += data.len() as u64;
+
+                        // Leaves were counted when their parent was expanded;
+                        // anything else counts itself plus any leaves it links to
+                        if !leaf {
+                            let found = queue_children(&data, &mut pending, &mut queued);
+                            add_total(&mut job, data.len() as u64 + found);
                         }
                     }
                     Err(e) => {
                         debug!("Failed to fetch {}: {}", to_hex(&hash), e);
                         // Continue with other chunks - don't fail the whole tree
+                        failed.push((hash, leaf));
                     }
                 }
             }
+
+            if last_checkpoint.elapsed() >= CHECKPOINT_INTERVAL {
+                let mut frontier: Vec<([u8; 32], bool)> =
+                    pending.iter().chain(&failed).copied().collect();
+                frontier.extend(in_flight.iter().map(|(hash, leaf)| (*hash, *leaf)));
+                record_progress(&mut job, &frontier, last_checkpoint);
+                if !store.checkpoint_fetch_job(&job)? {
+                    anyhow::bail!("Fetch {} was cancelled", job.id);
+                }
+                last_checkpoint = Instant::now();
+            }
+        }
+
+        if failed.is_empty() {
+            store.delete_fetch_job(&job.id)?;
+        } else {
+            // Keep the job so a later run retries the misses
+            record_progress(&mut job, &failed, last_checkpoint);
+            store.checkpoint_fetch_job(&job)?;
         }
 
-        Ok((
-            chunks_fetched.load(Ordering::Relaxed),
-            bytes_fetched.load(Ordering::Relaxed),
-        ))
+        Ok((chunks_fetched, bytes_fetched))
     }
 
     /// Fetch a file by hash, fetching all chunks if needed
@@ -318,3 +384,48 @@ fn with_local_daemon_read(blossom: BlossomClient) -> BlossomClient {
     servers.insert(0, local_url);
     blossom.with_read_servers(servers)
 }
+
+/// Store a checkpoint's frontier and timing in the job
+fn record_progress(job: &mut FetchJob, frontier: &[([u8; 32], bool)], since: Instant) {
+    job.frontier = frontier
+        .iter()
+        .map(|(hash, leaf)| (to_hex(hash), *leaf))
+        .collect();
+    job.active_secs += since.elapsed().as_secs();
+    job.updated_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+}
+
+/// Grow the job's known size as the walk discovers more of the tree
+fn add_total(job: &mut FetchJob, bytes: u64) {
+    job.total_bytes = Some(job.total_bytes.unwrap_or(0) + bytes);
+}
+
+/// Queue the children of a tree node that haven't been seen yet
+///
+/// Only blob links of File nodes are known leaves. Directory entries made
+/// with `from_cid` are Blob links even when they point at subdirectories or
+/// chunked files, so those are fetched as tree nodes and expanded if they
+/// decode as one. Returns the size of the newly queued leaves.
+fn queue_children(
+    data: &[u8],
+    pending: &mut VecDeque<([u8; 32], bool)>,
+    queued: &mut std::collections::HashSet<[u8; 32]>,
+) -> u64 {
+    let Ok(node) = decode_tree_node(data) else {
+        return 0;
+    };
+    let mut leaf_bytes = 0;
+    for link in node.links {
+        let leaf = node.node_type == LinkType::File && link.link_type == LinkType::Blob;
+        if queued.insert(link.hash) {
+            if leaf {
+                leaf_bytes += link.size;
+            }
+            pending.push_back((link.hash, leaf));
+        }
+    }
+    leaf_bytes
+}
@@ -30,8 +30,9 @@ pub use hashtree_resolver::{
 };
 pub use server::HashtreeServer;
 pub use storage::{
-    CachedRoot, HashtreeStore, StorageByPriority, TreeMeta, PRIORITY_FOLLOWED, PRIORITY_OTHER,
-    PRIORITY_OWN,
+    import_archive_file, select_root_version, CachedRoot, FetchJob, HashtreeStore, RootVersion,
+    StorageByPriority, TreeMeta, GC_ROOT_HISTORY, MAX_ROOT_HISTORY, PRIORITY_FOLLOWED,
+    PRIORITY_OTHER, PRIORITY_OWN,
 };
 pub use sync::{BackgroundSync, SyncConfig, SyncPriority, SyncStatus, SyncTask};
 pub use webrtc::{ConnectionState, WebRTCState};
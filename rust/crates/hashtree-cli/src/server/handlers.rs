@@ -1,20 +1,23 @@
 use super::auth::AppState;
 use super::mime::get_mime_type;
+use super::site::{tree_path, SiteManifest, SiteRoute, SITE_MANIFEST};
 use super::ui::root_page;
 use crate::socialgraph;
 use crate::webrtc::{ConnectionState, WebRTCState};
 use axum::{
     body::Body,
     extract::{Multipart, Path, Query, State},
-    http::{header, Response, StatusCode},
+    http::{header, HeaderMap, Response, StatusCode, Uri},
     response::{IntoResponse, Json},
 };
 use bytes::Bytes;
 use futures::stream::{self, StreamExt};
 use hashtree_core::{
-    from_hex, nhash_decode, to_hex, Cid, HashTree, HashTreeConfig, LinkType, Store,
+    archive_stream, from_hex, is_nhash, nhash_decode, prove_range, to_hex, ArchiveFormat, Cid,
+    HashTree, HashTreeConfig, LinkType, ProofError, Store,
 };
 use hashtree_resolver::{
+    composite::CompositeResolver,
     nostr::{NostrResolverConfig, NostrRootResolver},
     RootResolver,
 };
@@ -159,6 +162,7 @@ async fn htree_nhash_impl(
     nhash: String,
     path: Option<String>,
     Query(params): Query<HashMap<String, String>>,
+    uri: Uri,
     headers: axum::http::HeaderMap,
     connect_info: axum::extract::ConnectInfo<std::net::SocketAddr>,
 ) -> Response<Body> {
@@ -187,6 +191,7 @@ async fn htree_nhash_impl(
     }
 
     let effective_path = path.filter(|p| !p.is_empty());
+    let want_proof = params.contains_key("proof");
 
     let store = state.store.store_arc();
     let tree = HashTree::new(HashTreeConfig::new(store).public());
@@ -199,6 +204,28 @@ async fn htree_nhash_impl(
     let is_dir = tree.is_dir(&cid).await.unwrap_or(false);
 
     if is_dir {
+        if let Some(format) = params.get("format") {
+            let name = effective_path
+                .as_deref()
+                .and_then(|p| p.trim_end_matches('/').rsplit('/').next())
+                .map(str::to_string)
+                .unwrap_or_else(|| to_hex(&cid.hash)[..12].to_string());
+            return serve_archive(&state, &cid, effective_path.as_deref(), format, &name, true)
+                .await;
+        }
+
+        let request = SiteRequest {
+            base: format!("/htree/{}", nhash),
+            path: effective_path.clone().unwrap_or_default(),
+            uri: &uri,
+            headers: headers.clone(),
+            is_immutable: true,
+            is_localhost,
+        };
+        if let Some(response) = try_serve_site(&state, &cid, request).await {
+            return response;
+        }
+
         if let Some(path) = effective_path.clone() {
             let entry = match tree.resolve_path(&cid, &path).await {
                 Ok(Some(entry)) => entry,
@@ -217,8 +244,16 @@ async fn htree_nhash_impl(
                         .unwrap();
                 }
             };
-            return serve_cid_with_range(&state, &entry, headers, true, is_localhost, Some(&path))
-                .await;
+            return serve_cid_with_range(
+                &state,
+                &entry,
+                headers,
+                true,
+                is_localhost,
+                Some(&path),
+                want_proof,
+            )
+            .await;
         }
 
         return list_directory_json(&state, &cid, true, is_localhost).await;
@@ -241,6 +276,7 @@ async fn htree_nhash_impl(
         true,
         is_localhost,
         effective_path.as_deref(),
+        want_proof,
     )
     .await
 }
@@ -249,6 +285,7 @@ pub async fn htree_nhash(
     State(state): State<AppState>,
     Path(nhash): Path<String>,
     Query(params): Query<HashMap<String, String>>,
+    uri: Uri,
     headers: axum::http::HeaderMap,
     connect_info: axum::extract::ConnectInfo<std::net::SocketAddr>,
 ) -> impl IntoResponse {
@@ -258,6 +295,7 @@ pub async fn htree_nhash(
         full,
         None,
         Query(params),
+        uri,
         headers,
         connect_info,
     )
@@ -268,6 +306,7 @@ pub async fn htree_nhash_path(
     State(state): State<AppState>,
     Path((nhash, path)): Path<(String, String)>,
     Query(params): Query<HashMap<String, String>>,
+    uri: Uri,
     headers: axum::http::HeaderMap,
     connect_info: axum::extract::ConnectInfo<std::net::SocketAddr>,
 ) -> impl IntoResponse {
@@ -277,6 +316,7 @@ pub async fn htree_nhash_path(
         full,
         Some(path),
         Query(params),
+        uri,
         headers,
         connect_info,
     )
@@ -378,12 +418,14 @@ async fn htree_npub_impl(
     treename: String,
     path: Option<String>,
     Query(params): Query<HashMap<String, String>>,
+    uri: Uri,
     headers: axum::http::HeaderMap,
     connect_info: axum::extract::ConnectInfo<std::net::SocketAddr>,
 ) -> Response<Body> {
     let is_localhost = connect_info.0.ip().is_loopback();
     let key = format!("{}/{}", npub, treename);
     let link_key = parse_hex_key(params.get("k"));
+    let want_proof = params.contains_key("proof");
 
     let resolver = match NostrRootResolver::new(resolver_config()).await {
         Ok(r) => r,
@@ -438,6 +480,39 @@ async fn htree_npub_impl(
     }
 
     let mut effective_path = path.filter(|p| !p.is_empty());
+    let is_dir = tree.is_dir(&cid).await.unwrap_or(false);
+
+    if is_dir {
+        if let Some(format) = params.get("format") {
+            let name = effective_path
+                .as_deref()
+                .and_then(|p| p.trim_end_matches('/').rsplit('/').next())
+                .unwrap_or(&treename)
+                .to_string();
+            return serve_archive(
+                &state,
+                &cid,
+                effective_path.as_deref(),
+                format,
+                &name,
+                false,
+            )
+            .await;
+        }
+
+        let request = SiteRequest {
+            base: format!("/htree/{}", key),
+            path: effective_path.clone().unwrap_or_default(),
+            uri: &uri,
+            headers: headers.clone(),
+            is_immutable: false,
+            is_localhost,
+        };
+        if let Some(response) = try_serve_site(&state, &cid, request).await {
+            return response;
+        }
+    }
+
     if let Some(path) = effective_path.clone() {
         if path == "thumbnail" || path.ends_with("/thumbnail") {
             if let Some(resolved_path) = resolve_thumbnail_path(&tree, &cid, &path).await {
@@ -446,7 +521,6 @@ async fn htree_npub_impl(
         }
     }
 
-    let is_dir = tree.is_dir(&cid).await.unwrap_or(false);
     if is_dir {
         if let Some(path) = effective_path.clone() {
             let entry = match tree.resolve_path(&cid, &path).await {
@@ -466,8 +540,16 @@ async fn htree_npub_impl(
                         .unwrap();
                 }
             };
-            return serve_cid_with_range(&state, &entry, headers, false, is_localhost, Some(&path))
-                .await;
+            return serve_cid_with_range(
+                &state,
+                &entry,
+                headers,
+                false,
+                is_localhost,
+                Some(&path),
+                want_proof,
+            )
+            .await;
         }
 
         return list_directory_json(&state, &cid, false, is_localhost).await;
@@ -480,6 +562,7 @@ async fn htree_npub_impl(
         false,
         is_localhost,
         effective_path.as_deref(),
+        want_proof,
     )
     .await
 }
@@ -488,6 +571,7 @@ pub async fn htree_npub(
     State(state): State<AppState>,
     Path((npub, treename)): Path<(String, String)>,
     Query(params): Query<HashMap<String, String>>,
+    uri: Uri,
     headers: axum::http::HeaderMap,
     connect_info: axum::extract::ConnectInfo<std::net::SocketAddr>,
 ) -> impl IntoResponse {
@@ -498,6 +582,7 @@ pub async fn htree_npub(
         treename,
         None,
         Query(params),
+        uri,
         headers,
         connect_info,
     )
@@ -508,6 +593,7 @@ pub async fn htree_npub_path(
     State(state): State<AppState>,
     Path((npub, treename, path)): Path<(String, String, String)>,
     Query(params): Query<HashMap<String, String>>,
+    uri: Uri,
     headers: axum::http::HeaderMap,
     connect_info: axum::extract::ConnectInfo<std::net::SocketAddr>,
 ) -> impl IntoResponse {
@@ -518,14 +604,264 @@ pub async fn htree_npub_path(
         treename,
         Some(path),
         Query(params),
+        uri,
         headers,
         connect_info,
     )
     .await
 }
 
+/// A directory request that may be served as a static site
+struct SiteRequest<'a> {
+    /// URL prefix of the tree root, e.g. `/htree/npub1.../docs`
+    base: String,
+    /// Path within the tree, without a leading slash
+    path: String,
+    uri: &'a Uri,
+    headers: HeaderMap,
+    is_immutable: bool,
+    is_localhost: bool,
+}
+
+/// Serve a directory tree as a website if it has a `.htree-site.json` manifest
+/// Returns None for trees without a manifest
+async fn try_serve_site(
+    state: &AppState,
+    root: &Cid,
+    request: SiteRequest<'_>,
+) -> Option<Response<Body>> {
+    let store = state.store.store_arc();
+    let tree = HashTree::new(HashTreeConfig::new(store).public());
+
+    let manifest_cid = tree
+        .resolve_path(root, SITE_MANIFEST)
+        .await
+        .ok()
+        .flatten()?;
+    let site = match tree.get(&manifest_cid, None).await {
+        Ok(Some(data)) => {
+            SiteManifest::parse(&data).map_err(|e| format!("Invalid {}: {}", SITE_MANIFEST, e))
+        }
+        Ok(None) => Err(format!("{} not found", SITE_MANIFEST)),
+        Err(e) => Err(format!("Error: {}", e)),
+    };
+    let site = match site {
+        Ok(site) => site,
+        Err(message) => {
+            return Some(
+                Response::builder()
+                    .status(StatusCode::INTERNAL_SERVER_ERROR)
+                    .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+                    .body(Body::from(message))
+                    .unwrap(),
+            );
+        }
+    };
+
+    Some(serve_site(&tree, state, root, &site, request).await)
+}
+
+async fn serve_site<S: Store>(
+    tree: &HashTree<S>,
+    state: &AppState,
+    root: &Cid,
+    site: &SiteManifest,
+    request: SiteRequest<'_>,
+) -> Response<Body> {
+    let request_path = format!("/{}", request.path);
+    let path = match site.route(&request_path) {
+        SiteRoute::Serve(path) => path,
+        SiteRoute::Redirect { location, status } => {
+            let location = if location.starts_with('/') {
+                format!("{}{}", request.base, location)
+            } else {
+                location
+            };
+            return redirect_response(status, &location);
+        }
+    };
+
+    let mut file = None;
+    if let Some(entry) = resolve_site_entry(tree, root, tree_path(&path)).await {
+        if tree.is_dir(&entry).await.unwrap_or(false) {
+            // Relative links in an index page need the trailing slash
+            let uri_path = request.uri.path();
+            if path == request_path && !uri_path.ends_with('/') {
+                let location = match request.uri.query() {
+                    Some(query) => format!("{}/?{}", uri_path, query),
+                    None => format!("{}/", uri_path),
+                };
+                return redirect_response(StatusCode::MOVED_PERMANENTLY, &location);
+            }
+            let index = match tree_path(&path) {
+                "" => site.index.clone(),
+                dir => format!("{}/{}", dir, site.index),
+            };
+            if let Some(entry) = resolve_site_entry(tree, root, &index).await {
+                file = Some((entry, index, StatusCode::OK));
+            }
+        } else {
+            file = Some((entry, tree_path(&path).to_string(), StatusCode::OK));
+        }
+    }
+
+    if file.is_none() {
+        if let Some((page, status)) = site.fallback_for(&path) {
+            if let Some(entry) = resolve_site_entry(tree, root, tree_path(page)).await {
+                file = Some((entry, tree_path(page).to_string(), status));
+            }
+        }
+    }
+
+    let Some((entry, file_path, status)) = file else {
+        return Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+            .body(Body::from("File not found"))
+            .unwrap();
+    };
+
+    let mut response = serve_cid_with_range(
+        state,
+        &entry,
+        request.headers,
+        request.is_immutable,
+        request.is_localhost,
+        Some(&file_path),
+        false,
+    )
+    .await;
+    if response.status() == StatusCode::OK {
+        *response.status_mut() = status;
+    }
+    for (name, value) in site.headers_for(&request_path) {
+        response.headers_mut().insert(name, value);
+    }
+    response
+}
+
+/// Resolve a path inside a site tree ("" is the root itself)
+async fn resolve_site_entry<S: Store>(tree: &HashTree<S>, root: &Cid, path: &str) -> Option<Cid> {
+    if path.is_empty() {
+        return Some(root.clone());
+    }
+    tree.resolve_path(root, path).await.ok().flatten()
+}
+
+fn redirect_response(status: StatusCode, location: &str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(header::LOCATION, location)
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .body(Body::empty())
+        .unwrap()
+}
+
+/// Stream a directory as a tar or zip download (`?format=tar|zip`)
+///
+/// The archive is produced by a background task that walks the tree and
+/// hands chunks over a bounded channel, so memory stays flat regardless of
+/// the directory size.
+async fn serve_archive(
+    state: &AppState,
+    root: &Cid,
+    path: Option<&str>,
+    format: &str,
+    name: &str,
+    is_immutable: bool,
+) -> Response<Body> {
+    let format: ArchiveFormat = match format.parse() {
+        Ok(format) => format,
+        Err(e) => {
+            return Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+                .body(Body::from(e))
+                .unwrap();
+        }
+    };
+
+    let tree = HashTree::new(HashTreeConfig::new(state.store.store_arc()).public());
+    let dir = match path
+        .map(|p| p.trim_end_matches('/'))
+        .filter(|p| !p.is_empty())
+    {
+        Some(path) => match tree.resolve_path(root, path).await {
+            Ok(Some(entry)) => entry,
+            Ok(None) => {
+                return Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+                    .body(Body::from("File not found"))
+                    .unwrap();
+            }
+            Err(e) => {
+                return Response::builder()
+                    .status(StatusCode::INTERNAL_SERVER_ERROR)
+                    .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+                    .body(Body::from(format!("Error: {}", e)))
+                    .unwrap();
+            }
+        },
+        None => root.clone(),
+    };
+    if !tree.is_dir(&dir).await.unwrap_or(false) {
+        return Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+            .body(Body::from("Archive export requires a directory"))
+            .unwrap();
+    }
+
+    let (tx, rx) = tokio::sync::mpsc::channel::<Result<Bytes, std::io::Error>>(4);
+    tokio::spawn(async move {
+        let mut chunks = archive_stream(&tree, dir, format);
+        while let Some(chunk) = chunks.next().await {
+            let item = chunk
+                .map(Bytes::from)
+                .map_err(|e| std::io::Error::other(e.to_string()));
+            let failed = item.is_err();
+            // Stop walking once the client has gone away
+            if tx.send(item).await.is_err() || failed {
+                break;
+            }
+        }
+    });
+    let stream = stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|item| (item, rx))
+    });
+
+    // Header values must be visible ASCII
+    let filename: String = name
+        .chars()
+        .map(|c| match c {
+            '"' | '\\' | '/' => '_',
+            c if c.is_ascii_graphic() || c == ' ' => c,
+            _ => '_',
+        })
+        .collect();
+    let mut builder = Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, format.content_type())
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!(
+                "attachment; filename=\"{}.{}\"",
+                filename,
+                format.extension()
+            ),
+        )
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*");
+    if is_immutable {
+        builder = builder.header(header::CACHE_CONTROL, IMMUTABLE_CACHE_CONTROL);
+    }
+    builder.body(Body::from_stream(stream)).unwrap()
+}
+
 /// Cache-Control header for immutable content-addressed data (1 year)
 const IMMUTABLE_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+/// Content-Type of `?proof=1` responses (an encoded `RangeProof`)
+const RANGE_PROOF_CONTENT_TYPE: &str = "application/vnd.hashtree.range-proof";
 
 /// Source of blob data for X-Source header
 #[derive(Debug, Clone)]
@@ -602,9 +938,13 @@ async fn serve_cid_with_range(
     is_immutable: bool,
     is_localhost: bool,
     filename_hint: Option<&str>,
+    with_proof: bool,
 ) -> Response<Body> {
     let store = state.store.store_arc();
     let tree = HashTree::new(HashTreeConfig::new(store).public());
+    if with_proof {
+        return serve_range_proof(&tree, cid, &headers, is_immutable, is_localhost).await;
+    }
     let content_type = content_type_for_path(filename_hint);
 
     let range_header = headers.get(header::RANGE).and_then(|v| v.to_str().ok());
@@ -740,6 +1080,130 @@ async fn serve_cid_with_range(
     builder.body(Body::from(data)).unwrap()
 }
 
+/// A `Range` header resolved against the file size
+#[derive(Debug, PartialEq, Eq)]
+enum ByteRange {
+    /// No range, or one we don't understand: serve the whole file
+    Whole,
+    /// Half-open byte range within the file
+    Partial(u64, u64),
+    Unsatisfiable,
+}
+
+/// Resolve a single `bytes=first-last`, `bytes=first-` or `bytes=-suffix`
+/// range against `size`
+fn parse_byte_range(range: Option<&str>, size: u64) -> ByteRange {
+    let Some((first, last)) = range
+        .and_then(|v| v.trim().strip_prefix("bytes="))
+        .and_then(|v| v.split_once('-'))
+    else {
+        return ByteRange::Whole;
+    };
+    if first.is_empty() {
+        // Suffix range: the last N bytes
+        return match last.parse::<u64>() {
+            Ok(0) => ByteRange::Unsatisfiable,
+            Ok(_) if size == 0 => ByteRange::Unsatisfiable,
+            Ok(suffix) => ByteRange::Partial(size - suffix.min(size), size),
+            Err(_) => ByteRange::Whole,
+        };
+    }
+    let Ok(start) = first.parse::<u64>() else {
+        return ByteRange::Whole;
+    };
+    let end = if last.is_empty() {
+        size
+    } else {
+        match last.parse::<u64>() {
+            Ok(last) if last < start => return ByteRange::Unsatisfiable,
+            Ok(last) => last.saturating_add(1).min(size),
+            Err(_) => return ByteRange::Whole,
+        }
+    };
+    if start >= size {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Partial(start, end)
+}
+
+/// Serve a Merkle proof for the requested byte range (`?proof=1`)
+///
+/// The body is an encoded `RangeProof`; its covering chunks carry the data, so
+/// a light client gets the bytes from `verify_range_proof` against the nhash.
+/// Without a `Range` header the proof covers the whole file.
+async fn serve_range_proof<S: Store>(
+    tree: &HashTree<S>,
+    cid: &Cid,
+    headers: &HeaderMap,
+    is_immutable: bool,
+    is_localhost: bool,
+) -> Response<Body> {
+    // A proof of the empty range is just the root block, which gives the size
+    let size = match prove_range(tree, cid, 0, Some(0)).await {
+        Ok(Some(proof)) => proof.size,
+        result => return range_proof_error(result.err()),
+    };
+    let range_header = headers.get(header::RANGE).and_then(|v| v.to_str().ok());
+    let range = parse_byte_range(range_header, size);
+    let (start, end) = match range {
+        ByteRange::Whole => (0, size),
+        ByteRange::Partial(start, end) => (start, end),
+        ByteRange::Unsatisfiable => {
+            return Response::builder()
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+                .header(header::CONTENT_TYPE, "text/plain")
+                .header(header::CONTENT_RANGE, format!("bytes */{}", size))
+                .body(Body::from("Range not satisfiable"))
+                .unwrap();
+        }
+    };
+
+    let proof = match prove_range(tree, cid, start, Some(end)).await {
+        Ok(Some(proof)) => proof,
+        result => return range_proof_error(result.err()),
+    };
+
+    let mut builder = Response::builder().header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*");
+    if range == ByteRange::Whole {
+        builder = builder.status(StatusCode::OK);
+    } else {
+        let content_range = format!("bytes {}-{}/{}", proof.start, proof.end - 1, proof.size);
+        builder = builder
+            .status(StatusCode::PARTIAL_CONTENT)
+            .header(header::CONTENT_RANGE, content_range);
+    }
+
+    let body = proof.encode();
+    builder = builder
+        .header(header::CONTENT_TYPE, RANGE_PROOF_CONTENT_TYPE)
+        .header(header::CONTENT_LENGTH, body.len());
+    if is_immutable {
+        builder = builder.header(header::CACHE_CONTROL, IMMUTABLE_CACHE_CONTROL);
+    }
+    if is_localhost {
+        builder = builder.header("X-Source", "local");
+    }
+    builder.body(Body::from(body)).unwrap()
+}
+
+/// Response for a `prove_range` that found nothing (`None`) or failed
+fn range_proof_error(error: Option<ProofError>) -> Response<Body> {
+    let (status, body) = match error {
+        None => (StatusCode::NOT_FOUND, "Not found".to_string()),
+        Some(ProofError::NotAFile) => (
+            StatusCode::BAD_REQUEST,
+            "Range proofs require a file".to_string(),
+        ),
+        Some(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("Error: {}", e)),
+    };
+    Response::builder()
+        .status(status)
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .body(Body::from(body))
+        .unwrap()
+}
+
 /// Internal content serving (shared by CID and blossom routes)
 ///
 /// `is_immutable`: if true, adds Cache-Control: immutable header.
@@ -1214,9 +1678,28 @@ pub async fn list_pins(State(state): State<AppState>) -> impl IntoResponse {
     }
 }
 
+/// Parse a pin target: an nhash (with its key, if any), `hash:key` or a bare hash
+///
+/// Returns whether the encryption is known: an nhash or `hash:key` says,
+/// a bare hash doesn't.
+fn parse_pin_target(cid: &str) -> Result<(Cid, bool), String> {
+    if is_nhash(cid) {
+        let data = nhash_decode(cid).map_err(|e| e.to_string())?;
+        let target = Cid {
+            hash: data.hash,
+            key: data.decrypt_key,
+        };
+        Ok((target, true))
+    } else {
+        let target = Cid::parse(cid).map_err(|e| e.to_string())?;
+        let known = target.key.is_some();
+        Ok((target, known))
+    }
+}
+
 pub async fn pin_cid(State(state): State<AppState>, Path(cid): Path<String>) -> impl IntoResponse {
-    let hash = match from_hex(&cid) {
-        Ok(h) => h,
+    let (target, known) = match parse_pin_target(&cid) {
+        Ok(parsed) => parsed,
         Err(e) => {
             return Json(json!({
                 "success": false,
@@ -1225,7 +1708,13 @@ pub async fn pin_cid(State(state): State<AppState>, Path(cid): Path<String>) ->
         }
     };
     let store = &state.store;
-    match store.pin(&hash) {
+    // A bare hash is pinned as-is; gc refuses to run if it can't decode it
+    let pinned = if known {
+        store.pin_cid(&target)
+    } else {
+        store.pin(&target.hash)
+    };
+    match pinned {
         Ok(_) => Json(json!({
             "success": true,
             "cid": cid
@@ -1241,8 +1730,8 @@ pub async fn unpin_cid(
     State(state): State<AppState>,
     Path(cid): Path<String>,
 ) -> impl IntoResponse {
-    let hash = match from_hex(&cid) {
-        Ok(h) => h,
+    let hash = match parse_pin_target(&cid) {
+        Ok((target, _)) => target.hash,
         Err(e) => {
             return Json(json!({
                 "success": false,
@@ -1299,22 +1788,45 @@ pub async fn webrtc_peers(State(state): State<AppState>) -> impl IntoResponse {
     };
 
     let peers = webrtc_state.peers.read().await;
-    let peer_list: Vec<_> = peers.iter().map(|(id, entry)| {
+    let mut peer_list: Vec<_> = peers.iter().map(|(id, entry)| {
         let rtc_state = entry.peer.as_ref().map(|p| format!("{:?}", p.state()));
         json!({
             "id": id,
+            "transport": "webrtc",
             "pubkey": entry.peer_id.pubkey,
             "state": format!("{:?}", entry.state),
             "rtc_state": rtc_state,
             "pool": format!("{:?}", entry.pool),
             "connected": entry.state == ConnectionState::Connected,
             "has_data_channel": entry.peer.as_ref().map(|p| p.has_data_channel()).unwrap_or(false),
+            "bytes_sent": entry.bytes_sent,
+            "bytes_received": entry.bytes_received,
+            "ledger": webrtc_state.peer_ledger(id),
+            "leecher": webrtc_state.is_leecher(id),
         })
     }).collect();
+    drop(peers);
+
+    // Daemons connected directly over their /ws endpoint
+    for ws in webrtc_state.ws_peer_statuses().await {
+        let ledger = webrtc_state.peer_ledger(&ws.id);
+        peer_list.push(json!({
+            "id": ws.id,
+            "transport": "websocket",
+            "url": ws.url,
+            "pool": ws.pool,
+            "connected": ws.connected,
+            "has_data_channel": ws.connected,
+            "bytes_sent": ledger.as_ref().map(|l| l.uploaded).unwrap_or(0),
+            "bytes_received": ledger.as_ref().map(|l| l.downloaded).unwrap_or(0),
+            "leecher": webrtc_state.is_leecher(&ws.id),
+            "ledger": ledger,
+        }));
+    }
 
     Json(json!({
         "enabled": true,
-        "total": peers.len(),
+        "total": peer_list.len(),
         "connected": peer_list.iter().filter(|p| p["connected"].as_bool().unwrap_or(false)).count(),
         "with_data_channel": peer_list.iter().filter(|p| p["has_data_channel"].as_bool().unwrap_or(false)).count(),
         "peers": peer_list
@@ -1394,21 +1906,40 @@ pub async fn daemon_status(
         "blossom_servers": state.upstream_blossom.len(),
     });
 
+    let fetch_jobs: Vec<serde_json::Value> = state
+        .store
+        .list_fetch_jobs()
+        .unwrap_or_default()
+        .iter()
+        .map(|job| job.status_json())
+        .collect();
+
     Json(json!({
         "status": "running",
         "storage": storage,
         "webrtc": webrtc,
         "upstream": upstream,
+        "fetch_jobs": fetch_jobs,
     }))
     .into_response()
 }
 
-pub async fn garbage_collect(State(state): State<AppState>) -> impl IntoResponse {
+pub async fn garbage_collect(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
     let store = &state.store;
-    match store.gc() {
+    let dry_run = params
+        .get("dry_run")
+        .map(|v| v == "1" || v == "true")
+        .unwrap_or(false);
+    match store.gc_with_options(dry_run) {
         Ok(gc_stats) => Json(json!({
             "deleted_dags": gc_stats.deleted_dags,
-            "freed_bytes": gc_stats.freed_bytes
+            "freed_bytes": gc_stats.freed_bytes,
+            "roots": gc_stats.roots,
+            "reachable_dags": gc_stats.reachable_dags,
+            "dry_run": gc_stats.dry_run
         })),
         Err(e) => Json(json!({
             "error": e.to_string()
@@ -1558,6 +2089,19 @@ fn resolver_config() -> NostrResolverConfig {
     }
 }
 
+/// Resolver for named trees: configured root maps and Nostr, combined per the
+/// `[resolver]` policy, falling back to the cache of last-known roots
+async fn named_resolver() -> Result<CompositeResolver, String> {
+    let nostr = NostrRootResolver::new(resolver_config())
+        .await
+        .map_err(|e| format!("Failed to create resolver: {}", e))?;
+    crate::config::Config::load()
+        .unwrap_or_default()
+        .resolver
+        .composite(Some(Arc::new(nostr)))
+        .map_err(|e| format!("Failed to create resolver: {}", e))
+}
+
 /// Resolve npub/treename to hash and serve content
 /// Route: /n/:pubkey/:treename or /n/:pubkey/:treename/*path
 pub async fn resolve_and_serve(
@@ -1568,7 +2112,7 @@ pub async fn resolve_and_serve(
     let (pubkey, treename) = params;
     let key = format!("{}/{}", pubkey, treename);
 
-    let resolver = match NostrRootResolver::new(resolver_config()).await {
+    let resolver = match named_resolver().await {
         Ok(r) => r,
         Err(e) => {
             return Response::builder()
@@ -1577,7 +2121,7 @@ pub async fn resolve_and_serve(
                 .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
                 .body(Body::from(
                     json!({
-                        "error": format!("Failed to create resolver: {}", e),
+                        "error": e,
                         "key": key
                     })
                     .to_string(),
@@ -1587,13 +2131,29 @@ pub async fn resolve_and_serve(
         }
     };
 
-    // Use resolve_wait with timeout - waits for key to appear
-    // This is a mutable route (npub/treename can change over time)
-    match tokio::time::timeout(HTTP_RESOLVER_TIMEOUT, resolver.resolve_wait(&key)).await {
-        Ok(Ok(cid)) => {
+    // This is a mutable route (npub/treename can change over time); when every
+    // backend is down the last known root is served from the cache
+    match tokio::time::timeout(HTTP_RESOLVER_TIMEOUT, resolver.resolve(&key)).await {
+        Ok(Ok(Some(cid))) => {
             let _ = resolver.stop().await;
             serve_content_internal(&state, &cid.hash, headers, false, false).await
         }
+        Ok(Ok(None)) => {
+            let _ = resolver.stop().await;
+            Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .header(header::CONTENT_TYPE, "application/json")
+                .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+                .body(Body::from(
+                    json!({
+                        "error": "Not found",
+                        "key": key
+                    })
+                    .to_string(),
+                ))
+                .unwrap()
+                .into_response()
+        }
         Ok(Err(e)) => {
             let _ = resolver.stop().await;
             Response::builder()
@@ -1630,26 +2190,33 @@ pub async fn resolve_and_serve(
 }
 
 /// API endpoint to resolve npub/treename to hash (returns JSON)
+///
+/// `source` names the backend that answered, or the cache when all were down.
 pub async fn resolve_to_hash(Path(params): Path<(String, String)>) -> impl IntoResponse {
     let (pubkey, treename) = params;
     let key = format!("{}/{}", pubkey, treename);
 
-    let resolver = match NostrRootResolver::new(resolver_config()).await {
+    let resolver = match named_resolver().await {
         Ok(r) => r,
         Err(e) => {
             return Json(json!({
-                "error": format!("Failed to create resolver: {}", e),
+                "error": e,
                 "key": key
             }));
         }
     };
 
     let result =
-        match tokio::time::timeout(HTTP_RESOLVER_TIMEOUT, resolver.resolve_wait(&key)).await {
-            Ok(Ok(cid)) => Json(json!({
+        match tokio::time::timeout(HTTP_RESOLVER_TIMEOUT, resolver.resolve_detailed(&key)).await {
+            Ok(Ok(Some(resolution))) => Json(json!({
                 "key": key,
-                "hash": to_hex(&cid.hash),
-                "cid": cid.to_string()
+                "hash": to_hex(&resolution.cid.hash),
+                "cid": resolution.cid.to_string(),
+                "source": resolution.source.to_string()
+            })),
+            Ok(Ok(None)) => Json(json!({
+                "error": "Not found",
+                "key": key
             })),
             Ok(Err(e)) => Json(json!({
                 "error": e.to_string(),
@@ -1665,6 +2232,52 @@ pub async fn resolve_to_hash(Path(params): Path<(String, String)>) -> impl IntoR
     result
 }
 
+/// Recorded root history of a tree, newest first (local index, no network)
+pub async fn root_history(
+    State(state): State<AppState>,
+    Path(params): Path<(String, String)>,
+) -> impl IntoResponse {
+    let (pubkey, treename) = params;
+    let key = format!("{}/{}", pubkey, treename);
+
+    let pubkey_hex = if pubkey.starts_with("npub1") {
+        crate::config::parse_npub(&pubkey).ok().map(hex::encode)
+    } else {
+        from_hex(&pubkey).ok().map(|pk| to_hex(&pk))
+    };
+    let Some(pubkey_hex) = pubkey_hex else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": "Invalid pubkey", "key": key })),
+        );
+    };
+
+    match state.store.root_history(&pubkey_hex, &treename) {
+        Ok(history) => (
+            StatusCode::OK,
+            Json(json!({
+                "key": key,
+                "versions": history.iter().map(|v| {
+                    let cid = Cid {
+                        hash: from_hex(&v.hash).unwrap_or_default(),
+                        key: v.key.as_deref().and_then(|k| hashtree_core::key_from_hex(k).ok()),
+                    };
+                    json!({
+                        "hash": v.hash,
+                        "cid": cid.to_string(),
+                        "created_at": v.created_at,
+                        "prev": v.prev,
+                    })
+                }).collect::<Vec<_>>()
+            })),
+        ),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": e.to_string(), "key": key })),
+        ),
+    }
+}
+
 /// List all trees for a pubkey
 pub async fn list_trees(Path(pubkey): Path<String>) -> impl IntoResponse {
     let resolver = match NostrRootResolver::new(resolver_config()).await {
@@ -1820,7 +2433,7 @@ async fn query_upstream_blossom(servers: &[String], hash_hex: &str) -> Option<(V
 #[cfg(test)]
 mod tests {
     use super::*;
-    use hashtree_core::{DirEntry, MemoryStore};
+    use hashtree_core::{verify_range_proof, DirEntry, MemoryStore, RangeProof};
 
     #[tokio::test]
     async fn test_query_upstream_blossom_no_servers() {
@@ -1894,4 +2507,89 @@ mod tests {
         assert_eq!(content_type_for_path(Some("image.jpeg")), "image/jpeg");
         assert_eq!(content_type_for_path(None), "application/octet-stream");
     }
+
+    async fn proof_response(
+        tree: &HashTree<MemoryStore>,
+        cid: &Cid,
+        range: Option<&str>,
+    ) -> Response<Body> {
+        let mut headers = HeaderMap::new();
+        if let Some(range) = range {
+            headers.insert(header::RANGE, range.parse().unwrap());
+        }
+        serve_range_proof(tree, cid, &headers, true, false).await
+    }
+
+    #[tokio::test]
+    async fn range_proof_handles_range_forms() {
+        let store = Arc::new(MemoryStore::new());
+        // Small chunks so ranges span several leaves
+        let tree = HashTree::new(HashTreeConfig::new(store).public().with_chunk_size(64));
+        let data: Vec<u8> = (0..=255u8).cycle().take(1000).collect();
+        let (cid, _size) = tree.put(&data).await.unwrap();
+
+        for (range, start, end) in [
+            ("bytes=10-19", 10, 20),
+            ("bytes=990-", 990, 1000),
+            ("bytes=-100", 900, 1000),
+            ("bytes=-5000", 0, 1000),
+            ("bytes=995-2000", 995, 1000),
+        ] {
+            let response = proof_response(&tree, &cid, Some(range)).await;
+            assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT, "{}", range);
+            assert_eq!(
+                response.headers()[header::CONTENT_RANGE],
+                format!("bytes {}-{}/1000", start, end - 1),
+                "{}",
+                range
+            );
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let proof = RangeProof::decode(&body).unwrap();
+            assert_eq!(
+                verify_range_proof(&cid, &proof).unwrap(),
+                data[start..end],
+                "{}",
+                range
+            );
+        }
+
+        let whole = proof_response(&tree, &cid, None).await;
+        assert_eq!(whole.status(), StatusCode::OK);
+        assert!(whole.headers().get(header::CONTENT_RANGE).is_none());
+    }
+
+    #[tokio::test]
+    async fn range_proof_rejects_unsatisfiable_ranges() {
+        let store = Arc::new(MemoryStore::new());
+        let tree = HashTree::new(HashTreeConfig::new(store).public());
+        let (cid, _size) = tree.put(&[7u8; 100]).await.unwrap();
+
+        for range in ["bytes=100-", "bytes=500-600", "bytes=-0", "bytes=50-10"] {
+            let response = proof_response(&tree, &cid, Some(range)).await;
+            assert_eq!(
+                response.status(),
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                "{}",
+                range
+            );
+            assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes */100");
+        }
+    }
+
+    #[test]
+    fn parse_byte_range_ignores_unknown_forms() {
+        assert_eq!(parse_byte_range(None, 10), ByteRange::Whole);
+        assert_eq!(parse_byte_range(Some("items=0-1"), 10), ByteRange::Whole);
+        assert_eq!(
+            parse_byte_range(Some("bytes=0-1,4-5"), 10),
+            ByteRange::Whole
+        );
+        assert_eq!(parse_byte_range(Some("bytes=-"), 10), ByteRange::Whole);
+        assert_eq!(
+            parse_byte_range(Some("bytes=-3"), 0),
+            ByteRange::Unsatisfiable
+        );
+    }
 }
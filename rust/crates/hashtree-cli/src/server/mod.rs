@@ -2,6 +2,7 @@ mod auth;
 pub mod blossom;
 mod handlers;
 mod mime;
+mod site;
 #[cfg(feature = "p2p")]
 pub mod stun;
 mod ui;
@@ -150,9 +151,11 @@ impl HashtreeServer {
             )
             // /htree/nhash1...[/path] - content-addressed (immutable)
             .route("/htree/nhash1:nhash", get(handlers::htree_nhash))
+            .route("/htree/nhash1:nhash/", get(handlers::htree_nhash))
             .route("/htree/nhash1:nhash/*path", get(handlers::htree_nhash_path))
             // /htree/npub1.../tree[/path] - mutable (resolver-backed)
             .route("/htree/npub1:npub/:treename", get(handlers::htree_npub))
+            .route("/htree/npub1:npub/:treename/", get(handlers::htree_npub))
             .route(
                 "/htree/npub1:npub/:treename/*path",
                 get(handlers::htree_npub_path),
@@ -198,6 +201,10 @@ impl HashtreeServer {
                 get(handlers::resolve_to_hash),
             )
             .route("/api/trees/:pubkey", get(handlers::list_trees))
+            .route(
+                "/api/history/:pubkey/:treename",
+                get(handlers::root_history),
+            )
             .with_state(state.clone());
 
         // Protected endpoints (require auth if enabled)
@@ -0,0 +1,290 @@
+//! Static website hosting for `/htree` routes
+//!
+//! A directory tree with a `.htree-site.json` manifest at its root is served
+//! as a website instead of a JSON listing (a dotfile rather than `_site.json`,
+//! since names starting with `_` are reserved for internal tree nodes):
+//!
+//! ```json
+//! {
+//!   "index": "index.html",
+//!   "not_found": "404.html",
+//!   "spa_fallback": "index.html",
+//!   "redirects": [
+//!     { "from": "/old/*", "to": "/new/:splat", "status": 301 },
+//!     { "from": "/api/:name", "to": "https://api.example.com/:name", "status": 302 },
+//!     { "from": "/app/*", "to": "/app.html", "status": 200 }
+//!   ],
+//!   "headers": [
+//!     { "for": "/assets/*", "values": { "Cache-Control": "public, max-age=3600" } }
+//!   ]
+//! }
+//! ```
+//!
+//! Rules use `_redirects`/`_headers` patterns: `:name` matches one path
+//! segment, a trailing `*` matches the rest of the path (`:splat`). The first
+//! matching redirect wins; a 200 status rewrites the request internally.
+
+use axum::http::{HeaderName, HeaderValue, StatusCode};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Manifest file name looked up in the tree root
+pub const SITE_MANIFEST: &str = ".htree-site.json";
+
+/// Site-serving options from `.htree-site.json`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SiteManifest {
+    /// File served for directory requests
+    pub index: String,
+    /// Page served with status 404 when nothing matches
+    pub not_found: Option<String>,
+    /// Page served with status 200 for unmatched paths without an extension
+    pub spa_fallback: Option<String>,
+    pub redirects: Vec<RedirectRule>,
+    pub headers: Vec<HeaderRule>,
+}
+
+impl Default for SiteManifest {
+    fn default() -> Self {
+        Self {
+            index: "index.html".to_string(),
+            not_found: None,
+            spa_fallback: None,
+            redirects: Vec::new(),
+            headers: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RedirectRule {
+    pub from: String,
+    pub to: String,
+    #[serde(default = "default_redirect_status")]
+    pub status: u16,
+}
+
+fn default_redirect_status() -> u16 {
+    301
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HeaderRule {
+    #[serde(rename = "for")]
+    pub pattern: String,
+    pub values: HashMap<String, String>,
+}
+
+/// Outcome of matching a request path against the redirect rules
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SiteRoute {
+    /// Serve this site path (the request path, or a rewrite target)
+    Serve(String),
+    /// Redirect to a site path (starting with `/`) or an absolute URL
+    Redirect {
+        location: String,
+        status: StatusCode,
+    },
+}
+
+impl SiteManifest {
+    pub fn parse(data: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(data)
+    }
+
+    /// Apply the first matching redirect rule to `path`
+    pub fn route(&self, path: &str) -> SiteRoute {
+        let path = normalize(path);
+        for rule in &self.redirects {
+            let Some(params) = match_pattern(&rule.from, &path) else {
+                continue;
+            };
+            let target = expand(&rule.to, &params);
+            return match StatusCode::from_u16(rule.status) {
+                Ok(status) if status.is_redirection() => SiteRoute::Redirect {
+                    location: target,
+                    status,
+                },
+                _ if is_absolute_url(&target) => SiteRoute::Redirect {
+                    location: target,
+                    status: StatusCode::FOUND,
+                },
+                _ => SiteRoute::Serve(normalize(&target)),
+            };
+        }
+        SiteRoute::Serve(path)
+    }
+
+    /// Extra response headers for `path`, in rule order
+    pub fn headers_for(&self, path: &str) -> Vec<(HeaderName, HeaderValue)> {
+        let path = normalize(path);
+        self.headers
+            .iter()
+            .filter(|rule| match_pattern(&rule.pattern, &path).is_some())
+            .flat_map(|rule| rule.values.iter())
+            .filter_map(|(name, value)| {
+                Some((
+                    HeaderName::from_bytes(name.as_bytes()).ok()?,
+                    HeaderValue::from_str(value).ok()?,
+                ))
+            })
+            .collect()
+    }
+
+    /// Fallback page for a path that resolved to nothing, with its status
+    pub fn fallback_for(&self, path: &str) -> Option<(&str, StatusCode)> {
+        let last = path.rsplit('/').next().unwrap_or("");
+        if let Some(spa) = self.spa_fallback.as_deref() {
+            if !last.contains('.') {
+                return Some((spa, StatusCode::OK));
+            }
+        }
+        self.not_found
+            .as_deref()
+            .map(|page| (page, StatusCode::NOT_FOUND))
+    }
+}
+
+/// Path inside the tree for a site path (no leading or trailing slashes)
+pub fn tree_path(path: &str) -> &str {
+    path.trim_matches('/')
+}
+
+fn is_absolute_url(target: &str) -> bool {
+    target.starts_with("http://") || target.starts_with("https://")
+}
+
+fn normalize(path: &str) -> String {
+    if path.starts_with('/') || is_absolute_url(path) {
+        path.to_string()
+    } else {
+        format!("/{}", path)
+    }
+}
+
+/// Match a `/a/:name/*` pattern, returning the captured placeholders
+fn match_pattern(pattern: &str, path: &str) -> Option<HashMap<String, String>> {
+    let mut params = HashMap::new();
+    let mut rest = path.trim_start_matches('/');
+    let mut segments = pattern.trim_start_matches('/').split('/').peekable();
+
+    while let Some(segment) = segments.next() {
+        if segment == "*" && segments.peek().is_none() {
+            params.insert("splat".to_string(), rest.to_string());
+            return Some(params);
+        }
+        let (part, tail) = rest.split_once('/').unwrap_or((rest, ""));
+        if let Some(name) = segment.strip_prefix(':') {
+            if part.is_empty() {
+                return None;
+            }
+            params.insert(name.to_string(), part.to_string());
+        } else if segment != part {
+            return None;
+        }
+        rest = tail;
+    }
+
+    // Allow a trailing slash on the request
+    rest.is_empty().then_some(params)
+}
+
+/// Substitute `:name` placeholders (longest names first)
+fn expand(target: &str, params: &HashMap<String, String>) -> String {
+    let mut names: Vec<&String> = params.keys().collect();
+    names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+    let mut out = target.to_string();
+    for name in names {
+        out = out.replace(&format!(":{}", name), &params[name]);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(json: &str) -> SiteManifest {
+        SiteManifest::parse(json.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_defaults() {
+        let site = manifest("{}");
+        assert_eq!(site.index, "index.html");
+        assert_eq!(site.route("docs/"), SiteRoute::Serve("/docs/".to_string()));
+        assert_eq!(site.fallback_for("/missing"), None);
+    }
+
+    #[test]
+    fn test_redirect_placeholders_and_splat() {
+        let site = manifest(
+            r#"{"redirects": [
+                {"from": "/blog/:year/:slug", "to": "/posts/:year-:slug", "status": 302},
+                {"from": "/old/*", "to": "/new/:splat"},
+                {"from": "/ext/*", "to": "https://example.com/:splat", "status": 200},
+                {"from": "/app/*", "to": "/app.html", "status": 200}
+            ]}"#,
+        );
+
+        assert_eq!(
+            site.route("/blog/2024/hello"),
+            SiteRoute::Redirect {
+                location: "/posts/2024-hello".to_string(),
+                status: StatusCode::FOUND,
+            }
+        );
+        assert_eq!(
+            site.route("/old/a/b.html"),
+            SiteRoute::Redirect {
+                location: "/new/a/b.html".to_string(),
+                status: StatusCode::MOVED_PERMANENTLY,
+            }
+        );
+        // Rewrites can't leave the tree, so external targets redirect
+        assert_eq!(
+            site.route("/ext/x"),
+            SiteRoute::Redirect {
+                location: "https://example.com/x".to_string(),
+                status: StatusCode::FOUND,
+            }
+        );
+        assert_eq!(
+            site.route("/app/settings"),
+            SiteRoute::Serve("/app.html".to_string())
+        );
+        assert_eq!(
+            site.route("/blog/2024"),
+            SiteRoute::Serve("/blog/2024".to_string())
+        );
+    }
+
+    #[test]
+    fn test_headers_and_fallbacks() {
+        let site = manifest(
+            r#"{
+                "not_found": "404.html",
+                "spa_fallback": "index.html",
+                "headers": [
+                    {"for": "/assets/*", "values": {"Cache-Control": "max-age=60"}},
+                    {"for": "/*", "values": {"X-Frame-Options": "DENY", "Bad Name": "x"}}
+                ]
+            }"#,
+        );
+
+        let headers = site.headers_for("/assets/app.js");
+        assert_eq!(headers.len(), 2);
+        assert_eq!(headers[0].0, "cache-control");
+        assert_eq!(site.headers_for("/index.html").len(), 1);
+
+        assert_eq!(
+            site.fallback_for("/settings/profile"),
+            Some(("index.html", StatusCode::OK))
+        );
+        assert_eq!(
+            site.fallback_for("/assets/missing.js"),
+            Some(("404.html", StatusCode::NOT_FOUND))
+        );
+    }
+}
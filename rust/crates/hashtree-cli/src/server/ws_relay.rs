@@ -17,7 +17,8 @@ use tokio::sync::mpsc;
 
 use super::auth::{AppState, PendingRequest, WsProtocol};
 use crate::webrtc::types::{
-    encode_request, encode_response, parse_message, DataMessage, DataRequest, DataResponse, MAX_HTL,
+    default_htl, encode_not_found, encode_request, encode_response, parse_message, should_forward,
+    DataMessage, DataNotFound, DataRequest, DataResponse, MAX_HTL,
 };
 use hex::encode as hex_encode;
 
@@ -25,7 +26,12 @@ use hex::encode as hex_encode;
 #[serde(tag = "type")]
 enum WsClientMessage {
     #[serde(rename = "req")]
-    Request { id: u32, hash: String },
+    Request {
+        id: u32,
+        hash: String,
+        #[serde(default = "default_htl")]
+        htl: u8,
+    },
     #[serde(rename = "res")]
     Response { id: u32, hash: String, found: bool },
 }
@@ -42,6 +48,9 @@ struct WsRequest {
     kind: String,
     id: u32,
     hash: String,
+    /// Hops left, so daemons forwarding it again stop in time
+    #[serde(default = "default_htl")]
+    htl: u8,
 }
 
 #[derive(Debug, Serialize)]
@@ -151,12 +160,13 @@ async fn handle_message(client_id: u64, msg: Message, state: &AppState) {
                     WsTextMessage::Hashtree(msg) => {
                         set_client_protocol(state, client_id, WsProtocol::HashtreeJson).await;
                         match msg {
-                            WsClientMessage::Request { id, hash } => {
+                            WsClientMessage::Request { id, hash, htl } => {
                                 handle_request(
                                     client_id,
                                     id,
                                     hash,
                                     WsProtocol::HashtreeJson,
+                                    htl,
                                     state,
                                 )
                                 .await;
@@ -189,25 +199,14 @@ async fn handle_request(
     request_id: u32,
     hash: String,
     origin_protocol: WsProtocol,
+    htl: u8,
     state: &AppState,
 ) {
     let hash_hex = hash.to_lowercase();
     let hash_bytes = match from_hex(&hash_hex) {
         Ok(bytes) => bytes,
         Err(_) => {
-            if origin_protocol == WsProtocol::HashtreeJson {
-                send_json(
-                    state,
-                    client_id,
-                    WsResponse {
-                        kind: "res",
-                        id: request_id,
-                        hash,
-                        found: false,
-                    },
-                )
-                .await;
-            }
+            send_not_found(state, client_id, request_id, &hash, origin_protocol).await;
             return;
         }
     };
@@ -254,20 +253,10 @@ async fn handle_request(
             .collect()
     };
 
-    if peers.is_empty() {
-        if origin_protocol == WsProtocol::HashtreeJson {
-            send_json(
-                state,
-                client_id,
-                WsResponse {
-                    kind: "res",
-                    id: request_id,
-                    hash,
-                    found: false,
-                },
-            )
-            .await;
-        }
+    // Other clients may be daemons that forward again, so stop once hops run out
+    let forward_htl = htl.min(MAX_HTL).saturating_sub(1);
+    if peers.is_empty() || !should_forward(forward_htl) {
+        send_not_found(state, client_id, request_id, &hash, origin_protocol).await;
         return;
     }
 
@@ -290,12 +279,13 @@ async fn handle_request(
         kind: "req".to_string(),
         id: request_id,
         hash: hash.clone(),
+        htl: forward_htl,
     })
     .unwrap_or_else(|_| String::new());
     for (peer_id, tx, protocol) in peers {
         match protocol {
             WsProtocol::HashtreeMsgpack => {
-                let _ = send_msgpack_request(state, peer_id, &hash_bytes).await;
+                let _ = send_msgpack_request(state, peer_id, &hash_bytes, forward_htl).await;
             }
             WsProtocol::HashtreeJson => {
                 let _ = tx.send(Message::Text(request_text.clone()));
@@ -325,19 +315,14 @@ async fn handle_request(
             .unwrap_or(WsProtocol::HashtreeJson);
         pending.retain(|(_, id), p| !(*id == request_id && p.origin_id == client_id));
         drop(pending);
-        if origin_protocol == WsProtocol::HashtreeJson {
-            send_json(
-                &timeout_state,
-                client_id,
-                WsResponse {
-                    kind: "res",
-                    id: request_id,
-                    hash: timeout_hash,
-                    found: false,
-                },
-            )
-            .await;
-        }
+        send_not_found(
+            &timeout_state,
+            client_id,
+            request_id,
+            &timeout_hash,
+            origin_protocol,
+        )
+        .await;
     });
 }
 
@@ -396,18 +381,8 @@ async fn handle_response(
         .any(|((_, id), p)| *id == request_id && p.origin_id == origin_id);
     drop(pending);
 
-    if !has_remaining && origin_protocol == WsProtocol::HashtreeJson {
-        send_json(
-            state,
-            origin_id,
-            WsResponse {
-                kind: "res",
-                id: request_id,
-                hash: pending_hash,
-                found: false,
-            },
-        )
-        .await;
+    if !has_remaining {
+        send_not_found(state, origin_id, request_id, &pending_hash, origin_protocol).await;
     }
 }
 
@@ -423,6 +398,7 @@ async fn handle_binary(client_id: u64, data: Vec<u8>, state: &AppState) {
                     request_id,
                     hash_hex,
                     WsProtocol::HashtreeMsgpack,
+                    req.htl,
                     state,
                 )
                 .await;
@@ -430,6 +406,9 @@ async fn handle_binary(client_id: u64, data: Vec<u8>, state: &AppState) {
             DataMessage::Response(res) => {
                 handle_msgpack_response(client_id, res, state).await;
             }
+            DataMessage::NotFound(msg) => {
+                handle_msgpack_not_found(client_id, msg, state).await;
+            }
         }
         return;
     }
@@ -526,6 +505,13 @@ fn parse_msgpack_message(data: &[u8]) -> Option<DataMessage> {
                 None
             }
         }
+        DataMessage::NotFound(msg) => {
+            if msg.h.len() == 32 {
+                Some(DataMessage::NotFound(msg))
+            } else {
+                None
+            }
+        }
     }
 }
 
@@ -586,6 +572,60 @@ async fn handle_msgpack_response(client_id: u64, res: DataResponse, state: &AppS
     pending.retain(|(_, id), p| !completed.contains(&(p.origin_id, *id)));
 }
 
+/// A MessagePack client doesn't have a hash we forwarded to it: the same as
+/// a JSON `found: false` response for each request waiting on it
+async fn handle_msgpack_not_found(client_id: u64, msg: DataNotFound, state: &AppState) {
+    let hash_hex = hex_encode(&msg.h);
+    let request_ids: Vec<u32> = {
+        let pending = state.ws_relay.pending.lock().await;
+        pending
+            .iter()
+            .filter(|((peer_id, _), p)| *peer_id == client_id && p.hash == hash_hex)
+            .map(|((_, request_id), _)| *request_id)
+            .collect()
+    };
+    for request_id in request_ids {
+        handle_response(client_id, request_id, hash_hex.clone(), false, state).await;
+    }
+}
+
+/// Tell a client we don't have a hash, in the protocol it speaks
+async fn send_not_found(
+    state: &AppState,
+    client_id: u64,
+    request_id: u32,
+    hash: &str,
+    protocol: WsProtocol,
+) {
+    match protocol {
+        WsProtocol::HashtreeJson => {
+            send_json(
+                state,
+                client_id,
+                WsResponse {
+                    kind: "res",
+                    id: request_id,
+                    hash: hash.to_string(),
+                    found: false,
+                },
+            )
+            .await;
+        }
+        WsProtocol::HashtreeMsgpack => {
+            let Ok(hash_bytes) = from_hex(hash) else {
+                return;
+            };
+            let msg = DataNotFound {
+                h: hash_bytes.to_vec(),
+            };
+            if let Ok(wire) = encode_not_found(&msg) {
+                send_to_client(state, client_id, Message::Binary(wire)).await;
+            }
+        }
+        WsProtocol::Unknown => {}
+    }
+}
+
 async fn send_json(state: &AppState, client_id: u64, response: WsResponse) {
     if let Ok(text) = serde_json::to_string(&response) {
         send_to_client(state, client_id, Message::Text(text)).await;
@@ -596,10 +636,11 @@ async fn send_msgpack_request(
     state: &AppState,
     client_id: u64,
     hash: &[u8],
+    htl: u8,
 ) -> Result<(), rmp_serde::encode::Error> {
     let req = DataRequest {
         h: hash.to_vec(),
-        htl: MAX_HTL,
+        htl,
     };
     let wire = encode_request(&req)?;
     send_to_client(state, client_id, Message::Binary(wire)).await;
@@ -6,8 +6,10 @@ use futures::StreamExt;
 use hashtree_config::StorageBackend;
 use hashtree_core::store::{Store, StoreError};
 use hashtree_core::{
-    from_hex, sha256, to_hex, types::Hash, Cid, DirEntry as HashTreeDirEntry, HashTree,
-    HashTreeConfig, TreeNode,
+    collect_hashes, decrypt_chk, entry_is_dir, export_bundle, from_hex, import_bundle, import_tar,
+    import_zip, is_tree_node, path_diff, sha256, to_hex, types::Hash, BundleStats, Cid,
+    DirEntry as HashTreeDirEntry, HashTree, HashTreeConfig, ImportedBundle, LinkType, PathChange,
+    PosixMeta, TreeNode,
 };
 use hashtree_fs::FsBlobStore;
 #[cfg(feature = "lmdb")]
@@ -15,8 +17,8 @@ use hashtree_lmdb::LmdbBlobStore;
 use heed::types::*;
 use heed::{Database, EnvOpenOptions};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
-use std::io::{Read, Write};
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::Path;
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -54,6 +56,127 @@ pub struct CachedRoot {
     pub visibility: String,
 }
 
+/// One version of a mutable tree, as seen in a root event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RootVersion {
+    /// Root hash (hex)
+    pub hash: String,
+    /// Optional decryption key (hex)
+    pub key: Option<String>,
+    /// Event created_at (unix seconds)
+    pub created_at: u64,
+    /// Previous root hash from the event's `prev` tag (hex)
+    pub prev: Option<String>,
+}
+
+/// Versions kept per tree in the root history index (oldest are dropped)
+pub const MAX_ROOT_HISTORY: usize = 1000;
+
+/// Newest versions per tree that gc keeps the content of; checking out an
+/// older version needs its content to be fetchable again
+pub const GC_ROOT_HISTORY: usize = 10;
+
+/// Selectors at or above this are unix timestamps, below it version numbers
+const TIMESTAMP_SELECTOR_MIN: u64 = 1_000_000_000;
+
+/// Pick a version from newest-first `history`
+///
+/// `selector` is either a version number (0 = newest, 1 = the one before, ...)
+/// or a unix timestamp, which picks the newest version created at or before it.
+pub fn select_root_version<'a>(
+    history: &'a [RootVersion],
+    selector: &str,
+) -> Result<Option<&'a RootVersion>> {
+    let value: u64 = selector
+        .parse()
+        .with_context(|| format!("Invalid version selector: {}", selector))?;
+    if value >= TIMESTAMP_SELECTOR_MIN {
+        Ok(history.iter().find(|v| v.created_at <= value))
+    } else {
+        Ok(usize::try_from(value).ok().and_then(|n| history.get(n)))
+    }
+}
+
+/// Persistent record of an unfinished tree fetch, so it can resume after a restart
+///
+/// The frontier holds every hash that was queued or in flight at the last
+/// checkpoint. Nodes fetched after that are re-expanded from local storage on
+/// resume, so a crash between checkpoints never loses part of the tree.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FetchJob {
+    /// Job id (first 16 hex chars of the root hash)
+    pub id: String,
+    /// Root hash being fetched (hex)
+    pub root: String,
+    /// Hashes still to fetch or expand: (hash hex, known leaf blob)
+    pub frontier: Vec<(String, bool)>,
+    /// Chunks downloaded so far, across resumes
+    pub chunks_done: u64,
+    /// Bytes downloaded so far, across resumes
+    pub bytes_done: u64,
+    /// Size of the tree discovered so far (fetched tree nodes plus known
+    /// leaves); grows as the walk expands, final once the frontier is empty
+    pub total_bytes: Option<u64>,
+    /// Seconds spent fetching, across resumes (for the ETA)
+    pub active_secs: u64,
+    /// Unix timestamp when the job was created
+    pub started_at: u64,
+    /// Unix timestamp of the last checkpoint
+    pub updated_at: u64,
+}
+
+impl FetchJob {
+    /// Start a job for a root that hasn't been fetched yet
+    pub fn new(root: &Hash) -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let root = to_hex(root);
+        Self {
+            id: root[..16].to_string(),
+            frontier: vec![(root.clone(), false)],
+            root,
+            chunks_done: 0,
+            bytes_done: 0,
+            total_bytes: None,
+            active_secs: 0,
+            started_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Job id for a root hash
+    pub fn id_for(root: &Hash) -> String {
+        to_hex(root)[..16].to_string()
+    }
+
+    /// Estimated seconds remaining, from the average download rate so far
+    pub fn eta_secs(&self) -> Option<u64> {
+        let total = self.total_bytes?;
+        if self.bytes_done == 0 || self.active_secs == 0 {
+            return None;
+        }
+        let remaining = total.saturating_sub(self.bytes_done);
+        Some(remaining.saturating_mul(self.active_secs) / self.bytes_done)
+    }
+
+    /// Summary for status output (omits the frontier itself)
+    pub fn status_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.id,
+            "root": self.root,
+            "chunks_done": self.chunks_done,
+            "bytes_done": self.bytes_done,
+            "total_bytes": self.total_bytes,
+            "pending": self.frontier.len(),
+            "eta_secs": self.eta_secs(),
+            "started_at": self.started_at,
+            "updated_at": self.updated_at,
+        })
+    }
+}
+
 /// Storage statistics
 #[derive(Debug, Clone)]
 pub struct LocalStoreStats {
@@ -112,6 +235,36 @@ impl LocalStore {
         }
     }
 
+    /// Store several blobs (one transaction on LMDB)
+    pub fn put_many_sync(&self, items: &[(Hash, Vec<u8>)]) -> Result<Vec<bool>, StoreError> {
+        match self {
+            LocalStore::Fs(store) => items
+                .iter()
+                .map(|(hash, data)| store.put_sync(*hash, data))
+                .collect(),
+            #[cfg(feature = "lmdb")]
+            LocalStore::Lmdb(store) => store.put_many_sync(items),
+        }
+    }
+
+    /// Fetch several blobs (one transaction on LMDB)
+    pub fn get_many_sync(&self, hashes: &[Hash]) -> Result<Vec<Option<Vec<u8>>>, StoreError> {
+        match self {
+            LocalStore::Fs(store) => hashes.iter().map(|hash| store.get_sync(hash)).collect(),
+            #[cfg(feature = "lmdb")]
+            LocalStore::Lmdb(store) => store.get_many_sync(hashes),
+        }
+    }
+
+    /// Check several hashes (one transaction on LMDB)
+    pub fn has_many_sync(&self, hashes: &[Hash]) -> Result<Vec<bool>, StoreError> {
+        match self {
+            LocalStore::Fs(store) => Ok(hashes.iter().map(|hash| store.exists(hash)).collect()),
+            #[cfg(feature = "lmdb")]
+            LocalStore::Lmdb(store) => store.has_many_sync(hashes),
+        }
+    }
+
     /// Sync delete operation
     pub fn delete_sync(&self, hash: &Hash) -> Result<bool, StoreError> {
         match self {
@@ -169,6 +322,22 @@ impl Store for LocalStore {
     async fn delete(&self, hash: &Hash) -> Result<bool, StoreError> {
         self.delete_sync(hash)
     }
+
+    async fn put_many(&self, items: Vec<(Hash, Vec<u8>)>) -> Result<Vec<bool>, StoreError> {
+        self.put_many_sync(&items)
+    }
+
+    async fn get_many(&self, hashes: &[Hash]) -> Result<Vec<Option<Vec<u8>>>, StoreError> {
+        self.get_many_sync(hashes)
+    }
+
+    async fn has_many(&self, hashes: &[Hash]) -> Result<Vec<bool>, StoreError> {
+        self.has_many_sync(hashes)
+    }
+
+    async fn list(&self) -> Result<Vec<Hash>, StoreError> {
+        LocalStore::list(self)
+    }
 }
 
 #[cfg(feature = "s3")]
@@ -408,6 +577,47 @@ impl StorageRouter {
         Ok(false)
     }
 
+    /// Store several blobs - one local batch, then queue S3 uploads
+    pub fn put_many_sync(&self, items: &[(Hash, Vec<u8>)]) -> Result<Vec<bool>, StoreError> {
+        let stored = self.local.put_many_sync(items)?;
+
+        #[cfg(feature = "s3")]
+        if let Some(ref tx) = self.sync_tx {
+            for (hash, data) in items {
+                if let Err(e) = tx.send(S3SyncMessage::Upload {
+                    hash: *hash,
+                    data: data.clone(),
+                }) {
+                    tracing::error!("Failed to queue S3 upload: {}", e);
+                }
+            }
+        }
+
+        Ok(stored)
+    }
+
+    /// Get several blobs - one local batch, S3 fallback for misses
+    pub fn get_many_sync(&self, hashes: &[Hash]) -> Result<Vec<Option<Vec<u8>>>, StoreError> {
+        let mut results = self.local.get_many_sync(hashes)?;
+        for (hash, slot) in hashes.iter().zip(results.iter_mut()) {
+            if slot.is_none() {
+                *slot = self.get_sync(hash)?;
+            }
+        }
+        Ok(results)
+    }
+
+    /// Check several hashes - one local batch, S3 fallback for misses
+    pub fn has_many_sync(&self, hashes: &[Hash]) -> Result<Vec<bool>, StoreError> {
+        let mut results = self.local.has_many_sync(hashes)?;
+        for (hash, found) in hashes.iter().zip(results.iter_mut()) {
+            if !*found {
+                *found = self.exists(hash)?;
+            }
+        }
+        Ok(results)
+    }
+
     /// Delete data from both local and S3 stores
     pub fn delete_sync(&self, hash: &Hash) -> Result<bool, StoreError> {
         let deleted = self.local.delete_sync(hash)?;
@@ -462,12 +672,30 @@ impl Store for StorageRouter {
     async fn delete(&self, hash: &Hash) -> Result<bool, StoreError> {
         self.delete_sync(hash)
     }
+
+    async fn put_many(&self, items: Vec<(Hash, Vec<u8>)>) -> Result<Vec<bool>, StoreError> {
+        self.put_many_sync(&items)
+    }
+
+    async fn get_many(&self, hashes: &[Hash]) -> Result<Vec<Option<Vec<u8>>>, StoreError> {
+        self.get_many_sync(hashes)
+    }
+
+    async fn has_many(&self, hashes: &[Hash]) -> Result<Vec<bool>, StoreError> {
+        self.has_many_sync(hashes)
+    }
+
+    async fn list(&self) -> Result<Vec<Hash>, StoreError> {
+        StorageRouter::list(self)
+    }
 }
 
 pub struct HashtreeStore {
     env: heed::Env,
     /// Set of pinned hashes (32-byte raw hashes, prevents garbage collection)
     pins: Database<Bytes, Unit>,
+    /// Decryption keys for encrypted pins: hash (32 bytes) -> key (32 bytes), used by gc to walk them
+    pin_keys: Database<Bytes, Bytes>,
     /// Blob ownership: sha256 (32 bytes) ++ pubkey (32 bytes) -> () (composite key for multi-owner)
     blob_owners: Database<Bytes, Unit>,
     /// Maps pubkey (32 bytes) -> blob metadata JSON (for blossom list)
@@ -480,6 +708,10 @@ pub struct HashtreeStore {
     tree_refs: Database<Str, Bytes>,
     /// Cached roots from Nostr: "pubkey_hex/tree_name" -> CachedRoot (msgpack)
     cached_roots: Database<Str, Bytes>,
+    /// Every root seen per tree: "pubkey_hex/tree_name" -> Vec<RootVersion>, newest first (msgpack)
+    root_history: Database<Str, Bytes>,
+    /// Unfinished tree fetches: job id -> FetchJob (msgpack)
+    fetch_jobs: Database<Str, Bytes>,
     /// Storage router - handles LMDB + optional S3 (Arc for sharing with HashTree)
     router: Arc<StorageRouter>,
     /// Maximum storage size in bytes (from config)
@@ -509,18 +741,21 @@ impl HashtreeStore {
         let env = unsafe {
             EnvOpenOptions::new()
                 .map_size(10 * 1024 * 1024 * 1024) // 10GB virtual address space
-                .max_dbs(8) // pins, blob_owners, pubkey_blobs, tree_meta, blob_trees, tree_refs, cached_roots, blobs
+                .max_dbs(11) // pins, pin_keys, blob_owners, pubkey_blobs, tree_meta, blob_trees, tree_refs, cached_roots, root_history, fetch_jobs, blobs
                 .open(path)?
         };
 
         let mut wtxn = env.write_txn()?;
         let pins = env.create_database(&mut wtxn, Some("pins"))?;
+        let pin_keys = env.create_database(&mut wtxn, Some("pin_keys"))?;
         let blob_owners = env.create_database(&mut wtxn, Some("blob_owners"))?;
         let pubkey_blobs = env.create_database(&mut wtxn, Some("pubkey_blobs"))?;
         let tree_meta = env.create_database(&mut wtxn, Some("tree_meta"))?;
         let blob_trees = env.create_database(&mut wtxn, Some("blob_trees"))?;
         let tree_refs = env.create_database(&mut wtxn, Some("tree_refs"))?;
         let cached_roots = env.create_database(&mut wtxn, Some("cached_roots"))?;
+        let root_history = env.create_database(&mut wtxn, Some("root_history"))?;
+        let fetch_jobs = env.create_database(&mut wtxn, Some("fetch_jobs"))?;
         wtxn.commit()?;
 
         // Get storage backend from config
@@ -560,12 +795,15 @@ impl HashtreeStore {
         Ok(Self {
             env,
             pins,
+            pin_keys,
             blob_owners,
             pubkey_blobs,
             tree_meta,
             blob_trees,
             tree_refs,
             cached_roots,
+            root_history,
+            fetch_jobs,
             router,
             max_size_bytes,
         })
@@ -606,9 +844,7 @@ impl HashtreeStore {
 
         // Only pin if requested (htree add = pin, blossom upload = no pin)
         if pin {
-            let mut wtxn = self.env.write_txn()?;
-            self.pins.put(&mut wtxn, cid.hash.as_slice(), &())?;
-            wtxn.commit()?;
+            self.pin_cid(&cid)?;
         }
 
         Ok(to_hex(&cid.hash))
@@ -635,9 +871,7 @@ impl HashtreeStore {
         callback(&root_hex);
 
         // Auto-pin on upload
-        let mut wtxn = self.env.write_txn()?;
-        self.pins.put(&mut wtxn, cid.hash.as_slice(), &())?;
-        wtxn.commit()?;
+        self.pin_cid(&cid)?;
 
         Ok(root_hex)
     }
@@ -667,9 +901,7 @@ impl HashtreeStore {
 
         let root_hex = to_hex(&root_cid.hash);
 
-        let mut wtxn = self.env.write_txn()?;
-        self.pins.put(&mut wtxn, root_cid.hash.as_slice(), &())?;
-        wtxn.commit()?;
+        self.pin_cid(&root_cid)?;
 
         Ok(root_hex)
     }
@@ -684,8 +916,10 @@ impl HashtreeStore {
         use ignore::WalkBuilder;
         use std::collections::HashMap;
 
-        // Build directory structure from flat file list - store full Cid with key
-        let mut dir_contents: HashMap<String, Vec<(String, Cid)>> = HashMap::new();
+        // Build directory structure from flat file list - entries carry link type and
+        // POSIX metadata, directories keep their own metadata for the parent's link
+        let mut dir_contents: HashMap<String, Vec<HashTreeDirEntry>> = HashMap::new();
+        let mut dir_meta: HashMap<String, PosixMeta> = HashMap::new();
         dir_contents.insert(String::new(), Vec::new()); // Root
 
         let walker = WalkBuilder::new(current_path)
@@ -705,40 +939,70 @@ impl HashtreeStore {
             }
 
             let relative = path.strip_prefix(current_path).unwrap_or(path);
+            let posix = posix_meta(path)?;
+
+            // Get parent directory path and entry name
+            let parent = relative
+                .parent()
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let name = relative
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            let is_symlink = entry.file_type().is_some_and(|t| t.is_symlink());
+            if is_symlink {
+                // Store the link itself (target bytes), never what it points to
+                let target = std::fs::read_link(path)
+                    .with_context(|| format!("Failed to read link {}", path.display()))?;
+                let target = target.to_string_lossy();
+                let (cid, size) = tree.put(target.as_bytes()).await.map_err(|e| {
+                    anyhow::anyhow!("Failed to upload link {}: {}", path.display(), e)
+                })?;
+                let posix = posix.with_symlink(target);
 
-            if path.is_file() {
+                dir_contents.entry(parent).or_default().push(
+                    HashTreeDirEntry::from_cid(name, &cid)
+                        .with_size(size)
+                        .with_posix(&posix),
+                );
+            } else if path.is_file() {
                 let file = std::fs::File::open(path)
                     .with_context(|| format!("Failed to open file {}", path.display()))?;
-                let (cid, _size) = tree.put_stream(AllowStdIo::new(file)).await.map_err(|e| {
+                let (cid, size) = tree.put_stream(AllowStdIo::new(file)).await.map_err(|e| {
                     anyhow::anyhow!("Failed to upload file {}: {}", path.display(), e)
                 })?;
+                let link_type = if tree.is_tree(&cid.hash).await.unwrap_or(false) {
+                    LinkType::File
+                } else {
+                    LinkType::Blob
+                };
 
-                // Get parent directory path and file name
-                let parent = relative
-                    .parent()
-                    .map(|p| p.to_string_lossy().to_string())
-                    .unwrap_or_default();
-                let name = relative
-                    .file_name()
-                    .map(|n| n.to_string_lossy().to_string())
-                    .unwrap_or_default();
-
-                dir_contents.entry(parent).or_default().push((name, cid));
+                dir_contents.entry(parent).or_default().push(
+                    HashTreeDirEntry::from_cid(name, &cid)
+                        .with_size(size)
+                        .with_link_type(link_type)
+                        .with_posix(&posix),
+                );
             } else if path.is_dir() {
                 // Ensure directory entry exists
                 let dir_path = relative.to_string_lossy().to_string();
-                dir_contents.entry(dir_path).or_default();
+                dir_contents.entry(dir_path.clone()).or_default();
+                dir_meta.insert(dir_path, posix);
             }
         }
 
         // Build directory tree bottom-up
-        self.build_directory_tree(tree, &mut dir_contents).await
+        self.build_directory_tree(tree, &mut dir_contents, &dir_meta)
+            .await
     }
 
     async fn build_directory_tree<S: Store>(
         &self,
         tree: &HashTree<S>,
-        dir_contents: &mut std::collections::HashMap<String, Vec<(String, Cid)>>,
+        dir_contents: &mut std::collections::HashMap<String, Vec<HashTreeDirEntry>>,
+        dir_meta: &std::collections::HashMap<String, PosixMeta>,
     ) -> Result<Cid> {
         // Sort directories by depth (deepest first) to build bottom-up
         let mut dirs: Vec<String> = dir_contents.keys().cloned().collect();
@@ -751,12 +1015,7 @@ impl HashtreeStore {
         let mut dir_cids: std::collections::HashMap<String, Cid> = std::collections::HashMap::new();
 
         for dir_path in dirs {
-            let files = dir_contents.get(&dir_path).cloned().unwrap_or_default();
-
-            let mut entries: Vec<HashTreeDirEntry> = files
-                .into_iter()
-                .map(|(name, cid)| HashTreeDirEntry::from_cid(name, &cid))
-                .collect();
+            let mut entries = dir_contents.remove(&dir_path).unwrap_or_default();
 
             // Add subdirectory entries
             for (subdir_path, cid) in &dir_cids {
@@ -770,7 +1029,12 @@ impl HashtreeStore {
                         .file_name()
                         .map(|n| n.to_string_lossy().to_string())
                         .unwrap_or_default();
-                    entries.push(HashTreeDirEntry::from_cid(name, cid));
+                    let posix = dir_meta.get(subdir_path).cloned().unwrap_or_default();
+                    entries.push(
+                        HashTreeDirEntry::from_cid(name, cid)
+                            .with_link_type(LinkType::Dir)
+                            .with_posix(&posix),
+                    );
                 }
             }
 
@@ -804,9 +1068,7 @@ impl HashtreeStore {
 
         let cid_str = cid.to_string();
 
-        let mut wtxn = self.env.write_txn()?;
-        self.pins.put(&mut wtxn, cid.hash.as_slice(), &())?;
-        wtxn.commit()?;
+        self.pin_cid(&cid)?;
 
         Ok(cid_str)
     }
@@ -838,14 +1100,58 @@ impl HashtreeStore {
 
         let cid_str = root_cid.to_string(); // Returns "hash:key" or "hash"
 
-        let mut wtxn = self.env.write_txn()?;
-        // Pin by hash only (the key is for decryption, not identification)
-        self.pins.put(&mut wtxn, root_cid.hash.as_slice(), &())?;
-        wtxn.commit()?;
+        // Pin by hash (the key is kept separately so gc can walk the tree)
+        self.pin_cid(&root_cid)?;
 
         Ok(cid_str)
     }
 
+    /// Import a tar or zip archive as a public directory without unpacking it
+    /// Returns the root hash (hex)
+    pub fn import_archive<P: AsRef<Path>>(&self, archive_path: P) -> Result<String> {
+        let tree = HashTree::new(HashTreeConfig::new(self.store_arc()).public());
+        let root_cid = sync_block_on(import_archive_file(&tree, archive_path.as_ref()))?;
+        self.pin_cid(&root_cid)?;
+        Ok(to_hex(&root_cid.hash))
+    }
+
+    /// Import a tar or zip archive with CHK encryption
+    /// Returns CID as "hash:key" format
+    pub fn import_archive_encrypted<P: AsRef<Path>>(&self, archive_path: P) -> Result<String> {
+        let tree = HashTree::new(HashTreeConfig::new(self.store_arc()));
+        let root_cid = sync_block_on(import_archive_file(&tree, archive_path.as_ref()))?;
+        self.pin_cid(&root_cid)?;
+        Ok(root_cid.to_string())
+    }
+
+    /// Write every block reachable from `cid` as a portable bundle
+    pub fn export_bundle<W: Write>(
+        &self,
+        cid: &Cid,
+        writer: W,
+        with_index: bool,
+    ) -> Result<BundleStats> {
+        let tree = HashTree::new(HashTreeConfig::new(self.store_arc()).public());
+        sync_block_on(export_bundle(
+            &tree,
+            std::slice::from_ref(cid),
+            writer,
+            with_index,
+        ))
+        .map_err(|e| anyhow::anyhow!("Failed to export bundle: {}", e))
+    }
+
+    /// Import a bundle, verifying every block, and pin its roots
+    pub fn import_bundle<R: Read>(&self, reader: R) -> Result<ImportedBundle> {
+        let store = self.store_arc();
+        let imported = sync_block_on(import_bundle(&*store, reader))
+            .map_err(|e| anyhow::anyhow!("Failed to import bundle: {}", e))?;
+        for root in &imported.roots {
+            self.pin_cid(root)?;
+        }
+        Ok(imported)
+    }
+
     /// Get tree node by hash (raw bytes)
     pub fn get_tree_node(&self, hash: &[u8; 32]) -> Result<Option<TreeNode>> {
         let store = self.store_arc();
@@ -881,6 +1187,13 @@ impl HashtreeStore {
             .map_err(|e| anyhow::anyhow!("Failed to check blob: {}", e))
     }
 
+    /// Check several blobs at once, in order (one local batch).
+    pub fn blobs_exist(&self, hashes: &[[u8; 32]]) -> Result<Vec<bool>> {
+        self.router
+            .has_many_sync(hashes)
+            .map_err(|e| anyhow::anyhow!("Failed to check blobs: {}", e))
+    }
+
     // === Blossom ownership tracking ===
     // Uses composite key: sha256 (32 bytes) ++ pubkey (32 bytes) -> ()
     // This allows efficient multi-owner tracking with O(1) lookups
@@ -1173,6 +1486,18 @@ impl HashtreeStore {
         })
     }
 
+    /// Compare two trees by path (added, removed, modified and renamed entries)
+    pub fn diff_paths(&self, old: &Cid, new: &Cid) -> Result<Vec<PathChange>> {
+        let store = self.store_arc();
+        let tree = HashTree::new(HashTreeConfig::new(store).public());
+
+        sync_block_on(async {
+            path_diff(&tree, old, new)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to diff trees: {}", e))
+        })
+    }
+
     /// Get chunk metadata for a file (chunk list, sizes, total size)
     pub fn get_file_chunk_metadata(&self, hash: &[u8; 32]) -> Result<Option<FileChunkMetadata>> {
         let store = self.store_arc();
@@ -1373,15 +1698,20 @@ impl HashtreeStore {
                 .await
                 .map_err(|e| anyhow::anyhow!("Failed to list directory: {}", e))?;
 
-            let entries: Vec<DirEntry> = tree_entries
-                .into_iter()
-                .map(|e| DirEntry {
-                    name: e.name,
+            let mut entries = Vec::with_capacity(tree_entries.len());
+            for e in tree_entries {
+                // Blob links don't say whether they are directories
+                let is_directory = entry_is_dir(&tree, &e)
+                    .await
+                    .map_err(|err| anyhow::anyhow!("Failed to check directory: {}", err))?;
+                entries.push(DirEntry {
                     cid: to_hex(&e.hash),
-                    is_directory: e.link_type.is_tree(),
+                    is_directory,
                     size: e.size,
-                })
-                .collect();
+                    posix: e.posix(),
+                    name: e.name,
+                });
+            }
 
             Ok(Some(DirectoryListing {
                 dir_name: String::new(),
@@ -1391,6 +1721,9 @@ impl HashtreeStore {
     }
 
     /// Pin a hash (prevent garbage collection)
+    ///
+    /// Nothing is recorded about encryption, so gc refuses to run while this
+    /// is an undecodable root; prefer `pin_cid` when the key is known.
     pub fn pin(&self, hash: &[u8; 32]) -> Result<()> {
         let mut wtxn = self.env.write_txn()?;
         self.pins.put(&mut wtxn, hash.as_slice(), &())?;
@@ -1398,10 +1731,22 @@ impl HashtreeStore {
         Ok(())
     }
 
+    /// Pin a Cid, remembering its decryption key so gc can walk encrypted trees
+    /// (an empty key records that the pin is public)
+    pub fn pin_cid(&self, cid: &Cid) -> Result<()> {
+        let mut wtxn = self.env.write_txn()?;
+        self.pins.put(&mut wtxn, cid.hash.as_slice(), &())?;
+        let key: &[u8] = cid.key.as_ref().map_or(&[], |key| key.as_slice());
+        self.pin_keys.put(&mut wtxn, cid.hash.as_slice(), key)?;
+        wtxn.commit()?;
+        Ok(())
+    }
+
     /// Unpin a hash (allow garbage collection)
     pub fn unpin(&self, hash: &[u8; 32]) -> Result<()> {
         let mut wtxn = self.env.write_txn()?;
         self.pins.delete(&mut wtxn, hash.as_slice())?;
+        self.pin_keys.delete(&mut wtxn, hash.as_slice())?;
         wtxn.commit()?;
         Ok(())
     }
@@ -1926,6 +2271,9 @@ impl HashtreeStore {
     }
 
     /// Set cached root for a pubkey/tree_name pair
+    ///
+    /// Also records the root in the tree's history, linked to the previously
+    /// cached root.
     pub fn set_cached_root(
         &self,
         pubkey_hex: &str,
@@ -1945,11 +2293,98 @@ impl HashtreeStore {
         let bytes = rmp_serde::to_vec(&root)
             .map_err(|e| anyhow::anyhow!("Failed to serialize CachedRoot: {}", e))?;
         let mut wtxn = self.env.write_txn()?;
+        let prev = match self.cached_roots.get(&wtxn, &db_key)? {
+            Some(bytes) => rmp_serde::from_slice::<CachedRoot>(bytes)
+                .ok()
+                .map(|prev| prev.hash)
+                .filter(|prev| prev != hash),
+            None => None,
+        };
         self.cached_roots.put(&mut wtxn, &db_key, &bytes)?;
+        let version = RootVersion {
+            hash: root.hash,
+            key: root.key,
+            created_at: updated_at,
+            prev,
+        };
+        self.put_root_version(&mut wtxn, &db_key, version)?;
         wtxn.commit()?;
         Ok(())
     }
 
+    // === Root history ===
+
+    /// Record a root seen for a tree; returns false if it was already known
+    ///
+    /// The same root recorded again next to itself (by `htree publish`, the
+    /// relay event and the cached root, each with its own timestamp) is merged
+    /// into one version dated when it was first seen.
+    pub fn record_root_version(
+        &self,
+        pubkey_hex: &str,
+        tree_name: &str,
+        version: RootVersion,
+    ) -> Result<bool> {
+        let db_key = format!("{}/{}", pubkey_hex, tree_name);
+        let mut wtxn = self.env.write_txn()?;
+        let added = self.put_root_version(&mut wtxn, &db_key, version)?;
+        wtxn.commit()?;
+        Ok(added)
+    }
+
+    fn put_root_version(
+        &self,
+        wtxn: &mut heed::RwTxn,
+        db_key: &str,
+        version: RootVersion,
+    ) -> Result<bool> {
+        let mut history: Vec<RootVersion> = match self.root_history.get(wtxn, db_key)? {
+            Some(bytes) => rmp_serde::from_slice(bytes)
+                .map_err(|e| anyhow::anyhow!("Failed to deserialize root history: {}", e))?,
+            None => Vec::new(),
+        };
+        if history
+            .iter()
+            .any(|v| v.hash == version.hash && v.created_at == version.created_at)
+        {
+            return Ok(false);
+        }
+
+        let known = history.len();
+        history.push(version);
+        history.sort_by(|a, b| {
+            b.created_at
+                .cmp(&a.created_at)
+                .then_with(|| b.hash.cmp(&a.hash))
+        });
+        history.dedup_by(|older, newer| {
+            if older.hash != newer.hash {
+                return false;
+            }
+            newer.created_at = older.created_at;
+            newer.key = newer.key.take().or(older.key.take());
+            newer.prev = newer.prev.take().or(older.prev.take());
+            true
+        });
+        let added = history.len() > known;
+        history.truncate(MAX_ROOT_HISTORY);
+        let bytes = rmp_serde::to_vec(&history)
+            .map_err(|e| anyhow::anyhow!("Failed to serialize root history: {}", e))?;
+        self.root_history.put(wtxn, db_key, &bytes)?;
+        Ok(added)
+    }
+
+    /// Every recorded root of a tree, newest first
+    pub fn root_history(&self, pubkey_hex: &str, tree_name: &str) -> Result<Vec<RootVersion>> {
+        let key = format!("{}/{}", pubkey_hex, tree_name);
+        let rtxn = self.env.read_txn()?;
+        match self.root_history.get(&rtxn, &key)? {
+            Some(bytes) => rmp_serde::from_slice(bytes)
+                .map_err(|e| anyhow::anyhow!("Failed to deserialize root history: {}", e)),
+            None => Ok(Vec::new()),
+        }
+    }
+
     /// List all cached roots for a pubkey
     pub fn list_cached_roots(&self, pubkey_hex: &str) -> Result<Vec<(String, CachedRoot)>> {
         let prefix = format!("{}/", pubkey_hex);
@@ -1978,45 +2413,152 @@ impl HashtreeStore {
         Ok(deleted)
     }
 
-    /// Garbage collect unpinned content
-    pub fn gc(&self) -> Result<GcStats> {
+    // === Fetch jobs ===
+
+    /// Get an unfinished fetch job by id
+    pub fn get_fetch_job(&self, id: &str) -> Result<Option<FetchJob>> {
         let rtxn = self.env.read_txn()?;
+        match self.fetch_jobs.get(&rtxn, id)? {
+            Some(bytes) => {
+                Ok(Some(rmp_serde::from_slice(bytes).map_err(|e| {
+                    anyhow::anyhow!("Failed to deserialize FetchJob: {}", e)
+                })?))
+            }
+            None => Ok(None),
+        }
+    }
 
-        // Get all pinned hashes as raw bytes
-        let pinned: HashSet<Hash> = self
-            .pins
-            .iter(&rtxn)?
-            .filter_map(|item| item.ok())
-            .filter_map(|(hash_bytes, _)| {
-                if hash_bytes.len() == 32 {
-                    let mut hash = [0u8; 32];
-                    hash.copy_from_slice(hash_bytes);
-                    Some(hash)
-                } else {
-                    None
-                }
-            })
-            .collect();
+    /// List all unfinished fetch jobs, oldest first
+    pub fn list_fetch_jobs(&self) -> Result<Vec<FetchJob>> {
+        let rtxn = self.env.read_txn()?;
+        let mut jobs = Vec::new();
+        for item in self.fetch_jobs.iter(&rtxn)? {
+            let (_, bytes) = item?;
+            if let Ok(job) = rmp_serde::from_slice::<FetchJob>(bytes) {
+                jobs.push(job);
+            }
+        }
+        jobs.sort_by_key(|job| job.started_at);
+        Ok(jobs)
+    }
+
+    /// Create or replace a fetch job
+    pub fn put_fetch_job(&self, job: &FetchJob) -> Result<()> {
+        let bytes = rmp_serde::to_vec(job)
+            .map_err(|e| anyhow::anyhow!("Failed to serialize FetchJob: {}", e))?;
+        let mut wtxn = self.env.write_txn()?;
+        self.fetch_jobs.put(&mut wtxn, &job.id, &bytes)?;
+        wtxn.commit()?;
+        Ok(())
+    }
+
+    /// Save progress for a running job
+    /// Returns false (and saves nothing) if the job was cancelled meanwhile
+    pub fn checkpoint_fetch_job(&self, job: &FetchJob) -> Result<bool> {
+        let bytes = rmp_serde::to_vec(job)
+            .map_err(|e| anyhow::anyhow!("Failed to serialize FetchJob: {}", e))?;
+        let mut wtxn = self.env.write_txn()?;
+        if self.fetch_jobs.get(&wtxn, &job.id)?.is_none() {
+            return Ok(false);
+        }
+        self.fetch_jobs.put(&mut wtxn, &job.id, &bytes)?;
+        wtxn.commit()?;
+        Ok(true)
+    }
+
+    /// Remove a fetch job (finished or cancelled)
+    /// A fetch still running for it stops at its next checkpoint
+    pub fn delete_fetch_job(&self, id: &str) -> Result<bool> {
+        let mut wtxn = self.env.write_txn()?;
+        let deleted = self.fetch_jobs.delete(&mut wtxn, id)?;
+        wtxn.commit()?;
+        Ok(deleted)
+    }
+
+    /// Garbage collect content unreachable from any pinned, indexed, cached or recent root
+    pub fn gc(&self) -> Result<GcStats> {
+        self.gc_with_options(false)
+    }
+
+    /// Mark-and-sweep garbage collection
+    ///
+    /// Marks every hash reachable from pinned roots, indexed trees, cached
+    /// Nostr roots and the newest `GC_ROOT_HISTORY` versions of each tree
+    /// (decrypting with stored keys), then deletes all other local blobs. With `dry_run`, nothing is deleted and the stats report what would be.
+    ///
+    /// Fails without deleting anything if a root can't be decoded (a key that
+    /// doesn't decrypt it, or a pin made without its key), since its children
+    /// would otherwise be swept.
+    pub fn gc_with_options(&self, dry_run: bool) -> Result<GcStats> {
+        let (roots, unknown_keys) = self.gc_roots()?;
+
+        // Mark: walk local storage only - S3 is an archive and is never swept
+        let local = self.router.local_store();
+
+        let mut opaque = Vec::new();
+        for cid in &roots {
+            let Some(data) = local
+                .get_sync(&cid.hash)
+                .map_err(|e| anyhow::anyhow!("Failed to read root {}: {}", to_hex(&cid.hash), e))?
+            else {
+                continue;
+            };
+            let decodable = match &cid.key {
+                Some(key) => decrypt_chk(&data, key).is_ok(),
+                None => !unknown_keys.contains(&cid.hash) || is_tree_node(&data),
+            };
+            if !decodable {
+                opaque.push(to_hex(&cid.hash));
+            }
+        }
+        if !opaque.is_empty() {
+            anyhow::bail!(
+                "Refusing to gc: cannot decode root(s) {}; pin them again by nhash or unpin them",
+                opaque.join(", ")
+            );
+        }
+
+        let tree = HashTree::new(HashTreeConfig::new(local).public());
+        let mut reachable: HashSet<Hash> = HashSet::new();
+
+        for cid in &roots {
+            let hashes = sync_block_on(async { collect_hashes(&tree, cid, 32).await })
+                .map_err(|e| anyhow::anyhow!("Failed to walk root {}: {}", to_hex(&cid.hash), e))?;
+            reachable.extend(hashes);
+        }
 
+        // Blobs recorded for indexed trees are kept even if the walk could not reach them
+        let rtxn = self.env.read_txn()?;
+        for item in self.blob_trees.iter(&rtxn)? {
+            let (key_bytes, _) = item?;
+            if key_bytes.len() >= 32 {
+                let blob_hash: Hash = key_bytes[..32].try_into().unwrap();
+                reachable.insert(blob_hash);
+            }
+        }
         drop(rtxn);
 
-        // Get all stored hashes
+        // Sweep
         let all_hashes = self
             .router
             .list()
             .map_err(|e| anyhow::anyhow!("Failed to list hashes: {}", e))?;
 
-        // Delete unpinned hashes
         let mut deleted = 0;
         let mut freed_bytes = 0u64;
+        let mut reachable_dags = 0;
 
         for hash in all_hashes {
-            if !pinned.contains(&hash) {
-                if let Ok(Some(data)) = self.router.get_sync(&hash) {
-                    freed_bytes += data.len() as u64;
+            if reachable.contains(&hash) {
+                reachable_dags += 1;
+                continue;
+            }
+            if let Ok(Some(data)) = self.router.get_sync(&hash) {
+                freed_bytes += data.len() as u64;
+                deleted += 1;
+                if !dry_run {
                     // Delete locally only - keep S3 as archive
                     let _ = self.router.delete_local_only(&hash);
-                    deleted += 1;
                 }
             }
         }
@@ -2024,9 +2566,104 @@ impl HashtreeStore {
         Ok(GcStats {
             deleted_dags: deleted,
             freed_bytes,
+            roots: roots.len(),
+            reachable_dags,
+            dry_run,
         })
     }
 
+    /// Collect gc roots: pins (with stored keys), indexed trees, all cached Nostr roots,
+    /// recent root history and the roots of unfinished fetches
+    ///
+    /// Also returns the pins whose encryption is unknown (pinned by bare hash).
+    fn gc_roots(&self) -> Result<(Vec<Cid>, HashSet<Hash>)> {
+        let rtxn = self.env.read_txn()?;
+        let mut roots: HashMap<Hash, Option<[u8; 32]>> = HashMap::new();
+        let mut unknown_keys: HashSet<Hash> = HashSet::new();
+
+        for item in self.pins.iter(&rtxn)? {
+            let (hash_bytes, _) = item?;
+            let hash: Hash = match hash_bytes.try_into() {
+                Ok(hash) => hash,
+                Err(_) => continue,
+            };
+            let key = match self.pin_keys.get(&rtxn, hash_bytes)? {
+                Some(k) => k.try_into().ok(),
+                None => {
+                    unknown_keys.insert(hash);
+                    None
+                }
+            };
+            roots.insert(hash, key);
+        }
+
+        for item in self.tree_meta.iter(&rtxn)? {
+            let (hash_bytes, _) = item?;
+            if let Ok(hash) = Hash::try_from(hash_bytes) {
+                // Indexed trees are public
+                roots.entry(hash).or_insert(None);
+                unknown_keys.remove(&hash);
+            }
+        }
+
+        for item in self.cached_roots.iter(&rtxn)? {
+            let (_, bytes) = item?;
+            let root: CachedRoot = match rmp_serde::from_slice(bytes) {
+                Ok(root) => root,
+                Err(_) => continue,
+            };
+            let hash = match from_hex(&root.hash) {
+                Ok(hash) => hash,
+                Err(_) => continue,
+            };
+            let key = root.key.as_deref().and_then(|k| from_hex(k).ok());
+            let entry = roots.entry(hash).or_insert(None);
+            if entry.is_none() {
+                *entry = key;
+            }
+            unknown_keys.remove(&hash);
+        }
+
+        for item in self.root_history.iter(&rtxn)? {
+            let (_, bytes) = item?;
+            let history: Vec<RootVersion> = match rmp_serde::from_slice(bytes) {
+                Ok(history) => history,
+                Err(_) => continue,
+            };
+            for version in history.into_iter().take(GC_ROOT_HISTORY) {
+                let hash = match from_hex(&version.hash) {
+                    Ok(hash) => hash,
+                    Err(_) => continue,
+                };
+                let key = version.key.as_deref().and_then(|k| from_hex(k).ok());
+                let entry = roots.entry(hash).or_insert(None);
+                if entry.is_none() {
+                    *entry = key;
+                }
+                unknown_keys.remove(&hash);
+            }
+        }
+
+        for item in self.fetch_jobs.iter(&rtxn)? {
+            let (_, bytes) = item?;
+            let job: FetchJob = match rmp_serde::from_slice(bytes) {
+                Ok(job) => job,
+                Err(_) => continue,
+            };
+            if let Ok(hash) = from_hex(&job.root) {
+                // Fetches walk public trees
+                roots.entry(hash).or_insert(None);
+                unknown_keys.remove(&hash);
+            }
+        }
+
+        let roots = roots
+            .into_iter()
+            .map(|(hash, key)| Cid { hash, key })
+            .collect();
+        Ok((roots, unknown_keys))
+    }
+
     /// Verify LMDB blob integrity - checks that stored data matches its key hash
     /// Returns verification statistics and optionally deletes corrupted entries
     pub fn verify_lmdb_integrity(&self, delete: bool) -> Result<VerifyResult> {
@@ -2366,10 +3003,56 @@ impl Iterator for FileRangeChunksOwned {
     }
 }
 
+/// POSIX metadata for an uploaded path (mtime only where modes don't exist)
+#[cfg(unix)]
+fn posix_meta(path: &Path) -> Result<PosixMeta> {
+    PosixMeta::from_path(path)
+        .with_context(|| format!("Failed to read metadata for {}", path.display()))
+}
+
+#[cfg(not(unix))]
+fn posix_meta(path: &Path) -> Result<PosixMeta> {
+    let metadata = std::fs::symlink_metadata(path)
+        .with_context(|| format!("Failed to read metadata for {}", path.display()))?;
+    let mut posix = PosixMeta::default();
+    if let Ok(mtime) = metadata.modified()?.duration_since(UNIX_EPOCH) {
+        posix = posix.with_mtime(mtime.as_secs() as i64);
+    }
+    Ok(posix)
+}
+
+/// Import an archive file into `tree`, detecting the format from its first bytes
+///
+/// Accepts zip, plain tar, and tar compressed with gzip or zstd.
+pub async fn import_archive_file<S: Store>(tree: &HashTree<S>, path: &Path) -> Result<Cid> {
+    let mut file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open archive {}", path.display()))?;
+    let mut magic = Vec::with_capacity(4);
+    (&mut file).take(4).read_to_end(&mut magic)?;
+    file.seek(SeekFrom::Start(0))?;
+
+    let reader = std::io::BufReader::new(file);
+    let result = match magic.as_slice() {
+        [b'P', b'K', 3, 4] | [b'P', b'K', 5, 6] => import_zip(tree, reader).await,
+        [0x1f, 0x8b, ..] => import_tar(tree, flate2::read::MultiGzDecoder::new(reader)).await,
+        [0x28, 0xb5, 0x2f, 0xfd] => {
+            import_tar(tree, zstd::stream::read::Decoder::with_buffer(reader)?).await
+        }
+        _ => import_tar(tree, reader).await,
+    };
+    result.with_context(|| format!("Failed to import archive {}", path.display()))
+}
+
 #[derive(Debug)]
 pub struct GcStats {
+    /// Unreachable blobs deleted (or that would be, in a dry run)
     pub deleted_dags: usize,
     pub freed_bytes: u64,
+    /// Number of roots walked during the mark phase
+    pub roots: usize,
+    /// Local blobs kept because they are reachable
+    pub reachable_dags: usize,
+    pub dry_run: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -2378,6 +3061,8 @@ pub struct DirEntry {
     pub cid: String,
     pub is_directory: bool,
     pub size: u64,
+    /// Mode, mtime and symlink target, if recorded at upload
+    pub posix: PosixMeta,
 }
 
 #[derive(Debug, Clone)]
@@ -5,6 +5,9 @@
 //! 2. Followed users' public trees - lower priority
 //!
 //! Uses WebRTC peers first, falls back to Blossom HTTP servers
+//!
+//! Roots are mirrored as published; merging concurrent edits is out of scope
+//! here. The FUSE mount merges before it publishes (see `hashtree_core::merge`).
 
 use anyhow::Result;
 use hashtree_core::{from_hex, to_hex, Cid};
@@ -17,7 +20,7 @@ use tokio::sync::RwLock;
 use tracing::{error, info, warn};
 
 use crate::fetch::{FetchConfig, Fetcher};
-use crate::storage::{HashtreeStore, PRIORITY_FOLLOWED, PRIORITY_OWN};
+use crate::storage::{HashtreeStore, RootVersion, PRIORITY_FOLLOWED, PRIORITY_OWN};
 use crate::webrtc::WebRTCState;
 
 /// Sync priority levels
@@ -433,6 +436,7 @@ impl BackgroundSync {
         // Extract hash and key from tags
         let mut hash_hex: Option<String> = None;
         let mut key_hex: Option<String> = None;
+        let mut prev_hex: Option<String> = None;
 
         for tag in event.tags.iter() {
             let tag_vec = tag.as_slice();
@@ -440,6 +444,7 @@ impl BackgroundSync {
                 match tag_vec[0].as_str() {
                     "hash" => hash_hex = Some(tag_vec[1].clone()),
                     "key" => key_hex = Some(tag_vec[1].clone()),
+                    "prev" => prev_hex = Some(tag_vec[1].clone()),
                     _ => {}
                 }
             }
@@ -450,6 +455,26 @@ impl BackgroundSync {
             None => return,
         };
 
+        // Delegated roots are tagged "<owner hex>/<tree>"; file them under the owner's tree
+        let (owner_hex, history_name) = match tree_name.split_once('/') {
+            Some((owner, name)) if PublicKey::from_hex(owner).is_ok() => {
+                (owner.to_string(), name.to_string())
+            }
+            _ => (event.pubkey.to_hex(), tree_name.clone()),
+        };
+        let version = RootVersion {
+            hash: to_hex(&hash),
+            key: key_hex.clone(),
+            created_at: event.created_at.as_u64(),
+            prev: prev_hex.filter(|p| from_hex(p).is_ok()),
+        };
+        if let Err(e) = self
+            .store
+            .record_root_version(&owner_hex, &history_name, version)
+        {
+            warn!("Failed to record root history for {}: {}", tree_name, e);
+        }
+
         let key = key_hex.and_then(|k| {
             let bytes = hex::decode(&k).ok()?;
             if bytes.len() == 32 {
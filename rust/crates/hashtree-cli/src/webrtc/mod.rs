@@ -4,10 +4,15 @@
 //! - Event kind: 30078 (KIND_APP_DATA)
 //! - Tag: ["l", "webrtc"]
 //! - Message types: hello, offer, answer, candidate
+//!
+//! Daemons that can reach each other directly also connect over their `/ws`
+//! endpoint (see `ws_peer`), bypassing STUN/ICE. Those can be configured or
+//! found on the local network with LAN discovery.
 
 mod peer;
 mod signaling;
 pub mod types;
+mod ws_peer;
 
 #[cfg(test)]
 mod tests;
@@ -18,3 +23,16 @@ pub use types::{
     encode_request, DataMessage, DataRequest, PeerDirection, PeerId, PeerPool, PoolConfig,
     PoolSettings, SignalingMessage, WebRTCConfig, MAX_HTL,
 };
+pub use ws_peer::{ws_peer_key, WsPeer, WsPeerStatus};
+
+// Peer selection and the persisted reputation / ban list formats
+pub use hashtree_webrtc::{
+    load_state_file, peer_pubkey, save_state_file, AccessList, PeerAccess, PeerReputation,
+    PeerSelector, ReputationMap, AUTO_BAN_CORRUPT,
+};
+
+// Upload rate limits and per-peer byte ledgers
+pub use hashtree_webrtc::{BandwidthConfig, BandwidthLimiter, ByteLedger};
+
+// Local network discovery
+pub use hashtree_webrtc::{LanDiscovery, LanDiscoveryConfig, LanEvent, LanPeer};
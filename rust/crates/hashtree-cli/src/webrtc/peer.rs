@@ -26,6 +26,7 @@ use super::types::{
     encode_message, encode_request, encode_response, hash_to_hex, parse_message, DataMessage,
     DataRequest, DataResponse, PeerDirection, PeerId, PeerStateEvent, SignalingMessage,
 };
+use super::BandwidthLimiter;
 use crate::nostr_relay::NostrRelay;
 use nostr::{ClientMessage as NostrClientMessage, JsonUtil as NostrJsonUtil};
 
@@ -71,6 +72,9 @@ pub struct Peer {
 
     // Optional Nostr relay for text messages over data channel
     nostr_relay: Option<Arc<NostrRelay>>,
+
+    // Optional upload rate limits for serving requests
+    bandwidth: Option<Arc<BandwidthLimiter>>,
 }
 
 impl Peer {
@@ -177,6 +181,7 @@ impl Peer {
             message_rx: Some(message_rx),
             state_event_tx,
             nostr_relay,
+            bandwidth: None,
         })
     }
 
@@ -185,6 +190,11 @@ impl Peer {
         self.store = Some(store);
     }
 
+    /// Rate limit responses served to this peer (call before connecting)
+    pub fn set_bandwidth_limiter(&mut self, bandwidth: Arc<BandwidthLimiter>) {
+        self.bandwidth = Some(bandwidth);
+    }
+
     /// Get connection state
     pub fn state(&self) -> RTCPeerConnectionState {
         self.pc.connection_state()
@@ -348,6 +358,7 @@ impl Peer {
         let data_channel_holder = self.data_channel.clone();
         let nostr_relay = self.nostr_relay.clone();
         let peer_pubkey = Some(self.peer_id.pubkey.clone());
+        let bandwidth = self.bandwidth.clone();
 
         self.pc
             .on_data_channel(Box::new(move |dc: Arc<RTCDataChannel>| {
@@ -358,6 +369,7 @@ impl Peer {
                 let data_channel_holder = data_channel_holder.clone();
                 let nostr_relay = nostr_relay.clone();
                 let peer_pubkey = peer_pubkey.clone();
+                let bandwidth = bandwidth.clone();
 
                 // Work MUST be inside the returned future
                 Box::pin(async move {
@@ -382,6 +394,7 @@ impl Peer {
                         store,
                         nostr_relay,
                         peer_pubkey,
+                        bandwidth,
                     )
                     .await;
                 })
@@ -480,6 +493,7 @@ impl Peer {
         let store = self.store.clone();
         let nostr_relay = self.nostr_relay.clone();
         let peer_pubkey = Some(self.peer_id.pubkey.clone());
+        let bandwidth = self.bandwidth.clone();
 
         Self::setup_dc_handlers(
             dc,
@@ -489,6 +503,7 @@ impl Peer {
             store,
             nostr_relay,
             peer_pubkey,
+            bandwidth,
         )
         .await;
         Ok(())
@@ -503,9 +518,11 @@ impl Peer {
         store: Option<Arc<dyn ContentStore>>,
         nostr_relay: Option<Arc<NostrRelay>>,
         peer_pubkey: Option<String>,
+        bandwidth: Option<Arc<BandwidthLimiter>>,
     ) {
         let label = dc.label().to_string();
         let peer_short = peer_id.short();
+        let peer_key = peer_id.to_string();
 
         // Track pending binary data (request_id -> expected after response)
         let _pending_binary: Arc<Mutex<Option<u32>>> = Arc::new(Mutex::new(None));
@@ -575,6 +592,8 @@ impl Peer {
         let nostr_client_id_for_msg = nostr_client_id;
 
         dc.on_message(Box::new(move |msg: DataChannelMessage| {
+            let peer_key = peer_key.clone();
+            let bandwidth = bandwidth.clone();
             let dc = dc_for_msg.clone();
             let peer_short = peer_short_msg.clone();
             let pending_requests = pending_requests.clone();
@@ -644,6 +663,20 @@ impl Peer {
                                 None
                             };
 
+                            // Drop the response if it would exceed the upload limits
+                            let data = data.filter(|data| {
+                                let allowed = bandwidth
+                                    .as_ref()
+                                    .is_none_or(|bw| bw.try_serve(&peer_key, data.len() as u64));
+                                if !allowed {
+                                    info!(
+                                        "[Peer {}] Upload limit hit, not serving {}",
+                                        peer_short, hash_short
+                                    );
+                                }
+                                allowed
+                            });
+
                             // Send response only if we have data
                             if let Some(data) = data {
                                 let data_len = data.len();
@@ -681,6 +714,18 @@ impl Peer {
                                 let _ = req.response_tx.send(Some(res.d));
                             }
                         }
+                        DataMessage::NotFound(msg) => {
+                            let hash_hex = hash_to_hex(&msg.h);
+                            debug!(
+                                "[Peer {}] {} not found",
+                                peer_short,
+                                &hash_hex[..8.min(hash_hex.len())]
+                            );
+                            let mut pending = pending_requests.lock().await;
+                            if let Some(req) = pending.remove(&hash_hex) {
+                                let _ = req.response_tx.send(None);
+                            }
+                        }
                     },
                     Err(e) => {
                         warn!("[Peer {}] Failed to parse message: {:?}", peer_short, e);
@@ -27,6 +27,11 @@ use super::types::{
     PeerDirection, PeerId, PeerPool, PeerStateEvent, PeerStatus, SignalingMessage, WebRTCConfig,
     HELLO_TAG, WEBRTC_KIND,
 };
+use super::ws_peer::{WsPeer, WsPeerStatus};
+use super::{
+    load_state_file, save_state_file, AccessList, BandwidthConfig, BandwidthLimiter, ByteLedger,
+    LanDiscovery, LanEvent, PeerSelector, ReputationMap,
+};
 use crate::nostr_relay::NostrRelay;
 
 /// Callback type for classifying peers into pools
@@ -72,18 +77,70 @@ pub struct WebRTCState {
     pub bytes_sent: std::sync::atomic::AtomicU64,
     /// Total bytes received across all peers (cumulative)
     pub bytes_received: std::sync::atomic::AtomicU64,
+    /// Peer reputation and ban/allow list, keyed by "pubkey:uuid"
+    pub selector: RwLock<PeerSelector>,
+    /// Upload rate limits and byte ledgers, keyed by pubkey (kept across reconnects)
+    pub bandwidth: Arc<BandwidthLimiter>,
+    /// Daemons connected directly over their `/ws` endpoint, keyed by "host:port"
+    pub ws_peers: RwLock<HashMap<String, Arc<WsPeer>>>,
+}
+
+/// How a request reaches a peer
+enum PeerLink {
+    DataChannel(Arc<webrtc::data_channel::RTCDataChannel>),
+    WebSocket(Arc<WsPeer>),
+}
+
+impl PeerLink {
+    async fn send(&self, wire: Vec<u8>) -> bool {
+        match self {
+            PeerLink::DataChannel(dc) => dc.send(&bytes::Bytes::from(wire)).await.is_ok(),
+            PeerLink::WebSocket(ws) => ws.send(wire),
+        }
+    }
 }
 
 impl WebRTCState {
     pub fn new() -> Self {
+        Self::with_bandwidth(BandwidthConfig::default())
+    }
+
+    /// Create state that rate limits serving peers
+    pub fn with_bandwidth(config: BandwidthConfig) -> Self {
         Self {
             peers: RwLock::new(HashMap::new()),
             connected_count: std::sync::atomic::AtomicUsize::new(0),
             bytes_sent: std::sync::atomic::AtomicU64::new(0),
             bytes_received: std::sync::atomic::AtomicU64::new(0),
+            selector: RwLock::new(PeerSelector::new()),
+            bandwidth: Arc::new(BandwidthLimiter::new(config)),
+            ws_peers: RwLock::new(HashMap::new()),
         }
     }
 
+    /// Status of the configured WebSocket peers
+    pub async fn ws_peer_statuses(&self) -> Vec<WsPeerStatus> {
+        let mut statuses: Vec<_> = self
+            .ws_peers
+            .read()
+            .await
+            .values()
+            .map(|peer| peer.status())
+            .collect();
+        statuses.sort_by(|a, b| a.id.cmp(&b.id));
+        statuses
+    }
+
+    /// Byte ledger for a peer (uploaded, downloaded, throttled)
+    pub fn peer_ledger(&self, peer_id: &str) -> Option<ByteLedger> {
+        self.bandwidth.ledger(peer_id)
+    }
+
+    /// Whether the reciprocity policy deprioritizes a peer
+    pub fn is_leecher(&self, peer_id: &str) -> bool {
+        self.bandwidth.is_leecher(peer_id)
+    }
+
     /// Get current bandwidth stats (bytes sent/received)
     pub fn get_bandwidth(&self) -> (u64, u64) {
         (
@@ -106,16 +163,30 @@ impl WebRTCState {
     pub async fn record_received(&self, peer_id: &str, bytes: u64) {
         self.bytes_received
             .fetch_add(bytes, std::sync::atomic::Ordering::Relaxed);
+        self.bandwidth.record_received(peer_id, bytes);
         if let Some(entry) = self.peers.write().await.get_mut(peer_id) {
             entry.bytes_received += bytes;
         }
     }
 
     /// Request content by hash from connected peers
-    /// Queries peers sequentially with 500ms intervals until one responds
-    /// Returns the first successful response, or None if no peer has it
+    /// Queries peers sequentially (best reputation first) with 500ms intervals
+    /// until one responds with data matching the hash.
+    /// Returns the first verified response, or None if no peer has it
     pub async fn request_from_peers(&self, hash_hex: &str) -> Option<Vec<u8>> {
-        use super::types::{encode_request, DataRequest, MAX_HTL};
+        self.forward_request(hash_hex, super::types::MAX_HTL, None)
+            .await
+    }
+
+    /// Request content from connected WebRTC and WebSocket peers with the
+    /// given HTL, skipping the peer the request came from
+    pub async fn forward_request(
+        &self,
+        hash_hex: &str,
+        htl: u8,
+        from: Option<&str>,
+    ) -> Option<Vec<u8>> {
+        use super::types::{encode_request, DataRequest};
 
         let peers = self.peers.read().await;
 
@@ -127,7 +198,7 @@ impl WebRTCState {
             .filter_map(|p| {
                 p.peer.as_ref().map(|peer| {
                     (
-                        p.peer_id.short(),
+                        p.peer_id.to_string(),
                         peer.data_channel.clone(),
                         peer.pending_requests.clone(),
                     )
@@ -141,13 +212,42 @@ impl WebRTCState {
         let mut connected_peers: Vec<(
             String,
             Arc<Mutex<HashMap<String, PendingRequest>>>,
-            Arc<webrtc::data_channel::RTCDataChannel>,
+            PeerLink,
         )> = Vec::new();
         for (peer_id, dc_mutex, pending) in peer_refs {
             let dc_guard = dc_mutex.lock().await;
             if let Some(dc) = dc_guard.as_ref() {
-                connected_peers.push((peer_id, pending, dc.clone()));
+                connected_peers.push((peer_id, pending, PeerLink::DataChannel(dc.clone())));
+            }
+        }
+        for (key, ws) in self.ws_peers.read().await.iter() {
+            if ws.is_connected() {
+                connected_peers.push((
+                    key.clone(),
+                    ws.pending_requests.clone(),
+                    PeerLink::WebSocket(ws.clone()),
+                ));
+            }
+        }
+        if let Some(from) = from {
+            connected_peers.retain(|(peer_id, _, _)| peer_id != from);
+        }
+
+        // Order by selector preference (backed-off peers last); banned and
+        // auto-excluded peers are skipped
+        {
+            let mut selector = self.selector.write().await;
+            for (peer_id, _, _) in &connected_peers {
+                selector.add_peer(peer_id.clone());
             }
+            let order = selector.select_peers();
+            connected_peers.retain(|(peer_id, _, _)| !selector.is_blocked(peer_id));
+            connected_peers.sort_by_key(|(peer_id, _, _)| {
+                order
+                    .iter()
+                    .position(|id| id == peer_id)
+                    .unwrap_or(usize::MAX)
+            });
         }
 
         if connected_peers.is_empty() {
@@ -171,7 +271,7 @@ impl WebRTCState {
         };
 
         // Query peers sequentially with 500ms delay between each
-        for (_i, (peer_id, pending_requests, dc)) in connected_peers.into_iter().enumerate() {
+        for (_i, (peer_id, pending_requests, link)) in connected_peers.into_iter().enumerate() {
             debug!(
                 "Querying peer {} for {}",
                 peer_id,
@@ -196,30 +296,62 @@ impl WebRTCState {
             // Send request
             let req = DataRequest {
                 h: hash_bytes.clone(),
-                htl: MAX_HTL,
+                htl,
             };
             if let Ok(wire) = encode_request(&req) {
                 let wire_len = wire.len() as u64;
-                if dc.send(&bytes::Bytes::from(wire)).await.is_ok() {
+                if link.send(wire).await {
                     self.record_sent(&peer_id, wire_len).await;
+                    self.selector
+                        .write()
+                        .await
+                        .record_request(&peer_id, wire_len);
+                    let started = Instant::now();
                     // Wait 500ms for response from this peer
-                    match tokio::time::timeout(std::time::Duration::from_millis(500), rx).await {
+                    let response =
+                        tokio::time::timeout(std::time::Duration::from_millis(500), rx).await;
+                    let rtt_ms = started.elapsed().as_millis() as u64;
+                    match response {
                         Ok(Ok(Some(data))) => {
                             self.record_received(&peer_id, data.len() as u64).await;
-                            debug!(
-                                "Got response from peer {} for {}",
+                            if hashtree_core::sha256(&data).as_slice() == hash_bytes.as_slice() {
+                                self.selector.write().await.record_success(
+                                    &peer_id,
+                                    rtt_ms,
+                                    data.len() as u64,
+                                );
+                                debug!(
+                                    "Got response from peer {} for {}",
+                                    peer_id,
+                                    &hash_hex[..8.min(hash_hex.len())]
+                                );
+                                return Some(data);
+                            }
+                            // Don't trust this peer's data again, try the next one
+                            warn!(
+                                "Peer {} sent data not matching {}",
                                 peer_id,
                                 &hash_hex[..8.min(hash_hex.len())]
                             );
-                            return Some(data);
+                            self.selector.write().await.record_corrupt(&peer_id);
+                        }
+                        Ok(Ok(None)) => {
+                            self.selector
+                                .write()
+                                .await
+                                .record_not_found(&peer_id, rtt_ms);
                         }
-                        _ => {
-                            // Timeout or no data - clean up and try next peer
+                        Err(_) => {
+                            // Timeout - clean up and try next peer
                             debug!(
                                 "No response from peer {} for {}",
                                 peer_id,
                                 &hash_hex[..8.min(hash_hex.len())]
                             );
+                            self.selector.write().await.record_timeout(&peer_id);
+                        }
+                        Ok(Err(_)) => {
+                            self.selector.write().await.record_failure(&peer_id);
                         }
                     }
                 }
@@ -272,11 +404,29 @@ impl WebRTCManager {
         // Default classifier: all peers go to 'other' pool
         let peer_classifier: PeerClassifier = Arc::new(|_| PeerPool::Other);
 
+        let state = WebRTCState::with_bandwidth(config.bandwidth.clone());
+        {
+            // Nobody else holds the state yet, so the lock is always free
+            let mut selector = state.selector.try_write().expect("fresh selector lock");
+            if let Some(path) = &config.peer_reputation_path {
+                match load_state_file::<ReputationMap>(path) {
+                    Ok(reputation) => selector.restore_reputation(reputation),
+                    Err(e) => warn!("Failed to load peer reputation: {}", e),
+                }
+            }
+            if let Some(path) = &config.peer_access_path {
+                match load_state_file::<AccessList>(path) {
+                    Ok(list) => selector.set_access_list(list),
+                    Err(e) => warn!("Failed to load peer ban list: {}", e),
+                }
+            }
+        }
+
         Self {
             config,
             my_peer_id,
             keys,
-            state: Arc::new(WebRTCState::new()),
+            state: Arc::new(state),
             shutdown: Arc::new(shutdown),
             shutdown_rx,
             signaling_tx,
@@ -400,7 +550,8 @@ impl WebRTCManager {
                         follows_connected += 1;
                     }
                 }
-                PeerPool::Other => {
+                // WebRTC peers are never in the LAN pool; count any with other
+                PeerPool::Other | PeerPool::Lan => {
                     if is_active {
                         other_active += 1;
                     }
@@ -424,7 +575,9 @@ impl WebRTCManager {
         let (_, follows_active, _, other_active) = *pool_counts;
         match pool {
             PeerPool::Follows => follows_active < self.config.pools.follows.max_connections,
-            PeerPool::Other => other_active < self.config.pools.other.max_connections,
+            PeerPool::Other | PeerPool::Lan => {
+                other_active < self.config.pools.other.max_connections
+            }
         }
     }
 
@@ -440,7 +593,9 @@ impl WebRTCManager {
             PeerPool::Follows => {
                 follows_connected >= self.config.pools.follows.satisfied_connections
             }
-            PeerPool::Other => other_connected >= self.config.pools.other.satisfied_connections,
+            PeerPool::Other | PeerPool::Lan => {
+                other_connected >= self.config.pools.other.satisfied_connections
+            }
         }
     }
 
@@ -508,6 +663,42 @@ impl WebRTCManager {
             });
         }
 
+        // Connect directly to configured daemons over their /ws endpoint
+        for url in &self.config.ws_peers {
+            match WsPeer::new(url) {
+                Ok(peer) => {
+                    let peer = Arc::new(peer);
+                    self.state
+                        .ws_peers
+                        .write()
+                        .await
+                        .insert(peer.key.clone(), peer.clone());
+                    tokio::spawn(peer.run(
+                        self.state.clone(),
+                        self.store.clone(),
+                        self.shutdown_rx.clone(),
+                    ));
+                }
+                Err(e) => warn!("{}", e),
+            }
+        }
+
+        // Find daemons on the local network, which works without any relay
+        if let Some(lan_config) = self.config.lan_discovery.clone() {
+            match LanDiscovery::bind(self.my_peer_id.to_string(), lan_config).await {
+                Ok(discovery) => {
+                    tokio::spawn(Self::lan_task(
+                        Arc::new(discovery),
+                        self.state.clone(),
+                        self.store.clone(),
+                        self.config.lan_max_peers,
+                        self.shutdown_rx.clone(),
+                    ));
+                }
+                Err(e) => warn!("LAN discovery disabled: {}", e),
+            }
+        }
+
         // Process incoming events and outgoing signaling messages
         let mut shutdown_rx = self.shutdown_rx.clone();
         // Cleanup interval - run every 30 seconds as a fallback (not for real-time sync)
@@ -517,6 +708,7 @@ impl WebRTCManager {
                 _ = shutdown_rx.changed() => {
                     if *shutdown_rx.borrow() {
                         info!("WebRTC manager shutting down");
+                        self.sync_peer_state().await;
                         break;
                     }
                 }
@@ -536,6 +728,7 @@ impl WebRTCManager {
                 _ = cleanup_interval.tick() => {
                     // Periodic cleanup of stale peers and state sync (fallback)
                     self.cleanup_stale_peers().await;
+                    self.sync_peer_state().await;
                 }
             }
         }
@@ -543,6 +736,75 @@ impl WebRTCManager {
         Ok(())
     }
 
+    /// Connect to daemons announced on the local network over their `/ws`
+    /// endpoint, and drop them once they stop announcing. Peers turned away
+    /// while the LAN pool is full are forgotten, so they're offered again on
+    /// their next announcement.
+    async fn lan_task(
+        discovery: Arc<LanDiscovery>,
+        state: Arc<WebRTCState>,
+        store: Option<Arc<dyn ContentStore>>,
+        max_peers: usize,
+        shutdown_rx: tokio::sync::watch::Receiver<bool>,
+    ) {
+        let (event_tx, mut event_rx) = mpsc::channel(32);
+        let runner = discovery.clone();
+        tokio::spawn(async move { runner.run(event_tx, shutdown_rx).await });
+
+        // LAN peer ID -> (WebSocket peer key, stop signal for its connection)
+        let mut connections: HashMap<String, (String, tokio::sync::watch::Sender<bool>)> =
+            HashMap::new();
+        while let Some(event) = event_rx.recv().await {
+            let peer_id = match &event {
+                LanEvent::Discovered(peer) => peer.peer_id.clone(),
+                LanEvent::Lost(peer_id) => peer_id.clone(),
+            };
+            // Lost peers and peers announcing a new endpoint drop the old connection
+            if let Some((key, stop)) = connections.remove(&peer_id) {
+                let _ = stop.send(true);
+                state.ws_peers.write().await.remove(&key);
+            }
+
+            let LanEvent::Discovered(peer) = event else {
+                continue;
+            };
+            let Some(endpoint) = peer.endpoint else {
+                continue;
+            };
+            let Ok(ws) = WsPeer::new(&format!("ws://{}/ws", endpoint)) else {
+                continue;
+            };
+            let ws = Arc::new(ws.with_pool(PeerPool::Lan));
+            {
+                let mut ws_peers = state.ws_peers.write().await;
+                // Already connected, e.g. also listed in ws_peers
+                if ws_peers.contains_key(&ws.key) {
+                    continue;
+                }
+                let lan_count = ws_peers
+                    .values()
+                    .filter(|p| p.pool == PeerPool::Lan)
+                    .count();
+                if lan_count >= max_peers {
+                    debug!("Ignoring LAN peer {} - LAN pool full", peer.peer_id);
+                    discovery.forget(&peer.peer_id);
+                    continue;
+                }
+                ws_peers.insert(ws.key.clone(), ws.clone());
+            }
+
+            info!("Found LAN peer {} at {}", peer.peer_id, endpoint);
+            let (stop_tx, stop_rx) = tokio::sync::watch::channel(false);
+            connections.insert(peer.peer_id, (ws.key.clone(), stop_tx));
+            tokio::spawn(ws.run(state.clone(), store.clone(), stop_rx));
+        }
+
+        // Discovery stops at shutdown
+        for (_, stop) in connections.into_values() {
+            let _ = stop.send(true);
+        }
+    }
+
     /// Connect to a single relay and handle messages
     async fn relay_task(
         url: String,
@@ -957,6 +1219,11 @@ impl WebRTCManager {
         their_uuid: &str,
         relay_write_tx: &tokio::sync::broadcast::Sender<SignalingMessage>,
     ) -> Result<()> {
+        if self.state.selector.read().await.is_blocked(sender_pubkey) {
+            debug!("Ignoring hello from blocked peer {}", sender_pubkey);
+            return Ok(());
+        }
+
         let full_peer_id = PeerId::new(sender_pubkey.to_string(), Some(their_uuid.to_string()));
         let peer_key = full_peer_id.to_string();
 
@@ -1072,6 +1339,7 @@ impl WebRTCManager {
             self.nostr_relay.clone(),
         )
         .await?;
+        peer.set_bandwidth_limiter(self.state.bandwidth.clone());
 
         peer.setup_handlers().await?;
 
@@ -1116,6 +1384,10 @@ impl WebRTCManager {
             &sender_pubkey[..8.min(sender_pubkey.len())],
             their_uuid
         );
+        if self.state.selector.read().await.is_blocked(sender_pubkey) {
+            debug!("Refusing offer from blocked peer {}", sender_pubkey);
+            return Ok(());
+        }
         let full_peer_id = PeerId::new(sender_pubkey.to_string(), Some(their_uuid.to_string()));
         let peer_key = full_peer_id.to_string();
 
@@ -1186,6 +1458,7 @@ impl WebRTCManager {
             self.nostr_relay.clone(),
         )
         .await?;
+        peer.set_bandwidth_limiter(self.state.bandwidth.clone());
         debug!("Peer connection created for {}", full_peer_id.short());
 
         peer.setup_handlers().await?;
@@ -1350,6 +1623,7 @@ impl WebRTCManager {
                     "Peer {} connection failed - removing from pool",
                     peer_id.short()
                 );
+                self.state.selector.write().await.remove_peer(&peer_key);
                 let mut peers = self.state.peers.write().await;
                 if let Some(entry) = peers.remove(&peer_key) {
                     // Decrement connected count if was connected
@@ -1367,6 +1641,7 @@ impl WebRTCManager {
             PeerStateEvent::Disconnected(peer_id) => {
                 let peer_key = peer_id.to_string();
                 info!("Peer {} disconnected - removing from pool", peer_id.short());
+                self.state.selector.write().await.remove_peer(&peer_key);
                 let mut peers = self.state.peers.write().await;
                 if let Some(entry) = peers.remove(&peer_key) {
                     // Decrement connected count if was connected
@@ -1436,6 +1711,48 @@ impl WebRTCManager {
             .connected_count
             .store(connected_count, std::sync::atomic::Ordering::Relaxed);
     }
+
+    /// Pick up ban list edits (`htree peer ban`), drop newly blocked peers and
+    /// persist reputation
+    async fn sync_peer_state(&self) {
+        if let Some(path) = &self.config.peer_access_path {
+            match load_state_file::<AccessList>(path) {
+                Ok(list) => self.state.selector.write().await.set_access_list(list),
+                Err(e) => warn!("Failed to load peer ban list: {}", e),
+            }
+        }
+
+        let blocked: Vec<String> = {
+            let selector = self.state.selector.read().await;
+            let peers = self.state.peers.read().await;
+            peers
+                .keys()
+                .filter(|key| selector.is_blocked(key))
+                .cloned()
+                .collect()
+        };
+        for key in blocked {
+            let entry = self.state.peers.write().await.remove(&key);
+            if let Some(entry) = entry {
+                info!("Dropping blocked peer {}", entry.peer_id.short());
+                if entry.state == ConnectionState::Connected {
+                    self.state
+                        .connected_count
+                        .fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
+                }
+                if let Some(peer) = entry.peer {
+                    let _ = peer.close().await;
+                }
+            }
+        }
+
+        if let Some(path) = &self.config.peer_reputation_path {
+            let reputation = self.state.selector.read().await.reputation();
+            if let Err(e) = save_state_file(path, &reputation) {
+                warn!("Failed to save peer reputation: {}", e);
+            }
+        }
+    }
 }
 
 // Keep the old PeerState for backward compatibility with tests
@@ -1447,3 +1764,137 @@ pub struct PeerState {
     pub state: String,
     pub last_seen: Instant,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hashtree_webrtc::LanDiscoveryConfig;
+    use std::net::SocketAddr;
+
+    /// A `/ws` endpoint that accepts connections and keeps them open
+    async fn ws_endpoint() -> SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let Ok(mut ws) = tokio_tungstenite::accept_async(stream).await else {
+                        return;
+                    };
+                    while let Some(Ok(_)) = ws.next().await {}
+                });
+            }
+        });
+        addr
+    }
+
+    /// A loopback address nothing is bound to
+    fn free_loopback() -> SocketAddr {
+        std::net::UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+    }
+
+    async fn wait_for_ws_peers(state: &WebRTCState, done: impl Fn(&[WsPeerStatus]) -> bool) {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while !done(&state.ws_peer_statuses().await) {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("WebSocket peers never reached the expected state");
+    }
+
+    #[tokio::test]
+    async fn test_lan_task_connects_and_drops_discovered_daemons() {
+        let endpoint = ws_endpoint().await;
+        let target = free_loopback();
+        let discovery = LanDiscovery::bind(
+            "local:1",
+            LanDiscoveryConfig::default()
+                .with_addrs(target, target)
+                .with_interval(Duration::from_millis(20))
+                .with_peer_timeout(Duration::from_millis(300)),
+        )
+        .await
+        .unwrap();
+        let state = Arc::new(WebRTCState::new());
+        let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
+        tokio::spawn(WebRTCManager::lan_task(
+            Arc::new(discovery),
+            state.clone(),
+            None,
+            10,
+            shutdown_rx,
+        ));
+
+        // The other daemon announces its `/ws` port until it goes away
+        let remote = LanDiscovery::bind(
+            "remote:2",
+            LanDiscoveryConfig::default()
+                .with_addrs("127.0.0.1:0".parse().unwrap(), target)
+                .with_endpoint_port(endpoint.port()),
+        )
+        .await
+        .unwrap();
+        let announcing = tokio::spawn(async move {
+            loop {
+                let _ = remote.announce().await;
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        });
+
+        let key = endpoint.to_string();
+        wait_for_ws_peers(&state, |peers| {
+            peers.len() == 1 && peers[0].id == key && peers[0].pool == "Lan" && peers[0].connected
+        })
+        .await;
+
+        announcing.abort();
+        wait_for_ws_peers(&state, |peers| peers.is_empty()).await;
+        shutdown_tx.send(true).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_lan_task_respects_max_peers() {
+        let endpoint = ws_endpoint().await;
+        let target = free_loopback();
+        let discovery = LanDiscovery::bind(
+            "local:1",
+            LanDiscoveryConfig::default().with_addrs(target, target),
+        )
+        .await
+        .unwrap();
+        let discovery = Arc::new(discovery);
+        let state = Arc::new(WebRTCState::new());
+        let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
+        let task = tokio::spawn(WebRTCManager::lan_task(
+            discovery.clone(),
+            state.clone(),
+            None,
+            0,
+            shutdown_rx,
+        ));
+
+        let remote = LanDiscovery::bind(
+            "remote:2",
+            LanDiscoveryConfig::default()
+                .with_addrs("127.0.0.1:0".parse().unwrap(), target)
+                .with_endpoint_port(endpoint.port()),
+        )
+        .await
+        .unwrap();
+        remote.announce().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(state.ws_peer_statuses().await.is_empty());
+        // Forgotten, so its next announcement is offered to the pool again
+        assert!(discovery.peers().is_empty());
+
+        shutdown_tx.send(true).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), task)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
@@ -120,9 +120,51 @@ fn test_wire_format_response_encode_decode() {
     }
 }
 
+#[test]
+fn test_wire_format_not_found_encode_decode() {
+    let encoded = encode_not_found(&DataNotFound { h: vec![0xef; 32] }).unwrap();
+    assert_eq!(encoded[0], MSG_TYPE_NOT_FOUND);
+
+    match parse_message(&encoded).unwrap() {
+        DataMessage::NotFound(msg) => assert_eq!(msg.h, vec![0xef; 32]),
+        _ => panic!("Expected not found"),
+    }
+}
+
 #[test]
 fn test_wire_format_constants() {
     // These must match hashtree-ts constants
     assert_eq!(MSG_TYPE_REQUEST, 0x00);
     assert_eq!(MSG_TYPE_RESPONSE, 0x01);
+    // Same as hashtree-webrtc's not-found message
+    assert_eq!(
+        MSG_TYPE_NOT_FOUND,
+        hashtree_webrtc::protocol::MSG_TYPE_NOT_FOUND
+    );
+}
+
+#[test]
+fn test_ws_peer_key_and_url() {
+    use super::{ws_peer_key, WsPeer};
+
+    assert_eq!(
+        ws_peer_key("ws://10.0.0.2:8080/ws").as_deref(),
+        Some("10.0.0.2:8080")
+    );
+    assert_eq!(
+        ws_peer_key("wss://node.example.com").as_deref(),
+        Some("node.example.com")
+    );
+    assert!(ws_peer_key("https://node.example.com/ws").is_none());
+    assert!(ws_peer_key("ws:///ws").is_none());
+
+    // Bare host gets the daemon's /ws endpoint, explicit paths are kept
+    let peer = WsPeer::new("ws://10.0.0.2:8080/").unwrap();
+    assert_eq!(peer.url, "ws://10.0.0.2:8080/ws");
+    assert_eq!(peer.key, "10.0.0.2:8080");
+    let peer = WsPeer::new("wss://node.example.com/custom/ws").unwrap();
+    assert_eq!(peer.url, "wss://node.example.com/custom/ws");
+    assert!(!peer.is_connected());
+    assert!(!peer.send(vec![0]));
+    assert!(WsPeer::new("http://10.0.0.2:8080").is_err());
 }
@@ -312,6 +312,18 @@ pub struct WebRTCConfig {
     pub debug: bool,
     /// Pool settings for follows and other peers
     pub pools: PoolSettings,
+    /// JSON file persisting peer reputation across restarts
+    pub peer_reputation_path: Option<std::path::PathBuf>,
+    /// JSON file with the peer ban/allow list (`htree peer ban`), re-read periodically
+    pub peer_access_path: Option<std::path::PathBuf>,
+    /// Upload rate limits and reciprocity policy for serving peers
+    pub bandwidth: hashtree_webrtc::BandwidthConfig,
+    /// Other daemons' `/ws` endpoints to connect to directly, without WebRTC
+    pub ws_peers: Vec<String>,
+    /// Find daemons on the local network and connect to them over `/ws`
+    pub lan_discovery: Option<hashtree_webrtc::LanDiscoveryConfig>,
+    /// Most daemons found by LAN discovery to stay connected to at once
+    pub lan_max_peers: usize,
 }
 
 impl Default for WebRTCConfig {
@@ -335,6 +347,12 @@ impl Default for WebRTCConfig {
             ],
             debug: false,
             pools: PoolSettings::default(),
+            peer_reputation_path: None,
+            peer_access_path: None,
+            bandwidth: hashtree_webrtc::BandwidthConfig::default(),
+            ws_peers: Vec::new(),
+            lan_discovery: None,
+            lan_max_peers: 10,
         }
     }
 }
@@ -375,6 +393,9 @@ pub enum PeerPool {
     Follows,
     /// Everyone else
     Other,
+    /// Daemons found by LAN discovery, connected over their `/ws` endpoint
+    /// (only WebSocket peers, never WebRTC ones)
+    Lan,
 }
 
 /// Configuration for a peer pool
@@ -429,6 +450,7 @@ impl std::fmt::Display for PeerDirection {
 /// Message type bytes (prefix before MessagePack body)
 pub const MSG_TYPE_REQUEST: u8 = 0x00;
 pub const MSG_TYPE_RESPONSE: u8 = 0x01;
+pub const MSG_TYPE_NOT_FOUND: u8 = 0x03;
 
 /// Hashtree data channel protocol messages
 /// Shared between WebRTC data channels and WebSocket transport
@@ -436,6 +458,7 @@ pub const MSG_TYPE_RESPONSE: u8 = 0x01;
 /// Wire format: [type byte][msgpack body]
 /// Request:  [0x00][msgpack: {h: bytes32, htl?: u8}]
 /// Response: [0x01][msgpack: {h: bytes32, d: bytes}]
+/// NotFound: [0x03][msgpack: {h: bytes32}]
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DataRequest {
@@ -453,13 +476,23 @@ pub struct DataResponse {
     pub d: Vec<u8>, // Data
 }
 
+/// Explicit answer that the responder doesn't have a hash, so the requester
+/// can move on without waiting out its timeout
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataNotFound {
+    #[serde(with = "serde_bytes")]
+    pub h: Vec<u8>, // 32-byte hash
+}
+
 #[derive(Debug, Clone)]
 pub enum DataMessage {
     Request(DataRequest),
     Response(DataResponse),
+    NotFound(DataNotFound),
 }
 
-fn default_htl() -> u8 {
+/// HTL of requests that don't carry one
+pub(crate) fn default_htl() -> u8 {
     MAX_HTL
 }
 
@@ -487,6 +520,15 @@ pub fn encode_response(res: &DataResponse) -> Result<Vec<u8>, rmp_serde::encode:
     Ok(result)
 }
 
+/// Encode a not-found answer to wire format: [0x03][msgpack body]
+pub fn encode_not_found(msg: &DataNotFound) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+    let body = rmp_serde::to_vec_named(msg)?;
+    let mut result = Vec::with_capacity(1 + body.len());
+    result.push(MSG_TYPE_NOT_FOUND);
+    result.extend(body);
+    Ok(result)
+}
+
 /// Parse a wire format message
 pub fn parse_message(data: &[u8]) -> Result<DataMessage, rmp_serde::decode::Error> {
     if data.is_empty() {
@@ -505,6 +547,10 @@ pub fn parse_message(data: &[u8]) -> Result<DataMessage, rmp_serde::decode::Erro
             let res: DataResponse = rmp_serde::from_slice(body)?;
             Ok(DataMessage::Response(res))
         }
+        MSG_TYPE_NOT_FOUND => {
+            let msg: DataNotFound = rmp_serde::from_slice(body)?;
+            Ok(DataMessage::NotFound(msg))
+        }
         _ => Err(rmp_serde::decode::Error::LengthMismatch(msg_type as u32)),
     }
 }
@@ -519,5 +565,6 @@ pub fn encode_message(msg: &DataMessage) -> Result<Vec<u8>, rmp_serde::encode::E
     match msg {
         DataMessage::Request(req) => encode_request(req),
         DataMessage::Response(res) => encode_response(res),
+        DataMessage::NotFound(msg) => encode_not_found(msg),
     }
 }
@@ -0,0 +1,360 @@
+//! Direct daemon-to-daemon transport over another daemon's `/ws` endpoint
+//!
+//! Servers that can reach each other directly don't need STUN/ICE: the daemon
+//! dials each configured peer's `/ws` endpoint and speaks the data channel's
+//! MessagePack request/response protocol over binary frames. WebSocket peers
+//! share the peer selector, reputation and bandwidth ledgers with WebRTC
+//! peers, keyed by the URL's "host:port".
+//!
+//! Requests the remote daemon forwards to us are served from the local store,
+//! or passed on to our other peers while HTL allows.
+
+use anyhow::{bail, Result};
+use futures::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, watch, Mutex};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+use tracing::{debug, info, warn};
+
+use super::peer::{ContentStore, PendingRequest};
+use super::signaling::WebRTCState;
+use super::types::{
+    decrement_htl, default_htl, encode_not_found, encode_response, hash_to_hex, parse_message,
+    should_forward, DataMessage, DataNotFound, DataResponse, PeerHTLConfig, PeerPool,
+};
+
+/// First reconnect delay after a connection drops or fails
+const MIN_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+/// Reconnect delays double up to this
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+/// Give up on a connection attempt after this long
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// JSON request the relay sends to clients it still considers JSON clients
+#[derive(Debug, Deserialize)]
+struct WsJsonRequest {
+    #[serde(rename = "type")]
+    kind: String,
+    id: u32,
+    hash: String,
+    /// Hops left; relays forwarding a daemon's request pass its HTL on
+    #[serde(default = "default_htl")]
+    htl: u8,
+}
+
+#[derive(Debug, Serialize)]
+struct WsJsonResponse<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    id: u32,
+    hash: &'a str,
+    found: bool,
+}
+
+/// Status of a WebSocket peer (for `/api/peers`)
+#[derive(Debug, Clone, Serialize)]
+pub struct WsPeerStatus {
+    pub id: String,
+    pub url: String,
+    pub pool: String,
+    pub connected: bool,
+}
+
+/// Selector and ledger key for a `/ws` URL: its "host:port"
+pub fn ws_peer_key(url: &str) -> Option<String> {
+    let rest = url
+        .strip_prefix("ws://")
+        .or_else(|| url.strip_prefix("wss://"))?;
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    if authority.is_empty() {
+        return None;
+    }
+    Some(authority.to_string())
+}
+
+/// Another daemon reached through its `/ws` endpoint
+pub struct WsPeer {
+    /// Endpoint URL, e.g. `ws://10.0.0.2:8080/ws`
+    pub url: String,
+    /// Key in the peer selector and bandwidth ledgers ("host:port")
+    pub key: String,
+    /// Configured peers are in the other pool, discovered ones in the LAN pool
+    pub pool: PeerPool,
+    /// Requests awaiting a response, keyed by hash hex
+    pub pending_requests: Arc<Mutex<HashMap<String, PendingRequest>>>,
+    /// Outgoing frames while connected
+    sender: std::sync::Mutex<Option<mpsc::UnboundedSender<Message>>>,
+    /// HTL decrement behaviour towards this peer, fixed per peer
+    htl_config: PeerHTLConfig,
+}
+
+impl WsPeer {
+    /// Create a peer for a `ws://` or `wss://` URL; a URL without a path
+    /// gets the daemon's `/ws` endpoint
+    pub fn new(url: &str) -> Result<Self> {
+        let Some(key) = ws_peer_key(url) else {
+            bail!(
+                "Invalid WebSocket peer URL (expected ws:// or wss://): {}",
+                url
+            );
+        };
+        let url = url.trim_end_matches('/');
+        let scheme_len = url.find("://").map(|i| i + 3).unwrap_or(0);
+        let url = if url[scheme_len..].contains('/') {
+            url.to_string()
+        } else {
+            format!("{}/ws", url)
+        };
+
+        Ok(Self {
+            url,
+            key,
+            pool: PeerPool::Other,
+            pending_requests: Arc::new(Mutex::new(HashMap::new())),
+            sender: std::sync::Mutex::new(None),
+            htl_config: PeerHTLConfig::new(),
+        })
+    }
+
+    pub fn with_pool(mut self, pool: PeerPool) -> Self {
+        self.pool = pool;
+        self
+    }
+
+    /// Whether the connection is currently up
+    pub fn is_connected(&self) -> bool {
+        self.sender
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|tx| !tx.is_closed())
+    }
+
+    /// Queue a binary frame; false when not connected
+    pub fn send(&self, wire: Vec<u8>) -> bool {
+        self.sender
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|tx| tx.send(Message::Binary(wire)).is_ok())
+    }
+
+    /// Current status for `/api/peers`
+    pub fn status(&self) -> WsPeerStatus {
+        WsPeerStatus {
+            id: self.key.clone(),
+            url: self.url.clone(),
+            pool: format!("{:?}", self.pool),
+            connected: self.is_connected(),
+        }
+    }
+
+    /// Keep a connection to the peer open until shutdown, reconnecting with
+    /// exponential backoff
+    pub async fn run(
+        self: Arc<Self>,
+        state: Arc<WebRTCState>,
+        store: Option<Arc<dyn ContentStore>>,
+        mut shutdown_rx: watch::Receiver<bool>,
+    ) {
+        let mut delay = MIN_RECONNECT_DELAY;
+        while !*shutdown_rx.borrow() {
+            let started = Instant::now();
+            match self.session(&state, &store, &mut shutdown_rx).await {
+                Ok(()) => info!("WebSocket peer {} disconnected", self.url),
+                Err(e) => debug!("WebSocket peer {} error: {}", self.url, e),
+            }
+            if *shutdown_rx.borrow() {
+                break;
+            }
+
+            // A connection that stayed up for a while starts over at the minimum
+            if started.elapsed() > MAX_RECONNECT_DELAY {
+                delay = MIN_RECONNECT_DELAY;
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = shutdown_rx.changed() => {}
+            }
+            delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+        }
+    }
+
+    /// Run one connection until it closes
+    async fn session(
+        self: &Arc<Self>,
+        state: &Arc<WebRTCState>,
+        store: &Option<Arc<dyn ContentStore>>,
+        shutdown_rx: &mut watch::Receiver<bool>,
+    ) -> Result<()> {
+        let (ws, _) = tokio::time::timeout(CONNECT_TIMEOUT, connect_async(self.url.as_str()))
+            .await
+            .map_err(|_| anyhow::anyhow!("connect timed out"))??;
+        let (mut write, mut read) = ws.split();
+        let (tx, mut rx) = mpsc::unbounded_channel::<Message>();
+        *self.sender.lock().unwrap() = Some(tx);
+        info!("Connected to WebSocket peer {}", self.url);
+
+        let result = loop {
+            tokio::select! {
+                _ = shutdown_rx.changed() => {
+                    if *shutdown_rx.borrow() {
+                        let _ = write.send(Message::Close(None)).await;
+                        break Ok(());
+                    }
+                }
+                Some(msg) = rx.recv() => {
+                    if let Err(e) = write.send(msg).await {
+                        break Err(e.into());
+                    }
+                }
+                msg = read.next() => match msg {
+                    Some(Ok(Message::Binary(data))) => self.handle_binary(&data, state, store).await,
+                    Some(Ok(Message::Text(text))) => self.handle_text(&text, state, store),
+                    Some(Ok(Message::Close(_))) | None => break Ok(()),
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => break Err(e.into()),
+                },
+            }
+        };
+
+        *self.sender.lock().unwrap() = None;
+        // Dropping the senders fails outstanding requests instead of waiting out their timeout
+        self.pending_requests.lock().await.clear();
+        result
+    }
+
+    async fn handle_binary(
+        self: &Arc<Self>,
+        data: &[u8],
+        state: &Arc<WebRTCState>,
+        store: &Option<Arc<dyn ContentStore>>,
+    ) {
+        match parse_message(data) {
+            Ok(DataMessage::Request(req)) => {
+                let peer = Arc::clone(self);
+                let state = Arc::clone(state);
+                let store = store.clone();
+                // Forwarding can take a while, don't hold up the read loop
+                tokio::spawn(async move {
+                    let hash_hex = hash_to_hex(&req.h);
+                    let wire = match peer.lookup(&hash_hex, req.htl, &state, &store).await {
+                        Some(data) => encode_response(&DataResponse { h: req.h, d: data }),
+                        None => encode_not_found(&DataNotFound { h: req.h }),
+                    };
+                    if let Ok(wire) = wire {
+                        peer.send(wire);
+                    }
+                });
+            }
+            Ok(DataMessage::Response(res)) => {
+                let hash_hex = hash_to_hex(&res.h);
+                if let Some(pending) = self.pending_requests.lock().await.remove(&hash_hex) {
+                    let _ = pending.response_tx.send(Some(res.d));
+                }
+            }
+            Ok(DataMessage::NotFound(msg)) => {
+                let hash_hex = hash_to_hex(&msg.h);
+                if let Some(pending) = self.pending_requests.lock().await.remove(&hash_hex) {
+                    let _ = pending.response_tx.send(None);
+                }
+            }
+            Err(e) => debug!("Bad frame from WebSocket peer {}: {}", self.url, e),
+        }
+    }
+
+    /// The relay treats a client as a JSON client until it sends MessagePack,
+    /// so it may forward JSON requests before our first request goes out
+    fn handle_text(
+        self: &Arc<Self>,
+        text: &str,
+        state: &Arc<WebRTCState>,
+        store: &Option<Arc<dyn ContentStore>>,
+    ) {
+        let Ok(req) = serde_json::from_str::<WsJsonRequest>(text) else {
+            return;
+        };
+        if req.kind != "req" {
+            return;
+        }
+
+        let peer = Arc::clone(self);
+        let state = Arc::clone(state);
+        let store = store.clone();
+        tokio::spawn(async move {
+            let hash_hex = req.hash.to_lowercase();
+            let data = peer.lookup(&hash_hex, req.htl, &state, &store).await;
+            let response = WsJsonResponse {
+                kind: "res",
+                id: req.id,
+                hash: &req.hash,
+                found: data.is_some(),
+            };
+            let Ok(text) = serde_json::to_string(&response) else {
+                return;
+            };
+            let sender = peer.sender.lock().unwrap().clone();
+            let Some(tx) = sender else {
+                return;
+            };
+            let _ = tx.send(Message::Text(text));
+            if let Some(data) = data {
+                // Legacy binary response: [4-byte LE request id][data]
+                let mut packet = Vec::with_capacity(4 + data.len());
+                packet.extend_from_slice(&req.id.to_le_bytes());
+                packet.extend_from_slice(&data);
+                let _ = tx.send(Message::Binary(packet));
+            }
+        });
+    }
+
+    /// Find data the remote daemon asked for: local store first, then our
+    /// other peers while HTL allows. Upload and forward limits apply.
+    async fn lookup(
+        &self,
+        hash_hex: &str,
+        htl: u8,
+        state: &WebRTCState,
+        store: &Option<Arc<dyn ContentStore>>,
+    ) -> Option<Vec<u8>> {
+        if state.selector.read().await.is_blocked(&self.key) {
+            return None;
+        }
+
+        let local = store.as_ref().and_then(|store| {
+            store.get(hash_hex).unwrap_or_else(|e| {
+                warn!("Store error serving WebSocket peer {}: {}", self.key, e);
+                None
+            })
+        });
+        let data = match local {
+            Some(data) => data,
+            None => {
+                let htl = decrement_htl(htl, &self.htl_config);
+                if !should_forward(htl) || !state.bandwidth.try_forward(&self.key) {
+                    return None;
+                }
+                let data = state
+                    .forward_request(hash_hex, htl, Some(&self.key))
+                    .await?;
+                state
+                    .bandwidth
+                    .record_forwarded(&self.key, data.len() as u64);
+                data
+            }
+        };
+
+        if !state.bandwidth.try_serve(&self.key, data.len() as u64) {
+            info!(
+                "Upload limit hit, not serving {} to WebSocket peer {}",
+                &hash_hex[..8.min(hash_hex.len())],
+                self.key
+            );
+            return None;
+        }
+        Some(data)
+    }
+}
@@ -58,6 +58,23 @@ pub enum PeerPool {
     None,
 }
 
+/// Byte ledger stub
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct ByteLedger {
+    pub uploaded: u64,
+    pub downloaded: u64,
+    pub throttled: u64,
+}
+
+/// WebSocket peer status stub
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct WsPeerStatus {
+    pub id: String,
+    pub url: String,
+    pub pool: String,
+    pub connected: bool,
+}
+
 /// WebRTC state stub - always empty when P2P is disabled
 #[derive(Debug)]
 pub struct WebRTCState {
@@ -87,6 +104,21 @@ impl WebRTCState {
     pub fn get_bandwidth(&self) -> (u64, u64) {
         (0, 0)
     }
+
+    /// Byte ledger for a peer - always None when P2P is disabled
+    pub fn peer_ledger(&self, _peer_id: &str) -> Option<ByteLedger> {
+        None
+    }
+
+    /// Reciprocity status - always false when P2P is disabled
+    pub fn is_leecher(&self, _peer_id: &str) -> bool {
+        false
+    }
+
+    /// WebSocket peers - always empty when P2P is disabled
+    pub async fn ws_peer_statuses(&self) -> Vec<WsPeerStatus> {
+        Vec::new()
+    }
 }
 
 /// Content store trait stub
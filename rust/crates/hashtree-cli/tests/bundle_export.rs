@@ -0,0 +1,46 @@
+//! Integration tests for bundle export of fetched trees
+//!
+//! Run with: cargo test --package hashtree-cli --test bundle_export -- --nocapture
+
+mod common;
+
+use common::blob_server::{write_nested_tree, BlobServer};
+use hashtree_cli::HashtreeStore;
+use hashtree_core::{from_hex, Cid};
+use tempfile::TempDir;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn export_of_fetched_nested_tree_is_complete() {
+    let tmp = TempDir::new().unwrap();
+    let src_dir = tmp.path().join("src");
+    let files = write_nested_tree(&src_dir);
+
+    let source = HashtreeStore::new(tmp.path().join("source")).unwrap();
+    let root = Cid::public(from_hex(&source.upload_dir(&src_dir).unwrap()).unwrap());
+    let server = BlobServer::start(source, usize::MAX).await;
+
+    // Same path as `htree export`: fetch the tree, then bundle it
+    let local = HashtreeStore::new(tmp.path().join("local")).unwrap();
+    server
+        .fetcher()
+        .fetch_tree(&local, None, &root.hash)
+        .await
+        .unwrap();
+    let mut bundle = Vec::new();
+    let stats = local.export_bundle(&root, &mut bundle, false).unwrap();
+    assert!(stats.blocks > files.len() as u64);
+
+    let imported_store = HashtreeStore::new(tmp.path().join("imported")).unwrap();
+    let imported = imported_store.import_bundle(&bundle[..]).unwrap();
+    assert_eq!(imported.roots, vec![root.clone()]);
+    for (path, data) in files {
+        let cid = imported_store
+            .resolve_path(&root, path)
+            .unwrap()
+            .unwrap_or_else(|| panic!("{} missing from bundle", path));
+        assert_eq!(
+            imported_store.get_file(&cid.hash).unwrap().as_deref(),
+            Some(&data[..])
+        );
+    }
+}
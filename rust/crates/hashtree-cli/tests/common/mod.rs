@@ -254,3 +254,108 @@ pub fn write_keys_file(config_dir: &Path, nsec: &str) -> io::Result<()> {
     fs::create_dir_all(config_dir)?;
     fs::write(config_dir.join("keys"), format!("{nsec} self\n"))
 }
+
+/// Empty store in a fresh temp dir, which lives as long as the returned guard
+pub fn test_store() -> (hashtree_cli::HashtreeStore, tempfile::TempDir) {
+    let temp_dir = tempfile::TempDir::new().expect("Failed to create temp dir");
+    let store = hashtree_cli::HashtreeStore::new(temp_dir.path().join("store"))
+        .expect("Failed to create store");
+    (store, temp_dir)
+}
+
+pub mod blob_server {
+    use std::path::Path as FsPath;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use axum::extract::{Path, State};
+    use axum::http::StatusCode;
+    use axum::routing::get;
+    use axum::Router;
+    use hashtree_blossom::BlossomClient;
+    use hashtree_cli::{FetchConfig, Fetcher, HashtreeStore};
+    use hashtree_core::from_hex;
+    use nostr::Keys;
+
+    /// Blossom-style `/<hash>.bin` server over a local store that starts
+    /// answering 404 once its request budget runs out
+    pub struct BlobServer {
+        store: HashtreeStore,
+        budget: AtomicUsize,
+        url: String,
+    }
+
+    impl BlobServer {
+        pub async fn start(store: HashtreeStore, budget: usize) -> Arc<Self> {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+                .await
+                .expect("bind blob server");
+            let url = format!(
+                "http://{}",
+                listener.local_addr().expect("blob server addr")
+            );
+            let server = Arc::new(Self {
+                store,
+                budget: AtomicUsize::new(budget),
+                url,
+            });
+            let app = Router::new()
+                .route("/:file", get(serve_blob))
+                .with_state(Arc::clone(&server));
+            tokio::spawn(async move { axum::serve(listener, app).await });
+            server
+        }
+
+        pub fn set_budget(&self, budget: usize) {
+            self.budget.store(budget, Ordering::SeqCst);
+        }
+
+        /// Fetcher that downloads from this server only
+        pub fn fetcher(&self) -> Fetcher {
+            let blossom = BlossomClient::new_empty(Keys::generate())
+                .with_read_servers(vec![self.url.clone()]);
+            Fetcher::with_blossom(FetchConfig::default(), blossom)
+        }
+    }
+
+    async fn serve_blob(
+        State(server): State<Arc<BlobServer>>,
+        Path(file): Path<String>,
+    ) -> Result<Vec<u8>, StatusCode> {
+        let spent = server
+            .budget
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1));
+        if spent.is_err() {
+            return Err(StatusCode::NOT_FOUND);
+        }
+        let hash = file
+            .strip_suffix(".bin")
+            .and_then(|hex| from_hex(hex).ok())
+            .ok_or(StatusCode::BAD_REQUEST)?;
+        server
+            .store
+            .get_blob(&hash)
+            .ok()
+            .flatten()
+            .ok_or(StatusCode::NOT_FOUND)
+    }
+
+    /// Write a small nested directory, returning (relative path, contents)
+    pub fn write_nested_tree(dir: &FsPath) -> Vec<(&'static str, Vec<u8>)> {
+        // Larger than one chunk, so the file is a File node under a Blob link
+        let big: Vec<u8> = (0..5 * 1024 * 1024u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 24) as u8)
+            .collect();
+        let files = vec![
+            ("top.txt", b"top level".to_vec()),
+            ("sub/mid.txt", b"one level down".to_vec()),
+            ("sub/deeper/big.bin", big),
+        ];
+        for (path, data) in &files {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, data).unwrap();
+        }
+        files
+    }
+}
@@ -0,0 +1,40 @@
+//! Integration tests for directory listings
+//!
+//! Run with: cargo test --package hashtree-cli --test directory_listing -- --nocapture
+
+use futures::executor::block_on;
+use hashtree_cli::HashtreeStore;
+use hashtree_core::{DirEntry, HashTree, HashTreeConfig};
+use tempfile::TempDir;
+
+#[test]
+fn listing_detects_blob_linked_subdirectories() {
+    let tmp = TempDir::new().unwrap();
+    let store = HashtreeStore::new(tmp.path().join("store")).unwrap();
+    let tree = HashTree::new(HashTreeConfig::new(store.store_arc()).public());
+
+    // Entries linked without a link type, as `DirEntry::from_cid` leaves them
+    let root = block_on(async {
+        let (file, size) = tree.put(b"file").await.unwrap();
+        let sub = tree
+            .put_directory(vec![DirEntry::from_cid("inner.txt", &file).with_size(size)])
+            .await
+            .unwrap();
+        tree.put_directory(vec![
+            DirEntry::from_cid("sub", &sub),
+            DirEntry::from_cid("file.txt", &file).with_size(size),
+        ])
+        .await
+        .unwrap()
+    });
+
+    let listing = store.get_directory_listing(&root.hash).unwrap().unwrap();
+    let sub = listing.entries.iter().find(|e| e.name == "sub").unwrap();
+    assert!(sub.is_directory);
+    let file = listing
+        .entries
+        .iter()
+        .find(|e| e.name == "file.txt")
+        .unwrap();
+    assert!(!file.is_directory);
+}
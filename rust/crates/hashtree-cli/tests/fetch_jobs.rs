@@ -0,0 +1,111 @@
+//! Integration tests for persisted, resumable fetch jobs
+//!
+//! Run with: cargo test --package hashtree-cli --test fetch_jobs -- --nocapture
+
+mod common;
+
+use common::blob_server::{write_nested_tree, BlobServer};
+use common::test_store;
+use hashtree_cli::{FetchJob, HashtreeStore};
+use hashtree_core::{from_hex, sha256, Cid};
+use tempfile::TempDir;
+
+#[test]
+fn fetch_job_survives_reopen() {
+    let tmp = TempDir::new().unwrap();
+    let path = tmp.path().join("store");
+    let root = sha256(b"remote root");
+
+    {
+        let store = HashtreeStore::new(&path).unwrap();
+        let mut job = FetchJob::new(&root);
+        job.frontier.push((hex::encode(sha256(b"leaf")), true));
+        job.bytes_done = 1024;
+        store.put_fetch_job(&job).unwrap();
+    }
+
+    let store = HashtreeStore::new(&path).unwrap();
+    let job = store
+        .get_fetch_job(&FetchJob::id_for(&root))
+        .unwrap()
+        .expect("job should be persisted");
+    assert_eq!(job.root, hex::encode(root));
+    assert_eq!(job.frontier.len(), 2);
+    assert_eq!(job.bytes_done, 1024);
+    assert_eq!(store.list_fetch_jobs().unwrap().len(), 1);
+}
+
+#[test]
+fn checkpoint_after_cancel_is_rejected() {
+    let (store, _tmp) = test_store();
+    let mut job = FetchJob::new(&sha256(b"root"));
+    store.put_fetch_job(&job).unwrap();
+
+    job.chunks_done = 3;
+    assert!(store.checkpoint_fetch_job(&job).unwrap());
+    assert_eq!(
+        store.get_fetch_job(&job.id).unwrap().unwrap().chunks_done,
+        3
+    );
+
+    assert!(store.delete_fetch_job(&job.id).unwrap());
+    assert!(!store.checkpoint_fetch_job(&job).unwrap());
+    assert!(store.get_fetch_job(&job.id).unwrap().is_none());
+    assert!(!store.delete_fetch_job(&job.id).unwrap());
+}
+
+#[test]
+fn eta_uses_average_rate() {
+    let mut job = FetchJob::new(&sha256(b"root"));
+    assert_eq!(job.eta_secs(), None);
+
+    job.total_bytes = Some(1000);
+    job.bytes_done = 250;
+    job.active_secs = 10;
+    assert_eq!(job.eta_secs(), Some(30));
+
+    let status = job.status_json();
+    assert_eq!(status["pending"], 1);
+    assert_eq!(status["eta_secs"], 30);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn interrupted_fetch_resumes_nested_tree() {
+    let tmp = TempDir::new().unwrap();
+    let src_dir = tmp.path().join("src");
+    let files = write_nested_tree(&src_dir);
+
+    let source = HashtreeStore::new(tmp.path().join("source")).unwrap();
+    let root = from_hex(&source.upload_dir(&src_dir).unwrap()).unwrap();
+    let server = BlobServer::start(source, 3).await;
+    let fetcher = server.fetcher();
+    let dest = HashtreeStore::new(tmp.path().join("dest")).unwrap();
+
+    // The server runs dry partway through: the job stays behind
+    let (chunks, _) = fetcher.fetch_tree(&dest, None, &root).await.unwrap();
+    assert_eq!(chunks, 3);
+    let job = dest
+        .get_fetch_job(&FetchJob::id_for(&root))
+        .unwrap()
+        .expect("interrupted fetch keeps its job");
+    assert!(!job.frontier.is_empty());
+
+    server.set_budget(usize::MAX);
+    fetcher.fetch_tree(&dest, None, &root).await.unwrap();
+    assert!(dest
+        .get_fetch_job(&FetchJob::id_for(&root))
+        .unwrap()
+        .is_none());
+
+    let root_cid = Cid::public(root);
+    for (path, data) in files {
+        let cid = dest
+            .resolve_path(&root_cid, path)
+            .unwrap()
+            .unwrap_or_else(|| panic!("{} missing after resume", path));
+        assert_eq!(
+            dest.get_file(&cid.hash).unwrap().as_deref(),
+            Some(&data[..])
+        );
+    }
+}
@@ -0,0 +1,198 @@
+//! Integration tests for reachability-based garbage collection
+//!
+//! Run with: cargo test --package hashtree-cli --test gc -- --nocapture
+
+mod common;
+
+use std::sync::Arc;
+
+use common::test_store;
+use hashtree_cli::{HashtreeServer, HashtreeStore, RootVersion, GC_ROOT_HISTORY};
+use hashtree_core::{from_hex, nhash_encode_full, sha256, to_hex, Cid, NHashData};
+use tempfile::TempDir;
+
+/// Write a small source tree with one multi-chunk file
+fn write_source_dir(tmp: &TempDir) -> std::path::PathBuf {
+    let dir = tmp.path().join("src");
+    std::fs::create_dir_all(dir.join("nested")).unwrap();
+    std::fs::write(dir.join("a.txt"), b"hello gc").unwrap();
+    let big: Vec<u8> = (0..5_000_000u32).map(|i| (i % 251) as u8).collect();
+    std::fs::write(dir.join("nested/big.bin"), big).unwrap();
+    dir
+}
+
+#[test]
+fn gc_keeps_children_of_pinned_public_tree() {
+    let (store, tmp) = test_store();
+    let dir = write_source_dir(&tmp);
+
+    let root_hex = store.upload_dir_with_options(&dir, false).unwrap();
+    let orphan_hex = store.put_blob(b"not referenced by anything").unwrap();
+    let before = store.get_storage_stats().unwrap().total_dags;
+
+    // Dry run reports only the orphan and deletes nothing
+    let report = store.gc_with_options(true).unwrap();
+    assert!(report.dry_run);
+    assert_eq!(report.deleted_dags, 1);
+    assert_eq!(report.reachable_dags, before - 1);
+    assert!(store.blob_exists(&from_hex(&orphan_hex).unwrap()).unwrap());
+
+    let stats = store.gc().unwrap();
+    assert_eq!(stats.deleted_dags, 1);
+    assert!(!store.blob_exists(&from_hex(&orphan_hex).unwrap()).unwrap());
+
+    // The whole pinned tree is still readable
+    let root = Cid::public(from_hex(&root_hex).unwrap());
+    let big = store
+        .resolve_path(&root, "nested/big.bin")
+        .unwrap()
+        .unwrap();
+    let data = store.get_file_by_cid(&big).unwrap().unwrap();
+    assert_eq!(data.len(), 5_000_000);
+}
+
+#[test]
+fn gc_walks_encrypted_pins_with_stored_key() {
+    let (store, tmp) = test_store();
+    let dir = write_source_dir(&tmp);
+
+    let cid_str = store
+        .upload_dir_encrypted_with_options(&dir, false)
+        .unwrap();
+    let root = Cid::parse(&cid_str).unwrap();
+    assert!(root.key.is_some());
+
+    let stats = store.gc().unwrap();
+    assert_eq!(stats.deleted_dags, 0);
+
+    let big = store
+        .resolve_path(&root, "nested/big.bin")
+        .unwrap()
+        .unwrap();
+    let data = store.get_file_by_cid(&big).unwrap().unwrap();
+    assert_eq!(data.len(), 5_000_000);
+}
+
+#[test]
+fn gc_keeps_cached_roots_and_sweeps_unpinned_trees() {
+    let (store, tmp) = test_store();
+    let dir = write_source_dir(&tmp);
+
+    let cid_str = store
+        .upload_dir_encrypted_with_options(&dir, false)
+        .unwrap();
+    let root = Cid::parse(&cid_str).unwrap();
+    store.unpin(&root.hash).unwrap();
+
+    // Still referenced by a cached Nostr root
+    store
+        .set_cached_root(
+            &"ab".repeat(32),
+            "docs",
+            &hex::encode(root.hash),
+            root.key.map(hex::encode).as_deref(),
+            "public",
+            1,
+        )
+        .unwrap();
+    assert_eq!(store.gc().unwrap().deleted_dags, 0);
+
+    // Without the cached root it is still a recent version of the tree
+    store.delete_cached_root(&"ab".repeat(32), "docs").unwrap();
+    assert_eq!(store.gc().unwrap().deleted_dags, 0);
+
+    // Once newer versions push it out of the history gc keeps, everything is swept
+    for i in 0..GC_ROOT_HISTORY as u64 {
+        let version = RootVersion {
+            hash: to_hex(&sha256(&i.to_le_bytes())),
+            key: None,
+            created_at: 2 + i,
+            prev: None,
+        };
+        store
+            .record_root_version(&"ab".repeat(32), "docs", version)
+            .unwrap();
+    }
+    let stats = store.gc().unwrap();
+    assert!(stats.deleted_dags > 0);
+    assert_eq!(store.get_storage_stats().unwrap().total_dags, 0);
+}
+
+/// Serve `store` over HTTP on a background runtime, returning the port
+fn serve(store: Arc<HashtreeStore>) -> u16 {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    listener.set_nonblocking(true).unwrap();
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+            HashtreeServer::new(store, String::new())
+                .run_with_listener(listener)
+                .await
+        })
+        .unwrap();
+    });
+    port
+}
+
+#[test]
+fn gc_walks_encrypted_tree_pinned_by_nhash_over_http() {
+    let (store, tmp) = test_store();
+    let store = Arc::new(store);
+    let dir = write_source_dir(&tmp);
+
+    let cid_str = store
+        .upload_dir_encrypted_with_options(&dir, false)
+        .unwrap();
+    let root = Cid::parse(&cid_str).unwrap();
+    store.unpin(&root.hash).unwrap();
+    let before = store.get_storage_stats().unwrap().total_dags;
+
+    let nhash = nhash_encode_full(&NHashData {
+        hash: root.hash,
+        decrypt_key: root.key,
+    })
+    .unwrap();
+    let port = serve(Arc::clone(&store));
+    let response: serde_json::Value = reqwest::blocking::Client::new()
+        .post(format!("http://127.0.0.1:{}/api/pin/{}", port, nhash))
+        .send()
+        .unwrap()
+        .json()
+        .unwrap();
+    assert_eq!(response["success"], true, "{}", response);
+
+    let stats = store.gc().unwrap();
+    assert_eq!(stats.deleted_dags, 0);
+    assert_eq!(store.get_storage_stats().unwrap().total_dags, before);
+
+    let big = store
+        .resolve_path(&root, "nested/big.bin")
+        .unwrap()
+        .unwrap();
+    let data = store.get_file_by_cid(&big).unwrap().unwrap();
+    assert_eq!(data.len(), 5_000_000);
+}
+
+#[test]
+fn gc_refuses_to_sweep_encrypted_root_pinned_without_key() {
+    let (store, tmp) = test_store();
+    let dir = write_source_dir(&tmp);
+
+    let cid_str = store
+        .upload_dir_encrypted_with_options(&dir, false)
+        .unwrap();
+    let root = Cid::parse(&cid_str).unwrap();
+    store.unpin(&root.hash).unwrap();
+    store.pin(&root.hash).unwrap();
+    let before = store.get_storage_stats().unwrap().total_dags;
+
+    let err = store.gc().unwrap_err();
+    assert!(err.to_string().contains(&to_hex(&root.hash)), "{}", err);
+    assert_eq!(store.get_storage_stats().unwrap().total_dags, before);
+
+    // Pinning with the key makes the tree walkable again
+    store.pin_cid(&root).unwrap();
+    assert_eq!(store.gc().unwrap().deleted_dags, 0);
+}
@@ -0,0 +1,64 @@
+//! Integration tests for mode, mtime and symlink metadata in uploaded trees
+//!
+//! Run with: cargo test --package hashtree-cli --test posix_metadata -- --nocapture
+
+#![cfg(unix)]
+
+use std::os::unix::fs::PermissionsExt;
+
+use hashtree_cli::HashtreeStore;
+use hashtree_core::{from_hex, PosixMeta};
+use tempfile::TempDir;
+
+#[test]
+fn upload_dir_records_posix_metadata() {
+    let tmp = TempDir::new().unwrap();
+    let store = HashtreeStore::new(tmp.path().join("store")).unwrap();
+
+    let dir = tmp.path().join("src");
+    std::fs::create_dir_all(dir.join("bin")).unwrap();
+    let script = dir.join("bin/run.sh");
+    std::fs::write(&script, b"#!/bin/sh\n").unwrap();
+    PosixMeta::default()
+        .with_mode(0o750)
+        .with_mtime(1_600_000_000)
+        .restore(&script)
+        .unwrap();
+    std::os::unix::fs::symlink("bin/run.sh", dir.join("run")).unwrap();
+
+    let root = from_hex(&store.upload_dir_with_options(&dir, false).unwrap()).unwrap();
+    let listing = store.get_directory_listing(&root).unwrap().unwrap();
+
+    let link = listing.entries.iter().find(|e| e.name == "run").unwrap();
+    assert_eq!(link.posix.symlink.as_deref(), Some("bin/run.sh"));
+    assert!(!link.is_directory);
+    assert_eq!(
+        store.get_file(&from_hex(&link.cid).unwrap()).unwrap(),
+        Some(b"bin/run.sh".to_vec())
+    );
+
+    let bin = listing.entries.iter().find(|e| e.name == "bin").unwrap();
+    assert!(bin.is_directory);
+    assert!(bin.posix.mode.is_some());
+
+    let bin_listing = store
+        .get_directory_listing(&from_hex(&bin.cid).unwrap())
+        .unwrap()
+        .unwrap();
+    let run = &bin_listing.entries[0];
+    assert_eq!(run.posix.mode, Some(0o750));
+    assert_eq!(run.posix.mtime, Some(1_600_000_000));
+
+    // Restoring onto a fresh copy reproduces the attributes
+    let copy = tmp.path().join("copy.sh");
+    store
+        .write_file(&from_hex(&run.cid).unwrap(), &copy)
+        .unwrap();
+    run.posix.restore(&copy).unwrap();
+    let metadata = std::fs::metadata(&copy).unwrap();
+    assert_eq!(metadata.permissions().mode() & 0o7777, 0o750);
+    assert_eq!(
+        PosixMeta::from_path(&copy).unwrap().mtime,
+        Some(1_600_000_000)
+    );
+}
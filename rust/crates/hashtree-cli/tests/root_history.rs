@@ -0,0 +1,128 @@
+//! Integration tests for the local root history index
+//!
+//! Run with: cargo test --package hashtree-cli --test root_history -- --nocapture
+
+mod common;
+
+use common::test_store;
+use hashtree_cli::{select_root_version, RootVersion};
+use hashtree_core::sha256;
+
+const PUBKEY: &str = "7e7e9c42a91bfef19fa929e5fda1b72e0ebc1a4c1141673e2794234d86addf4e";
+
+fn version(label: &[u8], created_at: u64, prev: Option<&[u8]>) -> RootVersion {
+    RootVersion {
+        hash: hex::encode(sha256(label)),
+        key: None,
+        created_at,
+        prev: prev.map(|p| hex::encode(sha256(p))),
+    }
+}
+
+#[test]
+fn history_is_newest_first_and_deduplicated() {
+    let (store, _tmp) = test_store();
+
+    assert!(store
+        .record_root_version(PUBKEY, "docs", version(b"v2", 1_700_000_200, Some(b"v1")))
+        .unwrap());
+    assert!(store
+        .record_root_version(PUBKEY, "docs", version(b"v1", 1_700_000_100, None))
+        .unwrap());
+    assert!(store
+        .record_root_version(PUBKEY, "docs", version(b"v3", 1_700_000_300, Some(b"v2")))
+        .unwrap());
+    // Same event seen again from another relay
+    assert!(!store
+        .record_root_version(PUBKEY, "docs", version(b"v2", 1_700_000_200, Some(b"v1")))
+        .unwrap());
+
+    let history = store.root_history(PUBKEY, "docs").unwrap();
+    let created: Vec<u64> = history.iter().map(|v| v.created_at).collect();
+    assert_eq!(created, vec![1_700_000_300, 1_700_000_200, 1_700_000_100]);
+    assert_eq!(history[0].prev.as_deref(), Some(history[1].hash.as_str()));
+
+    assert!(store.root_history(PUBKEY, "other").unwrap().is_empty());
+}
+
+#[test]
+fn cached_root_updates_are_recorded_with_prev() {
+    let (store, _tmp) = test_store();
+    let v1 = hex::encode(sha256(b"v1"));
+    let v2 = hex::encode(sha256(b"v2"));
+
+    store
+        .set_cached_root(PUBKEY, "site", &v1, None, "public", 1_700_000_000)
+        .unwrap();
+    store
+        .set_cached_root(PUBKEY, "site", &v2, None, "public", 1_700_000_060)
+        .unwrap();
+
+    let history = store.root_history(PUBKEY, "site").unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].hash, v2);
+    assert_eq!(history[0].prev.as_deref(), Some(v1.as_str()));
+    assert_eq!(history[1].prev, None);
+}
+
+#[test]
+fn repeated_records_of_one_root_are_merged() {
+    let (store, _tmp) = test_store();
+    let v1 = hex::encode(sha256(b"v1"));
+    let v2 = hex::encode(sha256(b"v2"));
+
+    store
+        .set_cached_root(PUBKEY, "site", &v1, None, "public", 1_700_000_100)
+        .unwrap();
+
+    // One publish of v2: the cached root, `htree publish` and the relay event
+    store
+        .set_cached_root(PUBKEY, "site", &v2, None, "public", 1_700_000_160)
+        .unwrap();
+    assert!(!store
+        .record_root_version(PUBKEY, "site", version(b"v2", 1_700_000_161, Some(b"v1")))
+        .unwrap());
+    assert!(!store
+        .record_root_version(PUBKEY, "site", version(b"v2", 1_700_000_159, Some(b"v1")))
+        .unwrap());
+
+    let history = store.root_history(PUBKEY, "site").unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].hash, v2);
+    assert_eq!(history[0].created_at, 1_700_000_159);
+    assert_eq!(history[0].prev.as_deref(), Some(v1.as_str()));
+
+    // Rolling back to v1 later is a new version
+    assert!(store
+        .record_root_version(PUBKEY, "site", version(b"v1", 1_700_000_200, Some(b"v2")))
+        .unwrap());
+    let hashes: Vec<String> = store
+        .root_history(PUBKEY, "site")
+        .unwrap()
+        .into_iter()
+        .map(|v| v.hash)
+        .collect();
+    assert_eq!(hashes, vec![v1.clone(), v2, v1]);
+}
+
+#[test]
+fn select_by_index_or_timestamp() {
+    let history = vec![
+        version(b"v3", 1_700_000_300, Some(b"v2")),
+        version(b"v2", 1_700_000_200, Some(b"v1")),
+        version(b"v1", 1_700_000_100, None),
+    ];
+
+    let pick = |sel: &str| {
+        select_root_version(&history, sel)
+            .unwrap()
+            .map(|v| v.created_at)
+    };
+    assert_eq!(pick("0"), Some(1_700_000_300));
+    assert_eq!(pick("2"), Some(1_700_000_100));
+    assert_eq!(pick("3"), None);
+    assert_eq!(pick("1700000250"), Some(1_700_000_200));
+    assert_eq!(pick("1700000200"), Some(1_700_000_200));
+    assert_eq!(pick("1700000000"), None);
+    assert!(select_root_version(&history, "yesterday").is_err());
+}
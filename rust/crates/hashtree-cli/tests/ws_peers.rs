@@ -0,0 +1,79 @@
+//! Two embedded daemons talking over `/ws`: one lists the other in
+//! `ws_peers` and fetches through it without WebRTC or relays.
+//!
+//! Run with: cargo test --package hashtree-cli --test ws_peers -- --nocapture
+
+#![cfg(feature = "p2p")]
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use hashtree_cli::daemon::{start_embedded, EmbeddedDaemonInfo, EmbeddedDaemonOptions};
+use tempfile::TempDir;
+
+async fn start_daemon(dir: &Path, ws_peers: Vec<String>) -> EmbeddedDaemonInfo {
+    let data_dir = dir.join("data");
+    std::fs::create_dir_all(&data_dir).expect("create data dir");
+
+    let mut config = hashtree_cli::Config::default();
+    config.storage.data_dir = data_dir.to_string_lossy().to_string();
+    config.server.enable_auth = false;
+    config.server.enable_webrtc = false;
+    config.server.stun_port = 0;
+    config.server.ws_peers = ws_peers;
+
+    start_embedded(EmbeddedDaemonOptions {
+        config,
+        data_dir,
+        bind_address: "127.0.0.1:0".to_string(),
+        relays: None,
+        extra_routes: None,
+        cors: None,
+    })
+    .await
+    .expect("start embedded daemon")
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn daemons_exchange_data_over_ws() {
+    let dir = TempDir::new().expect("temp dir");
+    std::env::set_var("HTREE_CONFIG_DIR", dir.path());
+    std::env::set_var("HTREE_DATA_DIR", dir.path());
+
+    let server = start_daemon(&dir.path().join("a"), Vec::new()).await;
+    let hash = server.store.put_blob(b"hello over ws").unwrap();
+
+    let client = start_daemon(
+        &dir.path().join("b"),
+        vec![format!("ws://127.0.0.1:{}/ws", server.port)],
+    )
+    .await;
+    let state = client
+        .webrtc_state
+        .clone()
+        .expect("ws peers need WebRTC state");
+
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while !state.ws_peer_statuses().await.iter().any(|p| p.connected) {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("ws peer never connected");
+
+    let data = state.request_from_peers(&hash).await;
+    assert_eq!(data.as_deref(), Some(&b"hello over ws"[..]));
+
+    // The server answers not-found instead of leaving the request to time out
+    let missing = hex::encode([0x42u8; 32]);
+    let started = Instant::now();
+    assert!(state.request_from_peers(&missing).await.is_none());
+    assert!(
+        started.elapsed() < Duration::from_millis(400),
+        "not-found took {:?}",
+        started.elapsed()
+    );
+    let key = format!("127.0.0.1:{}", server.port);
+    let ledger = state.peer_ledger(&key).expect("ledger for the ws peer");
+    assert_eq!(ledger.downloaded, b"hello over ws".len() as u64);
+}
@@ -35,6 +35,10 @@ pub struct Config {
     pub blossom: BlossomConfig,
     #[serde(default)]
     pub sync: SyncConfig,
+    #[serde(default)]
+    pub git: GitConfig,
+    #[serde(default)]
+    pub resolver: ResolverConfig,
 }
 
 /// Server configuration
@@ -282,6 +286,75 @@ fn default_blossom_timeout_ms() -> u64 {
     10000
 }
 
+/// Layout of `.git/objects` written by git-remote-htree
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GitObjectLayout {
+    /// One zlib-compressed loose object per file under objects/XX/ (default)
+    #[default]
+    Loose,
+    /// Delta-compressed packfiles under objects/pack/, one new pack per push
+    Pack,
+}
+
+/// Git remote helper configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GitConfig {
+    /// Object layout used when pushing: "loose" (default) or "pack"
+    #[serde(default)]
+    pub object_layout: GitObjectLayout,
+}
+
+/// Root maps and root cache consulted alongside Nostr when resolving
+/// named trees
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolverConfig {
+    /// Local JSON or TOML file of key -> CID
+    #[serde(default)]
+    pub file: Option<String>,
+    /// URL of a JSON document of key -> CID
+    #[serde(default)]
+    pub http: Option<String>,
+    /// How often root maps are re-read for subscriptions, in milliseconds
+    #[serde(default = "default_resolver_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+    /// How answers from several backends are combined:
+    /// "first-wins", "newest-wins" or "quorum:<n>"
+    #[serde(default = "default_resolver_policy")]
+    pub policy: String,
+    /// Per-backend lookup timeout, in milliseconds
+    #[serde(default = "default_resolver_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Remember last-known roots on disk and serve them when backends are down
+    #[serde(default = "default_true")]
+    pub cache: bool,
+}
+
+impl Default for ResolverConfig {
+    fn default() -> Self {
+        Self {
+            file: None,
+            http: None,
+            poll_interval_ms: default_resolver_poll_interval_ms(),
+            policy: default_resolver_policy(),
+            timeout_ms: default_resolver_timeout_ms(),
+            cache: true,
+        }
+    }
+}
+
+fn default_resolver_poll_interval_ms() -> u64 {
+    1000
+}
+
+fn default_resolver_policy() -> String {
+    "first-wins".to_string()
+}
+
+fn default_resolver_timeout_ms() -> u64 {
+    5000
+}
+
 impl Config {
     /// Load config from file, or create default if doesn't exist
     pub fn load() -> Result<Self> {
@@ -337,6 +410,21 @@ pub fn get_keys_path() -> PathBuf {
     get_hashtree_dir().join("keys")
 }
 
+/// Get the cache of last-known tree roots (~/.hashtree/root-cache.json)
+pub fn get_root_cache_path() -> PathBuf {
+    get_hashtree_dir().join("root-cache.json")
+}
+
+/// Get the persisted P2P peer reputation (~/.hashtree/peer-reputation.json)
+pub fn get_peer_reputation_path() -> PathBuf {
+    get_hashtree_dir().join("peer-reputation.json")
+}
+
+/// Get the P2P peer ban/allow list (~/.hashtree/peer-access.json)
+pub fn get_peer_access_path() -> PathBuf {
+    get_hashtree_dir().join("peer-access.json")
+}
+
 /// A stored key entry from the keys file
 #[derive(Debug, Clone)]
 pub struct KeyEntry {
@@ -663,6 +751,42 @@ backend = "fs"
         assert_eq!(config.storage.backend, StorageBackend::Fs);
     }
 
+    #[test]
+    fn test_git_object_layout() {
+        let config = Config::default();
+        assert_eq!(config.git.object_layout, GitObjectLayout::Loose);
+
+        let toml = r#"
+[git]
+object_layout = "pack"
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        assert_eq!(config.git.object_layout, GitObjectLayout::Pack);
+    }
+
+    #[test]
+    fn test_resolver_config() {
+        let config = Config::default();
+        assert_eq!(config.resolver.policy, "first-wins");
+        assert!(config.resolver.cache);
+        assert!(config.resolver.file.is_none());
+
+        let toml = r#"
+[resolver]
+file = "/etc/hashtree/roots.json"
+policy = "quorum:2"
+cache = false
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        assert_eq!(
+            config.resolver.file.as_deref(),
+            Some("/etc/hashtree/roots.json")
+        );
+        assert_eq!(config.resolver.policy, "quorum:2");
+        assert_eq!(config.resolver.timeout_ms, 5000);
+        assert!(!config.resolver.cache);
+    }
+
     #[test]
     fn test_parse_keys_file() {
         let content = r#"
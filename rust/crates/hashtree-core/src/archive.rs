@@ -0,0 +1,853 @@
+//! Tar and zip export of directory trees
+//!
+//! `archive_stream` walks a directory with `HashTree::walk_stream` and yields
+//! the archive as byte chunks while file contents are read, so memory use
+//! doesn't grow with file sizes. Mode, mtime and symlink targets from the
+//! POSIX metadata profile are carried over when present.
+//!
+//! Zip members are stored uncompressed with data descriptors (the CRC is only
+//! known after streaming) and switch to ZIP64 fields past 4 GiB. The zip
+//! central directory keeps one small record per member until the end.
+
+use std::collections::HashSet;
+use std::pin::Pin;
+use std::str::FromStr;
+
+use futures::stream::{self, Stream, StreamExt};
+
+use crate::hashtree::{HashTree, HashTreeError};
+use crate::reader::WalkEntry;
+use crate::store::Store;
+use crate::types::{to_hex, Cid, LinkType};
+
+/// Permission bits for files without a stored mode
+pub const DEFAULT_FILE_MODE: u32 = 0o644;
+
+/// Permission bits for directories without a stored mode
+pub const DEFAULT_DIR_MODE: u32 = 0o755;
+
+/// Archive container format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Tar,
+    Zip,
+}
+
+impl ArchiveFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Tar => "tar",
+            ArchiveFormat::Zip => "zip",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Tar => "application/x-tar",
+            ArchiveFormat::Zip => "application/zip",
+        }
+    }
+}
+
+impl FromStr for ArchiveFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "tar" => Ok(ArchiveFormat::Tar),
+            "zip" => Ok(ArchiveFormat::Zip),
+            _ => Err(format!("invalid archive format: {}", s)),
+        }
+    }
+}
+
+/// Stream a directory tree as a tar or zip archive
+///
+/// Fails with `MissingChunk` if a file's content is shorter than its recorded
+/// size, since the archive headers have already been written by then.
+pub fn archive_stream<S: Store>(
+    tree: &HashTree<S>,
+    root: Cid,
+    format: ArchiveFormat,
+) -> Pin<Box<dyn Stream<Item = Result<Vec<u8>, HashTreeError>> + Send + '_>> {
+    let root_hex = to_hex(&root.hash);
+    let state = ArchiveState {
+        tree,
+        walk: tree.walk_stream(root, String::new()),
+        root_hex,
+        writer: match format {
+            ArchiveFormat::Tar => Writer::Tar,
+            ArchiveFormat::Zip => Writer::Zip(ZipWriter::default()),
+        },
+        content: None,
+        dirs: HashSet::new(),
+        last_file: None,
+        done: false,
+    };
+
+    Box::pin(stream::unfold(state, |mut state| async move {
+        match state.next_chunk().await {
+            Ok(Some(bytes)) => Some((Ok(bytes), state)),
+            Ok(None) => None,
+            Err(e) => {
+                state.done = true;
+                Some((Err(e), state))
+            }
+        }
+    }))
+}
+
+/// An archive member as seen by the format writers
+struct Member {
+    /// Path inside the archive, without a trailing slash
+    path: String,
+    kind: MemberKind,
+    size: u64,
+    mode: u32,
+    mtime: i64,
+}
+
+enum MemberKind {
+    Dir,
+    File,
+    Symlink(String),
+}
+
+/// File content being copied into the archive
+struct Content<'a> {
+    stream: Pin<Box<dyn Stream<Item = Result<Vec<u8>, HashTreeError>> + Send + 'a>>,
+    path: String,
+    expected: u64,
+    written: u64,
+}
+
+struct ArchiveState<'a, S: Store> {
+    tree: &'a HashTree<S>,
+    walk: Pin<Box<dyn Stream<Item = Result<WalkEntry, HashTreeError>> + Send + 'a>>,
+    root_hex: String,
+    writer: Writer,
+    content: Option<Content<'a>>,
+    /// Directory paths already written (internal `_` nodes repeat them)
+    dirs: HashSet<String>,
+    /// Path of the last file, whose chunks the walk yields next
+    last_file: Option<String>,
+    done: bool,
+}
+
+impl<'a, S: Store> ArchiveState<'a, S> {
+    async fn next_chunk(&mut self) -> Result<Option<Vec<u8>>, HashTreeError> {
+        loop {
+            if self.done {
+                return Ok(None);
+            }
+
+            if let Some(content) = self.content.as_mut() {
+                match content.stream.next().await {
+                    Some(chunk) => {
+                        let chunk = chunk?;
+                        content.written += chunk.len() as u64;
+                        if content.written > content.expected {
+                            return Err(size_mismatch(content));
+                        }
+                        if chunk.is_empty() {
+                            continue;
+                        }
+                        self.writer.data(&chunk);
+                        return Ok(Some(chunk));
+                    }
+                    None => {
+                        if content.written != content.expected {
+                            return Err(size_mismatch(content));
+                        }
+                        let expected = content.expected;
+                        self.content = None;
+                        return Ok(Some(self.writer.end_file(expected)));
+                    }
+                }
+            }
+
+            let Some(entry) = self.walk.next().await else {
+                self.done = true;
+                return Ok(Some(self.writer.finish()));
+            };
+            let entry = entry?;
+
+            if entry.path.is_empty() {
+                if entry.link_type != LinkType::Dir {
+                    return Err(HashTreeError::EntryNotFound(format!(
+                        "{} is not a directory",
+                        self.root_hex
+                    )));
+                }
+                continue;
+            }
+            // Chunks and chunk-tree nodes of a file share its path
+            if self.last_file.as_deref() == Some(entry.path.as_str()) {
+                continue;
+            }
+
+            let posix = entry.posix();
+            let member = if entry.link_type == LinkType::Dir {
+                if !self.dirs.insert(entry.path.clone()) {
+                    continue;
+                }
+                Member {
+                    path: entry.path,
+                    kind: MemberKind::Dir,
+                    size: 0,
+                    mode: posix.mode.unwrap_or(DEFAULT_DIR_MODE),
+                    mtime: posix.mtime.unwrap_or(0),
+                }
+            } else {
+                self.last_file = Some(entry.path.clone());
+                let kind = match posix.symlink {
+                    Some(target) => MemberKind::Symlink(target),
+                    None => MemberKind::File,
+                };
+                let (size, mode) = match kind {
+                    MemberKind::File => (entry.size, posix.mode.unwrap_or(DEFAULT_FILE_MODE)),
+                    _ => (0, 0o777),
+                };
+                Member {
+                    path: entry.path,
+                    kind,
+                    size,
+                    mode,
+                    mtime: posix.mtime.unwrap_or(0),
+                }
+            };
+
+            let header = self.writer.begin(&member);
+            if let MemberKind::File = member.kind {
+                let cid = Cid {
+                    hash: entry.hash,
+                    key: entry.key,
+                };
+                self.content = Some(Content {
+                    stream: self.tree.get_stream(&cid),
+                    path: member.path,
+                    expected: member.size,
+                    written: 0,
+                });
+            }
+            return Ok(Some(header));
+        }
+    }
+}
+
+fn size_mismatch(content: &Content<'_>) -> HashTreeError {
+    HashTreeError::MissingChunk(format!(
+        "{}: expected {} bytes, read {}",
+        content.path, content.expected, content.written
+    ))
+}
+
+enum Writer {
+    Tar,
+    Zip(ZipWriter),
+}
+
+impl Writer {
+    /// Header bytes for a member (plus the whole body for non-file members)
+    fn begin(&mut self, member: &Member) -> Vec<u8> {
+        match self {
+            Writer::Tar => tar_header(member),
+            Writer::Zip(zip) => zip.begin(member),
+        }
+    }
+
+    fn data(&mut self, chunk: &[u8]) {
+        if let Writer::Zip(zip) = self {
+            zip.data(chunk);
+        }
+    }
+
+    /// Bytes closing a file member after its content
+    fn end_file(&mut self, size: u64) -> Vec<u8> {
+        match self {
+            Writer::Tar => vec![0u8; tar_padding(size)],
+            Writer::Zip(zip) => zip.end_file(),
+        }
+    }
+
+    /// Trailer closing the archive
+    fn finish(&mut self) -> Vec<u8> {
+        match self {
+            Writer::Tar => vec![0u8; 2 * TAR_BLOCK],
+            Writer::Zip(zip) => zip.finish(),
+        }
+    }
+}
+
+// ============ TAR ============
+
+pub(crate) const TAR_BLOCK: usize = 512;
+
+pub(crate) fn tar_padding(size: u64) -> usize {
+    (TAR_BLOCK - (size % TAR_BLOCK as u64) as usize) % TAR_BLOCK
+}
+
+/// ustar header, preceded by GNU long-name records when needed
+fn tar_header(member: &Member) -> Vec<u8> {
+    let (name, typeflag, linkname) = match &member.kind {
+        MemberKind::Dir => (format!("{}/", member.path), b'5', ""),
+        MemberKind::File => (member.path.clone(), b'0', ""),
+        MemberKind::Symlink(target) => (member.path.clone(), b'2', target.as_str()),
+    };
+
+    let mut out = Vec::with_capacity(TAR_BLOCK);
+    if linkname.len() > 100 {
+        out.extend(tar_long_name(b'K', linkname));
+    }
+    if name.len() > 100 {
+        out.extend(tar_long_name(b'L', &name));
+    }
+
+    let mut header = [0u8; TAR_BLOCK];
+    put_truncated(&mut header[0..100], name.as_bytes());
+    write_octal(&mut header[100..108], member.mode as u64);
+    write_octal(&mut header[108..116], 0);
+    write_octal(&mut header[116..124], 0);
+    write_octal(&mut header[124..136], member.size);
+    write_octal(&mut header[136..148], member.mtime.max(0) as u64);
+    header[156] = typeflag;
+    put_truncated(&mut header[157..257], linkname.as_bytes());
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+    set_checksum(&mut header);
+    out.extend_from_slice(&header);
+    out
+}
+
+/// GNU `././@LongLink` record carrying a name that doesn't fit in 100 bytes
+fn tar_long_name(typeflag: u8, name: &str) -> Vec<u8> {
+    let data_len = name.len() + 1;
+    let mut header = [0u8; TAR_BLOCK];
+    put_truncated(&mut header[0..100], b"././@LongLink");
+    write_octal(&mut header[100..108], 0o644);
+    write_octal(&mut header[108..116], 0);
+    write_octal(&mut header[116..124], 0);
+    write_octal(&mut header[124..136], data_len as u64);
+    write_octal(&mut header[136..148], 0);
+    header[156] = typeflag;
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+    set_checksum(&mut header);
+
+    let mut out = header.to_vec();
+    out.extend_from_slice(name.as_bytes());
+    out.resize(out.len() + 1 + tar_padding(data_len as u64), 0);
+    out
+}
+
+fn put_truncated(field: &mut [u8], value: &[u8]) {
+    let len = value.len().min(field.len());
+    field[..len].copy_from_slice(&value[..len]);
+}
+
+/// NUL-terminated octal, or GNU base-256 when the value doesn't fit
+fn write_octal(field: &mut [u8], value: u64) {
+    let digits = field.len() - 1;
+    let octal = format!("{:0width$o}", value, width = digits);
+    if octal.len() <= digits {
+        field[..digits].copy_from_slice(octal.as_bytes());
+        field[digits] = 0;
+    } else {
+        field.fill(0);
+        let bytes = value.to_be_bytes();
+        let start = field.len() - bytes.len();
+        field[start..].copy_from_slice(&bytes);
+        field[0] = 0x80;
+    }
+}
+
+fn set_checksum(header: &mut [u8; TAR_BLOCK]) {
+    header[148..156].fill(b' ');
+    let sum: u32 = header.iter().map(|&b| b as u32).sum();
+    let octal = format!("{:06o}\0 ", sum);
+    header[148..156].copy_from_slice(octal.as_bytes());
+}
+
+// ============ ZIP ============
+
+pub(crate) const ZIP_LOCAL_HEADER: u32 = 0x0403_4b50;
+const ZIP_DATA_DESCRIPTOR: u32 = 0x0807_4b50;
+pub(crate) const ZIP_CENTRAL_HEADER: u32 = 0x0201_4b50;
+pub(crate) const ZIP64_END_OF_CENTRAL_DIR: u32 = 0x0606_4b50;
+pub(crate) const ZIP64_END_LOCATOR: u32 = 0x0706_4b50;
+pub(crate) const ZIP_END_OF_CENTRAL_DIR: u32 = 0x0605_4b50;
+
+/// Bit 3: sizes and CRC follow the data; bit 11: UTF-8 names
+const FLAG_DATA_DESCRIPTOR: u16 = 0x0008;
+const FLAG_UTF8: u16 = 0x0800;
+/// Made by Unix, spec version 4.5
+const VERSION_MADE_BY: u16 = (3 << 8) | 45;
+const VERSION_DEFAULT: u16 = 20;
+const VERSION_ZIP64: u16 = 45;
+pub(crate) const EXTRA_ZIP64: u16 = 0x0001;
+pub(crate) const EXTRA_TIMESTAMP: u16 = 0x5455;
+
+struct CentralRecord {
+    name: Vec<u8>,
+    flags: u16,
+    crc: u32,
+    size: u64,
+    offset: u64,
+    /// Unix `st_mode`, including the file type bits
+    unix_mode: u32,
+    is_dir: bool,
+    mtime: i64,
+    zip64: bool,
+}
+
+/// Member whose content is being streamed
+struct ZipCurrent {
+    hasher: crc32fast::Hasher,
+    size: u64,
+}
+
+#[derive(Default)]
+struct ZipWriter {
+    /// Bytes written so far
+    offset: u64,
+    records: Vec<CentralRecord>,
+    current: Option<ZipCurrent>,
+}
+
+impl ZipWriter {
+    fn begin(&mut self, member: &Member) -> Vec<u8> {
+        let (name, unix_mode, body) = match &member.kind {
+            MemberKind::Dir => (
+                format!("{}/", member.path),
+                0o040000 | member.mode,
+                Vec::new(),
+            ),
+            MemberKind::File => (member.path.clone(), 0o100000 | member.mode, Vec::new()),
+            MemberKind::Symlink(target) => {
+                (member.path.clone(), 0o120777, target.as_bytes().to_vec())
+            }
+        };
+        let streamed = matches!(member.kind, MemberKind::File);
+        let zip64 = streamed && member.size >= u32::MAX as u64;
+        let flags = if streamed {
+            FLAG_UTF8 | FLAG_DATA_DESCRIPTOR
+        } else {
+            FLAG_UTF8
+        };
+        let crc = crc32fast::hash(&body);
+
+        let mut extra = timestamp_extra(member.mtime);
+        if zip64 {
+            // Sizes follow in the data descriptor
+            extra.extend(EXTRA_ZIP64.to_le_bytes());
+            extra.extend(16u16.to_le_bytes());
+            extra.extend([0u8; 16]);
+        }
+
+        let mut out = Vec::with_capacity(30 + name.len() + extra.len() + body.len());
+        out.extend(ZIP_LOCAL_HEADER.to_le_bytes());
+        out.extend(
+            if zip64 {
+                VERSION_ZIP64
+            } else {
+                VERSION_DEFAULT
+            }
+            .to_le_bytes(),
+        );
+        out.extend(flags.to_le_bytes());
+        out.extend(0u16.to_le_bytes()); // stored
+        let (time, date) = dos_datetime(member.mtime);
+        out.extend(time.to_le_bytes());
+        out.extend(date.to_le_bytes());
+        if streamed {
+            let sizes = if zip64 { u32::MAX } else { 0 };
+            out.extend(0u32.to_le_bytes());
+            out.extend(sizes.to_le_bytes());
+            out.extend(sizes.to_le_bytes());
+        } else {
+            out.extend(crc.to_le_bytes());
+            out.extend((body.len() as u32).to_le_bytes());
+            out.extend((body.len() as u32).to_le_bytes());
+        }
+        out.extend((name.len() as u16).to_le_bytes());
+        out.extend((extra.len() as u16).to_le_bytes());
+        out.extend(name.as_bytes());
+        out.extend(&extra);
+        out.extend(&body);
+
+        self.records.push(CentralRecord {
+            name: name.into_bytes(),
+            flags,
+            crc,
+            size: body.len() as u64,
+            offset: self.offset,
+            unix_mode,
+            is_dir: matches!(member.kind, MemberKind::Dir),
+            mtime: member.mtime,
+            zip64,
+        });
+        self.offset += out.len() as u64;
+        if streamed {
+            self.current = Some(ZipCurrent {
+                hasher: crc32fast::Hasher::new(),
+                size: 0,
+            });
+        }
+        out
+    }
+
+    fn data(&mut self, chunk: &[u8]) {
+        if let Some(current) = self.current.as_mut() {
+            current.hasher.update(chunk);
+            current.size += chunk.len() as u64;
+        }
+        self.offset += chunk.len() as u64;
+    }
+
+    fn end_file(&mut self) -> Vec<u8> {
+        let (Some(current), Some(record)) = (self.current.take(), self.records.last_mut()) else {
+            return Vec::new();
+        };
+        record.crc = current.hasher.finalize();
+        record.size = current.size;
+
+        let mut out = Vec::with_capacity(24);
+        out.extend(ZIP_DATA_DESCRIPTOR.to_le_bytes());
+        out.extend(record.crc.to_le_bytes());
+        if record.zip64 {
+            out.extend(record.size.to_le_bytes());
+            out.extend(record.size.to_le_bytes());
+        } else {
+            out.extend((record.size as u32).to_le_bytes());
+            out.extend((record.size as u32).to_le_bytes());
+        }
+        self.offset += out.len() as u64;
+        out
+    }
+
+    fn finish(&mut self) -> Vec<u8> {
+        let cd_offset = self.offset;
+        let mut out = Vec::new();
+
+        for record in &self.records {
+            let large_size = record.size >= u32::MAX as u64;
+            let large_offset = record.offset >= u32::MAX as u64;
+            let mut extra = timestamp_extra(record.mtime);
+            if large_size || large_offset {
+                let mut zip64 = Vec::new();
+                if large_size {
+                    zip64.extend(record.size.to_le_bytes());
+                    zip64.extend(record.size.to_le_bytes());
+                }
+                if large_offset {
+                    zip64.extend(record.offset.to_le_bytes());
+                }
+                extra.extend(EXTRA_ZIP64.to_le_bytes());
+                extra.extend((zip64.len() as u16).to_le_bytes());
+                extra.extend(zip64);
+            }
+            let version_needed = if record.zip64 || large_size || large_offset {
+                VERSION_ZIP64
+            } else {
+                VERSION_DEFAULT
+            };
+            let size32 = record.size.min(u32::MAX as u64) as u32;
+            let offset32 = record.offset.min(u32::MAX as u64) as u32;
+            let dos_attrs = if record.is_dir { 0x10 } else { 0 };
+            let (time, date) = dos_datetime(record.mtime);
+
+            out.extend(ZIP_CENTRAL_HEADER.to_le_bytes());
+            out.extend(VERSION_MADE_BY.to_le_bytes());
+            out.extend(version_needed.to_le_bytes());
+            out.extend(record.flags.to_le_bytes());
+            out.extend(0u16.to_le_bytes()); // stored
+            out.extend(time.to_le_bytes());
+            out.extend(date.to_le_bytes());
+            out.extend(record.crc.to_le_bytes());
+            out.extend(size32.to_le_bytes());
+            out.extend(size32.to_le_bytes());
+            out.extend((record.name.len() as u16).to_le_bytes());
+            out.extend((extra.len() as u16).to_le_bytes());
+            out.extend(0u16.to_le_bytes()); // comment length
+            out.extend(0u16.to_le_bytes()); // disk number
+            out.extend(0u16.to_le_bytes()); // internal attributes
+            out.extend(((record.unix_mode << 16) | dos_attrs).to_le_bytes());
+            out.extend(offset32.to_le_bytes());
+            out.extend(&record.name);
+            out.extend(&extra);
+        }
+
+        let cd_size = out.len() as u64;
+        let count = self.records.len() as u64;
+        let zip64_end =
+            count >= u16::MAX as u64 || cd_size >= u32::MAX as u64 || cd_offset >= u32::MAX as u64;
+        if zip64_end {
+            let end_offset = cd_offset + cd_size;
+            out.extend(ZIP64_END_OF_CENTRAL_DIR.to_le_bytes());
+            out.extend(44u64.to_le_bytes()); // size of the rest of this record
+            out.extend(VERSION_MADE_BY.to_le_bytes());
+            out.extend(VERSION_ZIP64.to_le_bytes());
+            out.extend(0u32.to_le_bytes());
+            out.extend(0u32.to_le_bytes());
+            out.extend(count.to_le_bytes());
+            out.extend(count.to_le_bytes());
+            out.extend(cd_size.to_le_bytes());
+            out.extend(cd_offset.to_le_bytes());
+
+            out.extend(ZIP64_END_LOCATOR.to_le_bytes());
+            out.extend(0u32.to_le_bytes());
+            out.extend(end_offset.to_le_bytes());
+            out.extend(1u32.to_le_bytes());
+        }
+
+        let count16 = count.min(u16::MAX as u64) as u16;
+        out.extend(ZIP_END_OF_CENTRAL_DIR.to_le_bytes());
+        out.extend(0u16.to_le_bytes());
+        out.extend(0u16.to_le_bytes());
+        out.extend(count16.to_le_bytes());
+        out.extend(count16.to_le_bytes());
+        out.extend((cd_size.min(u32::MAX as u64) as u32).to_le_bytes());
+        out.extend((cd_offset.min(u32::MAX as u64) as u32).to_le_bytes());
+        out.extend(0u16.to_le_bytes()); // comment length
+
+        self.offset += out.len() as u64;
+        out
+    }
+}
+
+/// Extended timestamp extra field (exact UTC mtime)
+fn timestamp_extra(mtime: i64) -> Vec<u8> {
+    let mut extra = Vec::with_capacity(9);
+    extra.extend(EXTRA_TIMESTAMP.to_le_bytes());
+    extra.extend(5u16.to_le_bytes());
+    extra.push(1); // mtime present
+    extra.extend((mtime.clamp(0, u32::MAX as i64) as u32).to_le_bytes());
+    extra
+}
+
+/// MS-DOS (time, date) for a Unix timestamp, clamped to 1980..=2107
+pub(crate) fn dos_datetime(mtime: i64) -> (u16, u16) {
+    let days = mtime.div_euclid(86_400);
+    let secs = mtime.rem_euclid(86_400);
+
+    // Civil date from days since the epoch (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    if year < 1980 {
+        return (0, (1 << 5) | 1);
+    }
+    if year > 2107 {
+        return ((23 << 11) | (59 << 5) | 29, (127 << 9) | (12 << 5) | 31);
+    }
+    let time = ((secs / 3600) << 11) | (((secs % 3600) / 60) << 5) | ((secs % 60) / 2);
+    let date = ((year - 1980) << 9) | (month << 5) | day;
+    (time as u16, date as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashtree::HashTreeConfig;
+    use crate::posix::PosixMeta;
+    use crate::store::MemoryStore;
+    use crate::types::DirEntry;
+    use std::sync::Arc;
+
+    async fn sample_tree() -> (Arc<MemoryStore>, HashTree<MemoryStore>, Cid) {
+        let store = Arc::new(MemoryStore::new());
+        let tree = HashTree::new(HashTreeConfig::new(store.clone()).public());
+        // Small chunks so the big file is a chunk tree
+        let chunked = HashTree::new(
+            HashTreeConfig::new(store.clone())
+                .public()
+                .with_chunk_size(64),
+        );
+
+        let big: Vec<u8> = (0..1000u32).map(|i| (i % 251) as u8).collect();
+        let (big_cid, big_size) = chunked.put(&big).await.unwrap();
+        let (small_cid, _) = tree.put(b"hello").await.unwrap();
+        let (link_cid, _) = tree.put(b"big.bin").await.unwrap();
+
+        let sub = tree
+            .put_directory(vec![DirEntry::from_cid("big.bin", &big_cid)
+                .with_size(big_size)
+                .with_link_type(LinkType::File)
+                .with_posix(&PosixMeta::default().with_mode(0o600))])
+            .await
+            .unwrap();
+        let root = tree
+            .put_directory(vec![
+                DirEntry::from_cid("a.txt", &small_cid)
+                    .with_size(5)
+                    .with_posix(
+                        &PosixMeta::default()
+                            .with_mode(0o755)
+                            .with_mtime(1_700_000_000),
+                    ),
+                DirEntry::from_cid("link", &link_cid)
+                    .with_size(7)
+                    .with_posix(&PosixMeta::default().with_symlink("sub/big.bin")),
+                DirEntry::from_cid("sub", &sub).with_link_type(LinkType::Dir),
+            ])
+            .await
+            .unwrap();
+        (store, tree, root)
+    }
+
+    async fn collect(tree: &HashTree<MemoryStore>, root: Cid, format: ArchiveFormat) -> Vec<u8> {
+        let mut stream = archive_stream(tree, root, format);
+        let mut out = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            out.extend(chunk.unwrap());
+        }
+        out
+    }
+
+    fn octal(field: &[u8]) -> u64 {
+        let text = std::str::from_utf8(field).unwrap().trim_end_matches('\0');
+        u64::from_str_radix(text.trim(), 8).unwrap()
+    }
+
+    #[test]
+    fn test_format_parsing() {
+        assert_eq!("TAR".parse::<ArchiveFormat>(), Ok(ArchiveFormat::Tar));
+        assert_eq!("zip".parse::<ArchiveFormat>(), Ok(ArchiveFormat::Zip));
+        assert!("rar".parse::<ArchiveFormat>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_tar_members() {
+        let (_store, tree, root) = sample_tree().await;
+        let tar = collect(&tree, root, ArchiveFormat::Tar).await;
+        assert_eq!(tar.len() % TAR_BLOCK, 0);
+
+        // (name, typeflag, mode, size, linkname)
+        let mut members = Vec::new();
+        let mut pos = 0;
+        while tar[pos..pos + TAR_BLOCK].iter().any(|&b| b != 0) {
+            let header = &tar[pos..pos + TAR_BLOCK];
+            let name = String::from_utf8_lossy(&header[..100])
+                .trim_end_matches('\0')
+                .to_string();
+            let size = octal(&header[124..136]);
+            let linkname = String::from_utf8_lossy(&header[157..257])
+                .trim_end_matches('\0')
+                .to_string();
+            members.push((name, header[156], octal(&header[100..108]), size, linkname));
+            if header[156] == b'0' && size == 1000 {
+                let data = &tar[pos + TAR_BLOCK..pos + TAR_BLOCK + 1000];
+                assert!(data.iter().enumerate().all(|(i, &b)| b == (i % 251) as u8));
+            }
+            pos += TAR_BLOCK + size as usize + tar_padding(size);
+        }
+
+        assert_eq!(
+            members,
+            vec![
+                ("a.txt".into(), b'0', 0o755, 5, String::new()),
+                ("link".into(), b'2', 0o777, 0, "sub/big.bin".into()),
+                ("sub/".into(), b'5', 0o755, 0, String::new()),
+                ("sub/big.bin".into(), b'0', 0o600, 1000, String::new()),
+            ]
+        );
+        assert_eq!(tar.len(), pos + 2 * TAR_BLOCK);
+    }
+
+    #[tokio::test]
+    async fn test_zip_central_directory() {
+        let (_store, tree, root) = sample_tree().await;
+        let zip = collect(&tree, root, ArchiveFormat::Zip).await;
+
+        let end = &zip[zip.len() - 22..];
+        assert_eq!(&end[..4], &ZIP_END_OF_CENTRAL_DIR.to_le_bytes());
+        let count = u16::from_le_bytes([end[10], end[11]]);
+        let cd_offset = u32::from_le_bytes(end[16..20].try_into().unwrap()) as usize;
+        assert_eq!(count, 4);
+
+        let mut pos = cd_offset;
+        let mut names = Vec::new();
+        for _ in 0..count {
+            let header = &zip[pos..];
+            assert_eq!(&header[..4], &ZIP_CENTRAL_HEADER.to_le_bytes());
+            let crc = u32::from_le_bytes(header[16..20].try_into().unwrap());
+            let size = u32::from_le_bytes(header[24..28].try_into().unwrap());
+            let name_len = u16::from_le_bytes([header[28], header[29]]) as usize;
+            let extra_len = u16::from_le_bytes([header[30], header[31]]) as usize;
+            let attrs = u32::from_le_bytes(header[38..42].try_into().unwrap());
+            let offset = u32::from_le_bytes(header[42..46].try_into().unwrap()) as usize;
+            let name = String::from_utf8(header[46..46 + name_len].to_vec()).unwrap();
+
+            // Local header points at the same name, followed by the content
+            let local = &zip[offset..];
+            assert_eq!(&local[..4], &ZIP_LOCAL_HEADER.to_le_bytes());
+            let local_name_len = u16::from_le_bytes([local[26], local[27]]) as usize;
+            let local_extra_len = u16::from_le_bytes([local[28], local[29]]) as usize;
+            assert_eq!(&local[30..30 + local_name_len], name.as_bytes());
+            let data_start = 30 + local_name_len + local_extra_len;
+            let data = &local[data_start..data_start + size as usize];
+            assert_eq!(crc32fast::hash(data), crc);
+
+            names.push((name, attrs >> 16, size));
+            pos += 46 + name_len + extra_len;
+        }
+
+        assert_eq!(
+            names,
+            vec![
+                ("a.txt".into(), 0o100755, 5),
+                ("link".into(), 0o120777, 11),
+                ("sub/".into(), 0o040755, 0),
+                ("sub/big.bin".into(), 0o100600, 1000),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_missing_chunk_fails() {
+        let (store, tree, root) = sample_tree().await;
+        let sub = tree
+            .resolve_path(&root, "sub/big.bin")
+            .await
+            .unwrap()
+            .unwrap();
+        let node = tree.get_node(&sub).await.unwrap().unwrap();
+        store.delete(&node.links[1].hash).await.unwrap();
+
+        let mut stream = archive_stream(&tree, root, ArchiveFormat::Tar);
+        let mut failed = false;
+        while let Some(chunk) = stream.next().await {
+            if let Err(e) = chunk {
+                assert!(matches!(e, HashTreeError::MissingChunk(_)));
+                failed = true;
+            }
+        }
+        assert!(failed);
+    }
+
+    #[test]
+    fn test_dos_datetime() {
+        // 2023-11-14 22:13:20 UTC
+        let (time, date) = dos_datetime(1_700_000_000);
+        assert_eq!(date, ((2023 - 1980) << 9) | (11 << 5) | 14);
+        assert_eq!(time, (22 << 11) | (13 << 5) | 10);
+        assert_eq!(dos_datetime(0), (0, (1 << 5) | 1));
+    }
+
+    #[test]
+    fn test_large_values_use_base256() {
+        let mut field = [0u8; 12];
+        write_octal(&mut field, 0o777);
+        assert_eq!(&field, b"00000000777\0");
+        write_octal(&mut field, 10 << 30);
+        assert_eq!(field[0], 0x80);
+        assert_eq!(u64::from_be_bytes(field[4..].try_into().unwrap()), 10 << 30);
+    }
+}
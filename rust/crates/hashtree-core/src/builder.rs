@@ -8,6 +8,7 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use crate::chunker::ChunkingMode;
 use crate::codec::encode_and_hash;
 use crate::hash::sha256;
 use crate::store::Store;
@@ -32,6 +33,8 @@ pub struct BuilderConfig<S: Store> {
     pub max_links: usize,
     /// Whether to encrypt content (default: true when encryption feature enabled)
     pub encrypted: bool,
+    /// How file content is split into chunks (default: fixed `chunk_size`)
+    pub chunking: ChunkingMode,
 }
 
 impl<S: Store> BuilderConfig<S> {
@@ -41,6 +44,7 @@ impl<S: Store> BuilderConfig<S> {
             chunk_size: DEFAULT_CHUNK_SIZE,
             max_links: DEFAULT_MAX_LINKS,
             encrypted: true,
+            chunking: ChunkingMode::Fixed,
         }
     }
 
@@ -49,6 +53,12 @@ impl<S: Store> BuilderConfig<S> {
         self
     }
 
+    /// Set the chunking mode (e.g. content-defined chunking)
+    pub fn with_chunking(mut self, chunking: ChunkingMode) -> Self {
+        self.chunking = chunking;
+        self
+    }
+
     pub fn with_max_links(mut self, max_links: usize) -> Self {
         self.max_links = max_links;
         self
@@ -73,6 +83,7 @@ pub struct TreeBuilder<S: Store> {
     chunk_size: usize,
     max_links: usize,
     encrypted: bool,
+    chunking: ChunkingMode,
 }
 
 impl<S: Store> TreeBuilder<S> {
@@ -82,6 +93,7 @@ impl<S: Store> TreeBuilder<S> {
             chunk_size: config.chunk_size,
             max_links: config.max_links,
             encrypted: config.encrypted,
+            chunking: config.chunking,
         }
     }
 
@@ -129,20 +141,18 @@ impl<S: Store> TreeBuilder<S> {
     /// and the result contains the decryption key.
     pub async fn put(&self, data: &[u8]) -> Result<(Cid, u64), BuilderError> {
         let size = data.len() as u64;
+        let chunks = self.chunking.split(data, self.chunk_size);
 
         // Small file - store as single chunk
-        if data.len() <= self.chunk_size {
+        if chunks.len() <= 1 {
             let (hash, key) = self.put_chunk_internal(data).await?;
             return Ok((Cid { hash, key }, size));
         }
 
         // Large file - chunk it
         let mut links: Vec<Link> = Vec::new();
-        let mut offset = 0;
 
-        while offset < data.len() {
-            let end = (offset + self.chunk_size).min(data.len());
-            let chunk = &data[offset..end];
+        for chunk in chunks {
             let chunk_size = chunk.len() as u64;
             let (hash, key) = self.put_chunk_internal(chunk).await?;
             links.push(Link {
@@ -153,7 +163,6 @@ impl<S: Store> TreeBuilder<S> {
                 link_type: LinkType::Blob, // leaf chunk
                 meta: None,
             });
-            offset = end;
         }
 
         // Build tree from chunks
@@ -460,6 +469,7 @@ pub struct StreamBuilder<S: Store> {
     store: Arc<S>,
     chunk_size: usize,
     max_links: usize,
+    chunking: ChunkingMode,
 
     // Current partial chunk being built
     buffer: Vec<u8>,
@@ -471,11 +481,13 @@ pub struct StreamBuilder<S: Store> {
 
 impl<S: Store> StreamBuilder<S> {
     pub fn new(config: BuilderConfig<S>) -> Self {
+        let max_chunk_size = config.chunking.max_chunk_size(config.chunk_size);
         Self {
             store: config.store,
             chunk_size: config.chunk_size,
             max_links: config.max_links,
-            buffer: Vec::with_capacity(config.chunk_size),
+            chunking: config.chunking,
+            buffer: Vec::with_capacity(max_chunk_size),
             chunks: Vec::new(),
             total_size: 0,
         }
@@ -483,19 +495,23 @@ impl<S: Store> StreamBuilder<S> {
 
     /// Append data to the stream
     pub async fn append(&mut self, data: &[u8]) -> Result<(), BuilderError> {
+        let max_chunk_size = self.chunking.max_chunk_size(self.chunk_size);
         let mut offset = 0;
 
         while offset < data.len() {
-            let space = self.chunk_size - self.buffer.len();
+            let space = max_chunk_size - self.buffer.len();
             let to_write = space.min(data.len() - offset);
 
             self.buffer
                 .extend_from_slice(&data[offset..offset + to_write]);
             offset += to_write;
 
-            // Flush full chunk
-            if self.buffer.len() == self.chunk_size {
-                self.flush_chunk().await?;
+            // Flush every chunk whose boundary is known
+            while let Some(len) = self
+                .chunking
+                .next_chunk_len(&self.buffer, self.chunk_size, false)
+            {
+                self.flush_chunk(len).await?;
             }
         }
 
@@ -503,29 +519,29 @@ impl<S: Store> StreamBuilder<S> {
         Ok(())
     }
 
-    /// Flush current buffer as a chunk
-    async fn flush_chunk(&mut self) -> Result<(), BuilderError> {
-        if self.buffer.is_empty() {
+    /// Flush the first `len` buffered bytes as a chunk
+    async fn flush_chunk(&mut self, len: usize) -> Result<(), BuilderError> {
+        if len == 0 {
             return Ok(());
         }
 
-        let chunk = std::mem::take(&mut self.buffer);
+        let chunk: Vec<u8> = self.buffer.drain(..len).collect();
         let hash = sha256(&chunk);
+        let size = chunk.len() as u64;
         self.store
-            .put(hash, chunk.clone())
+            .put(hash, chunk)
             .await
             .map_err(|e| BuilderError::Store(e.to_string()))?;
 
         self.chunks.push(Link {
             hash,
             name: None,
-            size: chunk.len() as u64,
+            size,
             key: None,
             link_type: LinkType::Blob, // Leaf chunk (raw blob)
             meta: None,
         });
 
-        self.buffer = Vec::with_capacity(self.chunk_size);
         Ok(())
     }
 
@@ -536,13 +552,12 @@ impl<S: Store> StreamBuilder<S> {
             return Ok(None);
         }
 
-        // Temporarily include buffer
+        // Temporarily include buffer, split as if the stream ended here
         let mut temp_chunks = self.chunks.clone();
-        if !self.buffer.is_empty() {
-            let chunk = self.buffer.clone();
-            let hash = sha256(&chunk);
+        for chunk in self.chunking.split(&self.buffer, self.chunk_size) {
+            let hash = sha256(chunk);
             self.store
-                .put(hash, chunk.clone())
+                .put(hash, chunk.to_vec())
                 .await
                 .map_err(|e| BuilderError::Store(e.to_string()))?;
             temp_chunks.push(Link {
@@ -564,7 +579,12 @@ impl<S: Store> StreamBuilder<S> {
     /// Finalize the stream and return root hash
     pub async fn finalize(mut self) -> Result<(Hash, u64), BuilderError> {
         // Flush remaining buffer
-        self.flush_chunk().await?;
+        while let Some(len) = self
+            .chunking
+            .next_chunk_len(&self.buffer, self.chunk_size, true)
+        {
+            self.flush_chunk(len).await?;
+        }
 
         if self.chunks.is_empty() {
             // Empty stream - return hash of empty data
@@ -872,6 +892,35 @@ mod tests {
         assert!(store.has(&hash).await.unwrap());
     }
 
+    #[tokio::test]
+    async fn test_stream_builder_content_defined_matches_put() {
+        use crate::chunker::CdcParams;
+
+        let store = make_store();
+        let chunking = ChunkingMode::ContentDefined(CdcParams::new(64, 256, 1024));
+        let data: Vec<u8> = (0..20_000u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8)
+            .collect();
+
+        let builder = TreeBuilder::new(
+            BuilderConfig::new(store.clone())
+                .with_chunking(chunking)
+                .public(),
+        );
+        let (cid, _) = builder.put(&data).await.unwrap();
+
+        // Odd-sized appends must not change where chunks are cut
+        let mut stream =
+            StreamBuilder::new(BuilderConfig::new(store.clone()).with_chunking(chunking));
+        for part in data.chunks(777) {
+            stream.append(part).await.unwrap();
+        }
+        let (hash, size) = stream.finalize().await.unwrap();
+
+        assert_eq!(size, data.len() as u64);
+        assert_eq!(hash, cid.hash);
+    }
+
     #[tokio::test]
     async fn test_unified_put_public() {
         let store = make_store();
@@ -0,0 +1,582 @@
+//! Portable single-file bundles of complete trees
+//!
+//! A bundle carries every block reachable from one or more roots, so trees
+//! can move between machines without Blossom or WebRTC (much like a CAR
+//! file). Integers are little-endian:
+//!
+//! ```text
+//! header   "HTBUNDLE" version:u8 flags:u8 root_count:u32
+//!          root_count × (hash:32 has_key:u8 [key:32])
+//! records  len:u32 hash:32 data:len     raw stored bytes, parents first
+//! end      len = 0xFFFFFFFF
+//! index    count:u64 count × (hash:32 offset:u64 len:u32), sorted by hash
+//! trailer  index_offset:u64 "HTBINDEX"
+//! ```
+//!
+//! The index and trailer are only written with `FLAG_INDEX`; they let
+//! `BundleReader` fetch single blocks with one seek. Records hold blocks
+//! exactly as stored, so encrypted trees stay encrypted and keys only travel
+//! in the header roots.
+
+use std::collections::HashSet;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use futures::StreamExt;
+
+use crate::codec::try_decode_tree_node;
+use crate::crypto::decrypt_chk;
+use crate::hashtree::{HashTree, HashTreeError};
+use crate::store::{Store, StoreError};
+use crate::types::{to_hex, Cid, Hash, LinkType};
+use crate::verifying::verify_blob;
+
+/// First bytes of every bundle
+pub const BUNDLE_MAGIC: &[u8; 8] = b"HTBUNDLE";
+
+/// Format version written by this crate
+pub const BUNDLE_VERSION: u8 = 1;
+
+/// Header flag: an index and trailer follow the end marker
+pub const FLAG_INDEX: u8 = 0x01;
+
+/// Last bytes of an indexed bundle
+const INDEX_MAGIC: &[u8; 8] = b"HTBINDEX";
+
+/// Record length marking the end of the records
+const END_MARKER: u32 = u32::MAX;
+
+/// Size of one index entry
+const INDEX_ENTRY_LEN: usize = 32 + 8 + 4;
+
+/// Bundle error type
+#[derive(Debug, thiserror::Error)]
+pub enum BundleError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid bundle: {0}")]
+    InvalidFormat(String),
+    #[error("Missing block: {0}")]
+    MissingBlock(String),
+    #[error("Store error: {0}")]
+    Store(#[from] StoreError),
+    #[error(transparent)]
+    Tree(#[from] HashTreeError),
+}
+
+/// Counts from writing or reading a bundle
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BundleStats {
+    /// Unique blocks
+    pub blocks: u64,
+    /// Total size of the block data
+    pub bytes: u64,
+}
+
+/// Result of importing a bundle
+#[derive(Debug, Clone)]
+pub struct ImportedBundle {
+    pub roots: Vec<Cid>,
+    pub stats: BundleStats,
+}
+
+/// Write every block reachable from `roots` as a bundle
+///
+/// Blocks are written in `walk_stream` order, each once. Fails with
+/// `MissingBlock` if any reachable block is absent from the store, so a
+/// finished export is always complete.
+pub async fn export_bundle<S: Store, W: Write>(
+    tree: &HashTree<S>,
+    roots: &[Cid],
+    mut writer: W,
+    with_index: bool,
+) -> Result<BundleStats, BundleError> {
+    let flags = if with_index { FLAG_INDEX } else { 0 };
+    let header = encode_header(roots, flags)?;
+    writer.write_all(&header)?;
+    let mut offset = header.len() as u64;
+
+    let store = tree.get_store();
+    let mut stats = BundleStats::default();
+    let mut index: Vec<(Hash, u64, u32)> = Vec::new();
+    let mut written = HashSet::new();
+    // Children named by written nodes; the walk skips blocks it can't find
+    let mut expected: HashSet<Hash> = roots.iter().map(|root| root.hash).collect();
+
+    for root in roots {
+        let mut walk = tree.walk_stream(root.clone(), String::new());
+        while let Some(entry) = walk.next().await {
+            let entry = entry?;
+            if written.contains(&entry.hash) {
+                continue;
+            }
+            let data = store
+                .get(&entry.hash)
+                .await?
+                .ok_or_else(|| BundleError::MissingBlock(to_hex(&entry.hash)))?;
+            let len = u32::try_from(data.len())
+                .ok()
+                .filter(|&len| len != END_MARKER)
+                .ok_or_else(|| {
+                    BundleError::InvalidFormat(format!(
+                        "block {} is too large",
+                        to_hex(&entry.hash)
+                    ))
+                })?;
+
+            if entry.link_type != LinkType::Blob {
+                let plain = match &entry.key {
+                    Some(key) => decrypt_chk(&data, key)
+                        .map_err(|e| HashTreeError::Decryption(e.to_string()))?,
+                    None => data.clone(),
+                };
+                if let Some(node) = try_decode_tree_node(&plain) {
+                    expected.extend(node.links.iter().map(|link| link.hash));
+                }
+            }
+
+            writer.write_all(&len.to_le_bytes())?;
+            writer.write_all(&entry.hash)?;
+            writer.write_all(&data)?;
+            index.push((entry.hash, offset + 4 + 32, len));
+            offset += 4 + 32 + len as u64;
+            stats.blocks += 1;
+            stats.bytes += len as u64;
+            written.insert(entry.hash);
+        }
+    }
+
+    if let Some(missing) = expected.iter().find(|hash| !written.contains(*hash)) {
+        return Err(BundleError::MissingBlock(to_hex(missing)));
+    }
+
+    writer.write_all(&END_MARKER.to_le_bytes())?;
+    offset += 4;
+    if with_index {
+        index.sort_by_key(|entry| entry.0);
+        let mut out = Vec::with_capacity(8 + index.len() * INDEX_ENTRY_LEN + 16);
+        out.extend((index.len() as u64).to_le_bytes());
+        for (hash, data_offset, len) in &index {
+            out.extend(hash);
+            out.extend(data_offset.to_le_bytes());
+            out.extend(len.to_le_bytes());
+        }
+        out.extend(offset.to_le_bytes());
+        out.extend(INDEX_MAGIC);
+        writer.write_all(&out)?;
+    }
+    writer.flush()?;
+    Ok(stats)
+}
+
+/// Read a bundle into `store`, verifying every record
+///
+/// Each block is hashed before it is stored, and the roots are checked to be
+/// complete once all records are in. Blocks are written as they arrive, so a
+/// rejected bundle can leave verified but unpinned blocks behind for gc.
+pub async fn import_bundle<S: Store + ?Sized, R: Read>(
+    store: &S,
+    mut reader: R,
+) -> Result<ImportedBundle, BundleError> {
+    let (roots, _flags) = read_header(&mut reader)?;
+    let mut stats = BundleStats::default();
+    let mut seen = HashSet::new();
+
+    loop {
+        let len = read_u32(&mut reader)?;
+        if len == END_MARKER {
+            break;
+        }
+        let mut hash = [0u8; 32];
+        reader.read_exact(&mut hash).map_err(truncated)?;
+        // Grow with the data instead of trusting the length up front
+        let mut data = Vec::new();
+        (&mut reader).take(len as u64).read_to_end(&mut data)?;
+        if data.len() != len as usize {
+            return Err(truncated_bundle());
+        }
+        verify_blob(&hash, &data)?;
+
+        if seen.insert(hash) {
+            store.put(hash, data).await?;
+            stats.blocks += 1;
+            stats.bytes += len as u64;
+        }
+    }
+
+    check_complete(store, &roots, &seen).await?;
+    Ok(ImportedBundle { roots, stats })
+}
+
+/// Walk the roots through `store`, requiring every block to be in `seen`
+async fn check_complete<S: Store + ?Sized>(
+    store: &S,
+    roots: &[Cid],
+    seen: &HashSet<Hash>,
+) -> Result<(), BundleError> {
+    // (hash, key, is a file chunk that can't have children)
+    let mut stack: Vec<(Hash, Option<[u8; 32]>, bool)> = roots
+        .iter()
+        .map(|root| (root.hash, root.key, false))
+        .collect();
+    let mut visited = HashSet::new();
+
+    while let Some((hash, key, leaf)) = stack.pop() {
+        if !visited.insert(hash) {
+            continue;
+        }
+        if !seen.contains(&hash) {
+            return Err(BundleError::MissingBlock(to_hex(&hash)));
+        }
+        if leaf {
+            continue;
+        }
+        let data = store
+            .get(&hash)
+            .await?
+            .ok_or_else(|| BundleError::MissingBlock(to_hex(&hash)))?;
+        let data = match &key {
+            Some(key) => {
+                decrypt_chk(&data, key).map_err(|e| HashTreeError::Decryption(e.to_string()))?
+            }
+            None => data,
+        };
+        if let Some(node) = try_decode_tree_node(&data) {
+            for link in node.links {
+                let leaf = node.node_type == LinkType::File && link.link_type == LinkType::Blob;
+                stack.push((link.hash, link.key, leaf));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Random access to the blocks of an indexed bundle
+pub struct BundleReader<R> {
+    reader: R,
+    roots: Vec<Cid>,
+    /// (hash, data offset, length), sorted by hash
+    index: Vec<(Hash, u64, u32)>,
+}
+
+impl<R: Read + Seek> BundleReader<R> {
+    /// Read the header and index; fails for bundles written without an index
+    pub fn open(mut reader: R) -> Result<Self, BundleError> {
+        reader.seek(SeekFrom::Start(0))?;
+        let (roots, flags) = read_header(&mut reader)?;
+        if flags & FLAG_INDEX == 0 {
+            return Err(BundleError::InvalidFormat(
+                "bundle was written without an index".to_string(),
+            ));
+        }
+
+        let end = reader.seek(SeekFrom::End(-16))?;
+        let mut trailer = [0u8; 16];
+        reader.read_exact(&mut trailer)?;
+        if &trailer[8..] != INDEX_MAGIC {
+            return Err(BundleError::InvalidFormat(
+                "missing index trailer".to_string(),
+            ));
+        }
+        let index_offset = u64::from_le_bytes(trailer[..8].try_into().unwrap());
+        if index_offset > end {
+            return Err(BundleError::InvalidFormat(
+                "index out of bounds".to_string(),
+            ));
+        }
+
+        reader.seek(SeekFrom::Start(index_offset))?;
+        let count = read_u64(&mut reader)?;
+        if count.saturating_mul(INDEX_ENTRY_LEN as u64) != end - index_offset - 8 {
+            return Err(BundleError::InvalidFormat(
+                "index size mismatch".to_string(),
+            ));
+        }
+        let mut entries = vec![0u8; count as usize * INDEX_ENTRY_LEN];
+        reader.read_exact(&mut entries)?;
+        let index = entries
+            .chunks_exact(INDEX_ENTRY_LEN)
+            .map(|entry| {
+                (
+                    entry[..32].try_into().unwrap(),
+                    u64::from_le_bytes(entry[32..40].try_into().unwrap()),
+                    u32::from_le_bytes(entry[40..44].try_into().unwrap()),
+                )
+            })
+            .collect();
+
+        Ok(Self {
+            reader,
+            roots,
+            index,
+        })
+    }
+
+    pub fn roots(&self) -> &[Cid] {
+        &self.roots
+    }
+
+    /// Number of blocks in the index
+    pub fn block_count(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn contains(&self, hash: &Hash) -> bool {
+        self.find(hash).is_some()
+    }
+
+    /// Read one block, verifying its hash
+    pub fn get(&mut self, hash: &Hash) -> Result<Option<Vec<u8>>, BundleError> {
+        let Some((offset, len)) = self.find(hash) else {
+            return Ok(None);
+        };
+        self.reader.seek(SeekFrom::Start(offset))?;
+        let mut data = vec![0u8; len as usize];
+        self.reader.read_exact(&mut data).map_err(truncated)?;
+        verify_blob(hash, &data)?;
+        Ok(Some(data))
+    }
+
+    fn find(&self, hash: &Hash) -> Option<(u64, u32)> {
+        self.index
+            .binary_search_by(|entry| entry.0.cmp(hash))
+            .ok()
+            .map(|i| (self.index[i].1, self.index[i].2))
+    }
+}
+
+fn encode_header(roots: &[Cid], flags: u8) -> Result<Vec<u8>, BundleError> {
+    let count = u32::try_from(roots.len())
+        .map_err(|_| BundleError::InvalidFormat("too many roots".to_string()))?;
+    let mut out = Vec::with_capacity(14 + roots.len() * 65);
+    out.extend(BUNDLE_MAGIC);
+    out.push(BUNDLE_VERSION);
+    out.push(flags);
+    out.extend(count.to_le_bytes());
+    for root in roots {
+        out.extend(root.hash);
+        match &root.key {
+            Some(key) => {
+                out.push(1);
+                out.extend(key);
+            }
+            None => out.push(0),
+        }
+    }
+    Ok(out)
+}
+
+/// Parse the header, returning the roots and flags
+fn read_header<R: Read>(reader: &mut R) -> Result<(Vec<Cid>, u8), BundleError> {
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic).map_err(truncated)?;
+    if &magic != BUNDLE_MAGIC {
+        return Err(BundleError::InvalidFormat(
+            "not a hashtree bundle".to_string(),
+        ));
+    }
+    let mut version_flags = [0u8; 2];
+    reader.read_exact(&mut version_flags).map_err(truncated)?;
+    let [version, flags] = version_flags;
+    if version != BUNDLE_VERSION {
+        return Err(BundleError::InvalidFormat(format!(
+            "unsupported version {}",
+            version
+        )));
+    }
+
+    let count = read_u32(reader)?;
+    let mut roots = Vec::new();
+    for _ in 0..count {
+        let mut hash = [0u8; 32];
+        reader.read_exact(&mut hash).map_err(truncated)?;
+        let mut has_key = [0u8; 1];
+        reader.read_exact(&mut has_key).map_err(truncated)?;
+        let key = match has_key[0] {
+            0 => None,
+            1 => {
+                let mut key = [0u8; 32];
+                reader.read_exact(&mut key).map_err(truncated)?;
+                Some(key)
+            }
+            other => {
+                return Err(BundleError::InvalidFormat(format!(
+                    "bad key flag {}",
+                    other
+                )))
+            }
+        };
+        roots.push(Cid { hash, key });
+    }
+    Ok((roots, flags))
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, BundleError> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf).map_err(truncated)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64, BundleError> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf).map_err(truncated)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn truncated_bundle() -> BundleError {
+    BundleError::InvalidFormat("unexpected end of bundle".to_string())
+}
+
+fn truncated(e: std::io::Error) -> BundleError {
+    if e.kind() == std::io::ErrorKind::UnexpectedEof {
+        truncated_bundle()
+    } else {
+        BundleError::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::sha256;
+    use crate::hashtree::HashTreeConfig;
+    use crate::store::MemoryStore;
+    use crate::types::DirEntry;
+    use std::io::Cursor;
+    use std::sync::Arc;
+
+    /// Encrypted tree with a chunked file, a shared blob and a subdirectory
+    async fn sample_tree() -> (Arc<MemoryStore>, HashTree<MemoryStore>, Cid) {
+        let store = Arc::new(MemoryStore::new());
+        let tree = HashTree::new(HashTreeConfig::new(store.clone()).with_chunk_size(1024));
+
+        let big: Vec<u8> = (0..5000u32).map(|i| (i % 251) as u8).collect();
+        let (big_cid, big_size) = tree.put(&big).await.unwrap();
+        let (small_cid, small_size) = tree.put(b"shared").await.unwrap();
+        let sub = tree
+            .put_directory(vec![
+                DirEntry::from_cid("copy.txt", &small_cid).with_size(small_size)
+            ])
+            .await
+            .unwrap();
+        let root = tree
+            .put_directory(vec![
+                DirEntry::from_cid("big.bin", &big_cid)
+                    .with_size(big_size)
+                    .with_link_type(LinkType::File),
+                DirEntry::from_cid("small.txt", &small_cid).with_size(small_size),
+                DirEntry::from_cid("sub", &sub).with_link_type(LinkType::Dir),
+            ])
+            .await
+            .unwrap();
+        (store, tree, root)
+    }
+
+    async fn export(tree: &HashTree<MemoryStore>, root: &Cid, with_index: bool) -> Vec<u8> {
+        let mut out = Vec::new();
+        export_bundle(tree, std::slice::from_ref(root), &mut out, with_index)
+            .await
+            .unwrap();
+        out
+    }
+
+    #[tokio::test]
+    async fn test_round_trip() {
+        let (store, tree, root) = sample_tree().await;
+        let bundle = export(&tree, &root, false).await;
+
+        let target = Arc::new(MemoryStore::new());
+        let imported = import_bundle(&*target, Cursor::new(&bundle)).await.unwrap();
+        assert_eq!(imported.roots, vec![root.clone()]);
+        // The shared blob is stored once
+        assert_eq!(
+            imported.stats.blocks as usize,
+            store.list().await.unwrap().len()
+        );
+
+        let copy = HashTree::new(HashTreeConfig::new(target));
+        let big = copy.resolve_path(&root, "big.bin").await.unwrap().unwrap();
+        let data = copy.get(&big, None).await.unwrap().unwrap();
+        assert_eq!(data.len(), 5000);
+        let shared = copy
+            .resolve_path(&root, "sub/copy.txt")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(copy.get(&shared, None).await.unwrap().unwrap(), b"shared");
+    }
+
+    #[tokio::test]
+    async fn test_export_fails_on_missing_block() {
+        let (store, tree, root) = sample_tree().await;
+        let big = tree.resolve_path(&root, "big.bin").await.unwrap().unwrap();
+        let node = tree.get_node(&big).await.unwrap().unwrap();
+        store.delete(&node.links[2].hash).await.unwrap();
+
+        let result = export_bundle(&tree, &[root], Vec::new(), false).await;
+        assert!(matches!(result, Err(BundleError::MissingBlock(_))));
+    }
+
+    #[tokio::test]
+    async fn test_import_rejects_bad_bundles() {
+        let (_store, tree, root) = sample_tree().await;
+        let bundle = export(&tree, &root, false).await;
+        let header_len = encode_header(std::slice::from_ref(&root), 0).unwrap().len();
+
+        // Corrupt a byte of the first record's data
+        let mut corrupt = bundle.clone();
+        corrupt[header_len + 4 + 32] ^= 0xff;
+        let result = import_bundle(&MemoryStore::new(), Cursor::new(&corrupt)).await;
+        assert!(matches!(
+            result,
+            Err(BundleError::Store(StoreError::HashMismatch { .. }))
+        ));
+
+        // Drop the last record: the end marker follows the second-to-last one
+        let mut records = Vec::new();
+        let mut pos = header_len;
+        loop {
+            let len = u32::from_le_bytes(bundle[pos..pos + 4].try_into().unwrap());
+            if len == END_MARKER {
+                break;
+            }
+            records.push(pos);
+            pos += 4 + 32 + len as usize;
+        }
+        let mut incomplete = bundle[..*records.last().unwrap()].to_vec();
+        incomplete.extend(END_MARKER.to_le_bytes());
+        let result = import_bundle(&MemoryStore::new(), Cursor::new(&incomplete)).await;
+        assert!(matches!(result, Err(BundleError::MissingBlock(_))));
+
+        let truncated = &bundle[..bundle.len() - 10];
+        let result = import_bundle(&MemoryStore::new(), Cursor::new(truncated)).await;
+        assert!(matches!(result, Err(BundleError::InvalidFormat(_))));
+
+        let result = import_bundle(&MemoryStore::new(), Cursor::new(b"not a bundle")).await;
+        assert!(matches!(result, Err(BundleError::InvalidFormat(_))));
+    }
+
+    #[tokio::test]
+    async fn test_indexed_random_access() {
+        let (store, tree, root) = sample_tree().await;
+        let bundle = export(&tree, &root, true).await;
+
+        // Indexed bundles still import with a forward read
+        let target = MemoryStore::new();
+        import_bundle(&target, Cursor::new(&bundle)).await.unwrap();
+
+        let mut reader = BundleReader::open(Cursor::new(&bundle)).unwrap();
+        assert_eq!(reader.roots(), std::slice::from_ref(&root));
+        let hashes = store.list().await.unwrap();
+        assert_eq!(reader.block_count(), hashes.len());
+        for hash in &hashes {
+            assert_eq!(reader.get(hash).unwrap(), store.get(hash).await.unwrap());
+        }
+        assert_eq!(reader.get(&sha256(b"absent")).unwrap(), None);
+
+        let plain = export(&tree, &root, false).await;
+        assert!(matches!(
+            BundleReader::open(Cursor::new(&plain)),
+            Err(BundleError::InvalidFormat(_))
+        ));
+    }
+}
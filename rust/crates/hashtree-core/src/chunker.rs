@@ -0,0 +1,282 @@
+//! Chunking strategies for splitting file content into leaf chunks
+//!
+//! - `Fixed` splits at every `chunk_size` bytes (default, matches hashtree-ts)
+//! - `ContentDefined` uses FastCDC-style gear hashing with normalized chunking,
+//!   so boundaries follow the content and an insert or delete only changes the
+//!   chunks around the edit
+//!
+//! The gear table and cut-point rules are fully specified here so that other
+//! implementations can reproduce the same boundaries (see test-vectors).
+
+/// Default minimum content-defined chunk size: 256KB
+pub const DEFAULT_CDC_MIN_SIZE: usize = 256 * 1024;
+
+/// Default average content-defined chunk size: 1MB
+pub const DEFAULT_CDC_AVG_SIZE: usize = 1024 * 1024;
+
+/// Default maximum content-defined chunk size: 2MB (never exceeds DEFAULT_CHUNK_SIZE)
+pub const DEFAULT_CDC_MAX_SIZE: usize = 2 * 1024 * 1024;
+
+/// Gear table: 256 values from splitmix64 seeded with 0
+const GEAR: [u64; 256] = gear_table();
+
+const fn gear_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut state: u64 = 0;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+}
+
+/// Mask selecting the top `bits` bits of the rolling hash
+fn high_mask(bits: u32) -> u64 {
+    match bits {
+        0 => 0,
+        b if b >= 64 => u64::MAX,
+        b => u64::MAX << (64 - b),
+    }
+}
+
+/// Content-defined chunking parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CdcParams {
+    /// No chunk (except the last) is shorter than this
+    pub min_size: usize,
+    /// Target average chunk size
+    pub avg_size: usize,
+    /// Chunks are cut here if no content boundary was found
+    pub max_size: usize,
+}
+
+impl CdcParams {
+    /// Create parameters; requires `0 < min_size <= avg_size <= max_size`
+    pub fn new(min_size: usize, avg_size: usize, max_size: usize) -> Self {
+        assert!(
+            min_size > 0 && min_size <= avg_size && avg_size <= max_size,
+            "CDC sizes must satisfy 0 < min <= avg <= max"
+        );
+        Self {
+            min_size,
+            avg_size,
+            max_size,
+        }
+    }
+
+    /// Parameters around an average size, with min = avg/4 and max = avg*2
+    pub fn with_avg_size(avg_size: usize) -> Self {
+        Self::new((avg_size / 4).max(1), avg_size, avg_size * 2)
+    }
+
+    /// Length of the first chunk in `data`
+    ///
+    /// Scans from `min_size` with a gear hash (`fp = (fp << 1) + GEAR[byte]`).
+    /// Before `avg_size` a cut needs the top `log2(avg) + 2` bits to be zero,
+    /// after it the top `log2(avg) - 2` bits, which keeps sizes close to average.
+    pub fn cut_point(&self, data: &[u8]) -> usize {
+        let len = data.len();
+        if len <= self.min_size {
+            return len;
+        }
+
+        let end = len.min(self.max_size);
+        let normal = self.avg_size.min(end);
+        let bits = self.avg_size.ilog2();
+        let mask_strict = high_mask(bits + 2);
+        let mask_loose = high_mask(bits.saturating_sub(2));
+
+        let mut fp: u64 = 0;
+        let mut i = self.min_size;
+        while i < normal {
+            fp = (fp << 1).wrapping_add(GEAR[data[i] as usize]);
+            if fp & mask_strict == 0 {
+                return i + 1;
+            }
+            i += 1;
+        }
+        while i < end {
+            fp = (fp << 1).wrapping_add(GEAR[data[i] as usize]);
+            if fp & mask_loose == 0 {
+                return i + 1;
+            }
+            i += 1;
+        }
+        end
+    }
+}
+
+impl Default for CdcParams {
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_CDC_MIN_SIZE,
+            DEFAULT_CDC_AVG_SIZE,
+            DEFAULT_CDC_MAX_SIZE,
+        )
+    }
+}
+
+/// How file content is split into leaf chunks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChunkingMode {
+    /// Split every `chunk_size` bytes
+    #[default]
+    Fixed,
+    /// Split at content-defined boundaries
+    ContentDefined(CdcParams),
+}
+
+impl ChunkingMode {
+    /// Largest chunk this mode produces for the given fixed chunk size
+    pub fn max_chunk_size(&self, chunk_size: usize) -> usize {
+        match self {
+            ChunkingMode::Fixed => chunk_size,
+            ChunkingMode::ContentDefined(params) => params.max_size,
+        }
+    }
+
+    /// Length of the next chunk at the start of `data`
+    ///
+    /// Returns None when more input is needed to place the boundary. With
+    /// `eof` set, all remaining data is final and a length is always returned
+    /// for non-empty input.
+    pub fn next_chunk_len(&self, data: &[u8], chunk_size: usize, eof: bool) -> Option<usize> {
+        if data.is_empty() {
+            return None;
+        }
+        match self {
+            ChunkingMode::Fixed => {
+                if data.len() >= chunk_size {
+                    Some(chunk_size)
+                } else if eof {
+                    Some(data.len())
+                } else {
+                    None
+                }
+            }
+            ChunkingMode::ContentDefined(params) => {
+                if eof || data.len() >= params.max_size {
+                    Some(params.cut_point(data))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// Split complete data into chunks
+    pub fn split<'a>(&self, data: &'a [u8], chunk_size: usize) -> Vec<&'a [u8]> {
+        let mut chunks = Vec::new();
+        let mut offset = 0;
+        while let Some(len) = self.next_chunk_len(&data[offset..], chunk_size, true) {
+            chunks.push(&data[offset..offset + len]);
+            offset += len;
+        }
+        chunks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::sha256;
+    use std::collections::HashSet;
+
+    fn pseudo_random(len: usize) -> Vec<u8> {
+        let mut out = Vec::with_capacity(len);
+        let mut block = sha256(b"cdc");
+        while out.len() < len {
+            out.extend_from_slice(&block);
+            block = sha256(&block);
+        }
+        out.truncate(len);
+        out
+    }
+
+    #[test]
+    fn test_gear_table_first_values() {
+        // splitmix64(0) reference values
+        assert_eq!(GEAR[0], 0xe220_a839_7b1d_cdaf);
+        assert_eq!(GEAR[1], 0x6e78_9e6a_a1b9_65f4);
+    }
+
+    #[test]
+    fn test_fixed_split() {
+        let data = vec![0u8; 250];
+        let sizes: Vec<usize> = ChunkingMode::Fixed
+            .split(&data, 100)
+            .iter()
+            .map(|c| c.len())
+            .collect();
+        assert_eq!(sizes, vec![100, 100, 50]);
+    }
+
+    #[test]
+    fn test_cdc_respects_bounds() {
+        let params = CdcParams::new(64, 256, 1024);
+        let data = pseudo_random(50_000);
+        let chunks = ChunkingMode::ContentDefined(params).split(&data, 0);
+
+        assert_eq!(chunks.iter().map(|c| c.len()).sum::<usize>(), data.len());
+        for chunk in &chunks[..chunks.len() - 1] {
+            assert!(chunk.len() >= params.min_size);
+            assert!(chunk.len() <= params.max_size);
+        }
+        // Should land somewhere near the average, not at the max
+        let avg = data.len() / chunks.len();
+        assert!(
+            avg > params.min_size && avg < params.max_size,
+            "avg {}",
+            avg
+        );
+    }
+
+    #[test]
+    fn test_cdc_uniform_data_cuts_at_max() {
+        let params = CdcParams::new(16, 64, 128);
+        let data = vec![0u8; 1000];
+        let chunks = ChunkingMode::ContentDefined(params).split(&data, 0);
+        // Constant input still yields deterministic, bounded chunks
+        assert!(chunks.iter().all(|c| c.len() <= 128));
+        assert_eq!(chunks.iter().map(|c| c.len()).sum::<usize>(), 1000);
+    }
+
+    #[test]
+    fn test_cdc_insert_preserves_most_chunks() {
+        let mode = ChunkingMode::ContentDefined(CdcParams::new(64, 256, 1024));
+        let data = pseudo_random(100_000);
+        let mut edited = vec![0x42u8];
+        edited.extend_from_slice(&data);
+
+        let before: HashSet<_> = mode.split(&data, 0).iter().map(|c| sha256(c)).collect();
+        let after: Vec<_> = mode.split(&edited, 0).iter().map(|c| sha256(c)).collect();
+        let changed = after.iter().filter(|h| !before.contains(*h)).count();
+
+        // Boundaries resynchronize within a few chunks of the edit
+        assert!(
+            changed <= 5,
+            "{} of {} chunks changed",
+            changed,
+            after.len()
+        );
+    }
+
+    #[test]
+    fn test_next_chunk_len_waits_for_max() {
+        let params = CdcParams::new(16, 64, 128);
+        let mode = ChunkingMode::ContentDefined(params);
+        let data = pseudo_random(100);
+
+        assert_eq!(mode.next_chunk_len(&data, 0, false), None);
+        assert_eq!(
+            mode.next_chunk_len(&data, 0, true),
+            Some(params.cut_point(&data))
+        );
+        assert_eq!(mode.next_chunk_len(&[], 0, true), None);
+    }
+}
@@ -4,20 +4,24 @@
 //! hashes exist in the new tree but not the old tree. This enables
 //! efficient push operations where only changed content is uploaded.
 //!
+//! `path_diff` compares two directory trees by path instead, reporting
+//! added, removed, modified and renamed entries.
+//!
 //! # Key Optimization: Subtree Pruning
 //!
 //! When a subtree's root hash matches between old and new trees, the entire
 //! subtree is skipped - no need to traverse it since identical hash means
 //! identical content.
 
-use std::collections::HashSet;
+use std::collections::{HashSet, VecDeque};
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 use crate::codec::{decode_tree_node, is_tree_node};
 use crate::crypto::decrypt_chk;
 use crate::hashtree::{HashTree, HashTreeError};
+use crate::reader::TreeEntry;
 use crate::store::Store;
-use crate::types::{Cid, Hash};
+use crate::types::{Cid, Hash, LinkType};
 
 /// Result of a tree diff operation
 #[derive(Debug, Clone)]
@@ -59,15 +63,15 @@ pub struct DiffStats {
     pub unchanged_subtrees: usize,
 }
 
-/// Collect all hashes in a tree using parallel traversal
+/// Collect all hashes in a tree
 ///
-/// This walks the tree and collects all unique hashes (both tree nodes and blobs).
-/// Uses parallel fetching for efficiency.
+/// This walks the tree breadth-first and collects all unique hashes (both tree
+/// nodes and blobs). Nodes are read in sequential `get_many` batches.
 ///
 /// # Arguments
 /// * `tree` - The HashTree instance with store access
 /// * `root` - Root CID to start from
-/// * `concurrency` - Number of concurrent fetches
+/// * `concurrency` - Number of nodes fetched per `get_many` batch
 ///
 /// # Returns
 /// Set of all hashes in the tree
@@ -86,75 +90,37 @@ pub async fn collect_hashes_with_progress<S: Store>(
     concurrency: usize,
     progress: Option<&AtomicUsize>,
 ) -> Result<HashSet<Hash>, HashTreeError> {
-    use futures::stream::{FuturesUnordered, StreamExt};
-    use std::collections::VecDeque;
-
     let store = tree.get_store();
     let mut hashes = HashSet::new();
     let mut pending: VecDeque<(Hash, Option<[u8; 32]>)> = VecDeque::new();
-    let mut active = FuturesUnordered::new();
 
     // Seed with root
     pending.push_back((root.hash, root.key));
 
     loop {
-        // Fill up to concurrency limit
-        while active.len() < concurrency {
-            if let Some((hash, key)) = pending.pop_front() {
-                // Skip if already visited
-                if hashes.contains(&hash) {
-                    continue;
-                }
-                hashes.insert(hash);
-
-                let store = store.clone();
-                let fut = async move {
-                    let data = store
-                        .get(&hash)
-                        .await
-                        .map_err(|e| HashTreeError::Store(e.to_string()))?;
-                    Ok::<_, HashTreeError>((hash, key, data))
-                };
-                active.push(fut);
-            } else {
+        // Take up to `concurrency` unvisited nodes for one batched fetch
+        let mut batch = Vec::new();
+        while batch.len() < concurrency.max(1) {
+            let Some((hash, key)) = pending.pop_front() else {
                 break;
+            };
+            if hashes.insert(hash) {
+                batch.push((hash, key));
             }
         }
 
-        // If nothing active, we're done
-        if active.is_empty() {
+        // If nothing left, we're done
+        if batch.is_empty() {
             break;
         }
 
-        // Wait for any future to complete
-        if let Some(result) = active.next().await {
-            let (_hash, key, data) = result?;
-
-            if let Some(counter) = progress {
-                counter.fetch_add(1, Ordering::Relaxed);
-            }
-
-            let data = match data {
-                Some(d) => d,
-                None => continue,
-            };
-
-            // Decrypt if key present
-            let plaintext = if let Some(k) = key {
-                decrypt_chk(&data, &k).unwrap_or(data)
-            } else {
-                data
-            };
-
-            // If it's a tree node, queue children
-            if is_tree_node(&plaintext) {
-                if let Ok(node) = decode_tree_node(&plaintext) {
-                    for link in node.links {
-                        if !hashes.contains(&link.hash) {
-                            pending.push_back((link.hash, link.key));
-                        }
-                    }
-                }
+        let children = fetch_children(&*store, &batch).await?;
+        if let Some(counter) = progress {
+            counter.fetch_add(batch.len(), Ordering::Relaxed);
+        }
+        for (hash, key) in children.into_iter().flatten() {
+            if !hashes.contains(&hash) {
+                pending.push_back((hash, key));
             }
         }
     }
@@ -177,7 +143,7 @@ pub async fn collect_hashes_with_progress<S: Store>(
 /// * `tree` - HashTree instance
 /// * `old_root` - Root of the old tree (may be None for first push)
 /// * `new_root` - Root of the new tree
-/// * `concurrency` - Number of concurrent fetches
+/// * `concurrency` - Number of nodes fetched per `get_many` batch
 ///
 /// # Returns
 /// TreeDiff with added hashes and statistics
@@ -206,14 +172,10 @@ pub async fn tree_diff_with_old_hashes<S: Store>(
     new_root: &Cid,
     concurrency: usize,
 ) -> Result<TreeDiff, HashTreeError> {
-    use futures::stream::{FuturesUnordered, StreamExt};
-    use std::collections::VecDeque;
-
     let store = tree.get_store();
     let mut added: Vec<Hash> = Vec::new();
     let mut visited: HashSet<Hash> = HashSet::new();
     let mut pending: VecDeque<(Hash, Option<[u8; 32]>)> = VecDeque::new();
-    let mut active = FuturesUnordered::new();
 
     let mut stats = DiffStats {
         old_tree_nodes: old_hashes.len(),
@@ -225,70 +187,37 @@ pub async fn tree_diff_with_old_hashes<S: Store>(
     pending.push_back((new_root.hash, new_root.key));
 
     loop {
-        // Fill up to concurrency limit
-        while active.len() < concurrency {
-            if let Some((hash, key)) = pending.pop_front() {
-                // Skip if already visited
-                if visited.contains(&hash) {
-                    continue;
-                }
-                visited.insert(hash);
-
-                // KEY OPTIMIZATION: If hash exists in old tree, skip entire subtree
-                if old_hashes.contains(&hash) {
-                    stats.unchanged_subtrees += 1;
-                    continue;
-                }
-
-                // Hash is new - will need to upload
-                added.push(hash);
-                stats.new_tree_nodes += 1;
-
-                // Fetch to check for children
-                let store = store.clone();
-                let fut = async move {
-                    let data = store
-                        .get(&hash)
-                        .await
-                        .map_err(|e| HashTreeError::Store(e.to_string()))?;
-                    Ok::<_, HashTreeError>((hash, key, data))
-                };
-                active.push(fut);
-            } else {
+        let mut batch = Vec::new();
+        while batch.len() < concurrency.max(1) {
+            let Some((hash, key)) = pending.pop_front() else {
                 break;
+            };
+            // Skip if already visited
+            if !visited.insert(hash) {
+                continue;
             }
+
+            // KEY OPTIMIZATION: If hash exists in old tree, skip entire subtree
+            if old_hashes.contains(&hash) {
+                stats.unchanged_subtrees += 1;
+                continue;
+            }
+
+            // Hash is new - will need to upload
+            added.push(hash);
+            stats.new_tree_nodes += 1;
+            batch.push((hash, key));
         }
 
-        // If nothing active, we're done
-        if active.is_empty() {
+        // If nothing left, we're done
+        if batch.is_empty() {
             break;
         }
 
-        // Wait for any future to complete
-        if let Some(result) = active.next().await {
-            let (_hash, key, data) = result?;
-
-            let data = match data {
-                Some(d) => d,
-                None => continue,
-            };
-
-            // Decrypt if key present
-            let plaintext = if let Some(k) = key {
-                decrypt_chk(&data, &k).unwrap_or(data)
-            } else {
-                data
-            };
-
-            // If it's a tree node, queue children
-            if is_tree_node(&plaintext) {
-                if let Ok(node) = decode_tree_node(&plaintext) {
-                    for link in node.links {
-                        if !visited.contains(&link.hash) {
-                            pending.push_back((link.hash, link.key));
-                        }
-                    }
-                }
+        // Fetch the batch to check for children
+        for (hash, key) in fetch_children(&*store, &batch).await?.into_iter().flatten() {
+            if !visited.contains(&hash) {
+                pending.push_back((hash, key));
             }
         }
     }
@@ -311,13 +240,9 @@ where
     S: Store,
     F: FnMut(Hash) -> bool, // return false to stop early
 {
-    use futures::stream::{FuturesUnordered, StreamExt};
-    use std::collections::VecDeque;
-
     let store = tree.get_store();
     let mut visited: HashSet<Hash> = HashSet::new();
     let mut pending: VecDeque<(Hash, Option<[u8; 32]>)> = VecDeque::new();
-    let mut active = FuturesUnordered::new();
 
     let mut stats = DiffStats {
         old_tree_nodes: old_hashes.len(),
@@ -328,73 +253,360 @@ where
     pending.push_back((new_root.hash, new_root.key));
 
     loop {
-        while active.len() < concurrency {
-            if let Some((hash, key)) = pending.pop_front() {
-                if visited.contains(&hash) {
-                    continue;
-                }
-                visited.insert(hash);
-
-                if old_hashes.contains(&hash) {
-                    stats.unchanged_subtrees += 1;
-                    continue;
-                }
+        let mut batch = Vec::new();
+        while batch.len() < concurrency.max(1) {
+            let Some((hash, key)) = pending.pop_front() else {
+                break;
+            };
+            if !visited.insert(hash) {
+                continue;
+            }
 
-                stats.new_tree_nodes += 1;
+            if old_hashes.contains(&hash) {
+                stats.unchanged_subtrees += 1;
+                continue;
+            }
 
-                // Yield this hash via callback
-                if !callback(hash) {
-                    // Early termination requested
-                    return Ok(stats);
-                }
+            stats.new_tree_nodes += 1;
 
-                let store = store.clone();
-                let fut = async move {
-                    let data = store
-                        .get(&hash)
-                        .await
-                        .map_err(|e| HashTreeError::Store(e.to_string()))?;
-                    Ok::<_, HashTreeError>((hash, key, data))
-                };
-                active.push(fut);
-            } else {
-                break;
+            // Yield this hash via callback
+            if !callback(hash) {
+                // Early termination requested
+                return Ok(stats);
             }
+
+            batch.push((hash, key));
         }
 
-        if active.is_empty() {
+        if batch.is_empty() {
             break;
         }
 
-        if let Some(result) = active.next().await {
-            let (_hash, key, data) = result?;
+        for (hash, key) in fetch_children(&*store, &batch).await?.into_iter().flatten() {
+            if !visited.contains(&hash) {
+                pending.push_back((hash, key));
+            }
+        }
+    }
+
+    Ok(stats)
+}
 
-            let data = match data {
-                Some(d) => d,
-                None => continue,
+/// Fetch a batch of nodes with one `get_many` call
+///
+/// Returns the child links of each node, in batch order. Blobs and missing
+/// nodes have no children.
+async fn fetch_children<S: Store>(
+    store: &S,
+    batch: &[(Hash, Option<[u8; 32]>)],
+) -> Result<Vec<Vec<(Hash, Option<[u8; 32]>)>>, HashTreeError> {
+    let hashes: Vec<Hash> = batch.iter().map(|(hash, _)| *hash).collect();
+    let data = store
+        .get_many(&hashes)
+        .await
+        .map_err(|e| HashTreeError::Store(e.to_string()))?;
+
+    Ok(batch
+        .iter()
+        .zip(data)
+        .map(|((_, key), data)| {
+            let Some(data) = data else {
+                return Vec::new();
             };
 
+            // Decrypt if key present
             let plaintext = if let Some(k) = key {
-                decrypt_chk(&data, &k).unwrap_or(data)
+                decrypt_chk(&data, k).unwrap_or(data)
             } else {
                 data
             };
 
-            if is_tree_node(&plaintext) {
-                if let Ok(node) = decode_tree_node(&plaintext) {
-                    for link in node.links {
-                        if !visited.contains(&link.hash) {
-                            pending.push_back((link.hash, link.key));
+            // If it's a tree node, return children
+            if !is_tree_node(&plaintext) {
+                return Vec::new();
+            }
+            decode_tree_node(&plaintext)
+                .map(|node| {
+                    node.links
+                        .into_iter()
+                        .map(|link| (link.hash, link.key))
+                        .collect()
+                })
+                .unwrap_or_default()
+        })
+        .collect())
+}
+
+/// A change between two directory trees, by path
+///
+/// Paths are relative to the tree roots and `/`-separated. Added and removed
+/// directories are reported per file (an empty directory by its own path),
+/// except when the whole subtree moved, which is a single `Renamed`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathChange {
+    /// Path exists only in the new tree
+    Added(String),
+    /// Path exists only in the old tree
+    Removed(String),
+    /// File at the same path with different content (old, new)
+    Modified(String, Cid, Cid),
+    /// Same content moved from the first path to the second
+    Renamed(String, String),
+}
+
+impl PathChange {
+    /// Path in the new tree, or the old path for removals
+    pub fn path(&self) -> &str {
+        match self {
+            PathChange::Added(path) | PathChange::Removed(path) => path,
+            PathChange::Modified(path, _, _) => path,
+            PathChange::Renamed(_, to) => to,
+        }
+    }
+}
+
+/// Entry that exists on one side only, kept until renames are matched
+struct Unmatched {
+    path: String,
+    cid: Cid,
+    is_dir: bool,
+}
+
+/// Compute the path-level changes from `old_root` to `new_root`
+pub async fn path_diff<S: Store>(
+    tree: &HashTree<S>,
+    old_root: &Cid,
+    new_root: &Cid,
+) -> Result<Vec<PathChange>, HashTreeError> {
+    let mut changes = Vec::new();
+    path_diff_streaming(tree, old_root, new_root, |change| {
+        changes.push(change);
+        true
+    })
+    .await?;
+    Ok(changes)
+}
+
+/// Path-level diff that yields changes as they're found
+///
+/// Directories with the same hash on both sides are skipped without being
+/// read. Modifications are yielded during the walk; additions, removals and
+/// renames once both trees have been compared, since a rename pairs a removed
+/// entry with an added one anywhere in the tree.
+pub async fn path_diff_streaming<S, F>(
+    tree: &HashTree<S>,
+    old_root: &Cid,
+    new_root: &Cid,
+    mut callback: F,
+) -> Result<DiffStats, HashTreeError>
+where
+    S: Store,
+    F: FnMut(PathChange) -> bool, // return false to stop early
+{
+    use std::collections::{BTreeMap, HashMap};
+
+    let mut stats = DiffStats::default();
+    if old_root.hash == new_root.hash {
+        stats.unchanged_subtrees += 1;
+        return Ok(stats);
+    }
+    if !is_dir_node(tree, old_root).await? || !is_dir_node(tree, new_root).await? {
+        callback(PathChange::Modified(
+            String::new(),
+            old_root.clone(),
+            new_root.clone(),
+        ));
+        return Ok(stats);
+    }
+
+    let mut removed: Vec<Unmatched> = Vec::new();
+    let mut added: Vec<Unmatched> = Vec::new();
+    let mut stack = vec![(String::new(), old_root.clone(), new_root.clone())];
+
+    while let Some((prefix, old_dir, new_dir)) = stack.pop() {
+        let old_entries = tree.list_directory(&old_dir).await?;
+        let new_entries = tree.list_directory(&new_dir).await?;
+        stats.old_tree_nodes += 1;
+        stats.new_tree_nodes += 1;
+
+        let mut names: BTreeMap<String, (Option<TreeEntry>, Option<TreeEntry>)> = BTreeMap::new();
+        for entry in old_entries {
+            let name = entry.name.clone();
+            names.entry(name).or_default().0 = Some(entry);
+        }
+        for entry in new_entries {
+            let name = entry.name.clone();
+            names.entry(name).or_default().1 = Some(entry);
+        }
+
+        let mut subdirs = Vec::new();
+        for (name, (old, new)) in names {
+            let path = join_path(&prefix, &name);
+            match (old, new) {
+                (Some(old), Some(new)) if old.hash == new.hash => {
+                    stats.unchanged_subtrees += 1;
+                }
+                (Some(old), Some(new)) => {
+                    let old_is_dir = entry_is_dir(tree, &old).await?;
+                    let new_is_dir = entry_is_dir(tree, &new).await?;
+                    match (old_is_dir, new_is_dir) {
+                        (true, true) => subdirs.push((path, entry_cid(&old), entry_cid(&new))),
+                        (false, false) => {
+                            if !callback(PathChange::Modified(
+                                path,
+                                entry_cid(&old),
+                                entry_cid(&new),
+                            )) {
+                                return Ok(stats);
+                            }
+                        }
+                        // File replaced by a directory or vice versa
+                        _ => {
+                            removed.push(Unmatched::new(path.clone(), &old, old_is_dir));
+                            added.push(Unmatched::new(path, &new, new_is_dir));
                         }
                     }
                 }
+                (Some(old), None) => {
+                    let is_dir = entry_is_dir(tree, &old).await?;
+                    removed.push(Unmatched::new(path, &old, is_dir));
+                }
+                (None, Some(new)) => {
+                    let is_dir = entry_is_dir(tree, &new).await?;
+                    added.push(Unmatched::new(path, &new, is_dir));
+                }
+                (None, None) => {}
+            }
+        }
+
+        // Reverse so subdirectories are walked in name order
+        stack.extend(subdirs.into_iter().rev());
+    }
+
+    // Pair removed and added entries with identical content
+    let mut by_hash: HashMap<(Hash, bool), Vec<usize>> = HashMap::new();
+    for (i, entry) in removed.iter().enumerate().rev() {
+        by_hash
+            .entry((entry.cid.hash, entry.is_dir))
+            .or_default()
+            .push(i);
+    }
+    let mut renamed = vec![false; removed.len()];
+    let mut remaining_added = Vec::new();
+    for entry in added {
+        let matched = by_hash
+            .get_mut(&(entry.cid.hash, entry.is_dir))
+            .and_then(|candidates| candidates.pop());
+        match matched {
+            Some(i) => {
+                renamed[i] = true;
+                let from = removed[i].path.clone();
+                if !callback(PathChange::Renamed(from, entry.path)) {
+                    return Ok(stats);
+                }
             }
+            None => remaining_added.push(entry),
+        }
+    }
+
+    for (entry, renamed) in removed.into_iter().zip(renamed) {
+        if renamed {
+            continue;
+        }
+        if !expand_entry(tree, entry, &mut |path| callback(PathChange::Removed(path))).await? {
+            return Ok(stats);
+        }
+    }
+    for entry in remaining_added {
+        if !expand_entry(tree, entry, &mut |path| callback(PathChange::Added(path))).await? {
+            return Ok(stats);
         }
     }
 
     Ok(stats)
 }
 
+impl Unmatched {
+    fn new(path: String, entry: &TreeEntry, is_dir: bool) -> Self {
+        Self {
+            path,
+            cid: entry_cid(entry),
+            is_dir,
+        }
+    }
+}
+
+/// Directory check by node type, so empty directories count too
+async fn is_dir_node<S: Store>(tree: &HashTree<S>, cid: &Cid) -> Result<bool, HashTreeError> {
+    Ok(tree
+        .get_node(cid)
+        .await?
+        .is_some_and(|node| node.node_type == LinkType::Dir))
+}
+
+/// Whether a directory entry is a subdirectory. Blob links don't say (e.g.
+/// entries added with `DirEntry::from_cid`), so those are decoded to check.
+pub async fn entry_is_dir<S: Store>(
+    tree: &HashTree<S>,
+    entry: &TreeEntry,
+) -> Result<bool, HashTreeError> {
+    match entry.link_type {
+        LinkType::Dir => Ok(true),
+        LinkType::File => Ok(false),
+        LinkType::Blob => is_dir_node(tree, &entry_cid(entry)).await,
+    }
+}
+
+fn entry_cid(entry: &TreeEntry) -> Cid {
+    Cid {
+        hash: entry.hash,
+        key: entry.key,
+    }
+}
+
+fn join_path(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", prefix, name)
+    }
+}
+
+/// Report every file under an entry (or the entry itself if it's a file or
+/// an empty directory). Returns false if the callback asked to stop.
+async fn expand_entry<S, F>(
+    tree: &HashTree<S>,
+    entry: Unmatched,
+    emit: &mut F,
+) -> Result<bool, HashTreeError>
+where
+    S: Store,
+    F: FnMut(String) -> bool,
+{
+    if !entry.is_dir {
+        return Ok(emit(entry.path));
+    }
+
+    let mut stack = vec![(entry.path, entry.cid)];
+    while let Some((path, cid)) = stack.pop() {
+        let entries = tree.list_directory(&cid).await?;
+        if entries.is_empty() && !emit(path.clone()) {
+            return Ok(false);
+        }
+        let mut subdirs = Vec::new();
+        for child in entries {
+            let child_path = join_path(&path, &child.name);
+            if entry_is_dir(tree, &child).await? {
+                subdirs.push((child_path, entry_cid(&child)));
+            } else if !emit(child_path) {
+                return Ok(false);
+            }
+        }
+        stack.extend(subdirs.into_iter().rev());
+    }
+    Ok(true)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -735,4 +947,183 @@ mod tests {
         // Should have skipped ~95 unchanged files
         assert!(diff.stats.unchanged_subtrees >= 95);
     }
+
+    async fn dir(tree: &HashTree<MemoryStore>, entries: Vec<DirEntry>) -> Cid {
+        tree.put_directory(entries).await.unwrap()
+    }
+
+    async fn file(tree: &HashTree<MemoryStore>, name: &str, data: &[u8]) -> DirEntry {
+        let hash = tree.put_blob(data).await.unwrap();
+        DirEntry::new(name, hash).with_size(data.len() as u64)
+    }
+
+    fn subdir(name: &str, cid: &Cid) -> DirEntry {
+        DirEntry::from_cid(name, cid).with_link_type(LinkType::Dir)
+    }
+
+    #[tokio::test]
+    async fn test_path_diff_changes() {
+        let (_store, tree) = make_tree();
+
+        let docs = dir(&tree, vec![file(&tree, "a.md", b"a").await]).await;
+        let shared = dir(&tree, vec![file(&tree, "x", b"x").await]).await;
+        let old_root = dir(
+            &tree,
+            vec![
+                file(&tree, "readme", b"v1").await,
+                file(&tree, "old.txt", b"moved").await,
+                file(&tree, "gone", b"gone").await,
+                subdir("docs", &docs),
+                subdir("shared", &shared),
+            ],
+        )
+        .await;
+
+        let new_docs = dir(
+            &tree,
+            vec![
+                file(&tree, "a.md", b"a2").await,
+                file(&tree, "b.md", b"b").await,
+            ],
+        )
+        .await;
+        let empty = dir(&tree, vec![]).await;
+        let new_root = dir(
+            &tree,
+            vec![
+                file(&tree, "readme", b"v2").await,
+                file(&tree, "new.txt", b"moved").await,
+                subdir("docs", &new_docs),
+                subdir("shared", &shared),
+                subdir("empty", &empty),
+            ],
+        )
+        .await;
+
+        let changes = path_diff(&tree, &old_root, &new_root).await.unwrap();
+        let readme_old = tree
+            .resolve_path(&old_root, "readme")
+            .await
+            .unwrap()
+            .unwrap();
+        let readme_new = tree
+            .resolve_path(&new_root, "readme")
+            .await
+            .unwrap()
+            .unwrap();
+        let md_old = tree
+            .resolve_path(&old_root, "docs/a.md")
+            .await
+            .unwrap()
+            .unwrap();
+        let md_new = tree
+            .resolve_path(&new_root, "docs/a.md")
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            changes,
+            vec![
+                PathChange::Modified("readme".to_string(), readme_old, readme_new),
+                PathChange::Modified("docs/a.md".to_string(), md_old, md_new),
+                PathChange::Renamed("old.txt".to_string(), "new.txt".to_string()),
+                PathChange::Removed("gone".to_string()),
+                PathChange::Added("empty".to_string()),
+                PathChange::Added("docs/b.md".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_path_diff_prunes_and_renames_directories() {
+        let (_store, tree) = make_tree();
+
+        let big = dir(
+            &tree,
+            vec![
+                file(&tree, "1", b"one").await,
+                file(&tree, "2", b"two").await,
+            ],
+        )
+        .await;
+        let old_root = dir(&tree, vec![subdir("src", &big), subdir("same", &big)]).await;
+        let new_root = dir(&tree, vec![subdir("lib", &big), subdir("same", &big)]).await;
+
+        let mut changes = Vec::new();
+        let stats = path_diff_streaming(&tree, &old_root, &new_root, |change| {
+            changes.push(change);
+            true
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(
+            changes,
+            vec![PathChange::Renamed("src".to_string(), "lib".to_string())]
+        );
+        // Only the two roots were listed; "same" was skipped by hash
+        assert_eq!(stats.new_tree_nodes, 1);
+        assert_eq!(stats.unchanged_subtrees, 1);
+
+        let identical = path_diff(&tree, &old_root, &old_root).await.unwrap();
+        assert!(identical.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_path_diff_expands_added_directory() {
+        let (_store, tree) = make_tree();
+
+        let nested = dir(&tree, vec![file(&tree, "deep", b"deep").await]).await;
+        let added = dir(
+            &tree,
+            vec![file(&tree, "top", b"top").await, subdir("nested", &nested)],
+        )
+        .await;
+        let old_root = dir(&tree, vec![]).await;
+        let new_root = dir(&tree, vec![subdir("pkg", &added)]).await;
+
+        let changes = path_diff(&tree, &old_root, &new_root).await.unwrap();
+        let paths: Vec<&str> = changes.iter().map(|c| c.path()).collect();
+        assert_eq!(paths, vec!["pkg/top", "pkg/nested/deep"]);
+        assert!(changes.iter().all(|c| matches!(c, PathChange::Added(_))));
+    }
+
+    #[tokio::test]
+    async fn test_path_diff_descends_into_blob_linked_directories() {
+        let (_store, tree) = make_tree();
+
+        // Subdirectories linked without a link type, as `from_cid` leaves them
+        let nested = dir(&tree, vec![file(&tree, "deep", b"deep").await]).await;
+        let old_sub = dir(&tree, vec![file(&tree, "a", b"a1").await]).await;
+        let new_sub = dir(
+            &tree,
+            vec![
+                file(&tree, "a", b"a2").await,
+                DirEntry::from_cid("nested", &nested),
+            ],
+        )
+        .await;
+        let old_root = dir(&tree, vec![DirEntry::from_cid("sub", &old_sub)]).await;
+        let new_root = dir(&tree, vec![DirEntry::from_cid("sub", &new_sub)]).await;
+
+        let changes = path_diff(&tree, &old_root, &new_root).await.unwrap();
+        let a_old = tree
+            .resolve_path(&old_root, "sub/a")
+            .await
+            .unwrap()
+            .unwrap();
+        let a_new = tree
+            .resolve_path(&new_root, "sub/a")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            changes,
+            vec![
+                PathChange::Modified("sub/a".to_string(), a_old, a_new),
+                PathChange::Added("sub/nested/deep".to_string()),
+            ]
+        );
+    }
 }
@@ -3,6 +3,7 @@
 //! Single struct for creating, reading, and editing content-addressed merkle trees.
 //! Mirrors the hashtree-ts HashTree class API.
 
+use std::collections::HashMap;
 use std::pin::Pin;
 use std::sync::Arc;
 
@@ -11,6 +12,7 @@ use futures::stream::{self, Stream};
 use futures::AsyncReadExt;
 
 use crate::builder::{BuilderError, DEFAULT_CHUNK_SIZE, DEFAULT_MAX_LINKS};
+use crate::chunker::ChunkingMode;
 use crate::codec::{
     decode_tree_node, encode_and_hash, is_directory_node, is_tree_node, try_decode_tree_node,
 };
@@ -29,6 +31,8 @@ pub struct HashTreeConfig<S: Store> {
     pub max_links: usize,
     /// Whether to encrypt content (default: true when encryption feature enabled)
     pub encrypted: bool,
+    /// How file content is split into chunks (default: fixed `chunk_size`)
+    pub chunking: ChunkingMode,
 }
 
 impl<S: Store> HashTreeConfig<S> {
@@ -38,6 +42,7 @@ impl<S: Store> HashTreeConfig<S> {
             chunk_size: DEFAULT_CHUNK_SIZE,
             max_links: DEFAULT_MAX_LINKS,
             encrypted: true,
+            chunking: ChunkingMode::Fixed,
         }
     }
 
@@ -46,6 +51,12 @@ impl<S: Store> HashTreeConfig<S> {
         self
     }
 
+    /// Set the chunking mode (e.g. content-defined chunking)
+    pub fn with_chunking(mut self, chunking: ChunkingMode) -> Self {
+        self.chunking = chunking;
+        self
+    }
+
     pub fn with_max_links(mut self, max_links: usize) -> Self {
         self.max_links = max_links;
         self
@@ -77,6 +88,8 @@ pub enum HashTreeError {
     Decryption(String),
     #[error("Content size {actual_size} exceeds max_size {max_size}")]
     SizeLimitExceeded { max_size: u64, actual_size: u64 },
+    #[error("Invalid archive: {0}")]
+    InvalidArchive(String),
 }
 
 impl From<BuilderError> for HashTreeError {
@@ -109,6 +122,7 @@ pub struct HashTree<S: Store> {
     chunk_size: usize,
     max_links: usize,
     encrypted: bool,
+    chunking: ChunkingMode,
 }
 
 impl<S: Store> HashTree<S> {
@@ -118,6 +132,7 @@ impl<S: Store> HashTree<S> {
             chunk_size: config.chunk_size,
             max_links: config.max_links,
             encrypted: config.encrypted,
+            chunking: config.chunking,
         }
     }
 
@@ -132,20 +147,18 @@ impl<S: Store> HashTree<S> {
     /// Encrypts by default when encryption feature is enabled
     pub async fn put(&self, data: &[u8]) -> Result<(Cid, u64), HashTreeError> {
         let size = data.len() as u64;
+        let chunks = self.chunking.split(data, self.chunk_size);
 
         // Small data - store as single chunk
-        if data.len() <= self.chunk_size {
+        if chunks.len() <= 1 {
             let (hash, key) = self.put_chunk_internal(data).await?;
             return Ok((Cid { hash, key }, size));
         }
 
         // Large data - chunk it
         let mut links: Vec<Link> = Vec::new();
-        let mut offset = 0;
 
-        while offset < data.len() {
-            let end = (offset + self.chunk_size).min(data.len());
-            let chunk = &data[offset..end];
+        for chunk in chunks {
             let chunk_size = chunk.len() as u64;
             let (hash, key) = self.put_chunk_internal(chunk).await?;
             links.push(Link {
@@ -156,7 +169,6 @@ impl<S: Store> HashTree<S> {
                 link_type: LinkType::Blob, // Leaf chunk (raw blob)
                 meta: None,
             });
-            offset = end;
         }
 
         // Build tree from chunks
@@ -195,31 +207,33 @@ impl<S: Store> HashTree<S> {
         &self,
         mut reader: R,
     ) -> Result<(Cid, u64), HashTreeError> {
-        let mut buffer = vec![0u8; self.chunk_size];
+        let max_chunk_size = self.chunking.max_chunk_size(self.chunk_size);
+        let mut buffer = vec![0u8; max_chunk_size];
+        let mut pending: Vec<u8> = Vec::with_capacity(max_chunk_size);
+        let mut eof = false;
         let mut links = Vec::new();
         let mut total_size: u64 = 0;
         let mut consistent_key: Option<[u8; 32]> = None;
 
         loop {
-            let mut chunk = Vec::new();
-            let mut bytes_read = 0;
-
-            // Read until we have a full chunk or EOF
-            while bytes_read < self.chunk_size {
+            // Read until we have a full max-size chunk or EOF
+            while !eof && pending.len() < max_chunk_size {
                 let n = reader
-                    .read(&mut buffer[..self.chunk_size - bytes_read])
+                    .read(&mut buffer[..max_chunk_size - pending.len()])
                     .await
                     .map_err(|e| HashTreeError::Store(format!("read error: {}", e)))?;
                 if n == 0 {
-                    break; // EOF
+                    eof = true;
+                    break;
                 }
-                chunk.extend_from_slice(&buffer[..n]);
-                bytes_read += n;
+                pending.extend_from_slice(&buffer[..n]);
             }
 
-            if chunk.is_empty() {
-                break; // No more data
-            }
+            let len = match self.chunking.next_chunk_len(&pending, self.chunk_size, eof) {
+                Some(len) => len,
+                None => break, // No more data
+            };
+            let chunk: Vec<u8> = pending.drain(..len).collect();
 
             let chunk_len = chunk.len() as u64;
             total_size += chunk_len;
@@ -621,20 +635,19 @@ impl<S: Store> HashTree<S> {
     pub async fn put_file(&self, data: &[u8]) -> Result<(Cid, u64), HashTreeError> {
         let size = data.len() as u64;
 
+        let chunks = self.chunking.split(data, self.chunk_size);
+
         // Small file - store as single chunk
-        if data.len() <= self.chunk_size {
+        if chunks.len() <= 1 {
             let (hash, key) = self.put_chunk_internal(data).await?;
             return Ok((Cid { hash, key }, size));
         }
 
         // Large file - chunk it
         let mut links: Vec<Link> = Vec::new();
-        let mut offset = 0;
 
-        while offset < data.len() {
-            let end = (offset + self.chunk_size).min(data.len());
-            let chunk = &data[offset..end];
-            let chunk_size = (end - offset) as u64;
+        for chunk in chunks {
+            let chunk_size = chunk.len() as u64;
 
             let (hash, key) = self.put_chunk_internal(chunk).await?;
             links.push(Link {
@@ -645,7 +658,6 @@ impl<S: Store> HashTree<S> {
                 link_type: LinkType::Blob, // Leaf chunk
                 meta: None,
             });
-            offset = end;
         }
 
         // Build tree from chunks (uses encryption if enabled)
@@ -659,6 +671,90 @@ impl<S: Store> HashTree<S> {
         ))
     }
 
+    /// Store a single leaf chunk (encrypted if enabled) and return its link
+    pub async fn put_chunk(&self, data: &[u8]) -> Result<Link, HashTreeError> {
+        let (hash, key) = self.put_chunk_internal(data).await?;
+        Ok(Link {
+            hash,
+            name: None,
+            size: data.len() as u64,
+            key,
+            link_type: LinkType::Blob,
+            meta: None,
+        })
+    }
+
+    /// Build a file from already stored leaf chunks
+    ///
+    /// Produces the same Cid as `put()` of the concatenated chunk data when the
+    /// chunks follow this tree's chunking, so unchanged chunks can be reused
+    /// without being read or rewritten.
+    pub async fn put_file_from_chunks(
+        &self,
+        chunks: Vec<Link>,
+    ) -> Result<(Cid, u64), HashTreeError> {
+        if chunks.is_empty() {
+            return self.put(&[]).await;
+        }
+
+        let size: u64 = chunks.iter().map(|l| l.size).sum();
+        let (hash, key) = self.build_tree_internal(chunks, Some(size)).await?;
+        Ok((Cid { hash, key }, size))
+    }
+
+    /// List the leaf chunks of a file in order (handles decryption if key present)
+    pub async fn file_chunks(&self, cid: &Cid) -> Result<Vec<Link>, HashTreeError> {
+        let mut chunks = Vec::new();
+        self.collect_file_chunks(cid, &mut chunks).await?;
+        Ok(chunks)
+    }
+
+    async fn collect_file_chunks(
+        &self,
+        cid: &Cid,
+        chunks: &mut Vec<Link>,
+    ) -> Result<(), HashTreeError> {
+        let data = self
+            .store
+            .get(&cid.hash)
+            .await
+            .map_err(|e| HashTreeError::Store(e.to_string()))?
+            .ok_or_else(|| HashTreeError::MissingChunk(to_hex(&cid.hash)))?;
+        let data = match &cid.key {
+            Some(key) => {
+                decrypt_chk(&data, key).map_err(|e| HashTreeError::Decryption(e.to_string()))?
+            }
+            None => data,
+        };
+
+        if !is_tree_node(&data) {
+            chunks.push(Link {
+                hash: cid.hash,
+                name: None,
+                size: data.len() as u64,
+                key: cid.key,
+                link_type: LinkType::Blob,
+                meta: None,
+            });
+            return Ok(());
+        }
+
+        let node = decode_tree_node(&data)?;
+        for link in node.links {
+            if link.link_type == LinkType::Blob {
+                // Leaf chunk - the link already carries its size
+                chunks.push(link);
+            } else {
+                let child = Cid {
+                    hash: link.hash,
+                    key: link.key,
+                };
+                Box::pin(self.collect_file_chunks(&child, chunks)).await?;
+            }
+        }
+        Ok(())
+    }
+
     /// Build a directory from entries
     /// Returns Cid with key if encrypted
     ///
@@ -1461,7 +1557,7 @@ impl<S: Store> HashTree<S> {
     /// Walk entire tree depth-first (returns Vec)
     pub async fn walk(&self, cid: &Cid, path: &str) -> Result<Vec<WalkEntry>, HashTreeError> {
         let mut entries = Vec::new();
-        self.walk_recursive(cid, path, &mut entries).await?;
+        self.walk_recursive(cid, path, None, &mut entries).await?;
         Ok(entries)
     }
 
@@ -1469,6 +1565,7 @@ impl<S: Store> HashTree<S> {
         &self,
         cid: &Cid,
         path: &str,
+        meta: LinkMeta,
         entries: &mut Vec<WalkEntry>,
     ) -> Result<(), HashTreeError> {
         let data = match self
@@ -1497,6 +1594,7 @@ impl<S: Store> HashTree<S> {
                     link_type: LinkType::Blob,
                     size: data.len() as u64,
                     key: cid.key,
+                    meta,
                 });
                 return Ok(());
             }
@@ -1509,6 +1607,7 @@ impl<S: Store> HashTree<S> {
             link_type: node.node_type,
             size: node_size,
             key: cid.key,
+            meta,
         });
 
         for link in &node.links {
@@ -1520,7 +1619,7 @@ impl<S: Store> HashTree<S> {
                             hash: link.hash,
                             key: cid.key,
                         };
-                        Box::pin(self.walk_recursive(&sub_cid, path, entries)).await?;
+                        Box::pin(self.walk_recursive(&sub_cid, path, None, entries)).await?;
                         continue;
                     }
                     if path.is_empty() {
@@ -1537,14 +1636,15 @@ impl<S: Store> HashTree<S> {
                 hash: link.hash,
                 key: link.key,
             };
-            Box::pin(self.walk_recursive(&child_cid, &child_path, entries)).await?;
+            Box::pin(self.walk_recursive(&child_cid, &child_path, link.meta.clone(), entries))
+                .await?;
         }
 
         Ok(())
     }
 
-    /// Walk entire tree with parallel fetching
-    /// Uses a work-stealing approach: always keeps `concurrency` requests in flight
+    /// Walk entire tree with batched fetching
+    /// Fetches up to `concurrency` nodes per `Store::get_many` call
     pub async fn walk_parallel(
         &self,
         cid: &Cid,
@@ -1568,44 +1668,33 @@ impl<S: Store> HashTree<S> {
         concurrency: usize,
         progress: Option<&std::sync::atomic::AtomicUsize>,
     ) -> Result<Vec<WalkEntry>, HashTreeError> {
-        use futures::stream::{FuturesUnordered, StreamExt};
         use std::collections::VecDeque;
         use std::sync::atomic::Ordering;
 
         let mut entries = Vec::new();
-        let mut pending: VecDeque<(Cid, String)> = VecDeque::new();
-        let mut active = FuturesUnordered::new();
+        let mut pending: VecDeque<(Cid, String, LinkMeta)> = VecDeque::new();
 
         // Seed with root
-        pending.push_back((cid.clone(), path.to_string()));
+        pending.push_back((cid.clone(), path.to_string(), None));
 
         loop {
-            // Fill up to concurrency limit from pending queue
-            while active.len() < concurrency {
-                if let Some((node_cid, node_path)) = pending.pop_front() {
-                    let store = &self.store;
-                    let fut = async move {
-                        let data = store
-                            .get(&node_cid.hash)
-                            .await
-                            .map_err(|e| HashTreeError::Store(e.to_string()))?;
-                        Ok::<_, HashTreeError>((node_cid, node_path, data))
-                    };
-                    active.push(fut);
-                } else {
-                    break;
-                }
-            }
+            // Take up to `concurrency` pending nodes and fetch them in one batch
+            let take = pending.len().min(concurrency.max(1));
+            let batch: Vec<_> = pending.drain(..take).collect();
 
-            // If nothing active, we're done
-            if active.is_empty() {
+            // If nothing pending, we're done
+            if batch.is_empty() {
                 break;
             }
 
-            // Wait for any future to complete
-            if let Some(result) = active.next().await {
-                let (node_cid, node_path, data) = result?;
+            let hashes: Vec<Hash> = batch.iter().map(|(c, _, _)| c.hash).collect();
+            let fetched = self
+                .store
+                .get_many(&hashes)
+                .await
+                .map_err(|e| HashTreeError::Store(e.to_string()))?;
 
+            for ((node_cid, node_path, meta), data) in batch.into_iter().zip(fetched) {
                 // Update progress counter
                 if let Some(counter) = progress {
                     counter.fetch_add(1, Ordering::Relaxed);
@@ -1633,6 +1722,7 @@ impl<S: Store> HashTree<S> {
                             link_type: LinkType::Blob,
                             size: data.len() as u64,
                             key: node_cid.key,
+                            meta,
                         });
                         continue;
                     }
@@ -1646,6 +1736,7 @@ impl<S: Store> HashTree<S> {
                     link_type: node.node_type,
                     size: node_size,
                     key: node_cid.key,
+                    meta,
                 });
 
                 // Queue children - but DON'T fetch blobs, just add them directly
@@ -1658,7 +1749,7 @@ impl<S: Store> HashTree<S> {
                                     hash: link.hash,
                                     key: node_cid.key,
                                 };
-                                pending.push_back((sub_cid, node_path.clone()));
+                                pending.push_back((sub_cid, node_path.clone(), None));
                                 continue;
                             }
                             if node_path.is_empty() {
@@ -1679,6 +1770,7 @@ impl<S: Store> HashTree<S> {
                             link_type: LinkType::Blob,
                             size: link.size,
                             key: link.key,
+                            meta: link.meta.clone(),
                         });
                         if let Some(counter) = progress {
                             counter.fetch_add(1, Ordering::Relaxed);
@@ -1691,7 +1783,7 @@ impl<S: Store> HashTree<S> {
                         hash: link.hash,
                         key: link.key,
                     };
-                    pending.push_back((child_cid, child_path));
+                    pending.push_back((child_cid, child_path, link.meta.clone()));
                 }
             }
         }
@@ -1750,6 +1842,7 @@ impl<S: Store> HashTree<S> {
                                     link_type: LinkType::Blob,
                                     size: data.len() as u64,
                                     key: cid.key,
+                                    meta: None,
                                 };
                                 return Some((Ok(entry), WalkStreamState::Done));
                             }
@@ -1762,6 +1855,7 @@ impl<S: Store> HashTree<S> {
                             link_type: node.node_type,
                             size: node_size,
                             key: cid.key,
+                            meta: None,
                         };
 
                         // Create stack with children to process
@@ -1782,6 +1876,10 @@ impl<S: Store> HashTree<S> {
                                 hash: link.hash,
                                 path: child_path,
                                 key: link.key,
+                                meta: link.meta,
+                                chunk_size: (node.node_type == LinkType::File
+                                    && link.link_type == LinkType::Blob)
+                                    .then_some(link.size),
                             });
                         }
 
@@ -1801,6 +1899,24 @@ impl<S: Store> HashTree<S> {
         stack: &mut Vec<WalkStackItem>,
     ) -> Option<(Result<WalkEntry, HashTreeError>, WalkStreamState<'a, S>)> {
         while let Some(item) = stack.pop() {
+            if let Some(size) = item.chunk_size {
+                let entry = WalkEntry {
+                    path: item.path,
+                    hash: item.hash,
+                    link_type: LinkType::Blob,
+                    size,
+                    key: item.key,
+                    meta: item.meta,
+                };
+                return Some((
+                    Ok(entry),
+                    WalkStreamState::Processing {
+                        stack: std::mem::take(stack),
+                        tree: self,
+                    },
+                ));
+            }
+
             let data = match self.store.get(&item.hash).await {
                 Ok(Some(d)) => d,
                 Ok(None) => continue,
@@ -1812,6 +1928,20 @@ impl<S: Store> HashTree<S> {
                 }
             };
 
+            // Decrypt if key is present
+            let data = match &item.key {
+                Some(key) => match decrypt_chk(&data, key) {
+                    Ok(d) => d,
+                    Err(e) => {
+                        return Some((
+                            Err(HashTreeError::Decryption(e.to_string())),
+                            WalkStreamState::Done,
+                        ))
+                    }
+                },
+                None => data,
+            };
+
             let node = match try_decode_tree_node(&data) {
                 Some(n) => n,
                 None => {
@@ -1822,6 +1952,7 @@ impl<S: Store> HashTree<S> {
                         link_type: LinkType::Blob,
                         size: data.len() as u64,
                         key: item.key,
+                        meta: item.meta,
                     };
                     return Some((
                         Ok(entry),
@@ -1839,7 +1970,8 @@ impl<S: Store> HashTree<S> {
                 hash: item.hash,
                 link_type: node.node_type,
                 size: node_size,
-                key: None, // directories are not encrypted
+                key: item.key,
+                meta: item.meta,
             };
 
             // Push children to stack
@@ -1858,6 +1990,10 @@ impl<S: Store> HashTree<S> {
                     hash: link.hash,
                     path: child_path,
                     key: link.key,
+                    meta: link.meta,
+                    chunk_size: (node.node_type == LinkType::File
+                        && link.link_type == LinkType::Blob)
+                        .then_some(link.size),
                 });
             }
 
@@ -1884,6 +2020,20 @@ impl<S: Store> HashTree<S> {
         entry_cid: &Cid,
         size: u64,
         link_type: LinkType,
+    ) -> Result<Cid, HashTreeError> {
+        let entry = DirEntry::from_cid(name, entry_cid)
+            .with_size(size)
+            .with_link_type(link_type);
+        self.set_dir_entry(root, path, entry).await
+    }
+
+    /// Add or update an entry in a directory, keeping the entry's metadata
+    /// Returns new root Cid
+    pub async fn set_dir_entry(
+        &self,
+        root: &Cid,
+        path: &[&str],
+        entry: DirEntry,
     ) -> Result<Cid, HashTreeError> {
         let dir_cid = self.resolve_path_array(root, path).await?;
         let dir_cid = dir_cid.ok_or_else(|| HashTreeError::PathNotFound(path.join("/")))?;
@@ -1891,7 +2041,7 @@ impl<S: Store> HashTree<S> {
         let entries = self.list_directory(&dir_cid).await?;
         let mut new_entries: Vec<DirEntry> = entries
             .into_iter()
-            .filter(|e| e.name != name)
+            .filter(|e| e.name != entry.name)
             .map(|e| DirEntry {
                 name: e.name,
                 hash: e.hash,
@@ -1902,14 +2052,7 @@ impl<S: Store> HashTree<S> {
             })
             .collect();
 
-        new_entries.push(DirEntry {
-            name: name.to_string(),
-            hash: entry_cid.hash,
-            size,
-            key: entry_cid.key,
-            link_type,
-            meta: None,
-        });
+        new_entries.push(entry);
 
         let new_dir_cid = self.put_directory(new_entries).await?;
         self.rebuild_path(root, path, new_dir_cid).await
@@ -2118,6 +2261,11 @@ impl<S: Store> HashTree<S> {
         self.chunk_size
     }
 
+    /// Get chunking mode configuration
+    pub fn chunking(&self) -> ChunkingMode {
+        self.chunking
+    }
+
     /// Get max links configuration
     pub fn max_links(&self) -> usize {
         self.max_links
@@ -2142,10 +2290,16 @@ enum ReadStreamState<'a, S: Store> {
     Done,
 }
 
+/// Metadata of the link that led to a walked node
+type LinkMeta = Option<HashMap<String, serde_json::Value>>;
+
 struct WalkStackItem {
     hash: Hash,
     path: String,
     key: Option<[u8; 32]>,
+    meta: LinkMeta,
+    /// Size of a blob chunk inside a file node; chunks are yielded without fetching
+    chunk_size: Option<u64>,
 }
 
 enum WalkStreamState<'a, S: Store> {
@@ -2376,6 +2530,35 @@ mod tests {
         assert_eq!(retrieved, data);
     }
 
+    #[tokio::test]
+    async fn test_put_file_from_chunks_reuses_chunks() {
+        let store = Arc::new(MemoryStore::new());
+        let tree = HashTree::new(HashTreeConfig::new(store).with_chunk_size(100));
+
+        let data: Vec<u8> = (0..450).map(|i| (i % 251) as u8).collect();
+        let (cid, _) = tree.put(&data).await.unwrap();
+        let chunks = tree.file_chunks(&cid).await.unwrap();
+        assert_eq!(
+            chunks.iter().map(|c| c.size).collect::<Vec<_>>(),
+            vec![100, 100, 100, 100, 50]
+        );
+
+        // Rebuilding from the same chunks gives the same file
+        let (rebuilt, size) = tree.put_file_from_chunks(chunks.clone()).await.unwrap();
+        assert_eq!(rebuilt, cid);
+        assert_eq!(size, 450);
+
+        // Replace one chunk and keep the rest
+        let mut edited = data.clone();
+        edited[200..300].fill(7);
+        let mut new_chunks = chunks;
+        new_chunks[2] = tree.put_chunk(&edited[200..300]).await.unwrap();
+        let (patched, _) = tree.put_file_from_chunks(new_chunks).await.unwrap();
+        let (expected, _) = tree.put(&edited).await.unwrap();
+        assert_eq!(patched, expected);
+        assert_eq!(tree.get(&patched, None).await.unwrap().unwrap(), edited);
+    }
+
     #[tokio::test]
     async fn test_cid_deterministic() {
         let store = Arc::new(MemoryStore::new());
@@ -0,0 +1,963 @@
+//! Tar and zip import into directory trees
+//!
+//! Archive members are streamed straight into `HashTree::put_stream`, so an
+//! archive never has to be unpacked to disk. Mode, mtime and symlink targets
+//! are kept through the POSIX metadata profile; owners, devices and fifos are
+//! dropped. Hard links reuse the content of the member they point to.
+//!
+//! The root only depends on the archive's contents: entries are sorted by
+//! `put_directory`, later members replace earlier ones with the same path (as
+//! when extracting), and directories that only appear as path prefixes get no
+//! metadata. Importing the same archive twice yields the same root.
+
+use std::collections::BTreeMap;
+use std::io::{self, Read, Seek, SeekFrom};
+
+use futures::io::AllowStdIo;
+
+use crate::archive::{
+    tar_padding, EXTRA_TIMESTAMP, EXTRA_ZIP64, TAR_BLOCK, ZIP64_END_LOCATOR,
+    ZIP64_END_OF_CENTRAL_DIR, ZIP_CENTRAL_HEADER, ZIP_END_OF_CENTRAL_DIR, ZIP_LOCAL_HEADER,
+};
+use crate::hashtree::{HashTree, HashTreeError};
+use crate::posix::PosixMeta;
+use crate::store::Store;
+use crate::types::{Cid, DirEntry, LinkType};
+
+/// Import a tar stream as a directory tree and return its root
+///
+/// The reader is consumed front to back without seeking, so compressed
+/// tarballs only need a decoder in front.
+pub async fn import_tar<S: Store, R: Read>(
+    tree: &HashTree<S>,
+    mut reader: R,
+) -> Result<Cid, HashTreeError> {
+    let mut root = DirNode::default();
+    let mut pax = PaxHeader::default();
+    let mut long_name = None;
+    let mut long_link = None;
+
+    loop {
+        let mut header = [0u8; TAR_BLOCK];
+        if !read_block(&mut reader, &mut header)? || header.iter().all(|&b| b == 0) {
+            break;
+        }
+        verify_checksum(&header)?;
+
+        let typeflag = header[156];
+        let header_size = parse_number(&header[124..136])?;
+        match typeflag {
+            // GNU long name / long link target
+            b'L' | b'K' => {
+                let data = read_member_data(&mut reader, header_size)?;
+                let value = String::from_utf8_lossy(&data)
+                    .trim_end_matches('\0')
+                    .to_string();
+                if typeflag == b'L' {
+                    long_name = Some(value);
+                } else {
+                    long_link = Some(value);
+                }
+                continue;
+            }
+            // pax extended header for the next member
+            b'x' => {
+                let data = read_member_data(&mut reader, header_size)?;
+                pax = PaxHeader::parse(&data)?;
+                continue;
+            }
+            // pax global header: nothing we keep applies archive-wide
+            b'g' => {
+                skip(&mut reader, header_size + tar_padding(header_size) as u64)?;
+                continue;
+            }
+            _ => {}
+        }
+
+        let pax = std::mem::take(&mut pax);
+        let path = pax
+            .path
+            .or(long_name.take())
+            .unwrap_or_else(|| ustar_name(&header));
+        let link = pax
+            .linkpath
+            .or(long_link.take())
+            .unwrap_or_else(|| field_str(&header[157..257]));
+        let size = pax.size.unwrap_or(header_size);
+        let mode = parse_number(&header[100..108])? as u32;
+        let mtime = match pax.mtime {
+            Some(mtime) => mtime,
+            None => parse_number(&header[136..148])? as i64,
+        };
+        let posix = PosixMeta::default().with_mode(mode).with_mtime(mtime);
+        let padding = tar_padding(size) as u64;
+
+        let components = clean_path(&path)?;
+        let is_dir = typeflag == b'5' || (matches!(typeflag, b'0' | 0) && path.ends_with('/'));
+        let Some((name, parents)) = components.split_last() else {
+            // "./" describes the root itself, which has no link to carry metadata
+            skip(&mut reader, size + padding)?;
+            continue;
+        };
+
+        match typeflag {
+            _ if is_dir => {
+                root.dir_mut(&components).posix = posix;
+                skip(&mut reader, size + padding)?;
+            }
+            b'0' | 0 | b'7' => {
+                let entry = put_member(tree, &mut reader, size, name).await?;
+                root.dir_mut(parents).insert_file(entry.with_posix(&posix));
+                skip(&mut reader, padding)?;
+            }
+            b'2' => {
+                let entry = put_symlink(tree, name, &link, mtime).await?;
+                root.dir_mut(parents).insert_file(entry);
+                skip(&mut reader, size + padding)?;
+            }
+            b'1' => {
+                let target = clean_path(&link)?;
+                let entry = root.find_file(&target).ok_or_else(|| {
+                    invalid(format!("{}: hard link target {} not found", path, link))
+                })?;
+                let entry = DirEntry {
+                    name: name.to_string(),
+                    ..entry.clone()
+                };
+                root.dir_mut(parents).insert_file(entry);
+                skip(&mut reader, size + padding)?;
+            }
+            // Devices, fifos and unknown types have no place in a tree
+            _ => skip(&mut reader, size + padding)?,
+        }
+    }
+
+    build_dir(tree, root).await
+}
+
+/// Import a zip archive as a directory tree and return its root
+///
+/// Members are located through the central directory, so the reader must
+/// be seekable. Stored and deflated members are supported; encrypted ones
+/// are rejected.
+pub async fn import_zip<S: Store, R: Read + Seek>(
+    tree: &HashTree<S>,
+    mut reader: R,
+) -> Result<Cid, HashTreeError> {
+    let records = read_central_directory(&mut reader)?;
+    let mut root = DirNode::default();
+
+    for record in records {
+        let components = clean_path(&record.name)?;
+        let Some((name, parents)) = components.split_last() else {
+            continue;
+        };
+
+        let mut posix = PosixMeta::default().with_mtime(record.mtime);
+        if let Some(mode) = record.unix_mode {
+            posix = posix.with_mode(mode);
+        }
+        if record.is_dir() {
+            root.dir_mut(&components).posix = posix;
+            continue;
+        }
+
+        // Skip the local header; its sizes may be zero when a data descriptor follows
+        reader
+            .seek(SeekFrom::Start(record.offset))
+            .map_err(io_error)?;
+        let mut local = [0u8; 30];
+        reader.read_exact(&mut local).map_err(io_error)?;
+        if read_u32(&local, 0) != ZIP_LOCAL_HEADER {
+            return Err(invalid(format!("{}: bad local header", record.name)));
+        }
+        let skip_len = read_u16(&local, 26) as i64 + read_u16(&local, 28) as i64;
+        reader.seek(SeekFrom::Current(skip_len)).map_err(io_error)?;
+
+        let compressed = (&mut reader).take(record.compressed_size);
+        let content: Box<dyn Read + '_> = match record.method {
+            0 => Box::new(compressed),
+            8 => Box::new(flate2::read::DeflateDecoder::new(compressed)),
+            method => {
+                return Err(invalid(format!(
+                    "{}: unsupported compression method {}",
+                    record.name, method
+                )))
+            }
+        };
+        let mut content = CrcReader::new(content);
+
+        let entry = if record.is_symlink() {
+            let mut target = Vec::new();
+            content.read_to_end(&mut target).map_err(io_error)?;
+            if target.len() as u64 != record.size {
+                return Err(truncated(&record.name));
+            }
+            let target = String::from_utf8_lossy(&target).into_owned();
+            put_symlink(tree, name, &target, record.mtime).await?
+        } else {
+            put_member(tree, &mut content, record.size, name)
+                .await?
+                .with_posix(&posix)
+        };
+        if content.crc() != record.crc {
+            return Err(invalid(format!("{}: CRC mismatch", record.name)));
+        }
+        root.dir_mut(parents).insert_file(entry);
+    }
+
+    build_dir(tree, root).await
+}
+
+// ============ TREE ASSEMBLY ============
+
+#[derive(Default)]
+struct DirNode {
+    posix: PosixMeta,
+    entries: BTreeMap<String, Node>,
+}
+
+enum Node {
+    File(DirEntry),
+    Dir(DirNode),
+}
+
+impl DirNode {
+    /// Get or create the directory at `components`, replacing files in the way
+    fn dir_mut(&mut self, components: &[&str]) -> &mut DirNode {
+        let mut dir = self;
+        for name in components {
+            let node = dir
+                .entries
+                .entry(name.to_string())
+                .or_insert_with(|| Node::Dir(DirNode::default()));
+            if let Node::File(_) = node {
+                *node = Node::Dir(DirNode::default());
+            }
+            dir = match node {
+                Node::Dir(sub) => sub,
+                Node::File(_) => unreachable!("replaced above"),
+            };
+        }
+        dir
+    }
+
+    fn insert_file(&mut self, entry: DirEntry) {
+        self.entries.insert(entry.name.clone(), Node::File(entry));
+    }
+
+    fn find_file(&self, components: &[&str]) -> Option<&DirEntry> {
+        let (name, parents) = components.split_last()?;
+        let mut dir = self;
+        for parent in parents {
+            match dir.entries.get(*parent)? {
+                Node::Dir(sub) => dir = sub,
+                Node::File(_) => return None,
+            }
+        }
+        match dir.entries.get(*name)? {
+            Node::File(entry) => Some(entry),
+            Node::Dir(_) => None,
+        }
+    }
+}
+
+/// Store a directory bottom-up
+async fn build_dir<S: Store>(tree: &HashTree<S>, dir: DirNode) -> Result<Cid, HashTreeError> {
+    let mut entries = Vec::with_capacity(dir.entries.len());
+    for (name, node) in dir.entries {
+        entries.push(match node {
+            Node::File(entry) => entry,
+            Node::Dir(sub) => {
+                let posix = sub.posix.clone();
+                let cid = Box::pin(build_dir(tree, sub)).await?;
+                DirEntry::from_cid(name, &cid)
+                    .with_link_type(LinkType::Dir)
+                    .with_posix(&posix)
+            }
+        });
+    }
+    tree.put_directory(entries).await
+}
+
+/// Stream exactly `size` bytes of member content into the tree
+async fn put_member<S: Store, R: Read>(
+    tree: &HashTree<S>,
+    reader: &mut R,
+    size: u64,
+    name: &str,
+) -> Result<DirEntry, HashTreeError> {
+    let (cid, written) = tree.put_stream(AllowStdIo::new(reader.take(size))).await?;
+    if written != size {
+        return Err(truncated(name));
+    }
+    let link_type = if tree.get_node(&cid).await?.is_some() {
+        LinkType::File
+    } else {
+        LinkType::Blob
+    };
+    Ok(DirEntry::from_cid(name, &cid)
+        .with_size(size)
+        .with_link_type(link_type))
+}
+
+/// Store a symlink the way directory uploads do: a blob holding the target
+async fn put_symlink<S: Store>(
+    tree: &HashTree<S>,
+    name: &str,
+    target: &str,
+    mtime: i64,
+) -> Result<DirEntry, HashTreeError> {
+    let (cid, size) = tree.put(target.as_bytes()).await?;
+    let posix = PosixMeta::default().with_mtime(mtime).with_symlink(target);
+    Ok(DirEntry::from_cid(name, &cid)
+        .with_size(size)
+        .with_posix(&posix))
+}
+
+/// Split a member path into components, dropping `.` and empty segments
+fn clean_path(path: &str) -> Result<Vec<&str>, HashTreeError> {
+    let mut components = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => return Err(invalid(format!("{}: path escapes the archive", path))),
+            part => components.push(part),
+        }
+    }
+    Ok(components)
+}
+
+fn invalid(message: String) -> HashTreeError {
+    HashTreeError::InvalidArchive(message)
+}
+
+fn truncated(name: &str) -> HashTreeError {
+    invalid(format!("{}: unexpected end of data", name))
+}
+
+fn io_error(e: io::Error) -> HashTreeError {
+    if e.kind() == io::ErrorKind::UnexpectedEof {
+        invalid("unexpected end of archive".to_string())
+    } else {
+        HashTreeError::Store(format!("read error: {}", e))
+    }
+}
+
+// ============ TAR ============
+
+/// Fill one header block; false on a clean end of input
+fn read_block<R: Read>(reader: &mut R, block: &mut [u8; TAR_BLOCK]) -> Result<bool, HashTreeError> {
+    let mut filled = 0;
+    while filled < TAR_BLOCK {
+        match reader.read(&mut block[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(invalid("truncated tar header".to_string())),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(io_error(e)),
+        }
+    }
+    Ok(true)
+}
+
+/// Read a small metadata member (long names, pax records) with its padding
+fn read_member_data<R: Read>(reader: &mut R, size: u64) -> Result<Vec<u8>, HashTreeError> {
+    // Metadata records are tiny; refuse to buffer anything that isn't
+    if size > 1 << 20 {
+        return Err(invalid(format!("tar metadata record of {} bytes", size)));
+    }
+    let mut data = vec![0u8; size as usize];
+    reader.read_exact(&mut data).map_err(io_error)?;
+    skip(reader, tar_padding(size) as u64)?;
+    Ok(data)
+}
+
+fn skip<R: Read>(reader: &mut R, len: u64) -> Result<(), HashTreeError> {
+    let skipped = io::copy(&mut reader.take(len), &mut io::sink()).map_err(io_error)?;
+    if skipped != len {
+        return Err(invalid("unexpected end of archive".to_string()));
+    }
+    Ok(())
+}
+
+/// Accept both the unsigned and the historical signed checksum
+fn verify_checksum(header: &[u8; TAR_BLOCK]) -> Result<(), HashTreeError> {
+    let stored = parse_number(&header[148..156])?;
+    let field = 148..156;
+    let (mut unsigned, mut signed) = (0u64, 0i64);
+    for (i, &b) in header.iter().enumerate() {
+        let b = if field.contains(&i) { b' ' } else { b };
+        unsigned += b as u64;
+        signed += b as i8 as i64;
+    }
+    if stored == unsigned || stored as i64 == signed {
+        Ok(())
+    } else {
+        Err(invalid("tar header checksum mismatch".to_string()))
+    }
+}
+
+/// Octal field, or GNU base-256 when the high bit is set
+fn parse_number(field: &[u8]) -> Result<u64, HashTreeError> {
+    if field.first().is_some_and(|&b| b & 0x80 != 0) {
+        let mut value: u64 = (field[0] & 0x7f) as u64;
+        for &b in &field[1..] {
+            value = value
+                .checked_mul(256)
+                .and_then(|v| v.checked_add(b as u64))
+                .ok_or_else(|| invalid("tar number out of range".to_string()))?;
+        }
+        return Ok(value);
+    }
+    let text = std::str::from_utf8(field)
+        .map_err(|_| invalid("malformed tar number".to_string()))?
+        .trim_matches(|c: char| c == '\0' || c == ' ');
+    if text.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(text, 8).map_err(|_| invalid(format!("malformed tar number {:?}", text)))
+}
+
+fn field_str(field: &[u8]) -> String {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+/// Name field joined with the ustar prefix field
+fn ustar_name(header: &[u8; TAR_BLOCK]) -> String {
+    let name = field_str(&header[0..100]);
+    // GNU archives use the prefix area for other fields
+    if &header[257..263] != b"ustar\0" {
+        return name;
+    }
+    let prefix = field_str(&header[345..500]);
+    if prefix.is_empty() {
+        name
+    } else {
+        format!("{}/{}", prefix, name)
+    }
+}
+
+/// Overrides from a pax extended header
+#[derive(Default)]
+struct PaxHeader {
+    path: Option<String>,
+    linkpath: Option<String>,
+    size: Option<u64>,
+    mtime: Option<i64>,
+}
+
+impl PaxHeader {
+    /// Parse `"<len> <key>=<value>\n"` records
+    fn parse(mut data: &[u8]) -> Result<Self, HashTreeError> {
+        let malformed = || invalid("malformed pax header".to_string());
+        let mut pax = Self::default();
+        while !data.is_empty() {
+            let space = data.iter().position(|&b| b == b' ').ok_or_else(malformed)?;
+            let len: usize = std::str::from_utf8(&data[..space])
+                .ok()
+                .and_then(|len| len.parse().ok())
+                .filter(|&len| len > space + 1 && len <= data.len())
+                .ok_or_else(malformed)?;
+            let record = &data[space + 1..len];
+            let record = record.strip_suffix(b"\n").unwrap_or(record);
+            let eq = record
+                .iter()
+                .position(|&b| b == b'=')
+                .ok_or_else(malformed)?;
+            let value = String::from_utf8_lossy(&record[eq + 1..]).into_owned();
+            match &record[..eq] {
+                b"path" => pax.path = Some(value),
+                b"linkpath" => pax.linkpath = Some(value),
+                b"size" => pax.size = Some(value.parse().map_err(|_| malformed())?),
+                b"mtime" => {
+                    // Fractional seconds are dropped, like the mtime profile key
+                    let secs = value.split('.').next().unwrap_or("");
+                    pax.mtime = Some(secs.parse().map_err(|_| malformed())?);
+                }
+                _ => {}
+            }
+            data = &data[len..];
+        }
+        Ok(pax)
+    }
+}
+
+// ============ ZIP ============
+
+/// Central directory fields needed to import a member
+struct ZipRecord {
+    name: String,
+    method: u16,
+    crc: u32,
+    compressed_size: u64,
+    size: u64,
+    offset: u64,
+    /// Unix `st_mode` when the archive was made on Unix
+    unix_mode: Option<u32>,
+    mtime: i64,
+}
+
+impl ZipRecord {
+    fn file_type(&self) -> Option<u32> {
+        self.unix_mode
+            .map(|mode| mode & 0o170000)
+            .filter(|&t| t != 0)
+    }
+
+    fn is_dir(&self) -> bool {
+        self.name.ends_with('/') || self.file_type() == Some(0o040000)
+    }
+
+    fn is_symlink(&self) -> bool {
+        self.file_type() == Some(0o120000)
+    }
+}
+
+fn read_u16(data: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([data[at], data[at + 1]])
+}
+
+fn read_u32(data: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(data[at..at + 4].try_into().unwrap())
+}
+
+fn read_u64(data: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(data[at..at + 8].try_into().unwrap())
+}
+
+fn read_central_directory<R: Read + Seek>(reader: &mut R) -> Result<Vec<ZipRecord>, HashTreeError> {
+    // The end record is 22 bytes plus a comment of up to 64 KiB
+    let len = reader.seek(SeekFrom::End(0)).map_err(io_error)?;
+    let tail_len = len.min(22 + u16::MAX as u64);
+    let tail_start = len - tail_len;
+    reader.seek(SeekFrom::Start(tail_start)).map_err(io_error)?;
+    let mut tail = vec![0u8; tail_len as usize];
+    reader.read_exact(&mut tail).map_err(io_error)?;
+    let eocd = (0..tail.len().saturating_sub(21))
+        .rev()
+        .find(|&i| read_u32(&tail, i) == ZIP_END_OF_CENTRAL_DIR)
+        .ok_or_else(|| invalid("zip end of central directory not found".to_string()))?;
+
+    let mut count = read_u16(&tail, eocd + 10) as u64;
+    let mut cd_size = read_u32(&tail, eocd + 12) as u64;
+    let mut cd_offset = read_u32(&tail, eocd + 16) as u64;
+    if eocd >= 20 && read_u32(&tail, eocd - 20) == ZIP64_END_LOCATOR {
+        let record_offset = read_u64(&tail, eocd - 20 + 8);
+        reader
+            .seek(SeekFrom::Start(record_offset))
+            .map_err(io_error)?;
+        let mut record = [0u8; 56];
+        reader.read_exact(&mut record).map_err(io_error)?;
+        if read_u32(&record, 0) != ZIP64_END_OF_CENTRAL_DIR {
+            return Err(invalid("bad zip64 end of central directory".to_string()));
+        }
+        count = read_u64(&record, 32);
+        cd_size = read_u64(&record, 40);
+        cd_offset = read_u64(&record, 48);
+    }
+    if cd_offset.checked_add(cd_size).is_none_or(|end| end > len) {
+        return Err(invalid("zip central directory out of bounds".to_string()));
+    }
+
+    reader.seek(SeekFrom::Start(cd_offset)).map_err(io_error)?;
+    let mut cd = vec![0u8; cd_size as usize];
+    reader.read_exact(&mut cd).map_err(io_error)?;
+
+    let mut records = Vec::new();
+    let mut pos = 0;
+    for _ in 0..count {
+        if pos + 46 > cd.len() || read_u32(&cd, pos) != ZIP_CENTRAL_HEADER {
+            return Err(invalid("bad zip central directory entry".to_string()));
+        }
+        let header = &cd[pos..];
+        let made_by = read_u16(header, 4);
+        let flags = read_u16(header, 8);
+        let name_len = read_u16(header, 28) as usize;
+        let extra_len = read_u16(header, 30) as usize;
+        let comment_len = read_u16(header, 32) as usize;
+        let end = 46 + name_len + extra_len + comment_len;
+        if end > header.len() {
+            return Err(invalid("bad zip central directory entry".to_string()));
+        }
+        let name = String::from_utf8_lossy(&header[46..46 + name_len]).into_owned();
+        if flags & 0x0001 != 0 {
+            return Err(invalid(format!(
+                "{}: encrypted zip members are not supported",
+                name
+            )));
+        }
+
+        let external = read_u32(header, 38);
+        let mut record = ZipRecord {
+            method: read_u16(header, 10),
+            crc: read_u32(header, 16),
+            compressed_size: read_u32(header, 20) as u64,
+            size: read_u32(header, 24) as u64,
+            offset: read_u32(header, 42) as u64,
+            unix_mode: (made_by >> 8 == 3 && external >> 16 != 0).then_some(external >> 16),
+            mtime: dos_to_unix(read_u16(header, 12), read_u16(header, 14)),
+            name,
+        };
+        apply_extra(
+            &mut record,
+            &header[46 + name_len..46 + name_len + extra_len],
+        );
+        records.push(record);
+        pos += end;
+    }
+    Ok(records)
+}
+
+/// Apply ZIP64 sizes/offset and the exact mtime from extra fields
+fn apply_extra(record: &mut ZipRecord, mut extra: &[u8]) {
+    while extra.len() >= 4 {
+        let id = read_u16(extra, 0);
+        let len = (read_u16(extra, 2) as usize).min(extra.len() - 4);
+        let data = &extra[4..4 + len];
+        match id {
+            EXTRA_ZIP64 => {
+                // Only the fields saturated in the fixed header are present, in this order
+                let mut values = data.chunks_exact(8).map(|v| read_u64(v, 0));
+                if record.size == u32::MAX as u64 {
+                    record.size = values.next().unwrap_or(record.size);
+                }
+                if record.compressed_size == u32::MAX as u64 {
+                    record.compressed_size = values.next().unwrap_or(record.compressed_size);
+                }
+                if record.offset == u32::MAX as u64 {
+                    record.offset = values.next().unwrap_or(record.offset);
+                }
+            }
+            EXTRA_TIMESTAMP if len >= 5 && data[0] & 1 != 0 => {
+                record.mtime = read_u32(data, 1) as i64;
+            }
+            _ => {}
+        }
+        extra = &extra[4 + len..];
+    }
+}
+
+/// Unix timestamp for an MS-DOS (time, date), read as UTC
+fn dos_to_unix(time: u16, date: u16) -> i64 {
+    let year = 1980 + (date >> 9) as i64;
+    let month = ((date >> 5) & 0xf).clamp(1, 12) as i64;
+    let day = (date & 0x1f).max(1) as i64;
+
+    // Days since the epoch from a civil date (Howard Hinnant's algorithm)
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    let secs =
+        (time >> 11) as i64 * 3600 + ((time >> 5) & 0x3f) as i64 * 60 + (time & 0x1f) as i64 * 2;
+    days * 86_400 + secs
+}
+
+/// Reader computing the CRC-32 of everything read through it
+struct CrcReader<R> {
+    inner: R,
+    hasher: crc32fast::Hasher,
+}
+
+impl<R: Read> CrcReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            hasher: crc32fast::Hasher::new(),
+        }
+    }
+
+    fn crc(&self) -> u32 {
+        self.hasher.clone().finalize()
+    }
+}
+
+impl<R: Read> Read for CrcReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archive::{archive_stream, ArchiveFormat};
+    use crate::hashtree::HashTreeConfig;
+    use crate::store::MemoryStore;
+    use futures::StreamExt;
+    use std::io::{Cursor, Write};
+    use std::sync::Arc;
+
+    fn public_tree() -> HashTree<MemoryStore> {
+        HashTree::new(HashTreeConfig::new(Arc::new(MemoryStore::new())).public())
+    }
+
+    /// ustar header for a hand-built archive
+    fn tar_entry(name: &str, typeflag: u8, data: &[u8], link: &str) -> Vec<u8> {
+        let mut header = [0u8; TAR_BLOCK];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[100..108].copy_from_slice(b"0000640\0");
+        header[108..116].copy_from_slice(b"0000000\0");
+        header[116..124].copy_from_slice(b"0000000\0");
+        header[124..136].copy_from_slice(format!("{:011o}\0", data.len()).as_bytes());
+        header[136..148].copy_from_slice(format!("{:011o}\0", 1_600_000_000).as_bytes());
+        header[156] = typeflag;
+        header[157..157 + link.len()].copy_from_slice(link.as_bytes());
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+        header[148..156].fill(b' ');
+        let sum: u32 = header.iter().map(|&b| b as u32).sum();
+        header[148..156].copy_from_slice(format!("{:06o}\0 ", sum).as_bytes());
+
+        let mut out = header.to_vec();
+        out.extend_from_slice(data);
+        out.resize(out.len() + tar_padding(data.len() as u64), 0);
+        out
+    }
+
+    fn pax_record(key: &str, value: &str) -> String {
+        // The length prefix counts its own digits
+        let body = format!(" {}={}\n", key, value);
+        let len = (1..)
+            .find(|&n: &usize| n == body.len() + n.to_string().len())
+            .unwrap();
+        format!("{}{}", len, body)
+    }
+
+    async fn read_path(tree: &HashTree<MemoryStore>, root: &Cid, path: &str) -> Vec<u8> {
+        let cid = tree.resolve_path(root, path).await.unwrap().unwrap();
+        tree.get(&cid, None).await.unwrap().unwrap()
+    }
+
+    async fn export(tree: &HashTree<MemoryStore>, root: Cid, format: ArchiveFormat) -> Vec<u8> {
+        let mut stream = archive_stream(tree, root, format);
+        let mut out = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            out.extend(chunk.unwrap());
+        }
+        out
+    }
+
+    #[tokio::test]
+    async fn test_tar_members_and_metadata() {
+        let long_name = format!("deep/{}.txt", "n".repeat(120));
+        let mut tar = Vec::new();
+        tar.extend(tar_entry("./docs/", b'5', b"", ""));
+        tar.extend(tar_entry("./docs/readme.md", b'0', b"# hi", ""));
+        tar.extend(tar_entry("docs/latest", b'2', b"", "readme.md"));
+        tar.extend(tar_entry("copy.md", b'1', b"", "docs/readme.md"));
+        tar.extend(tar_entry("././@LongLink", b'L', long_name.as_bytes(), ""));
+        tar.extend(tar_entry("ignored", b'0', b"long", ""));
+        let pax = pax_record("path", "pax/named.txt") + &pax_record("mtime", "1700000000.5");
+        tar.extend(tar_entry("PaxHeaders/x", b'x', pax.as_bytes(), ""));
+        tar.extend(tar_entry("short", b'0', b"pax", ""));
+        tar.extend(tar_entry("fifo", b'6', b"", ""));
+        tar.extend([0u8; 2 * TAR_BLOCK]);
+
+        let tree = public_tree();
+        let root = import_tar(&tree, Cursor::new(&tar)).await.unwrap();
+
+        assert_eq!(read_path(&tree, &root, "docs/readme.md").await, b"# hi");
+        assert_eq!(read_path(&tree, &root, "copy.md").await, b"# hi");
+        assert_eq!(read_path(&tree, &root, &long_name).await, b"long");
+        assert_eq!(read_path(&tree, &root, "pax/named.txt").await, b"pax");
+        assert!(tree.resolve_path(&root, "fifo").await.unwrap().is_none());
+
+        let entries = tree.list_directory(&root).await.unwrap();
+        let names: Vec<_> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["copy.md", "deep", "docs", "pax"]);
+        let docs = entries.iter().find(|e| e.name == "docs").unwrap();
+        assert_eq!(
+            PosixMeta::from_meta(docs.meta.as_ref()),
+            PosixMeta::default()
+                .with_mode(0o640)
+                .with_mtime(1_600_000_000)
+        );
+
+        let docs_cid = tree.resolve_path(&root, "docs").await.unwrap().unwrap();
+        let docs_entries = tree.list_directory(&docs_cid).await.unwrap();
+        let latest = docs_entries.iter().find(|e| e.name == "latest").unwrap();
+        assert_eq!(
+            PosixMeta::from_meta(latest.meta.as_ref())
+                .symlink
+                .as_deref(),
+            Some("readme.md")
+        );
+
+        let pax_dir = tree.resolve_path(&root, "pax").await.unwrap().unwrap();
+        let named = &tree.list_directory(&pax_dir).await.unwrap()[0];
+        assert_eq!(
+            PosixMeta::from_meta(named.meta.as_ref()).mtime,
+            Some(1_700_000_000)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tar_rejects_bad_input() {
+        let tree = public_tree();
+
+        let escape = tar_entry("../evil", b'0', b"x", "");
+        assert!(matches!(
+            import_tar(&tree, Cursor::new(&escape)).await,
+            Err(HashTreeError::InvalidArchive(_))
+        ));
+
+        let mut truncated = tar_entry("file", b'0', &[7u8; 2000], "");
+        truncated.truncate(TAR_BLOCK + 100);
+        assert!(matches!(
+            import_tar(&tree, Cursor::new(&truncated)).await,
+            Err(HashTreeError::InvalidArchive(_))
+        ));
+
+        let mut corrupt = tar_entry("file", b'0', b"x", "");
+        corrupt[0] = b'g';
+        assert!(matches!(
+            import_tar(&tree, Cursor::new(&corrupt)).await,
+            Err(HashTreeError::InvalidArchive(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_round_trip_is_deterministic() {
+        // Chunks small enough to split the big file but not the directories
+        let chunked = HashTree::new(
+            HashTreeConfig::new(Arc::new(MemoryStore::new()))
+                .public()
+                .with_chunk_size(1024),
+        );
+        let big: Vec<u8> = (0..5000u32).map(|i| (i % 251) as u8).collect();
+        let mut tar = Vec::new();
+        tar.extend(tar_entry("a/b/big.bin", b'0', &big, ""));
+        tar.extend(tar_entry("a/small", b'0', b"small", ""));
+        tar.extend(tar_entry("z", b'2', b"", "a/small"));
+        tar.extend([0u8; 2 * TAR_BLOCK]);
+
+        let root = import_tar(&chunked, Cursor::new(&tar)).await.unwrap();
+        assert_eq!(root, import_tar(&chunked, Cursor::new(&tar)).await.unwrap());
+        assert_eq!(read_path(&chunked, &root, "a/b/big.bin").await, big);
+
+        // Export and re-import reach a fixed point in both formats
+        for format in [ArchiveFormat::Tar, ArchiveFormat::Zip] {
+            let first = export(&chunked, root.clone(), format).await;
+            let reimported = match format {
+                ArchiveFormat::Tar => import_tar(&chunked, Cursor::new(&first)).await,
+                ArchiveFormat::Zip => import_zip(&chunked, Cursor::new(&first)).await,
+            }
+            .unwrap();
+            assert_eq!(read_path(&chunked, &reimported, "a/b/big.bin").await, big);
+            let second = export(&chunked, reimported, format).await;
+            assert_eq!(first, second);
+        }
+    }
+
+    /// Minimal zip writer producing deflated members
+    fn zip_archive(members: &[(&str, &[u8], u32)]) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut central = Vec::new();
+        for (name, data, mode) in members {
+            let mut encoder =
+                flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(data).unwrap();
+            let compressed = encoder.finish().unwrap();
+            let crc = crc32fast::hash(data);
+            let offset = out.len() as u32;
+            // 2021-06-15 12:30:20
+            let (time, date) = (
+                (12u16 << 11) | (30 << 5) | 10,
+                ((2021u16 - 1980) << 9) | (6 << 5) | 15,
+            );
+
+            out.extend(ZIP_LOCAL_HEADER.to_le_bytes());
+            out.extend(20u16.to_le_bytes());
+            out.extend(0u16.to_le_bytes());
+            out.extend(8u16.to_le_bytes());
+            out.extend(time.to_le_bytes());
+            out.extend(date.to_le_bytes());
+            out.extend(crc.to_le_bytes());
+            out.extend((compressed.len() as u32).to_le_bytes());
+            out.extend((data.len() as u32).to_le_bytes());
+            out.extend((name.len() as u16).to_le_bytes());
+            out.extend(0u16.to_le_bytes());
+            out.extend(name.as_bytes());
+            out.extend(&compressed);
+
+            central.extend(ZIP_CENTRAL_HEADER.to_le_bytes());
+            central.extend(((3u16 << 8) | 20).to_le_bytes());
+            central.extend(20u16.to_le_bytes());
+            central.extend(0u16.to_le_bytes());
+            central.extend(8u16.to_le_bytes());
+            central.extend(time.to_le_bytes());
+            central.extend(date.to_le_bytes());
+            central.extend(crc.to_le_bytes());
+            central.extend((compressed.len() as u32).to_le_bytes());
+            central.extend((data.len() as u32).to_le_bytes());
+            central.extend((name.len() as u16).to_le_bytes());
+            central.extend([0u8; 8]);
+            central.extend((mode << 16).to_le_bytes());
+            central.extend(offset.to_le_bytes());
+            central.extend(name.as_bytes());
+        }
+        let cd_offset = out.len() as u32;
+        out.extend(&central);
+        out.extend(ZIP_END_OF_CENTRAL_DIR.to_le_bytes());
+        out.extend([0u8; 4]);
+        out.extend((members.len() as u16).to_le_bytes());
+        out.extend((members.len() as u16).to_le_bytes());
+        out.extend((central.len() as u32).to_le_bytes());
+        out.extend(cd_offset.to_le_bytes());
+        out.extend(0u16.to_le_bytes());
+        out
+    }
+
+    #[tokio::test]
+    async fn test_zip_deflated_members() {
+        let text = b"hello hello hello hello".repeat(50);
+        let zip = zip_archive(&[
+            ("bin/", b"", 0o040755),
+            ("bin/run.sh", b"#!/bin/sh\n", 0o100755),
+            ("bin/sh", b"run.sh", 0o120777),
+            ("notes/text.txt", &text, 0o100644),
+        ]);
+
+        let tree = public_tree();
+        let root = import_zip(&tree, Cursor::new(&zip)).await.unwrap();
+        assert_eq!(read_path(&tree, &root, "bin/run.sh").await, b"#!/bin/sh\n");
+        assert_eq!(read_path(&tree, &root, "notes/text.txt").await, text);
+
+        let bin = tree.resolve_path(&root, "bin").await.unwrap().unwrap();
+        let entries = tree.list_directory(&bin).await.unwrap();
+        let run = PosixMeta::from_meta(entries[0].meta.as_ref());
+        assert_eq!(run.mode, Some(0o755));
+        assert_eq!(run.mtime, Some(1_623_760_220));
+        assert_eq!(
+            PosixMeta::from_meta(entries[1].meta.as_ref())
+                .symlink
+                .as_deref(),
+            Some("run.sh")
+        );
+
+        // Flip a byte of run.sh's CRC (the entry after "bin/") in the central directory
+        let mut corrupt = zip.clone();
+        let run_sh = read_u32(&zip, zip.len() - 6) as usize + 46 + "bin/".len();
+        corrupt[run_sh + 16] ^= 0xff;
+        assert!(matches!(
+            import_zip(&tree, Cursor::new(&corrupt)).await,
+            Err(HashTreeError::InvalidArchive(_))
+        ));
+    }
+
+    #[test]
+    fn test_dos_to_unix() {
+        // Inverse of the export conversion for even seconds
+        let (time, date) = crate::archive::dos_datetime(1_700_000_000);
+        assert_eq!(dos_to_unix(time, date), 1_700_000_000);
+    }
+}
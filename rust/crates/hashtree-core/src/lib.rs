@@ -40,16 +40,24 @@
 //! }
 //! ```
 
+pub mod archive;
 pub mod builder;
+pub mod bundle;
+pub mod chunker;
 pub mod codec;
 pub mod crypto;
 pub mod diff;
 pub mod hash;
 pub mod hashtree;
+pub mod import;
+pub mod merge;
 pub mod nhash;
+pub mod posix;
+pub mod proof;
 pub mod reader;
 pub mod store;
 pub mod types;
+pub mod verifying;
 pub mod visibility;
 
 // Re-exports for convenience
@@ -59,6 +67,11 @@ pub use hashtree::{verify_tree as hashtree_verify_tree, HashTree, HashTreeConfig
 // Constants
 pub use builder::{BEP52_CHUNK_SIZE, DEFAULT_CHUNK_SIZE, DEFAULT_MAX_LINKS};
 
+// Chunking
+pub use chunker::{
+    CdcParams, ChunkingMode, DEFAULT_CDC_AVG_SIZE, DEFAULT_CDC_MAX_SIZE, DEFAULT_CDC_MIN_SIZE,
+};
+
 // Low-level codec
 pub use codec::{
     decode_tree_node, encode_and_hash, encode_tree_node, get_node_type, is_directory_node,
@@ -74,11 +87,13 @@ pub use nhash::{
     decode as nhash_decode_any, is_nhash, nhash_decode, nhash_encode, nhash_encode_full,
     DecodeResult, NHashData, NHashError,
 };
-pub use store::{MemoryStore, Store, StoreError};
+pub use posix::{PosixMeta, META_MODE, META_MTIME, META_SYMLINK, MODE_MASK};
+pub use store::{MemoryStore, Store, StoreError, BATCH_CONCURRENCY};
 pub use types::{
     from_hex, hash_equals, to_hex, Cid, CidParseError, DirEntry, Hash, Link, LinkType, PutResult,
     TreeNode,
 };
+pub use verifying::{verify_blob, VerifyingStore};
 
 pub use crypto::{
     content_hash, could_be_encrypted, decrypt, decrypt_chk, encrypt, encrypt_chk, encrypted_size,
@@ -87,8 +102,26 @@ pub use crypto::{
 };
 pub use visibility::{xor_keys, TreeVisibility};
 
+// Three-way merge
+pub use merge::{
+    merge_trees, ConflictKind, ConflictResolver, MergeConflict, MergeResult, MergeStrategy,
+    Resolution,
+};
+
+// Tar/zip export and import
+pub use archive::{archive_stream, ArchiveFormat};
+pub use import::{import_tar, import_zip};
+
+// Portable bundles
+pub use bundle::{
+    export_bundle, import_bundle, BundleError, BundleReader, BundleStats, ImportedBundle,
+};
+
+// Range inclusion proofs
+pub use proof::{prove_range, verify_range_proof, ProofBlock, ProofError, RangeProof};
+
 // Tree diff operations
 pub use diff::{
-    collect_hashes, collect_hashes_with_progress, tree_diff, tree_diff_streaming,
-    tree_diff_with_old_hashes, DiffStats, TreeDiff,
+    collect_hashes, collect_hashes_with_progress, entry_is_dir, path_diff, path_diff_streaming,
+    tree_diff, tree_diff_streaming, tree_diff_with_old_hashes, DiffStats, PathChange, TreeDiff,
 };
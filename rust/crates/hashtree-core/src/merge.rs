@@ -0,0 +1,540 @@
+//! Three-way merge of directory trees
+//!
+//! Merges two trees (`ours`, `theirs`) that were both derived from a common
+//! `base`. Entries changed on only one side take that side's version;
+//! directories changed on both sides are merged recursively. Anything else is
+//! a conflict, which a [`ConflictResolver`] settles and which is reported back
+//! per path.
+//!
+//! Subtrees with the same hash on both sides (or unchanged on one side) are
+//! taken as-is without being read.
+//!
+//! The FUSE mount merges a root published elsewhere into its own before each
+//! publish (and on demand with `HashtreeFuse::merge_root`). Merging in the
+//! daemon's background sync is out of scope: it has no local edits and only
+//! mirrors published roots.
+
+use std::collections::BTreeMap;
+
+use crate::diff::entry_is_dir;
+use crate::hashtree::{HashTree, HashTreeError};
+use crate::reader::TreeEntry;
+use crate::store::Store;
+use crate::types::{Cid, DirEntry, LinkType};
+
+/// Why an entry could not be merged automatically
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictKind {
+    /// Changed differently on both sides
+    BothModified,
+    /// Added on both sides with different content
+    BothAdded,
+    /// Changed on our side, deleted on theirs
+    ModifiedDeleted,
+    /// Deleted on our side, changed on theirs
+    DeletedModified,
+}
+
+/// How a conflict was settled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    /// Keep our version (or our deletion)
+    Ours,
+    /// Keep their version (or their deletion)
+    Theirs,
+    /// Keep ours under the original name and theirs as a renamed copy
+    KeepBoth,
+}
+
+/// A conflicting path and the versions involved
+#[derive(Debug, Clone)]
+pub struct MergeConflict {
+    /// Path relative to the tree root, `/`-separated
+    pub path: String,
+    pub kind: ConflictKind,
+    pub base: Option<TreeEntry>,
+    pub ours: Option<TreeEntry>,
+    pub theirs: Option<TreeEntry>,
+    /// Resolution that was applied
+    pub resolution: Resolution,
+    /// Name of their copy when resolved with `KeepBoth`
+    pub copy_name: Option<String>,
+}
+
+/// Decides how each conflict is resolved
+pub trait ConflictResolver: Send + Sync {
+    fn resolve(&self, path: &str, kind: ConflictKind) -> Resolution;
+}
+
+/// Resolve every conflict the same way
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergeStrategy {
+    #[default]
+    Ours,
+    Theirs,
+    KeepBoth,
+}
+
+impl ConflictResolver for MergeStrategy {
+    fn resolve(&self, _path: &str, _kind: ConflictKind) -> Resolution {
+        match self {
+            MergeStrategy::Ours => Resolution::Ours,
+            MergeStrategy::Theirs => Resolution::Theirs,
+            MergeStrategy::KeepBoth => Resolution::KeepBoth,
+        }
+    }
+}
+
+/// Result of a merge
+#[derive(Debug, Clone)]
+pub struct MergeResult {
+    /// Root of the merged tree
+    pub root: Cid,
+    /// Conflicts, already resolved in `root`
+    pub conflicts: Vec<MergeConflict>,
+}
+
+impl MergeResult {
+    pub fn has_conflicts(&self) -> bool {
+        !self.conflicts.is_empty()
+    }
+}
+
+/// Merge `ours` and `theirs` against their common ancestor `base`
+///
+/// Without a base, entries present on both sides with different content
+/// are `BothAdded` conflicts. The merged tree is written with `tree`'s
+/// encryption settings.
+pub async fn merge_trees<S: Store>(
+    tree: &HashTree<S>,
+    base: Option<&Cid>,
+    ours: &Cid,
+    theirs: &Cid,
+    resolver: &dyn ConflictResolver,
+) -> Result<MergeResult, HashTreeError> {
+    let mut conflicts = Vec::new();
+    let root = if ours.hash == theirs.hash || base.is_some_and(|b| b.hash == theirs.hash) {
+        ours.clone()
+    } else if base.is_some_and(|b| b.hash == ours.hash) {
+        theirs.clone()
+    } else {
+        merge_dir(
+            tree,
+            String::new(),
+            base.cloned(),
+            ours,
+            theirs,
+            resolver,
+            &mut conflicts,
+        )
+        .await?
+    };
+    Ok(MergeResult { root, conflicts })
+}
+
+/// Merge one directory level, recursing into directories changed on both sides
+async fn merge_dir<S: Store>(
+    tree: &HashTree<S>,
+    prefix: String,
+    base: Option<Cid>,
+    ours: &Cid,
+    theirs: &Cid,
+    resolver: &dyn ConflictResolver,
+    conflicts: &mut Vec<MergeConflict>,
+) -> Result<Cid, HashTreeError> {
+    let mut names: BTreeMap<String, [Option<TreeEntry>; 3]> = BTreeMap::new();
+    if let Some(base) = &base {
+        for entry in tree.list_directory(base).await? {
+            let name = entry.name.clone();
+            names.entry(name).or_default()[0] = Some(entry);
+        }
+    }
+    for entry in tree.list_directory(ours).await? {
+        let name = entry.name.clone();
+        names.entry(name).or_default()[1] = Some(entry);
+    }
+    for entry in tree.list_directory(theirs).await? {
+        let name = entry.name.clone();
+        names.entry(name).or_default()[2] = Some(entry);
+    }
+
+    let taken: Vec<String> = names.keys().cloned().collect();
+    let mut merged: Vec<DirEntry> = Vec::new();
+
+    for (name, [base, ours, theirs]) in names {
+        let path = if prefix.is_empty() {
+            name.clone()
+        } else {
+            format!("{}/{}", prefix, name)
+        };
+
+        if same_entry(&ours, &theirs) || same_entry(&base, &theirs) {
+            merged.extend(ours.map(to_dir_entry));
+            continue;
+        }
+        if same_entry(&base, &ours) {
+            merged.extend(theirs.map(to_dir_entry));
+            continue;
+        }
+
+        // Changed on both sides: directories merge, everything else conflicts
+        if let (Some(o), Some(t)) = (&ours, &theirs) {
+            if entry_is_dir(tree, o).await? && entry_is_dir(tree, t).await? {
+                let base_dir = match &base {
+                    Some(b) if entry_is_dir(tree, b).await? => Some(entry_cid(b)),
+                    _ => None,
+                };
+                let cid = Box::pin(merge_dir(
+                    tree,
+                    path,
+                    base_dir,
+                    &entry_cid(o),
+                    &entry_cid(t),
+                    resolver,
+                    conflicts,
+                ))
+                .await?;
+                merged.push(DirEntry {
+                    meta: o.meta.clone(),
+                    ..DirEntry::from_cid(name, &cid).with_link_type(LinkType::Dir)
+                });
+                continue;
+            }
+        }
+
+        let kind = match (&base, &ours, &theirs) {
+            (None, _, _) => ConflictKind::BothAdded,
+            (Some(_), Some(_), None) => ConflictKind::ModifiedDeleted,
+            (Some(_), None, Some(_)) => ConflictKind::DeletedModified,
+            _ => ConflictKind::BothModified,
+        };
+        let resolution = resolver.resolve(&path, kind);
+        let mut copy_name = None;
+        match resolution {
+            Resolution::Ours => merged.extend(ours.clone().map(to_dir_entry)),
+            Resolution::Theirs => merged.extend(theirs.clone().map(to_dir_entry)),
+            Resolution::KeepBoth => {
+                merged.extend(ours.clone().map(to_dir_entry));
+                if let Some(t) = theirs.clone() {
+                    if ours.is_some() {
+                        let copy = copy_name_for(&name, &taken, &merged);
+                        copy_name = Some(copy.clone());
+                        merged.push(DirEntry {
+                            name: copy,
+                            ..to_dir_entry(t)
+                        });
+                    } else {
+                        merged.push(to_dir_entry(t));
+                    }
+                }
+            }
+        }
+
+        conflicts.push(MergeConflict {
+            path,
+            kind,
+            base,
+            ours,
+            theirs,
+            resolution,
+            copy_name,
+        });
+    }
+
+    tree.put_directory(merged).await
+}
+
+/// Same content and metadata (both missing counts as the same)
+fn same_entry(a: &Option<TreeEntry>, b: &Option<TreeEntry>) -> bool {
+    match (a, b) {
+        (None, None) => true,
+        (Some(a), Some(b)) => {
+            a.hash == b.hash && a.key == b.key && a.link_type == b.link_type && a.meta == b.meta
+        }
+        _ => false,
+    }
+}
+
+fn entry_cid(entry: &TreeEntry) -> Cid {
+    Cid {
+        hash: entry.hash,
+        key: entry.key,
+    }
+}
+
+fn to_dir_entry(entry: TreeEntry) -> DirEntry {
+    DirEntry {
+        name: entry.name,
+        hash: entry.hash,
+        size: entry.size,
+        key: entry.key,
+        link_type: entry.link_type,
+        meta: entry.meta,
+    }
+}
+
+/// `name.theirs.ext`, numbered if that name is already in use
+fn copy_name_for(name: &str, taken: &[String], merged: &[DirEntry]) -> String {
+    let (stem, ext) = match name.rfind('.') {
+        Some(dot) if dot > 0 => (&name[..dot], &name[dot..]),
+        _ => (name, ""),
+    };
+    let in_use = |candidate: &str| {
+        taken.iter().any(|n| n == candidate) || merged.iter().any(|e| e.name == candidate)
+    };
+    let mut candidate = format!("{}.theirs{}", stem, ext);
+    let mut n = 2;
+    while in_use(&candidate) {
+        candidate = format!("{}.theirs{}{}", stem, n, ext);
+        n += 1;
+    }
+    candidate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MemoryStore;
+    use crate::HashTreeConfig;
+    use std::sync::Arc;
+
+    fn make_tree() -> HashTree<MemoryStore> {
+        HashTree::new(HashTreeConfig::new(Arc::new(MemoryStore::new())))
+    }
+
+    async fn file(tree: &HashTree<MemoryStore>, name: &str, data: &[u8]) -> DirEntry {
+        let (cid, size) = tree.put(data).await.unwrap();
+        DirEntry::from_cid(name, &cid).with_size(size)
+    }
+
+    async fn dir(tree: &HashTree<MemoryStore>, name: &str, entries: Vec<DirEntry>) -> DirEntry {
+        let cid = tree.put_directory(entries).await.unwrap();
+        DirEntry::from_cid(name, &cid).with_link_type(LinkType::Dir)
+    }
+
+    async fn root(tree: &HashTree<MemoryStore>, entries: Vec<DirEntry>) -> Cid {
+        tree.put_directory(entries).await.unwrap()
+    }
+
+    async fn read(tree: &HashTree<MemoryStore>, root: &Cid, path: &str) -> Option<Vec<u8>> {
+        let cid = tree.resolve_path(root, path).await.unwrap()?;
+        tree.get(&cid, None).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_merge_disjoint_changes() {
+        let tree = make_tree();
+        let base = root(
+            &tree,
+            vec![
+                file(&tree, "a", b"a").await,
+                dir(&tree, "src", vec![file(&tree, "x", b"x").await]).await,
+            ],
+        )
+        .await;
+        // Ours edits src/x, theirs adds src/y and removes a
+        let ours = root(
+            &tree,
+            vec![
+                file(&tree, "a", b"a").await,
+                dir(&tree, "src", vec![file(&tree, "x", b"x2").await]).await,
+            ],
+        )
+        .await;
+        let theirs = root(
+            &tree,
+            vec![
+                dir(
+                    &tree,
+                    "src",
+                    vec![file(&tree, "x", b"x").await, file(&tree, "y", b"y").await],
+                )
+                .await,
+            ],
+        )
+        .await;
+
+        let result = merge_trees(&tree, Some(&base), &ours, &theirs, &MergeStrategy::Ours)
+            .await
+            .unwrap();
+
+        assert!(!result.has_conflicts());
+        assert_eq!(read(&tree, &result.root, "a").await, None);
+        assert_eq!(
+            read(&tree, &result.root, "src/x").await,
+            Some(b"x2".to_vec())
+        );
+        assert_eq!(
+            read(&tree, &result.root, "src/y").await,
+            Some(b"y".to_vec())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_conflict_strategies() {
+        let tree = make_tree();
+        let base = root(
+            &tree,
+            vec![
+                file(&tree, "notes.txt", b"base").await,
+                file(&tree, "old", b"old").await,
+            ],
+        )
+        .await;
+        let ours = root(
+            &tree,
+            vec![
+                file(&tree, "notes.txt", b"ours").await,
+                file(&tree, "old", b"edited").await,
+            ],
+        )
+        .await;
+        let theirs = root(&tree, vec![file(&tree, "notes.txt", b"theirs").await]).await;
+
+        let result = merge_trees(&tree, Some(&base), &ours, &theirs, &MergeStrategy::Theirs)
+            .await
+            .unwrap();
+        let kinds: Vec<_> = result
+            .conflicts
+            .iter()
+            .map(|c| (c.path.as_str(), c.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("notes.txt", ConflictKind::BothModified),
+                ("old", ConflictKind::ModifiedDeleted),
+            ]
+        );
+        assert_eq!(
+            read(&tree, &result.root, "notes.txt").await,
+            Some(b"theirs".to_vec())
+        );
+        assert_eq!(read(&tree, &result.root, "old").await, None);
+
+        let result = merge_trees(&tree, Some(&base), &ours, &theirs, &MergeStrategy::KeepBoth)
+            .await
+            .unwrap();
+        assert_eq!(
+            result.conflicts[0].copy_name.as_deref(),
+            Some("notes.theirs.txt")
+        );
+        assert_eq!(
+            read(&tree, &result.root, "notes.txt").await,
+            Some(b"ours".to_vec())
+        );
+        assert_eq!(
+            read(&tree, &result.root, "notes.theirs.txt").await,
+            Some(b"theirs".to_vec())
+        );
+        // Modify/delete keeps the surviving side
+        assert_eq!(
+            read(&tree, &result.root, "old").await,
+            Some(b"edited".to_vec())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_custom_resolver_and_fast_paths() {
+        struct ByPath;
+        impl ConflictResolver for ByPath {
+            fn resolve(&self, path: &str, _kind: ConflictKind) -> Resolution {
+                if path.starts_with("mine/") {
+                    Resolution::Ours
+                } else {
+                    Resolution::Theirs
+                }
+            }
+        }
+
+        let tree = make_tree();
+        let ours = root(
+            &tree,
+            vec![
+                dir(&tree, "mine", vec![file(&tree, "f", b"o").await]).await,
+                file(&tree, "g", b"o").await,
+            ],
+        )
+        .await;
+        let theirs = root(
+            &tree,
+            vec![
+                dir(&tree, "mine", vec![file(&tree, "f", b"t").await]).await,
+                file(&tree, "g", b"t").await,
+            ],
+        )
+        .await;
+
+        // No base: both sides added different content
+        let result = merge_trees(&tree, None, &ours, &theirs, &ByPath)
+            .await
+            .unwrap();
+        assert!(result
+            .conflicts
+            .iter()
+            .all(|c| c.kind == ConflictKind::BothAdded));
+        assert_eq!(
+            read(&tree, &result.root, "mine/f").await,
+            Some(b"o".to_vec())
+        );
+        assert_eq!(read(&tree, &result.root, "g").await, Some(b"t".to_vec()));
+
+        // One side unchanged from base takes the other side's root as-is
+        let result = merge_trees(&tree, Some(&ours), &ours, &theirs, &ByPath)
+            .await
+            .unwrap();
+        assert_eq!(result.root, theirs);
+        assert!(!result.has_conflicts());
+    }
+
+    #[tokio::test]
+    async fn test_merge_recurses_into_blob_linked_directories() {
+        let tree = make_tree();
+        // Subdirectories linked without a link type, as `from_cid` leaves them
+        async fn blob_dir(
+            tree: &HashTree<MemoryStore>,
+            name: &str,
+            entries: Vec<DirEntry>,
+        ) -> DirEntry {
+            let cid = tree.put_directory(entries).await.unwrap();
+            DirEntry::from_cid(name, &cid)
+        }
+
+        let base = root(
+            &tree,
+            vec![blob_dir(&tree, "src", vec![file(&tree, "x", b"x").await]).await],
+        )
+        .await;
+        let ours = root(
+            &tree,
+            vec![blob_dir(&tree, "src", vec![file(&tree, "x", b"x2").await]).await],
+        )
+        .await;
+        let theirs = root(
+            &tree,
+            vec![
+                blob_dir(
+                    &tree,
+                    "src",
+                    vec![file(&tree, "x", b"x").await, file(&tree, "y", b"y").await],
+                )
+                .await,
+            ],
+        )
+        .await;
+
+        let result = merge_trees(&tree, Some(&base), &ours, &theirs, &MergeStrategy::Ours)
+            .await
+            .unwrap();
+
+        assert!(!result.has_conflicts());
+        assert_eq!(
+            read(&tree, &result.root, "src/x").await,
+            Some(b"x2".to_vec())
+        );
+        assert_eq!(
+            read(&tree, &result.root, "src/y").await,
+            Some(b"y".to_vec())
+        );
+    }
+}
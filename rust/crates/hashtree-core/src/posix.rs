@@ -0,0 +1,267 @@
+//! POSIX metadata profile for directory entries
+//!
+//! File mode, modification time and symlink target are stored in `Link.meta`
+//! under fixed keys, so readers that don't know the profile still see plain
+//! entries. Meta keys are sorted by the codec, which keeps the encoding
+//! deterministic.
+//!
+//! - `mode`: permission bits (including setuid/setgid/sticky), masked to `0o7777`
+//! - `mtime`: modification time in whole seconds since the Unix epoch
+//! - `symlink`: link target; the entry is a Blob holding the target bytes
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::reader::{TreeEntry, WalkEntry};
+use crate::types::{DirEntry, Link};
+
+/// Meta key for permission bits
+pub const META_MODE: &str = "mode";
+
+/// Meta key for modification time (seconds since the Unix epoch)
+pub const META_MTIME: &str = "mtime";
+
+/// Meta key for a symlink target
+pub const META_SYMLINK: &str = "symlink";
+
+/// Bits of `st_mode` kept in the `mode` key
+pub const MODE_MASK: u32 = 0o7777;
+
+/// POSIX attributes of a directory entry
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PosixMeta {
+    /// Permission bits, masked to `MODE_MASK`
+    pub mode: Option<u32>,
+    /// Modification time in seconds since the Unix epoch
+    pub mtime: Option<i64>,
+    /// Target path if the entry is a symlink
+    pub symlink: Option<String>,
+}
+
+impl PosixMeta {
+    /// Read the profile keys from entry metadata, ignoring malformed values
+    pub fn from_meta(meta: Option<&HashMap<String, Value>>) -> Self {
+        let Some(meta) = meta else {
+            return Self::default();
+        };
+        Self {
+            mode: meta
+                .get(META_MODE)
+                .and_then(Value::as_u64)
+                .and_then(|m| u32::try_from(m).ok())
+                .map(|m| m & MODE_MASK),
+            mtime: meta.get(META_MTIME).and_then(Value::as_i64),
+            symlink: meta
+                .get(META_SYMLINK)
+                .and_then(Value::as_str)
+                .map(str::to_string),
+        }
+    }
+
+    pub fn with_mode(mut self, mode: u32) -> Self {
+        self.mode = Some(mode & MODE_MASK);
+        self
+    }
+
+    pub fn with_mtime(mut self, mtime: i64) -> Self {
+        self.mtime = Some(mtime);
+        self
+    }
+
+    pub fn with_symlink(mut self, target: impl Into<String>) -> Self {
+        self.symlink = Some(target.into());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mode.is_none() && self.mtime.is_none() && self.symlink.is_none()
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.symlink.is_some()
+    }
+
+    /// Write the profile keys into `meta`, removing keys that are unset
+    pub fn apply_to(&self, meta: &mut HashMap<String, Value>) {
+        set_or_remove(meta, META_MODE, self.mode.map(Value::from));
+        set_or_remove(meta, META_MTIME, self.mtime.map(Value::from));
+        set_or_remove(meta, META_SYMLINK, self.symlink.clone().map(Value::from));
+    }
+
+    /// Capture mode, mtime and symlink target without following symlinks
+    #[cfg(unix)]
+    pub fn from_path(path: &std::path::Path) -> std::io::Result<Self> {
+        use std::os::unix::fs::MetadataExt;
+
+        let metadata = std::fs::symlink_metadata(path)?;
+        let mut posix = Self::default().with_mtime(metadata.mtime());
+        if metadata.file_type().is_symlink() {
+            let target = std::fs::read_link(path)?;
+            posix.symlink = Some(target.to_string_lossy().into_owned());
+        } else {
+            posix.mode = Some(metadata.mode() & MODE_MASK);
+        }
+        Ok(posix)
+    }
+
+    /// Apply mode and mtime to an existing file or directory
+    ///
+    /// Symlinks are left alone (their own mode is not meaningful on most
+    /// systems). Directories should be restored after their contents, since
+    /// writing children bumps the mtime and a read-only mode blocks writes.
+    #[cfg(unix)]
+    pub fn restore(&self, path: &std::path::Path) -> std::io::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        if self.is_symlink() {
+            return Ok(());
+        }
+        if let Some(mtime) = self.mtime {
+            let time = if mtime >= 0 {
+                std::time::UNIX_EPOCH + std::time::Duration::from_secs(mtime as u64)
+            } else {
+                std::time::UNIX_EPOCH - std::time::Duration::from_secs(mtime.unsigned_abs())
+            };
+            std::fs::File::open(path)?.set_modified(time)?;
+        }
+        if let Some(mode) = self.mode {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+        }
+        Ok(())
+    }
+}
+
+fn set_or_remove(meta: &mut HashMap<String, Value>, key: &str, value: Option<Value>) {
+    match value {
+        Some(value) => {
+            meta.insert(key.to_string(), value);
+        }
+        None => {
+            meta.remove(key);
+        }
+    }
+}
+
+impl DirEntry {
+    /// Merge POSIX attributes into this entry's metadata
+    pub fn with_posix(mut self, posix: &PosixMeta) -> Self {
+        if posix.is_empty() {
+            return self;
+        }
+        let mut meta = self.meta.take().unwrap_or_default();
+        posix.apply_to(&mut meta);
+        self.meta = Some(meta);
+        self
+    }
+}
+
+impl Link {
+    /// POSIX attributes stored in this link's metadata
+    pub fn posix(&self) -> PosixMeta {
+        PosixMeta::from_meta(self.meta.as_ref())
+    }
+}
+
+impl TreeEntry {
+    /// POSIX attributes stored in this entry's metadata
+    pub fn posix(&self) -> PosixMeta {
+        PosixMeta::from_meta(self.meta.as_ref())
+    }
+}
+
+impl WalkEntry {
+    /// POSIX attributes stored in the metadata of the link to this entry
+    pub fn posix(&self) -> PosixMeta {
+        PosixMeta::from_meta(self.meta.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::{decode_tree_node, encode_and_hash};
+    use crate::types::{LinkType, TreeNode};
+
+    #[test]
+    fn test_meta_roundtrip_keeps_other_keys() {
+        let posix = PosixMeta::default()
+            .with_mode(0o100755)
+            .with_mtime(1_700_000_000)
+            .with_symlink("../target");
+        let mut other = HashMap::new();
+        other.insert("mimeType".to_string(), Value::from("text/plain"));
+
+        let entry = DirEntry::new("a", [1u8; 32])
+            .with_meta(other)
+            .with_posix(&posix);
+        let meta = entry.meta.unwrap();
+
+        assert_eq!(meta["mimeType"], Value::from("text/plain"));
+        assert_eq!(meta[META_MODE], Value::from(0o755));
+        assert_eq!(PosixMeta::from_meta(Some(&meta)), posix.with_mode(0o755));
+    }
+
+    #[test]
+    fn test_empty_posix_leaves_meta_unset() {
+        let entry = DirEntry::new("a", [1u8; 32]).with_posix(&PosixMeta::default());
+        assert!(entry.meta.is_none());
+        assert!(PosixMeta::from_meta(None).is_empty());
+    }
+
+    #[test]
+    fn test_encoding_is_deterministic() {
+        let posix = PosixMeta::default().with_mode(0o644).with_mtime(42);
+        let link = |posix: &PosixMeta| {
+            let mut meta = HashMap::new();
+            posix.apply_to(&mut meta);
+            Link {
+                hash: [2u8; 32],
+                name: Some("f".to_string()),
+                size: 3,
+                key: None,
+                link_type: LinkType::Blob,
+                meta: Some(meta),
+            }
+        };
+        let (bytes_a, hash_a) =
+            encode_and_hash(&TreeNode::new(LinkType::Dir, vec![link(&posix)])).unwrap();
+        let (_, hash_b) =
+            encode_and_hash(&TreeNode::new(LinkType::Dir, vec![link(&posix)])).unwrap();
+        assert_eq!(hash_a, hash_b);
+
+        let node = decode_tree_node(&bytes_a).unwrap();
+        assert_eq!(node.links[0].posix(), posix);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_capture_and_restore() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("hashtree-posix-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("file");
+        std::fs::write(&file, b"data").unwrap();
+        std::os::unix::fs::symlink("file", dir.join("link")).unwrap();
+
+        let link = PosixMeta::from_path(&dir.join("link")).unwrap();
+        assert_eq!(link.symlink.as_deref(), Some("file"));
+        assert!(link.mode.is_none());
+
+        PosixMeta::default()
+            .with_mode(0o640)
+            .with_mtime(1_000_000)
+            .restore(&file)
+            .unwrap();
+        let captured = PosixMeta::from_path(&file).unwrap();
+        assert_eq!(captured.mode, Some(0o640));
+        assert_eq!(captured.mtime, Some(1_000_000));
+        assert_eq!(
+            std::fs::metadata(&file).unwrap().permissions().mode() & MODE_MASK,
+            0o640
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,486 @@
+//! Merkle inclusion proofs for byte ranges of a file
+//!
+//! A `RangeProof` holds the stored blocks on the paths from a file's root to
+//! the chunks covering a byte range: every tree node along the way, then the
+//! covering chunks themselves, in depth-first order. Sibling subtrees are
+//! skipped using the sizes in their parent links, so a proof grows with the
+//! tree depth and the range, not the file.
+//!
+//! Blocks of encrypted files carry their CHK key. Because a CHK key is the
+//! SHA-256 of the plaintext, `verify_range_proof` can check those keys too,
+//! and verifies against a bare root hash as well as a full nhash.
+
+use crate::codec::{decode_tree_node, try_decode_tree_node};
+use crate::crypto::{content_hash, decrypt_chk, EncryptionKey};
+use crate::hash::sha256;
+use crate::hashtree::{HashTree, HashTreeError};
+use crate::store::Store;
+use crate::types::{to_hex, Cid, Hash, LinkType, TreeNode};
+
+/// First bytes of an encoded proof
+const PROOF_MAGIC: &[u8; 7] = b"HTPROOF";
+
+/// Encoding version written by `RangeProof::encode`
+const PROOF_VERSION: u8 = 1;
+
+/// Range proof error type
+#[derive(Debug, thiserror::Error)]
+pub enum ProofError {
+    #[error(transparent)]
+    Tree(#[from] HashTreeError),
+    #[error("Not a file")]
+    NotAFile,
+    #[error("Hash mismatch: expected {expected}, got {actual}")]
+    HashMismatch { expected: String, actual: String },
+    #[error("Proof is missing block {0}")]
+    MissingBlock(String),
+    #[error("Key does not match block {0}")]
+    KeyMismatch(String),
+    #[error("Invalid proof: {0}")]
+    InvalidProof(String),
+}
+
+/// A stored block included in a proof
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofBlock {
+    /// Block bytes as stored (ciphertext for encrypted files)
+    pub data: Vec<u8>,
+    /// CHK key of the block, for encrypted files
+    pub key: Option<EncryptionKey>,
+}
+
+/// Proof that `start..end` of a file belongs to its root
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RangeProof {
+    pub start: u64,
+    /// Exclusive end, clamped to the file size
+    pub end: u64,
+    /// Total file size
+    pub size: u64,
+    /// Root block first, then nodes and chunks in depth-first order
+    pub blocks: Vec<ProofBlock>,
+}
+
+/// Build a proof for `start..end` of the file at `cid`
+///
+/// `end` defaults to (and is clamped to) the file size. Returns `None` if the
+/// root block is not in the store.
+pub async fn prove_range<S: Store>(
+    tree: &HashTree<S>,
+    cid: &Cid,
+    start: u64,
+    end: Option<u64>,
+) -> Result<Option<RangeProof>, ProofError> {
+    let store = tree.get_store();
+    let Some(data) = store
+        .get(&cid.hash)
+        .await
+        .map_err(|e| HashTreeError::Store(e.to_string()))?
+    else {
+        return Ok(None);
+    };
+    let plain = open_block(&data, cid.key.as_ref())?;
+    let mut blocks = vec![ProofBlock { data, key: cid.key }];
+
+    let node = match try_decode_tree_node(&plain) {
+        Some(node) if node.node_type == LinkType::Dir => return Err(ProofError::NotAFile),
+        node => node,
+    };
+    let size = match &node {
+        Some(node) => node.links.iter().map(|link| link.size).sum(),
+        None => plain.len() as u64,
+    };
+    let end = end.unwrap_or(size).min(size);
+    let start = start.min(end);
+
+    if let Some(node) = node {
+        prove_node(&*store, &node, 0, start, end, &mut blocks).await?;
+    }
+    Ok(Some(RangeProof {
+        start,
+        end,
+        size,
+        blocks,
+    }))
+}
+
+async fn prove_node<S: Store>(
+    store: &S,
+    node: &TreeNode,
+    mut offset: u64,
+    start: u64,
+    end: u64,
+    blocks: &mut Vec<ProofBlock>,
+) -> Result<(), ProofError> {
+    for link in &node.links {
+        let link_end = offset + link.size;
+        if link_end > start && offset < end {
+            let data = store
+                .get(&link.hash)
+                .await
+                .map_err(|e| HashTreeError::Store(e.to_string()))?
+                .ok_or_else(|| HashTreeError::MissingChunk(to_hex(&link.hash)))?;
+            let child = if link.link_type == LinkType::Blob {
+                None
+            } else {
+                let plain = open_block(&data, link.key.as_ref())?;
+                Some(decode_tree_node(&plain).map_err(HashTreeError::from)?)
+            };
+            blocks.push(ProofBlock {
+                data,
+                key: link.key,
+            });
+            if let Some(child) = child {
+                Box::pin(prove_node(store, &child, offset, start, end, blocks)).await?;
+            }
+        }
+        offset = link_end;
+        if offset >= end {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn open_block(data: &[u8], key: Option<&EncryptionKey>) -> Result<Vec<u8>, HashTreeError> {
+    match key {
+        Some(key) => decrypt_chk(data, key).map_err(|e| HashTreeError::Decryption(e.to_string())),
+        None => Ok(data.to_vec()),
+    }
+}
+
+/// Check `proof` against `root` and return the proven bytes
+///
+/// `root.key` may be omitted for encrypted files when the proof carries the
+/// root block's key.
+pub fn verify_range_proof(root: &Cid, proof: &RangeProof) -> Result<Vec<u8>, ProofError> {
+    if proof.start > proof.end || proof.end > proof.size {
+        return Err(ProofError::InvalidProof("range out of bounds".to_string()));
+    }
+
+    let mut verifier = Verifier {
+        blocks: proof.blocks.iter(),
+        start: proof.start,
+        end: proof.end,
+        out: Vec::with_capacity((proof.end - proof.start).min(1 << 20) as usize),
+    };
+    let plain = verifier.take(&root.hash, root.key.as_ref())?;
+    match try_decode_tree_node(&plain) {
+        Some(node) if node.node_type == LinkType::Dir => return Err(ProofError::NotAFile),
+        Some(node) => verifier.node(&node, 0, proof.size)?,
+        None => verifier.chunk(&plain, 0, proof.size)?,
+    }
+
+    if verifier.blocks.next().is_some() {
+        return Err(ProofError::InvalidProof(
+            "unexpected extra blocks".to_string(),
+        ));
+    }
+    Ok(verifier.out)
+}
+
+/// Replays `prove_node`, consuming proof blocks instead of reading a store
+struct Verifier<'a> {
+    blocks: std::slice::Iter<'a, ProofBlock>,
+    start: u64,
+    end: u64,
+    out: Vec<u8>,
+}
+
+impl Verifier<'_> {
+    /// Next block, checked against the hash (and key) its parent names
+    fn take(&mut self, hash: &Hash, key: Option<&EncryptionKey>) -> Result<Vec<u8>, ProofError> {
+        let block = self
+            .blocks
+            .next()
+            .ok_or_else(|| ProofError::MissingBlock(to_hex(hash)))?;
+        let actual = sha256(&block.data);
+        if actual != *hash {
+            return Err(ProofError::HashMismatch {
+                expected: to_hex(hash),
+                actual: to_hex(&actual),
+            });
+        }
+
+        let key = match (key, block.key.as_ref()) {
+            (Some(expected), Some(given)) if expected != given => {
+                return Err(ProofError::KeyMismatch(to_hex(hash)))
+            }
+            (Some(key), _) | (None, Some(key)) => key,
+            (None, None) => return Ok(block.data.clone()),
+        };
+        let plain =
+            decrypt_chk(&block.data, key).map_err(|_| ProofError::KeyMismatch(to_hex(hash)))?;
+        if content_hash(&plain) != *key {
+            return Err(ProofError::KeyMismatch(to_hex(hash)));
+        }
+        Ok(plain)
+    }
+
+    fn node(&mut self, node: &TreeNode, mut offset: u64, size: u64) -> Result<(), ProofError> {
+        let total = node
+            .links
+            .iter()
+            .try_fold(0u64, |total, link| total.checked_add(link.size));
+        if total != Some(size) {
+            return Err(ProofError::InvalidProof(
+                "link sizes don't add up".to_string(),
+            ));
+        }
+
+        for link in &node.links {
+            let link_end = offset + link.size;
+            if link_end > self.start && offset < self.end {
+                let plain = self.take(&link.hash, link.key.as_ref())?;
+                if link.link_type == LinkType::Blob {
+                    self.chunk(&plain, offset, link.size)?;
+                } else {
+                    let child = decode_tree_node(&plain)
+                        .map_err(|e| ProofError::InvalidProof(e.to_string()))?;
+                    if child.node_type != LinkType::File {
+                        return Err(ProofError::NotAFile);
+                    }
+                    self.node(&child, offset, link.size)?;
+                }
+            }
+            offset = link_end;
+            if offset >= self.end {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Append the part of a chunk at `offset` that falls in the range
+    fn chunk(&mut self, plain: &[u8], offset: u64, size: u64) -> Result<(), ProofError> {
+        if plain.len() as u64 != size {
+            return Err(ProofError::InvalidProof(format!(
+                "chunk at {} is {} bytes, expected {}",
+                offset,
+                plain.len(),
+                size
+            )));
+        }
+        let from = self.start.saturating_sub(offset).min(size) as usize;
+        let to = self.end.saturating_sub(offset).min(size) as usize;
+        if from < to {
+            self.out.extend_from_slice(&plain[from..to]);
+        }
+        Ok(())
+    }
+}
+
+impl RangeProof {
+    /// Binary encoding for transport; integers are little-endian:
+    ///
+    /// ```text
+    /// "HTPROOF" version:u8 start:u64 end:u64 size:u64 count:u32
+    /// count × (has_key:u8 [key:32] len:u32 data:len)
+    /// ```
+    pub fn encode(&self) -> Vec<u8> {
+        let data_len: usize = self.blocks.iter().map(|b| 37 + b.data.len()).sum();
+        let mut out = Vec::with_capacity(36 + data_len);
+        out.extend(PROOF_MAGIC);
+        out.push(PROOF_VERSION);
+        out.extend(self.start.to_le_bytes());
+        out.extend(self.end.to_le_bytes());
+        out.extend(self.size.to_le_bytes());
+        out.extend((self.blocks.len() as u32).to_le_bytes());
+        for block in &self.blocks {
+            match &block.key {
+                Some(key) => {
+                    out.push(1);
+                    out.extend(key);
+                }
+                None => out.push(0),
+            }
+            out.extend((block.data.len() as u32).to_le_bytes());
+            out.extend(&block.data);
+        }
+        out
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, ProofError> {
+        let mut reader = SliceReader { data };
+        if reader.take(PROOF_MAGIC.len())? != PROOF_MAGIC {
+            return Err(ProofError::InvalidProof("bad magic".to_string()));
+        }
+        let version = reader.take(1)?[0];
+        if version != PROOF_VERSION {
+            return Err(ProofError::InvalidProof(format!(
+                "unsupported version {}",
+                version
+            )));
+        }
+        let start = reader.u64()?;
+        let end = reader.u64()?;
+        let size = reader.u64()?;
+        let count = reader.u32()?;
+
+        let mut blocks = Vec::new();
+        for _ in 0..count {
+            let key = match reader.take(1)?[0] {
+                0 => None,
+                1 => Some(reader.take(32)?.try_into().unwrap()),
+                other => return Err(ProofError::InvalidProof(format!("bad key flag {}", other))),
+            };
+            let len = reader.u32()? as usize;
+            let data = reader.take(len)?.to_vec();
+            blocks.push(ProofBlock { data, key });
+        }
+        if !reader.data.is_empty() {
+            return Err(ProofError::InvalidProof("trailing bytes".to_string()));
+        }
+
+        Ok(Self {
+            start,
+            end,
+            size,
+            blocks,
+        })
+    }
+}
+
+struct SliceReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> SliceReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ProofError> {
+        if self.data.len() < len {
+            return Err(ProofError::InvalidProof("truncated".to_string()));
+        }
+        let (head, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32, ProofError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, ProofError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashtree::HashTreeConfig;
+    use crate::store::MemoryStore;
+    use std::sync::Arc;
+
+    fn sample_data() -> Vec<u8> {
+        (0..10_000u32).map(|i| (i * 7 % 251) as u8).collect()
+    }
+
+    /// Three levels of nodes: 100-byte chunks, at most 4 links per node
+    fn sample_tree(encrypted: bool) -> HashTree<MemoryStore> {
+        let config = HashTreeConfig::new(Arc::new(MemoryStore::new()))
+            .with_chunk_size(100)
+            .with_max_links(4);
+        HashTree::new(if encrypted { config } else { config.public() })
+    }
+
+    #[tokio::test]
+    async fn test_prove_and_verify_ranges() {
+        let data = sample_data();
+        for encrypted in [false, true] {
+            let tree = sample_tree(encrypted);
+            let (cid, _) = tree.put(&data).await.unwrap();
+            let block_count = tree.get_store().list().await.unwrap().len();
+
+            for (start, end) in [(0, 1), (150, 420), (9_950, 10_000), (5_000, 5_000)] {
+                let proof = prove_range(&tree, &cid, start, Some(end))
+                    .await
+                    .unwrap()
+                    .unwrap();
+                assert!(proof.blocks.len() < block_count / 4);
+                let proven = verify_range_proof(&cid, &proof).unwrap();
+                assert_eq!(proven, &data[start as usize..end as usize]);
+
+                let decoded = RangeProof::decode(&proof.encode()).unwrap();
+                assert_eq!(decoded, proof);
+            }
+
+            // Open-ended ranges are clamped to the file
+            let proof = prove_range(&tree, &cid, 9_990, Some(20_000))
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!((proof.end, proof.size), (10_000, 10_000));
+            assert_eq!(verify_range_proof(&cid, &proof).unwrap(), &data[9_990..]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verify_encrypted_against_bare_hash() {
+        let tree = sample_tree(true);
+        let (cid, _) = tree.put(&sample_data()).await.unwrap();
+        let proof = prove_range(&tree, &cid, 300, Some(700))
+            .await
+            .unwrap()
+            .unwrap();
+
+        let bare = Cid::public(cid.hash);
+        assert_eq!(verify_range_proof(&bare, &proof).unwrap().len(), 400);
+
+        // A wrong key for the root is caught even without the nhash key
+        let mut forged = proof.clone();
+        forged.blocks[0].key = Some([7u8; 32]);
+        assert!(matches!(
+            verify_range_proof(&bare, &forged),
+            Err(ProofError::KeyMismatch(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_single_chunk_file() {
+        let tree = sample_tree(false);
+        let (cid, _) = tree.put(b"hello world").await.unwrap();
+        let proof = prove_range(&tree, &cid, 6, None).await.unwrap().unwrap();
+        assert_eq!(proof.blocks.len(), 1);
+        assert_eq!(verify_range_proof(&cid, &proof).unwrap(), b"world");
+    }
+
+    #[tokio::test]
+    async fn test_rejects_tampered_proofs() {
+        let data = sample_data();
+        let tree = sample_tree(false);
+        let (cid, _) = tree.put(&data).await.unwrap();
+        let proof = prove_range(&tree, &cid, 150, Some(420))
+            .await
+            .unwrap()
+            .unwrap();
+
+        let mut changed = proof.clone();
+        let last = changed.blocks.last_mut().unwrap();
+        last.data[0] ^= 1;
+        assert!(matches!(
+            verify_range_proof(&cid, &changed),
+            Err(ProofError::HashMismatch { .. })
+        ));
+
+        let mut short = proof.clone();
+        short.blocks.pop();
+        assert!(matches!(
+            verify_range_proof(&cid, &short),
+            Err(ProofError::MissingBlock(_))
+        ));
+
+        // Claiming a different range than the blocks cover
+        let mut shifted = proof.clone();
+        shifted.start = 0;
+        assert!(verify_range_proof(&cid, &shifted).is_err());
+
+        let (other, _) = tree.put(b"other").await.unwrap();
+        assert!(matches!(
+            verify_range_proof(&other, &proof),
+            Err(ProofError::HashMismatch { .. })
+        ));
+
+        let encoded = proof.encode();
+        assert!(RangeProof::decode(&encoded[..encoded.len() - 1]).is_err());
+    }
+}
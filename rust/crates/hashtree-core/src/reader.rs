@@ -35,6 +35,8 @@ pub struct WalkEntry {
     pub size: u64,
     /// Optional decryption key (for encrypted content)
     pub key: Option<[u8; 32]>,
+    /// Metadata from the link pointing here (None for the walk root)
+    pub meta: Option<HashMap<String, serde_json::Value>>,
 }
 
 /// TreeReader - reads and traverses merkle trees
@@ -571,7 +573,7 @@ impl<S: Store> TreeReader<S> {
     /// Walk entire tree depth-first
     pub async fn walk(&self, hash: &Hash, path: &str) -> Result<Vec<WalkEntry>, ReaderError> {
         let mut entries = Vec::new();
-        self.walk_recursive(hash, path, &mut entries).await?;
+        self.walk_recursive(hash, path, None, &mut entries).await?;
         Ok(entries)
     }
 
@@ -579,6 +581,7 @@ impl<S: Store> TreeReader<S> {
         &self,
         hash: &Hash,
         path: &str,
+        meta: Option<HashMap<String, serde_json::Value>>,
         entries: &mut Vec<WalkEntry>,
     ) -> Result<(), ReaderError> {
         let data = match self
@@ -600,6 +603,7 @@ impl<S: Store> TreeReader<S> {
                     link_type: LinkType::Blob,
                     size: data.len() as u64,
                     key: None, // TreeReader doesn't track keys
+                    meta,
                 });
                 return Ok(());
             }
@@ -612,6 +616,7 @@ impl<S: Store> TreeReader<S> {
             link_type: node.node_type,
             size: node_size,
             key: None, // directories are not encrypted
+            meta,
         });
 
         for link in &node.links {
@@ -619,7 +624,7 @@ impl<S: Store> TreeReader<S> {
                 Some(name) => {
                     // Skip internal chunk nodes in path
                     if name.starts_with("_chunk_") || name.starts_with('_') {
-                        Box::pin(self.walk_recursive(&link.hash, path, entries)).await?;
+                        Box::pin(self.walk_recursive(&link.hash, path, None, entries)).await?;
                         continue;
                     }
                     if path.is_empty() {
@@ -631,7 +636,8 @@ impl<S: Store> TreeReader<S> {
                 None => path.to_string(),
             };
 
-            Box::pin(self.walk_recursive(&link.hash, &child_path, entries)).await?;
+            Box::pin(self.walk_recursive(&link.hash, &child_path, link.meta.clone(), entries))
+                .await?;
         }
 
         Ok(())
@@ -1,11 +1,15 @@
 //! Content-addressed key-value store interfaces and implementations
 
 use async_trait::async_trait;
+use futures::stream::{self, StreamExt, TryStreamExt};
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 
 use crate::types::{to_hex, Hash};
 
+/// Requests in flight for the default `get_many`/`has_many`
+pub const BATCH_CONCURRENCY: usize = 16;
+
 /// Storage statistics
 #[derive(Debug, Clone, Default)]
 pub struct StoreStats {
@@ -37,6 +41,48 @@ pub trait Store: Send + Sync {
     /// Returns true if deleted, false if didn't exist
     async fn delete(&self, hash: &Hash) -> Result<bool, StoreError>;
 
+    // ========================================================================
+    // Optional: Batch operations (default: one call per hash)
+    // ========================================================================
+
+    /// Store several items
+    /// Returns, in order, whether each was newly stored
+    async fn put_many(&self, items: Vec<(Hash, Vec<u8>)>) -> Result<Vec<bool>, StoreError> {
+        let mut stored = Vec::with_capacity(items.len());
+        for (hash, data) in items {
+            stored.push(self.put(hash, data).await?);
+        }
+        Ok(stored)
+    }
+
+    /// Retrieve several hashes
+    /// Returns data in the same order, None where not found
+    async fn get_many(&self, hashes: &[Hash]) -> Result<Vec<Option<Vec<u8>>>, StoreError> {
+        stream::iter(hashes.iter().copied())
+            .map(|hash| async move { self.get(&hash).await })
+            .buffered(BATCH_CONCURRENCY)
+            .try_collect()
+            .await
+    }
+
+    /// Check several hashes
+    /// Returns existence in the same order
+    async fn has_many(&self, hashes: &[Hash]) -> Result<Vec<bool>, StoreError> {
+        stream::iter(hashes.iter().copied())
+            .map(|hash| async move { self.has(&hash).await })
+            .buffered(BATCH_CONCURRENCY)
+            .try_collect()
+            .await
+    }
+
+    /// List all stored hashes
+    /// Stores that can't enumerate their contents return an error
+    async fn list(&self) -> Result<Vec<Hash>, StoreError> {
+        Err(StoreError::Other(
+            "listing is not supported by this store".to_string(),
+        ))
+    }
+
     // ========================================================================
     // Optional: Storage limits and eviction (default no-op implementations)
     // ========================================================================
@@ -92,6 +138,8 @@ pub enum StoreError {
     Io(#[from] std::io::Error),
     #[error("Store error: {0}")]
     Other(String),
+    #[error("Hash mismatch: expected {}, got {}", to_hex(expected), to_hex(actual))]
+    HashMismatch { expected: Hash, actual: Hash },
 }
 
 /// Entry in the memory store with metadata for LRU
@@ -241,6 +289,43 @@ impl Store for MemoryStore {
         Ok(inner.data.remove(&key).is_some())
     }
 
+    async fn put_many(&self, items: Vec<(Hash, Vec<u8>)>) -> Result<Vec<bool>, StoreError> {
+        let mut inner = self.inner.write().unwrap();
+        let mut stored = Vec::with_capacity(items.len());
+        for (hash, data) in items {
+            let key = to_hex(&hash);
+            if inner.data.contains_key(&key) {
+                stored.push(false);
+                continue;
+            }
+            let order = inner.next_order;
+            inner.next_order += 1;
+            inner.data.insert(key, MemoryEntry { data, order });
+            stored.push(true);
+        }
+        Ok(stored)
+    }
+
+    async fn get_many(&self, hashes: &[Hash]) -> Result<Vec<Option<Vec<u8>>>, StoreError> {
+        let inner = self.inner.read().unwrap();
+        Ok(hashes
+            .iter()
+            .map(|hash| inner.data.get(&to_hex(hash)).map(|e| e.data.clone()))
+            .collect())
+    }
+
+    async fn has_many(&self, hashes: &[Hash]) -> Result<Vec<bool>, StoreError> {
+        let inner = self.inner.read().unwrap();
+        Ok(hashes
+            .iter()
+            .map(|hash| inner.data.contains_key(&to_hex(hash)))
+            .collect())
+    }
+
+    async fn list(&self) -> Result<Vec<Hash>, StoreError> {
+        Ok(self.keys())
+    }
+
     fn set_max_bytes(&self, max: u64) {
         self.inner.write().unwrap().max_bytes = if max > 0 { Some(max) } else { None };
     }
@@ -417,6 +502,62 @@ mod tests {
         assert!(!result);
     }
 
+    /// Store that only implements the required methods, to exercise defaults
+    struct MinimalStore(MemoryStore);
+
+    #[async_trait]
+    impl Store for MinimalStore {
+        async fn put(&self, hash: Hash, data: Vec<u8>) -> Result<bool, StoreError> {
+            self.0.put(hash, data).await
+        }
+        async fn get(&self, hash: &Hash) -> Result<Option<Vec<u8>>, StoreError> {
+            self.0.get(hash).await
+        }
+        async fn has(&self, hash: &Hash) -> Result<bool, StoreError> {
+            self.0.has(hash).await
+        }
+        async fn delete(&self, hash: &Hash) -> Result<bool, StoreError> {
+            self.0.delete(hash).await
+        }
+    }
+
+    async fn check_batch_ops(store: &dyn Store) {
+        let items: Vec<(Hash, Vec<u8>)> = (0u8..40).map(|i| (sha256(&[i]), vec![i])).collect();
+        let hashes: Vec<Hash> = items.iter().map(|(h, _)| *h).collect();
+
+        store.put(hashes[3], vec![3]).await.unwrap();
+        let stored = store.put_many(items).await.unwrap();
+        assert_eq!(stored.len(), 40);
+        assert!(!stored[3]);
+        assert_eq!(stored.iter().filter(|s| **s).count(), 39);
+
+        let mut query = hashes[..5].to_vec();
+        query.push([0u8; 32]);
+        assert_eq!(
+            store.has_many(&query).await.unwrap(),
+            vec![true, true, true, true, true, false]
+        );
+        let data = store.get_many(&query).await.unwrap();
+        assert_eq!(data[4], Some(vec![4]));
+        assert_eq!(data[5], None);
+    }
+
+    #[tokio::test]
+    async fn test_batch_ops() {
+        check_batch_ops(&MemoryStore::new()).await;
+        check_batch_ops(&MinimalStore(MemoryStore::new())).await;
+    }
+
+    #[tokio::test]
+    async fn test_list() {
+        let store = MemoryStore::new();
+        let hash = sha256(b"x");
+        store.put(hash, b"x".to_vec()).await.unwrap();
+        assert_eq!(store.list().await.unwrap(), vec![hash]);
+
+        assert!(MinimalStore(store).list().await.is_err());
+    }
+
     #[tokio::test]
     async fn test_size() {
         let store = MemoryStore::new();
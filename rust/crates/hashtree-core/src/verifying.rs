@@ -0,0 +1,222 @@
+//! Store wrapper that checks content hashes
+//!
+//! Backends return whatever bytes they hold; a corrupt disk or a malicious
+//! server can hand back data that doesn't match the requested hash.
+//! `VerifyingStore` hashes every blob on `get` and `put` and turns a mismatch
+//! into `StoreError::HashMismatch` (a miss in `get_many`), so bad data never
+//! reaches the tree reader.
+
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::hash::sha256;
+use crate::store::{Store, StoreError, StoreStats};
+use crate::types::Hash;
+
+/// Check that `data` hashes to `expected`
+pub fn verify_blob(expected: &Hash, data: &[u8]) -> Result<(), StoreError> {
+    let actual = sha256(data);
+    if actual == *expected {
+        Ok(())
+    } else {
+        Err(StoreError::HashMismatch {
+            expected: *expected,
+            actual,
+        })
+    }
+}
+
+/// Store wrapper that rejects blobs whose content doesn't match their hash
+pub struct VerifyingStore<S: Store + ?Sized> {
+    inner: Arc<S>,
+    /// Delete corrupted entries from the inner store when detected
+    delete_corrupted: bool,
+    /// Number of hash mismatches seen on read or write
+    failures: AtomicU64,
+}
+
+impl<S: Store + ?Sized> VerifyingStore<S> {
+    pub fn new(inner: Arc<S>) -> Self {
+        Self {
+            inner,
+            delete_corrupted: false,
+            failures: AtomicU64::new(0),
+        }
+    }
+
+    /// Delete corrupted entries from the inner store when a read detects them,
+    /// so the next read misses cleanly and can be served from elsewhere
+    pub fn with_delete_corrupted(mut self, delete: bool) -> Self {
+        self.delete_corrupted = delete;
+        self
+    }
+
+    /// Get the wrapped store
+    pub fn inner(&self) -> &Arc<S> {
+        &self.inner
+    }
+
+    /// Number of hash mismatches detected so far
+    pub fn failures(&self) -> u64 {
+        self.failures.load(Ordering::Relaxed)
+    }
+
+    /// Verify data read for `hash`, quarantining it on mismatch
+    async fn check_read(&self, hash: &Hash, data: Vec<u8>) -> Result<Vec<u8>, StoreError> {
+        match verify_blob(hash, &data) {
+            Ok(()) => Ok(data),
+            Err(e) => {
+                self.failures.fetch_add(1, Ordering::Relaxed);
+                if self.delete_corrupted {
+                    let _ = self.inner.delete(hash).await;
+                }
+                Err(e)
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Store + ?Sized> Store for VerifyingStore<S> {
+    async fn put(&self, hash: Hash, data: Vec<u8>) -> Result<bool, StoreError> {
+        if let Err(e) = verify_blob(&hash, &data) {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+            return Err(e);
+        }
+        self.inner.put(hash, data).await
+    }
+
+    async fn get(&self, hash: &Hash) -> Result<Option<Vec<u8>>, StoreError> {
+        match self.inner.get(hash).await? {
+            Some(data) => self.check_read(hash, data).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    async fn has(&self, hash: &Hash) -> Result<bool, StoreError> {
+        self.inner.has(hash).await
+    }
+
+    async fn delete(&self, hash: &Hash) -> Result<bool, StoreError> {
+        self.inner.delete(hash).await
+    }
+
+    async fn put_many(&self, items: Vec<(Hash, Vec<u8>)>) -> Result<Vec<bool>, StoreError> {
+        for (hash, data) in &items {
+            if let Err(e) = verify_blob(hash, data) {
+                self.failures.fetch_add(1, Ordering::Relaxed);
+                return Err(e);
+            }
+        }
+        self.inner.put_many(items).await
+    }
+
+    /// Corrupt entries come back as misses (and are counted and quarantined
+    /// like in `get`), so one bad blob doesn't fail the rest of the batch
+    async fn get_many(&self, hashes: &[Hash]) -> Result<Vec<Option<Vec<u8>>>, StoreError> {
+        let mut results = Vec::with_capacity(hashes.len());
+        for (hash, data) in hashes.iter().zip(self.inner.get_many(hashes).await?) {
+            results.push(match data {
+                Some(data) => self.check_read(hash, data).await.ok(),
+                None => None,
+            });
+        }
+        Ok(results)
+    }
+
+    async fn has_many(&self, hashes: &[Hash]) -> Result<Vec<bool>, StoreError> {
+        self.inner.has_many(hashes).await
+    }
+
+    async fn list(&self) -> Result<Vec<Hash>, StoreError> {
+        self.inner.list().await
+    }
+
+    fn set_max_bytes(&self, max: u64) {
+        self.inner.set_max_bytes(max)
+    }
+
+    fn max_bytes(&self) -> Option<u64> {
+        self.inner.max_bytes()
+    }
+
+    async fn stats(&self) -> StoreStats {
+        self.inner.stats().await
+    }
+
+    async fn evict_if_needed(&self) -> Result<u64, StoreError> {
+        self.inner.evict_if_needed().await
+    }
+
+    async fn pin(&self, hash: &Hash) -> Result<(), StoreError> {
+        self.inner.pin(hash).await
+    }
+
+    async fn unpin(&self, hash: &Hash) -> Result<(), StoreError> {
+        self.inner.unpin(hash).await
+    }
+
+    fn pin_count(&self, hash: &Hash) -> u32 {
+        self.inner.pin_count(hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MemoryStore;
+
+    #[tokio::test]
+    async fn test_passes_valid_blobs() {
+        let store = VerifyingStore::new(Arc::new(MemoryStore::new()));
+        let hash = sha256(b"good");
+
+        assert!(store.put(hash, b"good".to_vec()).await.unwrap());
+        assert_eq!(store.get(&hash).await.unwrap(), Some(b"good".to_vec()));
+        assert_eq!(store.get(&sha256(b"missing")).await.unwrap(), None);
+        assert_eq!(store.failures(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_mismatched_put() {
+        let inner = Arc::new(MemoryStore::new());
+        let store = VerifyingStore::new(inner.clone());
+        let hash = sha256(b"expected");
+
+        let err = store.put(hash, b"other".to_vec()).await.unwrap_err();
+        assert!(matches!(err, StoreError::HashMismatch { expected, .. } if expected == hash));
+        assert!(!inner.has(&hash).await.unwrap());
+        assert_eq!(store.failures(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_corrupted_read_is_rejected_and_deleted() {
+        let inner = Arc::new(MemoryStore::new());
+        let good = sha256(b"good");
+        let bad = sha256(b"original");
+        inner.put(good, b"good".to_vec()).await.unwrap();
+        inner.put(bad, b"corrupted".to_vec()).await.unwrap();
+
+        // Without quarantine the bad entry stays in place
+        let store = VerifyingStore::new(inner.clone());
+        assert!(matches!(
+            store.get(&bad).await,
+            Err(StoreError::HashMismatch { actual, .. }) if actual == sha256(b"corrupted")
+        ));
+        assert!(inner.has(&bad).await.unwrap());
+
+        let store = VerifyingStore::new(inner.clone()).with_delete_corrupted(true);
+        // The rest of a batch still comes back
+        assert_eq!(
+            store.get_many(&[good, bad]).await.unwrap(),
+            vec![Some(b"good".to_vec()), None]
+        );
+        assert_eq!(store.failures(), 1);
+        assert!(!inner.has(&bad).await.unwrap());
+        assert!(inner.has(&good).await.unwrap());
+
+        // A later read misses cleanly
+        assert_eq!(store.get(&bad).await.unwrap(), None);
+    }
+}
@@ -4,8 +4,8 @@
 //! hashes and MessagePack encodings as the TypeScript implementation.
 
 use hashtree_core::{
-    encode_tree_node, from_hex, sha256, to_hex, HashTree, HashTreeConfig, Link, LinkType,
-    MemoryStore, TreeNode,
+    encode_tree_node, from_hex, sha256, to_hex, CdcParams, ChunkingMode, HashTree, HashTreeConfig,
+    Link, LinkType, MemoryStore, TreeNode,
 };
 use serde::Deserialize;
 use std::collections::HashMap;
@@ -26,6 +26,15 @@ struct TestInput {
     node: Option<NodeInput>,
     #[allow(dead_code)]
     entries: Option<Vec<EntryInput>>,
+    chunking: Option<ChunkingInput>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChunkingInput {
+    min_size: usize,
+    avg_size: usize,
+    max_size: usize,
 }
 
 #[derive(Debug, Deserialize)]
@@ -57,11 +66,13 @@ struct EntryInput {
 }
 
 #[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct TestExpected {
     hash: String,
     msgpack: Option<String>,
     ciphertext: Option<String>,
     size: Option<u64>,
+    chunk_sizes: Option<Vec<usize>>,
 }
 
 fn load_vectors() -> Vec<TestVector> {
@@ -69,6 +80,13 @@ fn load_vectors() -> Vec<TestVector> {
     serde_json::from_str(json).expect("Failed to parse test vectors")
 }
 
+/// Content-defined chunking vectors; Rust-only, so kept out of the generated
+/// interop file
+fn load_cdc_vectors() -> Vec<TestVector> {
+    let json = include_str!("cdc-vectors.json");
+    serde_json::from_str(json).expect("Failed to parse CDC test vectors")
+}
+
 #[test]
 fn test_sha256_vectors() {
     let vectors = load_vectors();
@@ -209,6 +227,45 @@ async fn test_file_vectors() {
     }
 }
 
+#[tokio::test]
+async fn test_cdc_file_vectors() {
+    let vectors = load_cdc_vectors();
+
+    for vector in vectors.iter().filter(|v| v.input.input_type == "cdc_file") {
+        let data = hex::decode(vector.input.data.as_ref().unwrap()).unwrap();
+        let params = vector.input.chunking.as_ref().unwrap();
+        let chunking = ChunkingMode::ContentDefined(CdcParams::new(
+            params.min_size,
+            params.avg_size,
+            params.max_size,
+        ));
+
+        // Chunk boundaries must match exactly
+        if let Some(ref expected_sizes) = vector.expected.chunk_sizes {
+            let sizes: Vec<usize> = chunking.split(&data, 0).iter().map(|c| c.len()).collect();
+            assert_eq!(
+                sizes, *expected_sizes,
+                "Chunk sizes mismatch for {}",
+                vector.name
+            );
+        }
+
+        let store = Arc::new(MemoryStore::new());
+        let tree = HashTree::new(HashTreeConfig::new(store).with_chunking(chunking).public());
+        let (cid, size) = tree.put(&data).await.unwrap();
+
+        assert_eq!(
+            to_hex(&cid.hash),
+            vector.expected.hash,
+            "CDC file hash mismatch for {}",
+            vector.name
+        );
+        assert_eq!(Some(size), vector.expected.size);
+
+        println!("✓ {}: chunk sizes and hash match", vector.name);
+    }
+}
+
 #[test]
 fn test_known_sha256_vectors() {
     // Standard SHA256 test vectors
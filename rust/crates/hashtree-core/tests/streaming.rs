@@ -1,7 +1,7 @@
 //! Streaming tests for HashTree put_stream and get_stream API
 
 use futures::StreamExt;
-use hashtree_core::{HashTree, HashTreeConfig, MemoryStore};
+use hashtree_core::{CdcParams, ChunkingMode, HashTree, HashTreeConfig, MemoryStore};
 use std::sync::Arc;
 
 #[tokio::test]
@@ -43,6 +43,30 @@ async fn test_put_stream_chunked() {
     assert_eq!(result, data);
 }
 
+#[tokio::test]
+async fn test_put_stream_content_defined_matches_put() {
+    let store = Arc::new(MemoryStore::new());
+    let chunking = ChunkingMode::ContentDefined(CdcParams::new(64, 256, 1024));
+    let tree = HashTree::new(HashTreeConfig::new(store).with_chunking(chunking));
+
+    let data: Vec<u8> = (0..10_000u32)
+        .map(|i| (i.wrapping_mul(2_654_435_761) >> 11) as u8)
+        .collect();
+    let (put_cid, _) = tree.put(&data).await.unwrap();
+
+    let cursor = std::io::Cursor::new(data.clone());
+    let (stream_cid, size) = tree
+        .put_stream(futures::io::AllowStdIo::new(cursor))
+        .await
+        .unwrap();
+
+    assert_eq!(size, 10_000);
+    assert_eq!(stream_cid, put_cid);
+
+    let result = tree.get(&stream_cid, None).await.unwrap().unwrap();
+    assert_eq!(result, data);
+}
+
 #[tokio::test]
 async fn test_get_stream_small() {
     let store = Arc::new(MemoryStore::new());
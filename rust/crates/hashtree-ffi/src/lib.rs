@@ -5,6 +5,7 @@ use futures::StreamExt;
 use hashtree_blossom::{BlossomClient, BlossomStore};
 use hashtree_core::{
     nhash_decode, nhash_encode_full, Cid, HashTree, HashTreeConfig, MemoryStore, NHashData, Store,
+    VerifyingStore,
 };
 use nostr::Keys;
 use tokio::io::AsyncWriteExt;
@@ -227,7 +228,9 @@ fn build_upload_client(
     Ok(client)
 }
 
-fn build_download_store(read_servers: Vec<String>) -> Result<Arc<BlossomStore>, HashtreeError> {
+fn build_download_store(
+    read_servers: Vec<String>,
+) -> Result<Arc<VerifyingStore<BlossomStore>>, HashtreeError> {
     let read = normalize_servers(read_servers);
     if read.is_empty() {
         return Err(HashtreeError::Message(
@@ -236,7 +239,8 @@ fn build_download_store(read_servers: Vec<String>) -> Result<Arc<BlossomStore>,
     }
 
     let client = BlossomClient::new_empty(Keys::generate()).with_read_servers(read);
-    Ok(Arc::new(BlossomStore::new(client)))
+    let blossom = Arc::new(BlossomStore::new(client));
+    Ok(Arc::new(VerifyingStore::new(blossom)))
 }
 
 async fn put_file_to_memory(file_path: &str) -> Result<(Cid, MemoryStore), HashtreeError> {
@@ -358,6 +358,25 @@ impl Store for FsBlobStore {
         self.delete_sync(hash)
     }
 
+    async fn put_many(&self, items: Vec<(Hash, Vec<u8>)>) -> Result<Vec<bool>, StoreError> {
+        items
+            .iter()
+            .map(|(hash, data)| self.put_sync(*hash, data))
+            .collect()
+    }
+
+    async fn get_many(&self, hashes: &[Hash]) -> Result<Vec<Option<Vec<u8>>>, StoreError> {
+        hashes.iter().map(|hash| self.get_sync(hash)).collect()
+    }
+
+    async fn has_many(&self, hashes: &[Hash]) -> Result<Vec<bool>, StoreError> {
+        Ok(hashes.iter().map(|hash| self.exists(hash)).collect())
+    }
+
+    async fn list(&self) -> Result<Vec<Hash>, StoreError> {
+        FsBlobStore::list(self)
+    }
+
     fn set_max_bytes(&self, max: u64) {
         self.max_bytes.store(max, Ordering::Relaxed);
     }
@@ -799,4 +818,28 @@ mod tests {
         store.delete(&hash).await.unwrap();
         assert_eq!(store.pin_count(&hash), 0);
     }
+
+    #[tokio::test]
+    async fn test_batch_ops() {
+        let temp = TempDir::new().unwrap();
+        let store = FsBlobStore::new(temp.path().join("blobs")).unwrap();
+
+        let items: Vec<(Hash, Vec<u8>)> = [&b"a"[..], b"b", b"a"]
+            .iter()
+            .map(|d| (sha256(d), d.to_vec()))
+            .collect();
+        let hashes: Vec<Hash> = items.iter().map(|(h, _)| *h).collect();
+        assert_eq!(
+            store.put_many(items).await.unwrap(),
+            vec![true, true, false]
+        );
+
+        let query = [hashes[1], sha256(b"missing")];
+        assert_eq!(store.has_many(&query).await.unwrap(), vec![true, false]);
+        assert_eq!(
+            store.get_many(&query).await.unwrap(),
+            vec![Some(b"b".to_vec()), None]
+        );
+        assert_eq!(Store::list(&store).await.unwrap().len(), 2);
+    }
 }
@@ -148,6 +148,72 @@ impl LmdbBlobStore {
             .map(|b| b.to_vec()))
     }
 
+    /// Store several blobs in a single write transaction.
+    pub fn put_many_sync(&self, items: &[(Hash, Vec<u8>)]) -> Result<Vec<bool>, StoreError> {
+        let mut wtxn = self
+            .env
+            .write_txn()
+            .map_err(|e| StoreError::Other(e.to_string()))?;
+
+        let mut stored = Vec::with_capacity(items.len());
+        for (hash, data) in items {
+            let existed = self
+                .blobs
+                .get(&wtxn, hash)
+                .map_err(|e| StoreError::Other(e.to_string()))?
+                .is_some();
+            if !existed {
+                self.blobs
+                    .put(&mut wtxn, hash, data)
+                    .map_err(|e| StoreError::Other(e.to_string()))?;
+            }
+            stored.push(!existed);
+        }
+
+        wtxn.commit()
+            .map_err(|e| StoreError::Other(e.to_string()))?;
+
+        Ok(stored)
+    }
+
+    /// Fetch several blobs in a single read transaction.
+    pub fn get_many_sync(&self, hashes: &[Hash]) -> Result<Vec<Option<Vec<u8>>>, StoreError> {
+        let rtxn = self
+            .env
+            .read_txn()
+            .map_err(|e| StoreError::Other(e.to_string()))?;
+
+        hashes
+            .iter()
+            .map(|hash| {
+                Ok(self
+                    .blobs
+                    .get(&rtxn, hash)
+                    .map_err(|e| StoreError::Other(e.to_string()))?
+                    .map(|b| b.to_vec()))
+            })
+            .collect()
+    }
+
+    /// Check several hashes in a single read transaction.
+    pub fn has_many_sync(&self, hashes: &[Hash]) -> Result<Vec<bool>, StoreError> {
+        let rtxn = self
+            .env
+            .read_txn()
+            .map_err(|e| StoreError::Other(e.to_string()))?;
+
+        hashes
+            .iter()
+            .map(|hash| {
+                Ok(self
+                    .blobs
+                    .get(&rtxn, hash)
+                    .map_err(|e| StoreError::Other(e.to_string()))?
+                    .is_some())
+            })
+            .collect()
+    }
+
     /// Sync delete operation (for use in sync contexts).
     pub fn delete_sync(&self, hash: &Hash) -> Result<bool, StoreError> {
         let mut wtxn = self
@@ -190,6 +256,22 @@ impl Store for LmdbBlobStore {
     async fn delete(&self, hash: &Hash) -> Result<bool, StoreError> {
         self.delete_sync(hash)
     }
+
+    async fn put_many(&self, items: Vec<(Hash, Vec<u8>)>) -> Result<Vec<bool>, StoreError> {
+        self.put_many_sync(&items)
+    }
+
+    async fn get_many(&self, hashes: &[Hash]) -> Result<Vec<Option<Vec<u8>>>, StoreError> {
+        self.get_many_sync(hashes)
+    }
+
+    async fn has_many(&self, hashes: &[Hash]) -> Result<Vec<bool>, StoreError> {
+        self.has_many_sync(hashes)
+    }
+
+    async fn list(&self) -> Result<Vec<Hash>, StoreError> {
+        LmdbBlobStore::list(self)
+    }
 }
 
 #[cfg(test)]
@@ -286,4 +368,31 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_batch_ops() -> Result<(), StoreError> {
+        let temp = TempDir::new().unwrap();
+        let store = LmdbBlobStore::new(temp.path().join("blobs"))?;
+
+        let existing = b"existing";
+        store.put(sha256(existing), existing.to_vec()).await?;
+
+        let items: Vec<(Hash, Vec<u8>)> = [&b"existing"[..], b"a", b"b", b"a"]
+            .iter()
+            .map(|d| (sha256(d), d.to_vec()))
+            .collect();
+        let hashes: Vec<Hash> = items.iter().map(|(h, _)| *h).collect();
+        assert_eq!(store.put_many(items).await?, vec![false, true, true, false]);
+
+        let missing = sha256(b"missing");
+        let query = [hashes[1], missing, hashes[2]];
+        assert_eq!(store.has_many(&query).await?, vec![true, false, true]);
+        assert_eq!(
+            store.get_many(&query).await?,
+            vec![Some(b"a".to_vec()), None, Some(b"b".to_vec())]
+        );
+        assert_eq!(Store::list(&store).await?.len(), 3);
+
+        Ok(())
+    }
 }
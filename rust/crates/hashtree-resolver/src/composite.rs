@@ -0,0 +1,809 @@
+//! Composite root resolver
+//!
+//! Chains several `RootResolver` backends (relay sets, root map files, HTTP
+//! endpoints, ...) behind a single resolver:
+//!
+//! - a `ResolvePolicy` decides which answer wins (fastest, newest, or one a
+//!   quorum of backends agree on);
+//! - every backend gets its own timeout, so one dead relay set can't stall
+//!   the rest;
+//! - an optional on-disk `RootCache` keeps the last-known root per key, which
+//!   is served when the backends give no answer the policy accepts (none at
+//!   all, e.g. offline, or too few agreeing for a quorum);
+//! - `resolve_detailed` reports which backend, or the cache, answered.
+//!
+//! Lookups with a share secret are never cached, since their keys only make
+//! sense together with the secret.
+
+use crate::{ResolvedRoot, ResolverEntry, ResolverError, RootResolver};
+use async_trait::async_trait;
+use hashtree_core::Cid;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+
+/// How a CompositeResolver picks among its backends' answers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResolvePolicy {
+    /// Take the first answer to arrive
+    FirstWins,
+    /// Ask every backend and take the root with the latest publication time
+    ///
+    /// Roots without a known time rank below dated ones; ties go to the
+    /// backend added first.
+    NewestWins,
+    /// Ask every backend and take the root at least this many agree on
+    Quorum(usize),
+}
+
+impl std::fmt::Display for ResolvePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResolvePolicy::FirstWins => write!(f, "first-wins"),
+            ResolvePolicy::NewestWins => write!(f, "newest-wins"),
+            ResolvePolicy::Quorum(n) => write!(f, "quorum:{}", n),
+        }
+    }
+}
+
+impl std::str::FromStr for ResolvePolicy {
+    type Err = ResolverError;
+
+    /// Parse "first-wins", "newest-wins" or "quorum:<n>"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "first-wins" => Ok(ResolvePolicy::FirstWins),
+            "newest-wins" => Ok(ResolvePolicy::NewestWins),
+            _ => s
+                .strip_prefix("quorum:")
+                .and_then(|n| n.parse().ok())
+                .filter(|n| *n > 0)
+                .map(ResolvePolicy::Quorum)
+                .ok_or_else(|| ResolverError::Other(format!("Unknown resolve policy: {}", s))),
+        }
+    }
+}
+
+/// Where a composite resolution came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolutionSource {
+    /// A live answer from the named backend
+    Backend(String),
+    /// The on-disk cache, holding what `backend` answered at `resolved_at`
+    Cache { backend: String, resolved_at: u64 },
+}
+
+impl std::fmt::Display for ResolutionSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResolutionSource::Backend(name) => write!(f, "{}", name),
+            ResolutionSource::Cache { backend, .. } => write!(f, "cache ({})", backend),
+        }
+    }
+}
+
+/// Result of a composite lookup
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resolution {
+    pub cid: Cid,
+    /// Unix seconds the root was published at, when known
+    pub created_at: Option<u64>,
+    pub source: ResolutionSource,
+    /// Backends that returned this same root (0 when served from cache)
+    pub votes: usize,
+}
+
+/// Last-known root of one key in a RootCache
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheEntry {
+    /// Cid as "hash" or "hash:key"
+    pub cid: String,
+    #[serde(default)]
+    pub created_at: Option<u64>,
+    /// Backend that answered
+    pub backend: String,
+    /// Unix seconds of the lookup
+    pub resolved_at: u64,
+}
+
+impl CacheEntry {
+    pub fn new(cid: &Cid, created_at: Option<u64>, backend: impl Into<String>) -> Self {
+        Self {
+            cid: cid.to_string(),
+            created_at,
+            backend: backend.into(),
+            resolved_at: now_secs(),
+        }
+    }
+
+    pub fn cid(&self) -> Option<Cid> {
+        Cid::parse(&self.cid).ok()
+    }
+}
+
+/// Persistent map of key -> last-known root, stored as JSON
+///
+/// Entries may hold decryption keys, so the file is only readable by its
+/// owner. Unreadable or corrupt files start out empty.
+pub struct RootCache {
+    path: PathBuf,
+    entries: Mutex<BTreeMap<String, CacheEntry>>,
+}
+
+impl RootCache {
+    pub fn open(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let entries = std::fs::read(&path)
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            entries: Mutex::new(entries),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get(&self, key: &str) -> Option<CacheEntry> {
+        self.entries.lock().unwrap().get(key).cloned()
+    }
+
+    /// Record the root for `key`, rewriting the file if the root changed
+    pub fn put(&self, key: &str, entry: CacheEntry) -> Result<(), ResolverError> {
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(existing) = entries.get(key) {
+            if existing.cid == entry.cid && existing.created_at == entry.created_at {
+                return Ok(());
+            }
+        }
+        entries.insert(key.to_string(), entry);
+        let data = serde_json::to_vec_pretty(&*entries)
+            .map_err(|e| ResolverError::Other(e.to_string()))?;
+        write_private(&self.path, &data)
+            .map_err(|e| ResolverError::Other(format!("Root cache: {}", e)))
+    }
+}
+
+/// Write via a temp file and rename, readable by the owner only
+fn write_private(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            std::fs::create_dir_all(parent)?;
+        }
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&tmp)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    std::fs::rename(&tmp, path)
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[derive(Clone)]
+struct Backend {
+    name: String,
+    resolver: Arc<dyn RootResolver>,
+}
+
+struct Answer {
+    index: usize,
+    root: ResolvedRoot,
+}
+
+/// What the backends said about a key
+struct Outcome {
+    answers: Vec<Answer>,
+    /// Backends that answered "not found"
+    empty: usize,
+    first_error: Option<ResolverError>,
+}
+
+/// CompositeResolver - Resolves keys through several backends under a policy
+#[derive(Clone)]
+pub struct CompositeResolver {
+    backends: Vec<Backend>,
+    policy: ResolvePolicy,
+    timeout: Duration,
+    cache: Option<Arc<RootCache>>,
+}
+
+impl CompositeResolver {
+    pub fn new(policy: ResolvePolicy) -> Self {
+        Self {
+            backends: Vec::new(),
+            policy,
+            timeout: Duration::from_secs(5),
+            cache: None,
+        }
+    }
+
+    /// Add a backend; names show up in `Resolution::source`
+    pub fn with_backend(
+        mut self,
+        name: impl Into<String>,
+        resolver: Arc<dyn RootResolver>,
+    ) -> Self {
+        self.backends.push(Backend {
+            name: name.into(),
+            resolver,
+        });
+        self
+    }
+
+    /// Per-backend timeout for each lookup (default 5s)
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Persist last-known roots in a JSON file for offline resolution
+    pub fn with_cache(mut self, path: impl Into<PathBuf>) -> Self {
+        self.cache = Some(Arc::new(RootCache::open(path)));
+        self
+    }
+
+    pub fn policy(&self) -> ResolvePolicy {
+        self.policy
+    }
+
+    /// Backend names, in the order they were added
+    pub fn backend_names(&self) -> Vec<&str> {
+        self.backends.iter().map(|b| b.name.as_str()).collect()
+    }
+
+    /// Resolve a key and report which backend (or the cache) answered
+    pub async fn resolve_detailed(&self, key: &str) -> Result<Option<Resolution>, ResolverError> {
+        self.resolve_with(key, None).await
+    }
+
+    async fn resolve_with(
+        &self,
+        key: &str,
+        share_secret: Option<[u8; 32]>,
+    ) -> Result<Option<Resolution>, ResolverError> {
+        let outcome = self.query(key, share_secret).await;
+        let cache = self.cache.as_ref().filter(|_| share_secret.is_none());
+
+        let picked = pick(self.policy, &outcome.answers);
+        if let Ok(Some((answer, votes))) = picked {
+            let name = self.backends[answer.index].name.clone();
+            if let Some(cache) = cache {
+                // A cache that can't be written shouldn't fail the lookup
+                let _ = cache.put(
+                    key,
+                    CacheEntry::new(&answer.root.cid, answer.root.created_at, &name),
+                );
+            }
+            return Ok(Some(Resolution {
+                cid: answer.root.cid.clone(),
+                created_at: answer.root.created_at,
+                source: ResolutionSource::Backend(name),
+                votes,
+            }));
+        }
+
+        // No accepted live answer: fall back to the last known root
+        if let Some(entry) = cache.and_then(|cache| cache.get(key)) {
+            if let Some(cid) = entry.cid() {
+                return Ok(Some(Resolution {
+                    cid,
+                    created_at: entry.created_at,
+                    source: ResolutionSource::Cache {
+                        backend: entry.backend,
+                        resolved_at: entry.resolved_at,
+                    },
+                    votes: 0,
+                }));
+            }
+        }
+
+        // Answers short of a quorum outrank backend errors
+        picked?;
+        match outcome.first_error {
+            Some(e) if outcome.empty == 0 => Err(e),
+            _ => Ok(None),
+        }
+    }
+
+    /// Ask the backends concurrently; FirstWins stops at the first answer
+    async fn query(&self, key: &str, share_secret: Option<[u8; 32]>) -> Outcome {
+        let mut tasks = JoinSet::new();
+        for (index, backend) in self.backends.iter().enumerate() {
+            let resolver = backend.resolver.clone();
+            let key = key.to_string();
+            let timeout = self.timeout;
+            tasks.spawn(async move {
+                let lookup = async {
+                    match share_secret {
+                        Some(secret) => resolver.resolve_shared(&key, &secret).await.map(|cid| {
+                            cid.map(|cid| ResolvedRoot {
+                                cid,
+                                created_at: None,
+                            })
+                        }),
+                        None => resolver.resolve_root(&key).await,
+                    }
+                };
+                let result = tokio::time::timeout(timeout, lookup)
+                    .await
+                    .unwrap_or_else(|_| {
+                        Err(ResolverError::Network(format!(
+                            "timed out after {:?}",
+                            timeout
+                        )))
+                    });
+                (index, result)
+            });
+        }
+
+        let mut outcome = Outcome {
+            answers: Vec::new(),
+            empty: 0,
+            first_error: None,
+        };
+        while let Some(joined) = tasks.join_next().await {
+            let Ok((index, result)) = joined else {
+                continue;
+            };
+            match result {
+                Ok(Some(root)) => {
+                    outcome.answers.push(Answer { index, root });
+                    if self.policy == ResolvePolicy::FirstWins {
+                        // Dropping the JoinSet aborts the slower lookups
+                        break;
+                    }
+                }
+                Ok(None) => outcome.empty += 1,
+                Err(e) => {
+                    outcome.first_error.get_or_insert(e);
+                }
+            }
+        }
+        outcome
+    }
+
+    /// Run `op` on every backend; succeeds if any backend accepted it
+    async fn publish_all<F, Fut>(&self, op: F) -> Result<Option<usize>, ResolverError>
+    where
+        F: Fn(Arc<dyn RootResolver>) -> Fut,
+        Fut: std::future::Future<Output = Result<bool, ResolverError>>,
+    {
+        let mut accepted = None;
+        let mut error = None;
+        for (index, backend) in self.backends.iter().enumerate() {
+            match op(backend.resolver.clone()).await {
+                Ok(true) => {
+                    accepted.get_or_insert(index);
+                }
+                Ok(false) | Err(ResolverError::NotAuthorized) => {}
+                Err(e) => {
+                    error.get_or_insert(e);
+                }
+            }
+        }
+        match (accepted, error) {
+            (Some(index), _) => Ok(Some(index)),
+            (None, Some(e)) => Err(e),
+            (None, None) => Ok(None),
+        }
+    }
+}
+
+/// Choose an answer under `policy`, with how many backends returned it
+fn pick(
+    policy: ResolvePolicy,
+    answers: &[Answer],
+) -> Result<Option<(&Answer, usize)>, ResolverError> {
+    let votes = |answer: &Answer| {
+        answers
+            .iter()
+            .filter(|a| a.root.cid == answer.root.cid)
+            .count()
+    };
+    let best = match policy {
+        ResolvePolicy::FirstWins => answers.first(),
+        ResolvePolicy::NewestWins => answers
+            .iter()
+            .max_by_key(|a| (a.root.created_at, Reverse(a.index))),
+        ResolvePolicy::Quorum(_) => answers.iter().max_by_key(|a| (votes(a), Reverse(a.index))),
+    };
+    let Some(best) = best else {
+        return Ok(None);
+    };
+
+    let count = votes(best);
+    if let ResolvePolicy::Quorum(needed) = policy {
+        if count < needed {
+            return Err(ResolverError::NoQuorum(format!(
+                "best root has {} of {} required answers",
+                count, needed
+            )));
+        }
+    }
+    Ok(Some((best, count)))
+}
+
+#[async_trait]
+impl RootResolver for CompositeResolver {
+    async fn resolve(&self, key: &str) -> Result<Option<Cid>, ResolverError> {
+        Ok(self.resolve_detailed(key).await?.map(|r| r.cid))
+    }
+
+    async fn resolve_root(&self, key: &str) -> Result<Option<ResolvedRoot>, ResolverError> {
+        Ok(self.resolve_detailed(key).await?.map(|r| ResolvedRoot {
+            cid: r.cid,
+            created_at: r.created_at,
+        }))
+    }
+
+    async fn resolve_shared(
+        &self,
+        key: &str,
+        share_secret: &[u8; 32],
+    ) -> Result<Option<Cid>, ResolverError> {
+        Ok(self
+            .resolve_with(key, Some(*share_secret))
+            .await?
+            .map(|r| r.cid))
+    }
+
+    async fn subscribe(&self, key: &str) -> Result<mpsc::Receiver<Option<Cid>>, ResolverError> {
+        let (tx, rx) = mpsc::channel(16);
+        let mut current = self.resolve(key).await?;
+        let _ = tx.send(current.clone()).await;
+
+        // Any backend update triggers a fresh lookup under the policy
+        let (changed_tx, mut changed_rx) = mpsc::channel::<()>(16);
+        for backend in &self.backends {
+            let subscribed = tokio::time::timeout(self.timeout, backend.resolver.subscribe(key));
+            let Ok(Ok(mut updates)) = subscribed.await else {
+                continue;
+            };
+            let changed_tx = changed_tx.clone();
+            tokio::spawn(async move {
+                while updates.recv().await.is_some() {
+                    if changed_tx.send(()).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(changed_tx);
+
+        let this = self.clone();
+        let key = key.to_string();
+        tokio::spawn(async move {
+            while changed_rx.recv().await.is_some() {
+                // Coalesce bursts into one lookup
+                while changed_rx.try_recv().is_ok() {}
+                let Ok(next) = this.resolve(&key).await else {
+                    continue;
+                };
+                if next != current {
+                    if tx.send(next.clone()).await.is_err() {
+                        break;
+                    }
+                    current = next;
+                }
+            }
+        });
+        Ok(rx)
+    }
+
+    async fn publish(&self, key: &str, cid: &Cid) -> Result<bool, ResolverError> {
+        let accepted = self
+            .publish_all(|resolver| {
+                let (key, cid) = (key.to_string(), cid.clone());
+                async move { resolver.publish(&key, &cid).await }
+            })
+            .await?;
+        let Some(index) = accepted else {
+            return Err(ResolverError::NotAuthorized);
+        };
+        if let Some(cache) = &self.cache {
+            let _ = cache.put(
+                key,
+                CacheEntry::new(cid, Some(now_secs()), &self.backends[index].name),
+            );
+        }
+        Ok(true)
+    }
+
+    async fn publish_shared(
+        &self,
+        key: &str,
+        cid: &Cid,
+        share_secret: &[u8; 32],
+    ) -> Result<bool, ResolverError> {
+        let accepted = self
+            .publish_all(|resolver| {
+                let (key, cid, secret) = (key.to_string(), cid.clone(), *share_secret);
+                async move { resolver.publish_shared(&key, &cid, &secret).await }
+            })
+            .await?;
+        accepted.map(|_| true).ok_or(ResolverError::NotAuthorized)
+    }
+
+    async fn writers(&self, key: &str) -> Result<Vec<String>, ResolverError> {
+        for backend in &self.backends {
+            if let Ok(writers) = backend.resolver.writers(key).await {
+                if !writers.is_empty() {
+                    return Ok(writers);
+                }
+            }
+        }
+        Ok(vec![])
+    }
+
+    async fn set_writers(&self, key: &str, writers: &[String]) -> Result<bool, ResolverError> {
+        let accepted = self
+            .publish_all(|resolver| {
+                let (key, writers) = (key.to_string(), writers.to_vec());
+                async move { resolver.set_writers(&key, &writers).await }
+            })
+            .await?;
+        accepted.map(|_| true).ok_or(ResolverError::NotAuthorized)
+    }
+
+    /// Union of all backends' entries; earlier backends win on conflicts
+    async fn list(&self, prefix: &str) -> Result<Vec<ResolverEntry>, ResolverError> {
+        let mut merged: BTreeMap<String, ResolverEntry> = BTreeMap::new();
+        let mut first_error = None;
+        let mut listed = false;
+        for backend in &self.backends {
+            match tokio::time::timeout(self.timeout, backend.resolver.list(prefix)).await {
+                Ok(Ok(entries)) => {
+                    listed = true;
+                    for entry in entries {
+                        merged.entry(entry.key.clone()).or_insert(entry);
+                    }
+                }
+                Ok(Err(e)) => {
+                    first_error.get_or_insert(e);
+                }
+                Err(_) => {}
+            }
+        }
+        match first_error {
+            Some(e) if !listed => Err(e),
+            _ => Ok(merged.into_values().collect()),
+        }
+    }
+
+    async fn stop(&self) -> Result<(), ResolverError> {
+        for backend in &self.backends {
+            let _ = backend.resolver.stop().await;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Fixed answers, optionally slow or failing
+    struct StaticResolver {
+        root: Result<Option<ResolvedRoot>, ()>,
+        delay: Duration,
+    }
+
+    impl StaticResolver {
+        fn answer(byte: u8, created_at: Option<u64>) -> Arc<dyn RootResolver> {
+            Arc::new(Self {
+                root: Ok(Some(ResolvedRoot {
+                    cid: cid(byte),
+                    created_at,
+                })),
+                delay: Duration::ZERO,
+            })
+        }
+
+        fn slow(byte: u8, delay: Duration) -> Arc<dyn RootResolver> {
+            Arc::new(Self {
+                root: Ok(Some(ResolvedRoot {
+                    cid: cid(byte),
+                    created_at: None,
+                })),
+                delay,
+            })
+        }
+
+        fn empty() -> Arc<dyn RootResolver> {
+            Arc::new(Self {
+                root: Ok(None),
+                delay: Duration::ZERO,
+            })
+        }
+
+        fn failing() -> Arc<dyn RootResolver> {
+            Arc::new(Self {
+                root: Err(()),
+                delay: Duration::ZERO,
+            })
+        }
+    }
+
+    #[async_trait]
+    impl RootResolver for StaticResolver {
+        async fn resolve(&self, key: &str) -> Result<Option<Cid>, ResolverError> {
+            Ok(self.resolve_root(key).await?.map(|r| r.cid))
+        }
+
+        async fn resolve_root(&self, _key: &str) -> Result<Option<ResolvedRoot>, ResolverError> {
+            tokio::time::sleep(self.delay).await;
+            self.root
+                .clone()
+                .map_err(|_| ResolverError::Network("relays down".into()))
+        }
+
+        async fn subscribe(
+            &self,
+            _key: &str,
+        ) -> Result<mpsc::Receiver<Option<Cid>>, ResolverError> {
+            Err(ResolverError::Other("Not implemented".into()))
+        }
+    }
+
+    fn cid(byte: u8) -> Cid {
+        Cid {
+            hash: [byte; 32],
+            key: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_first_wins_takes_fastest_answer() {
+        let resolver = CompositeResolver::new(ResolvePolicy::FirstWins)
+            .with_backend("slow", StaticResolver::slow(1, Duration::from_millis(500)))
+            .with_backend("down", StaticResolver::failing())
+            .with_backend("fast", StaticResolver::answer(2, None));
+
+        let resolution = resolver.resolve_detailed("k").await.unwrap().unwrap();
+        assert_eq!(resolution.cid, cid(2));
+        assert_eq!(resolution.source, ResolutionSource::Backend("fast".into()));
+    }
+
+    #[tokio::test]
+    async fn test_newest_wins_prefers_latest_timestamp() {
+        let resolver = CompositeResolver::new(ResolvePolicy::NewestWins)
+            .with_backend("undated", StaticResolver::answer(1, None))
+            .with_backend("old", StaticResolver::answer(2, Some(100)))
+            .with_backend("new", StaticResolver::answer(3, Some(200)))
+            .with_backend("also-new", StaticResolver::answer(3, Some(200)));
+
+        let resolution = resolver.resolve_detailed("k").await.unwrap().unwrap();
+        assert_eq!(resolution.cid, cid(3));
+        assert_eq!(resolution.source, ResolutionSource::Backend("new".into()));
+        assert_eq!(resolution.votes, 2);
+    }
+
+    #[tokio::test]
+    async fn test_quorum() {
+        let agreeing = CompositeResolver::new(ResolvePolicy::Quorum(2))
+            .with_backend("a", StaticResolver::answer(1, None))
+            .with_backend("b", StaticResolver::answer(2, None))
+            .with_backend("c", StaticResolver::answer(2, None));
+        let resolution = agreeing.resolve_detailed("k").await.unwrap().unwrap();
+        assert_eq!(resolution.cid, cid(2));
+        assert_eq!(resolution.votes, 2);
+
+        let split = CompositeResolver::new(ResolvePolicy::Quorum(2))
+            .with_backend("a", StaticResolver::answer(1, None))
+            .with_backend("b", StaticResolver::answer(2, None))
+            .with_backend("c", StaticResolver::empty());
+        assert!(matches!(
+            split.resolve_detailed("k").await,
+            Err(ResolverError::NoQuorum(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_cache_serves_last_known_root_when_backends_fail() {
+        let dir = TempDir::new().unwrap();
+        let cache_path = dir.path().join("roots-cache.json");
+
+        let online = CompositeResolver::new(ResolvePolicy::FirstWins)
+            .with_backend("relays", StaticResolver::answer(5, Some(123)))
+            .with_cache(&cache_path);
+        online.resolve("npub1x/repo").await.unwrap();
+
+        let offline = CompositeResolver::new(ResolvePolicy::FirstWins)
+            .with_backend("relays", StaticResolver::failing())
+            .with_backend("timeout", StaticResolver::slow(6, Duration::from_secs(10)))
+            .with_timeout(Duration::from_millis(50))
+            .with_cache(&cache_path);
+        let resolution = offline
+            .resolve_detailed("npub1x/repo")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(resolution.cid, cid(5));
+        assert_eq!(resolution.created_at, Some(123));
+        assert!(matches!(
+            resolution.source,
+            ResolutionSource::Cache { ref backend, .. } if backend == "relays"
+        ));
+
+        // Unknown keys still surface the backend error
+        assert!(offline.resolve("npub1x/other").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cache_serves_last_known_root_without_quorum() {
+        let dir = TempDir::new().unwrap();
+        let cache_path = dir.path().join("roots-cache.json");
+
+        let agreeing = CompositeResolver::new(ResolvePolicy::Quorum(2))
+            .with_backend("a", StaticResolver::answer(5, None))
+            .with_backend("b", StaticResolver::answer(5, None))
+            .with_cache(&cache_path);
+        agreeing.resolve("npub1x/repo").await.unwrap();
+
+        // Zero answers and answers below the quorum both fall back to the cache
+        let silent = CompositeResolver::new(ResolvePolicy::Quorum(2))
+            .with_backend("a", StaticResolver::failing())
+            .with_backend("b", StaticResolver::empty())
+            .with_cache(&cache_path);
+        let split = CompositeResolver::new(ResolvePolicy::Quorum(2))
+            .with_backend("a", StaticResolver::answer(6, None))
+            .with_backend("b", StaticResolver::answer(7, None))
+            .with_cache(&cache_path);
+        for resolver in [&silent, &split] {
+            let resolution = resolver
+                .resolve_detailed("npub1x/repo")
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(resolution.cid, cid(5));
+            assert!(matches!(resolution.source, ResolutionSource::Cache { .. }));
+        }
+
+        // Without a cached root the missing quorum is reported
+        assert!(matches!(
+            split.resolve_detailed("npub1x/other").await,
+            Err(ResolverError::NoQuorum(_))
+        ));
+        assert!(silent.resolve("npub1x/other").await.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_policy_parse() {
+        for policy in [
+            ResolvePolicy::FirstWins,
+            ResolvePolicy::NewestWins,
+            ResolvePolicy::Quorum(3),
+        ] {
+            assert_eq!(policy.to_string().parse::<ResolvePolicy>().unwrap(), policy);
+        }
+        assert!("quorum:0".parse::<ResolvePolicy>().is_err());
+        assert!("fastest".parse::<ResolvePolicy>().is_err());
+    }
+}
@@ -0,0 +1,265 @@
+//! File-based root resolver
+//!
+//! Maps keys to content identifiers (Cid) using a JSON or TOML file on local
+//! disk, so tests, CI and air-gapped machines can resolve named trees without
+//! any network. The format is picked from the file extension (`.toml`, anything
+//! else is JSON) and holds a flat map of key -> CID string:
+//!
+//! ```toml
+//! "local/site" = "ab12..."
+//! "npub1.../docs" = "cd34...:ef56..."
+//! ```
+//!
+//! Keys are free-form. The file is re-read on every lookup and polled for
+//! changes by subscriptions. Publishing rewrites the file atomically, so
+//! several processes may share it.
+
+use crate::root_map::{self, RootMap};
+use crate::{ResolverEntry, ResolverError, RootResolver};
+use async_trait::async_trait;
+use hashtree_core::Cid;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::{mpsc, watch, Mutex};
+
+/// Configuration for FileRootResolver
+#[derive(Debug, Clone)]
+pub struct FileResolverConfig {
+    /// Path of the JSON or TOML root map
+    pub path: PathBuf,
+    /// How often subscriptions check the file for changes
+    pub poll_interval: Duration,
+}
+
+impl FileResolverConfig {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            poll_interval: Duration::from_secs(1),
+        }
+    }
+
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MapFormat {
+    Json,
+    Toml,
+}
+
+impl MapFormat {
+    fn for_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => MapFormat::Toml,
+            _ => MapFormat::Json,
+        }
+    }
+}
+
+/// FileRootResolver - Maps keys to Cids through a root map file
+pub struct FileRootResolver {
+    config: FileResolverConfig,
+    format: MapFormat,
+    /// Serializes read-modify-write cycles within this process
+    write_lock: Mutex<()>,
+    stop_tx: watch::Sender<bool>,
+}
+
+impl FileRootResolver {
+    /// Create a resolver for `config.path`; the file is created on first publish
+    pub fn new(config: FileResolverConfig) -> Self {
+        let format = MapFormat::for_path(&config.path);
+        let (stop_tx, _) = watch::channel(false);
+        Self {
+            config,
+            format,
+            write_lock: Mutex::new(()),
+            stop_tx,
+        }
+    }
+
+    /// Path of the root map file
+    pub fn path(&self) -> &Path {
+        &self.config.path
+    }
+
+    fn load(&self) -> Result<RootMap, ResolverError> {
+        load_map(&self.config.path, self.format)
+    }
+
+    fn save(&self, map: &RootMap) -> Result<(), ResolverError> {
+        let raw = root_map::to_strings(map);
+        let text = match self.format {
+            MapFormat::Json => serde_json::to_string_pretty(&raw)
+                .map_err(|e| ResolverError::Other(e.to_string()))?,
+            MapFormat::Toml => {
+                toml::to_string(&raw).map_err(|e| ResolverError::Other(e.to_string()))?
+            }
+        };
+
+        // Write next to the target and rename, so readers never see a partial file
+        let mut tmp = self.config.path.clone().into_os_string();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        if let Some(parent) = self.config.path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent).map_err(io_error)?;
+            }
+        }
+        std::fs::write(&tmp, text).map_err(io_error)?;
+        std::fs::rename(&tmp, &self.config.path).map_err(io_error)
+    }
+
+    /// Watch the file, sending `select(map)` whenever it changes
+    fn watch<T, F>(&self, initial: T, tx: mpsc::Sender<T>, select: F)
+    where
+        T: PartialEq + Clone + Send + Sync + 'static,
+        F: Fn(&RootMap) -> T + Send + Sync + 'static,
+    {
+        let path = self.config.path.clone();
+        let format = self.format;
+        root_map::spawn_poller(
+            self.config.poll_interval,
+            self.stop_tx.subscribe(),
+            initial,
+            tx,
+            move || {
+                // A half-edited file keeps the last good value
+                let next = load_map(&path, format).ok().map(|map| select(&map));
+                async move { next }
+            },
+        );
+    }
+}
+
+fn load_map(path: &Path, format: MapFormat) -> Result<RootMap, ResolverError> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(RootMap::new()),
+        Err(e) => return Err(io_error(e)),
+    };
+    match format {
+        MapFormat::Json if text.trim().is_empty() => Ok(RootMap::new()),
+        MapFormat::Json => root_map::parse_json(text.as_bytes()),
+        MapFormat::Toml => root_map::parse_toml(&text),
+    }
+}
+
+fn io_error(e: std::io::Error) -> ResolverError {
+    ResolverError::Other(format!("Root map file: {}", e))
+}
+
+#[async_trait]
+impl RootResolver for FileRootResolver {
+    async fn resolve(&self, key: &str) -> Result<Option<Cid>, ResolverError> {
+        Ok(self.load()?.remove(key))
+    }
+
+    async fn subscribe(&self, key: &str) -> Result<mpsc::Receiver<Option<Cid>>, ResolverError> {
+        let (tx, rx) = mpsc::channel(16);
+        let current = self.load()?.remove(key);
+        let _ = tx.send(current.clone()).await;
+
+        let key = key.to_string();
+        self.watch(current, tx, move |map| map.get(&key).cloned());
+        Ok(rx)
+    }
+
+    async fn publish(&self, key: &str, cid: &Cid) -> Result<bool, ResolverError> {
+        let _guard = self.write_lock.lock().await;
+        let mut map = self.load()?;
+        map.insert(key.to_string(), cid.clone());
+        self.save(&map)?;
+        Ok(true)
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<ResolverEntry>, ResolverError> {
+        Ok(root_map::list(&self.load()?, prefix))
+    }
+
+    async fn subscribe_list(
+        &self,
+        prefix: &str,
+    ) -> Result<mpsc::Receiver<Vec<ResolverEntry>>, ResolverError> {
+        let (tx, rx) = mpsc::channel(16);
+        let current = root_map::list(&self.load()?, prefix);
+        let _ = tx.send(current.clone()).await;
+
+        let prefix = prefix.to_string();
+        self.watch(current, tx, move |map| root_map::list(map, &prefix));
+        Ok(rx)
+    }
+
+    async fn stop(&self) -> Result<(), ResolverError> {
+        self.stop_tx.send_replace(true);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn cid(byte: u8) -> Cid {
+        Cid {
+            hash: [byte; 32],
+            key: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_publish_resolve_roundtrip_json_and_toml() {
+        let dir = TempDir::new().unwrap();
+        for name in ["roots.json", "roots.toml"] {
+            let path = dir.path().join(name);
+            let resolver = FileRootResolver::new(FileResolverConfig::new(&path));
+            assert_eq!(resolver.resolve("local/site").await.unwrap(), None);
+
+            let keyed = Cid {
+                hash: [1; 32],
+                key: Some([2; 32]),
+            };
+            resolver.publish("local/site", &keyed).await.unwrap();
+            resolver.publish("local/docs", &cid(3)).await.unwrap();
+
+            // A second resolver on the same file sees the writes
+            let other = FileRootResolver::new(FileResolverConfig::new(&path));
+            assert_eq!(other.resolve("local/site").await.unwrap(), Some(keyed));
+            let keys: Vec<String> = other
+                .list("local/")
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|e| e.key)
+                .collect();
+            assert_eq!(keys, vec!["local/docs", "local/site"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_sees_external_edits() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("roots.json");
+        let resolver = FileRootResolver::new(
+            FileResolverConfig::new(&path).with_poll_interval(Duration::from_millis(20)),
+        );
+
+        let mut rx = resolver.subscribe("local/site").await.unwrap();
+        assert_eq!(rx.recv().await.unwrap(), None);
+
+        let writer = FileRootResolver::new(FileResolverConfig::new(&path));
+        writer.publish("local/site", &cid(7)).await.unwrap();
+
+        let update = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("subscription should notice the edit");
+        assert_eq!(update.unwrap(), Some(cid(7)));
+
+        resolver.stop().await.unwrap();
+    }
+}
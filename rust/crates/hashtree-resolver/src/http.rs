@@ -0,0 +1,222 @@
+//! HTTP-based root resolver
+//!
+//! Maps keys to content identifiers (Cid) by fetching a JSON root map from a
+//! URL - the same flat key -> CID string object the file resolver reads, so a
+//! static file server (or CI artifact host) can publish roots:
+//!
+//! ```json
+//! { "local/site": "ab12...", "npub1.../docs": "cd34...:ef56..." }
+//! ```
+//!
+//! Read-only: publishing is left to whatever produces the document.
+//! Subscriptions poll the URL.
+
+use crate::root_map::{self, RootMap};
+use crate::{ResolverEntry, ResolverError, RootResolver};
+use async_trait::async_trait;
+use hashtree_core::Cid;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+
+/// Configuration for HttpRootResolver
+#[derive(Debug, Clone)]
+pub struct HttpResolverConfig {
+    /// URL of the JSON root map
+    pub url: String,
+    /// How often subscriptions re-fetch the root map
+    pub poll_interval: Duration,
+    /// Timeout for each request
+    pub timeout: Duration,
+}
+
+impl HttpResolverConfig {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            poll_interval: Duration::from_secs(30),
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+/// HttpRootResolver - Maps keys to Cids through a JSON document on a web server
+pub struct HttpRootResolver {
+    config: HttpResolverConfig,
+    client: reqwest::Client,
+    stop_tx: watch::Sender<bool>,
+}
+
+impl HttpRootResolver {
+    pub fn new(config: HttpResolverConfig) -> Result<Self, ResolverError> {
+        let client = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .map_err(|e| ResolverError::Network(e.to_string()))?;
+        let (stop_tx, _) = watch::channel(false);
+        Ok(Self {
+            config,
+            client,
+            stop_tx,
+        })
+    }
+
+    /// URL of the root map
+    pub fn url(&self) -> &str {
+        &self.config.url
+    }
+
+    async fn fetch(&self) -> Result<RootMap, ResolverError> {
+        fetch_map(&self.client, &self.config.url).await
+    }
+
+    /// Poll the URL, sending `select(map)` whenever it changes
+    fn watch<T, F>(&self, initial: T, tx: mpsc::Sender<T>, select: F)
+    where
+        T: PartialEq + Clone + Send + Sync + 'static,
+        F: Fn(&RootMap) -> T + Send + Sync + 'static,
+    {
+        let client = self.client.clone();
+        let url = self.config.url.clone();
+        let select = std::sync::Arc::new(select);
+        root_map::spawn_poller(
+            self.config.poll_interval,
+            self.stop_tx.subscribe(),
+            initial,
+            tx,
+            move || {
+                let client = client.clone();
+                let url = url.clone();
+                let select = select.clone();
+                // Unreachable servers keep the last known value
+                async move { fetch_map(&client, &url).await.ok().map(|map| select(&map)) }
+            },
+        );
+    }
+}
+
+async fn fetch_map(client: &reqwest::Client, url: &str) -> Result<RootMap, ResolverError> {
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| ResolverError::Network(e.to_string()))?;
+    let body = response
+        .bytes()
+        .await
+        .map_err(|e| ResolverError::Network(e.to_string()))?;
+    root_map::parse_json(&body)
+}
+
+#[async_trait]
+impl RootResolver for HttpRootResolver {
+    async fn resolve(&self, key: &str) -> Result<Option<Cid>, ResolverError> {
+        Ok(self.fetch().await?.remove(key))
+    }
+
+    async fn subscribe(&self, key: &str) -> Result<mpsc::Receiver<Option<Cid>>, ResolverError> {
+        let (tx, rx) = mpsc::channel(16);
+        let current = self.fetch().await?.remove(key);
+        let _ = tx.send(current.clone()).await;
+
+        let key = key.to_string();
+        self.watch(current, tx, move |map| map.get(&key).cloned());
+        Ok(rx)
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<ResolverEntry>, ResolverError> {
+        Ok(root_map::list(&self.fetch().await?, prefix))
+    }
+
+    async fn subscribe_list(
+        &self,
+        prefix: &str,
+    ) -> Result<mpsc::Receiver<Vec<ResolverEntry>>, ResolverError> {
+        let (tx, rx) = mpsc::channel(16);
+        let current = root_map::list(&self.fetch().await?, prefix);
+        let _ = tx.send(current.clone()).await;
+
+        let prefix = prefix.to_string();
+        self.watch(current, tx, move |map| root_map::list(map, &prefix));
+        Ok(rx)
+    }
+
+    async fn stop(&self) -> Result<(), ResolverError> {
+        self.stop_tx.send_replace(true);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hashtree_core::to_hex;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serve `body` as JSON to every request on an ephemeral port
+    async fn serve(body: String) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let _ = socket.read(&mut buf).await;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        format!("http://{}/roots.json", addr)
+    }
+
+    #[tokio::test]
+    async fn test_resolve_and_list_from_url() {
+        let hash = [0x42; 32];
+        let url = serve(format!(
+            r#"{{"local/site": "{}", "local/docs": "{}"}}"#,
+            to_hex(&hash),
+            to_hex(&hash)
+        ))
+        .await;
+
+        let resolver = HttpRootResolver::new(HttpResolverConfig::new(url)).unwrap();
+        let cid = resolver.resolve("local/site").await.unwrap().unwrap();
+        assert_eq!(cid.hash, hash);
+        assert_eq!(resolver.resolve("local/missing").await.unwrap(), None);
+        assert_eq!(resolver.list("local/").await.unwrap().len(), 2);
+        assert!(matches!(
+            resolver.publish("local/site", &cid).await,
+            Err(ResolverError::NotAuthorized)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_url_is_network_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/roots.json", listener.local_addr().unwrap());
+        drop(listener);
+
+        let resolver = HttpRootResolver::new(
+            HttpResolverConfig::new(url).with_timeout(Duration::from_secs(2)),
+        )
+        .unwrap();
+        assert!(matches!(
+            resolver.resolve("local/site").await,
+            Err(ResolverError::Network(_))
+        ));
+    }
+}
@@ -12,7 +12,11 @@
 //! Key format is implementation-specific:
 //! - Nostr: "npub1.../treename"
 //! - DNS: "example.com/treename"
-//! - Local: "local/mydata"
+//! - File / HTTP root maps: free-form, e.g. "local/mydata"
+//!
+//! Backends are behind features: `nostr`, `file` (JSON/TOML map on disk) and
+//! `http` (JSON map fetched from a URL). `composite::CompositeResolver` chains
+//! any of them with a fallback policy and an on-disk cache of last-known roots.
 //!
 //! # Example
 //!
@@ -35,6 +39,15 @@
 
 mod traits;
 
+pub mod composite;
+
+#[cfg(any(feature = "file", feature = "http"))]
+mod root_map;
+
+#[cfg(feature = "file")]
+pub mod file;
+#[cfg(feature = "http")]
+pub mod http;
 #[cfg(feature = "nostr")]
 pub mod nostr;
 
@@ -12,10 +12,17 @@
 //! - encryptedKey-tag: XOR-masked key (link-visible)
 //! - selfEncryptedKey-tag: NIP-44 key encrypted to self (private)
 //! - encrypted_key-tag: legacy AES-GCM shared key (backwards compat)
-
-use crate::{ResolverEntry, ResolverError, RootResolver};
+//!
+//! Delegated writers: the owner publishes a writer list (d-tag
+//! "hashtree-writers/<treename>", l-tag "hashtree-writers", one p-tag per
+//! writer). Writers publish roots with d-tag "<owner hex>/<treename>" and a
+//! p-tag for the owner. Resolution takes the newest root from the owner or a
+//! currently listed writer; republishing the list without a writer revokes
+//! them.
+
+use crate::{ResolvedRoot, ResolverEntry, ResolverError, RootResolver};
 use async_trait::async_trait;
-use hashtree_core::{from_hex, to_hex, Cid};
+use hashtree_core::{from_hex, to_hex, Cid, Hash};
 use nostr_sdk::prelude::nip44;
 use nostr_sdk::prelude::*;
 use serde_json::Value;
@@ -28,6 +35,7 @@ use hashtree_core::{decrypt, xor_keys};
 
 const HASHTREE_KIND: u16 = 30078;
 const HASHTREE_LABEL: &str = "hashtree";
+const WRITERS_LABEL: &str = "hashtree-writers";
 
 /// Configuration for NostrRootResolver
 #[derive(Clone)]
@@ -57,6 +65,7 @@ impl Default for NostrResolverConfig {
 /// Tag names for hashtree events
 const TAG_HASH: &str = "hash";
 const TAG_KEY: &str = "key";
+const TAG_PREV: &str = "prev";
 const TAG_ENCRYPTED_KEY: &str = "encryptedKey";
 const TAG_SELF_ENCRYPTED_KEY: &str = "selfEncryptedKey";
 const TAG_ENCRYPTED_KEY_LEGACY: &str = "encrypted_key";
@@ -79,6 +88,163 @@ fn is_hashtree_event(event: &Event) -> bool {
     has_label(event, HASHTREE_LABEL) || !has_any_label(event)
 }
 
+fn d_tag(event: &Event) -> Option<String> {
+    event.tags.iter().find_map(|tag| {
+        if let Some(TagStandard::Identifier(id)) = tag.as_standardized() {
+            Some(id.clone())
+        } else {
+            None
+        }
+    })
+}
+
+/// d-tag of the owner's writer list for a tree
+fn writers_d_tag(tree_name: &str) -> String {
+    format!("{}/{}", WRITERS_LABEL, tree_name)
+}
+
+/// d-tag of roots published by delegated writers
+fn delegated_d_tag(owner: &PublicKey, tree_name: &str) -> String {
+    format!("{}/{}", owner.to_hex(), tree_name)
+}
+
+fn is_delegated_root(event: &Event) -> bool {
+    event.tags.iter().any(|tag| {
+        let tag_vec = tag.as_slice();
+        tag_vec.len() >= 2 && tag_vec[0].as_str() == "p"
+    })
+}
+
+/// Writer pubkeys (p-tags) of a writer list event
+fn writers_from_event(event: &Event) -> Vec<PublicKey> {
+    event
+        .tags
+        .iter()
+        .filter_map(|tag| {
+            let tag_vec = tag.as_slice();
+            if tag_vec.len() >= 2 && tag_vec[0].as_str() == "p" {
+                PublicKey::from_hex(&tag_vec[1]).ok()
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+fn parse_pubkey(value: &str) -> Result<PublicKey, ResolverError> {
+    PublicKey::from_bech32(value)
+        .or_else(|_| PublicKey::from_hex(value))
+        .map_err(|_| ResolverError::InvalidKey(format!("Invalid pubkey: {}", value)))
+}
+
+/// HTS-01 ordering: newest created_at, ties broken by the larger event id
+fn is_newer(event: &Event, than: &Event) -> bool {
+    (event.created_at, event.id) > (than.created_at, than.id)
+}
+
+fn replace_if_newer(slot: &mut Option<Event>, event: &Event) -> bool {
+    match slot {
+        Some(current) if !is_newer(event, current) => false,
+        _ => {
+            *slot = Some(event.clone());
+            true
+        }
+    }
+}
+
+/// Filters for every event that can decide a tree's root
+fn root_filters(owner: &PublicKey, tree_name: &str) -> Vec<Filter> {
+    vec![
+        Filter::new()
+            .kind(Kind::Custom(HASHTREE_KIND))
+            .author(*owner)
+            .custom_tag(
+                SingleLetterTag::lowercase(Alphabet::D),
+                vec![tree_name.to_string(), writers_d_tag(tree_name)],
+            ),
+        // Any author: the writer list decides which ones count
+        Filter::new().kind(Kind::Custom(HASHTREE_KIND)).custom_tag(
+            SingleLetterTag::lowercase(Alphabet::D),
+            vec![delegated_d_tag(owner, tree_name)],
+        ),
+    ]
+}
+
+/// Root events seen for one tree, from its owner and delegated writers
+struct RootCandidates {
+    owner: PublicKey,
+    tree_name: String,
+    owner_root: Option<Event>,
+    writer_list: Option<Event>,
+    /// Latest root per writer, kept even while unlisted so a later writer
+    /// list can admit it
+    delegated: HashMap<PublicKey, Event>,
+}
+
+impl RootCandidates {
+    fn new(owner: PublicKey, tree_name: &str) -> Self {
+        Self {
+            owner,
+            tree_name: tree_name.to_string(),
+            owner_root: None,
+            writer_list: None,
+            delegated: HashMap::new(),
+        }
+    }
+
+    /// Record an event, returning true if it changed anything
+    fn add(&mut self, event: &Event) -> bool {
+        if event.kind.as_u16() != HASHTREE_KIND {
+            return false;
+        }
+        let Some(d) = d_tag(event) else {
+            return false;
+        };
+
+        if event.pubkey == self.owner {
+            if d == self.tree_name && is_hashtree_event(event) {
+                return replace_if_newer(&mut self.owner_root, event);
+            }
+            if d == writers_d_tag(&self.tree_name) && has_label(event, WRITERS_LABEL) {
+                return replace_if_newer(&mut self.writer_list, event);
+            }
+            return false;
+        }
+
+        if d == delegated_d_tag(&self.owner, &self.tree_name) && is_hashtree_event(event) {
+            return match self.delegated.get(&event.pubkey) {
+                Some(current) if !is_newer(event, current) => false,
+                _ => {
+                    self.delegated.insert(event.pubkey, event.clone());
+                    true
+                }
+            };
+        }
+        false
+    }
+
+    fn writers(&self) -> Vec<PublicKey> {
+        self.writer_list
+            .as_ref()
+            .map(writers_from_event)
+            .unwrap_or_default()
+    }
+
+    /// Newest root from the owner or a currently listed writer
+    fn latest(&self) -> Option<&Event> {
+        let writers = self.writers();
+        self.owner_root
+            .iter()
+            .chain(
+                self.delegated
+                    .iter()
+                    .filter(|(writer, _)| writers.contains(*writer))
+                    .map(|(_, event)| event),
+            )
+            .max_by_key(|event| (event.created_at, event.id))
+    }
+}
+
 fn parse_legacy_content(content: &str) -> Option<(String, Option<String>)> {
     let trimmed = content.trim();
     if trimmed.is_empty() {
@@ -106,7 +272,7 @@ fn parse_legacy_content(content: &str) -> Option<(String, Option<String>)> {
 struct Subscription {
     tx: mpsc::Sender<Option<Cid>>,
     current_cid: Option<Cid>,
-    latest_created_at: Timestamp,
+    candidates: RootCandidates,
 }
 
 /// NostrRootResolver - Maps npub/treename keys to merkle root hashes
@@ -294,6 +460,135 @@ impl NostrRootResolver {
         Some(Cid { hash, key })
     }
 
+    /// Fetch the owner's root, writer list and delegated roots for a tree
+    async fn fetch_candidates(
+        &self,
+        owner: PublicKey,
+        tree_name: &str,
+    ) -> Result<RootCandidates, ResolverError> {
+        let source = EventSource::relays(Some(self.config.resolve_timeout));
+        let events = self
+            .client
+            .get_events_of(root_filters(&owner, tree_name), source)
+            .await
+            .map_err(|e| ResolverError::Network(e.to_string()))?;
+
+        let mut candidates = RootCandidates::new(owner, tree_name);
+        for event in events.iter() {
+            candidates.add(event);
+        }
+        Ok(candidates)
+    }
+
+    /// Base tags for a root event, checking that we may publish it
+    ///
+    /// The owner publishes under the tree name; a listed writer publishes
+    /// under the delegated d-tag.
+    async fn root_tags(
+        &self,
+        owner: PublicKey,
+        tree_name: &str,
+        keys: &Keys,
+    ) -> Result<Vec<Tag>, ResolverError> {
+        let label = Tag::custom(
+            TagKind::SingleLetter(SingleLetterTag::lowercase(Alphabet::L)),
+            vec![HASHTREE_LABEL],
+        );
+        if owner == keys.public_key() {
+            return Ok(vec![Tag::identifier(tree_name.to_string()), label]);
+        }
+
+        let candidates = self.fetch_candidates(owner, tree_name).await?;
+        if !candidates.writers().contains(&keys.public_key()) {
+            return Err(ResolverError::NotAuthorized);
+        }
+        Ok(vec![
+            Tag::identifier(delegated_d_tag(&owner, tree_name)),
+            label,
+            Tag::public_key(owner),
+        ])
+    }
+
+    /// Sign and send an event, feeding it to a live subscription for `key`
+    async fn send_tree_event(
+        &self,
+        key: &str,
+        keys: &Keys,
+        tags: Vec<Tag>,
+    ) -> Result<bool, ResolverError> {
+        let event = EventBuilder::new(Kind::Custom(HASHTREE_KIND), "", tags)
+            .to_event(keys)
+            .map_err(|e| ResolverError::Other(e.to_string()))?;
+
+        let output = self
+            .client
+            .send_event(event.clone())
+            .await
+            .map_err(|e| ResolverError::Network(e.to_string()))?;
+
+        // Update local subscription state
+        {
+            let mut subs = self.subscriptions.write().await;
+            if let Some(sub) = subs.get_mut(key) {
+                if sub.candidates.add(&event) {
+                    let new_cid = sub
+                        .candidates
+                        .latest()
+                        .and_then(|latest| Self::cid_from_event_with_keys(latest, Some(keys)));
+                    if new_cid != sub.current_cid {
+                        sub.current_cid = new_cid.clone();
+                        let _ = sub.tx.send(new_cid).await;
+                    }
+                }
+            }
+        }
+
+        Ok(!output.failed.is_empty() || !output.success.is_empty())
+    }
+
+    /// Publish a root, linking it to the root it replaces with a `prev` tag
+    ///
+    /// The `prev` tag is advisory: it lets readers walk a tree's history
+    /// without having seen every intermediate event.
+    pub async fn publish_with_prev(
+        &self,
+        key: &str,
+        cid: &Cid,
+        prev: Option<&Hash>,
+    ) -> Result<bool, ResolverError> {
+        let (pubkey, tree_name) = Self::parse_key(key)?;
+
+        // Owners and listed writers may publish
+        let keys = self
+            .config
+            .secret_key
+            .as_ref()
+            .ok_or(ResolverError::NotAuthorized)?;
+        let mut tags = self.root_tags(pubkey, &tree_name, keys).await?;
+        tags.push(Tag::custom(
+            TagKind::Custom(TAG_HASH.into()),
+            vec![to_hex(&cid.hash)],
+        ));
+
+        // Add key tag if present
+        if let Some(key) = cid.key {
+            tags.push(Tag::custom(
+                TagKind::Custom(TAG_KEY.into()),
+                vec![hex::encode(key)],
+            ));
+        }
+
+        if let Some(prev) = prev.filter(|prev| **prev != cid.hash) {
+            tags.push(Tag::custom(
+                TagKind::Custom(TAG_PREV.into()),
+                vec![to_hex(prev)],
+            ));
+        }
+
+        // Content is empty - all data in tags
+        self.send_tree_event(key, keys, tags).await
+    }
+
     /// Resolve a key, waiting indefinitely until found.
     ///
     /// Unlike `resolve()` which returns `None` after timeout, this method
@@ -375,57 +670,24 @@ impl NostrRootResolver {
 impl RootResolver for NostrRootResolver {
     async fn resolve(&self, key: &str) -> Result<Option<Cid>, ResolverError> {
         let (pubkey, tree_name) = Self::parse_key(key)?;
+        let candidates = self.fetch_candidates(pubkey, &tree_name).await?;
 
-        // Create filter for this specific tree
-        let filter = Filter::new()
-            .kind(Kind::Custom(HASHTREE_KIND))
-            .author(pubkey)
-            .custom_tag(
-                SingleLetterTag::lowercase(Alphabet::D),
-                vec![tree_name.clone()],
-            );
-
-        // Fetch events from relays
-        let source = EventSource::relays(Some(self.config.resolve_timeout));
-        let events = self
-            .client
-            .get_events_of(vec![filter], source)
-            .await
-            .map_err(|e| ResolverError::Network(e.to_string()))?;
-
-        // Find the latest event with matching d-tag
-        let mut latest_event: Option<&Event> = None;
-        let mut latest_created_at = Timestamp::from(0);
-
-        for event in events.iter() {
-            // Verify d-tag matches
-            let d_tag = event.tags.iter().find_map(|tag| {
-                if let Some(TagStandard::Identifier(id)) = tag.as_standardized() {
-                    Some(id.clone())
-                } else {
-                    None
-                }
-            });
-
-            if d_tag.as_deref() != Some(&tree_name) {
-                continue;
-            }
-
-            if !is_hashtree_event(event) {
-                continue;
-            }
-
-            if event.created_at > latest_created_at {
-                latest_created_at = event.created_at;
-                latest_event = Some(event);
-            }
-        }
+        // Extract Cid from the latest root event's tags
+        Ok(candidates
+            .latest()
+            .and_then(|event| self.cid_from_event(event)))
+    }
 
-        // Extract Cid from event tags
-        match latest_event {
-            Some(event) => Ok(self.cid_from_event(event)),
-            None => Ok(None),
-        }
+    async fn resolve_root(&self, key: &str) -> Result<Option<ResolvedRoot>, ResolverError> {
+        let (pubkey, tree_name) = Self::parse_key(key)?;
+        let candidates = self.fetch_candidates(pubkey, &tree_name).await?;
+
+        Ok(candidates.latest().and_then(|event| {
+            Some(ResolvedRoot {
+                cid: self.cid_from_event(event)?,
+                created_at: Some(event.created_at.as_u64()),
+            })
+        }))
     }
 
     async fn resolve_shared(
@@ -434,52 +696,11 @@ impl RootResolver for NostrRootResolver {
         share_secret: &[u8; 32],
     ) -> Result<Option<Cid>, ResolverError> {
         let (pubkey, tree_name) = Self::parse_key(key)?;
+        let candidates = self.fetch_candidates(pubkey, &tree_name).await?;
 
-        let filter = Filter::new()
-            .kind(Kind::Custom(HASHTREE_KIND))
-            .author(pubkey)
-            .custom_tag(
-                SingleLetterTag::lowercase(Alphabet::D),
-                vec![tree_name.clone()],
-            );
-
-        let source = EventSource::relays(Some(self.config.resolve_timeout));
-        let events = self
-            .client
-            .get_events_of(vec![filter], source)
-            .await
-            .map_err(|e| ResolverError::Network(e.to_string()))?;
-
-        let mut latest_event: Option<&Event> = None;
-        let mut latest_created_at = Timestamp::from(0);
-
-        for event in events.iter() {
-            let d_tag = event.tags.iter().find_map(|tag| {
-                if let Some(TagStandard::Identifier(id)) = tag.as_standardized() {
-                    Some(id.clone())
-                } else {
-                    None
-                }
-            });
-
-            if d_tag.as_deref() != Some(&tree_name) {
-                continue;
-            }
-
-            if !is_hashtree_event(event) {
-                continue;
-            }
-
-            if event.created_at > latest_created_at {
-                latest_created_at = event.created_at;
-                latest_event = Some(event);
-            }
-        }
-
-        match latest_event {
-            Some(event) => Ok(Self::cid_from_event_shared(event, share_secret)),
-            None => Ok(None),
-        }
+        Ok(candidates
+            .latest()
+            .and_then(|event| Self::cid_from_event_shared(event, share_secret)))
     }
 
     async fn subscribe(&self, key: &str) -> Result<mpsc::Receiver<Option<Cid>>, ResolverError> {
@@ -498,14 +719,8 @@ impl RootResolver for NostrRootResolver {
             }
         }
 
-        // Create filter
-        let filter = Filter::new()
-            .kind(Kind::Custom(HASHTREE_KIND))
-            .author(pubkey)
-            .custom_tag(
-                SingleLetterTag::lowercase(Alphabet::D),
-                vec![tree_name.clone()],
-            );
+        // Owner roots, the writer list and delegated roots
+        let filters = root_filters(&pubkey, &tree_name);
 
         // Store subscription state
         {
@@ -515,7 +730,7 @@ impl RootResolver for NostrRootResolver {
                 Subscription {
                     tx: tx.clone(),
                     current_cid: None,
-                    latest_created_at: Timestamp::from(0),
+                    candidates: RootCandidates::new(pubkey, &tree_name),
                 },
             );
         }
@@ -523,13 +738,12 @@ impl RootResolver for NostrRootResolver {
         // Subscribe to events
         let subscriptions = self.subscriptions.clone();
         let key_clone = key.to_string();
-        let tree_name_clone = tree_name.clone();
         let secret_key = self.config.secret_key.clone();
 
         // Spawn subscription handler
         let client = self.client.clone();
         tokio::spawn(async move {
-            let sub_id = client.subscribe(vec![filter], None).await;
+            let sub_id = client.subscribe(filters, None).await;
 
             if sub_id.is_err() {
                 return;
@@ -540,32 +754,18 @@ impl RootResolver for NostrRootResolver {
 
             while let Ok(notification) = notifications.recv().await {
                 if let RelayPoolNotification::Event { event, .. } = notification {
-                    // Verify d-tag matches
-                    let d_tag = event.tags.iter().find_map(|tag| {
-                        if let Some(TagStandard::Identifier(id)) = tag.as_standardized() {
-                            Some(id.clone())
-                        } else {
-                            None
-                        }
-                    });
-
-                    if d_tag.as_deref() != Some(&tree_name_clone) {
-                        continue;
-                    }
-
-                    if !is_hashtree_event(&event) {
-                        continue;
-                    }
-
                     let mut subs = subscriptions.write().await;
                     if let Some(sub) = subs.get_mut(&key_clone) {
-                        let new_cid = NostrRootResolver::cid_from_event_with_keys(
-                            &event,
-                            secret_key.as_ref(),
-                        );
-                        if event.created_at >= sub.latest_created_at && new_cid != sub.current_cid {
+                        // Ignores other trees; a new writer list can change
+                        // the winner without a new root
+                        if !sub.candidates.add(&event) {
+                            continue;
+                        }
+                        let new_cid = sub.candidates.latest().and_then(|latest| {
+                            NostrRootResolver::cid_from_event_with_keys(latest, secret_key.as_ref())
+                        });
+                        if new_cid != sub.current_cid {
                             sub.current_cid = new_cid.clone();
-                            sub.latest_created_at = event.created_at;
 
                             if sub.tx.send(new_cid).await.is_err() {
                                 // Receiver dropped, clean up
@@ -584,95 +784,96 @@ impl RootResolver for NostrRootResolver {
     }
 
     async fn publish(&self, key: &str, cid: &Cid) -> Result<bool, ResolverError> {
-        let (pubkey, tree_name) = Self::parse_key(key)?;
+        self.publish_with_prev(key, cid, None).await
+    }
 
-        // Check we own this key
-        let my_pubkey = self.pubkey().ok_or(ResolverError::NotAuthorized)?;
-        if pubkey != my_pubkey {
-            return Err(ResolverError::NotAuthorized);
-        }
+    async fn publish_shared(
+        &self,
+        key: &str,
+        cid: &Cid,
+        share_secret: &[u8; 32],
+    ) -> Result<bool, ResolverError> {
+        let (pubkey, tree_name) = Self::parse_key(key)?;
 
-        // Build event with tags
-        let mut tags = vec![
-            Tag::identifier(tree_name.clone()),
-            Tag::custom(
-                TagKind::SingleLetter(SingleLetterTag::lowercase(Alphabet::L)),
-                vec![HASHTREE_LABEL],
-            ),
-            Tag::custom(TagKind::Custom(TAG_HASH.into()), vec![to_hex(&cid.hash)]),
-        ];
+        let keys = self
+            .config
+            .secret_key
+            .as_ref()
+            .ok_or(ResolverError::NotAuthorized)?;
+        let mut tags = self.root_tags(pubkey, &tree_name, keys).await?;
+        tags.push(Tag::custom(
+            TagKind::Custom(TAG_HASH.into()),
+            vec![to_hex(&cid.hash)],
+        ));
 
-        // Add key tag if present
+        // Mask the key with share_secret (XOR)
         if let Some(key) = cid.key {
+            let masked = xor_keys(&key, share_secret);
             tags.push(Tag::custom(
-                TagKind::Custom(TAG_KEY.into()),
-                vec![hex::encode(key)],
+                TagKind::Custom(TAG_ENCRYPTED_KEY.into()),
+                vec![hex::encode(masked)],
             ));
         }
 
-        // Content is empty - all data in tags
-        let event = EventBuilder::new(Kind::Custom(HASHTREE_KIND), "", tags);
+        self.send_tree_event(key, keys, tags).await
+    }
 
-        // Publish
-        let output = self
+    async fn writers(&self, key: &str) -> Result<Vec<String>, ResolverError> {
+        let (pubkey, tree_name) = Self::parse_key(key)?;
+
+        let filter = Filter::new()
+            .kind(Kind::Custom(HASHTREE_KIND))
+            .author(pubkey)
+            .custom_tag(
+                SingleLetterTag::lowercase(Alphabet::D),
+                vec![writers_d_tag(&tree_name)],
+            );
+        let source = EventSource::relays(Some(self.config.resolve_timeout));
+        let events = self
             .client
-            .send_event_builder(event)
+            .get_events_of(vec![filter], source)
             .await
             .map_err(|e| ResolverError::Network(e.to_string()))?;
 
-        // Update local subscription state
-        {
-            let mut subs = self.subscriptions.write().await;
-            if let Some(sub) = subs.get_mut(key) {
-                sub.current_cid = Some(cid.clone());
-                sub.latest_created_at = Timestamp::now();
-                let _ = sub.tx.send(Some(cid.clone())).await;
-            }
+        let mut candidates = RootCandidates::new(pubkey, &tree_name);
+        for event in events.iter() {
+            candidates.add(event);
         }
-
-        Ok(!output.failed.is_empty() || !output.success.is_empty())
+        candidates
+            .writers()
+            .iter()
+            .map(|writer| {
+                writer
+                    .to_bech32()
+                    .map_err(|e| ResolverError::Other(e.to_string()))
+            })
+            .collect()
     }
 
-    async fn publish_shared(
-        &self,
-        key: &str,
-        cid: &Cid,
-        share_secret: &[u8; 32],
-    ) -> Result<bool, ResolverError> {
+    async fn set_writers(&self, key: &str, writers: &[String]) -> Result<bool, ResolverError> {
         let (pubkey, tree_name) = Self::parse_key(key)?;
 
-        let my_pubkey = self.pubkey().ok_or(ResolverError::NotAuthorized)?;
-        if pubkey != my_pubkey {
+        let keys = self
+            .config
+            .secret_key
+            .as_ref()
+            .ok_or(ResolverError::NotAuthorized)?;
+        if pubkey != keys.public_key() {
             return Err(ResolverError::NotAuthorized);
         }
 
         let mut tags = vec![
-            Tag::identifier(tree_name.clone()),
+            Tag::identifier(writers_d_tag(&tree_name)),
             Tag::custom(
                 TagKind::SingleLetter(SingleLetterTag::lowercase(Alphabet::L)),
-                vec![HASHTREE_LABEL],
+                vec![WRITERS_LABEL],
             ),
-            Tag::custom(TagKind::Custom(TAG_HASH.into()), vec![to_hex(&cid.hash)]),
         ];
-
-        // Mask the key with share_secret (XOR)
-        if let Some(key) = cid.key {
-            let masked = xor_keys(&key, share_secret);
-            tags.push(Tag::custom(
-                TagKind::Custom(TAG_ENCRYPTED_KEY.into()),
-                vec![hex::encode(masked)],
-            ));
+        for writer in writers {
+            tags.push(Tag::public_key(parse_pubkey(writer)?));
         }
 
-        let event = EventBuilder::new(Kind::Custom(HASHTREE_KIND), "", tags);
-
-        let output = self
-            .client
-            .send_event_builder(event)
-            .await
-            .map_err(|e| ResolverError::Network(e.to_string()))?;
-
-        Ok(!output.failed.is_empty() || !output.success.is_empty())
+        self.send_tree_event(key, keys, tags).await
     }
 
     async fn list(&self, prefix: &str) -> Result<Vec<ResolverEntry>, ResolverError> {
@@ -713,6 +914,11 @@ impl RootResolver for NostrRootResolver {
                 }
             });
 
+            // Roots published for other owners' trees
+            if is_delegated_root(event) {
+                continue;
+            }
+
             if let Some(d_tag) = d_tag {
                 let existing = entries_by_d_tag.get(&d_tag);
                 if existing.is_none() || existing.unwrap().created_at < event.created_at {
@@ -772,4 +978,91 @@ mod tests {
         let result = NostrRootResolver::parse_key(key);
         assert!(result.is_err());
     }
+
+    fn root_event(keys: &Keys, d: &str, hash: &str, owner: Option<&Keys>, secs: u64) -> Event {
+        let mut tags = vec![
+            Tag::identifier(d.to_string()),
+            Tag::custom(
+                TagKind::SingleLetter(SingleLetterTag::lowercase(Alphabet::L)),
+                vec![HASHTREE_LABEL],
+            ),
+            Tag::custom(TagKind::Custom(TAG_HASH.into()), vec![hash.to_string()]),
+        ];
+        if let Some(owner) = owner {
+            tags.push(Tag::public_key(owner.public_key()));
+        }
+        EventBuilder::new(Kind::Custom(HASHTREE_KIND), "", tags)
+            .custom_created_at(Timestamp::from_secs(secs))
+            .to_event(keys)
+            .unwrap()
+    }
+
+    fn writer_list(owner: &Keys, tree_name: &str, writers: &[&Keys], secs: u64) -> Event {
+        let mut tags = vec![
+            Tag::identifier(writers_d_tag(tree_name)),
+            Tag::custom(
+                TagKind::SingleLetter(SingleLetterTag::lowercase(Alphabet::L)),
+                vec![WRITERS_LABEL],
+            ),
+        ];
+        tags.extend(writers.iter().map(|w| Tag::public_key(w.public_key())));
+        EventBuilder::new(Kind::Custom(HASHTREE_KIND), "", tags)
+            .custom_created_at(Timestamp::from_secs(secs))
+            .to_event(owner)
+            .unwrap()
+    }
+
+    fn latest_hash(candidates: &RootCandidates) -> Option<String> {
+        let cid = NostrRootResolver::cid_from_event_with_keys(candidates.latest()?, None)?;
+        Some(to_hex(&cid.hash))
+    }
+
+    #[test]
+    fn test_delegated_writers() {
+        let owner = Keys::generate();
+        let writer = Keys::generate();
+        let stranger = Keys::generate();
+        let delegated = delegated_d_tag(&owner.public_key(), "team-docs");
+        let (h1, h2, h3) = ("11".repeat(32), "22".repeat(32), "33".repeat(32));
+
+        let mut candidates = RootCandidates::new(owner.public_key(), "team-docs");
+        assert!(candidates.add(&root_event(&owner, "team-docs", &h1, None, 100)));
+        // Unlisted writers are remembered but don't win
+        assert!(candidates.add(&root_event(&writer, &delegated, &h2, Some(&owner), 200)));
+        assert!(candidates.add(&root_event(&stranger, &delegated, &h3, Some(&owner), 300)));
+        assert_eq!(latest_hash(&candidates), Some(h1.clone()));
+
+        // Listing the writer admits its earlier root
+        assert!(candidates.add(&writer_list(&owner, "team-docs", &[&writer], 150)));
+        assert_eq!(latest_hash(&candidates), Some(h2.clone()));
+
+        // Other trees and older events are ignored
+        assert!(!candidates.add(&root_event(&owner, "other", &h3, None, 400)));
+        assert!(!candidates.add(&root_event(&writer, &delegated, &h1, Some(&owner), 50)));
+
+        // Revocation: a newer list without the writer
+        assert!(candidates.add(&writer_list(&owner, "team-docs", &[], 250)));
+        assert_eq!(latest_hash(&candidates), Some(h1));
+        assert!(!candidates.add(&writer_list(&owner, "team-docs", &[&writer], 120)));
+    }
+
+    #[test]
+    fn test_root_ties_break_on_event_id() {
+        let owner = Keys::generate();
+        let writer = Keys::generate();
+        let delegated = delegated_d_tag(&owner.public_key(), "docs");
+        let a = root_event(&owner, "docs", &"aa".repeat(32), None, 100);
+        let b = root_event(&writer, &delegated, &"bb".repeat(32), Some(&owner), 100);
+
+        let mut candidates = RootCandidates::new(owner.public_key(), "docs");
+        candidates.add(&writer_list(&owner, "docs", &[&writer], 10));
+        candidates.add(&a);
+        candidates.add(&b);
+        let expected = if a.id > b.id { a.id } else { b.id };
+        assert_eq!(candidates.latest().unwrap().id, expected);
+
+        // Delegated roots don't show up in the writer's own tree list
+        assert!(is_delegated_root(&b));
+        assert!(!is_delegated_root(&a));
+    }
 }
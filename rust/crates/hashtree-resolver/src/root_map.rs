@@ -0,0 +1,136 @@
+//! Key -> Cid maps shared by the file and HTTP resolvers
+//!
+//! Both backends read a flat map of keys to CID strings ("hash" or
+//! "hash:key", as printed by `Cid`'s `Display`), e.g.
+//!
+//! ```json
+//! { "local/site": "ab12...", "npub1.../docs": "cd34...:ef56..." }
+//! ```
+//!
+//! and notice changes by polling their source.
+
+use crate::{ResolverEntry, ResolverError};
+use hashtree_core::Cid;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+use tokio::time::MissedTickBehavior;
+
+/// Parsed root map, ordered by key
+pub(crate) type RootMap = BTreeMap<String, Cid>;
+
+/// Parse a JSON object of key -> CID string
+pub(crate) fn parse_json(data: &[u8]) -> Result<RootMap, ResolverError> {
+    let raw: BTreeMap<String, String> = serde_json::from_slice(data)
+        .map_err(|e| ResolverError::Other(format!("Invalid root map: {}", e)))?;
+    from_strings(raw)
+}
+
+/// Parse a TOML table of key -> CID string
+#[cfg(feature = "file")]
+pub(crate) fn parse_toml(text: &str) -> Result<RootMap, ResolverError> {
+    let raw: BTreeMap<String, String> = toml::from_str(text)
+        .map_err(|e| ResolverError::Other(format!("Invalid root map: {}", e)))?;
+    from_strings(raw)
+}
+
+fn from_strings(raw: BTreeMap<String, String>) -> Result<RootMap, ResolverError> {
+    raw.into_iter()
+        .map(|(key, cid)| {
+            let parsed = Cid::parse(&cid)
+                .map_err(|e| ResolverError::Other(format!("Invalid CID for {}: {}", key, e)))?;
+            Ok((key, parsed))
+        })
+        .collect()
+}
+
+/// Render a root map back to key -> CID string
+#[cfg(feature = "file")]
+pub(crate) fn to_strings(map: &RootMap) -> BTreeMap<String, String> {
+    map.iter()
+        .map(|(key, cid)| (key.clone(), cid.to_string()))
+        .collect()
+}
+
+/// Entries whose key starts with `prefix`
+pub(crate) fn list(map: &RootMap, prefix: &str) -> Vec<ResolverEntry> {
+    map.range(prefix.to_string()..)
+        .take_while(|(key, _)| key.starts_with(prefix))
+        .map(|(key, cid)| ResolverEntry {
+            key: key.clone(),
+            cid: cid.clone(),
+        })
+        .collect()
+}
+
+/// Re-read a source every `interval` and forward changed values to `tx`
+///
+/// `read` returns `None` when the source can't be read right now; the last
+/// value is kept until it can. Polling ends when the receiver is dropped or
+/// `stopped` flips to true.
+pub(crate) fn spawn_poller<T, F, Fut>(
+    interval: Duration,
+    stopped: watch::Receiver<bool>,
+    initial: T,
+    tx: mpsc::Sender<T>,
+    read: F,
+) where
+    T: PartialEq + Clone + Send + Sync + 'static,
+    F: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = Option<T>> + Send,
+{
+    tokio::spawn(async move {
+        let mut current = initial;
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        // The first tick completes immediately; the caller already sent `initial`
+        ticker.tick().await;
+
+        loop {
+            ticker.tick().await;
+            if *stopped.borrow() || tx.is_closed() {
+                break;
+            }
+            if let Some(next) = read().await {
+                if next != current {
+                    if tx.send(next.clone()).await.is_err() {
+                        break;
+                    }
+                    current = next;
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hashtree_core::to_hex;
+
+    #[test]
+    fn test_parse_json_and_list_prefix() {
+        let hash = [0x11; 32];
+        let key = [0x22; 32];
+        let json = format!(
+            r#"{{"local/a": "{}", "local/b": "{}:{}", "other/c": "{}"}}"#,
+            to_hex(&hash),
+            to_hex(&hash),
+            to_hex(&key),
+            to_hex(&hash)
+        );
+        let map = parse_json(json.as_bytes()).unwrap();
+        assert_eq!(map["local/b"].key, Some(key));
+
+        let keys: Vec<String> = list(&map, "local/").into_iter().map(|e| e.key).collect();
+        assert_eq!(keys, vec!["local/a", "local/b"]);
+        assert_eq!(list(&map, "").len(), 3);
+    }
+
+    #[test]
+    fn test_parse_json_rejects_bad_cid() {
+        assert!(parse_json(br#"{"local/a": "not-a-cid"}"#).is_err());
+        assert!(parse_json(b"[1, 2]").is_err());
+    }
+}
@@ -20,17 +20,28 @@ pub enum ResolverError {
     #[error("Resolver stopped")]
     Stopped,
 
+    #[error("No quorum: {0}")]
+    NoQuorum(String),
+
     #[error("Other error: {0}")]
     Other(String),
 }
 
 /// Entry in a resolver list
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ResolverEntry {
     pub key: String,
     pub cid: Cid,
 }
 
+/// A resolved root with its publication time, when the backend knows it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedRoot {
+    pub cid: Cid,
+    /// Unix seconds the root was published at
+    pub created_at: Option<u64>,
+}
+
 /// RootResolver - Maps human-readable keys to content identifiers (Cid)
 ///
 /// This abstraction allows different backends (Nostr, DNS, HTTP, local storage)
@@ -51,6 +62,16 @@ pub trait RootResolver: Send + Sync {
     /// For shared content, pass the share_secret to decrypt the encrypted_key.
     async fn resolve(&self, key: &str) -> Result<Option<Cid>, ResolverError>;
 
+    /// Resolve a key to its current root along with its publication time
+    ///
+    /// Backends that don't track time return `created_at: None`.
+    async fn resolve_root(&self, key: &str) -> Result<Option<ResolvedRoot>, ResolverError> {
+        Ok(self.resolve(key).await?.map(|cid| ResolvedRoot {
+            cid,
+            created_at: None,
+        }))
+    }
+
     /// Resolve with a share secret (for encrypted_key decryption)
     async fn resolve_shared(
         &self,
@@ -90,6 +111,24 @@ pub trait RootResolver: Send + Sync {
         Err(ResolverError::NotAuthorized)
     }
 
+    /// Writers the owner of `key` has authorized to publish roots for it
+    ///
+    /// Identifiers are backend-specific (npubs for Nostr). Backends without
+    /// delegation return an empty list.
+    async fn writers(&self, key: &str) -> Result<Vec<String>, ResolverError> {
+        let _ = key;
+        Ok(vec![])
+    }
+
+    /// Replace the list of delegated writers for a key this resolver owns
+    ///
+    /// Writers left out of the new list are revoked: roots they published
+    /// stop resolving. Pass an empty list to revoke everyone.
+    async fn set_writers(&self, key: &str, writers: &[String]) -> Result<bool, ResolverError> {
+        let _ = (key, writers);
+        Err(ResolverError::NotAuthorized)
+    }
+
     /// List all keys matching a prefix (one-shot)
     ///
     /// Returns array of matching keys with their current Cids.
@@ -26,8 +26,10 @@
 use async_trait::async_trait;
 use aws_sdk_s3::primitives::ByteStream;
 use aws_sdk_s3::Client as S3Client;
-use hashtree_core::store::{Store, StoreError};
-use hashtree_core::types::{to_hex, Hash};
+use futures::stream::{self, StreamExt};
+use hashtree_core::store::{Store, StoreError, BATCH_CONCURRENCY};
+use hashtree_core::types::{from_hex, to_hex, Hash};
+use std::collections::HashSet;
 use std::sync::Arc;
 use tokio::sync::mpsc;
 use tracing::{debug, error, info, warn};
@@ -250,6 +252,44 @@ impl<L: Store + 'static> S3Store<L> {
         }
     }
 
+    /// List all hashes stored under the prefix in S3
+    async fn list_s3(&self) -> Result<Vec<Hash>, S3StoreError> {
+        let mut hashes = Vec::new();
+        let mut continuation: Option<String> = None;
+
+        loop {
+            let output = self
+                .s3_client
+                .list_objects_v2()
+                .bucket(&self.bucket)
+                .prefix(&self.prefix)
+                .set_continuation_token(continuation.take())
+                .send()
+                .await
+                .map_err(|e| S3StoreError::S3(format!("S3 list failed: {}", e)))?;
+
+            for object in output.contents() {
+                let Some(key) = object.key() else { continue };
+                // Skip keys that aren't blob hashes (e.g. other tools sharing the prefix)
+                if let Some(hash) = key
+                    .strip_prefix(&self.prefix)
+                    .and_then(|hex| from_hex(hex).ok())
+                {
+                    hashes.push(hash);
+                }
+            }
+
+            match output.next_continuation_token() {
+                Some(token) if output.is_truncated() == Some(true) => {
+                    continuation = Some(token.to_string());
+                }
+                _ => break,
+            }
+        }
+
+        Ok(hashes)
+    }
+
     /// Queue a blob for upload to S3 (non-blocking)
     fn queue_upload(&self, hash: Hash, data: Vec<u8>) {
         if let Err(e) = self.sync_tx.send(SyncMessage::Upload { hash, data }) {
@@ -336,6 +376,96 @@ impl<L: Store + 'static> Store for S3Store<L> {
 
         Ok(deleted)
     }
+
+    async fn put_many(&self, items: Vec<(Hash, Vec<u8>)>) -> Result<Vec<bool>, StoreError> {
+        let stored = self.local.put_many(items.clone()).await?;
+
+        for ((hash, data), is_new) in items.into_iter().zip(&stored) {
+            if *is_new {
+                self.queue_upload(hash, data);
+            }
+        }
+
+        Ok(stored)
+    }
+
+    async fn get_many(&self, hashes: &[Hash]) -> Result<Vec<Option<Vec<u8>>>, StoreError> {
+        let mut results = self.local.get_many(hashes).await?;
+
+        // Fetch local misses from S3 concurrently
+        let misses: Vec<usize> = (0..hashes.len())
+            .filter(|&i| results[i].is_none())
+            .collect();
+        let fetched: Vec<(usize, Option<Vec<u8>>)> = stream::iter(misses)
+            .map(|i| async move {
+                match self.fetch_from_s3(&hashes[i]).await {
+                    Ok(data) => (i, data),
+                    Err(e) => {
+                        warn!("S3 fetch failed, returning None: {}", e);
+                        (i, None)
+                    }
+                }
+            })
+            .buffer_unordered(BATCH_CONCURRENCY)
+            .collect()
+            .await;
+
+        // Cache locally for future access
+        let mut cache = Vec::new();
+        for (i, data) in fetched {
+            if let Some(data) = data {
+                cache.push((hashes[i], data.clone()));
+                results[i] = Some(data);
+            }
+        }
+        if !cache.is_empty() {
+            let _ = self.local.put_many(cache).await;
+        }
+
+        Ok(results)
+    }
+
+    async fn has_many(&self, hashes: &[Hash]) -> Result<Vec<bool>, StoreError> {
+        let mut results = self.local.has_many(hashes).await?;
+
+        let misses: Vec<usize> = (0..hashes.len()).filter(|&i| !results[i]).collect();
+        let found: Vec<usize> = stream::iter(misses)
+            .map(|i| async move {
+                match self.exists_in_s3(&hashes[i]).await {
+                    Ok(exists) => exists.then_some(i),
+                    Err(e) => {
+                        warn!("S3 exists check failed, returning false: {}", e);
+                        None
+                    }
+                }
+            })
+            .buffer_unordered(BATCH_CONCURRENCY)
+            .filter_map(|i| async move { i })
+            .collect()
+            .await;
+
+        for i in found {
+            results[i] = true;
+        }
+        Ok(results)
+    }
+
+    async fn list(&self) -> Result<Vec<Hash>, StoreError> {
+        // Local blobs may not have been uploaded yet, so merge both listings
+        let mut seen = HashSet::new();
+        let mut hashes = Vec::new();
+        let local = self.local.list().await.unwrap_or_default();
+        let remote = self
+            .list_s3()
+            .await
+            .map_err(|e| StoreError::Other(e.to_string()))?;
+        for hash in local.into_iter().chain(remote) {
+            if seen.insert(hash) {
+                hashes.push(hash);
+            }
+        }
+        Ok(hashes)
+    }
 }
 
 /// S3 store specific errors
@@ -10,7 +10,7 @@ use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::RwLock;
 
-use hashtree_core::{HashTree, HashTreeConfig, MemoryStore};
+use hashtree_core::{sha256, HashTree, HashTreeConfig, MemoryStore, Store};
 use hashtree_webrtc::{
     GenericStore, MockConnectionFactory, MockRelay, MockRelayTransport, PoolConfig, PoolSettings,
     RelayTransport, SignalingManager,
@@ -118,6 +118,63 @@ pub struct SimStats {
     pub events: Vec<SimEvent>,
 }
 
+/// Content routing experiment: blocks placed on random nodes, then fetched
+/// from random nodes' direct peers
+#[derive(Debug, Clone)]
+pub struct FilterExperiment {
+    /// Distinct blocks in the network
+    pub blocks: usize,
+    /// Nodes holding each block
+    pub replicas: usize,
+    /// Fetches to measure
+    pub requests: usize,
+    /// Order peers by their advertised filters (false: connection order only)
+    pub use_filters: bool,
+}
+
+impl Default for FilterExperiment {
+    fn default() -> Self {
+        Self {
+            blocks: 500,
+            replicas: 3,
+            requests: 1000,
+            use_filters: true,
+        }
+    }
+}
+
+/// Results of a [`FilterExperiment`]
+#[derive(Debug, Clone, Default)]
+pub struct FilterStats {
+    /// Fetches measured
+    pub requests: usize,
+    /// Fetches where some direct peer held the block
+    pub found: usize,
+    /// Fetches answered by the first peer asked
+    pub first_hits: usize,
+    /// Requests sent to peers, until a hit or every peer was asked
+    pub peer_requests: usize,
+    /// Requests sent to peers that didn't have the block
+    pub wasted_requests: usize,
+}
+
+impl FilterStats {
+    /// Share of fetches answered by the first peer asked
+    pub fn hit_rate(&self) -> f64 {
+        self.first_hits as f64 / self.requests.max(1) as f64
+    }
+
+    /// Share of findable fetches answered by the first peer asked
+    pub fn found_hit_rate(&self) -> f64 {
+        self.first_hits as f64 / self.found.max(1) as f64
+    }
+
+    /// Peer requests sent per fetch
+    pub fn requests_per_fetch(&self) -> f64 {
+        self.peer_requests as f64 / self.requests.max(1) as f64
+    }
+}
+
 /// Network simulation using GenericStore with mock transports
 ///
 /// Uses the exact same code as production WebRTCStore, just with mocks.
@@ -247,13 +304,11 @@ impl Simulation {
         // Create local storage
         let local_store = Arc::new(MemoryStore::new());
 
-        // Create GenericStore
-        let store = Arc::new(GenericStore::new(
-            local_store,
-            signaling,
-            Duration::from_secs(1),
-            false,
-        ));
+        // Create GenericStore; hellos carry filters for `run_filter_experiment`
+        let store = Arc::new(
+            GenericStore::new(local_store, signaling, Duration::from_secs(1), false)
+                .with_advertise_filter(true),
+        );
 
         // Connect transport and start
         transport.connect(&[]).await.ok();
@@ -506,6 +561,105 @@ impl Simulation {
         self.nodes.read().await.len()
     }
 
+    /// Place blocks on random nodes and measure how well peer ordering finds
+    /// them among each requester's direct peers
+    ///
+    /// With filters on, every node advertises a filter of its store and peers
+    /// are ordered by the production `peer_ids_for`; off, by `peer_ids` only.
+    /// Requests aren't forwarded, so only direct peers count.
+    pub async fn run_filter_experiment(&self, experiment: &FilterExperiment) -> FilterStats {
+        let mut node_ids: Vec<String> = self.nodes.read().await.keys().cloned().collect();
+        node_ids.sort();
+        if node_ids.len() < 2 {
+            return FilterStats::default();
+        }
+
+        // Place blocks
+        let blocks: Vec<Vec<u8>> = (0..experiment.blocks)
+            .map(|i| format!("block {}", i).into_bytes())
+            .collect();
+        {
+            let nodes = self.nodes.read().await;
+            let mut rng = self.rng.write().await;
+            for data in &blocks {
+                for _ in 0..experiment.replicas {
+                    let node_id = &node_ids[rng.gen_range(0..node_ids.len())];
+                    let _ = nodes[node_id].store.put(sha256(data), data.clone()).await;
+                }
+            }
+        }
+
+        // Advertise what each node now holds
+        if experiment.use_filters {
+            for node in self.nodes.read().await.values() {
+                node.store.advertise().await.ok();
+            }
+            for _ in 0..3 {
+                self.process_all_messages().await;
+            }
+        }
+
+        let mut stats = FilterStats::default();
+        let nodes = self.nodes.read().await;
+        for _ in 0..experiment.requests {
+            let (node_id, hash) = {
+                let mut rng = self.rng.write().await;
+                let node_id = &node_ids[rng.gen_range(0..node_ids.len())];
+                let hash = sha256(&blocks[rng.gen_range(0..blocks.len())]);
+                (node_id, hash)
+            };
+            let node = &nodes[node_id];
+            if node.store.has(&hash).await.unwrap_or(false) {
+                continue;
+            }
+
+            let peers = if experiment.use_filters {
+                node.store.signaling().peer_ids_for(&hash).await
+            } else {
+                node.store.signaling().peer_ids().await
+            };
+
+            stats.requests += 1;
+            let mut holders = Vec::new();
+            for peer_id in &peers {
+                if let Some(peer) = nodes.get(peer_id) {
+                    holders.push(peer.store.has(&hash).await.unwrap_or(false));
+                }
+            }
+            match holders.iter().position(|&held| held) {
+                Some(i) => {
+                    stats.found += 1;
+                    stats.peer_requests += i + 1;
+                    stats.wasted_requests += i;
+                    if i == 0 {
+                        stats.first_hits += 1;
+                    }
+                }
+                None => {
+                    stats.peer_requests += holders.len();
+                    stats.wasted_requests += holders.len();
+                }
+            }
+        }
+        stats
+    }
+
+    /// Print filter experiment summary
+    pub fn print_filter_stats(label: &str, stats: &FilterStats) {
+        println!("=== Filter Experiment: {} ===", label);
+        println!(
+            "Fetches: {} (findable among direct peers: {})",
+            stats.requests, stats.found
+        );
+        println!(
+            "First-peer hit rate: {:.3} ({:.3} of findable)",
+            stats.hit_rate(),
+            stats.found_hit_rate()
+        );
+        println!("Requests per fetch: {:.2}", stats.requests_per_fetch());
+        println!("Wasted requests: {}", stats.wasted_requests);
+    }
+
     /// Print topology summary
     pub fn print_topology_stats(stats: &TopologyStats) {
         println!("=== Topology Analysis ===");
@@ -603,6 +757,52 @@ mod tests {
         );
     }
 
+    async fn run_filter_sim(use_filters: bool) -> FilterStats {
+        let config = SimConfig {
+            node_count: 50,
+            duration: Duration::from_secs(3),
+            seed: 7,
+            pool: PoolConfig {
+                max_connections: 10,
+                satisfied_connections: 6,
+            },
+            discovery_interval_ms: 100,
+            churn_rate: 0.0,
+            allow_rejoin: false,
+            network_latency_ms: 0,
+        };
+        let sim = Simulation::new(config);
+        sim.run().await;
+
+        let experiment = FilterExperiment {
+            blocks: 200,
+            replicas: 5,
+            requests: 500,
+            use_filters,
+        };
+        sim.run_filter_experiment(&experiment).await
+    }
+
+    #[tokio::test]
+    async fn test_filter_experiment_hit_rate() {
+        let without = run_filter_sim(false).await;
+        let with = run_filter_sim(true).await;
+
+        println!();
+        Simulation::print_filter_stats("connection order", &without);
+        Simulation::print_filter_stats("bloom filters", &with);
+
+        assert!(with.found > 0, "Some blocks should be held by direct peers");
+        // Only false positives can put a non-holder first
+        assert!(
+            with.found_hit_rate() > 0.95,
+            "Filters should find holders first, got {:.3}",
+            with.found_hit_rate()
+        );
+        assert!(with.hit_rate() > without.hit_rate());
+        assert!(with.wasted_requests < without.wasted_requests);
+    }
+
     #[tokio::test]
     async fn test_webrtc_sim_200_nodes_connectivity() {
         let config = SimConfig {
@@ -0,0 +1,450 @@
+//! Bandwidth accounting and rate limits for P2P traffic
+//!
+//! - Per-peer byte ledgers (uploaded, downloaded, throttled responses)
+//! - Token bucket limits for serving data, globally and per peer
+//! - Separate token bucket limits for requests forwarded on a peer's behalf
+//! - Optional reciprocity: peers that mostly download only get the upload
+//!   bandwidth that peers who give back leave unused
+//!
+//! Buckets refill continuously at their rate and hold one second of traffic.
+//! A response larger than a bucket is still sent once the bucket is full,
+//! leaving it in debt, so the average rate holds for any block size.
+//!
+//! Ledgers and per-peer buckets are keyed by pubkey (see [`ledger_key`]) and
+//! kept across disconnects, so reconnecting with a new session neither resets
+//! a leecher's history nor refills its buckets. The least recently active
+//! peers are forgotten once `MAX_TRACKED_PEERS` are known.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Share of the global upload bucket reserved for non-leeching peers
+const LEECHER_RESERVE: f64 = 0.5;
+
+/// Peers whose ledgers are kept; the least recently active are dropped first
+pub const MAX_TRACKED_PEERS: usize = 4096;
+
+/// Ledger key for a peer id
+///
+/// The pubkey of a "pubkey:uuid" WebRTC peer id, so every session of a peer
+/// shares one ledger; other ids (e.g. a WebSocket peer's "host:port") are
+/// used as they are.
+pub fn ledger_key(peer_id: &str) -> &str {
+    match peer_id.split_once(':') {
+        Some((pubkey, _))
+            if pubkey.len() == 64 && pubkey.bytes().all(|b| b.is_ascii_hexdigit()) =>
+        {
+            pubkey
+        }
+        _ => peer_id,
+    }
+}
+
+/// Rate limit and reciprocity settings (rates in bytes/sec, 0 = unlimited)
+#[derive(Debug, Clone, PartialEq)]
+pub struct BandwidthConfig {
+    /// Upload limit across all peers
+    pub upload_bps: u64,
+    /// Upload limit for each peer
+    pub peer_upload_bps: u64,
+    /// Limit on data fetched for forwarded requests, across all peers
+    pub forward_bps: u64,
+    /// Limit on data fetched for forwarded requests, for each requesting peer
+    pub peer_forward_bps: u64,
+    /// Deprioritize peers that download much more than they upload
+    pub reciprocity: bool,
+    /// Bytes a peer may download before reciprocity applies
+    pub reciprocity_grace_bytes: u64,
+    /// A peer leeches once its downloads exceed its uploads by this factor
+    pub reciprocity_ratio: f64,
+}
+
+impl Default for BandwidthConfig {
+    fn default() -> Self {
+        Self {
+            upload_bps: 0,
+            peer_upload_bps: 0,
+            forward_bps: 0,
+            peer_forward_bps: 0,
+            reciprocity: false,
+            reciprocity_grace_bytes: 16 * 1024 * 1024,
+            reciprocity_ratio: 4.0,
+        }
+    }
+}
+
+impl BandwidthConfig {
+    /// Whether any limit or policy is configured
+    pub fn is_unlimited(&self) -> bool {
+        self.upload_bps == 0
+            && self.peer_upload_bps == 0
+            && self.forward_bps == 0
+            && self.peer_forward_bps == 0
+            && !self.reciprocity
+    }
+}
+
+/// Token bucket holding one second of traffic at `rate` bytes/sec
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    rate: u64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// Create a full bucket (rate 0 = unlimited)
+    pub fn new(rate: u64) -> Self {
+        Self {
+            rate,
+            tokens: rate as f64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Rate in bytes/sec (0 = unlimited)
+    pub fn rate(&self) -> u64 {
+        self.rate
+    }
+
+    /// Currently available bytes (negative while in debt)
+    pub fn available(&mut self) -> f64 {
+        self.refill();
+        self.tokens
+    }
+
+    fn capacity(&self) -> f64 {
+        self.rate as f64
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.last_refill = now;
+        self.tokens = (self.tokens + elapsed * self.rate as f64).min(self.capacity());
+    }
+
+    /// Whether `bytes` may be sent while keeping `reserve` (a share of the
+    /// capacity) untouched
+    pub fn allows(&mut self, bytes: u64, reserve: f64) -> bool {
+        if self.rate == 0 {
+            return true;
+        }
+        self.refill();
+        let reserved = self.capacity() * reserve;
+        let needed = (bytes as f64).min(self.capacity() - reserved);
+        self.tokens - reserved >= needed
+    }
+
+    /// Spend `bytes`, going into debt if needed
+    pub fn consume(&mut self, bytes: u64) {
+        if self.rate == 0 {
+            return;
+        }
+        self.refill();
+        self.tokens -= bytes as f64;
+    }
+}
+
+/// Bytes exchanged with one peer
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ByteLedger {
+    /// Bytes sent to the peer
+    pub uploaded: u64,
+    /// Bytes received from the peer
+    pub downloaded: u64,
+    /// Requests refused because a rate limit was hit
+    pub throttled: u64,
+}
+
+impl ByteLedger {
+    /// Whether the peer took far more than it gave
+    pub fn is_leecher(&self, config: &BandwidthConfig) -> bool {
+        self.uploaded > config.reciprocity_grace_bytes
+            && self.uploaded as f64 > self.downloaded as f64 * config.reciprocity_ratio
+    }
+}
+
+#[derive(Debug)]
+struct PeerBandwidth {
+    ledger: ByteLedger,
+    upload: TokenBucket,
+    forward: TokenBucket,
+    last_active: Instant,
+}
+
+#[derive(Debug)]
+struct LimiterState {
+    upload: TokenBucket,
+    forward: TokenBucket,
+    peers: HashMap<String, PeerBandwidth>,
+}
+
+fn peer_entry<'a>(
+    peers: &'a mut HashMap<String, PeerBandwidth>,
+    peer_id: &str,
+    config: &BandwidthConfig,
+) -> &'a mut PeerBandwidth {
+    let key = ledger_key(peer_id);
+    if !peers.contains_key(key) && peers.len() >= MAX_TRACKED_PEERS {
+        let idle = peers
+            .iter()
+            .min_by_key(|(_, peer)| peer.last_active)
+            .map(|(id, _)| id.clone());
+        if let Some(idle) = idle {
+            peers.remove(&idle);
+        }
+    }
+    let peer = peers
+        .entry(key.to_string())
+        .or_insert_with(|| PeerBandwidth {
+            ledger: ByteLedger::default(),
+            upload: TokenBucket::new(config.peer_upload_bps),
+            forward: TokenBucket::new(config.peer_forward_bps),
+            last_active: Instant::now(),
+        });
+    peer.last_active = Instant::now();
+    peer
+}
+
+/// Shared rate limiter and byte ledger for all peers of a node
+#[derive(Debug)]
+pub struct BandwidthLimiter {
+    config: BandwidthConfig,
+    state: Mutex<LimiterState>,
+}
+
+impl Default for BandwidthLimiter {
+    fn default() -> Self {
+        Self::new(BandwidthConfig::default())
+    }
+}
+
+impl BandwidthLimiter {
+    /// Create a limiter with full buckets
+    pub fn new(config: BandwidthConfig) -> Self {
+        let state = LimiterState {
+            upload: TokenBucket::new(config.upload_bps),
+            forward: TokenBucket::new(config.forward_bps),
+            peers: HashMap::new(),
+        };
+        Self {
+            config,
+            state: Mutex::new(state),
+        }
+    }
+
+    /// Configured limits
+    pub fn config(&self) -> &BandwidthConfig {
+        &self.config
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, LimiterState> {
+        // The state stays consistent even if a holder panicked
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Reserve for the global buckets when serving `ledger`'s peer
+    fn reserve_for(&self, ledger: &ByteLedger) -> f64 {
+        if self.config.reciprocity && ledger.is_leecher(&self.config) {
+            LEECHER_RESERVE
+        } else {
+            0.0
+        }
+    }
+
+    /// Ask to send a `bytes` response to a peer
+    ///
+    /// Returns true (and records the upload) if the limits allow it;
+    /// otherwise counts a throttled request.
+    pub fn try_serve(&self, peer_id: &str, bytes: u64) -> bool {
+        let mut state = self.state();
+        let state = &mut *state;
+        let peer = peer_entry(&mut state.peers, peer_id, &self.config);
+        let reserve = self.reserve_for(&peer.ledger);
+
+        if !peer.upload.allows(bytes, 0.0) || !state.upload.allows(bytes, reserve) {
+            peer.ledger.throttled += 1;
+            return false;
+        }
+        peer.upload.consume(bytes);
+        state.upload.consume(bytes);
+        peer.ledger.uploaded += bytes;
+        true
+    }
+
+    /// Ask to forward a request for a peer to other peers
+    ///
+    /// The response size is unknown up front, so this only checks that the
+    /// forward buckets are not in debt; charge the result with
+    /// [`record_forwarded`](Self::record_forwarded).
+    pub fn try_forward(&self, peer_id: &str) -> bool {
+        let mut state = self.state();
+        let state = &mut *state;
+        let peer = peer_entry(&mut state.peers, peer_id, &self.config);
+        let reserve = self.reserve_for(&peer.ledger);
+
+        if !peer.forward.allows(1, 0.0) || !state.forward.allows(1, reserve) {
+            peer.ledger.throttled += 1;
+            return false;
+        }
+        true
+    }
+
+    /// Charge data fetched from other peers on behalf of `peer_id`
+    pub fn record_forwarded(&self, peer_id: &str, bytes: u64) {
+        let mut state = self.state();
+        state.forward.consume(bytes);
+        peer_entry(&mut state.peers, peer_id, &self.config)
+            .forward
+            .consume(bytes);
+    }
+
+    /// Record bytes received from a peer
+    pub fn record_received(&self, peer_id: &str, bytes: u64) {
+        peer_entry(&mut self.state().peers, peer_id, &self.config)
+            .ledger
+            .downloaded += bytes;
+    }
+
+    /// Whether reciprocity currently deprioritizes a peer
+    pub fn is_leecher(&self, peer_id: &str) -> bool {
+        self.config.reciprocity
+            && self
+                .state()
+                .peers
+                .get(ledger_key(peer_id))
+                .is_some_and(|p| p.ledger.is_leecher(&self.config))
+    }
+
+    /// Byte ledger for a peer
+    pub fn ledger(&self, peer_id: &str) -> Option<ByteLedger> {
+        self.state()
+            .peers
+            .get(ledger_key(peer_id))
+            .map(|p| p.ledger.clone())
+    }
+
+    /// Byte ledgers of all tracked peers, by ledger key
+    pub fn ledgers(&self) -> BTreeMap<String, ByteLedger> {
+        self.state()
+            .peers
+            .iter()
+            .map(|(id, p)| (id.clone(), p.ledger.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unlimited_serves_everything() {
+        let limiter = BandwidthLimiter::default();
+        for _ in 0..100 {
+            assert!(limiter.try_serve("a", 1 << 20));
+        }
+        assert!(limiter.try_forward("a"));
+        let ledger = limiter.ledger("a").unwrap();
+        assert_eq!(ledger.uploaded, 100 << 20);
+        assert_eq!(ledger.throttled, 0);
+    }
+
+    #[test]
+    fn test_per_peer_limit_throttles_only_that_peer() {
+        let limiter = BandwidthLimiter::new(BandwidthConfig {
+            peer_upload_bps: 1000,
+            ..Default::default()
+        });
+        assert!(limiter.try_serve("a", 600));
+        assert!(!limiter.try_serve("a", 600));
+        assert!(limiter.try_serve("b", 600));
+
+        assert_eq!(limiter.ledger("a").unwrap().throttled, 1);
+        assert_eq!(limiter.ledger("a").unwrap().uploaded, 600);
+        assert_eq!(limiter.ledger("b").unwrap().throttled, 0);
+    }
+
+    #[test]
+    fn test_global_limit_and_oversized_response() {
+        let limiter = BandwidthLimiter::new(BandwidthConfig {
+            upload_bps: 1000,
+            ..Default::default()
+        });
+        // Bigger than the bucket: sent from a full bucket, leaving debt
+        assert!(limiter.try_serve("a", 5000));
+        assert!(!limiter.try_serve("b", 10));
+        assert_eq!(limiter.ledger("b").unwrap().throttled, 1);
+    }
+
+    #[test]
+    fn test_forward_debt_blocks_further_forwarding() {
+        let limiter = BandwidthLimiter::new(BandwidthConfig {
+            peer_forward_bps: 1000,
+            ..Default::default()
+        });
+        assert!(limiter.try_forward("a"));
+        limiter.record_forwarded("a", 4000);
+        assert!(!limiter.try_forward("a"));
+        assert!(limiter.try_forward("b"));
+    }
+
+    #[test]
+    fn test_reciprocity_deprioritizes_leechers() {
+        let limiter = BandwidthLimiter::new(BandwidthConfig {
+            upload_bps: 1000,
+            reciprocity: true,
+            reciprocity_grace_bytes: 100,
+            reciprocity_ratio: 2.0,
+            ..Default::default()
+        });
+        assert!(limiter.try_serve("leech", 300));
+        assert!(limiter.try_serve("giver", 300));
+        limiter.record_received("giver", 300);
+        assert!(limiter.is_leecher("leech"));
+        assert!(!limiter.is_leecher("giver"));
+
+        // The leecher can't dip into the reserved half of the bucket...
+        assert!(!limiter.try_serve("leech", 50));
+        // ...which stays available to the peer that gives back
+        assert!(limiter.try_serve("giver", 400));
+    }
+
+    #[test]
+    fn test_ledger_survives_reconnect_with_new_session() {
+        let limiter = BandwidthLimiter::new(BandwidthConfig {
+            peer_upload_bps: 1000,
+            ..Default::default()
+        });
+        let pubkey = "ab".repeat(32);
+        assert!(limiter.try_serve(&format!("{}:session1", pubkey), 1000));
+        // A new session id doesn't come with a fresh bucket or ledger
+        let session2 = format!("{}:session2", pubkey);
+        assert!(!limiter.try_serve(&session2, 1000));
+        let ledger = limiter.ledger(&session2).unwrap();
+        assert_eq!(ledger.uploaded, 1000);
+        assert_eq!(ledger.throttled, 1);
+        assert!(limiter.ledgers().contains_key(&pubkey));
+
+        // WebSocket peers are keyed by "host:port" as is
+        assert_eq!(ledger_key("127.0.0.1:8080"), "127.0.0.1:8080");
+    }
+
+    #[test]
+    fn test_tracked_peers_are_bounded() {
+        let limiter = BandwidthLimiter::default();
+        limiter.record_received("first", 1);
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        for i in 0..MAX_TRACKED_PEERS {
+            limiter.record_received(&format!("peer{}", i), 1);
+        }
+        assert_eq!(limiter.ledgers().len(), MAX_TRACKED_PEERS);
+        assert!(limiter.ledger("first").is_none());
+        assert!(limiter
+            .ledger(&format!("peer{}", MAX_TRACKED_PEERS - 1))
+            .is_some());
+    }
+}
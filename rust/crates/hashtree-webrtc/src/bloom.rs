@@ -0,0 +1,514 @@
+//! Bloom filters of held hashes, advertised in hello messages
+//!
+//! Hellos can carry a compact filter of the roots and chunk hashes a peer
+//! stores (opt-in). Requests go to peers whose filter (or root list) matches
+//! the hash first, then to everyone else in the usual order, so false
+//! positives and stale filters only cost a little ordering, never a miss.
+//!
+//! Filters are capped at `MAX_FILTER_BYTES`, which holds about
+//! `MAX_ADVERTISED_HASHES` hashes at the default 1% rate. Bigger stores
+//! advertise only their roots and pinned hashes, and no filter is sent once
+//! its estimated false positive rate would pass
+//! `MAX_ADVERTISED_FALSE_POSITIVE_RATE`. [`FilterCache`] keeps the last
+//! filter until the store's item count or size changes, so hellos don't list
+//! the whole store each time.
+//!
+//! Hashes are SHA-256 digests, so bit positions are taken straight from their
+//! bytes (double hashing) instead of hashing them again.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use hashtree_core::{to_hex, Hash, Store};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// False positive rate filters are sized for
+pub const DEFAULT_FALSE_POSITIVE_RATE: f64 = 0.01;
+
+/// Largest filter we build or accept (64 Kibit, about 6800 hashes at 1%).
+/// Keeps hellos well inside relay event size limits; bigger stores get a
+/// higher false positive rate instead of a bigger filter.
+pub const MAX_FILTER_BYTES: usize = 8 * 1024;
+
+/// Most hashes a filter is built for; about what fits in `MAX_FILTER_BYTES`
+/// at `DEFAULT_FALSE_POSITIVE_RATE`
+pub const MAX_ADVERTISED_HASHES: usize = 6000;
+
+/// Filters estimated to be worse than this aren't advertised
+pub const MAX_ADVERTISED_FALSE_POSITIVE_RATE: f64 = 0.05;
+
+/// Rebuild a cached filter at least this often, for stores whose stats
+/// don't change with their contents
+pub const DEFAULT_FILTER_MAX_AGE: Duration = Duration::from_secs(600);
+
+/// Smallest filter, used when there is little or nothing to advertise
+const MIN_FILTER_BYTES: usize = 8;
+
+/// Upper bound on bit positions per hash
+const MAX_HASH_FUNCTIONS: u32 = 16;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum BloomError {
+    #[error("Invalid filter encoding: {0}")]
+    Encoding(String),
+    #[error("Filter too large: {0} bytes")]
+    TooLarge(usize),
+}
+
+/// Bloom filter over 32-byte hashes
+///
+/// Serialized as `"<hash functions>:<hex bits>"`.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct BloomFilter {
+    bits: Vec<u8>,
+    hash_functions: u32,
+}
+
+impl BloomFilter {
+    /// Empty filter sized for `items` hashes at the given false positive rate
+    pub fn with_capacity(items: usize, false_positive_rate: f64) -> Self {
+        let rate = false_positive_rate.clamp(1e-6, 0.5);
+        let n = items.max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+        let bits = (-n * rate.ln() / (ln2 * ln2)).ceil() as usize;
+        let bytes = bits.div_ceil(8).clamp(MIN_FILTER_BYTES, MAX_FILTER_BYTES);
+        let hash_functions = ((bytes * 8) as f64 / n * ln2).round() as u32;
+        Self {
+            bits: vec![0; bytes],
+            hash_functions: hash_functions.clamp(1, MAX_HASH_FUNCTIONS),
+        }
+    }
+
+    /// Filter holding the given hashes
+    pub fn from_hashes<'a>(
+        hashes: impl IntoIterator<Item = &'a Hash>,
+        false_positive_rate: f64,
+    ) -> Self {
+        let hashes: Vec<&Hash> = hashes.into_iter().collect();
+        let mut filter = Self::with_capacity(hashes.len(), false_positive_rate);
+        for hash in hashes {
+            filter.insert(hash);
+        }
+        filter
+    }
+
+    /// Add a hash
+    pub fn insert(&mut self, hash: &Hash) {
+        for bit in self.bit_positions(hash) {
+            self.bits[bit / 8] |= 1 << (bit % 8);
+        }
+    }
+
+    /// Whether the hash may have been added (false positives possible)
+    pub fn contains(&self, hash: &Hash) -> bool {
+        self.bit_positions(hash)
+            .all(|bit| self.bits[bit / 8] & (1 << (bit % 8)) != 0)
+    }
+
+    /// Size of the bit array in bytes
+    pub fn size_bytes(&self) -> usize {
+        self.bits.len()
+    }
+
+    /// Number of bit positions set per hash
+    pub fn hash_functions(&self) -> u32 {
+        self.hash_functions
+    }
+
+    /// Expected false positive rate given the bits set so far
+    pub fn estimated_false_positive_rate(&self) -> f64 {
+        let set: u32 = self.bits.iter().map(|b| b.count_ones()).sum();
+        let fill = set as f64 / (self.bits.len() * 8) as f64;
+        fill.powi(self.hash_functions as i32)
+    }
+
+    /// Encode as `"<hash functions>:<hex bits>"`
+    pub fn encode(&self) -> String {
+        format!("{}:{}", self.hash_functions, hex::encode(&self.bits))
+    }
+
+    /// Decode a filter produced by [`BloomFilter::encode`]
+    pub fn decode(s: &str) -> Result<Self, BloomError> {
+        let (k, bits) = s
+            .split_once(':')
+            .ok_or_else(|| BloomError::Encoding("missing ':'".to_string()))?;
+        let hash_functions: u32 = k
+            .parse()
+            .map_err(|_| BloomError::Encoding(format!("bad hash function count {:?}", k)))?;
+        if !(1..=MAX_HASH_FUNCTIONS).contains(&hash_functions) {
+            return Err(BloomError::Encoding(format!(
+                "hash function count {} out of range",
+                hash_functions
+            )));
+        }
+        if bits.len() / 2 > MAX_FILTER_BYTES {
+            return Err(BloomError::TooLarge(bits.len() / 2));
+        }
+        let bits = hex::decode(bits).map_err(|e| BloomError::Encoding(e.to_string()))?;
+        if bits.is_empty() {
+            return Err(BloomError::Encoding("empty bit array".to_string()));
+        }
+        Ok(Self {
+            bits,
+            hash_functions,
+        })
+    }
+
+    /// Bit positions for a hash: h1 + i*h2 over the filter size
+    fn bit_positions(&self, hash: &Hash) -> impl Iterator<Item = usize> {
+        let h1 = u64::from_le_bytes(hash[0..8].try_into().unwrap());
+        // Odd step so positions don't repeat early
+        let h2 = u64::from_le_bytes(hash[8..16].try_into().unwrap()) | 1;
+        let m = (self.bits.len() * 8) as u64;
+        (0..self.hash_functions as u64)
+            .map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % m) as usize)
+    }
+}
+
+impl fmt::Debug for BloomFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BloomFilter")
+            .field("bytes", &self.bits.len())
+            .field("hash_functions", &self.hash_functions)
+            .finish()
+    }
+}
+
+impl From<BloomFilter> for String {
+    fn from(filter: BloomFilter) -> Self {
+        filter.encode()
+    }
+}
+
+impl TryFrom<String> for BloomFilter {
+    type Error = BloomError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Self::decode(&s)
+    }
+}
+
+/// Filter of our roots plus the hashes the store can list
+///
+/// Stores with more than `MAX_ADVERTISED_HASHES` items only advertise their
+/// pinned hashes, and stores that can't enumerate their contents only the
+/// roots. Returns None when the filter would be too full to be useful.
+pub async fn store_filter<S: Store + ?Sized>(
+    store: &S,
+    roots: &[Hash],
+    false_positive_rate: f64,
+) -> Option<BloomFilter> {
+    let mut hashes = store.list().await.unwrap_or_default();
+    if hashes.len() > MAX_ADVERTISED_HASHES {
+        hashes.retain(|hash| store.is_pinned(hash));
+    }
+    if hashes.len() > MAX_ADVERTISED_HASHES {
+        hashes.clear();
+    }
+    hashes.extend_from_slice(roots);
+    let filter = BloomFilter::from_hashes(&hashes, false_positive_rate);
+    (filter.estimated_false_positive_rate() <= MAX_ADVERTISED_FALSE_POSITIVE_RATE).then_some(filter)
+}
+
+struct CachedFilter {
+    /// Store item count and size, and the roots, the filter was built from
+    key: (u64, u64, Vec<Hash>),
+    built: Instant,
+    filter: Option<BloomFilter>,
+}
+
+/// Last advertised filter, rebuilt only when the store changes
+pub struct FilterCache {
+    false_positive_rate: f64,
+    max_age: Duration,
+    cached: Mutex<Option<CachedFilter>>,
+}
+
+impl Default for FilterCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_FALSE_POSITIVE_RATE)
+    }
+}
+
+impl FilterCache {
+    pub fn new(false_positive_rate: f64) -> Self {
+        Self {
+            false_positive_rate,
+            max_age: DEFAULT_FILTER_MAX_AGE,
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// Rebuild at least this often even if the store looks unchanged
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Filter for the store's current contents (see [`store_filter`])
+    ///
+    /// Reuses the last filter while the store's item count and size and the
+    /// roots are unchanged and it is younger than the max age.
+    pub async fn filter<S: Store + ?Sized>(
+        &self,
+        store: &S,
+        roots: &[Hash],
+    ) -> Option<BloomFilter> {
+        let stats = store.stats().await;
+        let key = (stats.count, stats.bytes, roots.to_vec());
+        {
+            let cached = self.cached.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(cached) = cached.as_ref() {
+                if cached.key == key && cached.built.elapsed() < self.max_age {
+                    return cached.filter.clone();
+                }
+            }
+        }
+
+        let filter = store_filter(store, roots, self.false_positive_rate).await;
+        *self.cached.lock().unwrap_or_else(|e| e.into_inner()) = Some(CachedFilter {
+            key,
+            built: Instant::now(),
+            filter: filter.clone(),
+        });
+        filter
+    }
+}
+
+/// What a peer advertised in its last hello
+#[derive(Debug, Clone, Default)]
+pub struct PeerAdvert {
+    /// Root hashes (hex)
+    pub roots: Vec<String>,
+    /// Filter of held hashes, if the peer sends one
+    pub filter: Option<BloomFilter>,
+}
+
+impl PeerAdvert {
+    pub fn new(roots: &[String], filter: Option<BloomFilter>) -> Self {
+        Self {
+            roots: roots.to_vec(),
+            filter,
+        }
+    }
+
+    /// Whether the peer claims to hold the hash (false positives possible)
+    pub fn matches(&self, hash: &Hash) -> bool {
+        if self.filter.as_ref().is_some_and(|f| f.contains(hash)) {
+            return true;
+        }
+        !self.roots.is_empty() && self.roots.contains(&to_hex(hash))
+    }
+}
+
+/// Reorder peers so those advertising `hash` come first, keeping the
+/// existing order (pools, selector score) within both groups
+pub fn advertised_first<T>(
+    peers: Vec<(String, T)>,
+    hash: &Hash,
+    adverts: &HashMap<String, PeerAdvert>,
+) -> Vec<(String, T)> {
+    let (mut matched, rest): (Vec<_>, Vec<_>) = peers
+        .into_iter()
+        .partition(|(id, _)| adverts.get(id).is_some_and(|a| a.matches(hash)));
+    matched.extend(rest);
+    matched
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use hashtree_core::store::StoreStats;
+    use hashtree_core::{sha256, MemoryStore, StoreError};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn hashes(range: std::ops::Range<u32>) -> Vec<Hash> {
+        range.map(|i| sha256(&i.to_le_bytes())).collect()
+    }
+
+    #[test]
+    fn test_no_false_negatives() {
+        let held = hashes(0..1000);
+        let filter = BloomFilter::from_hashes(&held, DEFAULT_FALSE_POSITIVE_RATE);
+        assert!(held.iter().all(|h| filter.contains(h)));
+    }
+
+    #[test]
+    fn test_false_positive_rate_near_target() {
+        let filter = BloomFilter::from_hashes(&hashes(0..1000), DEFAULT_FALSE_POSITIVE_RATE);
+        let false_positives = hashes(1000..11_000)
+            .iter()
+            .filter(|h| filter.contains(h))
+            .count();
+        // Target is 1% of 10000; leave room for variance
+        assert!(false_positives < 250, "{} false positives", false_positives);
+        assert!(filter.estimated_false_positive_rate() < 0.02);
+    }
+
+    #[test]
+    fn test_size_is_capped() {
+        let filter = BloomFilter::with_capacity(10_000_000, DEFAULT_FALSE_POSITIVE_RATE);
+        assert_eq!(filter.size_bytes(), MAX_FILTER_BYTES);
+        let empty = BloomFilter::with_capacity(0, DEFAULT_FALSE_POSITIVE_RATE);
+        assert_eq!(empty.size_bytes(), MIN_FILTER_BYTES);
+        assert!(!empty.contains(&sha256(b"anything")));
+    }
+
+    #[test]
+    fn test_encode_decode_roundtrip() {
+        let filter = BloomFilter::from_hashes(&hashes(0..50), DEFAULT_FALSE_POSITIVE_RATE);
+        let decoded = BloomFilter::decode(&filter.encode()).unwrap();
+        assert_eq!(decoded, filter);
+
+        let json = serde_json::to_string(&filter).unwrap();
+        let from_json: BloomFilter = serde_json::from_str(&json).unwrap();
+        assert_eq!(from_json, filter);
+
+        assert!(BloomFilter::decode("").is_err());
+        assert!(BloomFilter::decode("0:00ff").is_err());
+        assert!(BloomFilter::decode("3:").is_err());
+        assert!(BloomFilter::decode("3:zz").is_err());
+        let huge = format!("3:{}", "00".repeat(MAX_FILTER_BYTES + 1));
+        assert_eq!(
+            BloomFilter::decode(&huge),
+            Err(BloomError::TooLarge(MAX_FILTER_BYTES + 1))
+        );
+    }
+
+    #[test]
+    fn test_advertised_first_keeps_order() {
+        let wanted = sha256(b"wanted");
+        let mut adverts = HashMap::new();
+        adverts.insert(
+            "b".to_string(),
+            PeerAdvert::new(&[], Some(BloomFilter::from_hashes([&wanted], 0.01))),
+        );
+        adverts.insert("d".to_string(), PeerAdvert::new(&[to_hex(&wanted)], None));
+        adverts.insert("c".to_string(), PeerAdvert::new(&[], None));
+
+        let peers: Vec<(String, ())> = ["a", "b", "c", "d"]
+            .iter()
+            .map(|id| (id.to_string(), ()))
+            .collect();
+        let ordered: Vec<String> = advertised_first(peers, &wanted, &adverts)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(ordered, vec!["b", "d", "a", "c"]);
+    }
+
+    #[tokio::test]
+    async fn test_store_filter_includes_chunks_and_roots() {
+        let store = MemoryStore::new();
+        let chunk = sha256(b"chunk");
+        store.put(chunk, b"chunk".to_vec()).await.unwrap();
+        let root = sha256(b"root");
+
+        let filter = store_filter(&store, &[root], DEFAULT_FALSE_POSITIVE_RATE)
+            .await
+            .unwrap();
+        assert!(filter.contains(&chunk));
+        assert!(filter.contains(&root));
+    }
+
+    #[tokio::test]
+    async fn test_large_store_advertises_pinned_only() {
+        let store = MemoryStore::new();
+        let held = hashes(0..100_000);
+        for hash in &held {
+            store.put(*hash, hash.to_vec()).await.unwrap();
+        }
+        let root = sha256(b"root");
+
+        // Too many to advertise and nothing pinned: only the root
+        let filter = store_filter(&store, &[root], DEFAULT_FALSE_POSITIVE_RATE)
+            .await
+            .unwrap();
+        assert!(filter.contains(&root));
+        assert_eq!(filter.size_bytes(), MIN_FILTER_BYTES);
+        let false_positives = held[..10_000]
+            .iter()
+            .filter(|h| filter.contains(h))
+            .count();
+        assert!(false_positives < 50, "{} false positives", false_positives);
+
+        let pinned = &held[..1000];
+        for hash in pinned {
+            store.pin(hash).await.unwrap();
+        }
+        let filter = store_filter(&store, &[root], DEFAULT_FALSE_POSITIVE_RATE)
+            .await
+            .unwrap();
+        assert!(pinned.iter().all(|h| filter.contains(h)));
+        assert!(filter.contains(&root));
+        assert!(filter.estimated_false_positive_rate() < 0.02);
+        let false_positives = held[1000..11_000]
+            .iter()
+            .filter(|h| filter.contains(h))
+            .count();
+        assert!(false_positives < 250, "{} false positives", false_positives);
+    }
+
+    /// MemoryStore that counts `list` calls
+    #[derive(Default)]
+    struct CountingStore {
+        inner: MemoryStore,
+        lists: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Store for CountingStore {
+        async fn put(&self, hash: Hash, data: Vec<u8>) -> Result<bool, StoreError> {
+            self.inner.put(hash, data).await
+        }
+        async fn get(&self, hash: &Hash) -> Result<Option<Vec<u8>>, StoreError> {
+            self.inner.get(hash).await
+        }
+        async fn has(&self, hash: &Hash) -> Result<bool, StoreError> {
+            self.inner.has(hash).await
+        }
+        async fn delete(&self, hash: &Hash) -> Result<bool, StoreError> {
+            self.inner.delete(hash).await
+        }
+        async fn list(&self) -> Result<Vec<Hash>, StoreError> {
+            self.lists.fetch_add(1, Ordering::SeqCst);
+            self.inner.list().await
+        }
+        async fn stats(&self) -> StoreStats {
+            self.inner.stats().await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_filter_cache_rebuilds_only_on_change() {
+        let store = CountingStore::default();
+        let cache = FilterCache::default();
+        let first = sha256(b"first");
+        store.put(first, b"first".to_vec()).await.unwrap();
+
+        let filter = cache.filter(&store, &[]).await.unwrap();
+        assert!(filter.contains(&first));
+        cache.filter(&store, &[]).await;
+        assert_eq!(store.lists.load(Ordering::SeqCst), 1);
+
+        let second = sha256(b"second");
+        store.put(second, b"second".to_vec()).await.unwrap();
+        let filter = cache.filter(&store, &[]).await.unwrap();
+        assert!(filter.contains(&second));
+        assert_eq!(store.lists.load(Ordering::SeqCst), 2);
+
+        // New roots also rebuild
+        cache.filter(&store, &[sha256(b"root")]).await;
+        assert_eq!(store.lists.load(Ordering::SeqCst), 3);
+
+        let expiring = FilterCache::default().with_max_age(Duration::ZERO);
+        expiring.filter(&store, &[]).await;
+        expiring.filter(&store, &[]).await;
+        assert_eq!(store.lists.load(Ordering::SeqCst), 5);
+    }
+}
@@ -10,11 +10,12 @@ use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::{oneshot, RwLock};
 
-use hashtree_core::{Hash, Store, StoreError};
+use hashtree_core::{Hash, Store, StoreError, VerifyingStore};
 
+use crate::bloom::FilterCache;
 use crate::protocol::{
     create_request, create_response, encode_request, encode_response, hash_to_key, parse_message,
-    DataMessage,
+    DataMessage, MAX_WANT_LIST_LEN,
 };
 use crate::signaling::SignalingManager;
 use crate::transport::{PeerConnectionFactory, RelayTransport, TransportError};
@@ -38,6 +39,8 @@ where
 {
     /// Local backing store
     local_store: Arc<S>,
+    /// Hash-checked view of the local store (corrupt blobs are dropped and refetched)
+    verified_local: VerifyingStore<S>,
     /// Signaling manager (handles peer discovery and connection)
     signaling: Arc<SignalingManager<R, F>>,
     /// Per-peer HTL config
@@ -49,7 +52,13 @@ where
     /// Debug mode
     debug: bool,
     /// Running flag
-    running: RwLock<bool>,
+    running: Arc<RwLock<bool>>,
+    /// Advertise a filter of held hashes in hellos
+    advertise_filter: bool,
+    /// Our last advertised filter
+    filters: Arc<FilterCache>,
+    /// Resend hellos this often once started (None: only on start)
+    hello_interval: Option<Duration>,
 }
 
 impl<S, R, F> GenericStore<S, R, F>
@@ -66,26 +75,97 @@ where
         debug: bool,
     ) -> Self {
         Self {
+            verified_local: VerifyingStore::new(local_store.clone()).with_delete_corrupted(true),
             local_store,
             signaling,
             htl_configs: RwLock::new(HashMap::new()),
             pending_requests: RwLock::new(HashMap::new()),
             request_timeout,
             debug,
-            running: RwLock::new(false),
+            running: Arc::new(RwLock::new(false)),
+            advertise_filter: false,
+            filters: Arc::new(FilterCache::default()),
+            hello_interval: None,
         }
     }
 
+    /// Include a filter of held hashes in hellos
+    pub fn with_advertise_filter(mut self, advertise_filter: bool) -> Self {
+        self.advertise_filter = advertise_filter;
+        self
+    }
+
+    /// Resend hellos periodically after `start`, so peers keep finding us
+    /// and learn about newly stored content
+    pub fn with_hello_interval(mut self, interval: Duration) -> Self {
+        self.hello_interval = Some(interval);
+        self
+    }
+
     /// Start the store (begin listening for messages)
     pub async fn start(&self) -> Result<(), TransportError> {
         *self.running.write().await = true;
 
         // Send initial hello
-        self.signaling.send_hello(vec![]).await?;
-
+        self.advertise().await?;
+        self.start_hello_timer();
         Ok(())
     }
 
+    /// Send a hello, with a filter of the local store's hashes if enabled
+    ///
+    /// The filter is only rebuilt when the store changed since the last hello.
+    pub async fn advertise(&self) -> Result<(), TransportError> {
+        Self::send_hello(
+            &self.local_store,
+            &self.signaling,
+            &self.filters,
+            self.advertise_filter,
+        )
+        .await
+    }
+
+    async fn send_hello(
+        local_store: &S,
+        signaling: &SignalingManager<R, F>,
+        filters: &FilterCache,
+        advertise_filter: bool,
+    ) -> Result<(), TransportError> {
+        let filter = if advertise_filter {
+            filters.filter(local_store, &[]).await
+        } else {
+            None
+        };
+        signaling.send_hello(vec![], filter).await
+    }
+
+    /// Resend hellos every `hello_interval` until stopped
+    fn start_hello_timer(&self) {
+        let Some(interval) = self.hello_interval else {
+            return;
+        };
+        let local_store = self.local_store.clone();
+        let signaling = self.signaling.clone();
+        let filters = self.filters.clone();
+        let advertise_filter = self.advertise_filter;
+        let running = self.running.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            // The first tick is immediate and `start` already said hello
+            interval.tick().await;
+
+            loop {
+                interval.tick().await;
+                if !*running.read().await {
+                    break;
+                }
+                let _ =
+                    Self::send_hello(&local_store, &signaling, &filters, advertise_filter).await;
+            }
+        });
+    }
+
     /// Stop the store
     pub async fn stop(&self) {
         *self.running.write().await = false;
@@ -120,9 +200,9 @@ where
         self.signaling.needs_peers().await
     }
 
-    /// Request data from peers
+    /// Request data from peers, those advertising the hash first
     async fn request_from_peers(&self, hash: &Hash) -> Option<Vec<u8>> {
-        let peer_ids = self.signaling.peer_ids().await;
+        let peer_ids = self.signaling.peer_ids_for(hash).await;
         if peer_ids.is_empty() {
             return None;
         }
@@ -191,6 +271,17 @@ where
         None
     }
 
+    /// Send a response if the hash is in the local store
+    async fn respond_if_local(&self, from_peer: &str, hash: &Hash) {
+        if let Ok(Some(data)) = self.local_store.get(hash).await {
+            let res = create_response(hash, data);
+            let response_bytes = encode_response(&res);
+            if let Some(channel) = self.signaling.get_channel(from_peer).await {
+                let _ = channel.send(response_bytes).await;
+            }
+        }
+    }
+
     /// Handle incoming data message
     pub async fn handle_data_message(&self, from_peer: &str, data: &[u8]) {
         let parsed = match parse_message(data) {
@@ -200,22 +291,28 @@ where
 
         match parsed {
             DataMessage::Request(req) => {
-                let hash = match crate::protocol::bytes_to_hash(&req.h) {
-                    Some(h) => h,
-                    None => return,
-                };
-
-                // Check local store
-                if let Ok(Some(data)) = self.local_store.get(&hash).await {
-                    // Send response
-                    let res = create_response(&hash, data);
-                    let response_bytes = encode_response(&res);
-                    if let Some(channel) = self.signaling.get_channel(from_peer).await {
-                        let _ = channel.send(response_bytes).await;
-                    }
+                if let Some(hash) = crate::protocol::bytes_to_hash(&req.h) {
+                    self.respond_if_local(from_peer, &hash).await;
                 }
                 // For now, don't forward - keep it simple
             }
+            DataMessage::WantList(want) => {
+                for h in want.hs.iter().take(MAX_WANT_LIST_LEN) {
+                    if let Some(hash) = crate::protocol::bytes_to_hash(h) {
+                        self.respond_if_local(from_peer, &hash).await;
+                    }
+                }
+            }
+            DataMessage::NotFound(msg) => {
+                // Resolve as a miss so request_from_peers moves on immediately
+                let hash_key = hash_to_key(&msg.h);
+                if let Some(pending) = self.pending_requests.write().await.remove(&hash_key) {
+                    let _ = pending.response_tx.send(None);
+                }
+            }
+            // Nothing is forwarded, so there is nothing to cancel, and we never
+            // send version 1 messages ourselves
+            DataMessage::Cancel(_) | DataMessage::Hello(_) => {}
             DataMessage::Response(res) => {
                 let hash_key = hash_to_key(&res.h);
 
@@ -249,13 +346,17 @@ where
     F: PeerConnectionFactory + Send + Sync + 'static,
 {
     async fn put(&self, hash: Hash, data: Vec<u8>) -> Result<bool, StoreError> {
-        self.local_store.put(hash, data).await
+        self.verified_local.put(hash, data).await
     }
 
     async fn get(&self, hash: &Hash) -> Result<Option<Vec<u8>>, StoreError> {
         // Try local first
-        if let Some(data) = self.local_store.get(hash).await? {
-            return Ok(Some(data));
+        match self.verified_local.get(hash).await {
+            Ok(Some(data)) => return Ok(Some(data)),
+            Ok(None) => {}
+            // Corrupted local copy was deleted - refetch from peers
+            Err(StoreError::HashMismatch { .. }) => {}
+            Err(e) => return Err(e),
         }
 
         // Try peers
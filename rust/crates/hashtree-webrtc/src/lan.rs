@@ -0,0 +1,499 @@
+//! Local network peer discovery over UDP multicast
+//!
+//! Nostr signaling needs relays, so peers on an offline network never see
+//! each other's hellos. LAN discovery periodically multicasts a small JSON
+//! announcement with our peer id and, optionally, the port of a data endpoint
+//! (e.g. a daemon's `/ws`), and listens for announcements from other peers.
+//!
+//! ```json
+//! {"proto":"hashtree-lan/1","peer_id":"<pubkey>:<uuid>","port":8080}
+//! ```
+//!
+//! The endpoint address is the announcement's source IP plus `port`, so a
+//! daemon bound to `0.0.0.0` never has to know its own LAN address. Peers that
+//! stop announcing are reported lost after `peer_timeout`.
+//!
+//! Announcements are unauthenticated, so at most `max_peers` are tracked and
+//! a known peer's endpoint only changes when it announces from the same IP.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::net::UdpSocket;
+use tokio::sync::{mpsc, watch};
+
+/// Protocol marker in every announcement
+pub const LAN_PROTOCOL: &str = "hashtree-lan/1";
+
+/// Default multicast group (organization-local scope)
+pub const LAN_MULTICAST_GROUP: Ipv4Addr = Ipv4Addr::new(239, 255, 72, 84);
+
+/// Default discovery port
+pub const LAN_DISCOVERY_PORT: u16 = 48_884;
+
+/// Announcements larger than this are ignored
+const MAX_ANNOUNCEMENT_LEN: usize = 1024;
+
+/// Errors from LAN discovery
+#[derive(Debug, Error)]
+pub enum LanError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Encode error: {0}")]
+    Encode(#[from] serde_json::Error),
+}
+
+/// Configuration for LAN discovery
+#[derive(Debug, Clone)]
+pub struct LanDiscoveryConfig {
+    /// Address to receive announcements on
+    pub bind: SocketAddr,
+    /// Where announcements are sent; a multicast group is joined on bind
+    pub announce_to: SocketAddr,
+    /// How often to announce ourselves
+    pub interval: Duration,
+    /// Forget peers that haven't announced for this long
+    pub peer_timeout: Duration,
+    /// Port of our data endpoint, announced to peers
+    pub endpoint_port: Option<u16>,
+    /// Most peers tracked at once; announcements from others are ignored
+    pub max_peers: usize,
+}
+
+impl Default for LanDiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            bind: SocketAddr::from((Ipv4Addr::UNSPECIFIED, LAN_DISCOVERY_PORT)),
+            announce_to: SocketAddr::from((LAN_MULTICAST_GROUP, LAN_DISCOVERY_PORT)),
+            interval: Duration::from_secs(5),
+            peer_timeout: Duration::from_secs(20),
+            endpoint_port: None,
+            max_peers: 256,
+        }
+    }
+}
+
+impl LanDiscoveryConfig {
+    pub fn with_endpoint_port(mut self, port: u16) -> Self {
+        self.endpoint_port = Some(port);
+        self
+    }
+
+    /// Listen on `bind` and announce to `announce_to` (e.g. unicast on loopback)
+    pub fn with_addrs(mut self, bind: SocketAddr, announce_to: SocketAddr) -> Self {
+        self.bind = bind;
+        self.announce_to = announce_to;
+        self
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn with_peer_timeout(mut self, timeout: Duration) -> Self {
+        self.peer_timeout = timeout;
+        self
+    }
+
+    pub fn with_max_peers(mut self, max_peers: usize) -> Self {
+        self.max_peers = max_peers;
+        self
+    }
+}
+
+/// Announcement datagram
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LanAnnouncement {
+    pub proto: String,
+    pub peer_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+}
+
+impl LanAnnouncement {
+    pub fn new(peer_id: impl Into<String>, port: Option<u16>) -> Self {
+        Self {
+            proto: LAN_PROTOCOL.to_string(),
+            peer_id: peer_id.into(),
+            port,
+        }
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>, LanError> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    /// Parse a datagram; None for anything that isn't a valid announcement
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() > MAX_ANNOUNCEMENT_LEN {
+            return None;
+        }
+        let announcement: Self = serde_json::from_slice(data).ok()?;
+        (announcement.proto == LAN_PROTOCOL && !announcement.peer_id.is_empty())
+            .then_some(announcement)
+    }
+}
+
+/// A peer seen on the local network
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LanPeer {
+    /// Announced peer id ("pubkey:uuid")
+    pub peer_id: String,
+    /// Source address of the announcement
+    pub addr: SocketAddr,
+    /// Data endpoint: the source IP with the announced port
+    pub endpoint: Option<SocketAddr>,
+}
+
+impl LanPeer {
+    fn from_announcement(announcement: LanAnnouncement, addr: SocketAddr) -> Self {
+        Self {
+            peer_id: announcement.peer_id,
+            addr,
+            endpoint: announcement
+                .port
+                .map(|port| SocketAddr::new(addr.ip(), port)),
+        }
+    }
+}
+
+/// Discovery events from [`LanDiscovery::run`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LanEvent {
+    /// A new peer, or a known peer whose endpoint changed
+    Discovered(LanPeer),
+    /// A peer stopped announcing (peer id)
+    Lost(String),
+}
+
+/// UDP announcer and listener for LAN discovery
+pub struct LanDiscovery {
+    peer_id: String,
+    config: LanDiscoveryConfig,
+    socket: UdpSocket,
+    /// Known peers and when they last announced
+    peers: Mutex<HashMap<String, (LanPeer, Instant)>>,
+}
+
+impl LanDiscovery {
+    /// Bind the discovery socket, joining the multicast group if
+    /// `announce_to` is one
+    pub async fn bind(
+        peer_id: impl Into<String>,
+        config: LanDiscoveryConfig,
+    ) -> Result<Self, LanError> {
+        let socket = UdpSocket::bind(config.bind).await?;
+        if let IpAddr::V4(group) = config.announce_to.ip() {
+            if group.is_multicast() {
+                let interface = match config.bind.ip() {
+                    IpAddr::V4(ip) => ip,
+                    IpAddr::V6(_) => Ipv4Addr::UNSPECIFIED,
+                };
+                socket.join_multicast_v4(group, interface)?;
+                // Other daemons on this host listen too
+                socket.set_multicast_loop_v4(true)?;
+                socket.set_multicast_ttl_v4(1)?;
+            }
+        }
+        Ok(Self {
+            peer_id: peer_id.into(),
+            config,
+            socket,
+            peers: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Address the discovery socket is bound to
+    pub fn local_addr(&self) -> Result<SocketAddr, LanError> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    /// Our peer id
+    pub fn peer_id(&self) -> &str {
+        &self.peer_id
+    }
+
+    /// Send one announcement
+    pub async fn announce(&self) -> Result<(), LanError> {
+        let data =
+            LanAnnouncement::new(self.peer_id.clone(), self.config.endpoint_port).encode()?;
+        self.socket.send_to(&data, self.config.announce_to).await?;
+        Ok(())
+    }
+
+    /// Wait for the next announcement from another peer
+    pub async fn recv(&self) -> Result<LanPeer, LanError> {
+        let mut buf = [0u8; MAX_ANNOUNCEMENT_LEN + 1];
+        loop {
+            let (len, addr) = self.socket.recv_from(&mut buf).await?;
+            let Some(announcement) = LanAnnouncement::decode(&buf[..len]) else {
+                continue;
+            };
+            if announcement.peer_id == self.peer_id {
+                continue;
+            }
+            return Ok(LanPeer::from_announcement(announcement, addr));
+        }
+    }
+
+    /// Peers currently known
+    pub fn peers(&self) -> Vec<LanPeer> {
+        self.peers
+            .lock()
+            .unwrap()
+            .values()
+            .map(|(peer, _)| peer.clone())
+            .collect()
+    }
+
+    /// Forget a peer so its next announcement is reported as a discovery again
+    pub fn forget(&self, peer_id: &str) {
+        self.peers.lock().unwrap().remove(peer_id);
+    }
+
+    /// Record an announcement; true if the peer is new or its endpoint changed
+    fn observe(&self, peer: LanPeer) -> bool {
+        let mut peers = self.peers.lock().unwrap();
+        let full = peers.len() >= self.config.max_peers;
+        match peers.get_mut(&peer.peer_id) {
+            // Anyone can claim a peer id; only its own host may move it
+            Some((known, _)) if known.addr.ip() != peer.addr.ip() => false,
+            Some((known, seen)) => {
+                let changed = known.endpoint != peer.endpoint;
+                *known = peer;
+                *seen = Instant::now();
+                changed
+            }
+            None if full => false,
+            None => {
+                peers.insert(peer.peer_id.clone(), (peer, Instant::now()));
+                true
+            }
+        }
+    }
+
+    /// Drop peers that stopped announcing, returning their ids
+    fn expire(&self) -> Vec<String> {
+        let timeout = self.config.peer_timeout;
+        let mut lost = Vec::new();
+        self.peers.lock().unwrap().retain(|peer_id, (_, seen)| {
+            let alive = seen.elapsed() < timeout;
+            if !alive {
+                lost.push(peer_id.clone());
+            }
+            alive
+        });
+        lost
+    }
+
+    /// Announce every `interval` and report peers coming and going until
+    /// `shutdown` is set or the receiver is dropped
+    pub async fn run(&self, events: mpsc::Sender<LanEvent>, mut shutdown: watch::Receiver<bool>) {
+        let mut ticker = tokio::time::interval(self.config.interval);
+        // Check for lost peers a few times per timeout
+        let mut expiry =
+            tokio::time::interval((self.config.peer_timeout / 4).max(Duration::from_millis(10)));
+        loop {
+            let event = tokio::select! {
+                _ = shutdown.changed() => {
+                    if *shutdown.borrow() {
+                        return;
+                    }
+                    continue;
+                }
+                _ = ticker.tick() => {
+                    if let Err(e) = self.announce().await {
+                        tracing::debug!("LAN announce failed: {}", e);
+                    }
+                    continue;
+                }
+                _ = expiry.tick() => {
+                    for peer_id in self.expire() {
+                        if events.send(LanEvent::Lost(peer_id)).await.is_err() {
+                            return;
+                        }
+                    }
+                    continue;
+                }
+                peer = self.recv() => match peer {
+                    Ok(peer) if self.observe(peer.clone()) => LanEvent::Discovered(peer),
+                    Ok(_) => continue,
+                    Err(e) => {
+                        tracing::debug!("LAN discovery receive failed: {}", e);
+                        continue;
+                    }
+                },
+            };
+            if events.send(event).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loopback() -> SocketAddr {
+        SocketAddr::from((Ipv4Addr::LOCALHOST, 0))
+    }
+
+    #[test]
+    fn test_announcement_roundtrip_and_rejects_garbage() {
+        let announcement = LanAnnouncement::new("abc:123", Some(8080));
+        let data = announcement.encode().unwrap();
+        assert_eq!(LanAnnouncement::decode(&data), Some(announcement));
+
+        assert!(LanAnnouncement::decode(b"not json").is_none());
+        assert!(LanAnnouncement::decode(br#"{"proto":"other/1","peer_id":"abc:123"}"#).is_none());
+        assert!(LanAnnouncement::decode(br#"{"proto":"hashtree-lan/1","peer_id":""}"#).is_none());
+        assert_eq!(
+            LanAnnouncement::decode(br#"{"proto":"hashtree-lan/1","peer_id":"abc:123"}"#)
+                .unwrap()
+                .port,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_discovers_peer_on_loopback() {
+        let listener = LanDiscovery::bind(
+            "listener:1",
+            LanDiscoveryConfig::default().with_addrs(loopback(), loopback()),
+        )
+        .await
+        .unwrap();
+        let target = listener.local_addr().unwrap();
+
+        let announcer = LanDiscovery::bind(
+            "announcer:2",
+            LanDiscoveryConfig::default()
+                .with_addrs(loopback(), target)
+                .with_endpoint_port(8080),
+        )
+        .await
+        .unwrap();
+        announcer.announce().await.unwrap();
+
+        let peer = tokio::time::timeout(Duration::from_secs(5), listener.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(peer.peer_id, "announcer:2");
+        assert_eq!(peer.addr, announcer.local_addr().unwrap());
+        assert_eq!(
+            peer.endpoint,
+            Some(SocketAddr::from((Ipv4Addr::LOCALHOST, 8080)))
+        );
+    }
+
+    fn lan_peer(peer_id: &str, addr: &str, port: u16) -> LanPeer {
+        LanPeer::from_announcement(
+            LanAnnouncement::new(peer_id, Some(port)),
+            addr.parse().unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_observe_caps_peers_and_pins_source_ip() {
+        let discovery = LanDiscovery::bind(
+            "local:1",
+            LanDiscoveryConfig::default()
+                .with_addrs(loopback(), loopback())
+                .with_max_peers(1),
+        )
+        .await
+        .unwrap();
+
+        assert!(discovery.observe(lan_peer("a:1", "10.0.0.1:4000", 8080)));
+        assert!(!discovery.observe(lan_peer("a:1", "10.0.0.1:4000", 8080)));
+        // Full: other peers are ignored until one is forgotten or expires
+        assert!(!discovery.observe(lan_peer("b:2", "10.0.0.2:4000", 8080)));
+        assert_eq!(discovery.peers().len(), 1);
+
+        // Another host can't redirect a known peer
+        assert!(!discovery.observe(lan_peer("a:1", "10.0.0.66:4000", 9999)));
+        assert_eq!(
+            discovery.peers()[0].endpoint,
+            Some("10.0.0.1:8080".parse().unwrap())
+        );
+        // The peer itself can, e.g. after a restart on another port
+        assert!(discovery.observe(lan_peer("a:1", "10.0.0.1:4001", 9090)));
+        assert_eq!(
+            discovery.peers()[0].endpoint,
+            Some("10.0.0.1:9090".parse().unwrap())
+        );
+
+        discovery.forget("a:1");
+        assert!(discovery.observe(lan_peer("b:2", "10.0.0.2:4000", 8080)));
+        assert_eq!(discovery.peers()[0].peer_id, "b:2");
+    }
+
+    /// A loopback address nothing is bound to, so a socket can announce to itself
+    fn free_loopback() -> SocketAddr {
+        std::net::UdpSocket::bind(loopback())
+            .unwrap()
+            .local_addr()
+            .unwrap()
+    }
+
+    async fn next_event(events: &mut mpsc::Receiver<LanEvent>) -> LanEvent {
+        tokio::time::timeout(Duration::from_secs(5), events.recv())
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_run_reports_discovered_and_lost_peers() {
+        // The listener announces to itself, which it must ignore
+        let target = free_loopback();
+        let listener = std::sync::Arc::new(
+            LanDiscovery::bind(
+                "listener:1",
+                LanDiscoveryConfig::default()
+                    .with_addrs(target, target)
+                    .with_interval(Duration::from_millis(20))
+                    .with_peer_timeout(Duration::from_millis(200)),
+            )
+            .await
+            .unwrap(),
+        );
+
+        let (events_tx, mut events) = mpsc::channel(16);
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let runner = listener.clone();
+        let handle = tokio::spawn(async move { runner.run(events_tx, shutdown_rx).await });
+
+        let announcer = LanDiscovery::bind(
+            "announcer:2",
+            LanDiscoveryConfig::default().with_addrs(loopback(), target),
+        )
+        .await
+        .unwrap();
+        announcer.announce().await.unwrap();
+        announcer.announce().await.unwrap();
+
+        match next_event(&mut events).await {
+            LanEvent::Discovered(peer) => assert_eq!(peer.peer_id, "announcer:2"),
+            other => panic!("expected discovery, got {:?}", other),
+        }
+        // A repeated announcement is not a new discovery; silence means lost
+        assert_eq!(
+            next_event(&mut events).await,
+            LanEvent::Lost("announcer:2".into())
+        );
+        assert!(listener.peers().is_empty());
+
+        shutdown_tx.send(true).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), handle)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
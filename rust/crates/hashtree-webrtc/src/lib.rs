@@ -7,8 +7,13 @@
 //!
 //! - **Peer Discovery**: Uses Nostr relay network for signaling
 //! - **Data Exchange**: WebRTC data channels for binary data transfer
-//! - **Protocol**: Request/response with hash-based addressing
+//! - **Protocol**: Request/response with hash-based addressing, explicit misses
+//!   and batched want-lists for peers that negotiate version 1
 //! - **Adaptive Selection**: Intelligent peer selection based on performance
+//! - **Bandwidth Limits**: Token bucket upload/forward limits and byte ledgers
+//! - **LAN Discovery**: Optional UDP multicast announcements for offline networks
+//! - **Content Filters**: Hellos can advertise bloom filters of held hashes
+//!   (opt-in); peers whose filter matches are asked first
 //!
 //! # Example
 //!
@@ -37,8 +42,11 @@
 //! }
 //! ```
 
+pub mod bandwidth;
+pub mod bloom;
 pub mod channel;
 pub mod generic_store;
+pub mod lan;
 pub mod mock;
 pub mod nostr;
 pub mod peer;
@@ -50,18 +58,36 @@ pub mod store;
 pub mod transport;
 pub mod types;
 
+pub use bandwidth::{BandwidthConfig, BandwidthLimiter, ByteLedger, TokenBucket};
+pub use bloom::{
+    advertised_first, store_filter, BloomError, BloomFilter, FilterCache, PeerAdvert,
+    DEFAULT_FALSE_POSITIVE_RATE, MAX_ADVERTISED_FALSE_POSITIVE_RATE, MAX_ADVERTISED_HASHES,
+    MAX_FILTER_BYTES,
+};
 pub use channel::{ChannelError, LatencyChannel, MockChannel, PeerChannel};
 pub use generic_store::{GenericStore, ProductionStore, SimStore};
+pub use lan::{
+    LanAnnouncement, LanDiscovery, LanDiscoveryConfig, LanError, LanEvent, LanPeer,
+    LAN_DISCOVERY_PORT, LAN_MULTICAST_GROUP,
+};
 pub use mock::{
     clear_channel_registry, MockConnectionFactory, MockDataChannel, MockRelay, MockRelayTransport,
 };
 pub use nostr::NostrRelayTransport;
 pub use peer::{ForwardRequestCallback, Peer, PeerError};
-pub use peer_selector::{PeerSelector, PeerStats, SelectionStrategy, SelectorSummary};
+pub use peer_selector::{
+    load_state_file, peer_pubkey, save_state_file, AccessList, PeerAccess, PeerReputation,
+    PeerSelector, PeerStats, ReputationMap, SelectionStrategy, SelectorSummary, AUTO_BAN_CORRUPT,
+};
 pub use protocol::{
-    bytes_to_hash, create_fragment_response, create_request, create_response, encode_request,
-    encode_response, hash_to_bytes, hash_to_key, is_fragmented, parse_message, DataMessage,
-    DataRequest, DataResponse, FRAGMENT_SIZE, MSG_TYPE_REQUEST, MSG_TYPE_RESPONSE,
+    bytes_to_hash, create_cancel, create_fragment_response, create_hello, create_not_found,
+    create_request, create_response, create_want_list, encode_cancel, encode_hello,
+    encode_not_found, encode_request, encode_response, encode_want_list, hash_to_bytes,
+    hash_to_key, is_fragmented, parse_message, supports_extended, DataCancel, DataHello,
+    DataMessage, DataNotFound, DataRequest, DataResponse, DataWantList, FRAGMENT_SIZE,
+    MAX_WANT_LIST_LEN, MSG_TYPE_CANCEL, MSG_TYPE_HELLO, MSG_TYPE_NOT_FOUND, MSG_TYPE_REQUEST,
+    MSG_TYPE_RESPONSE, MSG_TYPE_WANT_LIST, PROTOCOL_VERSION, PROTOCOL_VERSION_EXTENDED,
+    PROTOCOL_VERSION_LEGACY,
 };
 pub use real_factory::RealPeerConnectionFactory;
 pub use signaling::{PeerEntry, SignalingManager};
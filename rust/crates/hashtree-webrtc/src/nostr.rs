@@ -11,12 +11,16 @@ use std::time::Duration;
 use tokio::sync::{broadcast, Mutex};
 use tracing::{debug, info, warn};
 
+use crate::bloom::BloomFilter;
 use crate::transport::{RelayTransport, TransportError};
 use crate::types::{SignalingMessage, NOSTR_KIND_HASHTREE};
 
 /// Hello tag for broadcast peer discovery
 const HELLO_TAG: &str = "hello";
 
+/// Hello tag carrying the sender's bloom filter of held hashes
+const FILTER_TAG: &str = "filter";
+
 /// Nostr relay transport for production WebRTC signaling
 pub struct NostrRelayTransport {
     /// Our peer ID (pubkey:uuid)
@@ -168,6 +172,7 @@ impl NostrRelayTransport {
                 return Some(SignalingMessage::Hello {
                     peer_id: their_peer_id,
                     roots: vec![],
+                    filter: get_tag(FILTER_TAG).and_then(|f| BloomFilter::decode(&f).ok()),
                 });
             }
             return None;
@@ -387,7 +392,7 @@ impl RelayTransport for NostrRelayTransport {
 
             // Add expiration tag (5 minutes) to match browser behavior
             let expiration = Timestamp::now() + Duration::from_secs(5 * 60);
-            let tags = vec![
+            let mut tags = vec![
                 Tag::custom(
                     nostr_sdk::TagKind::SingleLetter(nostr_sdk::SingleLetterTag::lowercase(
                         nostr_sdk::Alphabet::L,
@@ -400,6 +405,16 @@ impl RelayTransport for NostrRelayTransport {
                 ),
                 Tag::expiration(expiration),
             ];
+            if let SignalingMessage::Hello {
+                filter: Some(filter),
+                ..
+            } = &msg
+            {
+                tags.push(Tag::custom(
+                    nostr_sdk::TagKind::Custom(std::borrow::Cow::Borrowed(FILTER_TAG)),
+                    vec![filter.encode()],
+                ));
+            }
 
             let builder = EventBuilder::new(Kind::Custom(NOSTR_KIND_HASHTREE), "", tags);
 
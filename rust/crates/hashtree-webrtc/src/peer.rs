@@ -6,11 +6,20 @@
 //! Wire protocol (compatible with hashtree-ts):
 //! - Request:  [0x00][msgpack: {h: bytes32, htl?: u8}]
 //! - Response: [0x01][msgpack: {h: bytes32, d: bytes, i?: u32, n?: u32}]
+//!
+//! Peers that announce version 1 in a hello also get not-found answers,
+//! want-lists and cancels (see `protocol`).
+//!
+//! With a `BandwidthLimiter`, responses and forwarding are rate limited and
+//! refused requests are answered like misses.
 
+use crate::bandwidth::BandwidthLimiter;
 use crate::protocol::{
-    bytes_to_hash, create_fragment_response, create_request, create_response, encode_request,
-    encode_response, hash_to_key, is_fragmented, parse_message, DataMessage as ProtoMessage,
-    DataResponse, FRAGMENT_SIZE,
+    bytes_to_hash, create_cancel, create_fragment_response, create_hello, create_not_found,
+    create_request, create_response, create_want_list, encode_cancel, encode_hello,
+    encode_not_found, encode_request, encode_response, encode_want_list, hash_to_key,
+    is_fragmented, parse_message, supports_extended, DataMessage as ProtoMessage, DataResponse,
+    FRAGMENT_SIZE, MAX_WANT_LIST_LEN, PROTOCOL_VERSION_LEGACY,
 };
 use crate::types::{
     should_forward, ForwardRequest, ForwardTx, PeerHTLConfig, PeerId, PeerState, SignalingMessage,
@@ -21,9 +30,11 @@ use hashtree_core::{Hash, Store};
 use lru::LruCache;
 use std::collections::HashMap;
 use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU8, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
-use tokio::sync::{mpsc, oneshot, RwLock};
+use tokio::sync::{mpsc, oneshot, Notify, RwLock};
 use webrtc::api::interceptor_registry::register_default_interceptors;
 use webrtc::api::media_engine::MediaEngine;
 use webrtc::api::APIBuilder;
@@ -57,18 +68,25 @@ pub enum PeerError {
 /// Default LRU cache sizes (matching hashtree-ts)
 const THEIR_REQUESTS_SIZE: usize = 200;
 
+/// How long we wait for an answer to a request we sent
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// Fragment reassembly timeout constants (for future use)
 #[allow(dead_code)]
 const FRAGMENT_STALL_TIMEOUT_MS: u64 = 5000;
 #[allow(dead_code)]
 const FRAGMENT_TOTAL_TIMEOUT_MS: u64 = 120000;
 
+/// Answer to a request we sent: data, `Ok(None)` on a hash mismatch,
+/// or `Err(PeerError::NotFound)` when the peer said it doesn't have it
+type RequestResult = Result<Option<Vec<u8>>, PeerError>;
+
 /// Pending request awaiting response (requests WE sent)
 /// Keyed by hash hex string
 struct PendingRequest {
     #[allow(dead_code)] // for debugging
     hash: Hash,
-    response_tx: oneshot::Sender<Option<Vec<u8>>>,
+    response_tx: oneshot::Sender<RequestResult>,
 }
 
 /// Request this peer sent TO US that we couldn't fulfill locally
@@ -80,6 +98,8 @@ struct TheirRequest {
     /// When they requested it (for future timeout/cleanup)
     #[allow(dead_code)]
     requested_at: std::time::Instant,
+    /// Signalled when they cancel, aborting an in-flight forward
+    cancelled: Arc<Notify>,
 }
 
 /// Fragment reassembly tracking
@@ -95,6 +115,19 @@ struct PendingReassembly {
     last_fragment_at: std::time::Instant,
 }
 
+/// Copy of a request result, for hashes asked for more than once
+///
+/// Awaiting a response only yields the errors copied here.
+fn copy_result(result: &RequestResult) -> RequestResult {
+    match result {
+        Ok(data) => Ok(data.clone()),
+        Err(PeerError::NotFound) => Err(PeerError::NotFound),
+        Err(PeerError::Timeout) => Err(PeerError::Timeout),
+        Err(PeerError::NotReady) => Err(PeerError::NotReady),
+        Err(_) => Err(PeerError::ChannelClosed),
+    }
+}
+
 /// Callback type for forwarding requests to other peers (deprecated, use ForwardTx channel)
 /// Parameters: (hash, exclude_peer_id, htl)
 /// Returns: data if found, None otherwise
@@ -166,6 +199,10 @@ pub struct Peer<S: Store> {
     forward_tx: Option<ForwardTx>,
     /// Callback to forward request to other peers (deprecated, use forward_tx)
     on_forward_request: Option<ForwardRequestCallback>,
+    /// Protocol version announced by the remote (legacy until its hello arrives)
+    remote_version: Arc<AtomicU8>,
+    /// Upload/forward rate limits and byte ledger shared by all peers
+    bandwidth: Option<Arc<BandwidthLimiter>>,
 }
 
 impl<S: Store + 'static> Peer<S> {
@@ -196,6 +233,28 @@ impl<S: Store + 'static> Peer<S> {
         local_store: Arc<S>,
         debug: bool,
         forward_tx: Option<ForwardTx>,
+    ) -> Result<Self, PeerError> {
+        Self::with_bandwidth(
+            remote_id,
+            local_peer_id,
+            signaling_tx,
+            local_store,
+            debug,
+            forward_tx,
+            None,
+        )
+        .await
+    }
+
+    /// Create a new peer connection with a forwarding channel and rate limits
+    pub async fn with_bandwidth(
+        remote_id: PeerId,
+        local_peer_id: String,
+        signaling_tx: mpsc::Sender<SignalingMessage>,
+        local_store: Arc<S>,
+        debug: bool,
+        forward_tx: Option<ForwardTx>,
+        bandwidth: Option<Arc<BandwidthLimiter>>,
     ) -> Result<Self, PeerError> {
         // Create WebRTC API
         let mut media_engine = MediaEngine::default();
@@ -242,6 +301,8 @@ impl<S: Store + 'static> Peer<S> {
             htl_config: PeerHTLConfig::random(),
             forward_tx,
             on_forward_request: None,
+            remote_version: Arc::new(AtomicU8::new(PROTOCOL_VERSION_LEGACY)),
+            bandwidth,
         };
 
         peer.setup_handlers().await?;
@@ -262,6 +323,8 @@ impl<S: Store + 'static> Peer<S> {
         let forward_tx = self.forward_tx.clone();
         let on_forward_request = self.on_forward_request.clone();
         let peer_id_str = self.remote_id.to_peer_string();
+        let remote_version = self.remote_version.clone();
+        let bandwidth = self.bandwidth.clone();
 
         // Handle connection state changes
         let state_clone = state.clone();
@@ -304,6 +367,8 @@ impl<S: Store + 'static> Peer<S> {
         let forward_tx_clone = forward_tx.clone();
         let on_forward_clone = on_forward_request.clone();
         let peer_id_clone = peer_id_str.clone();
+        let remote_version_clone = remote_version.clone();
+        let bandwidth_clone = bandwidth.clone();
         self.connection.on_data_channel(Box::new(move |dc| {
             let data_channel = data_channel_clone.clone();
             let pending_requests = pending_requests_clone.clone();
@@ -314,6 +379,8 @@ impl<S: Store + 'static> Peer<S> {
             let forward_tx = forward_tx_clone.clone();
             let on_forward = on_forward_clone.clone();
             let peer_id = peer_id_clone.clone();
+            let remote_version = remote_version_clone.clone();
+            let bandwidth = bandwidth_clone.clone();
 
             Box::pin(async move {
                 if dc.label() == DATA_CHANNEL_LABEL {
@@ -328,8 +395,11 @@ impl<S: Store + 'static> Peer<S> {
                         forward_tx,
                         on_forward,
                         peer_id,
+                        remote_version,
+                        bandwidth,
                     )
                     .await;
+                    Self::send_hello(&dc, debug).await;
                     *data_channel.write().await = Some(dc);
                     *state.write().await = PeerState::Ready;
                     if debug {
@@ -400,181 +470,42 @@ impl<S: Store + 'static> Peer<S> {
         forward_tx: Option<ForwardTx>,
         on_forward_request: Option<ForwardRequestCallback>,
         peer_id: String,
+        remote_version: Arc<AtomicU8>,
+        bandwidth: Option<Arc<BandwidthLimiter>>,
     ) {
-        let pending_requests_clone = pending_requests.clone();
-        let their_requests_clone = their_requests.clone();
-        let pending_reassemblies_clone = pending_reassemblies.clone();
-        let local_store_clone = local_store.clone();
-        let dc_clone = dc.clone();
-        let forward_tx_clone = forward_tx.clone();
-        let on_forward_clone = on_forward_request.clone();
-        let peer_id_clone = peer_id.clone();
+        let ctx = Arc::new(ChannelContext {
+            dc: dc.clone(),
+            pending_requests,
+            their_requests,
+            pending_reassemblies,
+            local_store,
+            debug,
+            htl_config,
+            forward_tx,
+            on_forward_request,
+            peer_id,
+            remote_version,
+            bandwidth,
+        });
 
         dc.on_message(Box::new(move |msg: DataChannelMessage| {
-            let pending_requests = pending_requests_clone.clone();
-            let their_requests = their_requests_clone.clone();
-            let pending_reassemblies = pending_reassemblies_clone.clone();
-            let local_store = local_store_clone.clone();
-            let dc = dc_clone.clone();
-            let forward_tx = forward_tx_clone.clone();
-            let on_forward = on_forward_clone.clone();
-            let peer_id = peer_id_clone.clone();
-
+            let ctx = ctx.clone();
             Box::pin(async move {
-                let data = msg.data.to_vec();
-                if data.is_empty() {
-                    return;
-                }
-
-                // Parse MessagePack binary protocol
-                let parsed = match parse_message(&data) {
-                    Some(m) => m,
-                    None => {
-                        if debug {
-                            println!("[Peer] Failed to parse message");
-                        }
-                        return;
-                    }
-                };
-
-                match parsed {
-                    ProtoMessage::Request(req) => {
-                        let htl = req.htl.unwrap_or(MAX_HTL);
-                        let hash_key = hash_to_key(&req.h);
-
-                        if debug {
-                            println!(
-                                "[Peer] Request: hash={}..., htl={}",
-                                &hash_key[..16.min(hash_key.len())],
-                                htl
-                            );
-                        }
-
-                        // Convert to Hash type
-                        let hash_bytes = match bytes_to_hash(&req.h) {
-                            Some(h) => h,
-                            None => return,
-                        };
-
-                        // Try local store first
-                        let local_result = local_store.get(&hash_bytes).await;
-
-                        if let Ok(Some(payload)) = local_result {
-                            // Found locally - send response
-                            Self::send_response(&dc, &hash_bytes, payload, debug).await;
-                            return;
-                        }
-
-                        // Not found locally - try forwarding if HTL allows
-                        let can_forward = forward_tx.is_some() || on_forward.is_some();
-                        if can_forward && should_forward(htl) {
-                            // Track request for later push
-                            {
-                                let mut their_reqs = their_requests.write().await;
-                                their_reqs.put(
-                                    hash_key.clone(),
-                                    TheirRequest {
-                                        hash: hash_bytes,
-                                        requested_at: std::time::Instant::now(),
-                                    },
-                                );
-                            }
-
-                            // Decrement HTL before forwarding
-                            let forward_htl = htl_config.decrement(htl);
-
-                            if debug {
-                                println!(
-                                    "[Peer] Forwarding request htl={}->{}, hash={}...",
-                                    htl,
-                                    forward_htl,
-                                    &hash_key[..16.min(hash_key.len())]
-                                );
-                            }
-
-                            // Forward to other peers
-                            let forward_result = if let Some(ref tx) = forward_tx {
-                                forward_via_channel(tx, hash_bytes, peer_id.clone(), forward_htl)
-                                    .await
-                            } else if let Some(ref forward_cb) = on_forward {
-                                forward_cb(hash_bytes, peer_id.clone(), forward_htl).await
-                            } else {
-                                None
-                            };
-
-                            if let Some(payload) = forward_result {
-                                // Got it from another peer
-                                their_requests.write().await.pop(&hash_key);
-                                Self::send_response(&dc, &hash_bytes, payload, debug).await;
-
-                                if debug {
-                                    println!(
-                                        "[Peer] Forward success for hash={}...",
-                                        &hash_key[..16.min(hash_key.len())]
-                                    );
-                                }
-                                return;
-                            }
-                        }
-
-                        // Not found - stay silent (hashtree-ts behavior)
-                        // Keep in their_requests for potential later push
-                        {
-                            let mut their_reqs = their_requests.write().await;
-                            their_reqs.put(
-                                hash_key,
-                                TheirRequest {
-                                    hash: hash_bytes,
-                                    requested_at: std::time::Instant::now(),
-                                },
-                            );
-                        }
-                    }
-                    ProtoMessage::Response(res) => {
-                        let hash_key = hash_to_key(&res.h);
-
-                        // Handle fragmented vs unfragmented responses
-                        let final_data = if is_fragmented(&res) {
-                            // Fragmented response - reassemble
-                            Self::handle_fragment_response(&res, &pending_reassemblies, debug).await
-                        } else {
-                            // Unfragmented response - use directly
-                            Some(res.d)
-                        };
-
-                        let final_data = match final_data {
-                            Some(d) => d,
-                            None => return, // Incomplete fragment, wait for more
-                        };
-
-                        if debug {
-                            println!(
-                                "[Peer] Response: hash={}..., size={}",
-                                &hash_key[..16.min(hash_key.len())],
-                                final_data.len()
-                            );
-                        }
-
-                        // Resolve pending request
-                        let mut requests = pending_requests.write().await;
-                        if let Some(request) = requests.remove(&hash_key) {
-                            // Verify hash matches
-                            let computed_hash = hashtree_core::sha256(&final_data);
-                            if computed_hash.to_vec() == res.h {
-                                let _ = request.response_tx.send(Some(final_data));
-                            } else {
-                                if debug {
-                                    println!("[Peer] Hash mismatch for response");
-                                }
-                                let _ = request.response_tx.send(None);
-                            }
-                        }
-                    }
-                }
+                ctx.handle_message(msg.data.to_vec()).await;
             })
         }));
     }
 
+    /// Announce our protocol version; legacy peers ignore the message
+    async fn send_hello(dc: &Arc<RTCDataChannel>, debug: bool) {
+        let encoded = encode_hello(&create_hello());
+        if let Err(e) = dc.send(&Bytes::from(encoded)).await {
+            if debug {
+                println!("[Peer] Failed to send hello: {}", e);
+            }
+        }
+    }
+
     /// Send a response (with fragmentation if needed)
     async fn send_response(dc: &Arc<RTCDataChannel>, hash: &Hash, data: Vec<u8>, debug: bool) {
         if data.len() <= FRAGMENT_SIZE {
@@ -684,6 +615,8 @@ impl<S: Store + 'static> Peer<S> {
             self.forward_tx.clone(),
             self.on_forward_request.clone(),
             self.remote_id.to_peer_string(),
+            self.remote_version.clone(),
+            self.bandwidth.clone(),
         )
         .await;
 
@@ -691,10 +624,14 @@ impl<S: Store + 'static> Peer<S> {
         let state = self.state.clone();
         let debug = self.debug;
         dc.on_open(Box::new(move || {
-            let _data_channel = data_channel.clone();
+            let data_channel = data_channel.clone();
             let state = state.clone();
 
             Box::pin(async move {
+                let dc = data_channel.read().await.clone();
+                if let Some(dc) = dc {
+                    Self::send_hello(&dc, debug).await;
+                }
                 *state.write().await = PeerState::Ready;
                 if debug {
                     println!("[Peer] Data channel opened (outgoing)");
@@ -848,19 +785,15 @@ impl<S: Store + 'static> Peer<S> {
 
     /// Request data by hash with specified HTL
     /// Uses binary MessagePack protocol compatible with hashtree-ts
+    ///
+    /// Returns `Err(PeerError::NotFound)` as soon as a version 1 peer says it
+    /// doesn't have the hash; legacy peers stay silent until the timeout.
     pub async fn request_with_htl(
         &self,
         hash: &Hash,
         htl: u8,
     ) -> Result<Option<Vec<u8>>, PeerError> {
-        let state = *self.state.read().await;
-        if state != PeerState::Ready {
-            return Err(PeerError::NotReady);
-        }
-
-        let dc = self.data_channel.read().await;
-        let dc = dc.as_ref().ok_or(PeerError::NotReady)?;
-
+        let dc = self.ready_channel().await?;
         let hash_key = hash_to_key(hash);
 
         // Check if we already have a pending request for this hash
@@ -888,7 +821,10 @@ impl<S: Store + 'static> Peer<S> {
         let send_htl = self.htl_config.decrement(htl);
         let req = create_request(hash, send_htl);
         let encoded = encode_request(&req);
-        dc.send(&Bytes::from(encoded)).await?;
+        if let Err(e) = dc.send(&Bytes::from(encoded)).await {
+            self.pending_requests.write().await.remove(&hash_key);
+            return Err(e.into());
+        }
 
         if self.debug {
             println!(
@@ -898,18 +834,151 @@ impl<S: Store + 'static> Peer<S> {
             );
         }
 
-        // Wait for response with timeout
-        match tokio::time::timeout(std::time::Duration::from_secs(10), rx).await {
-            Ok(Ok(data)) => Ok(data),
+        self.await_response(hash, rx).await
+    }
+
+    /// Request several hashes at once
+    ///
+    /// Version 1 peers get want-lists of up to `MAX_WANT_LIST_LEN` hashes per
+    /// frame, legacy peers one request per hash. Results are in input order;
+    /// a hash listed more than once is requested once and shares the answer.
+    pub async fn request_batch(
+        &self,
+        hashes: &[Hash],
+        htl: u8,
+    ) -> Result<Vec<Result<Option<Vec<u8>>, PeerError>>, PeerError> {
+        let dc = self.ready_channel().await?;
+
+        // Coalesce duplicates so each hash has a single pending request
+        let mut unique: Vec<Hash> = Vec::with_capacity(hashes.len());
+        let mut slots = Vec::with_capacity(hashes.len());
+        {
+            let mut seen = HashMap::with_capacity(hashes.len());
+            for hash in hashes {
+                let slot = *seen.entry(*hash).or_insert_with(|| {
+                    unique.push(*hash);
+                    unique.len() - 1
+                });
+                slots.push(slot);
+            }
+        }
+        let hashes = unique.as_slice();
+
+        let mut receivers = Vec::with_capacity(hashes.len());
+        {
+            let mut requests = self.pending_requests.write().await;
+            for hash in hashes {
+                let (tx, rx) = oneshot::channel();
+                requests.insert(
+                    hash_to_key(hash),
+                    PendingRequest {
+                        hash: *hash,
+                        response_tx: tx,
+                    },
+                );
+                receivers.push(rx);
+            }
+        }
+
+        let send_htl = self.htl_config.decrement(htl);
+        let frames: Vec<Vec<u8>> = if self.remote_is_extended() {
+            hashes
+                .chunks(MAX_WANT_LIST_LEN)
+                .map(|chunk| encode_want_list(&create_want_list(chunk, send_htl)))
+                .collect()
+        } else {
+            hashes
+                .iter()
+                .map(|hash| encode_request(&create_request(hash, send_htl)))
+                .collect()
+        };
+
+        for frame in frames {
+            if let Err(e) = dc.send(&Bytes::from(frame)).await {
+                let mut requests = self.pending_requests.write().await;
+                for hash in hashes {
+                    requests.remove(&hash_to_key(hash));
+                }
+                return Err(e.into());
+            }
+        }
+
+        if self.debug {
+            println!(
+                "[Peer] Sent batch of {} requests, htl={}",
+                hashes.len(),
+                send_htl
+            );
+        }
+
+        let responses = hashes
+            .iter()
+            .zip(receivers)
+            .map(|(hash, rx)| self.await_response(hash, rx));
+        let results = futures::future::join_all(responses).await;
+        Ok(slots
+            .into_iter()
+            .map(|slot| copy_result(&results[slot]))
+            .collect())
+    }
+
+    /// Withdraw a request we sent
+    /// Version 1 peers are told to stop forwarding it
+    pub async fn cancel(&self, hash: &Hash) -> Result<(), PeerError> {
+        let removed = self
+            .pending_requests
+            .write()
+            .await
+            .remove(&hash_to_key(hash));
+        if removed.is_none() || !self.remote_is_extended() {
+            return Ok(());
+        }
+
+        let dc = self.ready_channel().await?;
+        dc.send(&Bytes::from(encode_cancel(&create_cancel(hash))))
+            .await?;
+        Ok(())
+    }
+
+    /// Data channel of a ready peer
+    async fn ready_channel(&self) -> Result<Arc<RTCDataChannel>, PeerError> {
+        if *self.state.read().await != PeerState::Ready {
+            return Err(PeerError::NotReady);
+        }
+        self.data_channel
+            .read()
+            .await
+            .clone()
+            .ok_or(PeerError::NotReady)
+    }
+
+    /// Wait for the answer to a request we sent, cancelling it on timeout
+    async fn await_response(
+        &self,
+        hash: &Hash,
+        rx: oneshot::Receiver<RequestResult>,
+    ) -> Result<Option<Vec<u8>>, PeerError> {
+        match tokio::time::timeout(REQUEST_TIMEOUT, rx).await {
+            Ok(Ok(result)) => result,
             Ok(Err(_)) => Err(PeerError::ChannelClosed),
             Err(_) => {
                 // Remove pending request on timeout
-                self.pending_requests.write().await.remove(&hash_key);
+                let _ = self.cancel(hash).await;
                 Err(PeerError::Timeout)
             }
         }
     }
 
+    /// Protocol version the remote announced (0 until its hello arrives)
+    pub fn remote_version(&self) -> u8 {
+        self.remote_version.load(Ordering::Relaxed)
+    }
+
+    /// Whether the remote understands not-found, want-list and cancel
+    fn remote_is_extended(&self) -> bool {
+        supports_extended(self.remote_version())
+    }
+
     /// Send data response using binary MessagePack protocol
     /// Note: For found data, use the internal fragmentation-aware send_response
     /// This method is kept for API compatibility but now uses binary protocol
@@ -924,9 +993,11 @@ impl<S: Store + 'static> Peer<S> {
         if let Some(payload) = data {
             // Use the internal helper with fragmentation support
             Self::send_response(dc, hash, payload.to_vec(), self.debug).await;
+        } else if self.remote_is_extended() {
+            dc.send(&Bytes::from(encode_not_found(&create_not_found(hash))))
+                .await?;
         }
-        // Note: In the new protocol, "not found" is implicit (no response sent)
-        // If needed, we could send an empty response
+        // Legacy peers get no answer for "not found"
 
         Ok(())
     }
@@ -972,6 +1043,11 @@ impl<S: Store + 'static> Peer<S> {
         let Some(their_req) = their_req else {
             return Ok(false);
         };
+        if let Some(bandwidth) = &self.bandwidth {
+            if !bandwidth.try_serve(&self.remote_id.to_peer_string(), data.len() as u64) {
+                return Ok(false);
+            }
+        }
 
         let dc = self.data_channel.read().await;
         let dc = dc.as_ref().ok_or(PeerError::NotReady)?;
@@ -1004,3 +1080,310 @@ impl<S: Store + 'static> Peer<S> {
         self.pending_requests.read().await.len()
     }
 }
+
+/// Shared state for one data channel's message handler
+struct ChannelContext<S: Store> {
+    dc: Arc<RTCDataChannel>,
+    pending_requests: Arc<RwLock<HashMap<String, PendingRequest>>>,
+    their_requests: Arc<RwLock<LruCache<String, TheirRequest>>>,
+    pending_reassemblies: Arc<RwLock<HashMap<String, PendingReassembly>>>,
+    local_store: Arc<S>,
+    debug: bool,
+    htl_config: PeerHTLConfig,
+    forward_tx: Option<ForwardTx>,
+    on_forward_request: Option<ForwardRequestCallback>,
+    peer_id: String,
+    /// Protocol version the remote announced in its hello
+    remote_version: Arc<AtomicU8>,
+    bandwidth: Option<Arc<BandwidthLimiter>>,
+}
+
+impl<S: Store + 'static> ChannelContext<S> {
+    /// Whether the remote understands not-found, want-list and cancel
+    fn remote_is_extended(&self) -> bool {
+        supports_extended(self.remote_version.load(Ordering::Relaxed))
+    }
+
+    /// Whether the upload limits let us send `bytes` to the remote now
+    fn may_serve(&self, bytes: usize) -> bool {
+        self.bandwidth
+            .as_ref()
+            .is_none_or(|bw| bw.try_serve(&self.peer_id, bytes as u64))
+    }
+
+    /// Whether the forward limits let us fetch something for the remote
+    fn may_forward(&self) -> bool {
+        self.bandwidth
+            .as_ref()
+            .is_none_or(|bw| bw.try_forward(&self.peer_id))
+    }
+
+    /// Answer a request we won't serve, so extended peers can fail over
+    async fn refuse(&self, hash: &Hash) {
+        if self.remote_is_extended() {
+            let encoded = encode_not_found(&create_not_found(hash));
+            let _ = self.dc.send(&Bytes::from(encoded)).await;
+        }
+    }
+
+    /// Handle one incoming data channel message
+    ///
+    /// Requests are answered in spawned tasks so the channel keeps reading
+    /// (and sees cancels) while they forward; only their bookkeeping is
+    /// done inline.
+    async fn handle_message(self: &Arc<Self>, data: Vec<u8>) {
+        if data.is_empty() {
+            return;
+        }
+
+        // Parse MessagePack binary protocol
+        let parsed = match parse_message(&data) {
+            Some(m) => m,
+            None => {
+                if self.debug {
+                    println!("[Peer] Failed to parse message");
+                }
+                return;
+            }
+        };
+
+        match parsed {
+            ProtoMessage::Request(req) => {
+                let htl = req.htl.unwrap_or(MAX_HTL);
+                if let Some(hash) = bytes_to_hash(&req.h) {
+                    let cancel_signal = self.track_their_request(&hash_to_key(&hash), hash).await;
+                    let ctx = Arc::clone(self);
+                    tokio::spawn(async move {
+                        ctx.handle_request(hash, htl, cancel_signal).await;
+                    });
+                }
+            }
+            ProtoMessage::WantList(want) => {
+                let htl = want.htl.unwrap_or(MAX_HTL);
+                if self.debug {
+                    println!("[Peer] Want list: {} hashes, htl={}", want.hs.len(), htl);
+                }
+                let mut tracked = Vec::new();
+                for hash in want
+                    .hs
+                    .iter()
+                    .take(MAX_WANT_LIST_LEN)
+                    .filter_map(|h| bytes_to_hash(h))
+                {
+                    let cancel_signal = self.track_their_request(&hash_to_key(&hash), hash).await;
+                    tracked.push((hash, cancel_signal));
+                }
+                let ctx = Arc::clone(self);
+                tokio::spawn(async move {
+                    let requests = tracked
+                        .into_iter()
+                        .map(|(hash, cancel_signal)| ctx.handle_request(hash, htl, cancel_signal));
+                    futures::future::join_all(requests).await;
+                });
+            }
+            ProtoMessage::Response(res) => self.handle_response(res).await,
+            ProtoMessage::NotFound(msg) => {
+                let hash_key = hash_to_key(&msg.h);
+                if self.debug {
+                    println!(
+                        "[Peer] Not found: hash={}...",
+                        &hash_key[..16.min(hash_key.len())]
+                    );
+                }
+                if let Some(request) = self.pending_requests.write().await.remove(&hash_key) {
+                    let _ = request.response_tx.send(Err(PeerError::NotFound));
+                }
+            }
+            ProtoMessage::Cancel(msg) => {
+                // Stop forwarding it; forgetting the request also keeps a
+                // late result from being sent
+                let removed = self.their_requests.write().await.pop(&hash_to_key(&msg.h));
+                if let Some(request) = removed {
+                    request.cancelled.notify_one();
+                }
+            }
+            ProtoMessage::Hello(hello) => {
+                if self.debug {
+                    println!("[Peer] Remote protocol version {}", hello.v);
+                }
+                self.remote_version.store(hello.v, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Answer one requested hash from the local store or by forwarding
+    ///
+    /// The request is already in `their_requests`; `cancel_signal` is raised
+    /// if the requester cancels it.
+    async fn handle_request(&self, hash: Hash, htl: u8, cancel_signal: Arc<Notify>) {
+        let hash_key = hash_to_key(&hash);
+
+        if self.debug {
+            println!(
+                "[Peer] Request: hash={}..., htl={}",
+                &hash_key[..16.min(hash_key.len())],
+                htl
+            );
+        }
+
+        // Try local store first
+        if let Ok(Some(payload)) = self.local_store.get(&hash).await {
+            if !self.finish_their_request(&hash_key).await {
+                return;
+            }
+            // Found locally - send response unless rate limited
+            if self.may_serve(payload.len()) {
+                Peer::<S>::send_response(&self.dc, &hash, payload, self.debug).await;
+            } else {
+                if self.debug {
+                    println!(
+                        "[Peer] Upload limit hit, refusing hash={}...",
+                        &hash_key[..16.min(hash_key.len())]
+                    );
+                }
+                self.refuse(&hash).await;
+            }
+            return;
+        }
+
+        // Not found locally - try forwarding if HTL and forward limits allow
+        let can_forward = self.forward_tx.is_some() || self.on_forward_request.is_some();
+        if can_forward && should_forward(htl) && self.may_forward() {
+            // Decrement HTL before forwarding
+            let forward_htl = self.htl_config.decrement(htl);
+
+            if self.debug {
+                println!(
+                    "[Peer] Forwarding request htl={}->{}, hash={}...",
+                    htl,
+                    forward_htl,
+                    &hash_key[..16.min(hash_key.len())]
+                );
+            }
+
+            // Forward to other peers until the requester cancels; dropping
+            // the forward tells the store to withdraw it downstream
+            let forward = async {
+                if let Some(ref tx) = self.forward_tx {
+                    forward_via_channel(tx, hash, self.peer_id.clone(), forward_htl).await
+                } else if let Some(ref forward_cb) = self.on_forward_request {
+                    forward_cb(hash, self.peer_id.clone(), forward_htl).await
+                } else {
+                    None
+                }
+            };
+            let forward_result = tokio::select! {
+                result = forward => result,
+                _ = cancel_signal.notified() => None,
+            };
+
+            let cancelled = !self.finish_their_request(&hash_key).await;
+
+            if let Some(payload) = forward_result {
+                // Got it from another peer
+                if let Some(bandwidth) = &self.bandwidth {
+                    bandwidth.record_forwarded(&self.peer_id, payload.len() as u64);
+                }
+                if !cancelled {
+                    if self.may_serve(payload.len()) {
+                        Peer::<S>::send_response(&self.dc, &hash, payload, self.debug).await;
+                    } else {
+                        self.refuse(&hash).await;
+                    }
+                }
+
+                if self.debug {
+                    println!(
+                        "[Peer] Forward success for hash={}...",
+                        &hash_key[..16.min(hash_key.len())]
+                    );
+                }
+                return;
+            }
+            if cancelled {
+                return;
+            }
+        } else if !self.finish_their_request(&hash_key).await {
+            return;
+        }
+
+        if self.remote_is_extended() {
+            // Tell the requester right away so it can fail over to another peer
+            let encoded = encode_not_found(&create_not_found(&hash));
+            let _ = self.dc.send(&Bytes::from(encoded)).await;
+        } else {
+            // Not found - stay silent (hashtree-ts behavior)
+            // Keep in their_requests for potential later push
+            self.track_their_request(&hash_key, hash).await;
+        }
+    }
+
+    /// Forget a request we are about to answer
+    ///
+    /// Returns false if an extended requester cancelled it meanwhile.
+    async fn finish_their_request(&self, hash_key: &str) -> bool {
+        let still_wanted = self.their_requests.write().await.pop(hash_key).is_some();
+        still_wanted || !self.remote_is_extended()
+    }
+
+    /// Remember a request we couldn't answer yet
+    ///
+    /// Returns the signal raised when the requester cancels it.
+    async fn track_their_request(&self, hash_key: &str, hash: Hash) -> Arc<Notify> {
+        let cancelled = Arc::new(Notify::new());
+        self.their_requests.write().await.put(
+            hash_key.to_string(),
+            TheirRequest {
+                hash,
+                requested_at: std::time::Instant::now(),
+                cancelled: cancelled.clone(),
+            },
+        );
+        cancelled
+    }
+
+    /// Resolve a pending request with a (possibly fragmented) response
+    async fn handle_response(&self, res: DataResponse) {
+        let hash_key = hash_to_key(&res.h);
+        if let Some(bandwidth) = &self.bandwidth {
+            bandwidth.record_received(&self.peer_id, res.d.len() as u64);
+        }
+
+        // Handle fragmented vs unfragmented responses
+        let final_data = if is_fragmented(&res) {
+            // Fragmented response - reassemble
+            Peer::<S>::handle_fragment_response(&res, &self.pending_reassemblies, self.debug).await
+        } else {
+            // Unfragmented response - use directly
+            Some(res.d)
+        };
+
+        let final_data = match final_data {
+            Some(d) => d,
+            None => return, // Incomplete fragment, wait for more
+        };
+
+        if self.debug {
+            println!(
+                "[Peer] Response: hash={}..., size={}",
+                &hash_key[..16.min(hash_key.len())],
+                final_data.len()
+            );
+        }
+
+        // Resolve pending request
+        let mut requests = self.pending_requests.write().await;
+        if let Some(request) = requests.remove(&hash_key) {
+            // Verify hash matches
+            let computed_hash = hashtree_core::sha256(&final_data);
+            if computed_hash.to_vec() == res.h {
+                let _ = request.response_tx.send(Ok(Some(final_data)));
+            } else {
+                if self.debug {
+                    println!("[Peer] Hash mismatch for response");
+                }
+                let _ = request.response_tx.send(Ok(None));
+            }
+        }
+    }
+}
@@ -4,11 +4,18 @@
 //! - Per-peer performance tracking (RTT, success rate)
 //! - RFC 2988-style smoothed RTT calculation
 //! - Exponential backoff for failing/slow peers
+//! - Explicit "not found" answers count as responsive, not as failures
 //! - Fairness constraints to prevent overloading any single peer
 //! - Weighted selection combining multiple signals
+//! - Heavy penalties for peers whose responses fail hash verification
+//! - Reputation persisted per pubkey, so restarts remember bad peers
+//! - An explicit ban/allow list, keyed by pubkey
 
-use std::collections::HashMap;
-use std::time::{Duration, Instant};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 /// Constants from Freenet's PeerManager
 const SELECTION_PERCENTAGE_WARNING: f64 = 0.30; // Skip if selected >30% of time
@@ -24,6 +31,12 @@ const MIN_RTO_MS: u64 = 50; // Minimum retransmission timeout
 const MAX_RTO_MS: u64 = 60_000; // Maximum RTO (60 seconds)
 const INITIAL_RTO_MS: u64 = 1000; // Initial RTO before any measurements
 
+/// Corrupt data constants
+const CORRUPT_BACKOFF_STEPS: u32 = 4; // One bad response backs off like 4 failures
+const CORRUPT_SCORE_FACTOR: f64 = 0.5; // Each bad response halves the score
+/// Peers with this many corrupt responses are excluded unless explicitly allowed
+pub const AUTO_BAN_CORRUPT: u64 = 3;
+
 /// Per-peer performance statistics
 #[derive(Debug, Clone)]
 pub struct PeerStats {
@@ -37,8 +50,12 @@ pub struct PeerStats {
     pub successes: u64,
     /// Total timeouts
     pub timeouts: u64,
+    /// Total explicit "not found" answers
+    pub not_found: u64,
     /// Total failures (bad data, disconnects, etc.)
     pub failures: u64,
+    /// Responses that failed hash verification (also counted in `failures`)
+    pub corrupt: u64,
     /// Smoothed round-trip time (RFC 2988 SRTT)
     pub srtt_ms: f64,
     /// RTT variance (RFC 2988 RTTVAR)
@@ -70,7 +87,9 @@ impl PeerStats {
             requests_sent: 0,
             successes: 0,
             timeouts: 0,
+            not_found: 0,
             failures: 0,
+            corrupt: 0,
             srtt_ms: 0.0,
             rttvar_ms: 0.0,
             rto_ms: INITIAL_RTO_MS,
@@ -85,11 +104,15 @@ impl PeerStats {
     }
 
     /// Get success rate (0.0 to 1.0)
+    ///
+    /// Requests the peer answered with "not found" are left out: the peer was
+    /// responsive, it just didn't have the data.
     pub fn success_rate(&self) -> f64 {
-        if self.requests_sent == 0 {
+        let answerable = self.requests_sent.saturating_sub(self.not_found);
+        if answerable == 0 {
             return 0.5; // Neutral for new peers
         }
-        self.successes as f64 / self.requests_sent as f64
+        self.successes as f64 / answerable as f64
     }
 
     /// Get selection rate (selections per second since connected)
@@ -139,7 +162,19 @@ impl PeerStats {
         self.backed_off_until = None;
         self.backoff_level = 0;
 
-        // RFC 2988 RTT update
+        self.update_rtt(rtt_ms);
+    }
+
+    /// Record an explicit "not found" answer with RTT
+    /// The peer responded promptly, so RTT is updated and no backoff applies
+    pub fn record_not_found(&mut self, rtt_ms: u64) {
+        self.not_found += 1;
+        self.consecutive_rto_backoffs = 0;
+        self.update_rtt(rtt_ms);
+    }
+
+    /// RFC 2988 RTT update
+    fn update_rtt(&mut self, rtt_ms: u64) {
         let rtt = rtt_ms as f64;
         if self.srtt_ms == 0.0 {
             // First measurement
@@ -181,10 +216,21 @@ impl PeerStats {
         self.apply_backoff();
     }
 
+    /// Record a response whose data didn't match the requested hash
+    ///
+    /// Counts as a failure, backs off much harder than a timeout and lowers
+    /// the score for good (see `AUTO_BAN_CORRUPT`).
+    pub fn record_corrupt(&mut self) {
+        self.corrupt += 1;
+        self.backoff_level += CORRUPT_BACKOFF_STEPS - 1;
+        self.record_failure();
+    }
+
     /// Apply exponential backoff
     fn apply_backoff(&mut self) {
         self.backoff_level += 1;
-        let backoff_ms = (INITIAL_BACKOFF_MS * BACKOFF_MULTIPLIER.pow(self.backoff_level - 1))
+        let backoff_ms = INITIAL_BACKOFF_MS
+            .saturating_mul(BACKOFF_MULTIPLIER.saturating_pow(self.backoff_level - 1))
             .min(MAX_BACKOFF_MS);
         self.backed_off_until = Some(Instant::now() + Duration::from_millis(backoff_ms));
     }
@@ -217,10 +263,152 @@ impl PeerStats {
 
         // Combine scores (weighted)
         // Success rate is most important (60%), RTT next (30%), recency last (10%)
-        0.6 * success_score + 0.3 * rtt_score + 0.1 * (1.0 + recency_bonus)
+        let score = 0.6 * success_score + 0.3 * rtt_score + 0.1 * (1.0 + recency_bonus);
+
+        // Corrupt data outweighs everything else
+        score * CORRUPT_SCORE_FACTOR.powi(self.corrupt.min(64) as i32)
+    }
+
+    /// Snapshot of the counters worth keeping across restarts
+    pub fn reputation(&self) -> PeerReputation {
+        let backed_off_until = self
+            .backed_off_until
+            .and_then(|until| until.checked_duration_since(Instant::now()))
+            .map(|remaining| unix_now() + remaining.as_secs().max(1));
+        PeerReputation {
+            requests_sent: self.requests_sent,
+            successes: self.successes,
+            timeouts: self.timeouts,
+            not_found: self.not_found,
+            failures: self.failures,
+            corrupt: self.corrupt,
+            srtt_ms: self.srtt_ms,
+            rttvar_ms: self.rttvar_ms,
+            rto_ms: self.rto_ms,
+            backoff_level: self.backoff_level,
+            backed_off_until,
+            bytes_received: self.bytes_received,
+            bytes_sent: self.bytes_sent,
+            updated_at: unix_now(),
+        }
+    }
+
+    /// Stats for a new session of a peer with a known reputation
+    pub fn from_reputation(peer_id: impl Into<String>, reputation: &PeerReputation) -> Self {
+        let mut stats = Self::new(peer_id);
+        stats.requests_sent = reputation.requests_sent;
+        stats.successes = reputation.successes;
+        stats.timeouts = reputation.timeouts;
+        stats.not_found = reputation.not_found;
+        stats.failures = reputation.failures;
+        stats.corrupt = reputation.corrupt;
+        stats.srtt_ms = reputation.srtt_ms;
+        stats.rttvar_ms = reputation.rttvar_ms;
+        if reputation.rto_ms > 0 {
+            stats.rto_ms = reputation.rto_ms.clamp(MIN_RTO_MS, MAX_RTO_MS);
+        }
+        stats.backoff_level = reputation.backoff_level;
+        stats.backed_off_until = reputation
+            .backed_off_until
+            .and_then(|until| until.checked_sub(unix_now()))
+            .filter(|remaining| *remaining > 0)
+            .map(|remaining| Instant::now() + Duration::from_secs(remaining));
+        stats.bytes_received = reputation.bytes_received;
+        stats.bytes_sent = reputation.bytes_sent;
+        stats
     }
 }
 
+/// Persisted performance record of a peer, keyed by pubkey
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PeerReputation {
+    pub requests_sent: u64,
+    pub successes: u64,
+    pub timeouts: u64,
+    pub not_found: u64,
+    pub failures: u64,
+    pub corrupt: u64,
+    pub srtt_ms: f64,
+    pub rttvar_ms: f64,
+    pub rto_ms: u64,
+    pub backoff_level: u32,
+    /// Unix seconds the current backoff ends at
+    pub backed_off_until: Option<u64>,
+    pub bytes_received: u64,
+    pub bytes_sent: u64,
+    /// Unix seconds of the last snapshot
+    pub updated_at: u64,
+}
+
+/// Explicit decision about a peer pubkey
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PeerAccess {
+    /// Never selected for requests and never accepted as a connection
+    Banned,
+    /// Exempt from automatic exclusion (e.g. after corrupt responses)
+    Allowed,
+}
+
+impl std::fmt::Display for PeerAccess {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PeerAccess::Banned => write!(f, "banned"),
+            PeerAccess::Allowed => write!(f, "allowed"),
+        }
+    }
+}
+
+/// Reputation of every known peer, by pubkey
+pub type ReputationMap = BTreeMap<String, PeerReputation>;
+
+/// Ban/allow list, by pubkey
+pub type AccessList = BTreeMap<String, PeerAccess>;
+
+/// Pubkey part of a "pubkey:uuid" peer id (the whole string if it has no uuid).
+/// WebSocket peers are keyed by "host:port", which is kept whole.
+pub fn peer_pubkey(peer_id: &str) -> &str {
+    if let Some((_, port)) = peer_id.rsplit_once(':') {
+        if port.parse::<u16>().is_ok() {
+            return peer_id;
+        }
+    }
+    peer_id.split(':').next().unwrap_or(peer_id)
+}
+
+/// Read a JSON state file (reputation or access list); a missing file is empty
+pub fn load_state_file<T: DeserializeOwned + Default>(path: &Path) -> std::io::Result<T> {
+    match std::fs::read(path) {
+        Ok(data) => serde_json::from_slice(&data)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Write a JSON state file atomically (temp file + rename)
+pub fn save_state_file<T: Serialize>(path: &Path, value: &T) -> std::io::Result<()> {
+    let data = serde_json::to_vec_pretty(value)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            std::fs::create_dir_all(parent)?;
+        }
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    std::fs::write(&tmp, data)?;
+    std::fs::rename(&tmp, path)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
 /// Peer selection strategy
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
 pub enum SelectionStrategy {
@@ -244,10 +432,18 @@ pub enum SelectionStrategy {
 /// - Prefers low latency peers
 /// - Backs off failing peers exponentially
 /// - Ensures fairness (no peer gets >30% of traffic with 5+ peers)
+/// - Skips banned pubkeys and peers that keep sending corrupt data
+///
+/// Live stats are kept per session ("pubkey:uuid"); reputation carries over
+/// between sessions and restarts per pubkey.
 #[derive(Debug, Default)]
 pub struct PeerSelector {
     /// Per-peer statistics
     stats: HashMap<String, PeerStats>,
+    /// Reputation of peers from earlier sessions, by pubkey
+    reputations: HashMap<String, PeerReputation>,
+    /// Ban/allow list, by pubkey
+    access: HashMap<String, PeerAccess>,
     /// Selection strategy
     strategy: SelectionStrategy,
     /// Enable fairness constraints (Freenet FOAF mitigation)
@@ -259,18 +455,15 @@ pub struct PeerSelector {
 impl PeerSelector {
     /// Create a new peer selector with default weighted strategy
     pub fn new() -> Self {
-        Self {
-            stats: HashMap::new(),
-            strategy: SelectionStrategy::Weighted,
-            fairness_enabled: true,
-            round_robin_idx: 0,
-        }
+        Self::with_strategy(SelectionStrategy::Weighted)
     }
 
     /// Create with specific strategy
     pub fn with_strategy(strategy: SelectionStrategy) -> Self {
         Self {
             stats: HashMap::new(),
+            reputations: HashMap::new(),
+            access: HashMap::new(),
             strategy,
             fairness_enabled: true,
             round_robin_idx: 0,
@@ -282,17 +475,100 @@ impl PeerSelector {
         self.fairness_enabled = enabled;
     }
 
-    /// Add a peer to track
+    /// Add a peer to track, starting from its pubkey's reputation if known
     pub fn add_peer(&mut self, peer_id: impl Into<String>) {
         let peer_id = peer_id.into();
+        let reputation = self.reputations.get(peer_pubkey(&peer_id));
         self.stats
             .entry(peer_id.clone())
-            .or_insert_with(|| PeerStats::new(peer_id));
+            .or_insert_with(|| match reputation {
+                Some(reputation) => PeerStats::from_reputation(peer_id, reputation),
+                None => PeerStats::new(peer_id),
+            });
     }
 
-    /// Remove a peer
+    /// Remove a peer, keeping its record as the pubkey's reputation
     pub fn remove_peer(&mut self, peer_id: &str) {
-        self.stats.remove(peer_id);
+        if let Some(stats) = self.stats.remove(peer_id) {
+            self.reputations
+                .insert(peer_pubkey(peer_id).to_string(), stats.reputation());
+        }
+    }
+
+    /// Reputation of every peer seen, including live sessions
+    pub fn reputation(&self) -> ReputationMap {
+        let mut map: ReputationMap = self
+            .reputations
+            .iter()
+            .map(|(pubkey, rep)| (pubkey.clone(), rep.clone()))
+            .collect();
+        // Live sessions start from the stored record, so they supersede it;
+        // with several sessions per pubkey the busiest one wins
+        let mut live: HashMap<&str, &PeerStats> = HashMap::new();
+        for stats in self.stats.values() {
+            let entry = live.entry(peer_pubkey(&stats.peer_id)).or_insert(stats);
+            if stats.requests_sent > entry.requests_sent {
+                *entry = stats;
+            }
+        }
+        for (pubkey, stats) in live {
+            map.insert(pubkey.to_string(), stats.reputation());
+        }
+        map
+    }
+
+    /// Load reputation from an earlier run; applies to peers added afterwards
+    pub fn restore_reputation(&mut self, reputation: ReputationMap) {
+        self.reputations = reputation.into_iter().collect();
+    }
+
+    /// Ban or allow a pubkey (None clears the entry)
+    pub fn set_access(&mut self, pubkey: &str, access: Option<PeerAccess>) {
+        match access {
+            Some(access) => self.access.insert(pubkey.to_string(), access),
+            None => self.access.remove(pubkey),
+        };
+    }
+
+    /// Explicit ban/allow entry for a pubkey
+    pub fn access(&self, pubkey: &str) -> Option<PeerAccess> {
+        self.access.get(pubkey).copied()
+    }
+
+    /// Current ban/allow list
+    pub fn access_list(&self) -> AccessList {
+        self.access
+            .iter()
+            .map(|(pubkey, access)| (pubkey.clone(), *access))
+            .collect()
+    }
+
+    /// Replace the ban/allow list (e.g. after it was edited on disk)
+    pub fn set_access_list(&mut self, list: AccessList) {
+        self.access = list.into_iter().collect();
+    }
+
+    /// Whether a peer (by "pubkey:uuid" id or bare pubkey) must not be used
+    ///
+    /// Banned pubkeys always are; allowed ones never are; anyone else is once
+    /// they have sent `AUTO_BAN_CORRUPT` corrupt responses.
+    pub fn is_blocked(&self, peer: &str) -> bool {
+        let pubkey = peer_pubkey(peer);
+        match self.access.get(pubkey) {
+            Some(PeerAccess::Banned) => true,
+            Some(PeerAccess::Allowed) => false,
+            None => self.corrupt_count(pubkey) >= AUTO_BAN_CORRUPT,
+        }
+    }
+
+    /// Corrupt responses seen from a pubkey, across sessions
+    fn corrupt_count(&self, pubkey: &str) -> u64 {
+        let stored = self.reputations.get(pubkey).map_or(0, |r| r.corrupt);
+        self.stats
+            .values()
+            .filter(|s| peer_pubkey(&s.peer_id) == pubkey)
+            .map(|s| s.corrupt)
+            .fold(stored, u64::max)
     }
 
     /// Get peer stats (immutable)
@@ -324,6 +600,13 @@ impl PeerSelector {
         }
     }
 
+    /// Record an explicit "not found" answer
+    pub fn record_not_found(&mut self, peer_id: &str, rtt_ms: u64) {
+        if let Some(stats) = self.stats.get_mut(peer_id) {
+            stats.record_not_found(rtt_ms);
+        }
+    }
+
     /// Record a timeout
     pub fn record_timeout(&mut self, peer_id: &str) {
         if let Some(stats) = self.stats.get_mut(peer_id) {
@@ -338,11 +621,18 @@ impl PeerSelector {
         }
     }
 
-    /// Get available (non-backed-off) peers
+    /// Record a response that failed hash verification
+    pub fn record_corrupt(&mut self, peer_id: &str) {
+        if let Some(stats) = self.stats.get_mut(peer_id) {
+            stats.record_corrupt();
+        }
+    }
+
+    /// Get available (non-backed-off, non-blocked) peers
     fn available_peers(&self) -> Vec<String> {
         self.stats
             .iter()
-            .filter(|(_, s)| !s.is_backed_off())
+            .filter(|(id, s)| !s.is_backed_off() && !self.is_blocked(id))
             .map(|(id, _)| id.clone())
             .collect()
     }
@@ -377,7 +667,8 @@ impl PeerSelector {
     /// Select peers ordered by preference
     ///
     /// Returns all available peers sorted by preference (best first).
-    /// Respects backoff states and fairness constraints.
+    /// Respects backoff states and fairness constraints; blocked peers are
+    /// never returned.
     pub fn select_peers(&mut self) -> Vec<String> {
         let available = self.available_peers();
         if available.is_empty() {
@@ -386,7 +677,7 @@ impl PeerSelector {
             let mut backed_off: Vec<_> = self
                 .stats
                 .iter()
-                .filter(|(_, s)| s.is_backed_off())
+                .filter(|(id, s)| s.is_backed_off() && !self.is_blocked(id))
                 .map(|(id, s)| (id.clone(), s.backoff_remaining()))
                 .collect();
             backed_off.sort_by_key(|(_, remaining)| *remaining);
@@ -505,6 +796,7 @@ impl PeerSelector {
         let total_requests: u64 = self.stats.values().map(|s| s.requests_sent).sum();
         let total_successes: u64 = self.stats.values().map(|s| s.successes).sum();
         let total_timeouts: u64 = self.stats.values().map(|s| s.timeouts).sum();
+        let total_not_found: u64 = self.stats.values().map(|s| s.not_found).sum();
         let backed_off = self.stats.values().filter(|s| s.is_backed_off()).count();
 
         let avg_rtt = {
@@ -526,6 +818,7 @@ impl PeerSelector {
             total_requests,
             total_successes,
             total_timeouts,
+            total_not_found,
             backed_off_count: backed_off,
             avg_rtt_ms: avg_rtt,
             overall_success_rate: if total_requests > 0 {
@@ -544,6 +837,7 @@ pub struct SelectorSummary {
     pub total_requests: u64,
     pub total_successes: u64,
     pub total_timeouts: u64,
+    pub total_not_found: u64,
     pub backed_off_count: usize,
     pub avg_rtt_ms: f64,
     pub overall_success_rate: f64,
@@ -606,6 +900,36 @@ mod tests {
         assert_eq!(stats.backoff_level, 0);
     }
 
+    #[test]
+    fn test_peer_stats_not_found_is_not_a_failure() {
+        let mut stats = PeerStats::new("peer1");
+        stats.record_request(40);
+        stats.record_success(50, 1024);
+        stats.record_request(40);
+        stats.record_not_found(30);
+
+        assert!(!stats.is_backed_off());
+        assert_eq!(stats.not_found, 1);
+        assert_eq!(stats.success_rate(), 1.0);
+        // The miss still counts as an RTT sample
+        assert!(stats.srtt_ms < 50.0);
+    }
+
+    #[test]
+    fn test_peer_selector_prefers_misses_over_timeouts() {
+        let mut selector = PeerSelector::new();
+        selector.add_peer("silent");
+        selector.add_peer("honest");
+
+        selector.record_request("silent", 40);
+        selector.record_timeout("silent");
+        selector.record_request("honest", 40);
+        selector.record_not_found("honest", 20);
+
+        assert_eq!(selector.select_peers(), vec!["honest".to_string()]);
+        assert_eq!(selector.summary().total_not_found, 1);
+    }
+
     #[test]
     fn test_peer_selector_add_remove() {
         let mut selector = PeerSelector::new();
@@ -758,6 +1082,110 @@ mod tests {
         assert!(good_score > bad_score);
     }
 
+    #[test]
+    fn test_corrupt_responses_penalize_and_exclude() {
+        let mut selector = PeerSelector::new();
+        selector.add_peer("liar:s1");
+        selector.add_peer("slow:s1");
+
+        selector.record_request("slow:s1", 40);
+        selector.record_timeout("slow:s1");
+        selector.record_request("liar:s1", 40);
+        selector.record_corrupt("liar:s1");
+
+        let liar = selector.get_stats("liar:s1").unwrap();
+        let slow = selector.get_stats("slow:s1").unwrap();
+        assert_eq!(liar.failures, 1);
+        assert!(liar.backoff_remaining() > slow.backoff_remaining());
+        assert!(liar.score() < slow.score());
+
+        for _ in 1..AUTO_BAN_CORRUPT {
+            selector.record_corrupt("liar:s1");
+        }
+        assert!(selector.is_blocked("liar"));
+        // Even when everyone is backed off, excluded peers stay out
+        assert_eq!(selector.select_peers(), vec!["slow:s1".to_string()]);
+
+        // An explicit allow overrides the automatic exclusion
+        selector.set_access("liar", Some(PeerAccess::Allowed));
+        assert!(!selector.is_blocked("liar:s1"));
+    }
+
+    #[test]
+    fn test_peer_pubkey() {
+        assert_eq!(peer_pubkey("alice:session1"), "alice");
+        assert_eq!(peer_pubkey("alice"), "alice");
+        assert_eq!(peer_pubkey("10.0.0.2:8080"), "10.0.0.2:8080");
+        assert_eq!(peer_pubkey("[::1]:8080"), "[::1]:8080");
+    }
+
+    #[test]
+    fn test_ban_list_by_address() {
+        let mut selector = PeerSelector::new();
+        selector.add_peer("10.0.0.2:8080");
+        selector.add_peer("10.0.0.2:8081");
+
+        selector.set_access("10.0.0.2:8080", Some(PeerAccess::Banned));
+        assert!(selector.is_blocked("10.0.0.2:8080"));
+        assert_eq!(selector.select_peers(), vec!["10.0.0.2:8081".to_string()]);
+    }
+
+    #[test]
+    fn test_ban_list() {
+        let mut selector = PeerSelector::new();
+        selector.add_peer("good:s1");
+        selector.add_peer("bad:s1");
+        selector.add_peer("bad:s2");
+
+        selector.set_access("bad", Some(PeerAccess::Banned));
+        assert!(selector.is_blocked("bad"));
+        assert!(selector.is_blocked("bad:s2"));
+        assert_eq!(selector.select_peers(), vec!["good:s1".to_string()]);
+        assert_eq!(
+            selector.access_list(),
+            AccessList::from([("bad".to_string(), PeerAccess::Banned)])
+        );
+
+        selector.set_access("bad", None);
+        assert_eq!(selector.select_peers().len(), 3);
+    }
+
+    #[test]
+    fn test_reputation_carries_over_by_pubkey() {
+        let mut selector = PeerSelector::new();
+        selector.add_peer("alice:session1");
+        for _ in 0..4 {
+            selector.record_request("alice:session1", 40);
+            selector.record_success("alice:session1", 30, 1024);
+        }
+        selector.record_request("alice:session1", 40);
+        selector.record_corrupt("alice:session1");
+
+        let path = std::env::temp_dir().join(format!(
+            "hashtree-peer-reputation-{}-{}.json",
+            std::process::id(),
+            unix_now()
+        ));
+        save_state_file(&path, &selector.reputation()).unwrap();
+
+        // A restarted node sees alice again under a new session id
+        let mut restarted = PeerSelector::new();
+        restarted.restore_reputation(load_state_file(&path).unwrap());
+        restarted.add_peer("alice:session2");
+        let stats = restarted.get_stats("alice:session2").unwrap();
+        assert_eq!(stats.successes, 4);
+        assert_eq!(stats.corrupt, 1);
+        assert!(stats.is_backed_off());
+        assert!((stats.srtt_ms - 30.0).abs() < 0.1);
+
+        restarted.remove_peer("alice:session2");
+        assert_eq!(restarted.reputation()["alice"].corrupt, 1);
+
+        std::fs::remove_file(&path).unwrap();
+        let missing: ReputationMap = load_state_file(&path).unwrap();
+        assert!(missing.is_empty());
+    }
+
     #[test]
     fn test_lowest_latency_strategy() {
         let mut selector = PeerSelector::with_strategy(SelectionStrategy::LowestLatency);
@@ -5,17 +5,42 @@
 //! - Response: [0x01][msgpack: {h: bytes32, d: bytes, i?: u32, n?: u32}]
 //!
 //! Fragmented responses include `i` (index) and `n` (total), unfragmented omit them.
+//!
+//! Protocol version 1 adds:
+//! - Hello:    [0x02][msgpack: {v: u8}]
+//! - NotFound: [0x03][msgpack: {h: bytes32}]
+//! - WantList: [0x04][msgpack: {hs: [bytes32], htl?: u8}]
+//! - Cancel:   [0x05][msgpack: {h: bytes32}]
+//!
+//! Every peer sends a hello when the data channel opens. Peers that never send
+//! one (hashtree-ts, older builds) are treated as version 0 and only ever get
+//! requests and responses; a missing hash is still answered with silence.
 
 use hashtree_core::Hash;
 use serde::{Deserialize, Serialize};
+use serde_bytes::ByteBuf;
 
 /// Message type bytes (prefix before MessagePack body)
 pub const MSG_TYPE_REQUEST: u8 = 0x00;
 pub const MSG_TYPE_RESPONSE: u8 = 0x01;
+pub const MSG_TYPE_HELLO: u8 = 0x02;
+pub const MSG_TYPE_NOT_FOUND: u8 = 0x03;
+pub const MSG_TYPE_WANT_LIST: u8 = 0x04;
+pub const MSG_TYPE_CANCEL: u8 = 0x05;
+
+/// Version assumed for peers that never sent a hello
+pub const PROTOCOL_VERSION_LEGACY: u8 = 0;
+/// First version with hello, not-found, want-list and cancel messages
+pub const PROTOCOL_VERSION_EXTENDED: u8 = 1;
+/// Version announced in our hello
+pub const PROTOCOL_VERSION: u8 = PROTOCOL_VERSION_EXTENDED;
 
 /// Fragment size for large data (32KB - safe limit for WebRTC)
 pub const FRAGMENT_SIZE: usize = 32 * 1024;
 
+/// Maximum hashes in one want-list frame (~2.3KB encoded)
+pub const MAX_WANT_LIST_LEN: usize = 64;
+
 /// Data request message body
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DataRequest {
@@ -44,31 +69,88 @@ pub struct DataResponse {
     pub n: Option<u32>,
 }
 
+/// Protocol version announcement, sent once when the data channel opens
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataHello {
+    /// Highest protocol version the sender speaks
+    pub v: u8,
+}
+
+/// Explicit answer that the responder doesn't have a hash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataNotFound {
+    /// 32-byte hash
+    #[serde(with = "serde_bytes")]
+    pub h: Vec<u8>,
+}
+
+/// Several requests in one frame, answered one response or not-found per hash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataWantList {
+    /// 32-byte hashes
+    pub hs: Vec<ByteBuf>,
+    /// Hops To Live (optional, defaults to MAX_HTL)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub htl: Option<u8>,
+}
+
+/// Withdraw an earlier request; the responder stops forwarding it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataCancel {
+    /// 32-byte hash
+    #[serde(with = "serde_bytes")]
+    pub h: Vec<u8>,
+}
+
 /// Parsed data message
 #[derive(Debug, Clone)]
 pub enum DataMessage {
     Request(DataRequest),
     Response(DataResponse),
+    Hello(DataHello),
+    NotFound(DataNotFound),
+    WantList(DataWantList),
+    Cancel(DataCancel),
 }
 
-/// Encode a request message to wire format
+/// Prefix a MessagePack body with its message type
 /// Uses named/map encoding for compatibility with hashtree-ts and to support optional fields
-pub fn encode_request(req: &DataRequest) -> Vec<u8> {
-    let body = rmp_serde::to_vec_named(req).expect("Failed to encode request");
+fn encode_message<T: Serialize>(msg_type: u8, body: &T) -> Vec<u8> {
+    let body = rmp_serde::to_vec_named(body).expect("Failed to encode message");
     let mut result = Vec::with_capacity(1 + body.len());
-    result.push(MSG_TYPE_REQUEST);
+    result.push(msg_type);
     result.extend(body);
     result
 }
 
+/// Encode a request message to wire format
+pub fn encode_request(req: &DataRequest) -> Vec<u8> {
+    encode_message(MSG_TYPE_REQUEST, req)
+}
+
 /// Encode a response message to wire format
-/// Uses named/map encoding for compatibility with hashtree-ts and to support optional fields
 pub fn encode_response(res: &DataResponse) -> Vec<u8> {
-    let body = rmp_serde::to_vec_named(res).expect("Failed to encode response");
-    let mut result = Vec::with_capacity(1 + body.len());
-    result.push(MSG_TYPE_RESPONSE);
-    result.extend(body);
-    result
+    encode_message(MSG_TYPE_RESPONSE, res)
+}
+
+/// Encode a hello message to wire format
+pub fn encode_hello(hello: &DataHello) -> Vec<u8> {
+    encode_message(MSG_TYPE_HELLO, hello)
+}
+
+/// Encode a not-found message to wire format
+pub fn encode_not_found(msg: &DataNotFound) -> Vec<u8> {
+    encode_message(MSG_TYPE_NOT_FOUND, msg)
+}
+
+/// Encode a want-list message to wire format
+pub fn encode_want_list(msg: &DataWantList) -> Vec<u8> {
+    encode_message(MSG_TYPE_WANT_LIST, msg)
+}
+
+/// Encode a cancel message to wire format
+pub fn encode_cancel(msg: &DataCancel) -> Vec<u8> {
+    encode_message(MSG_TYPE_CANCEL, msg)
 }
 
 /// Parse a wire format message
@@ -87,6 +169,18 @@ pub fn parse_message(data: &[u8]) -> Option<DataMessage> {
         MSG_TYPE_RESPONSE => rmp_serde::from_slice::<DataResponse>(body)
             .ok()
             .map(DataMessage::Response),
+        MSG_TYPE_HELLO => rmp_serde::from_slice::<DataHello>(body)
+            .ok()
+            .map(DataMessage::Hello),
+        MSG_TYPE_NOT_FOUND => rmp_serde::from_slice::<DataNotFound>(body)
+            .ok()
+            .map(DataMessage::NotFound),
+        MSG_TYPE_WANT_LIST => rmp_serde::from_slice::<DataWantList>(body)
+            .ok()
+            .map(DataMessage::WantList),
+        MSG_TYPE_CANCEL => rmp_serde::from_slice::<DataCancel>(body)
+            .ok()
+            .map(DataMessage::Cancel),
         _ => None,
     }
 }
@@ -124,6 +218,36 @@ pub fn create_fragment_response(
     }
 }
 
+/// Create a hello announcing our protocol version
+pub fn create_hello() -> DataHello {
+    DataHello {
+        v: PROTOCOL_VERSION,
+    }
+}
+
+/// Create a not-found answer
+pub fn create_not_found(hash: &Hash) -> DataNotFound {
+    DataNotFound { h: hash.to_vec() }
+}
+
+/// Create a want-list; callers split lists longer than MAX_WANT_LIST_LEN
+pub fn create_want_list(hashes: &[Hash], htl: u8) -> DataWantList {
+    DataWantList {
+        hs: hashes.iter().map(|h| ByteBuf::from(h.to_vec())).collect(),
+        htl: Some(htl),
+    }
+}
+
+/// Create a cancel for an earlier request
+pub fn create_cancel(hash: &Hash) -> DataCancel {
+    DataCancel { h: hash.to_vec() }
+}
+
+/// Whether a peer at this version understands not-found, want-list and cancel
+pub fn supports_extended(version: u8) -> bool {
+    version >= PROTOCOL_VERSION_EXTENDED
+}
+
 /// Check if a response is fragmented
 pub fn is_fragmented(res: &DataResponse) -> bool {
     res.i.is_some() && res.n.is_some()
@@ -212,6 +336,65 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_legacy_wire_format_unchanged() {
+        let hash = [0x01; 32];
+        let encoded = encode_request(&create_request(&hash, 7));
+        let mut expected = vec![MSG_TYPE_REQUEST, 0x82, 0xa1, b'h', 0xc4, 32];
+        expected.extend_from_slice(&hash);
+        expected.extend_from_slice(&[0xa3, b'h', b't', b'l', 7]);
+        assert_eq!(encoded, expected);
+    }
+
+    #[test]
+    fn test_encode_decode_extended_messages() {
+        let hash = [0x42; 32];
+
+        match parse_message(&encode_hello(&create_hello())).unwrap() {
+            DataMessage::Hello(h) => assert_eq!(h.v, PROTOCOL_VERSION),
+            _ => panic!("Expected hello"),
+        }
+
+        let encoded = encode_not_found(&create_not_found(&hash));
+        assert_eq!(encoded[0], MSG_TYPE_NOT_FOUND);
+        match parse_message(&encoded).unwrap() {
+            DataMessage::NotFound(n) => assert_eq!(n.h, hash.to_vec()),
+            _ => panic!("Expected not found"),
+        }
+
+        match parse_message(&encode_cancel(&create_cancel(&hash))).unwrap() {
+            DataMessage::Cancel(c) => assert_eq!(c.h, hash.to_vec()),
+            _ => panic!("Expected cancel"),
+        }
+    }
+
+    #[test]
+    fn test_encode_decode_want_list() {
+        let hashes = [[0x01; 32], [0x02; 32], [0x03; 32]];
+        let encoded = encode_want_list(&create_want_list(&hashes, 5));
+        assert_eq!(encoded[0], MSG_TYPE_WANT_LIST);
+
+        match parse_message(&encoded).unwrap() {
+            DataMessage::WantList(w) => {
+                let parsed: Vec<Hash> = w.hs.iter().filter_map(|h| bytes_to_hash(h)).collect();
+                assert_eq!(parsed, hashes.to_vec());
+                assert_eq!(w.htl, Some(5));
+            }
+            _ => panic!("Expected want list"),
+        }
+
+        let full = vec![[0xff; 32]; MAX_WANT_LIST_LEN];
+        assert!(encode_want_list(&create_want_list(&full, 10)).len() < FRAGMENT_SIZE);
+    }
+
+    #[test]
+    fn test_version_negotiation() {
+        assert!(!supports_extended(PROTOCOL_VERSION_LEGACY));
+        assert!(supports_extended(PROTOCOL_VERSION));
+        // Unknown future types are ignored rather than misparsed
+        assert!(parse_message(&[0x7f, 0x80]).is_none());
+    }
+
     #[test]
     fn test_hash_conversions() {
         let hash = [0x12; 32];
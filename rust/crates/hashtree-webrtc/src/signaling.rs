@@ -11,6 +11,9 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+use hashtree_core::Hash;
+
+use crate::bloom::{advertised_first, BloomFilter, PeerAdvert};
 use crate::transport::{DataChannel, PeerConnectionFactory, RelayTransport, TransportError};
 use crate::types::{is_polite_peer, ClassifyRequest, PeerPool, PoolSettings, SignalingMessage};
 
@@ -45,8 +48,8 @@ pub struct SignalingManager<R: RelayTransport, F: PeerConnectionFactory> {
     pending_offers: RwLock<HashMap<String, ()>>,
     /// Pool settings
     pools: PoolSettings,
-    /// Known peer roots (for future use)
-    peer_roots: RwLock<HashMap<String, Vec<String>>>,
+    /// Roots and filters from each peer's last hello
+    peer_adverts: RwLock<HashMap<String, PeerAdvert>>,
     /// Classifier channel (optional)
     classifier_tx: Option<tokio::sync::mpsc::Sender<ClassifyRequest>>,
     /// Debug mode
@@ -71,7 +74,7 @@ impl<R: RelayTransport + 'static, F: PeerConnectionFactory + 'static> SignalingM
             peers: RwLock::new(HashMap::new()),
             pending_offers: RwLock::new(HashMap::new()),
             pools,
-            peer_roots: RwLock::new(HashMap::new()),
+            peer_adverts: RwLock::new(HashMap::new()),
             classifier_tx: None,
             debug,
         }
@@ -87,11 +90,16 @@ impl<R: RelayTransport + 'static, F: PeerConnectionFactory + 'static> SignalingM
         &self.peer_id
     }
 
-    /// Send hello broadcast
-    pub async fn send_hello(&self, roots: Vec<String>) -> Result<(), TransportError> {
+    /// Send hello broadcast, with an optional filter of held hashes
+    pub async fn send_hello(
+        &self,
+        roots: Vec<String>,
+        filter: Option<BloomFilter>,
+    ) -> Result<(), TransportError> {
         let msg = SignalingMessage::Hello {
             peer_id: self.peer_id.clone(),
             roots,
+            filter,
         };
         self.relay.publish(msg).await
     }
@@ -148,7 +156,11 @@ impl<R: RelayTransport + 'static, F: PeerConnectionFactory + 'static> SignalingM
     /// This is the core signaling logic shared between production and simulation.
     pub async fn handle_message(&self, msg: SignalingMessage) -> Result<(), TransportError> {
         match &msg {
-            SignalingMessage::Hello { peer_id, roots } => self.handle_hello(peer_id, roots).await,
+            SignalingMessage::Hello {
+                peer_id,
+                roots,
+                filter,
+            } => self.handle_hello(peer_id, roots, filter.clone()).await,
             SignalingMessage::Offer {
                 peer_id,
                 target_peer_id,
@@ -187,6 +199,7 @@ impl<R: RelayTransport + 'static, F: PeerConnectionFactory + 'static> SignalingM
         &self,
         from_peer_id: &str,
         roots: &[String],
+        filter: Option<BloomFilter>,
     ) -> Result<(), TransportError> {
         // Ignore our own hello
         if from_peer_id == self.peer_id {
@@ -201,8 +214,20 @@ impl<R: RelayTransport + 'static, F: PeerConnectionFactory + 'static> SignalingM
 
         // Check pool limits
         let (follows_count, other_count) = self.count_pools().await;
+        let can_accept = self.can_accept_peer(pool, follows_count, other_count);
+
+        // Remember what the peer holds, refreshed with every hello; connected
+        // peers keep refreshing even once their pool is full
+        {
+            let peers = self.peers.read().await;
+            if can_accept || peers.contains_key(from_peer_id) {
+                let mut adverts = self.peer_adverts.write().await;
+                adverts.retain(|id, _| peers.contains_key(id));
+                adverts.insert(from_peer_id.to_string(), PeerAdvert::new(roots, filter));
+            }
+        }
 
-        if !self.can_accept_peer(pool, follows_count, other_count) {
+        if !can_accept {
             if self.debug {
                 println!(
                     "[Signaling] Ignoring hello from {} - {:?} pool full",
@@ -212,12 +237,6 @@ impl<R: RelayTransport + 'static, F: PeerConnectionFactory + 'static> SignalingM
             return Ok(());
         }
 
-        // Store peer roots
-        self.peer_roots
-            .write()
-            .await
-            .insert(from_peer_id.to_string(), roots.to_vec());
-
         // Perfect negotiation: send offer if we NEED more peers
         // Both sides may send offers - collision handled in handle_offer
         if self.pool_needs_peers(pool, follows_count, other_count) {
@@ -369,6 +388,21 @@ impl<R: RelayTransport + 'static, F: PeerConnectionFactory + 'static> SignalingM
         self.peers.read().await.keys().cloned().collect()
     }
 
+    /// Get peer IDs, those advertising `hash` first
+    pub async fn peer_ids_for(&self, hash: &Hash) -> Vec<String> {
+        let peers: Vec<(String, ())> = self
+            .peer_ids()
+            .await
+            .into_iter()
+            .map(|id| (id, ()))
+            .collect();
+        let adverts = self.peer_adverts.read().await;
+        advertised_first(peers, hash, &adverts)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect()
+    }
+
     /// Get a peer's channel
     pub async fn get_channel(&self, peer_id: &str) -> Option<Arc<dyn DataChannel>> {
         self.peers
@@ -3,20 +3,25 @@
 //! Implements the Store trait by fetching data from connected WebRTC peers.
 //! Uses Nostr relays for peer discovery and signaling.
 
+use crate::bandwidth::BandwidthLimiter;
+use crate::bloom::{advertised_first, FilterCache, PeerAdvert};
 use crate::peer::{Peer, PeerError};
-use crate::peer_selector::PeerSelector;
+use crate::peer_selector::{
+    load_state_file, save_state_file, AccessList, PeerAccess, PeerSelector, ReputationMap,
+};
 use crate::types::{
     ClassifyRequest, ForwardRx, ForwardTx, PeerId, PeerPool, PeerState, SignalingMessage,
     WebRTCStats, WebRTCStoreConfig, NOSTR_KIND_HASHTREE,
 };
 use async_trait::async_trait;
-use hashtree_core::{to_hex, Hash, Store, StoreError};
+use hashtree_core::{to_hex, Hash, Store, StoreError, VerifyingStore};
 use nostr_sdk::prelude::*;
 use nostr_sdk::ClientBuilder;
 use std::collections::HashMap;
 use std::sync::Arc;
 use thiserror::Error;
 use tokio::sync::{mpsc, oneshot, RwLock};
+use tracing::warn;
 use uuid::Uuid;
 
 #[derive(Debug, Error)]
@@ -43,6 +48,8 @@ struct PeerEntry<S: Store> {
 pub struct WebRTCStore<S: Store> {
     /// Local backing store
     local_store: Arc<S>,
+    /// Hash-checked view of the local store (corrupt blobs are dropped and refetched)
+    verified_local: VerifyingStore<S>,
     /// Configuration
     config: WebRTCStoreConfig,
     /// Nostr client for signaling
@@ -51,8 +58,10 @@ pub struct WebRTCStore<S: Store> {
     peer_id: PeerId,
     /// Connected peers with pool classification
     peers: Arc<RwLock<HashMap<String, PeerEntry<S>>>>,
-    /// Known peer roots (peer_id -> Vec<root_hash>)
-    peer_roots: Arc<RwLock<HashMap<String, Vec<String>>>>,
+    /// Roots and filters from each peer's last hello
+    peer_adverts: Arc<RwLock<HashMap<String, PeerAdvert>>>,
+    /// Our last advertised filter
+    filters: Arc<FilterCache>,
     /// Signaling message sender
     signaling_tx: mpsc::Sender<SignalingMessage>,
     /// Signaling message receiver
@@ -67,6 +76,8 @@ pub struct WebRTCStore<S: Store> {
     stats: Arc<RwLock<WebRTCStats>>,
     /// Adaptive peer selector for intelligent peer ordering
     peer_selector: Arc<RwLock<PeerSelector>>,
+    /// Upload/forward rate limits and per-peer byte ledgers
+    bandwidth: Arc<BandwidthLimiter>,
 }
 
 impl<S: Store + 'static> WebRTCStore<S> {
@@ -77,20 +88,39 @@ impl<S: Store + 'static> WebRTCStore<S> {
 
         let peer_id = PeerId::new(String::new(), Uuid::new_v4().to_string());
 
+        let mut peer_selector = PeerSelector::new();
+        if let Some(path) = &config.peer_reputation_path {
+            match load_state_file::<ReputationMap>(path) {
+                Ok(reputation) => peer_selector.restore_reputation(reputation),
+                Err(e) => warn!("[Store] Ignoring peer reputation file: {}", e),
+            }
+        }
+        if let Some(path) = &config.peer_access_path {
+            match load_state_file::<AccessList>(path) {
+                Ok(list) => peer_selector.set_access_list(list),
+                Err(e) => warn!("[Store] Ignoring peer access list: {}", e),
+            }
+        }
+
+        let bandwidth = Arc::new(BandwidthLimiter::new(config.bandwidth.clone()));
+
         Self {
+            verified_local: VerifyingStore::new(local_store.clone()).with_delete_corrupted(true),
             local_store,
             config,
             client: None,
             peer_id,
             peers: Arc::new(RwLock::new(HashMap::new())),
-            peer_roots: Arc::new(RwLock::new(HashMap::new())),
+            peer_adverts: Arc::new(RwLock::new(HashMap::new())),
+            filters: Arc::new(FilterCache::default()),
             signaling_tx,
             signaling_rx: Arc::new(RwLock::new(Some(signaling_rx))),
             forward_tx,
             forward_rx: Arc::new(RwLock::new(Some(forward_rx))),
             running: Arc::new(RwLock::new(false)),
             stats: Arc::new(RwLock::new(WebRTCStats::default())),
-            peer_selector: Arc::new(RwLock::new(PeerSelector::new())),
+            peer_selector: Arc::new(RwLock::new(peer_selector)),
+            bandwidth,
         }
     }
 
@@ -144,6 +174,7 @@ impl<S: Store + 'static> WebRTCStore<S> {
         self.start_signaling_sender(client).await;
         self.start_hello_timer().await;
         self.start_forward_handler().await;
+        self.start_peer_state_timer().await;
 
         Ok(())
     }
@@ -152,13 +183,14 @@ impl<S: Store + 'static> WebRTCStore<S> {
     async fn start_forward_handler(&self) {
         let mut rx = self.forward_rx.write().await.take().unwrap();
         let peers = self.peers.clone();
+        let peer_adverts = self.peer_adverts.clone();
         let peer_selector = self.peer_selector.clone();
         let local_store = self.local_store.clone();
         let running = self.running.clone();
         let debug = self.config.debug;
 
         tokio::spawn(async move {
-            while let Some(req) = rx.recv().await {
+            while let Some(mut req) = rx.recv().await {
                 if !*running.read().await {
                     break;
                 }
@@ -198,12 +230,19 @@ impl<S: Store + 'static> WebRTCStore<S> {
                 }
                 drop(peers_read);
 
+                // Peers advertising the hash go first
+                let candidates = advertised_first(
+                    follows_peers.into_iter().chain(other_peers).collect(),
+                    &req.hash,
+                    &*peer_adverts.read().await,
+                );
+
                 // Request size estimate for metrics
                 let request_bytes = 40u64;
 
                 // Query peers sequentially (follows first, then others) in selector order
                 let mut result = None;
-                for (peer_id, peer) in follows_peers.into_iter().chain(other_peers.into_iter()) {
+                for (peer_id, peer) in candidates {
                     // Record request being sent
                     peer_selector
                         .write()
@@ -211,13 +250,19 @@ impl<S: Store + 'static> WebRTCStore<S> {
                         .record_request(&peer_id, request_bytes);
                     let start_time = std::time::Instant::now();
 
-                    // Use request_with_htl to forward with the given HTL
-                    match tokio::time::timeout(
-                        std::time::Duration::from_millis(500), // Short timeout per peer
-                        peer.request_with_htl(&req.hash, req.htl),
-                    )
-                    .await
-                    {
+                    // Use request_with_htl to forward with the given HTL, giving
+                    // up once the requester cancelled and stopped listening
+                    let outcome = tokio::select! {
+                        outcome = tokio::time::timeout(
+                            std::time::Duration::from_millis(500), // Short timeout per peer
+                            peer.request_with_htl(&req.hash, req.htl),
+                        ) => outcome,
+                        _ = req.response.closed() => {
+                            let _ = peer.cancel(&req.hash).await;
+                            break;
+                        }
+                    };
+                    match outcome {
                         Ok(Ok(Some(data))) => {
                             // Verify hash
                             if hashtree_core::sha256(&data) == req.hash {
@@ -234,22 +279,32 @@ impl<S: Store + 'static> WebRTCStore<S> {
                                 result = Some(data);
                                 break;
                             } else {
-                                // Hash mismatch
-                                peer_selector.write().await.record_failure(&peer_id);
+                                // Hash mismatch - corrupt data weighs heavier than a failure
+                                peer_selector.write().await.record_corrupt(&peer_id);
                             }
                         }
                         Ok(Ok(None)) => {
                             // Peer doesn't have data - not a failure
                             continue;
                         }
+                        Ok(Err(PeerError::NotFound)) => {
+                            // Explicit miss - move on to the next peer right away
+                            let rtt_ms = start_time.elapsed().as_millis() as u64;
+                            peer_selector
+                                .write()
+                                .await
+                                .record_not_found(&peer_id, rtt_ms);
+                            continue;
+                        }
                         Ok(Err(_)) => {
                             // Error from peer
                             peer_selector.write().await.record_failure(&peer_id);
                             continue;
                         }
                         Err(_) => {
-                            // Timeout
+                            // Timeout - stop the peer from forwarding it further
                             peer_selector.write().await.record_timeout(&peer_id);
+                            let _ = peer.cancel(&req.hash).await;
                             continue;
                         }
                     }
@@ -270,12 +325,13 @@ impl<S: Store + 'static> WebRTCStore<S> {
 
     /// Send hello message to discover peers
     async fn send_hello(&self) -> Result<(), WebRTCStoreError> {
-        let roots: Vec<String> = self.config.roots.iter().map(to_hex).collect();
-
-        let msg = SignalingMessage::Hello {
-            peer_id: self.peer_id.to_peer_string(),
-            roots,
-        };
+        let msg = Self::hello_message(
+            self.peer_id.to_peer_string(),
+            &self.local_store,
+            &self.filters,
+            &self.config,
+        )
+        .await;
 
         self.signaling_tx
             .send(msg)
@@ -285,10 +341,29 @@ impl<S: Store + 'static> WebRTCStore<S> {
         Ok(())
     }
 
+    /// Hello with our roots and, if enabled, a filter of held hashes
+    async fn hello_message(
+        peer_id: String,
+        local_store: &S,
+        filters: &FilterCache,
+        config: &WebRTCStoreConfig,
+    ) -> SignalingMessage {
+        let filter = if config.advertise_filter {
+            filters.filter(local_store, &config.roots).await
+        } else {
+            None
+        };
+        SignalingMessage::Hello {
+            peer_id,
+            roots: config.roots.iter().map(to_hex).collect(),
+            filter,
+        }
+    }
+
     /// Start event handler for incoming Nostr events
     async fn start_event_handler(&self, client: Client) {
         let peers = self.peers.clone();
-        let peer_roots = self.peer_roots.clone();
+        let peer_adverts = self.peer_adverts.clone();
         let local_peer_id = self.peer_id.to_peer_string();
         let signaling_tx = self.signaling_tx.clone();
         let forward_tx = self.forward_tx.clone();
@@ -297,6 +372,7 @@ impl<S: Store + 'static> WebRTCStore<S> {
         let config = self.config.clone();
         let stats = self.stats.clone();
         let peer_selector = self.peer_selector.clone();
+        let bandwidth = self.bandwidth.clone();
 
         // Get our own broadcast receiver for notifications
         // Each call to notifications() returns a new receiver that receives all notifications
@@ -334,13 +410,14 @@ impl<S: Store + 'static> WebRTCStore<S> {
                                         msg,
                                         &local_peer_id,
                                         peers.clone(),
-                                        peer_roots.clone(),
+                                        peer_adverts.clone(),
                                         signaling_tx.clone(),
                                         forward_tx.clone(),
                                         local_store.clone(),
                                         &config,
                                         stats.clone(),
                                         peer_selector.clone(),
+                                        bandwidth.clone(),
                                     )
                                     .await;
                                 } else if config.debug {
@@ -431,16 +508,21 @@ impl<S: Store + 'static> WebRTCStore<S> {
         msg: SignalingMessage,
         local_peer_id: &str,
         peers: Arc<RwLock<HashMap<String, PeerEntry<S>>>>,
-        peer_roots: Arc<RwLock<HashMap<String, Vec<String>>>>,
+        peer_adverts: Arc<RwLock<HashMap<String, PeerAdvert>>>,
         signaling_tx: mpsc::Sender<SignalingMessage>,
         forward_tx: ForwardTx,
         local_store: Arc<S>,
         config: &WebRTCStoreConfig,
         stats: Arc<RwLock<WebRTCStats>>,
         peer_selector: Arc<RwLock<PeerSelector>>,
+        bandwidth: Arc<BandwidthLimiter>,
     ) {
         match &msg {
-            SignalingMessage::Hello { peer_id, roots } => {
+            SignalingMessage::Hello {
+                peer_id,
+                roots,
+                filter,
+            } => {
                 if peer_id == local_peer_id {
                     return; // Ignore own messages
                 }
@@ -448,15 +530,31 @@ impl<S: Store + 'static> WebRTCStore<S> {
                 // Extract pubkey from peer_id (format: "pubkey:uuid")
                 let peer_pubkey = peer_id.split(':').next().unwrap_or("");
 
+                if peer_selector.read().await.is_blocked(peer_pubkey) {
+                    if config.debug {
+                        println!("[Store] Ignoring hello from blocked peer {}", peer_id);
+                    }
+                    return;
+                }
+
                 // Classify the peer
                 let pool = Self::classify_peer(peer_pubkey, config).await;
 
                 // Check pool limits
                 let peers_read = peers.read().await;
                 let (follows_count, other_count) = Self::count_pools(&peers_read).await;
+                let can_accept = Self::can_accept_peer(pool, follows_count, other_count, config);
+
+                // Remember what the peer holds, refreshed with every hello;
+                // connected peers keep refreshing even once their pool is full
+                if can_accept || peers_read.contains_key(peer_id) {
+                    let mut adverts = peer_adverts.write().await;
+                    adverts.retain(|id, _| peers_read.contains_key(id));
+                    adverts.insert(peer_id.clone(), PeerAdvert::new(roots, filter.clone()));
+                }
                 drop(peers_read);
 
-                if !Self::can_accept_peer(pool, follows_count, other_count, config) {
+                if !can_accept {
                     if config.debug {
                         println!(
                             "[Store] Ignoring hello from {} - {:?} pool full",
@@ -470,12 +568,6 @@ impl<S: Store + 'static> WebRTCStore<S> {
                     println!("[Store] Received hello from {} (pool: {:?})", peer_id, pool);
                 }
 
-                // Store peer roots
-                peer_roots
-                    .write()
-                    .await
-                    .insert(peer_id.clone(), roots.clone());
-
                 // Perfect negotiation: send offer if we NEED more peers
                 // Both sides may send offers - collisions handled in offer handler
                 if Self::pool_needs_peers(pool, follows_count, other_count, config) {
@@ -488,13 +580,14 @@ impl<S: Store + 'static> WebRTCStore<S> {
                                 );
                             }
                             // Create peer and add to map BEFORE connecting to avoid race with incoming answer
-                            if let Ok(peer) = Peer::with_forward_channel(
+                            if let Ok(peer) = Peer::with_bandwidth(
                                 remote_id,
                                 local_peer_id.to_string(),
                                 signaling_tx.clone(),
                                 local_store.clone(),
                                 config.debug,
                                 Some(forward_tx.clone()),
+                                Some(bandwidth.clone()),
                             )
                             .await
                             {
@@ -547,6 +640,13 @@ impl<S: Store + 'static> WebRTCStore<S> {
                 // Extract pubkey from peer_id
                 let peer_pubkey = peer_id.split(':').next().unwrap_or("");
 
+                if peer_selector.read().await.is_blocked(peer_pubkey) {
+                    if config.debug {
+                        println!("[Store] Ignoring signaling from blocked peer {}", peer_id);
+                    }
+                    return;
+                }
+
                 // Classify the peer
                 let pool = Self::classify_peer(peer_pubkey, config).await;
 
@@ -575,13 +675,14 @@ impl<S: Store + 'static> WebRTCStore<S> {
                     Some(p) => p,
                     None => {
                         if let Some(remote_id) = PeerId::from_peer_string(peer_id) {
-                            if let Ok(p) = Peer::with_forward_channel(
+                            if let Ok(p) = Peer::with_bandwidth(
                                 remote_id,
                                 local_peer_id.to_string(),
                                 signaling_tx.clone(),
                                 local_store.clone(),
                                 config.debug,
                                 Some(forward_tx.clone()),
+                                Some(bandwidth.clone()),
                             )
                             .await
                             {
@@ -645,11 +746,13 @@ impl<S: Store + 'static> WebRTCStore<S> {
         });
     }
 
-    /// Start periodic hello sender
+    /// Start periodic hello sender (the filter is rebuilt if the store changed)
     async fn start_hello_timer(&self) {
         let signaling_tx = self.signaling_tx.clone();
         let peer_id = self.peer_id.to_peer_string();
-        let roots: Vec<String> = self.config.roots.iter().map(to_hex).collect();
+        let local_store = self.local_store.clone();
+        let filters = self.filters.clone();
+        let config = self.config.clone();
         let interval_ms = self.config.hello_interval_ms;
         let running = self.running.clone();
 
@@ -663,19 +766,90 @@ impl<S: Store + 'static> WebRTCStore<S> {
                     break;
                 }
 
-                let msg = SignalingMessage::Hello {
-                    peer_id: peer_id.clone(),
-                    roots: roots.clone(),
-                };
-
+                let msg =
+                    Self::hello_message(peer_id.clone(), &local_store, &filters, &config).await;
                 let _ = signaling_tx.send(msg).await;
             }
         });
     }
 
+    /// Periodically persist peer reputation and pick up ban list edits
+    async fn start_peer_state_timer(&self) {
+        if self.config.peer_reputation_path.is_none() && self.config.peer_access_path.is_none() {
+            return;
+        }
+        let peer_selector = self.peer_selector.clone();
+        let config = self.config.clone();
+        let running = self.running.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(30));
+            interval.tick().await;
+
+            loop {
+                interval.tick().await;
+                if !*running.read().await {
+                    break;
+                }
+                Self::sync_peer_state(&peer_selector, &config).await;
+            }
+        });
+    }
+
+    /// Reload the ban/allow list and save reputation
+    async fn sync_peer_state(peer_selector: &RwLock<PeerSelector>, config: &WebRTCStoreConfig) {
+        if let Some(path) = &config.peer_access_path {
+            if let Ok(list) = load_state_file::<AccessList>(path) {
+                peer_selector.write().await.set_access_list(list);
+            }
+        }
+        if let Some(path) = &config.peer_reputation_path {
+            let reputation = peer_selector.read().await.reputation();
+            if let Err(e) = save_state_file(path, &reputation) {
+                warn!("[Store] Failed to save peer reputation: {}", e);
+            }
+        }
+    }
+
+    /// Ban or allow a peer pubkey (None clears the entry)
+    ///
+    /// Banned peers are dropped right away and their connections refused.
+    /// The list is saved to `peer_access_path` when configured.
+    pub async fn set_peer_access(
+        &self,
+        pubkey: &str,
+        access: Option<PeerAccess>,
+    ) -> Result<(), WebRTCStoreError> {
+        let list = {
+            let mut selector = self.peer_selector.write().await;
+            selector.set_access(pubkey, access);
+            selector.access_list()
+        };
+        if let Some(path) = &self.config.peer_access_path {
+            save_state_file(path, &list).map_err(StoreError::from)?;
+        }
+
+        if access == Some(PeerAccess::Banned) {
+            let mut peers = self.peers.write().await;
+            let banned: Vec<String> = peers
+                .keys()
+                .filter(|id| id.split(':').next() == Some(pubkey))
+                .cloned()
+                .collect();
+            for id in banned {
+                if let Some(entry) = peers.remove(&id) {
+                    let _ = entry.peer.close().await;
+                }
+                self.peer_selector.write().await.remove_peer(&id);
+            }
+        }
+        Ok(())
+    }
+
     /// Stop the WebRTC store
     pub async fn stop(&self) {
         *self.running.write().await = false;
+        Self::sync_peer_state(&self.peer_selector, &self.config).await;
 
         // Close all peer connections
         let peers = self.peers.read().await;
@@ -708,8 +882,10 @@ impl<S: Store + 'static> WebRTCStore<S> {
 
     /// Request data from peers using adaptive peer selection
     ///
-    /// Uses PeerSelector to order peers by performance (success rate, RTT).
+    /// Peers whose hello advertised the hash are tried first. Otherwise uses
+    /// PeerSelector to order peers by performance (success rate, RTT).
     /// Follows pool is still prioritized, but ordering within each pool uses selector.
+    /// Peers that answer "not found" are skipped immediately instead of timing out.
     async fn request_from_peers(&self, hash: &Hash) -> Result<Option<Vec<u8>>, WebRTCStoreError> {
         // Get ordered peer list from selector
         let ordered_peer_ids = self.peer_selector.write().await.select_peers();
@@ -734,11 +910,17 @@ impl<S: Store + 'static> WebRTCStore<S> {
         }
         drop(peers);
 
+        let candidates = advertised_first(
+            follows_peers.into_iter().chain(other_peers).collect(),
+            hash,
+            &*self.peer_adverts.read().await,
+        );
+
         // Request size estimate for metrics (hash request is ~40 bytes)
         let request_bytes = 40u64;
 
-        // Try follows first, then others (in selector order within each pool)
-        for (peer_id, peer) in follows_peers.into_iter().chain(other_peers.into_iter()) {
+        // Advertising peers first, then follows, then others (in selector order within each pool)
+        for (peer_id, peer) in candidates {
             // Record request being sent
             self.peer_selector
                 .write()
@@ -765,14 +947,23 @@ impl<S: Store + 'static> WebRTCStore<S> {
                         stats.bytes_received += data.len() as u64;
                         return Ok(Some(data));
                     } else {
-                        // Hash mismatch - record as failure
-                        self.peer_selector.write().await.record_failure(&peer_id);
+                        // Hash mismatch - corrupt data weighs heavier than a failure
+                        self.peer_selector.write().await.record_corrupt(&peer_id);
                     }
                 }
                 Ok(None) => {
                     // Peer doesn't have data - not a failure, just continue
                     continue;
                 }
+                Err(PeerError::NotFound) => {
+                    // Explicit miss - fail over without waiting for a timeout
+                    let rtt_ms = start_time.elapsed().as_millis() as u64;
+                    self.peer_selector
+                        .write()
+                        .await
+                        .record_not_found(&peer_id, rtt_ms);
+                    continue;
+                }
                 Err(PeerError::Timeout) => {
                     // Record timeout
                     self.peer_selector.write().await.record_timeout(&peer_id);
@@ -789,22 +980,36 @@ impl<S: Store + 'static> WebRTCStore<S> {
         Ok(None)
     }
 
+    /// Rate limiter holding per-peer byte ledgers
+    pub fn bandwidth(&self) -> &Arc<BandwidthLimiter> {
+        &self.bandwidth
+    }
+
     /// Get peer selector summary statistics
     pub async fn selector_summary(&self) -> crate::peer_selector::SelectorSummary {
         self.peer_selector.read().await.summary()
     }
+
+    /// Number of corrupted local blobs detected on read
+    pub fn verification_failures(&self) -> u64 {
+        self.verified_local.failures()
+    }
 }
 
 #[async_trait]
 impl<S: Store + 'static> Store for WebRTCStore<S> {
     async fn put(&self, hash: Hash, data: Vec<u8>) -> Result<bool, StoreError> {
-        self.local_store.put(hash, data).await
+        self.verified_local.put(hash, data).await
     }
 
     async fn get(&self, hash: &Hash) -> Result<Option<Vec<u8>>, StoreError> {
         // Try local first
-        if let Some(data) = self.local_store.get(hash).await? {
-            return Ok(Some(data));
+        match self.verified_local.get(hash).await {
+            Ok(Some(data)) => return Ok(Some(data)),
+            Ok(None) => {}
+            // Corrupted local copy was deleted - refetch from peers
+            Err(StoreError::HashMismatch { .. }) => {}
+            Err(e) => return Err(e),
         }
 
         // Update stats
@@ -3,6 +3,8 @@
 //! Defines message types for WebRTC signaling via Nostr relays
 //! and the data channel protocol for hash-based data requests.
 
+use crate::bandwidth::BandwidthConfig;
+use crate::bloom::BloomFilter;
 use hashtree_core::Hash;
 use serde::{Deserialize, Serialize};
 
@@ -47,6 +49,9 @@ pub enum SignalingMessage {
         #[serde(rename = "peerId")]
         peer_id: String,
         roots: Vec<String>,
+        /// Filter of held roots and chunks (absent from older peers)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        filter: Option<BloomFilter>,
     },
 
     /// WebRTC offer (SDP)
@@ -385,6 +390,16 @@ pub struct WebRTCStoreConfig {
     /// Channel for peer classification (optional)
     /// If None, all peers go to "Other" pool
     pub classifier_tx: Option<ClassifierTx>,
+    /// JSON file persisting peer reputation across restarts (optional)
+    pub peer_reputation_path: Option<std::path::PathBuf>,
+    /// JSON file holding the peer ban/allow list (optional, re-read periodically)
+    pub peer_access_path: Option<std::path::PathBuf>,
+    /// Upload/forward rate limits and reciprocity policy (unlimited by default)
+    pub bandwidth: BandwidthConfig,
+    /// Advertise a bloom filter of held roots and chunks in hellos, so peers
+    /// ask us first for content we have (off by default: anyone on the relays
+    /// can test the filter for hashes they know)
+    pub advertise_filter: bool,
 }
 
 impl Default for WebRTCStoreConfig {
@@ -400,6 +415,10 @@ impl Default for WebRTCStoreConfig {
             debug: false,
             pools: PoolSettings::default(),
             classifier_tx: None,
+            peer_reputation_path: None,
+            peer_access_path: None,
+            bandwidth: BandwidthConfig::default(),
+            advertise_filter: false,
         }
     }
 }
@@ -0,0 +1,90 @@
+//! Tests for GenericStore hellos over the mock relay
+
+use hashtree_core::{sha256, MemoryStore, Store};
+use hashtree_webrtc::{
+    GenericStore, MockConnectionFactory, MockRelay, MockRelayTransport, PoolSettings,
+    RelayTransport, SignalingManager, SignalingMessage,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+async fn store_on(
+    relay: &MockRelay,
+    peer_id: &str,
+) -> GenericStore<MemoryStore, MockRelayTransport, MockConnectionFactory> {
+    let transport = relay.create_transport(peer_id.to_string(), peer_id.to_string());
+    transport.connect(&[]).await.unwrap();
+    let transport = Arc::new(transport);
+    let factory = Arc::new(MockConnectionFactory::new(peer_id.to_string(), 0));
+    let signaling = Arc::new(SignalingManager::new(
+        peer_id.to_string(),
+        peer_id.to_string(),
+        transport,
+        factory,
+        PoolSettings::default(),
+        false,
+    ));
+    GenericStore::new(
+        Arc::new(MemoryStore::new()),
+        signaling,
+        Duration::from_secs(1),
+        false,
+    )
+}
+
+/// Hellos from `peer_id` seen so far, as their filters
+fn hellos_from(
+    listener: &MockRelayTransport,
+    peer_id: &str,
+) -> Vec<Option<hashtree_webrtc::BloomFilter>> {
+    let mut hellos = Vec::new();
+    while let Some(msg) = listener.try_recv() {
+        if let SignalingMessage::Hello {
+            peer_id: from,
+            filter,
+            ..
+        } = msg
+        {
+            if from == peer_id {
+                hellos.push(filter);
+            }
+        }
+    }
+    hellos
+}
+
+#[tokio::test]
+async fn test_hello_timer_refreshes_filter() {
+    let relay = MockRelay::new();
+    let listener = relay.create_transport("listener".to_string(), "listener".to_string());
+    listener.connect(&[]).await.unwrap();
+
+    let store = store_on(&relay, "1")
+        .await
+        .with_advertise_filter(true)
+        .with_hello_interval(Duration::from_millis(20));
+    store.start().await.unwrap();
+
+    let data = b"stored after start".to_vec();
+    let hash = sha256(&data);
+    store.put(hash, data).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    store.stop().await;
+
+    let hellos = hellos_from(&listener, "1");
+    assert!(hellos.len() >= 3, "{} hellos", hellos.len());
+    assert!(!hellos[0].as_ref().unwrap().contains(&hash));
+    assert!(hellos.last().unwrap().as_ref().unwrap().contains(&hash));
+}
+
+#[tokio::test]
+async fn test_filter_is_opt_in() {
+    let relay = MockRelay::new();
+    let listener = relay.create_transport("listener".to_string(), "listener".to_string());
+    listener.connect(&[]).await.unwrap();
+
+    let store = store_on(&relay, "2").await;
+    store.start().await.unwrap();
+
+    assert_eq!(hellos_from(&listener, "2"), vec![None]);
+}
@@ -468,3 +468,93 @@ async fn test_data_transfer_between_peers() {
         }
     }
 }
+
+/// Relay signaling messages from one peer to the other
+async fn relay_signaling(
+    mut rx: tokio::sync::mpsc::Receiver<hashtree_webrtc::SignalingMessage>,
+    to: Arc<hashtree_webrtc::Peer<MemoryStore>>,
+) {
+    while let Some(msg) = rx.recv().await {
+        let _ = to.handle_signaling(msg).await;
+    }
+}
+
+/// Test that a cancel sent over the data channel aborts a forward in progress.
+#[tokio::test]
+#[ignore = "requires ICE/STUN connectivity - run manually with --ignored"]
+async fn test_cancel_aborts_forward_over_data_channel() {
+    use hashtree_webrtc::{Peer, PeerId, PeerState};
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    let (signal_tx1, signal_rx1) = mpsc::channel(64);
+    let (signal_tx2, signal_rx2) = mpsc::channel(64);
+    let (forward_tx, mut forward_rx) = mpsc::channel(8);
+
+    let id1 = PeerId::new("a".repeat(64), "one".to_string());
+    let id2 = PeerId::new("b".repeat(64), "two".to_string());
+
+    // Peer 1 has nothing locally and forwards; peer 2 asks it for a hash
+    let peer1 = Arc::new(
+        Peer::with_forward_channel(
+            id2.clone(),
+            id1.to_peer_string(),
+            signal_tx1,
+            Arc::new(MemoryStore::new()),
+            false,
+            Some(forward_tx),
+        )
+        .await
+        .unwrap(),
+    );
+    let peer2 = Arc::new(
+        Peer::new(
+            id1,
+            id2.to_peer_string(),
+            signal_tx2,
+            Arc::new(MemoryStore::new()),
+            false,
+        )
+        .await
+        .unwrap(),
+    );
+    tokio::spawn(relay_signaling(signal_rx1, peer2.clone()));
+    tokio::spawn(relay_signaling(signal_rx2, peer1.clone()));
+
+    peer2.connect().await.unwrap();
+    let mut ready = false;
+    for _ in 0..100 {
+        if peer1.state().await == PeerState::Ready
+            && peer2.state().await == PeerState::Ready
+            && peer2.remote_version() > 0
+        {
+            ready = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(ready, "Peers did not connect - WebRTC connection failed");
+
+    let hash = [7u8; 32];
+    let requester = peer2.clone();
+    tokio::spawn(async move {
+        let _ = requester.request(&hash).await;
+    });
+
+    // Hold the forward open, then cancel through the channel
+    let mut forward = tokio::time::timeout(Duration::from_secs(5), forward_rx.recv())
+        .await
+        .expect("request was not forwarded")
+        .unwrap();
+    assert_eq!(forward.hash, hash);
+    peer2.cancel(&hash).await.unwrap();
+
+    // Peer 1 drops the forward as soon as it reads the cancel
+    tokio::time::timeout(Duration::from_secs(5), forward.response.closed())
+        .await
+        .expect("cancel did not abort the forward");
+    assert_eq!(peer1.their_request_count().await, 0);
+
+    let _ = peer1.close().await;
+    let _ = peer2.close().await;
+}
@@ -2,9 +2,9 @@
 
 use hashtree_webrtc::{
     bytes_to_hash, create_fragment_response, create_request, create_response, encode_request,
-    encode_response, is_fragmented, parse_message, should_forward, DataMessage, PeerHTLConfig,
-    PeerId, PeerState, SignalingMessage, WebRTCStats, WebRTCStoreConfig, MAX_HTL, MSG_TYPE_REQUEST,
-    MSG_TYPE_RESPONSE,
+    encode_response, is_fragmented, parse_message, should_forward, BloomFilter, DataMessage,
+    PeerHTLConfig, PeerId, PeerState, SignalingMessage, WebRTCStats, WebRTCStoreConfig,
+    DEFAULT_FALSE_POSITIVE_RATE, MAX_HTL, MSG_TYPE_REQUEST, MSG_TYPE_RESPONSE,
 };
 
 #[test]
@@ -38,10 +38,40 @@ fn test_signaling_message_hello_serialize() {
     let msg = SignalingMessage::Hello {
         peer_id: "test:123".to_string(),
         roots: vec!["abc".to_string(), "def".to_string()],
+        filter: None,
     };
     let json = serde_json::to_string(&msg).unwrap();
     assert!(json.contains("\"type\":\"hello\""));
     assert!(json.contains("\"peerId\":\"test:123\""));
+    assert!(!json.contains("filter"));
+}
+
+#[test]
+fn test_signaling_message_hello_filter() {
+    let held = hashtree_core::sha256(b"held");
+    let msg = SignalingMessage::Hello {
+        peer_id: "test:123".to_string(),
+        roots: vec![],
+        filter: Some(BloomFilter::from_hashes(
+            [&held],
+            DEFAULT_FALSE_POSITIVE_RATE,
+        )),
+    };
+    let json = serde_json::to_string(&msg).unwrap();
+    match serde_json::from_str::<SignalingMessage>(&json).unwrap() {
+        SignalingMessage::Hello {
+            filter: Some(filter),
+            ..
+        } => assert!(filter.contains(&held)),
+        other => panic!("Expected hello with filter, got {:?}", other),
+    }
+
+    // Hellos from peers without filters still parse
+    let old = r#"{"type":"hello","peerId":"test:123","roots":[]}"#;
+    assert!(matches!(
+        serde_json::from_str::<SignalingMessage>(old).unwrap(),
+        SignalingMessage::Hello { filter: None, .. }
+    ));
 }
 
 #[test]
@@ -174,6 +204,7 @@ fn test_webrtc_store_config_default() {
     assert_eq!(config.request_timeout_ms, 10000);
     assert!(!config.debug);
     assert!(config.classifier_tx.is_none());
+    assert!(!config.advertise_filter);
 }
 
 #[test]